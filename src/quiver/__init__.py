@@ -1,4 +1,75 @@
 # This file marks the directory as a Python package.
-from .quiver import Quiver
+from .quiver import (
+    EXIT_QUOTA_REACHED,
+    EntryResult,
+    EntryStatus,
+    Quiver,
+    SliceResult,
+    Warnings,
+    best_per_group,
+    build_index,
+    dedup_tags,
+    extract_pdbs,
+    extract_sequences,
+    generate_test_archive,
+    group_stats,
+    head_entries,
+    ingest_directory,
+    legacy_error_handler,
+    merge_archives,
+    modernize_archive,
+    move_entries,
+    normalize_field,
+    parse_score_value,
+    payload_digest,
+    recover_move,
+    rename_tags_map,
+    repack,
+    repair_archive,
+    resolve_duplicates,
+    stable_header,
+    tail_entries,
+    validate_archive,
+    verify_archive,
+)
+from .async_quiver import AsyncQuiver
+from .structure import Atom, Chain, Residue, Structure
 
-__all__ = ["Quiver"]
+__all__ = [
+    "AsyncQuiver",
+    "EXIT_QUOTA_REACHED",
+    "Atom",
+    "Chain",
+    "EntryResult",
+    "EntryStatus",
+    "Quiver",
+    "Residue",
+    "SliceResult",
+    "Structure",
+    "Warnings",
+    "best_per_group",
+    "build_index",
+    "dedup_tags",
+    "extract_pdbs",
+    "extract_sequences",
+    "generate_test_archive",
+    "group_stats",
+    "head_entries",
+    "ingest_directory",
+    "legacy_error_handler",
+    "merge_archives",
+    "modernize_archive",
+    "move_entries",
+    "normalize_field",
+    "parse_score_value",
+    "payload_digest",
+    "recover_move",
+    "rename_tags_map",
+    "repack",
+    "repair_archive",
+    "resolve_duplicates",
+    "stable_header",
+    "tail_entries",
+    "validate_archive",
+    "verify_archive",
+]
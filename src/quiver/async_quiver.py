@@ -0,0 +1,104 @@
+#!/usr/bin/env python3
+"""
+An asyncio facade over a read-only Quiver file.
+
+Every read runs on a thread pool, so an event loop (e.g. a FastAPI service)
+keeps serving other requests while entries are read from a large archive.
+Each read opens the file on its own, so reads can run concurrently.
+
+Usage:
+    async with AsyncQuiver("designs.qv") as qv:
+        lines = await qv.get_pdblines("design_0001")
+"""
+
+import asyncio
+import functools
+from concurrent.futures import ThreadPoolExecutor
+
+from .quiver import Quiver
+
+
+class AsyncQuiver:
+    def __init__(
+        self, filename, max_workers=None, executor=None, access_hook=None, duplicate_policy="last"
+    ):
+        """Open `filename` for reading.
+
+        Reads run on `executor` if given (it is left open on close()), else on
+        a pool of `max_workers` threads owned by this object. `access_hook`
+        and `duplicate_policy` are passed on to Quiver.
+        """
+        self._qv = Quiver(
+            filename, "r", access_hook=access_hook, duplicate_policy=duplicate_policy
+        )
+        self._own_executor = executor is None
+        self._executor = executor or ThreadPoolExecutor(
+            max_workers=max_workers, thread_name_prefix="quiver"
+        )
+        self.closed = False
+
+    async def __aenter__(self):
+        return self
+
+    async def __aexit__(self, exc_type, exc_value, traceback):
+        self.close()
+        return False
+
+    def close(self):
+        """Shut down the owned thread pool. Safe to call more than once."""
+        if self._own_executor and not self.closed:
+            self._executor.shutdown(wait=False)
+        self.closed = True
+
+    async def _run(self, method, *args, **kwargs):
+        if self.closed:
+            raise RuntimeError("Quiver file is closed.")
+        loop = asyncio.get_running_loop()
+        return await loop.run_in_executor(
+            self._executor, functools.partial(method, *args, **kwargs)
+        )
+
+    # The tag list is read when the file is opened, so these need no thread
+
+    @property
+    def fn(self):
+        return self._qv.fn
+
+    def get_tags(self):
+        return self._qv.get_tags()
+
+    def size(self):
+        return self._qv.size()
+
+    def __len__(self):
+        return len(self._qv)
+
+    def __contains__(self, tag):
+        return tag in self._qv
+
+    async def get_pdblines(self, tag, record_filter=None):
+        """The PDB lines of `tag`, see Quiver.get_pdblines()."""
+        return await self._run(self._qv.get_pdblines, tag, record_filter)
+
+    async def try_get(self, tag):
+        """EntryResult for `tag`, see Quiver.try_get()."""
+        return await self._run(self._qv.try_get, tag)
+
+    async def get_scores(self, tag):
+        return await self._run(self._qv.get_scores, tag)
+
+    async def get_struct_list(self, tag_list):
+        return await self._run(self._qv.get_struct_list, tag_list)
+
+    async def filter_by_score(self, column, min=None, max=None):
+        return await self._run(self._qv.filter_by_score, column, min=min, max=max)
+
+    async def sort_tags(self, score_key, descending=True, missing="last"):
+        return await self._run(
+            self._qv.sort_tags, score_key, descending=descending, missing=missing
+        )
+
+    async def entries(self):
+        """All (tag, score_str, pdb_lines) entries in file order, read in one
+        pass off the event loop."""
+        return await self._run(lambda: list(self._qv))
@@ -0,0 +1,8 @@
+//! Reading and writing Quiver archives: many PDB files in one text file.
+//!
+//! The command-line tools in this crate are thin wrappers around this
+//! library; the `ffi` feature adds the C interface in `quiver.h`.
+
+mod quiver;
+
+pub use quiver::*;
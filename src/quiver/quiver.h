@@ -0,0 +1,61 @@
+/*
+ * C interface to the Quiver archive reader, built with the `ffi` feature.
+ *
+ * Archives are opened read-only, through their .idx tag index when it is up
+ * to date, with the same reader the command-line tools use:
+ *
+ *     QuiverHandle *qv = quiver_open("designs.qv");
+ *     if (!qv) { fprintf(stderr, "%s\n", quiver_last_error()); return 1; }
+ *     char *pdb = quiver_get(qv, "design_7");
+ *     ...
+ *     quiver_string_free(pdb);
+ *     quiver_close(qv);
+ *
+ * Functions returning a pointer return NULL on failure; quiver_last_error()
+ * then describes the error and quiver_last_error_code() gives the exit
+ * status the command-line tools use for it (3: tag not found, 4: malformed
+ * archive, 5: I/O error, ...). Both are per thread. Strings returned by the
+ * library belong to the caller and are freed with quiver_string_free().
+ * A handle may be shared by threads reading from it.
+ */
+#ifndef QUIVER_H
+#define QUIVER_H
+
+#include <stddef.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct QuiverHandle QuiverHandle;
+
+/* Open a local archive or an http(s):// or s3:// URL read-only. */
+QuiverHandle *quiver_open(const char *path);
+
+/* Close a handle; NULL is ignored. */
+void quiver_close(QuiverHandle *handle);
+
+/* Number of entries; 0 with a last error if handle is NULL. */
+size_t quiver_len(const QuiverHandle *handle);
+
+/* The tags in file order, each followed by a newline. */
+char *quiver_list(const QuiverHandle *handle);
+
+/* The text of the PDB or mmCIF file stored under `tag`. */
+char *quiver_get(const QuiverHandle *handle, const char *tag);
+
+/* Free a string returned by quiver_list() or quiver_get(); NULL is ignored. */
+void quiver_string_free(char *s);
+
+/* Message of the last failed call on this thread, NULL after a success.
+ * Owned by the library and valid until the next call. */
+const char *quiver_last_error(void);
+
+/* Exit status of the last failed call on this thread, 0 after a success. */
+int quiver_last_error_code(void);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* QUIVER_H */
@@ -7,24 +7,775 @@ This Quiver implementation will be just a list of PDB lines in a single file, wi
 Later this can be made more sophisticated by using a proper database, but for now this will be the simplest implementation.
 """
 
+import bisect
+import codecs
+import enum
+import fnmatch
+import functools
+import gzip
+import heapq
+import io
+import json
+import math
 import os
+import re
+import sys
+import threading
+import time
+import warnings
+from collections import deque, namedtuple
+from concurrent.futures import ThreadPoolExecutor
+
+try:
+    from . import remote
+    from .structure import THREE_TO_ONE, Structure, header_records
+    from .validate import PDB_RECORDS, sniff_structure, structure_content
+except ImportError:  # run as a script from this directory
+    import remote
+    from structure import THREE_TO_ONE, Structure, header_records
+    from validate import PDB_RECORDS, sniff_structure, structure_content
+
+# Environment variable naming a log file every Quiver appends accesses to
+ACCESS_LOG_ENV = "QUIVER_ACCESS_LOG"
+
+# Line prefixes Quiver itself reads; a PDB line starting with one would be
+# taken for a record of the archive
+RESERVED_PREFIXES = ("QV_TAG", "QV_SCORE", "QV_META", "QV_HASH", "QV_HEADER")
+
+# QV_META field holding an entry's comma-separated labels, set by qvlabel
+LABELS_FIELD = "labels"
+
+# QV_HEADER fields the Rust rewrites keep for the archive's generation; they
+# describe the archive they are in, so splits and slices do not copy them
+GENERATION_FIELDS = ("generation", "generation_entries")
+
+# Spellings of the record keywords found in archives from older writers,
+# read as the canonical keyword with Quiver(..., legacy_records=True)
+LEGACY_RECORDS = {
+    "QVTAG": "QV_TAG",
+    "QV TAG": "QV_TAG",
+    "QVSCORE": "QV_SCORE",
+    "QV SCORE": "QV_SCORE",
+    "QVMETA": "QV_META",
+    "QV META": "QV_META",
+    "QVHASH": "QV_HASH",
+    "QV HASH": "QV_HASH",
+    "QVHEADER": "QV_HEADER",
+    "QV HEADER": "QV_HEADER",
+}
+
+
+def _fnv1a64(data):
+    # 64-bit FNV-1a, the hash the Rust tools use
+    h = 0xCBF29CE484222325
+    for byte in data:
+        h = ((h ^ byte) * 0x100000001B3) & 0xFFFFFFFFFFFFFFFF
+    return h
+
+
+def payload_digest(pdb_lines):
+    """Checksum of payload lines as stored on a QV_HASH line: FNV-1a over
+    the lines, each ended by a single newline."""
+    payload = "".join(line.rstrip("\r\n") + "\n" for line in pdb_lines)
+    return f"fnv1a64:{_fnv1a64(payload.encode('utf-8')):016x}"
+
+
+_INT_RE = re.compile(r"[+-]?[0-9]+")
+
+
+def parse_score_value(raw):
+    """Type a raw score value: int, then float (including nan and inf), then
+    bool (true/false, any case), else the string itself.
+
+    Matches the typing of the Rust tools, so both export the same columns.
+    """
+    if _INT_RE.fullmatch(raw):
+        return int(raw)
+    if "_" not in raw:
+        try:
+            return float(raw)
+        except ValueError:
+            pass
+    if raw.lower() in ("true", "false"):
+        return raw.lower() == "true"
+    return raw
+
+
+DUPLICATE_POLICIES = ("first", "last", "error", "warn")
+
+
+def resolve_duplicates(fields, policy="last", tag=None):
+    """Turn the (name, value) pairs of one score line into a dict, resolving
+    repeated names by `policy`:
+
+    first  keep the first value
+    last   keep the last value (the default)
+    error  raise ValueError
+    warn   keep the last value and warn about the name
+
+    Each name keeps the position of its first occurrence, as in the Rust tools.
+    """
+    if policy not in DUPLICATE_POLICIES:
+        raise ValueError(
+            f"Unknown duplicate policy '{policy}', expected first, last, error or warn"
+        )
+    where = f"Tag {tag}" if tag is not None else "Score line"
+    resolved = {}
+    for name, value in fields:
+        if name in resolved:
+            if policy == "error":
+                raise ValueError(f"{where}: duplicate score field '{name}'")
+            if policy == "warn":
+                warnings.warn(
+                    f"{where} repeats score field '{name}', keeping the last value"
+                )
+            if policy == "first":
+                continue
+        resolved[name] = value
+    return resolved
+
+
+FIELD_CHARSETS = ("utf8", "ascii", "fold")
+
+# ASCII spellings of the "fold" charset; other non-ASCII characters become "_"
+_FOLD = {
+    c: ascii
+    for chars, ascii in (
+        ("ÀÁÂÃÄÅ\u212b", "A"), ("àáâãäå", "a"), ("Æ", "AE"), ("æ", "ae"),
+        ("Ç", "C"), ("ç", "c"), ("ÈÉÊË", "E"), ("èéêë", "e"), ("ÌÍÎÏ", "I"),
+        ("ìíîï", "i"), ("Ð", "D"), ("ð", "d"), ("Ñ", "N"), ("ñ", "n"),
+        ("ÒÓÔÕÖØ", "O"), ("òóôõöø", "o"), ("Œ", "OE"), ("œ", "oe"), ("Š", "S"),
+        ("š", "s"), ("ÙÚÛÜ", "U"), ("ùúûü", "u"), ("ÝŸ", "Y"), ("ýÿ", "y"),
+        ("Ž", "Z"), ("ž", "z"), ("Þ", "Th"), ("þ", "th"), ("ß", "ss"),
+        ("Δ", "Delta"), ("δ", "delta"), ("µμ", "u"), ("°", "deg"), ("²", "2"),
+        ("³", "3"), ("–—", "-"),
+    )
+    for c in chars
+}
+
+
+def normalize_field(name, charset="utf8"):
+    """Apply a charset policy to a new score field name:
+
+    utf8   store it as given (the default)
+    ascii  raise ValueError if it is not ASCII
+    fold   transliterate accented letters and common symbols ("ΔG" ->
+           "DeltaG") and replace any other non-ASCII character with "_"
+
+    Matches the Rust tools' --field-charset.
+    """
+    if charset not in FIELD_CHARSETS:
+        raise ValueError(f"Unknown field charset '{charset}', expected utf8, ascii or fold")
+    if name.isascii() or charset == "utf8":
+        return name
+    if charset == "ascii":
+        raise ValueError(
+            f"Score field '{name}' is not ASCII (use the fold charset to transliterate it)"
+        )
+    return "".join(c if c.isascii() else _FOLD.get(c, "_") for c in name)
+
+
+# Windows-1252 leaves these bytes unassigned; like the Rust tools, read them
+# as the C1 control of the same value
+_CP1252_UNASSIGNED = (0x81, 0x8D, 0x8F, 0x90, 0x9D)
+
+
+def _decode_cp1252(error):
+    bad = error.object[error.start:error.end]
+    text = "".join(
+        chr(b) if b in _CP1252_UNASSIGNED else bytes([b]).decode("cp1252") for b in bad
+    )
+    return text, error.end
+
+
+codecs.register_error("quiver-cp1252", _decode_cp1252)
+
+
+def legacy_error_handler(policy="cp1252"):
+    """The codec error handler reading bytes that are not UTF-8 by `policy`:
+
+    cp1252        decode them as Windows-1252 (the default)
+    replace       replace them with U+FFFD
+    replace=TEXT  replace them with TEXT
+    error         raise UnicodeDecodeError
+
+    Pass the result as `errors=` to open(); matches the Rust tools'
+    --legacy-bytes.
+    """
+    if policy == "cp1252":
+        return "quiver-cp1252"
+    if policy == "replace":
+        return "replace"
+    if policy == "error":
+        return "strict"
+    if policy.startswith("replace="):
+        text = policy[len("replace="):]
+        name = f"quiver-replace={text}"
+        codecs.register_error(name, lambda error: (text, error.end))
+        return name
+    raise ValueError(
+        f"Unknown legacy byte policy '{policy}', expected cp1252, replace, replace=TEXT or error"
+    )
+
+
+# A build that has not touched its index lock for this many seconds is taken
+# to be dead and its lock broken
+INDEX_LOCK_STALE = 60
+# Bytes scanned between progress calls (and lock touches) while indexing
+_INDEX_PROGRESS_EVERY = 64 << 20
+
+
+def index_path(quiver_file):
+    """The sidecar tag index of `quiver_file`: "<quiver_file>.idx"."""
+    return f"{quiver_file}.idx"
+
+
+def _lock_held(lock):
+    try:
+        return time.time() - os.stat(lock).st_mtime <= INDEX_LOCK_STALE
+    except FileNotFoundError:
+        return False
+
+
+def _acquire_index_lock(lock):
+    # True if this process now holds the lock; a stale one is broken first
+    if os.path.exists(lock) and not _lock_held(lock):
+        warnings.warn(f"Breaking stale index lock {lock}")
+        try:
+            os.remove(lock)
+        except FileNotFoundError:
+            pass
+    try:
+        fd = os.open(lock, os.O_CREAT | os.O_EXCL | os.O_WRONLY)
+    except FileExistsError:
+        return False
+    with os.fdopen(fd, "w") as f:
+        f.write(f"{os.getpid()}\n")
+    return True
+
+
+def _load_index(quiver_file):
+    # [(tag, offset, length)] if the index file is up to date, else None
+    try:
+        with open(index_path(quiver_file), encoding="utf-8") as f:
+            stamp = f.readline().rstrip("\n").split("\t")
+            st = os.stat(quiver_file)
+            if stamp != ["QV_INDEX", str(st.st_size), str(st.st_mtime_ns)]:
+                return None
+            entries = []
+            for line in f:
+                tag, offset, length = line.rstrip("\n").rsplit("\t", 2)
+                entries.append((tag, int(offset), int(length)))
+            return entries
+    except FileNotFoundError:
+        return None
+
+
+def _scan_index(quiver_file, progress, lock):
+    st = os.stat(quiver_file)
+    entries = []
+    pos = reported = 0
+    with open(quiver_file, "rb") as f:
+        for line in f:
+            if line.startswith(b"QV_TAG"):
+                if entries:
+                    entries[-1][2] = pos - entries[-1][1]
+                parts = line.split()
+                if len(parts) > 1:
+                    entries.append([parts[1].decode("utf-8", "replace"), pos, 0])
+            pos += len(line)
+            if pos - reported >= _INDEX_PROGRESS_EVERY:
+                reported = pos
+                os.utime(lock)
+                if progress is not None:
+                    progress(pos, st.st_size)
+    if entries:
+        entries[-1][2] = pos - entries[-1][1]
+    if progress is not None:
+        progress(pos, st.st_size)
+
+    path = index_path(quiver_file)
+    tmp_fn = os.path.join(
+        os.path.dirname(os.path.abspath(path)),
+        f".{os.path.basename(path)}.tmp{os.getpid()}",
+    )
+    try:
+        with open(tmp_fn, "w", encoding="utf-8") as out:
+            out.write(f"QV_INDEX\t{st.st_size}\t{st.st_mtime_ns}\n")
+            out.writelines(f"{tag}\t{offset}\t{length}\n" for tag, offset, length in entries)
+            out.flush()
+            os.fsync(out.fileno())
+        os.replace(tmp_fn, path)
+    finally:
+        if os.path.exists(tmp_fn):
+            os.remove(tmp_fn)
+    return [tuple(entry) for entry in entries]
+
+
+def build_index(quiver_file, force_rebuild=False, progress=None):
+    """The tag index of `quiver_file` as [(tag, offset, length)], read from
+    its "<quiver_file>.idx" sidecar if that is up to date, otherwise built
+    and saved there, as the `qvindex` tool does.
+
+    Builds are serialized by a "<quiver_file>.idx.lock" file, so a process
+    that finds another one building waits and then reads its index; a lock
+    not touched for INDEX_LOCK_STALE seconds is broken. The index is written
+    to a temporary file and renamed into place, so it is never seen half
+    written. `force_rebuild` ignores an existing index (unless another
+    process rebuilt it meanwhile), and `progress(bytes_done, bytes_total)`
+    is called while building. Only plain text archives can be indexed.
+    """
+    with open(quiver_file, "rb") as f:
+        head = f.read(4)
+    if head.startswith((b"\x1f\x8b", b"\x28\xb5\x2f\xfd", b"QVB")):
+        raise ValueError(f"Cannot index '{quiver_file}': only plain text archives can be indexed")
+
+    lock = f"{index_path(quiver_file)}.lock"
+    while True:
+        if not force_rebuild:
+            entries = _load_index(quiver_file)
+            if entries is not None:
+                return entries
+        if _acquire_index_lock(lock):
+            try:
+                return _scan_index(quiver_file, progress, lock)
+            finally:
+                os.remove(lock)
+        # Another process is building it; wait, then use its index
+        while _lock_held(lock):
+            time.sleep(0.1)
+        force_rebuild = False
+
+
+def _record_keeper(record_filter):
+    # Predicate keeping PDB lines whose record type (the first six columns)
+    # is in `record_filter`; None keeps every line
+    if record_filter is None:
+        return lambda line: True
+    if isinstance(record_filter, str):
+        raise TypeError("record_filter must be a list of record types, not a string")
+    records = frozenset(record.strip().upper() for record in record_filter)
+    return lambda line: line[:6].rstrip() in records
+
+
+# Machine-readable codes of the warnings collected in a Warnings
+WARNING_CODES = (
+    "missing-tag",
+    "duplicate-request",
+    "duplicate-tag",
+    "skipped-file",
+    "renamed-duplicate",
+    "partial-entry",
+    "output-quota",
+)
+
+# Exit status of the extraction tools when stopped at their output quota
+EXIT_QUOTA_REACHED = 6
+
+QuiverWarning = namedtuple("QuiverWarning", ["code", "message"])
+
+
+class Warnings:
+    """Warnings collected by an operation instead of printed as they
+    happen, in the order they were raised. Each is a QuiverWarning whose
+    `code` is one of WARNING_CODES; the same warning is kept once.
+    """
+
+    def __init__(self):
+        self._warnings = []
+        self._seen = set()
+
+    def push(self, code, message):
+        if code not in WARNING_CODES:
+            raise ValueError(f"Unknown warning code '{code}'")
+        warning = QuiverWarning(code, message)
+        if warning not in self._seen:
+            self._seen.add(warning)
+            self._warnings.append(warning)
+
+    def with_code(self, code):
+        """The warnings with the given code."""
+        return [w for w in self._warnings if w.code == code]
+
+    def report(self, file=None):
+        """Print every warning, one per line, on `file` (stderr by default)."""
+        for warning in self._warnings:
+            print(f"⚠️  [{warning.code}] {warning.message}", file=file or sys.stderr)
+
+    def __iter__(self):
+        return iter(self._warnings)
+
+    def __len__(self):
+        return len(self._warnings)
+
+    def __repr__(self):
+        return f"Warnings({self._warnings!r})"
+
+
+SliceResult = namedtuple("SliceResult", ["data", "found_tags", "missing_tags", "warnings"])
+
+ScoreLookup = namedtuple("ScoreLookup", ["scores", "missing_tags"])
+
+StructureStats = namedtuple("StructureStats", ["structures", "atoms", "residues", "chains", "bytes"])
+
+
+def _structure_stats(pdb_lines):
+    # StructureStats of one entry
+    structure = Structure.from_lines(pdb_lines)
+    return StructureStats(
+        1,
+        len(structure.atoms()),
+        len(structure.residues()),
+        tuple(chain.id for chain in structure.chains),
+        sum(len(line.encode("utf-8", "replace")) for line in pdb_lines),
+    )
+
+
+class EntryStatus(str, enum.Enum):
+    """What Quiver.try_get() found for a tag; compares equal to its name."""
+
+    FOUND = "found"  # read and, if it has a QV_HASH line, matching it
+    MISSING = "missing"  # not in the archive
+    CORRUPT = "corrupt"  # the payload does not match its QV_HASH line
+    STALE = "stale"  # the file changed and the entry is gone since it was opened
+
+
+# The outcome of Quiver.try_get(): `pdb_lines` for FOUND and CORRUPT entries
+# and `detail` explaining CORRUPT and STALE ones, else None
+EntryResult = namedtuple("EntryResult", ["tag", "status", "pdb_lines", "detail"])
+
+# The last entry of an archive cut short by its writer, see Quiver.partial_entry
+PartialEntry = namedtuple("PartialEntry", ["tag", "offset", "bytes"])
+
+
+class _BoundedReader(io.RawIOBase):
+    # The first `limit` bytes of binary file `raw`, hiding what follows
+
+    def __init__(self, raw, limit):
+        self._raw = raw
+        self._limit = limit
+
+    def readable(self):
+        return True
+
+    def seekable(self):
+        return self._raw.seekable()
+
+    def tell(self):
+        return self._raw.tell()
+
+    def seek(self, offset, whence=io.SEEK_SET):
+        if whence == io.SEEK_END:
+            return self._raw.seek(self._limit + offset)
+        return self._raw.seek(offset, whence)
+
+    def readinto(self, b):
+        n = max(min(len(b), self._limit - self._raw.tell()), 0)
+        data = self._raw.read(n)
+        b[: len(data)] = data
+        return len(data)
+
+    def close(self):
+        self._raw.close()
+        super().close()
+
+
+def _legacy_table(legacy_records):
+    # `legacy_records` (True for LEGACY_RECORDS, or a dict of variant ->
+    # canonical keyword) as (variant, canonical) bytes pairs, longest first
+    # so a short variant does not shadow a longer one
+    if legacy_records is True:
+        legacy_records = LEGACY_RECORDS
+    table = []
+    for variant, canonical in legacy_records.items():
+        if canonical not in RESERVED_PREFIXES:
+            raise ValueError(
+                f"Unknown record '{canonical}', expected one of {', '.join(RESERVED_PREFIXES)}"
+            )
+        if not variant.strip() or variant != variant.strip() or variant in RESERVED_PREFIXES:
+            raise ValueError(
+                f"Invalid legacy spelling '{variant}': it must be non-empty, without "
+                "surrounding whitespace, and not a canonical record"
+            )
+        table.append((variant.encode("utf-8"), canonical.encode("utf-8")))
+    return sorted(table, key=lambda pair: (-len(pair[0]), pair[0]))
+
+
+def _modernize_line(line, table):
+    # Binary `line` with a legacy keyword of `table` at its start replaced by
+    # the canonical one; the same object if there is none
+    for variant, canonical in table:
+        rest = line[len(variant):]
+        if line.startswith(variant) and (not rest or rest[:1].isspace()):
+            return canonical + rest
+    return line
+
+
+class _LegacyReader(io.RawIOBase):
+    # Binary stream of `raw` with the legacy keywords of `table` rewritten
+
+    def __init__(self, raw, table):
+        self._raw = raw
+        self._table = table
+        self._pending = b""
+
+    def readable(self):
+        return True
+
+    def readinto(self, b):
+        while not self._pending:
+            line = self._raw.readline()
+            if not line:
+                return 0
+            self._pending = _modernize_line(line, self._table)
+        n = min(len(b), len(self._pending))
+        b[:n] = self._pending[:n]
+        self._pending = self._pending[n:]
+        return n
+
+    def close(self):
+        self._raw.close()
+        super().close()
+
+
+# Coordinates of selected atoms: `coords` is an (N, 3) float array, the
+# others are length-N arrays describing each atom
+Coordinates = namedtuple("Coordinates", ["coords", "chain", "resseq", "resname", "name"])
+
+
+class AccessLog:
+    """Appends one tab-separated line per entry access:
+
+    unix_time  user  program  pid  operation  archive  tag
+
+    The same format the Rust tools write, so both can share one log.
+    """
+
+    def __init__(self, path):
+        self.path = path
+        self.user = os.environ.get("USER") or os.environ.get("LOGNAME") or "-"
+        self.program = os.path.basename(sys.argv[0]) if sys.argv and sys.argv[0] else "-"
+
+    def __call__(self, archive, operation, tag):
+        line = (
+            f"{time.time():.3f}\t{self.user}\t{self.program}\t{os.getpid()}"
+            f"\t{operation}\t{archive}\t{tag}\n"
+        )
+        # One write on an append-mode file, so processes can share the log
+        with open(self.path, "a") as f:
+            f.write(line)
+
+
+def _check_layout(tag, score_str, pdb_lines):
+    # Would the entry read back as written? See the Rust Entry::check_layout
+    if not tag or any(c.isspace() for c in tag):
+        raise ValueError(f"tag '{tag}' is empty or contains whitespace")
+    if score_str is not None and any(c.isspace() for c in score_str):
+        raise ValueError("score string contains whitespace")
+    for i, line in enumerate(pdb_lines):
+        for part in line.split("\n"):
+            prefix = next((p for p in RESERVED_PREFIXES if part.startswith(p)), None)
+            if prefix:
+                raise ValueError(f"payload line {i + 1} starts with reserved record {prefix}")
 
 
 class Quiver:
-    def __init__(self, filename, mode):
+    def __init__(
+        self,
+        filename,
+        mode,
+        access_hook=None,
+        duplicate_policy="last",
+        field_charset="utf8",
+        legacy_bytes="cp1252",
+        validators=None,
+        strict=True,
+        write_hashes=False,
+        index=False,
+        force_rebuild=False,
+        progress=None,
+        include_partial=False,
+        legacy_records=None,
+    ):
+        """Open `filename` in "r" or "w" mode.
+
+        `access_hook(archive, operation, tag)` is called for every entry whose
+        PDB lines are read; without one, accesses are logged to the file
+        named by QUIVER_ACCESS_LOG if it is set.
+
+        `duplicate_policy` decides which value get_scores() returns when a
+        score line repeats a name, see resolve_duplicates().
+
+        `field_charset` applies to the names of scores written, see
+        normalize_field(), and `legacy_bytes` decides how bytes that are not
+        UTF-8 are read, see legacy_error_handler().
+
+        `validators` are checked on every add_pdb, see add_validator().
+        Before them, unless `strict` is False, add_pdb refuses entries that
+        would corrupt the archive: a tag or score string with whitespace, or
+        a PDB line starting with one of RESERVED_PREFIXES.
+
+        With `write_hashes`, add_pdb stores a checksum of every structure as
+        a QV_HASH line, for verify() to check.
+
+        With `index` (read mode only), tags and entry offsets come from the
+        "<filename>.idx" sidecar, built first if it is missing or stale, so
+        opening a huge archive again does not rescan it and get_pdblines()
+        reads only the entry's bytes. `force_rebuild` and `progress` are
+        passed to build_index().
+
+        In read mode, a last entry cut short by a writer that is still
+        running or was killed (its last line is not terminated, or its
+        structure does not match its QV_HASH) is hidden with a warning, so
+        only complete entries are read; it is described by `partial_entry`
+        either way. With `include_partial` it is read like the others.
+
+        `filename` may also be an http(s):// or s3:// URL, opened read-only
+        and read through ranged requests (see the quiver.remote module). A
+        "<url>.idx" index next to it is always used if present, so only the
+        index and the entries read are fetched.
+
+        With `legacy_records` (read mode only), record keywords spelled by
+        older writers are read as the canonical ones: True rewrites the
+        documented variants in LEGACY_RECORDS ("QVTAG", "QV TAG", ...), a
+        dict of variant -> canonical keyword rewrites those instead. The
+        file itself is not changed; see modernize_archive() to convert it.
+        """
         if mode not in ("r", "w"):
             raise ValueError(
                 f"Quiver file must be opened in 'r' or 'w' mode, not '{mode}'"
             )
+        if duplicate_policy not in DUPLICATE_POLICIES:
+            raise ValueError(
+                f"Unknown duplicate policy '{duplicate_policy}', expected first, last, error or warn"
+            )
+        self.duplicate_policy = duplicate_policy
+        normalize_field("", field_charset)
+        self.field_charset = field_charset
+        self._errors = legacy_error_handler(legacy_bytes)
         self.mode = mode
         self.fn = filename
-        self.tags = self._read_tags()
+        if index and mode != "r":
+            raise ValueError("A tag index can only be used in read mode")
+        # (variant, canonical) keywords rewritten as the file is read
+        self._legacy = None
+        if legacy_records:
+            if mode != "r" or index or remote.is_remote(filename):
+                raise ValueError(
+                    "Legacy record spellings can only be read from local files in read "
+                    "mode, without a tag index"
+                )
+            self._legacy = _legacy_table(legacy_records)
+        if remote.is_remote(filename) and mode != "r":
+            raise ValueError(f"Remote archives can only be opened in read mode: {filename}")
+        # tag -> (offset, length) of its entry, from the tag index
+        self._offsets = None
+        # Readers stop at this byte offset when the partial entry is hidden
+        self._limit = None
+        entries = None
+        if remote.is_remote(filename):
+            entries = remote.load_index(filename)
+        elif index and os.path.exists(filename):
+            entries = build_index(filename, force_rebuild, progress)
+        if entries is not None:
+            self._offsets = {}
+            for tag, offset, length in entries:
+                self._offsets.setdefault(tag, (offset, length))
+            self.tags = [tag for tag, _, _ in entries]
+        else:
+            self.tags = self._read_tags()
+        self.partial_entry = None
+        if mode == "r" and not remote.is_remote(filename) and os.path.exists(filename):
+            self.partial_entry = _partial_tail(filename)
+        if self.partial_entry is not None and not include_partial:
+            tag, offset, length = self.partial_entry
+            if tag is not None:
+                self.tags.pop()
+                if self._offsets is not None and self._offsets.get(tag, (None,))[0] == offset:
+                    del self._offsets[tag]
+            self._limit = offset
+            what = f"entry {tag}" if tag is not None else "entry"
+            warnings.warn(
+                f"Ignoring the incomplete last {what} ({length} bytes at byte {offset}); "
+                "its writer is still running or was killed"
+            )
+        self._tag_set = set(self.tags)
+        # Append handle kept open in write mode, opened on the first add_pdb
+        self._handle = None
+        self.closed = False
+        if access_hook is None and os.environ.get(ACCESS_LOG_ENV):
+            access_hook = AccessLog(os.environ[ACCESS_LOG_ENV])
+        self.access_hook = access_hook
+        self.validators = list(validators or [])
+        self.strict = strict
+        self.write_hashes = write_hashes
+
+    def add_validator(self, validator):
+        """Check every entry added from now on with
+        `validator(tag, pdb_lines, scores)`, after any added before it.
+
+        `scores` is a dict of the raw score strings. The validator raises
+        ValueError to reject the entry, which is then not written; see the
+        quiver.validate module for common rules.
+        """
+        self.validators.append(validator)
+
+    def _open(self):
+        if remote.is_remote(self.fn):
+            return remote.open_text(self.fn, self._errors)
+        if self._limit is not None or self._legacy is not None:
+            return io.TextIOWrapper(self._open_binary(), encoding="utf-8", errors=self._errors)
+        return open(self.fn, "r", encoding="utf-8", errors=self._errors)
+
+    def _open_binary(self):
+        if remote.is_remote(self.fn):
+            return remote.open_binary(self.fn)
+        if self._limit is not None:
+            f = io.BufferedReader(_BoundedReader(open(self.fn, "rb", buffering=0), self._limit))
+        else:
+            f = open(self.fn, "rb")
+        if self._legacy is not None:
+            return io.BufferedReader(_LegacyReader(f, self._legacy))
+        return f
+
+    def _exists(self):
+        # A remote archive that does not exist fails when it is opened
+        return remote.is_remote(self.fn) or os.path.exists(self.fn)
+
+    def _record_access(self, operation, tag):
+        if self.access_hook is not None:
+            self.access_hook(self.fn, operation, tag)
+
+    def __enter__(self):
+        return self
+
+    def __exit__(self, exc_type, exc_value, traceback):
+        # Entries are flushed as they are added, so closing after an
+        # exception leaves every entry added before it in the file.
+        self.close()
+        return False
+
+    def flush(self):
+        """Write buffered entries to disk so other readers can see them.
+        add_pdb() and add_raw_entry() already flush every entry they write."""
+        if self._handle is not None:
+            self._handle.flush()
+
+    def close(self):
+        """Flush and close the write handle. Safe to call more than once."""
+        if self._handle is not None:
+            self._handle.close()
+            self._handle = None
+        self.closed = True
 
     def _read_tags(self):
-        if not os.path.exists(self.fn):
+        if not self._exists():
             return []
-        with open(self.fn, "r") as f:
-            return [line.split()[1] for line in f if line.startswith("QV_TAG")]
+        with self._open() as f:
+            # A tag line without its tag was cut short by the writer
+            return [
+                line.split()[1] for line in f if line.startswith("QV_TAG") and len(line.split()) > 1
+            ]
 
     def get_tags(self):
         return list(self.tags)
@@ -32,30 +783,180 @@ class Quiver:
     def size(self):
         return len(self.tags)
 
-    def add_pdb(self, pdb_lines, tag, score_str=None):
+    def __len__(self):
+        return len(self.tags)
+
+    def __contains__(self, tag):
+        return tag in self._tag_set
+
+    def __getitem__(self, tag):
+        """The PDB lines of `tag`; raises KeyError if it is not in the file."""
+        return self.get_pdblines(tag)
+
+    def add_pdb(self, pdb_lines, tag, score_str=None, meta=None):
+        if self.mode != "w":
+            raise RuntimeError(
+                "Quiver file must be opened in write mode to allow for writing."
+            )
+        if self.closed:
+            raise RuntimeError("Quiver file is closed.")
+        if tag in self._tag_set:
+            raise ValueError(f"Tag {tag} already exists in this file.")
+
+        if score_str is not None and self.field_charset != "utf8":
+            fields = (kv.partition("=") for kv in score_str.split("|"))
+            score_str = "|".join(
+                f"{normalize_field(k, self.field_charset)}{eq}{v}" for k, eq, v in fields
+            )
+        if self.strict:
+            try:
+                _check_layout(tag, score_str, pdb_lines)
+            except ValueError as e:
+                raise ValueError(f"Entry {tag} rejected: {e}") from e
+        if meta:
+            for field in (str(f) for item in meta.items() for f in item):
+                _check_meta_field(field)
+        if self.validators:
+            fields = (kv.partition("=") for kv in (score_str or "").split("|"))
+            scores = {k: v for k, eq, v in fields if eq}
+            for validator in self.validators:
+                try:
+                    validator(tag, pdb_lines, scores)
+                except ValueError as e:
+                    raise ValueError(f"Entry {tag} rejected: {e}") from e
+
+        parts = [f"QV_TAG {tag}\n"]
+        if score_str is not None:
+            parts.append(f"QV_SCORE {tag} {score_str}\n")
+        if meta:
+            parts.append(_format_meta_line(tag, meta))
+        if self.write_hashes:
+            parts.append(f"QV_HASH {tag} {payload_digest(pdb_lines)}\n")
+        parts.extend(pdb_lines)
+        if pdb_lines and not pdb_lines[-1].endswith("\n"):
+            parts.append("\n")
+
+        if self._handle is None:
+            self._handle = open(self.fn, "a", encoding="utf-8")
+        # Flushed per entry, so other readers see it once add_pdb returns. A
+        # run killed mid-write can still leave part of an entry at the end,
+        # which readers skip (see partial_entry).
+        self._handle.write("".join(parts))
+        self._handle.flush()
+        self.tags.append(tag)
+        self._tag_set.add(tag)
+
+    def add_raw_entry(self, tag, raw_bytes, score_str=None, validate=False):
+        """Append a PDB entry whose payload is already formatted text
+        (bytes), written as given rather than split into lines, for
+        producers that hold whole structures in memory.
+
+        Only the tag is checked unless `validate` is set, in which case the
+        payload is split into lines and checked like add_pdb() does. Without
+        it, the caller vouches that no payload line starts with a QV_ record.
+        A missing final newline is added either way.
+        """
+        if validate:
+            lines = raw_bytes.decode("utf-8").splitlines(keepends=True)
+            return self.add_pdb(lines, tag, score_str)
         if self.mode != "w":
             raise RuntimeError(
                 "Quiver file must be opened in write mode to allow for writing."
             )
-        if tag in self.tags:
+        if self.closed:
+            raise RuntimeError("Quiver file is closed.")
+        if tag in self._tag_set:
             raise ValueError(f"Tag {tag} already exists in this file.")
+        if not tag or any(c.isspace() for c in tag):
+            raise ValueError(f"Entry {tag} rejected: tag '{tag}' is empty or contains whitespace")
+
+        if score_str is not None and self.field_charset != "utf8":
+            fields = (kv.partition("=") for kv in score_str.split("|"))
+            score_str = "|".join(
+                f"{normalize_field(k, self.field_charset)}{eq}{v}" for k, eq, v in fields
+            )
+        head = f"QV_TAG {tag}\n"
+        if score_str is not None:
+            head += f"QV_SCORE {tag} {score_str}\n"
+        if self.write_hashes:
+            if raw_bytes.endswith(b"\n") and b"\r" not in raw_bytes:
+                digest = f"fnv1a64:{_fnv1a64(raw_bytes):016x}"
+            else:
+                lines = raw_bytes.decode("utf-8", "replace").split("\n")
+                digest = payload_digest(lines[:-1] if lines[-1] == "" else lines)
+            head += f"QV_HASH {tag} {digest}\n"
+        tail = b"\n" if raw_bytes and not raw_bytes.endswith(b"\n") else b""
 
-        with open(self.fn, "a") as f:
-            f.write(f"QV_TAG {tag}\n")
-            if score_str is not None:
-                f.write(f"QV_SCORE {tag} {score_str}\n")
-            f.writelines(pdb_lines)
-            if pdb_lines and not pdb_lines[-1].endswith("\n"):
-                f.write("\n")
+        if self._handle is None:
+            self._handle = open(self.fn, "a", encoding="utf-8")
+        # One write per entry, past whatever the text layer still buffers
+        self._handle.flush()
+        self._handle.buffer.write(head.encode("utf-8") + raw_bytes + tail)
+        self._handle.buffer.flush()
         self.tags.append(tag)
+        self._tag_set.add(tag)
+
+    def __iter__(self):
+        """Yield (tag, score_str, pdb_lines) for every entry in file order.
+
+        The file is read once, so this is much faster than calling
+        get_pdblines() for every tag. score_str is None for unscored entries.
+        """
+        if self.mode != "r":
+            raise RuntimeError(
+                "Quiver file must be opened in read mode to allow for reading."
+            )
+        if not self._exists():
+            return
+
+        with self._open() as f:
+            tag, score_str, pdb_lines = None, None, []
+            for line in f:
+                if line.startswith("QV_TAG"):
+                    if tag is not None:
+                        self._record_access("iter", tag)
+                        yield tag, score_str, pdb_lines
+                    tag, score_str, pdb_lines = line.split()[1], None, []
+                elif tag is None:
+                    continue
+                elif line.startswith("QV_SCORE"):
+                    parts = line.split()
+                    score_str = parts[2] if len(parts) > 2 else None
+                elif not line.startswith(("QV_META", "QV_HASH")):
+                    pdb_lines.append(line)
+            if tag is not None:
+                self._record_access("iter", tag)
+                yield tag, score_str, pdb_lines
+
+    def get_pdblines(self, tag, record_filter=None):
+        """The PDB lines of `tag`; raises KeyError if it is not in the file.
 
-    def get_pdblines(self, tag):
+        With `record_filter`, a list of record types such as
+        ["ATOM", "HETATM", "TER", "END"], only lines of those types are kept,
+        dropped as the entry is read rather than in a second pass.
+        """
         if self.mode != "r":
             raise RuntimeError(
                 "Quiver file must be opened in read mode to allow for reading."
             )
+        keep = _record_keeper(record_filter)
+
+        if self._offsets is not None:
+            if tag not in self._offsets:
+                raise KeyError(f"Requested tag: {tag} does not exist")
+            offset, length = self._offsets[tag]
+            with self._open_binary() as f:
+                f.seek(offset)
+                block = f.read(length).decode("utf-8", self._errors)
+            lines = io.StringIO(block, newline=None).readlines()[1:]
+            self._record_access("get_pdblines", tag)
+            return [
+                line
+                for line in lines
+                if not line.startswith(("QV_SCORE", "QV_META", "QV_HASH")) and keep(line)
+            ]
 
-        with open(self.fn, "r") as f:
+        with self._open() as f:
             found = False
             pdb_lines = []
             for line in f:
@@ -67,60 +968,2204 @@ class Quiver:
                     elif found:
                         break
                 if found:
-                    if not line.startswith("QV_SCORE"):
+                    if not line.startswith(("QV_SCORE", "QV_META", "QV_HASH")) and keep(line):
                         pdb_lines.append(line)
             if not found:
                 raise KeyError(f"Requested tag: {tag} does not exist")
+            self._record_access("get_pdblines", tag)
             return pdb_lines
 
-    def get_struct_list(self, tag_list):
+    def try_get(self, tag):
+        """Read `tag` without raising for the ways one entry can be unusable,
+        returning EntryResult(tag, status, pdb_lines, detail) with an
+        EntryStatus: FOUND, MISSING (not in the file), CORRUPT (the payload
+        fails its QV_HASH checksum) or STALE (the file was rewritten since it
+        was opened and the entry is gone).
+        """
         if self.mode != "r":
             raise RuntimeError(
                 "Quiver file must be opened in read mode to allow for reading."
             )
+        if tag not in self._tag_set:
+            return EntryResult(tag, EntryStatus.MISSING, None, None)
 
-        tag_set = set(tag_list)
-        found_tags = []
-        struct_lines = []
-        write_mode = False
+        stored, pdb_lines = None, None
+        if self._offsets is not None:
+            offset, length = self._offsets[tag]
+            with self._open_binary() as f:
+                f.seek(offset)
+                block = f.read(length).decode("utf-8", self._errors)
+            lines = io.StringIO(block, newline=None).readlines()
+            if lines and lines[0].split()[:2] == ["QV_TAG", tag]:
+                pdb_lines = lines[1:]
+        elif self._exists():
+            with self._open() as f:
+                for line in f:
+                    if line.startswith("QV_TAG"):
+                        if pdb_lines is not None:
+                            break
+                        if line.split()[1] == tag:
+                            pdb_lines = []
+                    elif pdb_lines is not None:
+                        pdb_lines.append(line)
+        if pdb_lines is None:
+            return EntryResult(
+                tag, EntryStatus.STALE, None, "the entry is gone since the file was opened"
+            )
 
-        with open(self.fn, "r") as f:
+        self._record_access("try_get", tag)
+        for line in pdb_lines:
+            if line.startswith("QV_HASH"):
+                parts = line.split()
+                stored = parts[2] if len(parts) > 2 else None
+        pdb_lines = [line for line in pdb_lines if not line.startswith(RESERVED_PREFIXES)]
+        actual = payload_digest(pdb_lines)
+        if stored is not None and stored != actual:
+            return EntryResult(
+                tag,
+                EntryStatus.CORRUPT,
+                pdb_lines,
+                f"payload digest {actual} does not match the stored {stored}",
+            )
+        return EntryResult(tag, EntryStatus.FOUND, pdb_lines, None)
+
+    def verify(self, tag=None):
+        """Check entries against their QV_HASH checksums, to find structures
+        truncated or corrupted by an interrupted job or a partial
+        network-filesystem write.
+
+        Checks `tag`, or every entry when it is None. Returns a dict of tag ->
+        "ok", "mismatch" or "unhashed" (no checksum stored), in file order.
+        Entries that are not mismatched but hold no coordinates at all (see
+        validate.sniff_structure()) are "not-structure". Raises KeyError if
+        `tag` is not in the file.
+        """
+        if self.mode != "r":
+            raise RuntimeError(
+                "Quiver file must be opened in read mode to allow for reading."
+            )
+        if tag is not None and tag not in self._tag_set:
+            raise KeyError(f"Requested tag: {tag} does not exist")
+
+        results = {}
+
+        def check(current, stored, payload):
+            results[current] = _payload_integrity(stored, payload)
+            self._record_access("verify", current)
+
+        with self._open() as f:
+            current, stored, payload = None, None, []
             for line in f:
                 if line.startswith("QV_TAG"):
-                    current_tag = line.split()[1]
-                    write_mode = current_tag in tag_set
-                    if write_mode:
-                        found_tags.append(current_tag)
-                if write_mode:
-                    struct_lines.append(line)
-        return "".join(struct_lines), found_tags
+                    if current is not None and tag in (None, current):
+                        check(current, stored, payload)
+                    current, stored, payload = line.split()[1], None, []
+                elif current is None:
+                    continue
+                elif line.startswith("QV_HASH"):
+                    parts = line.split()
+                    stored = parts[2] if len(parts) > 2 else None
+                elif not line.startswith(RESERVED_PREFIXES):
+                    payload.append(line)
+            if current is not None and tag in (None, current):
+                check(current, stored, payload)
+        return results
 
-    def split(self, ntags, outdir, prefix):
+    def get_coords(self, tag, atom_selection="CA"):
+        """Coordinates of the ATOM records of `tag` as NumPy arrays, without
+        writing a PDB file.
+
+        `atom_selection` is an atom name ("CA"), a list of names, or "all".
+        Returns Coordinates(coords, chain, resseq, resname, name), where
+        `coords` has shape (N, 3) and the other arrays have length N, in
+        file order. Raises KeyError if the tag is not in the file.
+        """
+        if isinstance(atom_selection, str):
+            names = None if atom_selection == "all" else {atom_selection}
+        else:
+            names = set(atom_selection)
+            if not names:
+                raise ValueError("atom_selection must name at least one atom")
+        import numpy as np
+
+        coords, chains, resseqs, resnames, atom_names = [], [], [], [], []
+        for line in self.get_pdblines(tag):
+            if not line.startswith("ATOM") or len(line) < 54:
+                continue
+            name = line[12:16].strip()
+            if names is not None and name not in names:
+                continue
+            coords.append((float(line[30:38]), float(line[38:46]), float(line[46:54])))
+            chains.append(line[21])
+            resseqs.append(int(line[22:26]))
+            resnames.append(line[17:20].strip())
+            atom_names.append(name)
+        return Coordinates(
+            np.array(coords, dtype=np.float64).reshape(-1, 3),
+            np.array(chains, dtype=str),
+            np.array(resseqs, dtype=np.int64),
+            np.array(resnames, dtype=str),
+            np.array(atom_names, dtype=str),
+        )
+
+    def get_structure(self, tag):
+        """Parse the PDB lines of `tag` into a Structure of chains, residues
+        and atoms. Raises KeyError if the tag is not in the file."""
+        return Structure.from_lines(self.get_pdblines(tag))
+
+    def stats(self, tag=None):
+        """Atom, residue and chain counts and payload size of the structure
+        `tag`, or summed over every entry in one pass over the file, as a
+        StructureStats.
+
+        Residues and chains are as in get_structure(); `chains` lists the
+        chain IDs in the order they first appear (over the whole file when
+        summed) and `bytes` is the size of the structure's lines, without the
+        QV_* lines. Raises KeyError if `tag` is not in the file.
+        """
+        if tag is not None:
+            return _structure_stats(self.get_pdblines(tag))
+        total = StructureStats(0, 0, 0, (), 0)
+        chains = {}
+        for _, _, pdb_lines in self:
+            entry = _structure_stats(pdb_lines)
+            chains.update(dict.fromkeys(entry.chains))
+            total = StructureStats(
+                total.structures + 1,
+                total.atoms + entry.atoms,
+                total.residues + entry.residues,
+                (),
+                total.bytes + entry.bytes,
+            )
+        return total._replace(chains=tuple(chains))
+
+    def get_header_records(self, tag):
+        """The header records (HEADER, COMPND, SEQRES, ..., see
+        structure.HEADER_RECORDS) stored with `tag`, in file order. Raises
+        KeyError if the tag is not in the file."""
+        return header_records(self.get_pdblines(tag))
+
+    def rmsd(self, tag_a, tag_b, atom_selection="CA"):
+        """RMSD in Angstrom between two stored structures after Kabsch
+        superposition.
+
+        `atom_selection` is as for get_coords(); the selected atoms of both
+        structures are paired in file order. Raises ValueError if the
+        selections differ in size or are empty, and KeyError if a tag is not
+        in the file.
+        """
+        import numpy as np
+
+        a = self.get_coords(tag_a, atom_selection).coords
+        b = self.get_coords(tag_b, atom_selection).coords
+        label = atom_selection if isinstance(atom_selection, str) else ",".join(atom_selection)
+        if len(a) != len(b):
+            raise ValueError(f"{tag_a} has {len(a)} {label} atoms but {tag_b} has {len(b)}")
+        if len(a) == 0:
+            raise ValueError(f"No {label} atoms in {tag_a}")
+        a = a - a.mean(axis=0)
+        b = b - b.mean(axis=0)
+        u, sigma, vt = np.linalg.svd(b.T @ a)
+        if np.linalg.det(u @ vt) < 0:  # best fit is a reflection
+            sigma[-1] = -sigma[-1]
+        msd = ((a * a).sum() + (b * b).sum() - 2.0 * sigma.sum()) / len(a)
+        return float(np.sqrt(max(msd, 0.0)))
+
+    def get_scores(self, tag):
+        """The scores of `tag` as a dict of score name -> typed value (int,
+        float, bool or str, see parse_score_value()).
+
+        Raises KeyError if the tag is not in the file and ValueError if it has
+        no QV_SCORE line, the line cannot be parsed, or it repeats a name
+        under the "error" duplicate policy.
+        """
         if self.mode != "r":
             raise RuntimeError(
                 "Quiver file must be opened in read mode to allow for reading."
             )
+        if tag not in self._tag_set:
+            raise KeyError(f"Requested tag: {tag} does not exist")
 
-        os.makedirs(outdir, exist_ok=True)
-        file_idx = 0
-        tag_count = 0
-        out_file = None
+        with self._open() as f:
+            found = False
+            for line in f:
+                if line.startswith("QV_TAG"):
+                    if found:
+                        break
+                    found = line.split()[1] == tag
+                elif found and line.startswith("QV_SCORE"):
+                    parts = line.split()
+                    if len(parts) < 3:
+                        raise ValueError(f"Tag {tag} has an empty QV_SCORE line")
+                    fields = [s.split("=", 1) for s in parts[2].split("|")]
+                    if any(len(kv) != 2 or not kv[0] for kv in fields):
+                        raise ValueError(
+                            f"Tag {tag} has a malformed QV_SCORE line: {parts[2]}"
+                        )
+                    scores = resolve_duplicates(fields, self.duplicate_policy, tag)
+                    return {name: parse_score_value(value) for name, value in scores.items()}
+        raise ValueError(f"Tag {tag} has no QV_SCORE line")
 
-        def open_new_file():
-            nonlocal file_idx, out_file
-            if out_file:
-                out_file.close()
-            out_path = os.path.join(outdir, f"{prefix}_{file_idx}.qv")
-            out_file = open(out_path, "w")
-            file_idx += 1
+    def get_scores_many(self, tags):
+        """The scores of several tags at once, as a ScoreLookup of a dict of
+        tag -> scores (as in get_scores()) and the requested tags not in the
+        file, in request order.
+
+        Without a tag index the file is read once, up to the last requested
+        score line; with one, only the requested entries are read. A tag
+        whose entry has no QV_SCORE line maps to an empty dict. Raises
+        ValueError for a malformed QV_SCORE line of a requested tag.
+        """
+        if self.mode != "r":
+            raise RuntimeError(
+                "Quiver file must be opened in read mode to allow for reading."
+            )
+        wanted = dict.fromkeys(tag for tag in tags if tag in self._tag_set)
+        missing = [tag for tag in dict.fromkeys(tags) if tag not in self._tag_set]
+        lines = {}
+        if self._offsets is not None:
+            with self._open_binary() as f:
+                for tag in wanted:
+                    offset, length = self._offsets[tag]
+                    f.seek(offset)
+                    block = f.read(length).decode("utf-8", self._errors)
+                    score = [line for line in block.splitlines() if line.startswith("QV_SCORE")]
+                    if score:
+                        lines[tag] = score[0]
+        else:
+            with self._open() as f:
+                for line in f:
+                    if len(lines) == len(wanted):
+                        break
+                    if not line.startswith("QV_SCORE"):
+                        continue
+                    parts = line.split()
+                    if len(parts) > 1 and parts[1] in wanted:
+                        lines.setdefault(parts[1], line)
 
-        with open(self.fn, "r") as f:
+        scores = {}
+        for tag in wanted:
+            parts = lines.get(tag, "").split()
+            if len(parts) < 3:
+                scores[tag] = {}
+                continue
+            fields = [s.split("=", 1) for s in parts[2].split("|")]
+            if any(len(kv) != 2 or not kv[0] for kv in fields):
+                raise ValueError(f"Tag {tag} has a malformed QV_SCORE line: {parts[2]}")
+            resolved = resolve_duplicates(fields, self.duplicate_policy, tag)
+            scores[tag] = {name: parse_score_value(value) for name, value in resolved.items()}
+        return ScoreLookup(scores, missing)
+
+    def filter_by_score(self, column, min=None, max=None):
+        """Tags whose numeric `column` score lies within [min, max], in file
+        order, reading the file once. Either bound may be None (open).
+
+        Entries without the column, or whose value is not a number (or is
+        nan), never match. Raises ValueError for a malformed QV_SCORE line.
+        """
+        if self.mode != "r":
+            raise RuntimeError(
+                "Quiver file must be opened in read mode to allow for reading."
+            )
+        tags = []
+        with self._open() as f:
             for line in f:
-                if line.startswith("QV_TAG"):
-                    if tag_count % ntags == 0:
-                        open_new_file()
-                    tag_count += 1
-                if out_file:
-                    out_file.write(line)
-            if out_file:
-                out_file.close()
+                if not line.startswith("QV_SCORE"):
+                    continue
+                parts = line.split()
+                if len(parts) < 3:
+                    continue
+                tag = parts[1]
+                fields = [s.split("=", 1) for s in parts[2].split("|")]
+                if any(len(kv) != 2 or not kv[0] for kv in fields):
+                    raise ValueError(
+                        f"Tag {tag} has a malformed QV_SCORE line: {parts[2]}"
+                    )
+                scores = resolve_duplicates(fields, self.duplicate_policy, tag)
+                if column not in scores:
+                    continue
+                value = parse_score_value(scores[column])
+                # bool is an int subclass, but true/false are not scores here
+                if isinstance(value, bool) or not isinstance(value, (int, float)):
+                    continue
+                if value != value:
+                    continue
+                if (min is None or value >= min) and (max is None or value <= max):
+                    tags.append(tag)
+        return tags
+
+    def get_score_records(self):
+        """Every QV_SCORE line in file order as (tag, scores) pairs, with
+        typed values as in get_scores(). Entries without scores are left out.
+
+        Raises ValueError for a malformed QV_SCORE line.
+        """
+        if self.mode != "r":
+            raise RuntimeError(
+                "Quiver file must be opened in read mode to allow for reading."
+            )
+        records = []
+        with self._open() as f:
+            for line in f:
+                if not line.startswith("QV_SCORE"):
+                    continue
+                parts = line.split()
+                if len(parts) < 3:
+                    continue
+                tag = parts[1]
+                fields = [s.split("=", 1) for s in parts[2].split("|")]
+                if any(len(kv) != 2 or not kv[0] for kv in fields):
+                    raise ValueError(
+                        f"Tag {tag} has a malformed QV_SCORE line: {parts[2]}"
+                    )
+                scores = resolve_duplicates(fields, self.duplicate_policy, tag)
+                records.append(
+                    (tag, {name: parse_score_value(v) for name, v in scores.items()})
+                )
+        return records
+
+    def scores_dataframe(self, backend="pandas"):
+        """The scores as a DataFrame with one row per scored entry, read
+        straight from the file rather than through a scorefile.
+
+        `tag` is the first column, the others follow in the order they first
+        appear. `backend` is "pandas" (nullable dtypes, so int and bool
+        columns with gaps keep their type) or "polars"; missing scores are
+        nulls in both.
+        """
+        if backend not in ("pandas", "polars"):
+            raise ValueError(f"Unknown backend '{backend}', expected pandas or polars")
+        records = self.get_score_records()
+        # An ordered set of the score names
+        names = {}
+        for _, scores in records:
+            names.update(dict.fromkeys(name for name in scores if name != "tag"))
+        columns = {"tag": [tag for tag, _ in records]}
+        for name in names:
+            columns[name] = [scores.get(name) for _, scores in records]
+
+        if backend == "polars":
+            import polars as pl
+
+            return pl.DataFrame(columns, strict=False)
+        import pandas as pd
+
+        return pd.DataFrame(
+            {
+                name: pd.Series(values, dtype="string" if name == "tag" else _pandas_dtype(values))
+                for name, values in columns.items()
+            }
+        )
+
+    def _score_column(self, column):
+        """Typed `column` values by tag, leaving out tags without the column
+        or with a nan value."""
+        values = {}
+        with self._open() as f:
+            for line in f:
+                parts = line.split()
+                if not line.startswith("QV_SCORE") or len(parts) < 3:
+                    continue
+                fields = [s.split("=", 1) for s in parts[2].split("|")]
+                scores = resolve_duplicates(
+                    [kv for kv in fields if len(kv) == 2], self.duplicate_policy, parts[1]
+                )
+                if column in scores:
+                    value = parse_score_value(scores[column])
+                    if not (isinstance(value, float) and value != value):
+                        values[parts[1]] = value
+        return values
+
+    def sort_tags(self, score_key, descending=True, missing="last"):
+        """All tags ordered by their `score_key` score, reading the file once.
+
+        Numbers sort before booleans and strings, and ties keep file order.
+        Tags without the score (or with nan) go "first", "last" or are left
+        out ("drop").
+        """
+        if self.mode != "r":
+            raise RuntimeError(
+                "Quiver file must be opened in read mode to allow for reading."
+            )
+        if missing not in ("first", "last", "drop"):
+            raise ValueError(
+                f"Unknown missing-value placement '{missing}', expected first, last or drop"
+            )
+        values = self._score_column(score_key)
+        present = [tag for tag in self.tags if tag in values]
+        # reverse=True keeps ties in file order
+        present.sort(key=lambda tag: _score_sort_key(values[tag]), reverse=descending)
+        absent = [tag for tag in self.tags if tag not in values]
+        if missing == "first":
+            return absent + present
+        if missing == "last":
+            return present + absent
+        return present
+
+    def update_scores(self, tag, scores):
+        """Add or overwrite score fields of `tag` from a dict of name -> value.
+
+        Fields not in `scores` keep their values, and an entry without a
+        QV_SCORE line gets one. The file is rewritten atomically, as by
+        rename_tags().
+        """
+        if self.mode != "w":
+            raise RuntimeError(
+                "Quiver file must be opened in write mode to allow for writing."
+            )
+        if self.closed:
+            raise RuntimeError("Quiver file is closed.")
+        if tag not in self._tag_set:
+            raise KeyError(f"Requested tag: {tag} does not exist")
+        scores = {normalize_field(str(k), self.field_charset): v for k, v in scores.items()}
+        for field in (str(f) for item in scores.items() for f in item):
+            if not field or any(c.isspace() or c in "|=" for c in field):
+                raise ValueError(
+                    f"Invalid score field '{field}': score names and values must be "
+                    "non-empty and cannot contain whitespace, '|' or '='"
+                )
+
+        # Appends made so far must be in the file before it is rewritten
+        if self._handle is not None:
+            self._handle.close()
+            self._handle = None
+
+        def write_scores(dst, fields):
+            fields.update((str(k), str(v)) for k, v in scores.items())
+            body = "|".join(f"{k}={v}" for k, v in fields.items())
+            dst.write(f"QV_SCORE {tag} {body}\n")
+
+        def write(dst):
+            with self._open() as src:
+                # Score fields of `tag` collected from its QV_SCORE lines
+                pending = None
+                for line in src:
+                    if pending is not None:
+                        if line.startswith("QV_SCORE"):
+                            parts = line.split()
+                            if len(parts) > 2:
+                                pending.update(
+                                    kv.split("=", 1) for kv in parts[2].split("|") if "=" in kv
+                                )
+                            continue
+                        write_scores(dst, pending)
+                        pending = None
+                    if line.startswith("QV_TAG") and line.split()[1] == tag:
+                        pending = {}
+                    dst.write(line)
+                if pending is not None:
+                    write_scores(dst, pending)
+
+        _replace_atomically(self.fn, write)
+
+    def add_meta(self, tag, key, value):
+        """Set the metadata field `key` of `tag` to `value`, kept on the
+        entry's QV_META line. Other fields keep their values, and an entry
+        without a QV_META line gets one. The file is rewritten atomically, as
+        by rename_tags().
+        """
+        if self.mode != "w":
+            raise RuntimeError(
+                "Quiver file must be opened in write mode to allow for writing."
+            )
+        if self.closed:
+            raise RuntimeError("Quiver file is closed.")
+        if tag not in self._tag_set:
+            raise KeyError(f"Requested tag: {tag} does not exist")
+        key, value = str(key), str(value)
+        _check_meta_field(key)
+        _check_meta_field(value)
+        if key == LABELS_FIELD:
+            raise ValueError(f"'{LABELS_FIELD}' holds the labels of an entry, use qvlabel")
+
+        # Appends made so far must be in the file before it is rewritten
+        if self._handle is not None:
+            self._handle.close()
+            self._handle = None
+
+        def write(dst):
+            with self._open() as src:
+                # Metadata of `tag` collected from its QV_META lines
+                pending = None
+                for line in src:
+                    if pending is not None:
+                        if line.startswith("QV_META"):
+                            pending.update(_parse_meta_fields(line))
+                            continue
+                        if not line.startswith("QV_SCORE"):
+                            pending[key] = value
+                            dst.write(_format_meta_line(tag, pending))
+                            pending = None
+                    if line.startswith("QV_TAG") and line.split()[1:2] == [tag]:
+                        pending = {}
+                    dst.write(line)
+                if pending is not None:
+                    pending[key] = value
+                    dst.write(_format_meta_line(tag, pending))
+
+        _replace_atomically(self.fn, write)
+
+    def get_meta(self, tag):
+        """The metadata fields of `tag` as a dict of str, from its QV_META
+        lines; empty if it has none. Raises KeyError if `tag` is not in the
+        file.
+        """
+        if self.mode != "r":
+            raise RuntimeError(
+                "Quiver file must be opened in read mode to allow for reading."
+            )
+        if tag not in self._tag_set:
+            raise KeyError(f"Requested tag: {tag} does not exist")
+        meta = {}
+        with self._open() as f:
+            current = None
+            for line in f:
+                if line.startswith("QV_TAG"):
+                    current = line.split()[1:2]
+                elif current == [tag] and line.startswith("QV_META"):
+                    meta.update(_parse_meta_fields(line))
+        return meta
+
+    def rename_tags(self, mapping):
+        """Rename the entries in `mapping` (old tag -> new tag) in the file
+        itself and return how many were renamed; every other entry is left
+        as it is. Raises like rename_tags_map(), before changing anything.
+
+        The file is rewritten through a temporary file beside it, synced and
+        renamed over it, so readers never see it half renamed and no
+        temporary file is left behind.
+        """
+        if self.mode != "w":
+            raise RuntimeError(
+                "Quiver file must be opened in write mode to allow for writing."
+            )
+        if self.closed:
+            raise RuntimeError("Quiver file is closed.")
+        _check_rename_map(self.tags, mapping)
+        if not mapping:
+            return 0
+
+        # Appends made so far must be in the file before it is rewritten
+        if self._handle is not None:
+            self._handle.close()
+            self._handle = None
+
+        renamed = 0
+
+        def write(dst):
+            nonlocal renamed
+            with open(self.fn, "rb") as src:
+                renamed = _write_renamed(src, dst, mapping)
+
+        _replace_atomically(self.fn, write, "wb")
+        self.tags = [mapping.get(tag, tag) for tag in self.tags]
+        self._tag_set = set(self.tags)
+        return renamed
+
+    def set_score(self, tag, key, value):
+        """Set one score field of `tag`, see update_scores()."""
+        self.update_scores(tag, {key: value})
+
+    def get_struct_list(self, tag_list):
+        if self.mode != "r":
+            raise RuntimeError(
+                "Quiver file must be opened in read mode to allow for reading."
+            )
+
+        tag_set = set(tag_list)
+        found_tags = []
+        struct_lines = []
+        write_mode = False
+        started = False
+
+        with self._open() as f:
+            for line in f:
+                if line.startswith("QV_TAG"):
+                    started = True
+                    current_tag = line.split()[1]
+                    write_mode = current_tag in tag_set
+                    if write_mode:
+                        found_tags.append(current_tag)
+                elif not started and _is_archive_header(line):
+                    struct_lines.append(line)
+                if write_mode:
+                    struct_lines.append(line)
+        for tag in found_tags:
+            self._record_access("get_struct_list", tag)
+        return "".join(struct_lines), found_tags
+
+    def tags_matching(self, pattern, regex=False):
+        """The tags matching `pattern`, in file order and each once.
+
+        `pattern` is a glob where "*" matches any run of characters and "?"
+        any one, and the whole tag has to match; with `regex` it is a regular
+        expression that may match anywhere in the tag unless anchored, as in
+        the Rust tools. Raises re.error for an invalid expression.
+        """
+        matcher = re.compile(pattern if regex else _wildcard_regex(pattern))
+        match = matcher.search if regex else matcher.fullmatch
+        return list(dict.fromkeys(tag for tag in self.tags if match(tag)))
+
+    def get_struct_list_matching(self, pattern, regex=False):
+        """Like get_struct_list(), for every tag matching `pattern` (see
+        tags_matching()) instead of a list of tags.
+        """
+        return self.get_struct_list(self.tags_matching(pattern, regex))
+
+    def slice(self, tag_list, strict=False):
+        """The entries `tag_list` as a SliceResult: the Quiver text of the
+        entries, in file order, the tags written, the requested tags not in
+        the file and the Warnings about missing tags, tags requested twice
+        and tags found on more than one entry. With `strict`, any missing tag
+        raises KeyError and nothing is read.
+        """
+        warnings = Warnings()
+        missing_tags = []
+        seen = set()
+        for tag in tag_list:
+            if tag in seen:
+                warnings.push("duplicate-request", f"Tag {tag} was requested more than once")
+                continue
+            seen.add(tag)
+            if tag not in self._tag_set:
+                warnings.push("missing-tag", f"Tag not found in Quiver file: {tag}")
+                missing_tags.append(tag)
+        if strict and missing_tags:
+            raise KeyError(f"Tags not found in Quiver file: {', '.join(missing_tags)}")
+        data, found_tags = self.get_struct_list(tag_list)
+        counted = set()
+        for tag in found_tags:
+            if tag in counted:
+                warnings.push("duplicate-tag", f"Tag {tag} appears more than once in the file")
+            counted.add(tag)
+        return SliceResult(data, found_tags, missing_tags, warnings)
+
+    def split(self, ntags, outdir, prefix):
+        """Write the entries into "<outdir>/<prefix>_<n>.qv" files of at most
+        `ntags` entries each, every one starting with the QV_HEADER lines.
+        Returns the number of files written; an archive without entries
+        writes none.
+        """
+        if self.mode != "r":
+            raise RuntimeError(
+                "Quiver file must be opened in read mode to allow for reading."
+            )
+        if ntags < 1:
+            raise ValueError(f"ntags must be at least 1, not {ntags}")
+
+        os.makedirs(outdir, exist_ok=True)
+        header = []
+        file_idx = 0
+        tag_count = 0
+        out_file = None
+
+        def open_new_file():
+            nonlocal file_idx, out_file
+            if out_file:
+                out_file.close()
+            out_path = os.path.join(outdir, f"{prefix}_{file_idx}.qv")
+            out_file = open(out_path, "w", encoding="utf-8")
+            out_file.write("".join(header))
+            file_idx += 1
+
+        with self._open() as f:
+            for line in f:
+                if line.startswith("QV_TAG"):
+                    self._record_access("split", line.split()[1])
+                    if tag_count % ntags == 0:
+                        open_new_file()
+                    tag_count += 1
+                elif out_file is None:
+                    if _is_archive_header(line):
+                        header.append(line)
+                    continue
+                out_file.write(line)
+            if out_file:
+                out_file.close()
+        return file_idx
+
+    def split_by_size(self, max_bytes, outdir, prefix):
+        """Write the entries into "<outdir>/<prefix>_<n>.qv" files, starting
+        a new one whenever the next entry would take the current one past
+        `max_bytes` (UTF-8 bytes, QV_HEADER lines included). An entry is never
+        split across files, so one larger than `max_bytes` gets a file of its
+        own. Returns the number of files written.
+        """
+        if self.mode != "r":
+            raise RuntimeError(
+                "Quiver file must be opened in read mode to allow for reading."
+            )
+        if max_bytes < 1:
+            raise ValueError(f"max_bytes must be at least 1, not {max_bytes}")
+
+        os.makedirs(outdir, exist_ok=True)
+        header = []
+        file_idx = 0
+        out_file = None
+        shard_bytes = 0
+
+        def write_entry(entry):
+            nonlocal file_idx, out_file, shard_bytes
+            data = "".join(entry).encode("utf-8")
+            if out_file is None or shard_bytes + len(data) > max_bytes:
+                if out_file:
+                    out_file.close()
+                out_path = os.path.join(outdir, f"{prefix}_{file_idx}.qv")
+                out_file = open(out_path, "wb")
+                file_idx += 1
+                head = "".join(header).encode("utf-8")
+                out_file.write(head)
+                shard_bytes = len(head)
+            out_file.write(data)
+            shard_bytes += len(data)
+
+        with self._open() as f:
+            entry = None
+            for line in f:
+                if line.startswith("QV_TAG"):
+                    if entry is not None:
+                        write_entry(entry)
+                    self._record_access("split", line.split()[1])
+                    entry = [line]
+                elif entry is not None:
+                    entry.append(line)
+                elif _is_archive_header(line):
+                    header.append(line)
+            if entry is not None:
+                write_entry(entry)
+            if out_file:
+                out_file.close()
+        return file_idx
+
+    def split_round_robin(self, nshards, outdir, prefix):
+        """Deal the entries out to "<outdir>/<prefix>_<n>.qv" files in turn:
+        entry 0 to file 0, entry 1 to file 1, ..., entry `nshards` back to
+        file 0, so early and late entries are spread evenly over the files,
+        as for balancing array jobs. QV_HEADER lines are copied into every
+        file. Returns the number of files written.
+        """
+        if self.mode != "r":
+            raise RuntimeError(
+                "Quiver file must be opened in read mode to allow for reading."
+            )
+        if nshards < 1:
+            raise ValueError(f"nshards must be at least 1, not {nshards}")
+
+        os.makedirs(outdir, exist_ok=True)
+        header = []
+        out_files = []
+        dealt = 0
+        out_file = None
+        try:
+            with self._open() as f:
+                for line in f:
+                    if line.startswith("QV_TAG"):
+                        self._record_access("split", line.split()[1])
+                        if len(out_files) < nshards:
+                            out_path = os.path.join(outdir, f"{prefix}_{len(out_files)}.qv")
+                            out_files.append(open(out_path, "w", encoding="utf-8"))
+                            out_files[-1].write("".join(header))
+                        out_file = out_files[dealt % nshards]
+                        dealt += 1
+                    elif out_file is None:
+                        if _is_archive_header(line):
+                            header.append(line)
+                        continue
+                    out_file.write(line)
+        finally:
+            for f in out_files:
+                f.close()
+        return len(out_files)
+
+    def split_by_score(self, field, edges, outdir, prefix):
+        """Write the entries into "<outdir>/<prefix>_<bucket>.qv" files by
+        the value of score `field`. Edges [70, 90] give the buckets "lt70",
+        "70-90" (70 included, 90 not) and "ge90"; entries without a numeric
+        value go to "missing". Only buckets that receive an entry get a file.
+        Returns a dict of bucket name to number of entries.
+        """
+        if self.mode != "r":
+            raise RuntimeError(
+                "Quiver file must be opened in read mode to allow for reading."
+            )
+        edges = [float(e) for e in edges]
+        increasing = all(a < b for a, b in zip(edges, edges[1:]))
+        if not edges or not all(math.isfinite(e) for e in edges) or not increasing:
+            raise ValueError(f"Bucket edges must be finite and increasing, not {edges}")
+
+        os.makedirs(outdir, exist_ok=True)
+        header = []
+        out_files = {}
+        counts = {}
+
+        def write_entry(entry):
+            value = None
+            for line in entry:
+                parts = line.split()
+                if not line.startswith("QV_SCORE") or len(parts) < 3:
+                    continue
+                fields = [kv.split("=", 1) for kv in parts[2].split("|")]
+                scores = resolve_duplicates(
+                    [kv for kv in fields if len(kv) == 2], self.duplicate_policy, parts[1]
+                )
+                if field in scores:
+                    value = parse_score_value(scores[field])
+            bucket = _score_bucket(value, edges)
+            if bucket not in out_files:
+                out_path = os.path.join(outdir, f"{prefix}_{bucket}.qv")
+                out_files[bucket] = open(out_path, "w", encoding="utf-8")
+                out_files[bucket].write("".join(header))
+                counts[bucket] = 0
+            out_files[bucket].write("".join(entry))
+            counts[bucket] += 1
+
+        try:
+            with self._open() as f:
+                entry = None
+                for line in f:
+                    if line.startswith("QV_TAG"):
+                        if entry is not None:
+                            write_entry(entry)
+                        self._record_access("split", line.split()[1])
+                        entry = [line]
+                    elif entry is not None:
+                        entry.append(line)
+                    elif _is_archive_header(line):
+                        header.append(line)
+                if entry is not None:
+                    write_entry(entry)
+        finally:
+            for out_file in out_files.values():
+                out_file.close()
+        return counts
+
+
+def _is_archive_header(line):
+    # A QV_HEADER line that splits and slices copy, see GENERATION_FIELDS
+    if not line.startswith("QV_HEADER "):
+        return False
+    key = line[len("QV_HEADER "):].split("=", 1)[0]
+    return key not in GENERATION_FIELDS
+
+
+def _score_bucket(value, edges):
+    # Bucket names match the Rust tools, which print 70.0 as "70"
+    def edge(e):
+        return str(int(e)) if e.is_integer() else repr(e)
+
+    if isinstance(value, bool) or not isinstance(value, (int, float)) or math.isnan(value):
+        return "missing"
+    i = bisect.bisect_right(edges, value)
+    if i == 0:
+        return f"lt{edge(edges[0])}"
+    if i == len(edges):
+        return f"ge{edge(edges[-1])}"
+    return f"{edge(edges[i - 1])}-{edge(edges[i])}"
+
+
+def _wildcard_regex(pattern):
+    # A regex for a glob of only "*" and "?", unlike fnmatch, which also
+    # reads [...] as a character class
+    return "".join(
+        ".*" if c == "*" else "." if c == "?" else re.escape(c) for c in pattern
+    )
+
+def _score_sort_key(value):
+    # Numbers, then booleans, then strings, as in the Rust tools
+    if isinstance(value, bool):
+        return (1, value)
+    if isinstance(value, (int, float)):
+        return (0, value)
+    return (2, value)
+
+
+def _best_first(values, tags, descending=True):
+    # `tags` with a value in `values` in ranking order, popped from a heap so
+    # a consumer that stops early never sorts them all, then the rest in
+    # file order; ties keep file order, as in Quiver.sort_tags()
+    sign = -1 if descending else 1
+
+    def compare(a, b):
+        a, b = _score_sort_key(a), _score_sort_key(b)
+        return sign * ((a > b) - (a < b))
+
+    rank = functools.cmp_to_key(compare)
+    heap = [(rank(values[tag]), i, tag) for i, tag in enumerate(tags) if tag in values]
+    heapq.heapify(heap)
+    while heap:
+        yield heapq.heappop(heap)[2]
+    yield from (tag for tag in tags if tag not in values)
+
+
+def _pandas_dtype(values):
+    # The nullable dtype for a column, unified like the Rust tools: ints
+    # widen to floats, any other mix is a string column
+    kinds = {
+        "bool" if isinstance(v, bool) else "int" if isinstance(v, int)
+        else "float" if isinstance(v, float) else "str"
+        for v in values if v is not None
+    }
+    if kinds <= {"int"}:
+        return "Int64"
+    if kinds <= {"int", "float"}:
+        return "Float64"
+    if kinds == {"bool"}:
+        return "boolean"
+    return "string"
+
+
+def _group_of(pattern, tag):
+    # First capture group, or the whole match; unmatched tags keep their own
+    match = pattern.search(tag)
+    if match is None:
+        return tag
+    if pattern.groups and match.group(1) is not None:
+        return match.group(1)
+    return match.group(0)
+
+
+def best_per_group(quiver_file, group_regex, score_field, k=1):
+    """The `k` best tags of each group, in ranking order.
+
+    Tags are grouped by the first capture group of `group_regex` (or its whole
+    match; tags it does not match are a group of their own), e.g.
+    r"^(.*)_seed\\d+$" for the best seed of every design. `score_field` is
+    "name[:asc|desc]", ascending by default. Entries without the score (or
+    with nan) rank after every scored entry of their group, and ties keep
+    file order.
+    """
+    field, _, order = score_field.rpartition(":")
+    if not field:
+        field, order = order, "asc"
+    if order not in ("asc", "desc"):
+        raise ValueError(f"Unknown sort order '{order}', expected asc or desc")
+    pattern = re.compile(group_regex)
+
+    ranked = Quiver(quiver_file, "r").sort_tags(field, descending=order == "desc")
+
+    kept = {}
+    best = []
+    for tag in ranked:
+        group = _group_of(pattern, tag)
+        if kept.get(group, 0) < k:
+            kept[group] = kept.get(group, 0) + 1
+            best.append(tag)
+    return best
+
+
+def group_stats(quiver_file, group_regex, fields):
+    """Mean, min and max of score `fields` per group of tags, read in one
+    pass, e.g. per design across seeds.
+
+    Tags are grouped as for best_per_group(). Returns one dict per group, in
+    order of the group's first tag, with "group", "entries" (tags in the
+    group) and "<field>_mean", "<field>_min" and "<field>_max" for every
+    field. Missing, nan and non-numeric values are left out; a group without
+    any value of a field gets None.
+    """
+    if isinstance(fields, str):
+        fields = [fields]
+    pattern = re.compile(group_regex)
+    qv = Quiver(quiver_file, "r")
+    scores = dict(qv.get_score_records())
+
+    groups = {}
+    for tag in qv.get_tags():
+        group = _group_of(pattern, tag)
+        stats = groups.setdefault(group, {"entries": 0, "values": {f: [] for f in fields}})
+        stats["entries"] += 1
+        for field in fields:
+            value = scores.get(tag, {}).get(field)
+            if isinstance(value, bool) or not isinstance(value, (int, float)):
+                continue
+            if value == value:  # not nan
+                stats["values"][field].append(value)
+
+    table = []
+    for group, stats in groups.items():
+        row = {"group": group, "entries": stats["entries"]}
+        for field, values in stats["values"].items():
+            row[f"{field}_mean"] = sum(values) / len(values) if values else None
+            row[f"{field}_min"] = min(values) if values else None
+            row[f"{field}_max"] = max(values) if values else None
+        table.append(row)
+    return table
+
+
+EXTRACT_LAYOUTS = ("flat", "per-tag")
+
+
+def _entry_meta(qv, tag, pdb_lines):
+    # The meta.json document of an entry extracted with the per-tag layout
+    try:
+        scores = qv.get_scores(tag)
+    except ValueError:
+        # No score line to record
+        scores = {}
+    scores = {
+        name: None if isinstance(value, float) and not math.isfinite(value) else value
+        for name, value in scores.items()
+    }
+    meta = {
+        "tag": tag,
+        "model": "model.pdb",
+        "format": "pdb",
+        "scores": scores,
+        "provenance": {"source": os.path.abspath(qv.fn), "tool": "quiver.py"},
+        "checksum": payload_digest(pdb_lines),
+    }
+    return json.dumps(meta, indent=2) + "\n"
+
+
+def extract_pdbs(
+    quiver_file,
+    output_dir=".",
+    tags=None,
+    threads=1,
+    layout="flat",
+    record_filter=None,
+    warnings=None,
+    best_first=None,
+    ascending=False,
+    max_output_bytes=None,
+):
+    """Write every entry of `quiver_file` (or only `tags`) to
+    "<output_dir>/<tag>.pdb", as the `qvextract` tool does. Existing files
+    are skipped. Returns the paths written, in tag order, and raises
+    KeyError, before writing anything, if a tag is not in the file.
+
+    With layout="per-tag" each entry gets a folder instead,
+    "<output_dir>/<tag>/model.pdb", with a "meta.json" beside it recording
+    the entry's scores, the archive it came from and the QV_HASH checksum
+    of the model file (see payload_digest()).
+
+    `record_filter` keeps only the listed PDB record types, see
+    Quiver.get_pdblines().
+
+    Files skipped because they exist are reported as "skipped-file" on
+    `warnings`, a Warnings, if one is given.
+
+    With one thread the file is read in a single pass. With more, entries
+    are located through the tag index (see build_index(), which writes
+    "<quiver_file>.idx"), so each read takes only that entry's bytes; the
+    reads and writes release the GIL, so the threads overlap.
+
+    With `best_first`, a score name, entries are extracted in descending
+    order of that score (ascending with `ascending`), taken from a priority
+    queue and read through the tag index, so a job stopped by a time limit or
+    disk quota has the best designs. Entries without the score come last,
+    and the paths are returned in extraction order.
+
+    With `max_output_bytes`, extraction stops before the first PDB file that
+    would take the bytes written past it, and an "output-quota" warning is
+    pushed on `warnings`. It needs one thread, so that the files written are
+    a prefix of the extraction order.
+    """
+    if threads < 1:
+        raise ValueError(f"threads must be at least 1, not {threads}")
+    if max_output_bytes is not None and threads > 1:
+        raise ValueError("max_output_bytes needs threads=1")
+    if layout not in EXTRACT_LAYOUTS:
+        raise ValueError(
+            f"Unknown layout {layout!r}, expected one of: {', '.join(EXTRACT_LAYOUTS)}"
+        )
+    qv = Quiver(quiver_file, "r", index=threads > 1 or best_first is not None)
+    tags = qv.get_tags() if tags is None else list(tags)
+    missing = [tag for tag in tags if tag not in qv]
+    if missing:
+        raise KeyError(f"Requested tag: {missing[0]} does not exist")
+    os.makedirs(output_dir, exist_ok=True)
+
+    def output_name(tag):
+        # Sanitize tag to prevent path traversal
+        name = os.path.basename(tag)
+        if layout == "per-tag":
+            outfn = os.path.join(output_dir, name, "model.pdb")
+        else:
+            outfn = os.path.join(output_dir, f"{name}.pdb")
+        outfn = os.path.normpath(outfn)
+        if os.path.exists(outfn):
+            if warnings is not None:
+                warnings.push("skipped-file", f"File {outfn} already exists, skipping")
+            return None
+        return outfn
+
+    def write(outfn, tag, lines):
+        if layout == "per-tag":
+            os.makedirs(os.path.dirname(outfn), exist_ok=True)
+            with open(os.path.join(os.path.dirname(outfn), "meta.json"), "w", encoding="utf-8") as f:
+                f.write(_entry_meta(qv, tag, lines))
+        with open(outfn, "w", encoding="utf-8") as f:
+            f.writelines(lines)
+        return outfn
+
+    bytes_written = 0
+
+    def fits(outfn, lines):
+        # Whether the PDB file still fits in the quota, counting it if so
+        nonlocal bytes_written
+        size = sum(len(line.encode("utf-8")) for line in lines)
+        if max_output_bytes is not None and bytes_written + size > max_output_bytes:
+            if warnings is not None:
+                warnings.push(
+                    "output-quota",
+                    f"Output quota of {max_output_bytes} bytes reached, stopping before {outfn}",
+                )
+            return False
+        bytes_written += size
+        return True
+
+    if threads == 1 and best_first is None:
+        keep = _record_keeper(record_filter)
+        wanted = set(tags)
+        written = {}
+        for tag, _, lines in qv:
+            outfn = output_name(tag) if tag in wanted else None
+            if outfn is not None:
+                lines = [line for line in lines if keep(line)]
+                if not fits(outfn, lines):
+                    break
+                written[tag] = write(outfn, tag, lines)
+        return [written[tag] for tag in tags if tag in written]
+
+    def extract(tag):
+        outfn = output_name(tag)
+        if outfn is None:
+            return None
+        return write(outfn, tag, qv.get_pdblines(tag, record_filter))
+
+    if best_first is not None:
+        tags = _best_first(qv._score_column(best_first), tags, descending=not ascending)
+    if threads == 1:
+        written = []
+        for tag in tags:
+            outfn = output_name(tag)
+            if outfn is None:
+                continue
+            lines = qv.get_pdblines(tag, record_filter)
+            if not fits(outfn, lines):
+                break
+            written.append(write(outfn, tag, lines))
+        return written
+    with ThreadPoolExecutor(max_workers=threads, thread_name_prefix="qvextract") as pool:
+        return [outfn for outfn in pool.map(extract, tags) if outfn is not None]
+
+
+def _payload_integrity(stored, payload):
+    # "ok", "mismatch", "unhashed" or "not-structure" for one entry's payload
+    if stored is not None and payload_digest(payload) != stored:
+        return "mismatch"
+    try:
+        sniff_structure(payload)
+    except ValueError:
+        return "not-structure"
+    return "unhashed" if stored is None else "ok"
+
+
+def _block_integrity(block):
+    # _payload_integrity() of the raw bytes of one entry
+    lines = io.StringIO(block.decode("utf-8", "replace"), newline=None).readlines()[1:]
+    stored = None
+    for line in lines:
+        if line.startswith("QV_HASH"):
+            parts = line.split()
+            stored = parts[2] if len(parts) > 2 else None
+    payload = [line for line in lines if not line.startswith(RESERVED_PREFIXES)]
+    return _payload_integrity(stored, payload)
+
+
+def verify_archive(quiver_file, threads=1, fail_fast=False):
+    """Check every entry of `quiver_file` against its QV_HASH checksum, as
+    the `qvverify` tool does, and return a dict of tag -> "ok", "mismatch",
+    "unhashed" or "not-structure" in file order, like Quiver.verify().
+
+    Entries are located through the tag index (see build_index()) and read
+    by `threads` threads, each taking only that entry's bytes. With
+    `fail_fast` no entry is read once one has mismatched, so only the
+    entries checked by then are returned.
+    """
+    if threads < 1:
+        raise ValueError(f"threads must be at least 1, not {threads}")
+    entries = build_index(quiver_file)
+    failed = threading.Event()
+
+    def check(entry):
+        tag, offset, length = entry
+        if fail_fast and failed.is_set():
+            return None
+        with open(quiver_file, "rb") as f:
+            f.seek(offset)
+            status = _block_integrity(f.read(length))
+        if status == "mismatch":
+            failed.set()
+        return tag, status
+
+    with ThreadPoolExecutor(max_workers=threads, thread_name_prefix="qvverify") as pool:
+        return dict(result for result in pool.map(check, entries) if result is not None)
+
+
+def _read_structure(path):
+    # Text of a PDB file, decompressing gzip input
+    with open(path, "rb") as f:
+        data = f.read()
+    if data.startswith(b"\x1f\x8b"):
+        data = gzip.decompress(data)
+    return data.decode("utf-8")
+
+
+def ingest_directory(
+    directory,
+    output,
+    pattern="*.pdb",
+    threads=1,
+    on_duplicate="error",
+    warnings=None,
+    allow_non_structure=False,
+    reproducible=False,
+):
+    """Write every file under `directory` whose name matches `pattern`
+    (fnmatch wildcards) into a new archive at `output`, as the `qvfromdir`
+    tool does. Returns the number of entries written.
+
+    Files are tagged by their stem ("model.pdb.gz" -> "model") and written
+    in path order. `threads` workers read (and gunzip) files ahead of the
+    writer, holding only a few files per thread at a time. Two files with the
+    same stem raise ValueError, or with `on_duplicate` "skip" the later one
+    is skipped and with "rename" it is tagged "<stem>_2", "<stem>_3", ....
+    Those are reported as "skipped-file" and "renamed-duplicate" on
+    `warnings`, a Warnings, if one is given. Raises FileExistsError if
+    `output` exists.
+
+    Unless `allow_non_structure` is set, a file without coordinates (see
+    validate.sniff_structure()) raises ValueError, so a pattern that also
+    matches logs or notes cannot fill the archive with them.
+
+    With `reproducible`, entries are written in tag order with "\n" line
+    endings, so the same set of files always gives a byte-identical archive
+    (see merge_archives()).
+    """
+    if on_duplicate not in ("error", "skip", "rename"):
+        raise ValueError(
+            f"Unknown duplicate policy '{on_duplicate}', expected error, skip or rename"
+        )
+    if threads < 1:
+        raise ValueError(f"threads must be at least 1, not {threads}")
+    if os.path.exists(output):
+        raise FileExistsError(f"'{output}' already exists")
+
+    paths = sorted(
+        os.path.join(root, name)
+        for root, _, names in os.walk(directory)
+        for name in names
+        if fnmatch.fnmatchcase(name, pattern)
+    )
+    jobs = []
+    seen = set()
+    for path in paths:
+        name = os.path.basename(path)
+        stem = next(
+            (name[: -len(s)] for s in (".pdb.gz", ".pdb") if name.endswith(s)), name
+        )
+        tag = "_".join(stem.split())
+        if tag in seen:
+            if on_duplicate == "error":
+                raise ValueError(f"Duplicate tag '{tag}' derived from {path}")
+            if on_duplicate == "skip":
+                if warnings is not None:
+                    warnings.push("skipped-file", f"Duplicate tag for {path}, skipping")
+                continue
+            n = 2
+            while f"{tag}_{n}" in seen:
+                n += 1
+            if warnings is not None:
+                warnings.push(
+                    "renamed-duplicate", f"Duplicate tag {tag} for {path}, tagged {tag}_{n}"
+                )
+            tag = f"{tag}_{n}"
+        seen.add(tag)
+        jobs.append((path, tag))
+    if reproducible:
+        jobs.sort(key=lambda job: job[1])
+
+    validators = [] if allow_non_structure else [structure_content()]
+    with ThreadPoolExecutor(max_workers=threads, thread_name_prefix="qvfromdir") as pool, \
+            Quiver(output, "w", validators=validators) as qv:
+        # Files being read, in the order they are written
+        pending = deque()
+
+        def write_next():
+            tag, text = pending.popleft()
+            text = text.result()
+            if reproducible:
+                text = _normalize_newlines(text)
+            qv.add_pdb(text.splitlines(keepends=True), tag)
+
+        for path, tag in jobs:
+            pending.append((tag, pool.submit(_read_structure, path)))
+            if len(pending) >= 2 * threads:
+                write_next()
+        while pending:
+            write_next()
+    return len(jobs)
+
+
+# Header fields that differ between two builds of the same archive
+VOLATILE_HEADER_FIELDS = ("created",)
+
+
+def stable_header(header):
+    """`header`, a list of (key, value) pairs, with its volatile fields set
+    from SOURCE_DATE_EPOCH, or dropped when that is not set, as in the Rust
+    tools.
+    """
+    epoch = os.environ.get("SOURCE_DATE_EPOCH", "").strip()
+    epoch = epoch if epoch.isdigit() else None
+    return [
+        (key, epoch if key in VOLATILE_HEADER_FIELDS else value)
+        for key, value in header
+        if key not in VOLATILE_HEADER_FIELDS or epoch is not None
+    ]
+
+
+def _normalize_newlines(text):
+    # "\r\n" turned into "\n", and a final "\n" added if missing
+    text = text.replace("\r\n", "\n")
+    return text if not text or text.endswith("\n") else text + "\n"
+
+
+def _entry_spans(quiver_file):
+    # The QV_HEADER lines of `quiver_file` and its entries as
+    # [(tag, offset, length)], like build_index() but without an index file
+    header, spans, pos = [], [], 0
+    with open(quiver_file, "rb") as f:
+        for line in f:
+            if line.startswith(b"QV_TAG"):
+                if spans:
+                    spans[-1][2] = pos - spans[-1][1]
+                parts = line.split()
+                spans.append([parts[1].decode("utf-8") if len(parts) > 1 else "", pos, 0])
+            elif not spans and line.startswith(b"QV_HEADER"):
+                header.append(line.decode("utf-8"))
+            pos += len(line)
+    if spans:
+        spans[-1][2] = pos - spans[-1][1]
+    return header, [tuple(span) for span in spans]
+
+
+def merge_archives(quiver_files, output, reproducible=False):
+    """Concatenate `quiver_files` into a new archive at `output`, keeping
+    only the first file's QV_HEADER lines, as `qvtool merge` does. Returns
+    the number of entries written.
+
+    With `reproducible`, entries are written in tag order whatever the order
+    of `quiver_files`, with "\n" line endings, and the header's build time
+    is taken from SOURCE_DATE_EPOCH or left out (see stable_header()), so the
+    same inputs always give a byte-identical archive, as content-addressed
+    stores and build caches need.
+
+    Raises FileExistsError if `output` exists and ValueError, writing
+    nothing, if a tag appears twice.
+    """
+    if os.path.exists(output):
+        raise FileExistsError(f"'{output}' already exists")
+    scanned = [_entry_spans(path) for path in quiver_files]
+    entries = [
+        (tag, path, offset, length)
+        for path, (_, spans) in zip(quiver_files, scanned)
+        for tag, offset, length in spans
+    ]
+    seen = set()
+    for tag, path, _, _ in entries:
+        if tag in seen:
+            raise ValueError(f"Tag {tag} appears more than once (again in {path})")
+        seen.add(tag)
+    header = scanned[0][0] if scanned else []
+    if reproducible:
+        entries.sort(key=lambda entry: entry[0])
+        fields = [line[len("QV_HEADER "):].rstrip("\n").split("=", 1) for line in header]
+        header = [
+            f"QV_HEADER {key}={value}\n"
+            for key, value in stable_header([kv for kv in fields if len(kv) == 2])
+        ]
+
+    def write(out):
+        out.write("".join(header).encode("utf-8"))
+        for _, path, offset, length in entries:
+            with open(path, "rb") as f:
+                f.seek(offset)
+                block = f.read(length)
+            if reproducible:
+                block = _normalize_newlines(block.decode("utf-8")).encode("utf-8")
+            out.write(block)
+
+    _replace_atomically(output, write, "wb")
+    return len(entries)
+
+
+def _replace_atomically(path, write, mode="w"):
+    # Write `path` through a sibling temporary file renamed over it
+    tmp_fn = os.path.join(
+        os.path.dirname(os.path.abspath(path)),
+        f".{os.path.basename(path)}.tmp{os.getpid()}",
+    )
+    try:
+        with open(tmp_fn, mode, **({} if "b" in mode else {"encoding": "utf-8"})) as f:
+            write(f)
+            f.flush()
+            os.fsync(f.fileno())
+        os.replace(tmp_fn, path)
+    finally:
+        if os.path.exists(tmp_fn):
+            os.remove(tmp_fn)
+
+
+def move_journal_path(source):
+    """The journal of moves out of `source`: "<source>.move"."""
+    return f"{source}.move"
+
+
+def _save_move_journal(source, phase, destination, destination_len, tags):
+    length = "new" if destination_len is None else destination_len
+
+    def write(f):
+        f.write(f"QV_MOVE\t{phase}\t{length}\t{destination}\n")
+        f.writelines(f"{tag}\n" for tag in tags)
+
+    _replace_atomically(move_journal_path(source), write)
+
+
+def _remove_entries(quiver_file, tags):
+    # Rewrite `quiver_file` atomically without the entries `tags`
+    drop = set(tags)
+
+    def write(dst):
+        skipping = False
+        with open(quiver_file, "rb") as src:
+            for line in src:
+                if line.startswith(b"QV_TAG"):
+                    parts = line.split()
+                    skipping = len(parts) > 1 and parts[1].decode("utf-8", "replace") in drop
+                if not skipping:
+                    dst.write(line)
+
+    _replace_atomically(quiver_file, write, "wb")
+
+
+def _roll_back_move(destination, destination_len):
+    # Undo the appends of a move that had not finished copying
+    if destination_len is None:
+        if os.path.exists(destination):
+            os.remove(destination)
+    else:
+        os.truncate(destination, destination_len)
+
+
+def recover_move(source):
+    """Settle a move out of `source` that was cut short, as the Rust tools
+    do: rolled back if its entries were still being copied ("copying"),
+    completed otherwise ("copied"). Returns that phase, or None if there
+    was no move to settle."""
+    path = move_journal_path(source)
+    try:
+        with open(path, encoding="utf-8") as f:
+            first = f.readline().rstrip("\n").split("\t", 3)
+            tags = [line.rstrip("\n") for line in f]
+    except FileNotFoundError:
+        return None
+    if len(first) != 4 or first[0] != "QV_MOVE" or first[1] not in ("copying", "copied"):
+        raise ValueError(f"Malformed move journal {path}")
+    _, phase, length, destination = first
+    if phase == "copying":
+        _roll_back_move(destination, None if length == "new" else int(length))
+    else:
+        _remove_entries(source, tags)
+    os.remove(path)
+    return phase
+
+
+def move_entries(source, destination, tags):
+    """Move the entries `tags` of `source` to the end of `destination`, which
+    is created (with the source's QV_HEADER lines) if it does not exist, as
+    the `qvmove` tool does. Returns the number of entries moved.
+
+    The move runs in two phases journaled in "<source>.move": the entries
+    are appended to `destination` and synced, then removed from `source` by
+    an atomic rewrite. A move cut short by a crash is settled by
+    recover_move(), which every move runs first, so each entry ends up in
+    exactly one of the archives. Raises KeyError if a tag is not in
+    `source` and ValueError if one is already in `destination`; nothing is
+    changed then.
+    """
+    recover_move(source)
+    if os.path.exists(destination) and os.path.samefile(source, destination):
+        raise ValueError(f"Cannot move entries of {source} into itself")
+    wanted = set(tags)
+    # QV_HEADER lines before the first entry, and the raw lines of each entry moved
+    header, blocks = [], {}
+    current, started = None, False
+    with open(source, "rb") as f:
+        for line in f:
+            if line.startswith(b"QV_TAG"):
+                started = True
+                parts = line.split()
+                tag = parts[1].decode("utf-8", "replace") if len(parts) > 1 else None
+                current = tag if tag in wanted and tag not in blocks else None
+                if current is not None:
+                    blocks[current] = []
+            elif not started and line.startswith(b"QV_HEADER"):
+                header.append(line)
+            if current is not None:
+                blocks[current].append(line)
+    missing = [tag for tag in tags if tag not in blocks]
+    if missing:
+        raise KeyError(f"Tags not in {source}: {', '.join(missing)}")
+    if not blocks:
+        return 0
+
+    destination_len = os.path.getsize(destination) if os.path.exists(destination) else None
+    if destination_len is not None:
+        present = set(Quiver(destination, "r").get_tags())
+        clash = next((tag for tag in blocks if tag in present), None)
+        if clash is not None:
+            raise ValueError(f"Tag {clash} is already in {destination}")
+
+    moving = list(blocks)
+    _save_move_journal(source, "copying", destination, destination_len, moving)
+    try:
+        with open(destination, "ab") as out:
+            if destination_len is None:
+                out.writelines(header)
+            for tag in moving:
+                out.writelines(blocks[tag])
+                if not blocks[tag][-1].endswith(b"\n"):
+                    out.write(b"\n")
+            out.flush()
+            os.fsync(out.fileno())
+    except BaseException:
+        _roll_back_move(destination, destination_len)
+        os.remove(move_journal_path(source))
+        raise
+    _save_move_journal(source, "copied", destination, destination_len, moving)
+    _remove_entries(source, moving)
+    os.remove(move_journal_path(source))
+    return len(moving)
+
+
+def _check_rename_map(tags, mapping):
+    # Raise unless every old tag is in `tags` and no new tag is given twice
+    # or is the tag of an entry that is not renamed
+    present = set(tags)
+    for old, new in mapping.items():
+        if old not in present:
+            raise KeyError(f"Requested tag: {old} does not exist")
+        if not new or any(c.isspace() for c in new):
+            raise ValueError(
+                f"Invalid new tag '{new}' for {old}: tags must be non-empty and contain no whitespace"
+            )
+    kept = present - set(mapping)
+    new_tags = set()
+    for new in mapping.values():
+        if new in kept or new in new_tags:
+            raise ValueError(f"Renaming would leave two entries tagged {new}")
+        new_tags.add(new)
+
+
+def _check_meta_field(field):
+    if not field or any(c.isspace() or c in "|=" for c in field):
+        raise ValueError(
+            f"Invalid metadata field '{field}': keys and values must be "
+            "non-empty and cannot contain whitespace, '|' or '='"
+        )
+
+
+def _format_meta_line(tag, meta):
+    body = "|".join(f"{k}={v}" for k, v in meta.items())
+    return f"QV_META {tag} {body}\n"
+
+
+def _parse_meta_fields(line):
+    # The key=value fields of a QV_META line, in order
+    parts = line.split()
+    if len(parts) < 3:
+        return []
+    return [kv.split("=", 1) for kv in parts[2].split("|") if "=" in kv]
+
+
+def _retag_line(line, new_tag):
+    # `line` (bytes) with its tag field replaced, the rest kept as is
+    parts = line.rstrip(b"\n").split(b" ", 2)
+    if len(parts) > 1:
+        parts[1] = new_tag.encode("utf-8")
+    return b" ".join(parts) + b"\n"
+
+
+def _write_renamed(src, dst, mapping):
+    # Copy binary file `src` to `dst`, renaming the entries in `mapping`;
+    # returns how many were renamed
+    renamed = 0
+    current = None
+    for line in src:
+        if line.startswith(b"QV_TAG"):
+            parts = line.split()
+            current = mapping.get(parts[1].decode("utf-8", "replace")) if len(parts) > 1 else None
+            renamed += current is not None
+        if current is not None and line.startswith((b"QV_TAG", b"QV_SCORE", b"QV_META", b"QV_HASH")):
+            line = _retag_line(line, current)
+        dst.write(line)
+    return renamed
+
+
+def rename_tags_map(quiver_file, mapping, output=None, in_place=False):
+    """Write `quiver_file` to `output` with only the tags in `mapping`
+    (old tag -> new tag) renamed, as `qvrename --map` does, and return the
+    number of entries renamed. Score, metadata and hash lines follow their
+    entry; every other entry is copied as is. With `in_place` (and no
+    `output`) the file itself is renamed, see Quiver.rename_tags().
+
+    Raises KeyError if an old tag is not in the archive, ValueError if a new
+    tag is given twice or is the tag of an entry that is not renamed
+    (swapping two tags is fine), and FileExistsError if `output` exists.
+    """
+    if in_place == (output is not None):
+        raise ValueError("Pass either an output file or in_place=True")
+    if in_place:
+        with Quiver(quiver_file, "w") as qv:
+            return qv.rename_tags(mapping)
+    if os.path.exists(output):
+        raise FileExistsError(f"'{output}' already exists")
+    _check_rename_map(Quiver(quiver_file, "r").get_tags(), mapping)
+    with open(quiver_file, "rb") as src, open(output, "wb") as dst:
+        return _write_renamed(src, dst, mapping)
+
+
+def dedup_tags(quiver_file, keep="first", output=None):
+    """Keep one entry per tag of `quiver_file`, as the `qvdedup` tool does:
+    the first one in file order, or with `keep` "last" the last one. The
+    result is written to `output`, which must not exist, or without it the
+    file is rewritten atomically in place.
+
+    Returns the dropped entries as (tag, position) pairs in file order,
+    `position` counting entries of the original file from 0. An archive
+    without repeated tags is left alone (or copied as is to `output`). An
+    entry cut short at the end of the file (see Quiver.partial_entry) is
+    copied as it is and never dropped.
+    """
+    if keep not in ("first", "last"):
+        raise ValueError(f"Unknown keep policy '{keep}', expected first or last")
+    if output is not None and os.path.exists(output):
+        raise FileExistsError(f"'{output}' already exists")
+    # Every QV_TAG line is counted below, the partial entry's included
+    tags = Quiver(quiver_file, "r", include_partial=True).get_tags()
+    partial = _partial_tail(quiver_file)
+    if partial is not None and partial.tag is not None:
+        tags.pop()
+    order = tags if keep == "first" else tags[::-1]
+    kept = {}
+    for i, tag in enumerate(order):
+        kept.setdefault(tag, i if keep == "first" else len(tags) - 1 - i)
+    dropped = [(tag, i) for i, tag in enumerate(tags) if kept[tag] != i]
+
+    def write(dst):
+        position = -1
+        skipping = False
+        with open(quiver_file, "rb") as src:
+            for line in src:
+                if line.startswith(b"QV_TAG"):
+                    position += 1
+                    skipping = position < len(tags) and kept[tags[position]] != position
+                if not skipping:
+                    dst.write(line)
+
+    if output is not None:
+        with open(output, "wb") as dst:
+            write(dst)
+    elif dropped:
+        _replace_atomically(quiver_file, write, "wb")
+    return dropped
+
+
+# Kinds of problems reported by validate_archive()
+ISSUE_KINDS = (
+    "duplicate-tag",
+    "empty-structure",
+    "score-tag-mismatch",
+    "malformed-score",
+    "junk",
+)
+
+ValidationIssue = namedtuple("ValidationIssue", ["line", "kind", "tag", "message"])
+ValidationReport = namedtuple("ValidationReport", ["entries", "issues"])
+
+
+def validate_archive(quiver_file):
+    """Read `quiver_file` once and report its problems with their line
+    numbers, as the `qvvalidate` tool does, so an archive can be fixed
+    before a long production run trips over it.
+
+    Returns a ValidationReport of the number of entries and a list of
+    ValidationIssue(line, kind, tag, message) in file order, `kind` being
+    one of ISSUE_KINDS: a tag already used by an earlier entry, an entry
+    without ATOM or HETATM records (reported at its QV_TAG line), a QV_SCORE
+    line naming another tag than its entry, a score line without fields or
+    with a field that is not key=value, and a payload line that is not a PDB
+    record. `tag` is the entry the line is in, None before the first one.
+    """
+    issues = []
+    entries = 0
+    first_seen = {}
+    # [tag, QV_TAG line number, is PDB, payload lines] of the entry being read
+    current = None
+
+    def check_structure(entry):
+        tag, line, _, payload = entry
+        try:
+            sniff_structure(payload)
+        except ValueError as e:
+            issues.append(ValidationIssue(line, "empty-structure", tag, str(e)))
+
+    with open(quiver_file, "rb") as f:
+        for number, raw in enumerate(f, 1):
+            text = raw.decode("utf-8", "replace").rstrip("\r\n")
+            tag = current[0] if current is not None else None
+
+            def issue(kind, message, tag=tag):
+                issues.append(ValidationIssue(number, kind, tag, message))
+
+            if text.startswith("QV_TAG"):
+                if current is not None:
+                    check_structure(current)
+                parts = text.split()
+                new_tag = parts[1] if len(parts) > 1 else None
+                if new_tag is None:
+                    issue("junk", "QV_TAG line without a tag", None)
+                elif new_tag in first_seen:
+                    entries += 1
+                    message = f"tag {new_tag} is already used by the entry at line {first_seen[new_tag]}"
+                    issue("duplicate-tag", message, new_tag)
+                else:
+                    entries += 1
+                    first_seen[new_tag] = number
+                current = [new_tag, number, "FORMAT=cif" not in parts[2:], []]
+            elif text.startswith("QV_SCORE"):
+                parts = text.split()
+                if len(parts) < 2:
+                    issue("malformed-score", "QV_SCORE line without a tag")
+                elif tag is None:
+                    issue("score-tag-mismatch", f"QV_SCORE line for {parts[1]} before the first entry")
+                elif parts[1] != tag:
+                    issue("score-tag-mismatch", f"QV_SCORE line for {parts[1]} inside the entry of {tag}")
+                if len(parts) == 2:
+                    issue("malformed-score", "QV_SCORE line without fields")
+                elif len(parts) > 2:
+                    bad = [kv for kv in parts[2].split("|") if "=" not in kv or kv.startswith("=")]
+                    if bad:
+                        issue("malformed-score", f"score field '{bad[0]}' is not key=value")
+            elif text.startswith("QV_"):
+                pass  # header, metadata and checksum lines
+            elif current is not None:
+                record = text[:6].rstrip()
+                if current[2] and record and record not in PDB_RECORDS:
+                    issue("junk", f"not a PDB record: {text[:60]}")
+                current[3].append(text)
+            elif text.strip():
+                issue("junk", f"line before the first entry: {text[:60]}")
+    if current is not None:
+        check_structure(current)
+    # Empty structures are found when their entry ends
+    issues.sort(key=lambda issue: issue.line)
+    return ValidationReport(entries, issues)
+
+
+RepairReport = namedtuple("RepairReport", ["entries", "partial_entry", "orphan_scores"])
+
+
+def repair_archive(quiver_file, output):
+    """Copy `quiver_file` to a new archive `output` without the damage a
+    killed job leaves, as the `qvrepair` tool does: the incomplete record at
+    the end (see Quiver.partial_entry) and QV_SCORE lines not preceded by the
+    QV_TAG line of their tag. Everything else is copied byte for byte, and
+    `output` is written atomically.
+
+    Returns a RepairReport of the entries written, the PartialEntry dropped
+    (or None) and the dropped score lines as (tag, line) pairs, `line`
+    counting lines of `quiver_file` from 1.
+    """
+    if os.path.exists(output):
+        raise FileExistsError(f"Output file {output} already exists")
+    partial = _partial_tail(quiver_file)
+    end = partial.offset if partial is not None else os.path.getsize(quiver_file)
+    entries = 0
+    orphans = []
+
+    def write(dst):
+        nonlocal entries
+        current = None
+        with open(quiver_file, "rb") as src:
+            for number, line in enumerate(io.BufferedReader(_BoundedReader(src, end)), 1):
+                if line.startswith(b"QV_TAG"):
+                    parts = line.split()
+                    current = parts[1] if len(parts) > 1 else None
+                    entries += current is not None
+                elif line.startswith(b"QV_SCORE"):
+                    parts = line.split()
+                    tag = parts[1] if len(parts) > 1 else b""
+                    if tag != current:
+                        orphans.append((tag.decode("utf-8", "replace"), number))
+                        continue
+                dst.write(line)
+
+    _replace_atomically(output, write, "wb")
+    return RepairReport(entries, partial, orphans)
+
+
+ModernizeReport = namedtuple("ModernizeReport", ["entries", "rewritten"])
+
+
+def modernize_archive(quiver_file, output, legacy_records=True):
+    """Copy `quiver_file` to a new archive `output` with the record keywords
+    of older writers replaced by the canonical ones, as the `qvmodernize`
+    tool does. `legacy_records` is True for the documented variants in
+    LEGACY_RECORDS or a dict of variant -> canonical keyword. Every other
+    byte is copied as is, and `output` is written atomically.
+
+    Returns a ModernizeReport of the entries written and the number of lines
+    whose keyword was rewritten.
+    """
+    if os.path.exists(output):
+        raise FileExistsError(f"Output file {output} already exists")
+    table = _legacy_table(legacy_records)
+    entries = 0
+    rewritten = 0
+
+    def write(dst):
+        nonlocal entries, rewritten
+        with open(quiver_file, "rb") as src:
+            for line in src:
+                fixed = _modernize_line(line, table)
+                rewritten += fixed is not line
+                entries += fixed.startswith(b"QV_TAG")
+                dst.write(fixed)
+
+    _replace_atomically(output, write, "wb")
+    return ModernizeReport(entries, rewritten)
+
+
+def repack(quiver_file, order_by, warnings=None):
+    """Rewrite `quiver_file` atomically with its entries in the order they
+    will be read, as the `qvrepack` tool does, and return the number of
+    entries that moved. Entries are copied byte for byte.
+
+    `order_by` is "score:<field>[:asc|desc]" (ascending by default, entries
+    without the score last) or "taglist:<path>": the whitespace-separated
+    tags of that file first, in its order, then every other entry in file
+    order. Listed tags that are not in the archive or are listed twice are
+    reported as "missing-tag" and "duplicate-request" on `warnings`, a
+    Warnings, if one is given. An entry cut short at the end of the file
+    (see Quiver.partial_entry) stays where it is, after all the others.
+    """
+    kind, _, arg = order_by.partition(":")
+    if kind not in ("score", "taglist") or not arg:
+        raise ValueError(
+            f"Unknown order '{order_by}', expected score:<field>[:asc|desc] or taglist:<path>"
+        )
+    # Offset of each entry, in file order
+    entries = []
+    with open(quiver_file, "rb") as f:
+        pos = 0
+        for line in f:
+            if line.startswith(b"QV_TAG"):
+                entries.append((line.split()[1].decode("utf-8", "replace"), pos))
+            pos += len(line)
+    size = pos
+    partial = _partial_tail(quiver_file) if entries else None
+    # Entries that are reordered; the partial one is copied last as it is
+    complete = len(entries) - (partial is not None and partial.tag is not None)
+    tags = [tag for tag, _ in entries[:complete]]
+    by_tag = {}
+    for i, tag in enumerate(tags):
+        by_tag.setdefault(tag, deque()).append(i)
+
+    if kind == "score":
+        field, _, order = arg.rpartition(":")
+        if not field:
+            field, order = order, "asc"
+        if order not in ("asc", "desc"):
+            raise ValueError(f"Unknown sort order '{order}', expected asc or desc")
+        ranked = Quiver(quiver_file, "r").sort_tags(field, descending=order == "desc")
+        # Entries sharing a tag keep their relative order
+        positions = [by_tag[tag].popleft() for tag in ranked]
+    else:
+        with open(arg, encoding="utf-8") as f:
+            listed = f.read().split()
+        seen = set()
+        positions = []
+        for tag in listed:
+            if tag in seen:
+                if warnings is not None:
+                    warnings.push("duplicate-request", f"Tag {tag} is listed more than once")
+            elif tag not in by_tag:
+                if warnings is not None:
+                    warnings.push("missing-tag", f"Tag not found in Quiver file: {tag}")
+            else:
+                positions.extend(by_tag[tag])
+            seen.add(tag)
+        positions.extend(i for i, tag in enumerate(tags) if tag not in seen)
+
+    moved = sum(i != p for i, p in enumerate(positions))
+    if moved:
+        ends = [offset for _, offset in entries[1:]] + [size]
+
+        def write(dst):
+            with open(quiver_file, "rb") as src:
+                dst.write(src.read(entries[0][1]))
+                for i in positions:
+                    src.seek(entries[i][1])
+                    dst.write(src.read(ends[i] - entries[i][1]))
+                if complete < len(entries):
+                    src.seek(entries[complete][1])
+                    dst.write(src.read())
+
+        _replace_atomically(quiver_file, write, "wb")
+    return moved
+
+
+# Bytes read from the end of an archive by the first step of the backward
+# scan in tail_entries(); every further step doubles it
+TAIL_CHUNK = 1 << 20
+
+
+def _parse_entries(lines):
+    # (tag, score_str, pdb_lines) for the entries in `lines`, as Quiver.__iter__
+    tag, score_str, pdb_lines = None, None, []
+    for line in lines:
+        if line.startswith("QV_TAG"):
+            if tag is not None:
+                yield tag, score_str, pdb_lines
+            tag, score_str, pdb_lines = line.split()[1], None, []
+        elif tag is None:
+            continue
+        elif line.startswith("QV_SCORE"):
+            parts = line.split()
+            score_str = parts[2] if len(parts) > 2 else None
+        elif not line.startswith(("QV_META", "QV_HASH")):
+            pdb_lines.append(line)
+    if tag is not None:
+        yield tag, score_str, pdb_lines
+
+
+def _tag_offsets(f, pos, end):
+    # Offsets of the QV_TAG lines of binary file `f`, positioned at offset
+    # `pos` (the start of a line), up to offset `end`
+    offsets = []
+    for line in f:
+        if pos >= end:
+            break
+        if line.startswith(b"QV_TAG"):
+            offsets.append(pos)
+        pos += len(line)
+    return offsets
+
+
+def _indexed_tail_start(quiver_file, size, n):
+    # Where the last `n` entries start, from a tag index built from an
+    # earlier (or the current) state of the archive, or None without one
+    try:
+        with open(index_path(quiver_file), encoding="utf-8") as f:
+            stamp = f.readline().rstrip("\n").split("\t")
+            entries = [line.rstrip("\n").rsplit("\t", 2) for line in f]
+    except FileNotFoundError:
+        return None
+    if len(stamp) < 2 or stamp[0] != "QV_INDEX" or int(stamp[1]) > size or not entries:
+        return None
+    offsets = [int(offset) for _, offset, _ in entries]
+    with open(quiver_file, "rb") as f:
+        f.seek(offsets[-1])
+        first = f.readline()
+        # An archive rewritten since, not just appended to, no longer has the
+        # last indexed entry where the index says
+        parts = first.split()
+        if not first.startswith(b"QV_TAG") or len(parts) < 2:
+            return None
+        if parts[1].decode("utf-8", "replace") != entries[-1][0]:
+            return None
+        offsets.extend(_tag_offsets(f, offsets[-1] + len(first), size))
+    return offsets[max(len(offsets) - n, 0)]
+
+
+def _scan_back(f, size, n):
+    # Where the last `n` entries of the first `size` bytes of binary file
+    # `f` start, reading backwards from the end; `size` if there are none
+    chunk = TAIL_CHUNK
+    while True:
+        start = max(size - chunk, 0)
+        f.seek(start)
+        data = f.read(size - start)
+        # A chunk starting mid-line cannot tell whether that line is a tag
+        # line; the next, larger chunk will
+        skip = 0 if start == 0 else (data.find(b"\n") + 1 or len(data))
+        offsets = _tag_offsets(io.BytesIO(data[skip:]), start + skip, size)
+        if len(offsets) >= n or start == 0:
+            return offsets[max(len(offsets) - n, 0)] if offsets else size
+        chunk *= 2
+
+
+def _partial_tail(quiver_file):
+    # PartialEntry for the last entry of `quiver_file` if its writer left it
+    # incomplete: its last line is not terminated (a bare "END" is), or its
+    # structure does not match its QV_HASH. None if it is complete.
+    size = os.path.getsize(quiver_file)
+    with open(quiver_file, "rb") as f:
+        start = _scan_back(f, size, 1)
+        f.seek(start)
+        block = f.read(size - start)
+    if not block:
+        return None
+    torn = not block.endswith(b"\n") and block.rsplit(b"\n", 1)[-1].strip() != b"END"
+    if not torn and _block_integrity(block) != "mismatch":
+        return None
+    parts = block.split(b"\n", 1)[0].split()
+    tag = parts[1].decode("utf-8", "replace") if len(parts) > 1 else None
+    return PartialEntry(tag, start, size - start)
+
+
+def head_entries(quiver_file, n):
+    """The first `n` entries of `quiver_file` as (tag, score_str, pdb_lines),
+    reading no further than the entry after them, as `qvtool head` does."""
+    entries = []
+    if n <= 0 or not os.path.exists(quiver_file):
+        return entries
+    with open(quiver_file, encoding="utf-8", errors=legacy_error_handler()) as f:
+        for entry in _parse_entries(f):
+            entries.append(entry)
+            if len(entries) == n:
+                break
+    return entries
+
+
+def tail_entries(quiver_file, n):
+    """The last `n` entries of `quiver_file` as (tag, score_str, pdb_lines),
+    in file order, as `qvtool tail` does.
+
+    Meant for spot-checking what a job still appending to the archive has
+    written. A "<quiver_file>.idx" tag index built from an earlier, shorter
+    state of the archive is used to start near the end, and only what was
+    appended since is scanned; without one the archive is scanned backwards
+    from the end. The size is taken once, so an entry still being written
+    comes back as far as it was written then.
+    """
+    if n <= 0 or not os.path.exists(quiver_file):
+        return []
+    size = os.path.getsize(quiver_file)
+    start = _indexed_tail_start(quiver_file, size, n)
+    with open(quiver_file, "rb") as f:
+        if start is None:
+            start = _scan_back(f, size, n)
+        f.seek(start)
+        data = f.read(size - start)
+    text = data.decode("utf-8", legacy_error_handler())
+    return list(_parse_entries(text.splitlines(keepends=True)))
+
+
+class _SplitMix64:
+    # The deterministic generator of the Rust tools (transform::SplitMix64)
+
+    def __init__(self, seed):
+        self.state = seed & 0xFFFFFFFFFFFFFFFF
+
+    def next_u64(self):
+        self.state = (self.state + 0x9E3779B97F4A7C15) & 0xFFFFFFFFFFFFFFFF
+        z = self.state
+        z = ((z ^ (z >> 30)) * 0xBF58476D1CE4E5B9) & 0xFFFFFFFFFFFFFFFF
+        z = ((z ^ (z >> 27)) * 0x94D049BB133111EB) & 0xFFFFFFFFFFFFFFFF
+        return z ^ (z >> 31)
+
+    def next_f64(self):
+        return ((self.next_u64() >> 11) + 0.5) / (1 << 53)
+
+    def next_gaussian(self):
+        u1, u2 = self.next_f64(), self.next_f64()
+        return math.sqrt(-2.0 * math.log(u1)) * math.cos(2.0 * math.pi * u2)
+
+
+_SYNTHETIC_RESIDUES = (
+    "ALA", "ARG", "ASN", "ASP", "CYS", "GLN", "GLU", "GLY", "HIS", "ILE",
+    "LEU", "LYS", "MET", "PHE", "PRO", "SER", "THR", "TRP", "TYR", "VAL",
+)
+
+
+def _synthetic_structure(atoms, rng):
+    pos = [0.0, 0.0, 0.0]
+    lines = []
+    for i in range(atoms):
+        resname = _SYNTHETIC_RESIDUES[rng.next_u64() % len(_SYNTHETIC_RESIDUES)]
+        if i > 0:
+            step = [rng.next_gaussian(), rng.next_gaussian(), rng.next_gaussian()]
+            norm = math.sqrt(sum(c * c for c in step))
+            if norm > 0.0:
+                pos = [p + 3.8 * c / norm for p, c in zip(pos, step)]
+        lines.append(
+            f"ATOM  {i % 99999 + 1:>5}  CA  {resname} A{i % 9999 + 1:>4}    "
+            f"{pos[0]:8.3f}{pos[1]:8.3f}{pos[2]:8.3f}  1.00  0.00           C\n"
+        )
+    lines += ["TER\n", "END\n"]
+    return lines
+
+
+def generate_test_archive(path, n_entries, atoms_per_entry=100, with_scores=True, seed=0):
+    """Write a new archive at `path` of `n_entries` synthetic structures, as
+    the `qvgenerate` tool does, for fixtures of any size without data files.
+
+    Entry i is tagged "synthetic_<i:06>" and holds one chain A of
+    `atoms_per_entry` CA atoms on a random walk with 3.8 A steps; with
+    `with_scores` it gets "score" and "plddt" values. The same arguments
+    always give the same file. Raises FileExistsError if `path` exists.
+    """
+    if os.path.exists(path):
+        raise FileExistsError(f"'{path}' already exists")
+    rng = _SplitMix64(seed)
+    with Quiver(path, "w") as qv:
+        for i in range(n_entries):
+            lines = _synthetic_structure(atoms_per_entry, rng)
+            score = None
+            if with_scores:
+                score = f"score={-100.0 * rng.next_f64():.3f}|plddt={50.0 + 50.0 * rng.next_f64():.3f}"
+            qv.add_pdb(lines, f"synthetic_{i:06d}", score)
+
+
+def _seqres_sequences(pdb_lines):
+    """{chain: one-letter sequence} from the SEQRES records, in file order."""
+    chains = {}
+    for line in pdb_lines:
+        if line.startswith("SEQRES"):
+            chain = line[11:12].strip()
+            residues = "".join(THREE_TO_ONE.get(name, "X") for name in line[19:].split())
+            chains[chain] = chains.get(chain, "") + residues
+    return chains
+
+
+def extract_sequences(quiver_file, output_fasta, chains=None, source="atom"):
+    """Write the one-letter sequence of every chain of every structure to
+    `output_fasta`, as the `qvfasta` tool does.
+
+    Records are headed ">tag_chain" (">tag" for a chain without an
+    identifier), one sequence per line. `chains` limits the output to those
+    chain ids. With source="seqres", structures with SEQRES records use
+    those instead of their ATOM records. Returns the number of records.
+    """
+    if source not in ("atom", "seqres"):
+        raise ValueError(f"Unknown sequence source '{source}', expected atom or seqres")
+    wanted = set(chains) if chains is not None else None
+    written = 0
+    with open(output_fasta, "w", encoding="utf-8") as out:
+        for tag, _, pdb_lines in Quiver(quiver_file, "r"):
+            sequences = _seqres_sequences(pdb_lines) if source == "seqres" else {}
+            if not sequences:
+                sequences = {
+                    c.id: c.sequence() for c in Structure.from_lines(pdb_lines).chains
+                }
+            for chain, seq in sequences.items():
+                if not seq or (wanted is not None and chain not in wanted):
+                    continue
+                out.write(f">{tag}_{chain}\n{seq}\n" if chain else f">{tag}\n{seq}\n")
+                written += 1
+    return written
@@ -1,7 +1,76 @@
-use std::collections::HashSet;
-use std::fs::{self, File, OpenOptions};
-use std::io::{self, BufRead, BufReader, BufWriter, Write};
-use std::path::{Path, PathBuf};
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering as AtomicOrdering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub mod access;
+pub mod audit;
+pub mod charset;
+pub mod cif;
+pub mod compress;
+pub mod convert;
+pub mod dedup;
+pub mod diagnose;
+pub mod expr;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod format_v2;
+pub mod hashindex;
+pub mod info;
+pub mod ingest;
+pub mod integrity;
+pub mod layout;
+pub mod legacy;
+pub mod ligand;
+pub mod linkcache;
+pub mod parquet;
+pub mod pdb;
+pub mod peek;
+pub mod rank;
+pub mod remote;
+pub mod rename;
+pub mod repack;
+pub mod repair;
+pub mod reproducible;
+pub mod s3;
+pub mod scores;
+pub mod select;
+pub mod sequence;
+pub mod shard;
+pub mod storage;
+pub mod superpose;
+pub mod tagindex;
+pub mod testdata;
+pub mod transfer;
+pub mod transform;
+pub mod validate;
+pub mod warnings;
+
+use access::{AccessHook, AccessLog};
+use charset::{decode_line, normalize_field, normalize_score_str, FieldCharset, LegacyBytes};
+use compress::Compression;
+use expr::{Expr, ScoreKey};
+use rank::{rank, Missing, Order, RankKey};
+use scores::{json_string, resolve_duplicates, score_map, typed_fields, DuplicatePolicy, ScoreLookup, ScoreValue};
+use select::TagPattern;
+use shard::ScoreBuckets;
+use format_v2::V2File;
+use integrity::{check_block, format_hash_line, payload_digest, raw_payload_digest, Integrity};
+use legacy::{LegacyFile, LegacyRecords};
+use pdb::{Structure, StructureStats};
+use remote::RemoteFile;
+use storage::{CompressedFile, LocalFile, MappedFile, Storage};
+use superpose::kabsch_rmsd;
+use transform::RecordFilter;
+use validate::{sniff_structure, EntryValidator};
+use warnings::{WarningCode, Warnings};
 
 #[derive(Debug)]
 pub enum QuiverError {
@@ -10,148 +79,2221 @@ pub enum QuiverError {
     DuplicateTag(String),
     TagNotFound(String),
     InvalidOperation(String),
+    /// A malformed record, with its location already in the message
+    Malformed(String),
+}
+
+impl QuiverError {
+    /// A stable name for the kind of error, for scripts and logs.
+    pub fn category(&self) -> &'static str {
+        match self {
+            QuiverError::Io(_) => "io",
+            QuiverError::InvalidMode(_) => "usage",
+            QuiverError::TagNotFound(_) => "missing_tag",
+            QuiverError::Malformed(_) => "malformed",
+            QuiverError::DuplicateTag(_) | QuiverError::InvalidOperation(_) => "invalid",
+        }
+    }
+
+    /// The exit status the command-line tools use for this error, one per
+    /// [`QuiverError::category`]:
+    ///
+    /// | code | category |
+    /// |------|----------|
+    /// | 1 | `invalid` (refused operation, duplicate tag) |
+    /// | 2 | `usage` (also clap's code for bad arguments) |
+    /// | 3 | `missing_tag` |
+    /// | 4 | `malformed` |
+    /// | 5 | `io` |
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            QuiverError::InvalidMode(_) => 2,
+            QuiverError::TagNotFound(_) => 3,
+            QuiverError::Malformed(_) => 4,
+            QuiverError::Io(_) => 5,
+            QuiverError::DuplicateTag(_) | QuiverError::InvalidOperation(_) => 1,
+        }
+    }
+
+    /// The error as a JSON object with its category, exit status and
+    /// message, for the tools' `--json` output.
+    pub fn to_json(&self) -> String {
+        let message = match self {
+            QuiverError::Io(e) => e.to_string(),
+            QuiverError::InvalidMode(msg)
+            | QuiverError::DuplicateTag(msg)
+            | QuiverError::TagNotFound(msg)
+            | QuiverError::InvalidOperation(msg)
+            | QuiverError::Malformed(msg) => msg.clone(),
+        };
+        format!(
+            "{{\"category\": {}, \"exit_code\": {}, \"message\": {}}}",
+            json_string(self.category()),
+            self.exit_code(),
+            json_string(&message)
+        )
+    }
+}
+
+/// Exit status of the extraction tools when `--max-output-bytes` stopped
+/// them before every entry was written; the files written are complete.
+pub const EXIT_QUOTA_REACHED: i32 = 6;
+
+impl From<io::Error> for QuiverError {
+    fn from(err: io::Error) -> Self {
+        QuiverError::Io(err)
+    }
+}
+
+/// Where a line sits in an archive, for error messages.
+#[derive(Debug, Clone, Default)]
+struct Location {
+    source: Option<String>,
+    /// 1-based line number
+    line: usize,
+    /// Byte offset of the start of the line
+    offset: u64,
+    /// The last `QV_TAG` seen before the line
+    tag: Option<String>,
+    /// Byte offset of the line after this one
+    next: u64,
+}
+
+impl Location {
+    fn new(source: Option<String>) -> Self {
+        Self {
+            source,
+            ..Self::default()
+        }
+    }
+
+    /// Move on to the next line, which is `len` bytes long.
+    fn advance(&mut self, len: usize) {
+        self.line += 1;
+        self.offset = self.next;
+        self.next += len as u64;
+    }
+
+    /// Track `line` and check the record lines Quiver itself relies on.
+    ///
+    /// `QV_TAG`, `QV_SCORE`, `QV_META` and `QV_HASH` lines must name a tag.
+    fn check(&mut self, line: &[u8]) -> Result<(), QuiverError> {
+        self.advance(line.len());
+        for record in ["QV_TAG", "QV_SCORE", "QV_META", "QV_HASH"] {
+            if !line.starts_with(record.as_bytes()) {
+                continue;
+            }
+            let text = String::from_utf8_lossy(line);
+            let Some(tag) = text.split_whitespace().nth(1) else {
+                return Err(self.malformed(&format!("{} without a tag", record)));
+            };
+            if record == "QV_TAG" {
+                self.tag = Some(tag.to_string());
+            }
+        }
+        Ok(())
+    }
+
+    fn malformed(&self, what: &str) -> QuiverError {
+        let mut msg = format!("{} at line {} (byte {})", what, self.line, self.offset);
+        match &self.tag {
+            Some(tag) => msg.push_str(&format!(" after tag {}", tag)),
+            None => msg.push_str(" before the first tag"),
+        }
+        if let Some(source) = &self.source {
+            msg.push_str(&format!(" in {}", source));
+        }
+        QuiverError::Malformed(msg)
+    }
+}
+
+/// Key/value fields stored on a `QV_META` line.
+pub type MetaFields = Vec<(String, String)>;
+
+/// Payload format of an entry, recorded as `FORMAT=<name>` on its `QV_TAG`
+/// line. Entries without one are PDB.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PayloadFormat {
+    #[default]
+    Pdb,
+    Cif,
+}
+
+impl PayloadFormat {
+    pub fn name(self) -> &'static str {
+        match self {
+            PayloadFormat::Pdb => "pdb",
+            PayloadFormat::Cif => "cif",
+        }
+    }
+
+    /// File extension for extracted entries.
+    pub fn extension(self) -> &'static str {
+        self.name()
+    }
+
+    /// The format recorded on a `QV_TAG` line.
+    pub fn from_tag_line(line: &str) -> Self {
+        line.split_whitespace()
+            .skip(2)
+            .find_map(|attr| attr.strip_prefix("FORMAT="))
+            .and_then(|name| name.parse().ok())
+            .unwrap_or_default()
+    }
+
+    /// The format implied by a file name, ignoring a compression suffix.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Self {
+        let name = path.as_ref().to_string_lossy().to_lowercase();
+        let name = name
+            .strip_suffix(".gz")
+            .or_else(|| name.strip_suffix(".zst"))
+            .unwrap_or(&name);
+        if name.ends_with(".cif") {
+            PayloadFormat::Cif
+        } else {
+            PayloadFormat::Pdb
+        }
+    }
+}
+
+impl std::str::FromStr for PayloadFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pdb" => Ok(PayloadFormat::Pdb),
+            "cif" | "mmcif" => Ok(PayloadFormat::Cif),
+            other => Err(format!("Unknown payload format '{}', expected pdb or cif", other)),
+        }
+    }
+}
+
+/// Format a `QV_TAG` line (without trailing newline).
+///
+/// PDB entries keep the plain `QV_TAG <tag>` form older readers expect.
+pub fn format_tag_line(tag: &str, format: PayloadFormat) -> String {
+    match format {
+        PayloadFormat::Pdb => format!("QV_TAG {}", tag),
+        other => format!("QV_TAG {} FORMAT={}", tag, other.name()),
+    }
+}
+
+/// Line prefixes Quiver itself reads; a payload line starting with one would
+/// be taken for a record of the archive.
+pub const RESERVED_PREFIXES: [&str; 5] = ["QV_TAG", "QV_SCORE", "QV_META", "QV_HASH", "QV_HEADER"];
+
+/// Bytes read from the start of an entry to find its `QV_TAG` line.
+const TAG_LINE_PROBE: u64 = 4096;
+
+/// One structure block: its tag, optional score string, metadata and payload.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Entry {
+    pub tag: String,
+    /// Raw `key=value|key=value` score string
+    pub score: Option<String>,
+    pub meta: MetaFields,
+    pub lines: Vec<String>,
+    pub format: PayloadFormat,
+}
+
+impl Entry {
+    /// Serialize the entry exactly as it is stored in a Quiver file.
+    pub fn to_text(&self) -> String {
+        self.text(false)
+    }
+
+    /// Like [`Entry::to_text`], with a `QV_HASH` line of the payload (see
+    /// [`integrity`]) after the score and metadata lines.
+    pub fn to_hashed_text(&self) -> String {
+        self.text(true)
+    }
+
+    fn text(&self, hashed: bool) -> String {
+        let mut text = format_tag_line(&self.tag, self.format);
+        text.push('\n');
+        if let Some(score) = &self.score {
+            text.push_str(&format!("QV_SCORE {} {}\n", self.tag, score));
+        }
+        if !self.meta.is_empty() {
+            text.push_str(&format_meta_line(&self.tag, &self.meta));
+            text.push('\n');
+        }
+        if hashed {
+            text.push_str(&format_hash_line(&self.tag, &payload_digest(&self.lines)));
+            text.push('\n');
+        }
+        for line in &self.lines {
+            text.push_str(line);
+            if !line.ends_with('\n') {
+                text.push('\n');
+            }
+        }
+        text
+    }
+
+    /// Check that the entry reads back as written: the tag is one word, the
+    /// score string has no whitespace, metadata has no line breaks, and no
+    /// payload line starts with a [`RESERVED_PREFIXES`] record.
+    pub fn check_layout(&self) -> Result<(), String> {
+        if self.tag.is_empty() || self.tag.contains(char::is_whitespace) {
+            return Err(format!("tag '{}' is empty or contains whitespace", self.tag));
+        }
+        if self.score.as_deref().is_some_and(|s| s.contains(char::is_whitespace)) {
+            return Err("score string contains whitespace".to_string());
+        }
+        if self.meta.iter().any(|(k, v)| k.contains('\n') || v.contains('\n')) {
+            return Err("metadata contains a line break".to_string());
+        }
+        for (i, line) in self.lines.iter().enumerate() {
+            for part in line.split('\n') {
+                if let Some(prefix) = RESERVED_PREFIXES.iter().find(|p| part.starts_with(*p)) {
+                    return Err(format!("payload line {} starts with reserved record {}", i + 1, prefix));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Storage accounting for one entry, see [`Quiver::stat`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct EntryStat {
+    pub tag: String,
+    /// Size in the text format, including the `QV_*` lines
+    pub bytes: u64,
+    /// Bytes the archive spends on the entry
+    pub stored: u64,
+    /// False when `stored` is an estimate (gzip and zstd archives)
+    pub exact: bool,
+}
+
+/// What [`Quiver::try_get`] found for a tag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntryStatus {
+    /// The entry was read and, if it has a `QV_HASH` line, matches it
+    Found,
+    /// The tag is not in the archive
+    Missing,
+    /// The payload does not match its `QV_HASH` line
+    Corrupt,
+    /// The archive changed under the handle and the entry is no longer where
+    /// it was when the archive was opened
+    Stale,
+}
+
+impl EntryStatus {
+    pub fn name(self) -> &'static str {
+        match self {
+            EntryStatus::Found => "found",
+            EntryStatus::Missing => "missing",
+            EntryStatus::Corrupt => "corrupt",
+            EntryStatus::Stale => "stale",
+        }
+    }
+}
+
+/// The outcome of [`Quiver::try_get`] for one tag.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EntryResult {
+    pub tag: String,
+    pub status: EntryStatus,
+    /// Payload lines, for `Found` and `Corrupt` entries
+    pub lines: Option<Vec<String>>,
+    /// Why the entry is `Corrupt` or `Stale`
+    pub detail: Option<String>,
+}
+
+impl EntryStat {
+    /// Uncompressed size over stored size; 1.0 for plain archives.
+    pub fn ratio(&self) -> f64 {
+        if self.stored == 0 {
+            1.0
+        } else {
+            self.bytes as f64 / self.stored as f64
+        }
+    }
+}
+
+/// Output of [`Quiver::slice`].
+#[derive(Debug, Clone, Default)]
+pub struct SliceResult {
+    /// The sliced entries, ready to be written as a Quiver file
+    pub data: String,
+    /// Tags written to `data`, in file order
+    pub found_tags: Vec<String>,
+    /// Requested tags that are not in the file
+    pub missing_tags: Vec<String>,
+    /// Missing tags, tags requested twice and tags on more than one entry
+    pub warnings: Warnings,
+}
+
+/// Single-pass iterator over the entries of an archive, see [`Quiver::iter`].
+pub struct Entries<'a> {
+    lines: io::Lines<BufReader<io::Take<Box<dyn Read + 'a>>>>,
+    current: Option<Entry>,
+    done: bool,
+    /// Hook told about each entry yielded, with the archive name
+    access: Option<(&'a dyn AccessHook, String)>,
+}
+
+impl<'a> Entries<'a> {
+    /// The entries read from `reader`, which is positioned at a `QV_TAG`
+    /// line or at the start of an archive, with no access hook.
+    pub(crate) fn over(reader: Box<dyn Read + 'a>) -> Self {
+        Entries {
+            lines: BufReader::new(reader.take(u64::MAX)).lines(),
+            current: None,
+            done: false,
+            access: None,
+        }
+    }
+
+    fn recorded(&self, entry: Entry) -> Result<Entry, QuiverError> {
+        if let Some((hook, archive)) = &self.access {
+            hook.record(archive, "iter", &entry.tag)?;
+        }
+        Ok(entry)
+    }
+}
+
+impl Iterator for Entries<'_> {
+    type Item = Result<Entry, QuiverError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        for line in self.lines.by_ref() {
+            let line = match line {
+                Ok(line) => line,
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e.into()));
+                }
+            };
+            if line.starts_with("QV_TAG") {
+                let next = Entry {
+                    tag: line.split_whitespace().nth(1).unwrap_or("").to_string(),
+                    format: PayloadFormat::from_tag_line(&line),
+                    ..Entry::default()
+                };
+                if let Some(entry) = self.current.replace(next) {
+                    return Some(self.recorded(entry));
+                }
+                continue;
+            }
+            let Some(entry) = self.current.as_mut() else {
+                continue;
+            };
+            if line.starts_with("QV_HEADER") || line.starts_with("QV_HASH") {
+                continue;
+            } else if line.starts_with("QV_SCORE") {
+                entry.score = line.split_whitespace().nth(2).map(|s| s.to_string());
+            } else if let Some((_, fields)) = parse_meta_line(&line) {
+                entry.meta.extend(fields);
+            } else {
+                entry.lines.push(line);
+            }
+        }
+        self.done = true;
+        self.current.take().map(|entry| self.recorded(entry))
+    }
+}
+
+/// Handle on a Quiver archive.
+///
+/// `Quiver` is `Send + Sync`: read-only handles can be shared across threads,
+/// and every read opens its own cursor on the snapshot.
+pub struct Quiver {
+    storage: Box<dyn Storage>,
+    mode: String,
+    tags: Vec<String>,
+    /// Byte offset of each entry's `QV_TAG` line, parallel to `tags`
+    offsets: Vec<u64>,
+    /// Payload format of each entry, parallel to `tags`
+    formats: Vec<PayloadFormat>,
+    /// Labels of each entry, parallel to `tags`
+    labels: Vec<Vec<String>>,
+    /// In read mode, the number of bytes visible to this handle.
+    ///
+    /// Appends made by other writers after this point are invisible.
+    snapshot_len: Option<u64>,
+    header: MetaFields,
+    /// `(generation, entries)` recorded by the last rewrite, see
+    /// [`Quiver::generation`]
+    rewritten: (usize, usize),
+    /// Told about every entry read, see [`access`]
+    access: Option<Arc<dyn AccessHook>>,
+    /// How score reads treat a key repeated on one `QV_SCORE` line
+    duplicate_policy: DuplicatePolicy,
+    /// Characters allowed in the names of scores written, see [`charset`]
+    field_charset: FieldCharset,
+    /// How score reads decode bytes that are not UTF-8
+    legacy_bytes: LegacyBytes,
+    /// Run on every entry added, see [`validate`]
+    validators: Vec<Arc<dyn EntryValidator>>,
+    /// Refuse entries that would corrupt the archive, see [`Entry::check_layout`]
+    strict: bool,
+    /// Write a `QV_HASH` line with every entry added, see [`integrity`]
+    write_hashes: bool,
+    /// Opened through a remote tag index, which records no formats or
+    /// labels, see [`remote`]
+    indexed: bool,
+    /// Raised by reads since the last [`Quiver::take_warnings`]
+    warnings: Mutex<Warnings>,
+    /// The end of the archive cut short by its writer, hidden from reads
+    /// unless asked for, see [`Quiver::set_include_partial`]
+    partial: Option<Partial>,
+}
+
+/// The end of an archive cut short mid-entry, by a writer that is still
+/// running or was killed, see [`Quiver::partial_entry`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PartialEntry {
+    /// Tag of the cut entry as far as it was written, `None` if its
+    /// `QV_TAG` line has no tag yet
+    pub tag: Option<String>,
+    /// Byte offset where the incomplete data starts
+    pub offset: u64,
+    /// Length of the incomplete data
+    pub bytes: u64,
+}
+
+/// A [`PartialEntry`] with what it takes to show it again.
+struct Partial {
+    entry: PartialEntry,
+    format: PayloadFormat,
+    labels: Vec<String>,
+    included: bool,
+}
+
+/// What [`Quiver::rewrite`] does with one entry.
+enum EntryEdit<'a> {
+    Keep,
+    Drop,
+    /// Merge the entry's `QV_META` lines into one, edited by the function
+    Meta(&'a dyn Fn(&mut MetaFields)),
+    /// Likewise for the `QV_SCORE` lines that follow the `QV_TAG` line
+    Score(&'a dyn Fn(&mut MetaFields)),
+    /// Give the entry this tag, on its `QV_TAG`, `QV_SCORE`, `QV_META` and
+    /// `QV_HASH` lines
+    Rename(&'a str),
+}
+
+/// The fields of an entry being edited by [`EntryEdit::Meta`] or
+/// [`EntryEdit::Score`].
+struct PendingFields<'a> {
+    tag: String,
+    edit: &'a dyn Fn(&mut MetaFields),
+    fields: MetaFields,
+    score: bool,
 }
 
-impl From<io::Error> for QuiverError {
-    fn from(err: io::Error) -> Self {
-        QuiverError::Io(err)
+impl PendingFields<'_> {
+    fn write(mut self, out: &mut dyn Write) -> io::Result<()> {
+        (self.edit)(&mut self.fields);
+        if self.fields.is_empty() {
+            return Ok(());
+        }
+        let line = if self.score {
+            format_score_line(&self.tag, &self.fields)
+        } else {
+            format_meta_line(&self.tag, &self.fields)
+        };
+        writeln!(out, "{}", line)
+    }
+}
+
+/// What a scan of the tag lines learns about an archive.
+#[derive(Default)]
+struct Index {
+    tags: Vec<String>,
+    offsets: Vec<u64>,
+    formats: Vec<PayloadFormat>,
+    labels: Vec<Vec<String>>,
+    header: MetaFields,
+    /// Byte length of the scanned region
+    len: u64,
+    /// Where the data cut short by a writer starts, if the last line is
+    /// unterminated
+    torn_from: Option<u64>,
+    /// Whether the last entry has a `QV_HASH` line
+    last_hashed: bool,
+    /// The [`GENERATION_FIELD`] and [`GENERATION_ENTRIES_FIELD`] header
+    /// values, if present
+    rewritten_generation: Option<usize>,
+    rewritten_entries: Option<usize>,
+}
+
+impl Index {
+    fn rewritten(&self) -> (usize, usize) {
+        (self.rewritten_generation.unwrap_or(0), self.rewritten_entries.unwrap_or(0))
+    }
+
+    /// Generation of the entries scanned so far, `None` until all those
+    /// written by the last rewrite are in.
+    fn generation(&self) -> Option<usize> {
+        let (generation, entries) = self.rewritten();
+        self.tags.len().checked_sub(entries).map(|appended| generation + appended)
+    }
+}
+
+impl Quiver {
+    /// Open `filename` in `"r"` (read), `"w"` (write), `"a"` (append) or
+    /// `"rw"` (read/write) mode.
+    ///
+    /// Append mode requires an existing file, loads its tags so duplicates
+    /// are rejected, and keeps one handle open for every later write.
+    /// Read/write mode does the same but creates the file if needed, and
+    /// reads see every structure added through the handle. Write mode keeps
+    /// one buffered handle for all writes; call [`Quiver::flush`] before
+    /// reading the file elsewhere (dropping the handle also flushes).
+    ///
+    /// Gzip and zstd archives (`.qv.gz`, `.qv.zst`, or detected from their
+    /// magic bytes) are decompressed on the fly and appended to as new
+    /// compressed members. Binary v2 archives (`.qv2`, see [`format_v2`]) are
+    /// detected the same way. `s3://bucket/key` writes a new archive straight
+    /// to S3 (write mode only, see [`s3`]); call [`Quiver::finish`] to
+    /// complete it.
+    ///
+    /// In read mode, an `http(s)://` or `s3://` URL is read through ranged
+    /// requests (see [`remote`]). With a `<url>.idx` tag index beside the
+    /// archive only the index and the entries read are fetched; such a
+    /// handle reads each entry's format when asked, but knows no labels.
+    pub fn new<P: AsRef<Path>>(filename: P, mode: &str) -> Result<Self, QuiverError> {
+        Self::check_mode(mode)?;
+        let path = filename.as_ref();
+        if let Some(url) = path.to_str().filter(|p| remote::is_remote_url(p)) {
+            if mode == "r" {
+                return Self::open_remote(url);
+            }
+            if mode != "w" || !url.starts_with("s3://") {
+                return Err(QuiverError::InvalidOperation(format!(
+                    "Remote archives can only be read (mode 'r') or written to S3 as new files (mode 'w'), \
+                     not opened in '{}' mode",
+                    mode
+                )));
+            }
+            return Self::open(s3::open_writer(url)?, mode, None);
+        }
+        if mode == "a" && !path.exists() {
+            return Err(QuiverError::InvalidOperation(format!(
+                "Cannot append to '{}': the file does not exist",
+                path.display()
+            )));
+        }
+        Self::open(open_storage(path, mode)?, mode, None)
+    }
+
+    /// Open a Quiver archive on any storage backend.
+    pub fn with_storage(storage: Box<dyn Storage>, mode: &str) -> Result<Self, QuiverError> {
+        Self::check_mode(mode)?;
+        Self::open(storage, mode, None)
+    }
+
+    /// Open a plain archive read-only through a memory mapping (see
+    /// [`storage::MappedFile`]), for workloads that read many entries at
+    /// random.
+    pub fn open_mapped<P: AsRef<Path>>(filename: P) -> Result<Self, QuiverError> {
+        Self::open(Box::new(MappedFile::open(filename)?), "r", None)
+    }
+
+    /// Open an archive written with old spellings of the record keywords
+    /// (`QVTAG`, `QV TAG`, ...) read-only, rewriting the variants in
+    /// `records` to the canonical keywords as it is read (see [`legacy`]).
+    pub fn open_legacy<P: AsRef<Path>>(filename: P, records: &LegacyRecords) -> Result<Self, QuiverError> {
+        let storage = open_storage(filename.as_ref(), "r")?;
+        Self::open(Box::new(LegacyFile::new(storage, records.clone())?), "r", None)
+    }
+
+    /// Open a read-only snapshot of the archive as it was at `generation`
+    /// (see [`Quiver::generation`]), leaving out the entries appended since.
+    ///
+    /// Generations from before the last rewrite (a removal, rename, score or
+    /// metadata edit, repack) are gone and fail to open; a handle opened
+    /// before such a rewrite keeps reading the old archive.
+    pub fn open_snapshot<P: AsRef<Path>>(
+        filename: P,
+        generation: usize,
+    ) -> Result<Self, QuiverError> {
+        Self::open(Box::new(LocalFile::pinned(filename)?), "r", Some(generation))
+    }
+
+    fn check_mode(mode: &str) -> Result<(), QuiverError> {
+        if !["r", "w", "a", "rw"].contains(&mode) {
+            return Err(QuiverError::InvalidMode(format!(
+                "Quiver file must be opened in 'r', 'w', 'a' or 'rw' mode, not '{}'",
+                mode
+            )));
+        }
+        Ok(())
+    }
+
+    fn open(
+        storage: Box<dyn Storage>,
+        mode: &str,
+        generation: Option<usize>,
+    ) -> Result<Self, QuiverError> {
+        let mut index = if !storage.exists() {
+            Index::default()
+        } else {
+            let len = storage.len()?;
+            let reader = BufReader::new(storage.reader()?.take(len));
+            Self::read_index(reader, Location::new(storage.name()), generation)?
+        };
+        // Only read handles hide a cut entry: a writer appending after one
+        // has to see it
+        let partial = if mode == "r" && generation.is_none() {
+            Self::split_partial(storage.as_ref(), &mut index)?
+        } else {
+            None
+        };
+        if let Some(generation) = generation {
+            let (rewritten, _) = index.rewritten();
+            if generation < rewritten {
+                return Err(QuiverError::InvalidOperation(format!(
+                    "Generation {} predates the last rewrite of the file, at generation {}",
+                    generation, rewritten
+                )));
+            }
+            let current = index.generation().unwrap_or(rewritten);
+            if current < generation {
+                return Err(QuiverError::InvalidOperation(format!(
+                    "Generation {} does not exist yet; the file is at generation {}",
+                    generation, current
+                )));
+            }
+        }
+        let mut qv = Self::from_index(storage, mode, index)?;
+        if let Some(partial) = &partial {
+            let what = match &partial.entry.tag {
+                Some(tag) => format!("entry {}", tag),
+                None => "entry".to_string(),
+            };
+            qv.warnings.get_mut().unwrap_or_else(|e| e.into_inner()).push(
+                WarningCode::PartialEntry,
+                format!(
+                    "Ignoring the incomplete last {} ({} bytes at byte {}); its writer is still running or was killed",
+                    what, partial.entry.bytes, partial.entry.offset
+                ),
+            );
+        }
+        qv.partial = partial;
+        Ok(qv)
+    }
+
+    /// Take an entry cut short at the end of the archive out of `index`.
+    ///
+    /// The end is cut short if the last line is unterminated, or if the last
+    /// entry has a `QV_HASH` line its payload does not match. A final `END`
+    /// record without a newline is taken as complete, since PDB files often
+    /// end that way.
+    fn split_partial(storage: &dyn Storage, index: &mut Index) -> Result<Option<Partial>, QuiverError> {
+        let from = match (index.torn_from, index.offsets.last()) {
+            (Some(from), _) => from,
+            (None, Some(&offset)) if index.last_hashed => {
+                let block = storage.read_range(offset, index.len - offset)?;
+                if !matches!(check_block(&String::from_utf8_lossy(&block)), Integrity::Mismatch { .. }) {
+                    return Ok(None);
+                }
+                offset
+            }
+            _ => return Ok(None),
+        };
+        let bytes = index.len - from;
+        index.len = from;
+        let mut partial = Partial {
+            entry: PartialEntry {
+                tag: None,
+                offset: from,
+                bytes,
+            },
+            format: PayloadFormat::default(),
+            labels: Vec::new(),
+            included: false,
+        };
+        if index.offsets.last() == Some(&from) {
+            index.offsets.pop();
+            partial.entry.tag = index.tags.pop();
+            partial.format = index.formats.pop().unwrap_or_default();
+            partial.labels = index.labels.pop().unwrap_or_default();
+        }
+        Ok(Some(partial))
+    }
+
+    /// Open a remote archive read-only, through its tag index if it has one.
+    fn open_remote(url: &str) -> Result<Self, QuiverError> {
+        let client = remote::client_for(url)?;
+        let storage = RemoteFile::open(client.clone(), url)?;
+        let len = storage.len()?;
+        let Some(entries) = remote::load_index(client.as_ref(), url, len)? else {
+            return Self::open(Box::new(storage), "r", None);
+        };
+        Self::from_entries(Box::new(storage), entries, len)
+    }
+
+    /// Open a local archive read-only through its `<archive>.idx` tag index
+    /// (see [`tagindex`]) if that is up to date, so the archive is not
+    /// scanned for its tags; otherwise like [`Quiver::new`]. As with remote
+    /// archives, such a handle knows no labels.
+    pub fn open_indexed<P: AsRef<Path>>(filename: P) -> Result<Self, QuiverError> {
+        let path = filename.as_ref();
+        let fresh = match tagindex::TagIndex::load(tagindex::index_path(path))? {
+            Some(index) if index.is_fresh(path)? => Some(index),
+            _ => None,
+        };
+        let Some(index) = fresh else {
+            return Self::new(path, "r");
+        };
+        let len = index.archive_size();
+        let entries = index.entries().to_vec();
+        Self::from_entries(Box::new(LocalFile::pinned(path)?), entries, len)
+    }
+
+    /// A read-only handle on the first `len` bytes of `storage`, whose
+    /// entries are known from a tag index.
+    fn from_entries(
+        storage: Box<dyn Storage>,
+        entries: Vec<tagindex::IndexEntry>,
+        len: u64,
+    ) -> Result<Self, QuiverError> {
+        // The header lines come before the first entry
+        let head = storage.read_range(0, entries.first().map_or(len, |e| e.offset))?;
+        let mut index = Self::read_index(&head[..], Location::new(storage.name()), None)?;
+        index.formats = vec![PayloadFormat::default(); entries.len()];
+        index.labels = vec![Vec::new(); entries.len()];
+        index.offsets = entries.iter().map(|e| e.offset).collect();
+        index.tags = entries.into_iter().map(|e| e.tag).collect();
+        index.len = len;
+        let mut qv = Self::from_index(storage, "r", index)?;
+        qv.indexed = true;
+        Ok(qv)
+    }
+
+    fn from_index(storage: Box<dyn Storage>, mode: &str, index: Index) -> Result<Self, QuiverError> {
+        let rewritten = index.rewritten();
+        Ok(Self {
+            storage,
+            mode: mode.to_string(),
+            tags: index.tags,
+            offsets: index.offsets,
+            formats: index.formats,
+            labels: index.labels,
+            header: index.header,
+            rewritten,
+            snapshot_len: (mode == "r" || mode == "rw").then_some(index.len),
+            access: AccessLog::from_env()?.map(|log| Arc::new(log) as Arc<dyn AccessHook>),
+            duplicate_policy: DuplicatePolicy::default(),
+            field_charset: FieldCharset::default(),
+            legacy_bytes: LegacyBytes::default(),
+            validators: Vec::new(),
+            strict: true,
+            write_hashes: false,
+            indexed: false,
+            warnings: Mutex::new(Warnings::new()),
+            partial: None,
+        })
+    }
+
+    /// The entry cut short at the end of the archive, if its writer is still
+    /// running or was killed. Read handles leave it out of every read (with
+    /// a [`WarningCode::PartialEntry`] warning) unless
+    /// [`Quiver::set_include_partial`] asks for it.
+    pub fn partial_entry(&self) -> Option<&PartialEntry> {
+        self.partial.as_ref().map(|partial| &partial.entry)
+    }
+
+    /// Show (or hide again) the entry cut short at the end of the archive,
+    /// see [`Quiver::partial_entry`], e.g. to salvage what was written of
+    /// it. A `QV_TAG` line cut before its tag has no entry to show.
+    pub fn set_include_partial(&mut self, include: bool) {
+        let Some(partial) = self.partial.as_mut() else {
+            return;
+        };
+        let Some(tag) = &partial.entry.tag else {
+            return;
+        };
+        if partial.included == include {
+            return;
+        }
+        partial.included = include;
+        if include {
+            self.tags.push(tag.clone());
+            self.offsets.push(partial.entry.offset);
+            self.formats.push(partial.format);
+            self.labels.push(partial.labels.clone());
+            self.snapshot_len = Some(partial.entry.offset + partial.entry.bytes);
+        } else {
+            self.tags.pop();
+            self.offsets.pop();
+            self.formats.pop();
+            self.labels.pop();
+            self.snapshot_len = Some(partial.entry.offset);
+        }
+    }
+
+    /// Choose which value [`Quiver::get_scores`] and
+    /// [`Quiver::get_score_records`] keep when a `QV_SCORE` line repeats a
+    /// key. The default keeps the last one; `Warn` also raises a warning,
+    /// see [`Quiver::take_warnings`].
+    pub fn set_duplicate_policy(&mut self, policy: DuplicatePolicy) {
+        self.duplicate_policy = policy;
+    }
+
+    /// Choose which characters the names of scores written through this
+    /// handle may use. The default stores them as given.
+    pub fn set_field_charset(&mut self, charset: FieldCharset) {
+        self.field_charset = charset;
+    }
+
+    /// Choose how [`Quiver::get_scores`] and [`Quiver::get_score_records`]
+    /// read score lines that are not valid UTF-8. The default decodes the
+    /// invalid bytes as Windows-1252.
+    pub fn set_legacy_bytes(&mut self, policy: LegacyBytes) {
+        self.legacy_bytes = policy;
+    }
+
+    /// Apply the duplicate policy to the fields of one score line.
+    fn resolve_score_fields(&self, tag: &str, fields: &mut MetaFields) -> Result<(), String> {
+        let repeated = resolve_duplicates(fields, self.duplicate_policy)?;
+        if self.duplicate_policy == DuplicatePolicy::Warn {
+            let mut warnings = self.warnings.lock().unwrap_or_else(|e| e.into_inner());
+            for key in repeated {
+                warnings.push(
+                    WarningCode::RepeatedScoreField,
+                    format!("Tag {} repeats score field '{}', keeping the last value", tag, key),
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// The warnings raised by reads through this handle since the last call,
+    /// such as repeated score fields under [`DuplicatePolicy::Warn`].
+    pub fn take_warnings(&self) -> Warnings {
+        std::mem::take(&mut *self.warnings.lock().unwrap_or_else(|e| e.into_inner()))
+    }
+
+    /// Check every entry added from now on with `validator`, after any
+    /// added before it. A rejected entry is not written.
+    pub fn add_validator(&mut self, validator: Arc<dyn EntryValidator>) {
+        self.validators.push(validator);
+    }
+
+    /// Turn off (or back on) the check that entries added cannot corrupt
+    /// the archive, see [`Entry::check_layout`]. On by default.
+    pub fn set_strict(&mut self, strict: bool) {
+        self.strict = strict;
+    }
+
+    /// Store a checksum of the payload with every entry added from now on,
+    /// for [`Quiver::verify`] to check. Off by default.
+    pub fn set_write_hashes(&mut self, write_hashes: bool) {
+        self.write_hashes = write_hashes;
+    }
+
+    /// Tell `hook` about every entry read from now on, replacing the log
+    /// set through [`access::ACCESS_LOG_ENV`].
+    pub fn set_access_hook(&mut self, hook: Arc<dyn AccessHook>) {
+        self.access = Some(hook);
+    }
+
+    fn record_access(&self, operation: &str, tag: &str) -> Result<(), QuiverError> {
+        if let Some(hook) = &self.access {
+            hook.record(&self.storage.name().unwrap_or_else(|| "-".to_string()), operation, tag)?;
+        }
+        Ok(())
+    }
+
+    /// Scan the tag and header lines, stopping before entry `generation` if
+    /// one is given.
+    fn read_index<R: BufRead>(
+        mut reader: R,
+        mut location: Location,
+        generation: Option<usize>,
+    ) -> Result<Index, QuiverError> {
+        let mut index = Index::default();
+        let mut buf = Vec::new();
+        loop {
+            buf.clear();
+            let n = reader.read_until(b'\n', &mut buf)?;
+            if n == 0 {
+                break;
+            }
+            if buf.starts_with(b"QV_TAG") && generation.is_some() && generation == index.generation() {
+                break;
+            }
+            let torn = !buf.ends_with(b"\n") && buf.trim_ascii() != b"END";
+            if torn {
+                // Cut short by a writer, see `split_partial`
+                index.torn_from = Some(match index.offsets.last() {
+                    Some(&offset) if !buf.starts_with(b"QV_TAG") => offset,
+                    _ => index.len,
+                });
+            }
+            if let Err(e) = location.check(&buf) {
+                if !torn {
+                    return Err(e);
+                }
+                index.len += n as u64;
+                break;
+            }
+            if buf.starts_with(b"QV_TAG") {
+                index.last_hashed = false;
+                let line = String::from_utf8_lossy(&buf);
+                if let Some(tag) = line.split_whitespace().nth(1) {
+                    index.tags.push(tag.to_string());
+                    index.offsets.push(index.len);
+                    index.formats.push(PayloadFormat::from_tag_line(&line));
+                    index.labels.push(Vec::new());
+                }
+            } else if buf.starts_with(b"QV_HASH") {
+                index.last_hashed = true;
+            } else if buf.starts_with(b"QV_META") {
+                if let (Some((_, fields)), Some(labels)) =
+                    (parse_meta_line(&String::from_utf8_lossy(&buf)), index.labels.last_mut())
+                {
+                    labels.extend(labels_of(&fields));
+                }
+            } else if buf.starts_with(b"QV_HEADER") {
+                // Concatenated archives repeat headers; the first value of a key wins
+                if let Some((key, value)) = parse_header_line(&String::from_utf8_lossy(&buf)) {
+                    if key == GENERATION_FIELD {
+                        index.rewritten_generation = index.rewritten_generation.or(value.parse().ok());
+                    } else if key == GENERATION_ENTRIES_FIELD {
+                        index.rewritten_entries = index.rewritten_entries.or(value.parse().ok());
+                    } else if !index.header.iter().any(|(k, _)| *k == key) {
+                        index.header.push((key, value));
+                    }
+                }
+            }
+            index.len += n as u64;
+        }
+        Ok(index)
+    }
+
+    fn check_readable(&self) -> Result<(), QuiverError> {
+        if self.mode != "r" && self.mode != "rw" {
+            return Err(QuiverError::InvalidOperation(
+                "Quiver file must be opened in read mode to allow for reading.".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    fn check_writable(&self) -> Result<(), QuiverError> {
+        if self.mode == "r" {
+            return Err(QuiverError::InvalidOperation(
+                "Quiver file must be opened in write mode to allow for writing.".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Write out any structures still buffered by the storage backend.
+    pub fn flush(&mut self) -> Result<(), QuiverError> {
+        self.storage.flush()?;
+        Ok(())
+    }
+
+    /// Flush and complete the archive; no entries can be added afterwards.
+    ///
+    /// Archives written to S3 (see [`s3`]) only appear once this returns.
+    pub fn finish(mut self) -> Result<(), QuiverError> {
+        self.storage.finish()?;
+        Ok(())
+    }
+
+    /// Append `data` and, in `"rw"` mode, make it visible to later reads.
+    fn append(&mut self, data: &[u8]) -> Result<(), QuiverError> {
+        self.storage.append(data)?;
+        if self.mode == "rw" {
+            if let Some(len) = self.snapshot_len.as_mut() {
+                *len += data.len() as u64;
+            }
+        }
+        Ok(())
+    }
+
+    /// Open a buffered reader over the snapshot taken when the file was opened.
+    fn reader(&self) -> Result<BufReader<io::Take<Box<dyn Read + '_>>>, QuiverError> {
+        let limit = self.snapshot_len.unwrap_or(u64::MAX);
+        Ok(BufReader::new(self.storage.reader()?.take(limit)))
+    }
+
+    /// The generation of the archive as this handle sees it.
+    ///
+    /// Every mutation bumps it: each appended entry by one, and each rewrite
+    /// (removal, rename, score or metadata edit, repack) by one, recorded
+    /// in the [`GENERATION_FIELD`] and [`GENERATION_ENTRIES_FIELD`] header
+    /// lines it writes. An archive never rewritten is at the generation of
+    /// its number of entries.
+    pub fn generation(&self) -> usize {
+        let (generation, entries) = self.rewritten;
+        generation + self.tags.len().saturating_sub(entries)
+    }
+
+    /// Archive-wide `QV_HEADER` fields, e.g. creator or description.
+    pub fn get_header(&self) -> MetaFields {
+        self.header.clone()
+    }
+
+    /// Write the archive header. Only possible before the first entry is added.
+    pub fn set_header(&mut self, fields: &[(String, String)]) -> Result<(), QuiverError> {
+        self.check_writable()?;
+        if let Some((key, _)) = fields
+            .iter()
+            .find(|(key, _)| key == GENERATION_FIELD || key == GENERATION_ENTRIES_FIELD)
+        {
+            return Err(QuiverError::InvalidOperation(format!(
+                "The header field '{}' is kept by the archive itself",
+                key
+            )));
+        }
+        if self.storage.exists() && !self.storage.is_empty()? {
+            return Err(QuiverError::InvalidOperation(
+                "The header can only be set before anything is written to the file.".to_string(),
+            ));
+        }
+        self.append(format_header_lines(fields).as_bytes())?;
+        self.header = fields.to_vec();
+        Ok(())
+    }
+
+    /// The raw bytes of the `index`-th entry, from its `QV_TAG` line up to
+    /// the next entry.
+    pub(crate) fn entry_bytes(&self, index: usize) -> Result<Vec<u8>, QuiverError> {
+        self.check_readable()?;
+        let (start, end) = self.entry_range(index)?;
+        Ok(self.storage.read_range(start, end - start)?)
+    }
+
+    /// The raw bytes before the first entry: the `QV_HEADER` lines and
+    /// anything else a writer put there.
+    pub(crate) fn preamble_bytes(&self) -> Result<Vec<u8>, QuiverError> {
+        self.check_readable()?;
+        let end = match self.offsets.first() {
+            Some(&start) => start,
+            None => self.snapshot_len.map_or_else(|| self.storage.len(), Ok)?,
+        };
+        Ok(self.storage.read_range(0, end)?)
+    }
+
+    /// Start an empty archive with the preamble of `source` byte for byte,
+    /// see [`Quiver::preamble_bytes`]. Returns the bytes copied.
+    pub(crate) fn copy_preamble(&mut self, source: &Quiver) -> Result<Vec<u8>, QuiverError> {
+        self.check_writable()?;
+        if self.storage.exists() && !self.storage.is_empty()? {
+            return Err(QuiverError::InvalidOperation(
+                "The header can only be set before anything is written to the file.".to_string(),
+            ));
+        }
+        let bytes = source.preamble_bytes()?;
+        self.append(&bytes)?;
+        self.header = source.header.clone();
+        Ok(bytes)
+    }
+
+    /// Append the `index`-th entry of `source` byte for byte, its score,
+    /// metadata and `QV_HASH` lines included. Returns the bytes copied.
+    pub(crate) fn copy_entry(&mut self, source: &Quiver, index: usize) -> Result<Vec<u8>, QuiverError> {
+        self.check_writable()?;
+        let tag = &source.tags[index];
+        if self.tags.contains(tag) {
+            return Err(QuiverError::DuplicateTag(tag.clone()));
+        }
+        let bytes = source.entry_bytes(index)?;
+        let offset = if self.storage.exists() { self.storage.len()? } else { 0 };
+        self.append(&bytes)?;
+        self.tags.push(tag.clone());
+        self.offsets.push(offset);
+        self.formats.push(source.formats.get(index).copied().unwrap_or_default());
+        self.labels.push(source.labels.get(index).cloned().unwrap_or_default());
+        Ok(bytes)
+    }
+
+    /// Text offsets `start..end` of the `index`-th entry.
+    fn entry_range(&self, index: usize) -> Result<(u64, u64), QuiverError> {
+        let end = match self.offsets.get(index + 1) {
+            Some(&next) => next,
+            None => self.snapshot_len.map_or_else(|| self.storage.len(), Ok)?,
+        };
+        Ok((self.offsets[index], end))
+    }
+
+    /// Uncompressed and stored size of one entry.
+    pub fn stat(&self, tag: &str) -> Result<EntryStat, QuiverError> {
+        self.check_readable()?;
+        let index = self
+            .tags
+            .iter()
+            .position(|t| t == tag)
+            .ok_or_else(|| QuiverError::TagNotFound(tag.to_string()))?;
+        self.stat_at(index)
+    }
+
+    pub(crate) fn stat_at(&self, index: usize) -> Result<EntryStat, QuiverError> {
+        let (start, end) = self.entry_range(index)?;
+        let stored = self.storage.stored_len(start, end - start)?;
+        Ok(EntryStat {
+            tag: self.tags[index].clone(),
+            bytes: end - start,
+            stored: stored.bytes,
+            exact: stored.exact,
+        })
+    }
+
+    pub fn get_tags(&self) -> Vec<String> {
+        self.tags.clone()
+    }
+
+    pub fn size(&self) -> usize {
+        self.tags.len()
+    }
+
+    /// Check a batch of tags against the tag index in a single pass.
+    ///
+    /// Results are returned in the order the tags were given.
+    pub fn tags_exist(&self, tags: &[String]) -> Vec<(String, bool)> {
+        let present: HashSet<&str> = self.tags.iter().map(|t| t.as_str()).collect();
+        tags.iter()
+            .map(|t| (t.clone(), present.contains(t.as_str())))
+            .collect()
+    }
+
+    pub fn add_pdb(
+        &mut self,
+        pdb_lines: &[String],
+        tag: &str,
+        score_str: Option<&str>,
+    ) -> Result<(), QuiverError> {
+        self.add_entry(&Entry {
+            tag: tag.to_string(),
+            score: score_str.map(|s| s.to_string()),
+            meta: Vec::new(),
+            lines: pdb_lines.to_vec(),
+            format: PayloadFormat::Pdb,
+        })
+    }
+
+    /// Append a complete entry, including its score and metadata lines.
+    ///
+    /// An entry that would corrupt the archive (see [`Quiver::set_strict`])
+    /// or is rejected by a validator (see [`Quiver::add_validator`]) is not
+    /// written.
+    pub fn add_entry(&mut self, entry: &Entry) -> Result<(), QuiverError> {
+        self.check_writable()?;
+        if self.tags.contains(&entry.tag) {
+            return Err(QuiverError::DuplicateTag(entry.tag.clone()));
+        }
+        if self.strict {
+            entry
+                .check_layout()
+                .map_err(|e| QuiverError::InvalidOperation(format!("Entry {} rejected: {}", entry.tag, e)))?;
+        }
+        let entry = match &entry.score {
+            Some(score) if self.field_charset != FieldCharset::Utf8 => {
+                let score = normalize_score_str(score, self.field_charset).map_err(QuiverError::InvalidOperation)?;
+                Cow::Owned(Entry {
+                    score: Some(score.into_owned()),
+                    ..entry.clone()
+                })
+            }
+            _ => Cow::Borrowed(entry),
+        };
+        for validator in &self.validators {
+            validator
+                .validate(&entry)
+                .map_err(|e| QuiverError::InvalidOperation(format!("Entry {} rejected: {}", entry.tag, e)))?;
+        }
+        let text = if self.write_hashes { entry.to_hashed_text() } else { entry.to_text() };
+        let offset = if self.storage.exists() { self.storage.len()? } else { 0 };
+        self.append(text.as_bytes())?;
+        self.tags.push(entry.tag.clone());
+        self.offsets.push(offset);
+        self.formats.push(entry.format);
+        self.labels.push(labels_of(&entry.meta));
+        Ok(())
+    }
+
+    /// Append a PDB entry whose payload is already formatted text, copied
+    /// into the archive as given rather than split into lines, for producers
+    /// that hold whole structures as bytes.
+    ///
+    /// Only the tag is checked unless `validate` is set, in which case the
+    /// payload is split into lines and goes through the layout check and
+    /// validators like [`Quiver::add_entry`]. Without it, the caller vouches
+    /// that no payload line starts with a [`RESERVED_PREFIXES`] record. A
+    /// missing final newline is added either way.
+    pub fn add_raw_entry(
+        &mut self,
+        tag: &str,
+        raw: &[u8],
+        score_str: Option<&str>,
+        validate: bool,
+    ) -> Result<(), QuiverError> {
+        if validate {
+            let text = std::str::from_utf8(raw).map_err(|e| {
+                QuiverError::InvalidOperation(format!("Entry {} rejected: payload is not UTF-8: {}", tag, e))
+            })?;
+            return self.add_entry(&Entry {
+                tag: tag.to_string(),
+                score: score_str.map(|s| s.to_string()),
+                meta: Vec::new(),
+                lines: text.lines().map(|line| line.to_string()).collect(),
+                format: PayloadFormat::Pdb,
+            });
+        }
+        self.check_writable()?;
+        if self.tags.iter().any(|t| t == tag) {
+            return Err(QuiverError::DuplicateTag(tag.to_string()));
+        }
+        if tag.is_empty() || tag.contains(char::is_whitespace) {
+            return Err(QuiverError::InvalidOperation(format!(
+                "Entry {} rejected: tag '{}' is empty or contains whitespace",
+                tag, tag
+            )));
+        }
+        let mut text = format_tag_line(tag, PayloadFormat::Pdb);
+        text.push('\n');
+        if let Some(score) = score_str {
+            let score = normalize_score_str(score, self.field_charset).map_err(QuiverError::InvalidOperation)?;
+            text.push_str(&format!("QV_SCORE {} {}\n", tag, score));
+        }
+        if self.write_hashes {
+            text.push_str(&format_hash_line(tag, &raw_payload_digest(raw)));
+            text.push('\n');
+        }
+        // One write per entry, so an interrupted run never leaves half an entry
+        let mut bytes = Vec::with_capacity(text.len() + raw.len() + 1);
+        bytes.extend_from_slice(text.as_bytes());
+        bytes.extend_from_slice(raw);
+        if !raw.is_empty() && !raw.ends_with(b"\n") {
+            bytes.push(b'\n');
+        }
+        let offset = if self.storage.exists() { self.storage.len()? } else { 0 };
+        self.append(&bytes)?;
+        self.tags.push(tag.to_string());
+        self.offsets.push(offset);
+        self.formats.push(PayloadFormat::Pdb);
+        self.labels.push(Vec::new());
+        Ok(())
+    }
+
+    /// Delete structures, rewriting the archive atomically without them.
+    ///
+    /// Their `QV_SCORE` and `QV_META` lines go with them. Tags that are not
+    /// in the file are ignored; returns the number of entries removed.
+    pub fn remove_tags(&mut self, tags: &[String]) -> Result<usize, QuiverError> {
+        self.check_writable()?;
+        let doomed: HashSet<&str> = tags.iter().map(|t| t.as_str()).collect();
+        let removed = self.tags.iter().filter(|t| doomed.contains(t.as_str())).count();
+        if removed == 0 {
+            return Ok(0);
+        }
+        self.rewrite(|tag| {
+            if doomed.contains(tag) {
+                EntryEdit::Drop
+            } else {
+                EntryEdit::Keep
+            }
+        })?;
+        Ok(removed)
+    }
+
+    /// Add `label` to each of `tags`, rewriting the archive atomically.
+    ///
+    /// Labels live in the entries' `QV_META` lines (see [`LABELS_FIELD`]), so
+    /// they survive slicing, splitting and conversion. Tags that are not in
+    /// the file or already carry the label are ignored; returns the number
+    /// of entries labelled.
+    pub fn add_labels(&mut self, tags: &[String], label: &str) -> Result<usize, QuiverError> {
+        self.relabel(tags, label, true)
+    }
+
+    /// Add `label` to a single entry, see [`Quiver::add_labels`].
+    pub fn add_label(&mut self, tag: &str, label: &str) -> Result<(), QuiverError> {
+        if !self.tags.iter().any(|t| t == tag) {
+            return Err(QuiverError::TagNotFound(tag.to_string()));
+        }
+        self.add_labels(&[tag.to_string()], label)?;
+        Ok(())
+    }
+
+    /// Remove `label` from each of `tags`; returns the number of entries
+    /// that carried it.
+    pub fn remove_labels(&mut self, tags: &[String], label: &str) -> Result<usize, QuiverError> {
+        self.relabel(tags, label, false)
+    }
+
+    fn relabel(&mut self, tags: &[String], label: &str, add: bool) -> Result<usize, QuiverError> {
+        self.check_writable()?;
+        check_label(label)?;
+        let wanted: HashSet<&str> = tags.iter().map(|t| t.as_str()).collect();
+        let targets: HashSet<String> = self
+            .tags
+            .iter()
+            .zip(&self.labels)
+            .filter(|(tag, labels)| wanted.contains(tag.as_str()) && labels.iter().any(|l| l == label) != add)
+            .map(|(tag, _)| tag.clone())
+            .collect();
+        if targets.is_empty() {
+            return Ok(0);
+        }
+        let edit = |meta: &mut MetaFields| {
+            let mut labels = labels_of(meta);
+            if add {
+                labels.push(label.to_string());
+            } else {
+                labels.retain(|l| l != label);
+            }
+            meta.retain(|(k, _)| k != LABELS_FIELD);
+            if !labels.is_empty() {
+                meta.push((LABELS_FIELD.to_string(), labels.join(",")));
+            }
+        };
+        self.rewrite(|tag| {
+            if targets.contains(tag) {
+                EntryEdit::Meta(&edit)
+            } else {
+                EntryEdit::Keep
+            }
+        })?;
+        Ok(targets.len())
+    }
+
+    /// Rename the entries named in `pairs` (old tag, new tag), rewriting the
+    /// archive atomically; every other entry is left as it is.
+    ///
+    /// The pairs are checked first (see [`rename::check_mapping`]), so a
+    /// refused mapping changes nothing. Returns the number of entries
+    /// renamed.
+    pub fn rename_tags(&mut self, pairs: &[(String, String)]) -> Result<usize, QuiverError> {
+        self.check_writable()?;
+        let mapping = rename::check_mapping(&self.tags, pairs)?;
+        let renamed = self.tags.iter().filter(|t| mapping.contains_key(*t)).count();
+        if renamed == 0 {
+            return Ok(0);
+        }
+        self.rewrite(|tag| match mapping.get(tag) {
+            Some(new_tag) => EntryEdit::Rename(new_tag),
+            None => EntryEdit::Keep,
+        })?;
+        Ok(renamed)
+    }
+
+    /// Add or overwrite score fields of `tag`, rewriting the archive
+    /// atomically.
+    ///
+    /// Fields not named in `scores` keep their values, and an entry without
+    /// a `QV_SCORE` line gets one.
+    pub fn update_scores(&mut self, tag: &str, scores: &[(String, String)]) -> Result<(), QuiverError> {
+        self.check_writable()?;
+        if !self.tags.iter().any(|t| t == tag) {
+            return Err(QuiverError::TagNotFound(tag.to_string()));
+        }
+        let mut normalized = Vec::with_capacity(scores.len());
+        for (key, value) in scores {
+            let key = normalize_field(key, self.field_charset).map_err(QuiverError::InvalidOperation)?;
+            check_field("score", &key)?;
+            check_field("score", value)?;
+            normalized.push((key.into_owned(), value.clone()));
+        }
+        let edit = |fields: &mut MetaFields| {
+            for (key, value) in &normalized {
+                match fields.iter_mut().find(|(k, _)| k == key) {
+                    Some(field) => field.1 = value.clone(),
+                    None => fields.push((key.clone(), value.clone())),
+                }
+            }
+        };
+        self.rewrite(|t| {
+            if t == tag {
+                EntryEdit::Score(&edit)
+            } else {
+                EntryEdit::Keep
+            }
+        })
+    }
+
+    /// Set one score field of `tag` to a number, boolean or string, see
+    /// [`Quiver::update_scores`]. The value is written so that it reads back
+    /// with the same type, e.g. `1.0` stays a float.
+    pub fn set_score(&mut self, tag: &str, key: &str, value: impl Into<ScoreValue>) -> Result<(), QuiverError> {
+        self.update_scores(tag, &[(key.to_string(), value.into().to_string())])
+    }
+
+    /// The typed score fields of `tag`, see [`scores`].
+    ///
+    /// An entry without a `QV_SCORE` line is an error, like a missing tag.
+    pub fn get_scores(&self, tag: &str) -> Result<Vec<(String, ScoreValue)>, QuiverError> {
+        self.check_readable()?;
+        let Some(index) = self.tags.iter().position(|t| t == tag) else {
+            return Err(QuiverError::TagNotFound(tag.to_string()));
+        };
+        self.scores_at(index)?
+            .ok_or_else(|| QuiverError::InvalidOperation(format!("Tag {} has no QV_SCORE line", tag)))
+    }
+
+    /// The scores of several tags at once: those of every requested tag in
+    /// the archive, and the requested tags that are not. Only the requested
+    /// entries are read, instead of every score line of the archive; an
+    /// entry without a `QV_SCORE` line has no scores.
+    pub fn get_scores_many<S: AsRef<str>>(&self, tags: &[S]) -> Result<ScoreLookup, QuiverError> {
+        self.check_readable()?;
+        // The first entry of each tag, as get_scores() reads
+        let mut positions: HashMap<&str, usize> = HashMap::new();
+        for (index, tag) in self.tags.iter().enumerate() {
+            positions.entry(tag.as_str()).or_insert(index);
+        }
+        let mut lookup = ScoreLookup::default();
+        let mut seen = HashSet::new();
+        for tag in tags {
+            let tag = tag.as_ref();
+            if !seen.insert(tag) {
+                continue;
+            }
+            match positions.get(tag) {
+                Some(&index) => {
+                    let scores = self.scores_at(index)?.unwrap_or_default();
+                    lookup.scores.push((tag.to_string(), scores));
+                }
+                None => lookup.missing.push(tag.to_string()),
+            }
+        }
+        Ok(lookup)
+    }
+
+    /// The typed scores of entry `index`, `None` if it has no `QV_SCORE`
+    /// line.
+    fn scores_at(&self, index: usize) -> Result<Option<Vec<(String, ScoreValue)>>, QuiverError> {
+        let tag = &self.tags[index];
+        let bytes = self.entry_bytes(index)?;
+        let Some(line) = bytes.split(|&b| b == b'\n').find(|line| line.starts_with(b"QV_SCORE")) else {
+            return Ok(None);
+        };
+        let line = decode_line(line, &self.legacy_bytes)
+            .map_err(|e| QuiverError::InvalidOperation(format!("Tag {}: {}", tag, e)))?;
+        let Some((_, mut fields)) = parse_score_line(&line) else {
+            return Ok(None);
+        };
+        self.resolve_score_fields(tag, &mut fields)
+            .map_err(|e| QuiverError::InvalidOperation(format!("Tag {}: {}", tag, e)))?;
+        Ok(Some(typed_fields(&fields)))
+    }
+
+    /// Labels recorded for `tag`.
+    pub fn get_labels(&self, tag: &str) -> Result<Vec<String>, QuiverError> {
+        self.tags
+            .iter()
+            .position(|t| t == tag)
+            .map(|index| self.labels[index].clone())
+            .ok_or_else(|| QuiverError::TagNotFound(tag.to_string()))
+    }
+
+    /// Tags carrying `label`, in file order. Answered from the index.
+    pub fn list_by_label(&self, label: &str) -> Vec<String> {
+        self.tags
+            .iter()
+            .zip(&self.labels)
+            .filter(|(_, labels)| labels.iter().any(|l| l == label))
+            .map(|(tag, _)| tag.clone())
+            .collect()
+    }
+
+    /// The metadata fields of `tag`, from its `QV_META` lines.
+    ///
+    /// An entry with several lines gets their fields merged, the last value
+    /// of a key winning; an entry without any has no fields.
+    pub fn get_meta(&self, tag: &str) -> Result<MetaFields, QuiverError> {
+        let Some(index) = self.tags.iter().position(|t| t == tag) else {
+            return Err(QuiverError::TagNotFound(tag.to_string()));
+        };
+        let bytes = self.entry_bytes(index)?;
+        let mut meta = MetaFields::new();
+        for line in String::from_utf8_lossy(&bytes).lines() {
+            let Some((_, fields)) = parse_meta_line(line) else {
+                continue;
+            };
+            for (key, value) in fields {
+                match meta.iter_mut().find(|(k, _)| *k == key) {
+                    Some(field) => field.1 = value,
+                    None => meta.push((key, value)),
+                }
+            }
+        }
+        Ok(meta)
+    }
+
+    /// Set the metadata field `key` of `tag` to `value`, rewriting the
+    /// archive atomically.
+    ///
+    /// Other fields keep their values, and an entry without a `QV_META` line
+    /// gets one. Labels are edited with [`Quiver::add_label`] instead.
+    pub fn add_meta(&mut self, tag: &str, key: &str, value: &str) -> Result<(), QuiverError> {
+        self.check_writable()?;
+        if !self.tags.iter().any(|t| t == tag) {
+            return Err(QuiverError::TagNotFound(tag.to_string()));
+        }
+        check_field("metadata", key)?;
+        check_field("metadata", value)?;
+        if key == LABELS_FIELD {
+            return Err(QuiverError::InvalidOperation(format!(
+                "'{}' holds the labels of an entry, use add_label",
+                LABELS_FIELD
+            )));
+        }
+        let edit = |meta: &mut MetaFields| match meta.iter_mut().find(|(k, _)| k == key) {
+            Some(field) => field.1 = value.to_string(),
+            None => meta.push((key.to_string(), value.to_string())),
+        };
+        self.rewrite(|t| {
+            if t == tag {
+                EntryEdit::Meta(&edit)
+            } else {
+                EntryEdit::Keep
+            }
+        })
+    }
+
+    /// Rewrite the archive atomically, letting `edit` decide what happens to
+    /// each entry, then re-read the index.
+    ///
+    /// The new archive is swapped in through the storage backend, see
+    /// [`Quiver::replace_contents`]. Untouched entries are copied byte for byte.
+    fn rewrite<'e, F>(&mut self, mut edit: F) -> Result<(), QuiverError>
+    where
+        F: FnMut(&str) -> EntryEdit<'e>,
+    {
+        // Decided up front, once per entry: the count goes in the new header
+        // and `edit` may keep state
+        let edits: Vec<EntryEdit<'e>> = self.tags.iter().map(|tag| edit(tag)).collect();
+        let entries = edits.iter().filter(|e| !matches!(e, EntryEdit::Drop)).count();
+        let mut edits = edits.into_iter();
+        self.replace_contents(entries, |qv, spool| {
+            qv.spool_edited(spool, |_| edits.next().unwrap_or(EntryEdit::Keep))
+        })
+    }
+
+    /// Swap in the archive of `entries` entries that `spool` writes, through
+    /// the storage backend, then re-read the index.
+    ///
+    /// Backends on the local filesystem have `spool` write straight into the
+    /// file renamed over the archive (see [`Storage::stage`]); others get the
+    /// new archive as one buffer. It starts with the header lines recording
+    /// its generation, one past the current one; `spool` leaves out the old
+    /// ones (see [`is_generation_line`]).
+    fn replace_contents<F>(&mut self, entries: usize, spool: F) -> Result<(), QuiverError>
+    where
+        F: FnOnce(&Self, &mut dyn Write) -> Result<(), QuiverError>,
+    {
+        self.storage.flush()?;
+        let generation = format_header_lines(&[
+            (GENERATION_FIELD.to_string(), (self.generation() + 1).to_string()),
+            (GENERATION_ENTRIES_FIELD.to_string(), entries.to_string()),
+        ]);
+        let replaced = match self.storage.stage()? {
+            Some(mut staged) => {
+                staged.write_all(generation.as_bytes())?;
+                spool(self, &mut staged)?;
+                self.storage.commit(staged)
+            }
+            None => {
+                let mut data = generation.into_bytes();
+                spool(self, &mut data)?;
+                self.storage.replace(&mut data.as_slice())
+            }
+        };
+        replaced?;
+
+        let reader = BufReader::new(self.storage.reader()?);
+        let index = Self::read_index(reader, Location::new(self.storage.name()), None)?;
+        self.rewritten = index.rewritten();
+        self.tags = index.tags;
+        self.offsets = index.offsets;
+        self.formats = index.formats;
+        self.labels = index.labels;
+        if self.snapshot_len.is_some() {
+            self.snapshot_len = Some(index.len);
+        }
+        Ok(())
+    }
+
+    /// Stream the edited archive into `spool`.
+    fn spool_edited<'e, F>(&self, spool: &mut dyn Write, mut edit: F) -> Result<(), QuiverError>
+    where
+        F: FnMut(&str) -> EntryEdit<'e>,
+    {
+        let mut reader = self.storage.reader()?;
+        let mut reader = BufReader::new(reader.as_mut());
+        let mut skipping = false;
+        // New tag of the entry being copied, if it is renamed
+        let mut retag: Option<&str> = None;
+        // Fields being collected for an entry whose `QV_META` or `QV_SCORE`
+        // is edited
+        let mut pending: Option<PendingFields> = None;
+        let flush_fields = |pending: &mut Option<PendingFields>, spool: &mut dyn Write| match pending.take() {
+            Some(fields) => fields.write(spool),
+            None => Ok(()),
+        };
+        let mut buf = Vec::new();
+        loop {
+            buf.clear();
+            if reader.read_until(b'\n', &mut buf)? == 0 {
+                break;
+            }
+            if buf.starts_with(b"QV_TAG") {
+                flush_fields(&mut pending, spool)?;
+                let line = String::from_utf8_lossy(&buf);
+                let tag = line.split_whitespace().nth(1).unwrap_or("");
+                skipping = false;
+                retag = None;
+                let (edit, score) = match edit(tag) {
+                    EntryEdit::Keep => (None, false),
+                    EntryEdit::Drop => {
+                        skipping = true;
+                        (None, false)
+                    }
+                    EntryEdit::Meta(edit) => (Some(edit), false),
+                    EntryEdit::Score(edit) => (Some(edit), true),
+                    EntryEdit::Rename(new_tag) => {
+                        retag = Some(new_tag);
+                        (None, false)
+                    }
+                };
+                pending = edit.map(|edit| PendingFields {
+                    tag: tag.to_string(),
+                    edit,
+                    fields: Vec::new(),
+                    score,
+                });
+            } else if buf.starts_with(b"QV_HEADER") {
+                flush_fields(&mut pending, spool)?;
+                skipping = false;
+                retag = None;
+                if is_generation_line(&buf) {
+                    continue;
+                }
+            } else if let Some(pending_fields) = pending.as_mut() {
+                let line = String::from_utf8_lossy(&buf);
+                if pending_fields.score {
+                    if line.starts_with("QV_SCORE") {
+                        pending_fields.fields.extend(parse_score_line(&line).map(|(_, f)| f).unwrap_or_default());
+                        continue;
+                    }
+                    flush_fields(&mut pending, spool)?;
+                } else if let Some((_, fields)) = parse_meta_line(&line) {
+                    pending_fields.fields.extend(fields);
+                    continue;
+                } else if !line.starts_with("QV_SCORE") {
+                    flush_fields(&mut pending, spool)?;
+                }
+            }
+            match retag {
+                Some(new_tag)
+                    if [&b"QV_TAG"[..], b"QV_SCORE", b"QV_META", b"QV_HASH"]
+                        .iter()
+                        .any(|prefix| buf.starts_with(prefix)) =>
+                {
+                    spool.write_all(rename::retag_line(&String::from_utf8_lossy(&buf), new_tag).as_bytes())?;
+                }
+                _ if !skipping => spool.write_all(&buf)?,
+                _ => {}
+            }
+        }
+        flush_fields(&mut pending, spool)?;
+        spool.flush()?;
+        Ok(())
+    }
+
+    /// The raw entry block for `tag` (`QV_TAG`, `QV_SCORE`, `QV_META` and
+    /// payload lines) as one string, ready to forward into another archive.
+    ///
+    /// With `include_score = false` the `QV_SCORE` line is left out.
+    pub fn get_entry_text(&self, tag: &str, include_score: bool) -> Result<String, QuiverError> {
+        self.check_readable()?;
+        let Some(index) = self.tags.iter().position(|t| t == tag) else {
+            return Err(QuiverError::TagNotFound(tag.to_string()));
+        };
+        let bytes = self.entry_bytes(index)?;
+        self.record_access("get_entry_text", tag)?;
+        let mut text = String::with_capacity(bytes.len());
+        for line in String::from_utf8_lossy(&bytes).split_inclusive('\n') {
+            if line.starts_with("QV_HEADER") || (!include_score && line.starts_with("QV_SCORE")) {
+                continue;
+            }
+            text.push_str(line);
+        }
+        if !text.ends_with('\n') {
+            text.push('\n');
+        }
+        Ok(text)
     }
-}
 
-pub struct Quiver {
-    filename: PathBuf,
-    mode: String,
-    tags: Vec<String>,
-}
+    /// Payload format recorded for `tag`.
+    pub fn get_format(&self, tag: &str) -> Result<PayloadFormat, QuiverError> {
+        let index = self
+            .tags
+            .iter()
+            .position(|t| t == tag)
+            .ok_or_else(|| QuiverError::TagNotFound(tag.to_string()))?;
+        if !self.indexed {
+            return Ok(self.formats[index]);
+        }
+        // The tag index has no formats; read the entry's `QV_TAG` line
+        let (start, end) = self.entry_range(index)?;
+        let head = self.storage.read_range(start, (end - start).min(TAG_LINE_PROBE))?;
+        let line = head.split(|&b| b == b'\n').next().unwrap_or_default();
+        Ok(PayloadFormat::from_tag_line(&String::from_utf8_lossy(line)))
+    }
 
-impl Quiver {
-    pub fn new<P: AsRef<Path>>(filename: P, mode: &str) -> Result<Self, QuiverError> {
-        if mode != "r" && mode != "w" {
-            return Err(QuiverError::InvalidMode(format!(
-                "Quiver file must be opened in 'r' or 'w' mode, not '{}'",
-                mode
-            )));
+    /// Read one structure's PDB lines.
+    ///
+    /// Kept for existing callers; see [`Quiver::get_lines`].
+    pub fn get_pdblines(&self, tag: &str) -> Result<Vec<String>, QuiverError> {
+        self.get_lines(tag)
+    }
+
+    /// Read one structure's payload lines in their stored format (see
+    /// [`Quiver::get_format`]).
+    ///
+    /// The entry is located through the offset index, so only its own bytes
+    /// are read. Any number of threads can call this on the same read handle.
+    pub fn get_lines(&self, tag: &str) -> Result<Vec<String>, QuiverError> {
+        self.check_readable()?;
+        let Some(index) = self.tags.iter().position(|t| t == tag) else {
+            return Err(QuiverError::TagNotFound(tag.to_string()));
+        };
+        let bytes = self.entry_bytes(index)?;
+        self.record_access("get_lines", tag)?;
+        Ok(payload_lines(&String::from_utf8_lossy(&bytes)))
+    }
+
+    /// Like [`Quiver::get_lines`], keeping only the record types `filter`
+    /// names. Other lines are dropped as the entry is read, without
+    /// building the full payload first.
+    pub fn get_lines_filtered(&self, tag: &str, filter: &RecordFilter) -> Result<Vec<String>, QuiverError> {
+        self.check_readable()?;
+        let Some(index) = self.tags.iter().position(|t| t == tag) else {
+            return Err(QuiverError::TagNotFound(tag.to_string()));
+        };
+        let bytes = self.entry_bytes(index)?;
+        self.record_access("get_lines", tag)?;
+        Ok(String::from_utf8_lossy(&bytes)
+            .lines()
+            .skip(1)
+            .filter(|line| filter.keeps(line))
+            .map(|line| line.to_string())
+            .collect())
+    }
+
+    /// Read one structure without failing on the ways a single entry can be
+    /// unusable: the tag is missing, its payload fails its `QV_HASH`
+    /// checksum, or the archive was rewritten since this handle opened it.
+    /// The [`EntryStatus`] says which, so bulk readers need not match on
+    /// error messages. Other errors (I/O, a handle not open for reading) are
+    /// still returned as errors.
+    pub fn try_get(&self, tag: &str) -> Result<EntryResult, QuiverError> {
+        self.check_readable()?;
+        let result = |status, lines, detail| EntryResult {
+            tag: tag.to_string(),
+            status,
+            lines,
+            detail,
+        };
+        let Some(index) = self.tags.iter().position(|t| t == tag) else {
+            return Ok(result(EntryStatus::Missing, None, None));
+        };
+        let (start, end) = self.entry_range(index)?;
+        let bytes = self.entry_bytes(index)?;
+        let block = String::from_utf8_lossy(&bytes);
+        let stored_tag = block.lines().next().and_then(|line| {
+            line.strip_prefix("QV_TAG").and_then(|rest| rest.split_whitespace().next())
+        });
+        if (bytes.len() as u64) < end - start || stored_tag != Some(tag) {
+            let detail = format!("the entry at byte {} changed after the archive was opened", start);
+            return Ok(result(EntryStatus::Stale, None, Some(detail)));
         }
-        let filename = filename.as_ref().to_path_buf();
-        let tags = Self::read_tags(&filename)?;
-        Ok(Self {
-            filename,
-            mode: mode.to_string(),
-            tags,
+        self.record_access("try_get", tag)?;
+        let lines = Some(payload_lines(&block));
+        Ok(match check_block(&block) {
+            Integrity::Mismatch { stored, actual } => {
+                let detail = format!("payload digest {} does not match the stored {}", actual, stored);
+                result(EntryStatus::Corrupt, lines, Some(detail))
+            }
+            Integrity::Ok | Integrity::Unhashed | Integrity::NotStructure { .. } => {
+                result(EntryStatus::Found, lines, None)
+            }
         })
     }
 
-    fn read_tags(filename: &Path) -> Result<Vec<String>, QuiverError> {
-        if !filename.exists() {
-            return Ok(vec![]);
+    /// Check stored entries against their `QV_HASH` lines (see
+    /// [`integrity`]), to find structures truncated or corrupted by an
+    /// interrupted job or a partial network-filesystem write.
+    ///
+    /// Entries whose checksum holds are also sniffed for coordinates, and
+    /// reported as [`Integrity::NotStructure`] when they have none, so junk
+    /// ingested by a loose glob is caught too.
+    ///
+    /// Checks `tag`, or every entry when it is `None`; results are in file
+    /// order.
+    pub fn verify(&self, tag: Option<&str>) -> Result<Vec<(String, Integrity)>, QuiverError> {
+        self.check_readable()?;
+        let indices: Vec<usize> = match tag {
+            Some(tag) => match self.tags.iter().position(|t| t == tag) {
+                Some(index) => vec![index],
+                None => return Err(QuiverError::TagNotFound(tag.to_string())),
+            },
+            None => (0..self.tags.len()).collect(),
+        };
+        let mut results = Vec::with_capacity(indices.len());
+        for index in indices {
+            results.push(self.verify_entry(index)?);
         }
-        let file = File::open(filename)?;
-        let reader = BufReader::new(file);
-        let tags = reader
-            .lines()
-            .filter_map(|line| {
-                line.ok().and_then(|l| {
-                    if l.starts_with("QV_TAG") {
-                        l.split_whitespace().nth(1).map(|s| s.to_string())
-                    } else {
-                        None
-                    }
-                })
-            })
-            .collect();
-        Ok(tags)
+        Ok(results)
     }
 
-    pub fn get_tags(&self) -> Vec<String> {
-        self.tags.clone()
+    /// Check every entry like [`Quiver::verify`], with `threads` threads
+    /// each reading entries through their own cursor. Results are in file
+    /// order.
+    ///
+    /// With `fail_fast`, the threads stop once an entry does not match its
+    /// checksum, and only the entries checked by then are returned.
+    pub fn verify_parallel(&self, threads: usize, fail_fast: bool) -> Result<Vec<(String, Integrity)>, QuiverError> {
+        type Checked = Vec<(usize, (String, Integrity))>;
+        self.check_readable()?;
+        let next = AtomicUsize::new(0);
+        let failed = AtomicBool::new(false);
+        let mut checked = thread::scope(|s| {
+            let workers: Vec<_> = (0..threads.max(1))
+                .map(|_| {
+                    s.spawn(|| -> Result<Checked, QuiverError> {
+                        let mut checked = Vec::new();
+                        while !(fail_fast && failed.load(AtomicOrdering::Relaxed)) {
+                            let index = next.fetch_add(1, AtomicOrdering::Relaxed);
+                            if index >= self.tags.len() {
+                                break;
+                            }
+                            let result = self.verify_entry(index)?;
+                            if matches!(result.1, Integrity::Mismatch { .. }) {
+                                failed.store(true, AtomicOrdering::Relaxed);
+                            }
+                            checked.push((index, result));
+                        }
+                        Ok(checked)
+                    })
+                })
+                .collect();
+            let mut checked = Vec::new();
+            for worker in workers {
+                let worker = worker
+                    .join()
+                    .map_err(|_| QuiverError::InvalidOperation("A verify thread panicked".to_string()))?;
+                checked.extend(worker?);
+            }
+            Ok::<_, QuiverError>(checked)
+        })?;
+        checked.sort_by_key(|(index, _)| *index);
+        Ok(checked.into_iter().map(|(_, result)| result).collect())
     }
 
-    pub fn size(&self) -> usize {
-        self.tags.len()
+    /// Check the `index`-th entry against its `QV_HASH` line.
+    fn verify_entry(&self, index: usize) -> Result<(String, Integrity), QuiverError> {
+        let bytes = self.entry_bytes(index)?;
+        let tag = &self.tags[index];
+        self.record_access("verify", tag)?;
+        let block = String::from_utf8_lossy(&bytes);
+        let integrity = match check_block(&block) {
+            Integrity::Mismatch { stored, actual } => Integrity::Mismatch { stored, actual },
+            integrity => match sniff_structure(self.formats[index], &payload_lines(&block)) {
+                Ok(()) => integrity,
+                Err(reason) => Integrity::NotStructure { reason },
+            },
+        };
+        Ok((tag.clone(), integrity))
     }
 
-    pub fn add_pdb(
-        &mut self,
-        pdb_lines: &[String],
-        tag: &str,
-        score_str: Option<&str>,
-    ) -> Result<(), QuiverError> {
-        if self.mode != "w" {
-            return Err(QuiverError::InvalidOperation(
-                "Quiver file must be opened in write mode to allow for writing.".to_string(),
-            ));
+    /// Atom, residue and chain counts and size of the structure `tag`, or
+    /// summed over every entry, reading each entry once.
+    pub fn stats(&self, tag: Option<&str>) -> Result<StructureStats, QuiverError> {
+        if let Some(tag) = tag {
+            return Ok(StructureStats::of_lines(&self.get_lines(tag)?));
         }
-        if self.tags.contains(&tag.to_string()) {
-            return Err(QuiverError::DuplicateTag(tag.to_string()));
+        self.check_readable()?;
+        let mut total = StructureStats::default();
+        for (index, tag) in self.tags.iter().enumerate() {
+            let bytes = self.entry_bytes(index)?;
+            self.record_access("stats", tag)?;
+            total.add(&StructureStats::of_lines(&payload_lines(&String::from_utf8_lossy(&bytes))));
         }
+        Ok(total)
+    }
 
-        let mut file = OpenOptions::new()
-            .create(true)
-            .append(true)
-            .open(&self.filename)?;
-
-        writeln!(file, "QV_TAG {}", tag)?;
-        if let Some(score) = score_str {
-            writeln!(file, "QV_SCORE {} {}", tag, score)?;
+    /// Read and parse one stored PDB structure (see [`pdb::Structure`]).
+    ///
+    /// mmCIF entries are refused rather than parsed as empty structures.
+    pub fn get_structure(&self, tag: &str) -> Result<Structure, QuiverError> {
+        if self.get_format(tag)? != PayloadFormat::Pdb {
+            return Err(QuiverError::InvalidOperation(format!("{} is not stored as PDB", tag)));
         }
-        for line in pdb_lines {
-            file.write_all(line.as_bytes())?;
-            if !line.ends_with('\n') {
-                file.write_all(b"\n")?;
-            }
+        Ok(Structure::from_lines(&self.get_lines(tag)?))
+    }
+
+    /// The header records (`HEADER`, `COMPND`, `SEQRES`, ..., see
+    /// [`pdb::HEADER_RECORDS`]) stored with `tag`, in file order. Empty for
+    /// mmCIF entries.
+    pub fn get_header_records(&self, tag: &str) -> Result<Vec<String>, QuiverError> {
+        if self.get_format(tag)? != PayloadFormat::Pdb {
+            return Ok(Vec::new());
         }
-        self.tags.push(tag.to_string());
-        Ok(())
+        Ok(pdb::header_records(&self.get_lines(tag)?))
     }
 
-    pub fn get_pdblines(&self, tag: &str) -> Result<Vec<String>, QuiverError> {
-        if self.mode != "r" {
-            return Err(QuiverError::InvalidOperation(
-                "Quiver file must be opened in read mode to allow for reading.".to_string(),
-            ));
+    /// RMSD in Å between two stored structures after Kabsch superposition.
+    ///
+    /// `atom_selection` is `"all"` or a comma-separated list of atom names
+    /// (`"CA"`, `"N,CA,C,O"`); the selected ATOM records of both structures
+    /// are paired in file order, so they must select the same number of
+    /// atoms. HETATM records are never used.
+    pub fn rmsd(&self, tag_a: &str, tag_b: &str, atom_selection: &str) -> Result<f64, QuiverError> {
+        let names: Option<Vec<&str>> =
+            (atom_selection != "all").then(|| atom_selection.split(',').map(str::trim).collect());
+        let coords = |tag: &str| -> Result<Vec<[f64; 3]>, QuiverError> {
+            Ok(self
+                .get_structure(tag)?
+                .atoms()
+                .filter(|atom| !atom.hetatm)
+                .filter(|atom| names.as_ref().is_none_or(|n| n.contains(&atom.name.as_str())))
+                .map(|atom| atom.coord)
+                .collect())
+        };
+        let (a, b) = (coords(tag_a)?, coords(tag_b)?);
+        if a.len() != b.len() {
+            return Err(QuiverError::InvalidOperation(format!(
+                "{} has {} {} atoms but {} has {}",
+                tag_a,
+                a.len(),
+                atom_selection,
+                tag_b,
+                b.len()
+            )));
         }
-        let file = File::open(&self.filename)?;
-        let reader = BufReader::new(file);
-        let mut found = false;
-        let mut pdb_lines = Vec::new();
+        kabsch_rmsd(&a, &b)
+            .ok_or_else(|| QuiverError::InvalidOperation(format!("No {} atoms in {}", atom_selection, tag_a)))
+    }
 
+    /// Read every `QV_META` line in file order as `(tag, fields)` pairs.
+    pub fn get_meta_records(&self) -> Result<Vec<(String, MetaFields)>, QuiverError> {
+        self.check_readable()?;
+        let reader = self.reader()?;
+        let mut records = Vec::new();
         for line in reader.lines() {
             let line = line?;
-            if line.starts_with("QV_TAG") {
-                let current_tag = line.split_whitespace().nth(1).unwrap_or("");
-                if current_tag == tag {
-                    found = true;
-                    continue;
-                } else if found {
-                    break;
-                }
+            if let Some(record) = parse_meta_line(&line) {
+                records.push(record);
+            }
+        }
+        Ok(records)
+    }
+
+    /// Read every `QV_SCORE` line in file order as `(tag, fields)` pairs.
+    ///
+    /// Values are kept as the raw strings stored in the file; repeated keys
+    /// are resolved by the duplicate policy (see
+    /// [`Quiver::set_duplicate_policy`]).
+    pub fn get_score_records(&self) -> Result<Vec<(String, MetaFields)>, QuiverError> {
+        self.check_readable()?;
+        let mut reader = self.reader()?;
+        let mut location = Location::new(self.storage.name());
+        let mut records = Vec::new();
+        let mut buf = Vec::new();
+        loop {
+            buf.clear();
+            if reader.read_until(b'\n', &mut buf)? == 0 {
+                break;
             }
-            if found && !line.starts_with("QV_SCORE") {
-                pdb_lines.push(line);
+            location.check(&buf)?;
+            if !buf.starts_with(b"QV_SCORE") {
+                continue;
+            }
+            let line = decode_line(&buf, &self.legacy_bytes).map_err(|e| location.malformed(&e))?;
+            let well_formed = line
+                .split_whitespace()
+                .nth(2)
+                .is_some_and(|body| body.split('|').all(|kv| kv.contains('=')));
+            if !well_formed {
+                return Err(location.malformed("malformed QV_SCORE"));
+            }
+            if let Some((tag, mut fields)) = parse_score_line(&line) {
+                self.resolve_score_fields(&tag, &mut fields)
+                    .map_err(|e| location.malformed(&e))?;
+                records.push((tag, fields));
             }
         }
-        if !found {
-            return Err(QuiverError::TagNotFound(tag.to_string()));
+        Ok(records)
+    }
+
+    /// Tags whose numeric `key` score lies within `min..=max`, in file order.
+    ///
+    /// Either bound may be left open. `key` may also be an expression (see
+    /// [`ScoreKey`]). Entries without the key, or whose value is not a number
+    /// (or is `NaN`), never match.
+    pub fn filter_by_score(&self, key: &str, min: Option<f64>, max: Option<f64>) -> Result<Vec<String>, QuiverError> {
+        let key = ScoreKey::new(key);
+        Ok(self
+            .get_score_records()?
+            .into_iter()
+            .filter(|(_, fields)| {
+                key.number(&score_map(fields))
+                    .is_some_and(|v| min.is_none_or(|lo| v >= lo) && max.is_none_or(|hi| v <= hi))
+            })
+            .map(|(tag, _)| tag)
+            .collect())
+    }
+
+    /// Tags whose scores satisfy `expr` (see [`expr::Expr::matches`]), in
+    /// file order. Entries without a `QV_SCORE` line are matched against no
+    /// fields, so `not has(ddg)` selects them.
+    pub fn filter_by_expr(&self, expr: &Expr) -> Result<Vec<String>, QuiverError> {
+        let scores: HashMap<String, MetaFields> = self.get_score_records()?.into_iter().collect();
+        let mut tags = Vec::new();
+        for tag in &self.tags {
+            let fields = scores.get(tag).map(|fields| score_map(fields)).unwrap_or_default();
+            let matched = expr
+                .matches(&fields)
+                .map_err(|e| QuiverError::InvalidOperation(format!("Tag {}: {}", tag, e)))?;
+            if matched {
+                tags.push(tag.clone());
+            }
+        }
+        Ok(tags)
+    }
+
+    /// All tags ordered by their `key` score, reading the archive once.
+    ///
+    /// Values compare by type as in [`rank::rank`]; ties keep file order.
+    /// Entries without the key (or with `NaN`) are placed by `missing`.
+    pub fn sort_tags(&self, key: &str, descending: bool, missing: Missing) -> Result<Vec<String>, QuiverError> {
+        let scores = self.get_score_records()?;
+        let key = RankKey::new(key, if descending { Order::Desc } else { Order::Asc });
+        let (present, absent): (Vec<_>, Vec<_>) = rank(&self.get_tags(), &scores, &[key])
+            .into_iter()
+            .map(|entry| (entry.values[0].is_some(), entry.tag))
+            .partition(|(present, _)| *present);
+        let present = present.into_iter().map(|(_, tag)| tag);
+        let absent = absent.into_iter().map(|(_, tag)| tag);
+        Ok(match missing {
+            Missing::First => absent.chain(present).collect(),
+            Missing::Last => present.chain(absent).collect(),
+            Missing::Drop => present.collect(),
+        })
+    }
+
+    /// All tags in ranking order by `key`, taken from a priority queue built
+    /// from one pass over the score lines (see [`rank::BestFirst`]).
+    ///
+    /// Entries are then read through their offsets in that order, so an
+    /// extraction cut short still has the best entries.
+    pub fn best_first(&self, key: &RankKey) -> Result<rank::BestFirst, QuiverError> {
+        let scores = self.get_score_records()?;
+        Ok(rank::best_first(&self.tags, &scores, key))
+    }
+
+    /// The `n` best entries by `key`, in ranking order, reading the archive
+    /// once and holding at most `n` entries.
+    ///
+    /// Ties keep file order. Entries without the key (or with `NaN`) are
+    /// never selected, so fewer than `n` may be returned.
+    pub fn top_n(&self, key: &RankKey, n: usize) -> Result<Vec<Entry>, QuiverError> {
+        let mut best: Vec<(ScoreValue, Entry)> = Vec::new();
+        if n == 0 {
+            return Ok(Vec::new());
+        }
+        for entry in self.iter()? {
+            let entry = entry?;
+            let Some(score) = &entry.score else {
+                continue;
+            };
+            let mut fields: MetaFields = score
+                .split('|')
+                .filter_map(|kv| kv.split_once('='))
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect();
+            self.resolve_score_fields(&entry.tag, &mut fields)
+                .map_err(|e| QuiverError::InvalidOperation(format!("Tag {}: {}", entry.tag, e)))?;
+            let Some(value) = key.value(&fields) else {
+                continue;
+            };
+            // After every entry ranking no worse, so ties keep file order
+            let pos = best.partition_point(|(v, _)| key.compare(v, &value) != Ordering::Greater);
+            if pos < n {
+                best.insert(pos, (value, entry));
+                best.truncate(n);
+            }
+        }
+        Ok(best.into_iter().map(|(_, entry)| entry).collect())
+    }
+
+    /// Iterate over every entry in file order, reading the archive once.
+    ///
+    /// Each item carries the tag, score string and payload lines. Prefer this
+    /// to calling [`Quiver::get_lines`] for every tag when visiting the whole
+    /// archive. Lines before the first `QV_TAG` are skipped.
+    pub fn iter(&self) -> Result<Entries<'_>, QuiverError> {
+        self.check_readable()?;
+        Ok(Entries {
+            lines: self.reader()?.lines(),
+            current: None,
+            done: false,
+            access: self
+                .access
+                .as_deref()
+                .map(|hook| (hook, self.storage.name().unwrap_or_else(|| "-".to_string()))),
+        })
+    }
+
+    /// Stream every entry in file order, calling `f` once per structure.
+    pub fn scan_entries<F>(&self, mut f: F) -> Result<(), QuiverError>
+    where
+        F: FnMut(Entry) -> Result<(), QuiverError>,
+    {
+        for entry in self.iter()? {
+            f(entry?)?;
         }
-        Ok(pdb_lines)
+        Ok(())
+    }
+
+    /// The tags matching `pattern`, in file order and each once.
+    pub fn tags_matching(&self, pattern: &TagPattern) -> Vec<String> {
+        let mut seen = HashSet::new();
+        self.tags
+            .iter()
+            .filter(|tag| pattern.matches(tag) && seen.insert(tag.as_str()))
+            .cloned()
+            .collect()
+    }
+
+    /// Like [`Quiver::get_struct_list`], for every tag matching `pattern`
+    /// (see [`select`]) instead of a list of tags.
+    pub fn get_struct_list_matching(&self, pattern: &TagPattern) -> Result<(String, Vec<String>), QuiverError> {
+        self.check_readable()?;
+        self.get_struct_list(&self.tags_matching(pattern))
     }
 
     pub fn get_struct_list(
         &self,
         tag_list: &[String],
     ) -> Result<(String, Vec<String>), QuiverError> {
-        if self.mode != "r" {
-            return Err(QuiverError::InvalidOperation(
-                "Quiver file must be opened in read mode to allow for reading.".to_string(),
-            ));
-        }
+        self.check_readable()?;
         let tag_set: HashSet<_> = tag_list.iter().cloned().collect();
         let mut found_tags = Vec::new();
-        let mut struct_lines = String::new();
+        let mut struct_lines = format_header_lines(&self.header);
         let mut write_mode = false;
+        if tag_list.is_empty() {
+            return Ok((struct_lines, found_tags));
+        }
 
-        let file = File::open(&self.filename)?;
-        let reader = BufReader::new(file);
+        let reader = self.reader()?;
 
         for line in reader.lines() {
             let line = line?;
@@ -162,45 +2304,221 @@ impl Quiver {
                     found_tags.push(current_tag);
                 }
             }
-            if write_mode {
+            if write_mode && !line.starts_with("QV_HEADER") {
                 struct_lines.push_str(&line);
                 struct_lines.push('\n');
             }
         }
+        for tag in &found_tags {
+            self.record_access("get_struct_list", tag)?;
+        }
         Ok((struct_lines, found_tags))
     }
 
+    /// Slice the given tags into a new Quiver stream.
+    ///
+    /// Missing tags and other problems are reported in the result rather than
+    /// mixed into the data. With `strict`, any missing tag is an error and no
+    /// data is produced.
+    pub fn slice(&self, tag_list: &[String], strict: bool) -> Result<SliceResult, QuiverError> {
+        let present: HashSet<&str> = self.tags.iter().map(|t| t.as_str()).collect();
+        let mut seen = HashSet::new();
+        let mut missing_tags = Vec::new();
+        let mut warnings = Warnings::new();
+        for tag in tag_list {
+            if !seen.insert(tag.as_str()) {
+                warnings.push(
+                    WarningCode::DuplicateRequest,
+                    format!("Tag {} was requested more than once", tag),
+                );
+            } else if !present.contains(tag.as_str()) {
+                warnings.push(WarningCode::MissingTag, format!("Tag not found in Quiver file: {}", tag));
+                missing_tags.push(tag.clone());
+            }
+        }
+        if strict && !missing_tags.is_empty() {
+            return Err(QuiverError::TagNotFound(missing_tags.join(", ")));
+        }
+
+        let (data, found_tags) = self.get_struct_list(tag_list)?;
+        let mut counted = HashSet::new();
+        for tag in &found_tags {
+            if !counted.insert(tag.as_str()) {
+                warnings.push(
+                    WarningCode::DuplicateTag,
+                    format!("Tag {} appears more than once in the file", tag),
+                );
+            }
+        }
+        Ok(SliceResult {
+            data,
+            found_tags,
+            missing_tags,
+            warnings,
+        })
+    }
+
+    /// Write the entries into `<outdir>/<prefix>_<n>.qv` files of at most
+    /// `ntags` entries each, every one carrying the source header.
+    ///
+    /// With `label`, only entries carrying it are written. A manifest of
+    /// which shard holds each tag is written to
+    /// `<outdir>/<prefix>_manifest.tsv` for [`shard::unsplit`].
+    ///
+    /// Returns the number of shards written; an archive without (matching)
+    /// entries writes none, and no manifest.
     pub fn split(
         &self,
         ntags: usize,
         outdir: &str,
         prefix: &str,
-    ) -> Result<(), QuiverError> {
-        if self.mode != "r" {
+        label: Option<&str>,
+    ) -> Result<usize, QuiverError> {
+        self.check_readable()?;
+        if ntags == 0 {
             return Err(QuiverError::InvalidOperation(
-                "Quiver file must be opened in read mode to allow for reading.".to_string(),
+                "Cannot split into chunks of 0 tags".to_string(),
             ));
         }
+        let mut tag_count = 0usize;
+        self.split_where(outdir, prefix, label, |_| {
+            let starts = tag_count % ntags == 0;
+            tag_count += 1;
+            Ok(starts)
+        })
+    }
+
+    /// Like [`Quiver::split`], but starting a new shard whenever the next
+    /// entry would take the current one past `max_bytes`, counting the
+    /// header and the entries as stored in text form. An entry is never
+    /// split across shards, so one larger than `max_bytes` gets a shard of
+    /// its own.
+    pub fn split_by_size(
+        &self,
+        max_bytes: u64,
+        outdir: &str,
+        prefix: &str,
+        label: Option<&str>,
+    ) -> Result<usize, QuiverError> {
+        self.check_readable()?;
+        if max_bytes == 0 {
+            return Err(QuiverError::InvalidOperation(
+                "Cannot split into shards of 0 bytes".to_string(),
+            ));
+        }
+        let header = format_header_lines(&self.header).len() as u64;
+        // Bytes of the shard being written, `None` before the first
+        let mut shard_bytes: Option<u64> = None;
+        self.split_where(outdir, prefix, label, |index| {
+            let (start, end) = self.entry_range(index)?;
+            let size = end - start;
+            let (starts, base) = match shard_bytes {
+                Some(bytes) if bytes + size <= max_bytes => (false, bytes),
+                _ => (true, header),
+            };
+            shard_bytes = Some(base + size);
+            Ok(starts)
+        })
+    }
+
+    /// Like [`Quiver::split`], but dealing the entries out to `nshards`
+    /// shards in turn (entry 0 to shard 0, entry 1 to shard 1, ..., entry
+    /// `nshards` back to shard 0), so early and late entries are spread
+    /// evenly over the shards, as for balancing array jobs. `qvunsplit`
+    /// checks the shards against the manifest but merges them shard by
+    /// shard, not back in source order.
+    pub fn split_round_robin(
+        &self,
+        nshards: usize,
+        outdir: &str,
+        prefix: &str,
+        label: Option<&str>,
+    ) -> Result<usize, QuiverError> {
+        self.check_readable()?;
+        if nshards == 0 {
+            return Err(QuiverError::InvalidOperation(
+                "Cannot split into 0 shards".to_string(),
+            ));
+        }
+        let mut dealt = 0usize;
+        let written = self.split_among(outdir, prefix, label, |_| {
+            let shard = dealt % nshards;
+            dealt += 1;
+            Ok(shard.to_string())
+        })?;
+        Ok(written.len())
+    }
+
+    /// Write the selected entries into `<outdir>/<prefix>_<bucket>.qv` files
+    /// by the value of one score, see [`ScoreBuckets`], plus the manifest.
+    /// Only buckets that receive an entry get a file. Returns each bucket written with its
+    /// number of entries, in order of first appearance.
+    pub fn split_by_score(
+        &self,
+        buckets: &ScoreBuckets,
+        outdir: &str,
+        prefix: &str,
+        label: Option<&str>,
+    ) -> Result<Vec<(String, usize)>, QuiverError> {
+        self.check_readable()?;
+        let values: HashMap<String, Option<f64>> = self
+            .get_score_records()?
+            .into_iter()
+            .map(|(tag, fields)| {
+                let value = buckets.key.number(&score_map(&fields));
+                (tag, value)
+            })
+            .collect();
+        self.split_among(outdir, prefix, label, |index| {
+            let value = values.get(&self.tags[index]).copied().flatten();
+            Ok(buckets.bucket_of(value))
+        })
+    }
+
+    /// Write the selected entries into numbered shards, starting a new one
+    /// whenever `starts_shard` says so for the index of the next entry.
+    fn split_where<F>(
+        &self,
+        outdir: &str,
+        prefix: &str,
+        label: Option<&str>,
+        mut starts_shard: F,
+    ) -> Result<usize, QuiverError>
+    where
+        F: FnMut(usize) -> Result<bool, QuiverError>,
+    {
         fs::create_dir_all(outdir)?;
         let mut file_idx = 0usize;
-        let mut tag_count = 0usize;
+        let mut entry_idx = 0usize;
+        let mut selected = false;
         let mut out_file: Option<BufWriter<File>> = None;
+        let mut manifest = String::new();
 
-        let file = File::open(&self.filename)?;
-        let reader = BufReader::new(file);
+        let reader = self.reader()?;
 
         for line in reader.lines() {
             let line = line?;
             if line.starts_with("QV_TAG") {
-                if tag_count % ntags == 0 {
+                selected = label.is_none_or(|l| self.labels[entry_idx].iter().any(|have| have == l));
+                entry_idx += 1;
+                if !selected {
+                    continue;
+                }
+                let tag = line.split_whitespace().nth(1).unwrap_or("");
+                self.record_access("split", tag)?;
+                if starts_shard(entry_idx - 1)? || out_file.is_none() {
                     if let Some(mut f) = out_file.take() {
                         f.flush()?;
                     }
                     let out_path = Path::new(outdir).join(format!("{}_{}.qv", prefix, file_idx));
-                    out_file = Some(BufWriter::new(File::create(out_path)?));
+                    let mut f = BufWriter::new(File::create(out_path)?);
+                    f.write_all(format_header_lines(&self.header).as_bytes())?;
+                    out_file = Some(f);
                     file_idx += 1;
                 }
-                tag_count += 1;
+                manifest.push_str(&format!("{}_{}.qv\t{}\n", prefix, file_idx - 1, tag));
+            } else if line.starts_with("QV_HEADER") || !selected {
+                continue;
             }
             if let Some(f) = out_file.as_mut() {
                 writeln!(f, "{}", line)?;
@@ -208,7 +2526,440 @@ impl Quiver {
         }
         if let Some(mut f) = out_file {
             f.flush()?;
+            fs::write(Path::new(outdir).join(shard::manifest_name(prefix)), manifest)?;
         }
-        Ok(())
+        Ok(file_idx)
+    }
+
+    /// Write the selected entries into `<outdir>/<prefix>_<name>.qv` files,
+    /// where `shard_of` names the file for the index of each entry, and the
+    /// manifest as [`Quiver::split`] does. All files stay open until the
+    /// end, so the names should be few.
+    fn split_among<F>(
+        &self,
+        outdir: &str,
+        prefix: &str,
+        label: Option<&str>,
+        mut shard_of: F,
+    ) -> Result<Vec<(String, usize)>, QuiverError>
+    where
+        F: FnMut(usize) -> Result<String, QuiverError>,
+    {
+        fs::create_dir_all(outdir)?;
+        let mut shards: Vec<(String, usize, BufWriter<File>)> = Vec::new();
+        let mut by_name: HashMap<String, usize> = HashMap::new();
+        let mut current: Option<usize> = None;
+        let mut entry_idx = 0usize;
+        let mut manifest = String::new();
+
+        let reader = self.reader()?;
+
+        for line in reader.lines() {
+            let line = line?;
+            if line.starts_with("QV_TAG") {
+                let selected = label.is_none_or(|l| self.labels[entry_idx].iter().any(|have| have == l));
+                entry_idx += 1;
+                current = None;
+                if !selected {
+                    continue;
+                }
+                let tag = line.split_whitespace().nth(1).unwrap_or("");
+                self.record_access("split", tag)?;
+                let name = shard_of(entry_idx - 1)?;
+                let shard = match by_name.get(&name) {
+                    Some(&shard) => shard,
+                    None => {
+                        let out_path = Path::new(outdir).join(format!("{}_{}.qv", prefix, name));
+                        let mut f = BufWriter::new(File::create(out_path)?);
+                        f.write_all(format_header_lines(&self.header).as_bytes())?;
+                        by_name.insert(name.clone(), shards.len());
+                        shards.push((name, 0, f));
+                        shards.len() - 1
+                    }
+                };
+                shards[shard].1 += 1;
+                current = Some(shard);
+                manifest.push_str(&format!("{}_{}.qv\t{}\n", prefix, shards[shard].0, tag));
+            } else if line.starts_with("QV_HEADER") {
+                continue;
+            }
+            if let Some(shard) = current {
+                writeln!(shards[shard].2, "{}", line)?;
+            }
+        }
+        let mut written = Vec::with_capacity(shards.len());
+        for (name, count, mut f) in shards {
+            f.flush()?;
+            written.push((name, count));
+        }
+        if !written.is_empty() {
+            fs::write(Path::new(outdir).join(shard::manifest_name(prefix)), manifest)?;
+        }
+        Ok(written)
+    }
+}
+
+impl Drop for Quiver {
+    /// Errors are lost here; call [`Quiver::flush`] to see them.
+    fn drop(&mut self) {
+        let _ = self.storage.flush();
+    }
+}
+
+/// The storage backend for a local archive: compressed, v2 or plain text,
+/// detected from the file.
+fn open_storage(path: &Path, mode: &str) -> Result<Box<dyn Storage>, QuiverError> {
+    if let Some(codec) = Compression::detect(path)? {
+        return Ok(Box::new(CompressedFile::new(path, codec, mode == "r")?));
+    }
+    if format_v2::detect(path)? {
+        return Ok(Box::new(V2File::new(path, mode == "r")?));
+    }
+    Ok(match mode {
+        "r" => Box::new(LocalFile::pinned(path)?),
+        "a" => Box::new(LocalFile::appending(path)?),
+        "rw" => Box::new(LocalFile::read_write(path)?),
+        _ => Box::new(LocalFile::buffered(path)),
+    })
+}
+
+/// The payload lines of a raw entry block: everything after its `QV_TAG`
+/// line except the other `QV_*` records.
+fn payload_lines(block: &str) -> Vec<String> {
+    block
+        .lines()
+        .skip(1)
+        .filter(|line| {
+            !line.starts_with("QV_SCORE")
+                && !line.starts_with("QV_META")
+                && !line.starts_with("QV_HASH")
+                && !line.starts_with("QV_HEADER")
+        })
+        .map(|line| line.to_string())
+        .collect()
+}
+
+/// Write the tags of the archive at `path` to `out`, one per line, in file
+/// order, without opening a [`Quiver`] handle.
+///
+/// Only `chunk_size` tags are held at a time, so this works for archives
+/// whose tag list alone does not fit in memory. Returns the number of tags.
+pub fn dump_tags<P: AsRef<Path>, W: Write>(path: P, mut out: W, chunk_size: usize) -> Result<u64, QuiverError> {
+    let storage = open_storage(path.as_ref(), "r")?;
+    let chunk_size = chunk_size.max(1);
+    let mut chunk = String::new();
+    let mut in_chunk = 0usize;
+    let mut count = 0u64;
+    for line in BufReader::new(storage.reader()?).lines() {
+        let line = line?;
+        let Some(rest) = line.strip_prefix("QV_TAG") else {
+            continue;
+        };
+        if let Some(tag) = rest.split_whitespace().next() {
+            chunk.push_str(tag);
+            chunk.push('\n');
+            in_chunk += 1;
+            count += 1;
+        }
+        if in_chunk == chunk_size {
+            out.write_all(chunk.as_bytes())?;
+            chunk.clear();
+            in_chunk = 0;
+        }
+    }
+    out.write_all(chunk.as_bytes())?;
+    out.flush()?;
+    Ok(count)
+}
+
+/// `QV_META` field holding an entry's comma-separated labels.
+pub const LABELS_FIELD: &str = "labels";
+
+/// `QV_HEADER` field recording the generation of an archive when it was
+/// last rewritten, see [`Quiver::generation`].
+pub const GENERATION_FIELD: &str = "generation";
+
+/// `QV_HEADER` field recording the number of entries an archive had when
+/// it was last rewritten; entries after them were appended since.
+pub const GENERATION_ENTRIES_FIELD: &str = "generation_entries";
+
+/// Whether `line` is a [`GENERATION_FIELD`] or [`GENERATION_ENTRIES_FIELD`]
+/// header line, which rewrites replace rather than copy.
+fn is_generation_line(line: &[u8]) -> bool {
+    parse_header_line(&String::from_utf8_lossy(line))
+        .is_some_and(|(key, _)| key == GENERATION_FIELD || key == GENERATION_ENTRIES_FIELD)
+}
+
+/// The labels stored in an entry's metadata.
+pub fn labels_of(meta: &[(String, String)]) -> Vec<String> {
+    meta.iter()
+        .filter(|(k, _)| k == LABELS_FIELD)
+        .flat_map(|(_, v)| v.split(','))
+        .filter(|l| !l.is_empty())
+        .map(|l| l.to_string())
+        .collect()
+}
+
+/// Labels are stored inside `QV_META` fields, so they cannot contain the
+/// field separators or whitespace.
+fn check_label(label: &str) -> Result<(), QuiverError> {
+    if label.is_empty() || label.contains(|c: char| c.is_whitespace() || matches!(c, ',' | '|' | '=')) {
+        return Err(QuiverError::InvalidOperation(format!(
+            "Invalid label '{}': labels must be non-empty and cannot contain whitespace, ',', '|' or '='",
+            label
+        )));
+    }
+    Ok(())
+}
+
+/// Score and metadata lines are `key=value|key=value`, so neither keys nor
+/// values can contain the separators or whitespace. `kind` ("score" or
+/// "metadata") names the line in the error.
+fn check_field(kind: &str, field: &str) -> Result<(), QuiverError> {
+    if field.is_empty() || field.contains(|c: char| c.is_whitespace() || matches!(c, '|' | '=')) {
+        return Err(QuiverError::InvalidOperation(format!(
+            "Invalid {} field '{}': names and values must be non-empty and cannot contain whitespace, '|' or '='",
+            kind, field
+        )));
+    }
+    Ok(())
+}
+
+/// Format a `QV_META <tag> key=value|key=value` line (without trailing newline).
+pub fn format_meta_line(tag: &str, fields: &[(String, String)]) -> String {
+    let body: Vec<String> = fields.iter().map(|(k, v)| format!("{}={}", k, v)).collect();
+    format!("QV_META {} {}", tag, body.join("|"))
+}
+
+/// Parse a `QV_META` line into its tag and key/value fields.
+///
+/// Returns `None` if the line is not a metadata line.
+pub fn parse_meta_line(line: &str) -> Option<(String, MetaFields)> {
+    let rest = line.strip_prefix("QV_META ")?;
+    let (tag, body) = rest.trim_end().split_once(' ').unwrap_or((rest.trim_end(), ""));
+    let fields = body
+        .split('|')
+        .filter_map(|kv| kv.split_once('='))
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+    Some((tag.to_string(), fields))
+}
+
+/// Format a `QV_SCORE <tag> key=value|key=value` line (without trailing newline).
+pub fn format_score_line(tag: &str, fields: &[(String, String)]) -> String {
+    let body: Vec<String> = fields.iter().map(|(k, v)| format!("{}={}", k, v)).collect();
+    format!("QV_SCORE {} {}", tag, body.join("|"))
+}
+
+/// Parse a `QV_SCORE` line into its tag and key/value fields.
+///
+/// Returns `None` if the line is not a score line or has no score string.
+pub fn parse_score_line(line: &str) -> Option<(String, MetaFields)> {
+    if !line.starts_with("QV_SCORE") {
+        return None;
+    }
+    let splits: Vec<&str> = line.split_whitespace().collect();
+    if splits.len() < 3 {
+        return None;
+    }
+    let fields = splits[2]
+        .split('|')
+        .filter_map(|kv| kv.split_once('='))
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+    Some((splits[1].to_string(), fields))
+}
+
+/// Format header fields as `QV_HEADER key=value` lines, one per field.
+pub fn format_header_lines(fields: &[(String, String)]) -> String {
+    fields
+        .iter()
+        .map(|(k, v)| format!("QV_HEADER {}={}\n", k, v))
+        .collect()
+}
+
+/// Parse a `QV_HEADER key=value` line.
+pub fn parse_header_line(line: &str) -> Option<(String, String)> {
+    let (key, value) = line.strip_prefix("QV_HEADER ")?.trim_end().split_once('=')?;
+    Some((key.to_string(), value.to_string()))
+}
+
+/// Header fields describing a newly created archive. The creation time is
+/// `SOURCE_DATE_EPOCH` when that is set, see [`reproducible`].
+pub fn default_header() -> MetaFields {
+    let created = reproducible::source_date_epoch().unwrap_or_else(|| {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0)
+    });
+    vec![
+        ("creator".to_string(), format!("quiver {}", env!("CARGO_PKG_VERSION"))),
+        ("created".to_string(), created.to_string()),
+        ("format".to_string(), "qv1".to_string()),
+    ]
+}
+
+// Compile-time guarantee that handles stay usable from worker threads.
+const _: fn() = || {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<Quiver>();
+};
+
+#[cfg(test)]
+mod tests {
+    use super::testdata::scratch_dir;
+    use super::*;
+
+    fn write_archive(path: &Path, tags: &[&str]) {
+        let mut qv = Quiver::new(path, "w").unwrap();
+        for tag in tags {
+            qv.add_pdb(&[format!("ATOM {}\n", tag)], tag, None).unwrap();
+        }
+        qv.flush().unwrap();
+    }
+
+    #[test]
+    fn every_mutation_bumps_the_generation() {
+        let path = scratch_dir("generation-bumps").join("a.qv");
+        write_archive(&path, &["a", "b", "c"]);
+        let mut qv = Quiver::new(&path, "rw").unwrap();
+        assert_eq!(qv.generation(), 3);
+        qv.remove_tags(&["b".to_string()]).unwrap();
+        assert_eq!(qv.generation(), 4);
+        qv.add_pdb(&["ATOM d\n".to_string()], "d", None).unwrap();
+        assert_eq!(qv.generation(), 5);
+        qv.rename_tags(&[("a".to_string(), "z".to_string())]).unwrap();
+        assert_eq!(qv.generation(), 6);
+        drop(qv);
+        let qv = Quiver::new(&path, "r").unwrap();
+        assert_eq!(qv.generation(), 6);
+        assert_eq!(qv.get_tags(), vec!["z", "c", "d"]);
+        assert!(qv.get_header().is_empty());
+    }
+
+    #[test]
+    fn snapshot_taken_before_a_removal_is_stable() {
+        let path = scratch_dir("generation-snapshot").join("a.qv");
+        write_archive(&path, &["a", "b", "c"]);
+        let snapshot = Quiver::open_snapshot(&path, 3).unwrap();
+        let mut writer = Quiver::new(&path, "rw").unwrap();
+        writer.remove_tags(&["b".to_string()]).unwrap();
+        writer.add_pdb(&["ATOM d\n".to_string()], "d", None).unwrap();
+        writer.flush().unwrap();
+
+        assert_eq!(snapshot.generation(), 3);
+        assert_eq!(snapshot.get_tags(), vec!["a", "b", "c"]);
+        assert_eq!(snapshot.get_pdblines("b").unwrap(), vec!["ATOM b"]);
+
+        assert!(Quiver::open_snapshot(&path, 3).is_err());
+        assert!(Quiver::open_snapshot(&path, 6).is_err());
+        let after_removal = Quiver::open_snapshot(&path, 4).unwrap();
+        assert_eq!(after_removal.get_tags(), vec!["a", "c"]);
+        assert_eq!(Quiver::open_snapshot(&path, 5).unwrap().get_tags(), vec!["a", "c", "d"]);
+    }
+
+    #[test]
+    fn exit_codes_follow_the_variant() {
+        let errors = [
+            (QuiverError::InvalidOperation("x".to_string()), 1),
+            (QuiverError::DuplicateTag("x".to_string()), 1),
+            (QuiverError::InvalidMode("x".to_string()), 2),
+            (QuiverError::TagNotFound("x".to_string()), 3),
+            (QuiverError::Malformed("x".to_string()), 4),
+            (QuiverError::Io(io::Error::new(io::ErrorKind::NotFound, "x")), 5),
+        ];
+        for (error, code) in errors {
+            assert_eq!(error.exit_code(), code, "{:?}", error);
+        }
+        assert_eq!(
+            QuiverError::TagNotFound("design \"7\"".to_string()).to_json(),
+            r#"{"category": "missing_tag", "exit_code": 3, "message": "design \"7\""}"#
+        );
+    }
+
+    #[test]
+    fn score_selection_shares_the_expression_grammar() {
+        let dir = scratch_dir("score-expressions");
+        let path = dir.join("a.qv");
+        let mut qv = Quiver::new(&path, "w").unwrap();
+        for (tag, score) in [("a", Some("ddg=-5|ref_ddg=-1")), ("b", Some("ddg=-3|ref_ddg=-4")), ("c", None)] {
+            qv.add_pdb(&[format!("ATOM {}\n", tag)], tag, score).unwrap();
+        }
+        qv.flush().unwrap();
+
+        let qv = Quiver::new(&path, "r").unwrap();
+        let expr: Expr = "ddg - ref_ddg < 0 or not has(ddg)".parse().unwrap();
+        assert_eq!(qv.filter_by_expr(&expr).unwrap(), vec!["a", "c"]);
+        assert!(qv.filter_by_expr(&"ddg + 1".parse().unwrap()).is_err());
+        assert_eq!(qv.filter_by_score("ddg - ref_ddg", None, Some(0.0)).unwrap(), vec!["a"]);
+
+        let key: RankKey = "ddg - ref_ddg:desc".parse().unwrap();
+        let best: Vec<String> = qv.top_n(&key, 5).unwrap().into_iter().map(|entry| entry.tag).collect();
+        assert_eq!(best, vec!["b", "a"]);
+        assert_eq!(qv.best_first(&key).unwrap().collect::<Vec<_>>(), vec!["b", "a", "c"]);
+
+        let buckets = ScoreBuckets::new("ddg - ref_ddg", &[0.0]).unwrap();
+        let written = qv.split_by_score(&buckets, dir.join("split").to_str().unwrap(), "s", None).unwrap();
+        assert_eq!(written, vec![("lt0".to_string(), 1), ("ge0".to_string(), 1), ("missing".to_string(), 1)]);
+    }
+
+    #[test]
+    fn empty_archives_give_empty_results() {
+        let dir = scratch_dir("empty-archive");
+        for (name, text) in [("bare.qv", ""), ("header.qv", "QV_HEADER creator=test\n")] {
+            let path = dir.join(name);
+            fs::write(&path, text).unwrap();
+            let qv = Quiver::new(&path, "r").unwrap();
+            assert!(qv.get_tags().is_empty());
+            let outdir = dir.join(format!("{}.out", name));
+            let outdir = outdir.to_str().unwrap();
+            assert_eq!(qv.split(3, outdir, "split", None).unwrap(), 0);
+            assert_eq!(qv.split_round_robin(3, outdir, "stripe", None).unwrap(), 0);
+            assert_eq!(fs::read_dir(outdir).unwrap().count(), 0);
+            let (data, found) = qv.get_struct_list(&[]).unwrap();
+            assert_eq!(data, text);
+            assert!(found.is_empty());
+            let result = qv.slice(&[], true).unwrap();
+            assert!(result.found_tags.is_empty() && result.missing_tags.is_empty());
+        }
+    }
+
+    #[test]
+    fn splits_need_a_positive_size() {
+        let dir = scratch_dir("split-zero");
+        let path = dir.join("a.qv");
+        write_archive(&path, &["a", "b"]);
+        let qv = Quiver::new(&path, "r").unwrap();
+        let outdir = dir.join("out");
+        let outdir = outdir.to_str().unwrap();
+        assert!(matches!(qv.split(0, outdir, "s", None), Err(QuiverError::InvalidOperation(_))));
+        assert!(matches!(qv.split_by_size(0, outdir, "s", None), Err(QuiverError::InvalidOperation(_))));
+        assert!(matches!(qv.split_round_robin(0, outdir, "s", None), Err(QuiverError::InvalidOperation(_))));
+        assert_eq!(qv.split(1, outdir, "s", None).unwrap(), 2);
+    }
+
+    #[test]
+    fn set_score_keeps_the_value_type() {
+        let path = scratch_dir("set-score").join("a.qv");
+        write_archive(&path, &["a", "b"]);
+        let mut qv = Quiver::new(&path, "rw").unwrap();
+        qv.set_score("a", "ddg", 1.0).unwrap();
+        qv.set_score("a", "rank", 3i64).unwrap();
+        qv.set_score("a", "converged", true).unwrap();
+        qv.set_score("a", "method", "relax").unwrap();
+        assert!(qv.set_score("a", "note", "two words").is_err());
+        assert!(matches!(qv.set_score("nope", "ddg", 1.0), Err(QuiverError::TagNotFound(_))));
+        drop(qv);
+        let qv = Quiver::new(&path, "r").unwrap();
+        assert_eq!(
+            qv.get_scores("a").unwrap(),
+            vec![
+                ("ddg".to_string(), ScoreValue::Float(1.0)),
+                ("rank".to_string(), ScoreValue::Int(3)),
+                ("converged".to_string(), ScoreValue::Bool(true)),
+                ("method".to_string(), ScoreValue::Str("relax".to_string())),
+            ]
+        );
     }
 }
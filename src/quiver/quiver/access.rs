@@ -0,0 +1,126 @@
+//! Audit trail of entry access.
+//!
+//! An [`AccessHook`] set on a `Quiver` handle hears about every entry whose
+//! payload an operation hands out or writes elsewhere. [`AccessLog`] is the
+//! file-backed hook; setting `QUIVER_ACCESS_LOG` makes every handle, and so
+//! every command-line tool, log to that file.
+
+use std::env;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+use std::process;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Environment variable naming a log file every handle appends to.
+pub const ACCESS_LOG_ENV: &str = "QUIVER_ACCESS_LOG";
+
+/// Told about each entry an operation reads.
+///
+/// An error fails the operation, so no entry is handed out unrecorded.
+pub trait AccessHook: Send + Sync {
+    fn record(&self, archive: &str, operation: &str, tag: &str) -> io::Result<()>;
+}
+
+impl<F> AccessHook for F
+where
+    F: Fn(&str, &str, &str) -> io::Result<()> + Send + Sync,
+{
+    fn record(&self, archive: &str, operation: &str, tag: &str) -> io::Result<()> {
+        self(archive, operation, tag)
+    }
+}
+
+/// Appends one tab-separated line per access:
+///
+/// ```text
+/// unix_time  user  program  pid  operation  archive  tag
+/// ```
+///
+/// Each line is a single append, so several processes can share one log.
+pub struct AccessLog {
+    file: Mutex<File>,
+    user: String,
+    program: String,
+}
+
+impl AccessLog {
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let user = env::var("USER")
+            .or_else(|_| env::var("LOGNAME"))
+            .unwrap_or_else(|_| "-".to_string());
+        let program = env::args()
+            .next()
+            .and_then(|arg| Path::new(&arg).file_name().map(|n| n.to_string_lossy().to_string()))
+            .unwrap_or_else(|| "-".to_string());
+        Ok(Self {
+            file: Mutex::new(file),
+            user,
+            program,
+        })
+    }
+
+    /// The log named by [`ACCESS_LOG_ENV`], if it is set.
+    pub fn from_env() -> io::Result<Option<Self>> {
+        match env::var_os(ACCESS_LOG_ENV) {
+            Some(path) if !path.is_empty() => Self::open(path).map(Some),
+            _ => Ok(None),
+        }
+    }
+}
+
+impl AccessHook for AccessLog {
+    fn record(&self, archive: &str, operation: &str, tag: &str) -> io::Result<()> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+        let line = format!(
+            "{}.{:03}\t{}\t{}\t{}\t{}\t{}\t{}\n",
+            now.as_secs(),
+            now.subsec_millis(),
+            self.user,
+            self.program,
+            process::id(),
+            operation,
+            archive,
+            tag
+        );
+        let mut file = self.file.lock().unwrap_or_else(|e| e.into_inner());
+        file.write_all(line.as_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::testdata::scratch_dir;
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn log_appends_one_line_per_access() {
+        let path = scratch_dir("access-log").join("access.log");
+        let log = AccessLog::open(&path).unwrap();
+        log.record("a.qv", "get_pdblines", "design_1").unwrap();
+        AccessLog::open(&path).unwrap().record("b.qv", "extract", "design_2").unwrap();
+        let text = fs::read_to_string(&path).unwrap();
+        let lines: Vec<Vec<&str>> = text.lines().map(|line| line.split('\t').collect()).collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].len(), 7);
+        assert_eq!(lines[0][3], process::id().to_string());
+        assert_eq!(lines[0][4..], ["get_pdblines", "a.qv", "design_1"]);
+        assert_eq!(lines[1][4..], ["extract", "b.qv", "design_2"]);
+    }
+
+    #[test]
+    fn closures_are_hooks() {
+        let seen = Mutex::new(Vec::new());
+        let hook = |archive: &str, operation: &str, tag: &str| {
+            seen.lock().unwrap().push(format!("{} {} {}", archive, operation, tag));
+            Ok(())
+        };
+        hook.record("a.qv", "get_pdblines", "x").unwrap();
+        assert_eq!(*seen.lock().unwrap(), vec!["a.qv get_pdblines x"]);
+        let refuse = |_: &str, _: &str, _: &str| Err(io::Error::new(io::ErrorKind::PermissionDenied, "no"));
+        assert!(refuse.record("a.qv", "get_pdblines", "x").is_err());
+    }
+}
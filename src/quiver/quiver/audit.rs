@@ -0,0 +1,159 @@
+use std::collections::{HashMap, HashSet};
+
+/// Map characters that render like ASCII to their ASCII counterpart.
+///
+/// Covers fullwidth forms, common Cyrillic and Greek lookalikes, dashes and
+/// no-break spaces; zero-width characters map to `None` and are dropped.
+fn fold_confusable(c: char) -> Option<char> {
+    let folded = match c {
+        '\u{200B}' | '\u{200C}' | '\u{200D}' | '\u{2060}' | '\u{FEFF}' => return None,
+        '\u{00A0}' | '\u{2007}' | '\u{202F}' => ' ',
+        '\u{2010}' | '\u{2011}' | '\u{2012}' | '\u{2013}' | '\u{2014}' | '\u{2212}' => '-',
+        '\u{FF01}'..='\u{FF5E}' => char::from_u32(c as u32 - 0xFF01 + 0x21).unwrap_or(c),
+        'а' | 'α' => 'a',
+        'с' | 'ϲ' => 'c',
+        'е' => 'e',
+        'һ' => 'h',
+        'і' | 'ι' => 'i',
+        'ј' => 'j',
+        'ο' | 'о' => 'o',
+        'р' | 'ρ' => 'p',
+        'ѕ' => 's',
+        'х' | 'χ' => 'x',
+        'у' => 'y',
+        'А' | 'Α' => 'A',
+        'В' | 'Β' => 'B',
+        'С' => 'C',
+        'Е' | 'Ε' => 'E',
+        'Н' | 'Η' => 'H',
+        'І' | 'Ι' => 'I',
+        'К' | 'Κ' => 'K',
+        'М' | 'Μ' => 'M',
+        'Ν' => 'N',
+        'О' | 'Ο' => 'O',
+        'Р' | 'Ρ' => 'P',
+        'Т' | 'Τ' => 'T',
+        'Х' | 'Χ' => 'X',
+        'Υ' => 'Y',
+        'Ζ' => 'Z',
+        _ => c,
+    };
+    Some(folded)
+}
+
+/// The form two tags share if they differ only by case, surrounding
+/// whitespace or unicode confusables.
+pub fn canonical_tag(tag: &str) -> String {
+    tag.chars()
+        .filter_map(fold_confusable)
+        .collect::<String>()
+        .trim()
+        .to_lowercase()
+}
+
+/// Tags that collapse to the same canonical form.
+#[derive(Debug, Clone)]
+pub struct TagGroup {
+    pub canonical: String,
+    /// The look-alike tags, in file order
+    pub tags: Vec<String>,
+    /// Suggested `(old, new)` renames that make every tag in the group
+    /// distinct after canonicalization
+    pub renames: Vec<(String, String)>,
+}
+
+/// Find groups of almost-duplicate tags.
+///
+/// In each group the tag already equal to the canonical form (or else the
+/// first one) keeps its name; the others get `<canonical>_<n>` suggestions
+/// that clash with no existing tag.
+pub fn audit_tags(tags: &[String]) -> Vec<TagGroup> {
+    let mut order: Vec<String> = Vec::new();
+    let mut groups: HashMap<String, Vec<String>> = HashMap::new();
+    for tag in tags {
+        let canonical = canonical_tag(tag);
+        let members = groups.entry(canonical.clone()).or_insert_with(|| {
+            order.push(canonical);
+            Vec::new()
+        });
+        if !members.contains(tag) {
+            members.push(tag.clone());
+        }
+    }
+
+    let mut taken: HashSet<String> = tags.iter().map(|t| canonical_tag(t)).collect();
+    let mut audit = Vec::new();
+    for canonical in order {
+        let members = groups.remove(&canonical).unwrap_or_default();
+        if members.len() < 2 {
+            continue;
+        }
+        let keep = members
+            .iter()
+            .position(|t| *t == canonical)
+            .unwrap_or(0);
+        let mut renames = Vec::new();
+        let mut n = 2;
+        for (i, tag) in members.iter().enumerate() {
+            if i == keep {
+                continue;
+            }
+            let suggestion = loop {
+                let candidate = format!("{}_{}", canonical, n);
+                n += 1;
+                if taken.insert(candidate.clone()) {
+                    break candidate;
+                }
+            };
+            renames.push((tag.clone(), suggestion));
+        }
+        audit.push(TagGroup {
+            canonical,
+            tags: members,
+            renames,
+        });
+    }
+    audit
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tags(names: &[&str]) -> Vec<String> {
+        names.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn confusables_fold_to_ascii() {
+        assert_eq!(canonical_tag(" Design_1 "), "design_1");
+        // Cyrillic а and е, fullwidth digit, zero-width space
+        assert_eq!(canonical_tag("d\u{0435}sign\u{200B}_\u{FF11}"), "design_1");
+        assert_eq!(canonical_tag("a\u{2013}b"), "a-b");
+    }
+
+    #[test]
+    fn canonical_tag_keeps_its_name() {
+        let audit = audit_tags(&tags(&["Design_1", "design_1", "other", "DESIGN_1"]));
+        assert_eq!(audit.len(), 1);
+        assert_eq!(audit[0].canonical, "design_1");
+        assert_eq!(audit[0].tags, tags(&["Design_1", "design_1", "DESIGN_1"]));
+        assert_eq!(
+            audit[0].renames,
+            vec![
+                ("Design_1".to_string(), "design_1_2".to_string()),
+                ("DESIGN_1".to_string(), "design_1_3".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn suggestions_skip_existing_tags() {
+        let audit = audit_tags(&tags(&["A", "a_2", "a\u{00A0}", "b"]));
+        assert_eq!(audit.len(), 1);
+        assert_eq!(audit[0].tags, tags(&["A", "a\u{00A0}"]));
+        // "A" is kept as the first member; a_2 is taken
+        assert_eq!(audit[0].renames, vec![("a\u{00A0}".to_string(), "a_3".to_string())]);
+        assert!(audit_tags(&tags(&["a", "b", "a"])).is_empty());
+    }
+}
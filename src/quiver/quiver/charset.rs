@@ -0,0 +1,221 @@
+//! Character sets of score field names.
+//!
+//! Archives written by older tools sometimes carry score names such as
+//! `ΔG` or `énergie` encoded as Windows-1252 rather than UTF-8. On read,
+//! [`decode_line`] turns such bytes into text by a [`LegacyBytes`] policy, so
+//! every export is valid UTF-8. On write, [`normalize_field`] applies a
+//! [`FieldCharset`] policy to new field names.
+
+use std::borrow::Cow;
+use std::str::FromStr;
+
+/// Which characters new score field names may use.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FieldCharset {
+    /// Any UTF-8 text (the names are stored as given)
+    #[default]
+    Utf8,
+    /// ASCII only; other names are refused
+    Ascii,
+    /// Fold accented letters and common symbols to ASCII (`é` -> `e`,
+    /// `Δ` -> `Delta`, `°` -> `deg`) and replace anything else with `_`
+    Fold,
+}
+
+impl FromStr for FieldCharset {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "utf8" => Ok(FieldCharset::Utf8),
+            "ascii" => Ok(FieldCharset::Ascii),
+            "fold" => Ok(FieldCharset::Fold),
+            other => Err(format!(
+                "Unknown field charset '{}', expected one of: utf8, ascii, fold",
+                other
+            )),
+        }
+    }
+}
+
+/// The ASCII spelling of a non-ASCII character under [`FieldCharset::Fold`].
+fn fold_char(c: char) -> &'static str {
+    match c {
+        'À'..='Å' | '\u{212B}' => "A",
+        'à'..='å' => "a",
+        'Æ' => "AE",
+        'æ' => "ae",
+        'Ç' => "C",
+        'ç' => "c",
+        'È'..='Ë' => "E",
+        'è'..='ë' => "e",
+        'Ì'..='Ï' => "I",
+        'ì'..='ï' => "i",
+        'Ð' => "D",
+        'ð' => "d",
+        'Ñ' => "N",
+        'ñ' => "n",
+        'Ò'..='Ö' | 'Ø' => "O",
+        'ò'..='ö' | 'ø' => "o",
+        'Œ' => "OE",
+        'œ' => "oe",
+        'Š' => "S",
+        'š' => "s",
+        'Ù'..='Ü' => "U",
+        'ù'..='ü' => "u",
+        'Ý' | 'Ÿ' => "Y",
+        'ý' | 'ÿ' => "y",
+        'Ž' => "Z",
+        'ž' => "z",
+        'Þ' => "Th",
+        'þ' => "th",
+        'ß' => "ss",
+        'Δ' => "Delta",
+        'δ' => "delta",
+        'µ' | 'μ' => "u",
+        '°' => "deg",
+        '²' => "2",
+        '³' => "3",
+        '–' | '—' => "-",
+        _ => "_",
+    }
+}
+
+/// Apply `charset` to a new score field name.
+pub fn normalize_field(name: &str, charset: FieldCharset) -> Result<Cow<'_, str>, String> {
+    if name.is_ascii() || charset == FieldCharset::Utf8 {
+        return Ok(Cow::Borrowed(name));
+    }
+    match charset {
+        FieldCharset::Ascii => Err(format!(
+            "Score field '{}' is not ASCII (use the fold charset to transliterate it)",
+            name
+        )),
+        _ => Ok(Cow::Owned(
+            name.chars()
+                .map(|c| if c.is_ascii() { c.to_string() } else { fold_char(c).to_string() })
+                .collect(),
+        )),
+    }
+}
+
+/// Apply `charset` to the names of a `key=value|key=value` score string.
+pub fn normalize_score_str(score: &str, charset: FieldCharset) -> Result<Cow<'_, str>, String> {
+    if score.is_ascii() || charset == FieldCharset::Utf8 {
+        return Ok(Cow::Borrowed(score));
+    }
+    let fields: Result<Vec<String>, String> = score
+        .split('|')
+        .map(|kv| match kv.split_once('=') {
+            Some((k, v)) => Ok(format!("{}={}", normalize_field(k, charset)?, v)),
+            None => Ok(kv.to_string()),
+        })
+        .collect();
+    Ok(Cow::Owned(fields?.join("|")))
+}
+
+/// How to read bytes that are not valid UTF-8.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum LegacyBytes {
+    /// Decode them as Windows-1252, the usual legacy encoding
+    #[default]
+    Cp1252,
+    /// Replace each invalid sequence with this text (`replace` alone uses
+    /// U+FFFD)
+    Replace(String),
+    /// Refuse the line
+    Error,
+}
+
+impl FromStr for LegacyBytes {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "cp1252" => Ok(LegacyBytes::Cp1252),
+            "replace" => Ok(LegacyBytes::Replace("\u{FFFD}".to_string())),
+            "error" => Ok(LegacyBytes::Error),
+            other => match other.strip_prefix("replace=") {
+                Some(with) => Ok(LegacyBytes::Replace(with.to_string())),
+                None => Err(format!(
+                    "Unknown legacy byte policy '{}', expected one of: cp1252, replace, replace=TEXT, error",
+                    other
+                )),
+            },
+        }
+    }
+}
+
+/// Windows-1252 characters for bytes 0x80-0x9F; the five unassigned bytes
+/// map to the C1 control of the same value.
+const CP1252_HIGH: [char; 32] = [
+    '€', '\u{81}', '‚', 'ƒ', '„', '…', '†', '‡', 'ˆ', '‰', 'Š', '‹', 'Œ', '\u{8D}', 'Ž', '\u{8F}',
+    '\u{90}', '‘', '’', '“', '”', '•', '–', '—', '˜', '™', 'š', '›', 'œ', '\u{9D}', 'ž', 'Ÿ',
+];
+
+fn cp1252_char(byte: u8) -> char {
+    match byte {
+        0x80..=0x9F => CP1252_HIGH[(byte - 0x80) as usize],
+        _ => byte as char,
+    }
+}
+
+/// Decode one line. Valid UTF-8 is returned as is; any invalid bytes are
+/// handled by `policy`.
+pub fn decode_line<'a>(bytes: &'a [u8], policy: &LegacyBytes) -> Result<Cow<'a, str>, String> {
+    if let Ok(text) = std::str::from_utf8(bytes) {
+        return Ok(Cow::Borrowed(text));
+    }
+    let mut text = String::with_capacity(bytes.len() + 8);
+    for chunk in bytes.utf8_chunks() {
+        text.push_str(chunk.valid());
+        if chunk.invalid().is_empty() {
+            continue;
+        }
+        match policy {
+            LegacyBytes::Cp1252 => text.extend(chunk.invalid().iter().map(|&b| cp1252_char(b))),
+            LegacyBytes::Replace(with) => text.push_str(with),
+            LegacyBytes::Error => return Err("line is not valid UTF-8".to_string()),
+        }
+    }
+    Ok(Cow::Owned(text))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn field_charsets() {
+        assert_eq!(normalize_field("ΔG", FieldCharset::Utf8).unwrap(), "ΔG");
+        assert!(normalize_field("ΔG", FieldCharset::Ascii).is_err());
+        assert_eq!(normalize_field("ddg", FieldCharset::Ascii).unwrap(), "ddg");
+        assert_eq!(normalize_field("ΔG", FieldCharset::Fold).unwrap(), "DeltaG");
+        assert_eq!(normalize_field("énergie°", FieldCharset::Fold).unwrap(), "energiedeg");
+        assert_eq!(normalize_field("a☃b", FieldCharset::Fold).unwrap(), "a_b");
+        assert_eq!("fold".parse::<FieldCharset>(), Ok(FieldCharset::Fold));
+        assert!("latin1".parse::<FieldCharset>().is_err());
+    }
+
+    #[test]
+    fn score_strings_fold_only_the_names() {
+        let folded = normalize_score_str("ΔG=-1.5|note=é|plddt=90", FieldCharset::Fold).unwrap();
+        assert_eq!(folded, "DeltaG=-1.5|note=é|plddt=90");
+        assert!(normalize_score_str("ΔG=-1.5", FieldCharset::Ascii).is_err());
+    }
+
+    #[test]
+    fn legacy_bytes() {
+        let line = b"QV_SCORE a \xe9nergie=1.0|\x80=2";
+        assert_eq!(decode_line(b"QV_SCORE a x=1", &LegacyBytes::Error).unwrap(), "QV_SCORE a x=1");
+        assert_eq!(decode_line(line, &LegacyBytes::Cp1252).unwrap(), "QV_SCORE a énergie=1.0|€=2");
+        let replace: LegacyBytes = "replace=?".parse().unwrap();
+        assert_eq!(decode_line(line, &replace).unwrap(), "QV_SCORE a ?nergie=1.0|?=2");
+        assert_eq!(
+            decode_line(b"\x80", &"replace".parse().unwrap()).unwrap(),
+            "\u{FFFD}"
+        );
+        assert!(decode_line(line, &LegacyBytes::Error).is_err());
+        assert!("latin1".parse::<LegacyBytes>().is_err());
+    }
+}
@@ -0,0 +1,484 @@
+//! Just enough mmCIF to expand biological assemblies.
+//!
+//! A CIF payload may hold several `data_` blocks; each is parsed into its
+//! categories (loops and single-row `_key value` pairs). Values keep their
+//! raw text, quotes included, so rows that are not touched are written back
+//! exactly.
+
+use std::collections::HashSet;
+use std::ops::Range;
+
+/// One category of a data block, e.g. `atom_site`.
+#[derive(Debug, Clone, Default)]
+pub struct Table {
+    pub category: String,
+    /// Item names without the category prefix, e.g. `Cartn_x`
+    pub columns: Vec<String>,
+    /// Raw value tokens, one `Vec` per row
+    pub rows: Vec<Vec<String>>,
+    /// Lines of the block the category was read from
+    pub lines: Range<usize>,
+}
+
+impl Table {
+    pub fn column(&self, name: &str) -> Option<usize> {
+        self.columns.iter().position(|c| c == name)
+    }
+
+    /// The unquoted value of `name` in `row`.
+    pub fn get(&self, row: usize, name: &str) -> Option<&str> {
+        let col = self.column(name)?;
+        self.rows.get(row)?.get(col).map(|v| unquote(v))
+    }
+}
+
+/// Split a payload at its `data_` lines.
+///
+/// Lines before the first `data_` line form a block of their own.
+pub fn data_blocks(lines: &[String]) -> Vec<&[String]> {
+    let mut blocks = Vec::new();
+    let mut start = 0;
+    for (i, line) in lines.iter().enumerate() {
+        if i > start && line.starts_with("data_") {
+            blocks.push(&lines[start..i]);
+            start = i;
+        }
+    }
+    if start < lines.len() {
+        blocks.push(&lines[start..]);
+    }
+    blocks
+}
+
+enum Token {
+    Loop,
+    Tag(String),
+    Value(String),
+}
+
+/// Tokenize a block, pairing each token with its line index.
+fn tokenize(lines: &[String]) -> Vec<(usize, Token)> {
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < lines.len() {
+        let line = lines[i].trim_end_matches(['\n', '\r']);
+        // Semicolon text fields run until the next line starting with ';'
+        if line.starts_with(';') {
+            let start = i;
+            let mut text = vec![line.to_string()];
+            i += 1;
+            while i < lines.len() {
+                let l = lines[i].trim_end_matches(['\n', '\r']);
+                text.push(l.to_string());
+                i += 1;
+                if l.starts_with(';') {
+                    break;
+                }
+            }
+            tokens.push((start, Token::Value(text.join("\n"))));
+            continue;
+        }
+        let bytes = line.as_bytes();
+        let mut pos = 0;
+        while pos < bytes.len() {
+            if bytes[pos].is_ascii_whitespace() {
+                pos += 1;
+                continue;
+            }
+            if bytes[pos] == b'#' {
+                break;
+            }
+            let start = pos;
+            if bytes[pos] == b'\'' || bytes[pos] == b'"' {
+                // A quote only closes when followed by whitespace or the end of the line
+                let quote = bytes[pos];
+                pos += 1;
+                while pos < bytes.len()
+                    && !(bytes[pos] == quote && bytes.get(pos + 1).is_none_or(|b| b.is_ascii_whitespace()))
+                {
+                    pos += 1;
+                }
+                pos = (pos + 1).min(bytes.len());
+            } else {
+                while pos < bytes.len() && !bytes[pos].is_ascii_whitespace() {
+                    pos += 1;
+                }
+            }
+            let word = &line[start..pos];
+            let token = if word.eq_ignore_ascii_case("loop_") {
+                Token::Loop
+            } else if word.starts_with('_') {
+                Token::Tag(word.to_string())
+            } else {
+                Token::Value(word.to_string())
+            };
+            tokens.push((i, token));
+        }
+        i += 1;
+    }
+    tokens
+}
+
+fn split_tag(tag: &str) -> (String, String) {
+    let tag = tag.trim_start_matches('_');
+    match tag.split_once('.') {
+        Some((category, item)) => (category.to_string(), item.to_string()),
+        None => (tag.to_string(), String::new()),
+    }
+}
+
+/// Parse the categories of one data block.
+pub fn parse_block(lines: &[String]) -> Vec<Table> {
+    let tokens = tokenize(lines);
+    let mut tables: Vec<Table> = Vec::new();
+    let mut i = 0;
+    while i < tokens.len() {
+        match &tokens[i].1 {
+            Token::Loop => {
+                let start = tokens[i].0;
+                let mut table = Table::default();
+                i += 1;
+                while let Some((_, Token::Tag(tag))) = tokens.get(i) {
+                    let (category, item) = split_tag(tag);
+                    table.category = category;
+                    table.columns.push(item);
+                    i += 1;
+                }
+                let mut end = tokens[i - 1].0 + 1;
+                let mut row = Vec::new();
+                while let Some((line, Token::Value(value))) = tokens.get(i) {
+                    row.push(value.clone());
+                    if row.len() == table.columns.len() {
+                        table.rows.push(std::mem::take(&mut row));
+                    }
+                    end = line + text_field_len(&lines[*line..]);
+                    i += 1;
+                }
+                table.lines = start..end;
+                if !table.columns.is_empty() {
+                    tables.push(table);
+                }
+                continue;
+            }
+            Token::Tag(tag) => {
+                let (category, item) = split_tag(tag);
+                let line = tokens[i].0;
+                let value = match tokens.get(i + 1) {
+                    Some((_, Token::Value(value))) => {
+                        i += 1;
+                        value.clone()
+                    }
+                    _ => "?".to_string(),
+                };
+                let end = tokens[i].0 + text_field_len(&lines[tokens[i].0..]);
+                match tables.last_mut() {
+                    Some(table) if table.category == category && table.rows.len() == 1 && table.lines.end >= line => {
+                        table.columns.push(item);
+                        table.rows[0].push(value);
+                        table.lines.end = end;
+                    }
+                    _ => tables.push(Table {
+                        category,
+                        columns: vec![item],
+                        rows: vec![vec![value]],
+                        lines: line..end,
+                    }),
+                }
+            }
+            Token::Value(_) => {}
+        }
+        i += 1;
+    }
+    tables
+}
+
+/// Number of lines taken by the token starting `lines[0]`: a whole text
+/// field if the line starts with ';', otherwise one.
+fn text_field_len(lines: &[String]) -> usize {
+    if !lines.first().is_some_and(|l| l.starts_with(';')) {
+        return 1;
+    }
+    lines[1..]
+        .iter()
+        .position(|l| l.starts_with(';'))
+        .map_or(lines.len(), |p| p + 2)
+}
+
+/// Strip CIF quoting from a raw value.
+pub fn unquote(value: &str) -> &str {
+    if value.len() >= 2 && (value.starts_with('\'') && value.ends_with('\'') || value.starts_with('"') && value.ends_with('"')) {
+        return &value[1..value.len() - 1];
+    }
+    value
+}
+
+/// A rotation followed by a translation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Operator {
+    matrix: [[f64; 3]; 3],
+    vector: [f64; 3],
+}
+
+impl Operator {
+    const IDENTITY: Operator = Operator {
+        matrix: [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]],
+        vector: [0.0; 3],
+    };
+
+    fn apply(&self, p: [f64; 3]) -> [f64; 3] {
+        let mut out = self.vector;
+        for (r, o) in out.iter_mut().enumerate() {
+            *o += (0..3).map(|c| self.matrix[r][c] * p[c]).sum::<f64>();
+        }
+        out
+    }
+
+    /// `self` applied after `inner`.
+    fn then(&self, inner: &Operator) -> Operator {
+        let mut op = Operator {
+            matrix: [[0.0; 3]; 3],
+            vector: self.apply(inner.vector),
+        };
+        for r in 0..3 {
+            for c in 0..3 {
+                op.matrix[r][c] = (0..3).map(|k| self.matrix[r][k] * inner.matrix[k][c]).sum();
+            }
+        }
+        op
+    }
+}
+
+fn read_operator(table: &Table, row: usize) -> Result<Operator, String> {
+    let mut op = Operator::IDENTITY;
+    let number = |name: String| -> Result<f64, String> {
+        let value = table
+            .get(row, &name)
+            .ok_or_else(|| format!("pdbx_struct_oper_list is missing {}", name))?;
+        value
+            .parse()
+            .map_err(|_| format!("pdbx_struct_oper_list.{} is not a number: {}", name, value))
+    };
+    for r in 0..3 {
+        for c in 0..3 {
+            op.matrix[r][c] = number(format!("matrix[{}][{}]", r + 1, c + 1))?;
+        }
+        op.vector[r] = number(format!("vector[{}]", r + 1))?;
+    }
+    Ok(op)
+}
+
+/// Expand an `oper_expression` such as `1`, `1,2`, `(1-60)` or `(1-5)(6)`
+/// into operator id lists, one per copy. Within a list the last id is
+/// applied first.
+fn parse_oper_expression(expr: &str) -> Result<Vec<Vec<String>>, String> {
+    let groups: Vec<&str> = if expr.contains('(') {
+        expr.split(['(', ')']).filter(|g| !g.trim().is_empty()).collect()
+    } else {
+        vec![expr]
+    };
+    let mut combos: Vec<Vec<String>> = vec![Vec::new()];
+    for group in groups {
+        let mut ids = Vec::new();
+        for part in group.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            match part.split_once('-') {
+                Some((a, b)) => {
+                    let (a, b): (u64, u64) = match (a.trim().parse(), b.trim().parse()) {
+                        (Ok(a), Ok(b)) if a <= b => (a, b),
+                        _ => return Err(format!("Invalid operator range '{}' in '{}'", part, expr)),
+                    };
+                    ids.extend((a..=b).map(|n| n.to_string()));
+                }
+                None => ids.push(part.to_string()),
+            }
+        }
+        if ids.is_empty() {
+            return Err(format!("Empty operator expression '{}'", expr));
+        }
+        combos = combos
+            .into_iter()
+            .flat_map(|prefix| {
+                ids.iter().map(move |id| {
+                    let mut combo = prefix.clone();
+                    combo.push(id.clone());
+                    combo
+                })
+            })
+            .collect();
+    }
+    Ok(combos)
+}
+
+/// Replace every block's `atom_site` with the coordinates of biological
+/// assembly `assembly_id`.
+///
+/// Each copy of a chain made by an operator other than the first gets
+/// `-<operator>` appended to its asym ids, and atoms are renumbered from 1.
+/// Blocks without `atom_site` are copied unchanged.
+pub fn expand_assembly(lines: &[String], assembly_id: &str) -> Result<Vec<String>, String> {
+    let mut out = Vec::with_capacity(lines.len());
+    for block in data_blocks(lines) {
+        let name = block.first().map_or("", |l| l.trim());
+        let tables = parse_block(block);
+        let find = |category: &str| tables.iter().find(|t| t.category == category);
+        let Some(atoms) = find("atom_site") else {
+            out.extend(block.iter().cloned());
+            continue;
+        };
+        let gen = find("pdbx_struct_assembly_gen")
+            .ok_or_else(|| format!("{} has no pdbx_struct_assembly_gen category", name))?;
+        let opers = find("pdbx_struct_oper_list")
+            .ok_or_else(|| format!("{} has no pdbx_struct_oper_list category", name))?;
+
+        let operator = |id: &str| -> Result<Operator, String> {
+            let row = (0..opers.rows.len())
+                .find(|&r| opers.get(r, "id") == Some(id))
+                .ok_or_else(|| format!("Unknown operator '{}' in {}", id, name))?;
+            read_operator(opers, row)
+        };
+        let column = |name: &str| {
+            atoms
+                .column(name)
+                .ok_or_else(|| format!("atom_site is missing {}", name))
+        };
+        let (x, y, z) = (column("Cartn_x")?, column("Cartn_y")?, column("Cartn_z")?);
+        let label_asym = column("label_asym_id")?;
+        let auth_asym = atoms.column("auth_asym_id");
+        let id = atoms.column("id");
+
+        let mut rows = Vec::new();
+        let mut copies = 0usize;
+        for g in 0..gen.rows.len() {
+            if gen.get(g, "assembly_id") != Some(assembly_id) {
+                continue;
+            }
+            let expr = gen.get(g, "oper_expression").unwrap_or("");
+            let chains: HashSet<&str> = gen.get(g, "asym_id_list").unwrap_or("").split(',').map(str::trim).collect();
+            for combo in parse_oper_expression(expr)? {
+                let mut op = Operator::IDENTITY;
+                for oper_id in combo.iter().rev() {
+                    op = operator(oper_id)?.then(&op);
+                }
+                let suffix = if copies == 0 { String::new() } else { format!("-{}", combo.join("x")) };
+                copies += 1;
+                for atom in &atoms.rows {
+                    if !chains.contains(unquote(&atom[label_asym])) {
+                        continue;
+                    }
+                    let coord = |c: usize| -> Result<f64, String> {
+                        atom[c].parse().map_err(|_| format!("Invalid coordinate '{}' in {}", atom[c], name))
+                    };
+                    let p = op.apply([coord(x)?, coord(y)?, coord(z)?]);
+                    let mut row = atom.clone();
+                    row[x] = format!("{:.3}", p[0]);
+                    row[y] = format!("{:.3}", p[1]);
+                    row[z] = format!("{:.3}", p[2]);
+                    for c in std::iter::once(label_asym).chain(auth_asym) {
+                        if !suffix.is_empty() {
+                            row[c] = format!("{}{}", unquote(&row[c]), suffix);
+                        }
+                    }
+                    if let Some(c) = id {
+                        row[c] = (rows.len() + 1).to_string();
+                    }
+                    rows.push(row.join(" "));
+                }
+            }
+        }
+        if copies == 0 {
+            return Err(format!("{} has no assembly '{}'", name, assembly_id));
+        }
+
+        out.extend(block[..atoms.lines.start].iter().cloned());
+        out.push("loop_".to_string());
+        out.extend(atoms.columns.iter().map(|c| format!("_atom_site.{}", c)));
+        out.extend(rows);
+        out.extend(block[atoms.lines.end..].iter().cloned());
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(text: &str) -> Vec<String> {
+        text.lines().map(String::from).collect()
+    }
+
+    const DIMER: &str = "data_dimer
+_pdbx_struct_assembly_gen.assembly_id 1
+_pdbx_struct_assembly_gen.oper_expression '1,2'
+_pdbx_struct_assembly_gen.asym_id_list A
+loop_
+_pdbx_struct_oper_list.id
+_pdbx_struct_oper_list.matrix[1][1]
+_pdbx_struct_oper_list.matrix[1][2]
+_pdbx_struct_oper_list.matrix[1][3]
+_pdbx_struct_oper_list.vector[1]
+_pdbx_struct_oper_list.matrix[2][1]
+_pdbx_struct_oper_list.matrix[2][2]
+_pdbx_struct_oper_list.matrix[2][3]
+_pdbx_struct_oper_list.vector[2]
+_pdbx_struct_oper_list.matrix[3][1]
+_pdbx_struct_oper_list.matrix[3][2]
+_pdbx_struct_oper_list.matrix[3][3]
+_pdbx_struct_oper_list.vector[3]
+1 1 0 0 0 0 1 0 0 0 0 1 0
+2 1 0 0 10 0 1 0 0 0 0 1 0
+loop_
+_atom_site.group_PDB
+_atom_site.id
+_atom_site.label_atom_id
+_atom_site.label_asym_id
+_atom_site.Cartn_x
+_atom_site.Cartn_y
+_atom_site.Cartn_z
+ATOM 1 CA A 1.000 2.000 3.000
+ATOM 2 CA B 0.000 0.000 0.000
+#";
+
+    #[test]
+    fn categories_and_text_fields() {
+        let block = lines("data_x\n_entry.id X\n_entry.title\n;two\nlines\n;\n_cell.length_a 'a b'\n");
+        let tables = parse_block(&block);
+        assert_eq!(tables.len(), 2);
+        assert_eq!(tables[0].category, "entry");
+        assert_eq!(tables[0].columns, vec!["id", "title"]);
+        assert_eq!(tables[0].rows[0][1], ";two\nlines\n;");
+        assert_eq!(tables[0].lines, 1..6);
+        assert_eq!(tables[1].get(0, "length_a"), Some("a b"));
+        assert_eq!(unquote("\"it's\""), "it's");
+    }
+
+    #[test]
+    fn blocks_split_at_data_lines() {
+        let text = lines("# header\ndata_a\n_x.y 1\ndata_b\n_x.y 2");
+        let blocks = data_blocks(&text);
+        assert_eq!(blocks.len(), 3);
+        assert_eq!(blocks[1][0], "data_a");
+        assert_eq!(blocks[2], &text[3..]);
+    }
+
+    #[test]
+    fn operator_expressions() {
+        let ids = |expr: &str| parse_oper_expression(expr).unwrap();
+        assert_eq!(ids("1,2"), vec![vec!["1"], vec!["2"]]);
+        assert_eq!(ids("(1-3)").len(), 3);
+        assert_eq!(ids("(1-2)(5)"), vec![vec!["1", "5"], vec!["2", "5"]]);
+        assert!(parse_oper_expression("(3-1)").is_err());
+        assert!(parse_oper_expression("(1)(a-b)").is_err());
+    }
+
+    #[test]
+    fn assembly_copies_are_renamed_and_renumbered() {
+        let out = expand_assembly(&lines(DIMER), "1").unwrap();
+        let atoms: Vec<&str> = out.iter().filter(|l| l.starts_with("ATOM")).map(String::as_str).collect();
+        assert_eq!(atoms, vec!["ATOM 1 CA A 1.000 2.000 3.000", "ATOM 2 CA A-2 11.000 2.000 3.000"]);
+        assert_eq!(out.last().map(String::as_str), Some("#"));
+        assert!(out.iter().any(|l| l == "_pdbx_struct_assembly_gen.asym_id_list A"));
+        assert!(expand_assembly(&lines(DIMER), "2").unwrap_err().contains("has no assembly '2'"));
+        // Blocks without atoms are copied as is
+        let plain = lines("data_x\n_entry.id X");
+        assert_eq!(expand_assembly(&plain, "1").unwrap(), plain);
+    }
+}
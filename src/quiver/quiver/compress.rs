@@ -0,0 +1,138 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::Path;
+
+use flate2::read::MultiGzDecoder;
+use flate2::write::GzEncoder;
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+/// Compression codec of an archive or input file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    Gzip,
+    Zstd,
+}
+
+impl Compression {
+    /// Codec implied by a `.gz` or `.zst` file extension.
+    pub fn from_extension<P: AsRef<Path>>(path: P) -> Option<Self> {
+        match path.as_ref().extension()?.to_str()? {
+            "gz" => Some(Compression::Gzip),
+            "zst" => Some(Compression::Zstd),
+            _ => None,
+        }
+    }
+
+    /// Codec whose magic bytes `head` starts with.
+    pub fn from_magic(head: &[u8]) -> Option<Self> {
+        if head.starts_with(&GZIP_MAGIC) {
+            Some(Compression::Gzip)
+        } else if head.starts_with(&ZSTD_MAGIC) {
+            Some(Compression::Zstd)
+        } else {
+            None
+        }
+    }
+
+    /// Codec of `path`: from its magic bytes if it has any content, otherwise
+    /// from its extension.
+    pub fn detect<P: AsRef<Path>>(path: P) -> io::Result<Option<Self>> {
+        let path = path.as_ref();
+        if path.exists() {
+            let mut head = [0u8; 4];
+            let mut file = File::open(path)?;
+            let mut n = 0;
+            while n < head.len() {
+                match file.read(&mut head[n..])? {
+                    0 => break,
+                    read => n += read,
+                }
+            }
+            if n > 0 {
+                return Ok(Self::from_magic(&head[..n]));
+            }
+        }
+        Ok(Self::from_extension(path))
+    }
+
+    /// Wrap `reader` in a streaming decoder.
+    ///
+    /// Concatenated gzip members and zstd frames are read as one stream, which
+    /// is what appending to a compressed archive produces.
+    pub fn decoder<'a, R: Read + 'a>(self, reader: R) -> io::Result<Box<dyn Read + 'a>> {
+        Ok(match self {
+            Compression::Gzip => Box::new(MultiGzDecoder::new(reader)),
+            Compression::Zstd => Box::new(zstd::stream::read::Decoder::new(reader)?),
+        })
+    }
+
+    /// Compress `data` from start to end into `writer` as one member/frame.
+    pub fn encode<W: Write>(self, data: &mut dyn Read, writer: W) -> io::Result<u64> {
+        match self {
+            Compression::Gzip => {
+                let mut encoder = GzEncoder::new(writer, flate2::Compression::default());
+                let n = io::copy(data, &mut encoder)?;
+                encoder.finish()?;
+                Ok(n)
+            }
+            Compression::Zstd => {
+                let mut encoder = zstd::stream::write::Encoder::new(writer, 0)?;
+                let n = io::copy(data, &mut encoder)?;
+                encoder.finish()?;
+                Ok(n)
+            }
+        }
+    }
+}
+
+/// Open a file for streaming, decompressing gzip or zstd input on the fly.
+///
+/// Compression is detected from the magic bytes, not the file name.
+pub fn open_reader<P: AsRef<Path>>(path: P) -> io::Result<Box<dyn Read>> {
+    let mut reader = BufReader::new(File::open(path)?);
+    match Compression::from_magic(reader.fill_buf()?) {
+        Some(codec) => codec.decoder(reader),
+        None => Ok(Box::new(reader)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::testdata::scratch_dir;
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn magic_wins_over_extension() {
+        let dir = scratch_dir("compress-detect");
+        assert_eq!(Compression::detect(dir.join("new.qv.zst")).unwrap(), Some(Compression::Zstd));
+        assert_eq!(Compression::detect(dir.join("new.qv")).unwrap(), None);
+        let path = dir.join("plain.qv.gz");
+        fs::write(&path, "QV_TAG a\n").unwrap();
+        assert_eq!(Compression::detect(&path).unwrap(), None);
+        let mut packed = Vec::new();
+        Compression::Zstd.encode(&mut "QV_TAG a\n".as_bytes(), &mut packed).unwrap();
+        fs::write(&path, &packed).unwrap();
+        assert_eq!(Compression::detect(&path).unwrap(), Some(Compression::Zstd));
+    }
+
+    #[test]
+    fn concatenated_members_read_as_one_stream() {
+        let dir = scratch_dir("compress-members");
+        for (name, codec) in [("a.qv.gz", Compression::Gzip), ("a.qv.zst", Compression::Zstd)] {
+            let mut packed = Vec::new();
+            assert_eq!(codec.encode(&mut "QV_TAG a\n".as_bytes(), &mut packed).unwrap(), 9);
+            codec.encode(&mut "QV_TAG b\n".as_bytes(), &mut packed).unwrap();
+            fs::write(dir.join(name), &packed).unwrap();
+            let mut text = String::new();
+            open_reader(dir.join(name)).unwrap().read_to_string(&mut text).unwrap();
+            assert_eq!(text, "QV_TAG a\nQV_TAG b\n");
+        }
+        fs::write(dir.join("plain.qv"), "QV_TAG c\n").unwrap();
+        let mut text = String::new();
+        open_reader(dir.join("plain.qv")).unwrap().read_to_string(&mut text).unwrap();
+        assert_eq!(text, "QV_TAG c\n");
+    }
+}
@@ -0,0 +1,181 @@
+use std::str::FromStr;
+
+use super::{Quiver, QuiverError};
+
+/// On-disk encodings an archive can be converted to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// The plain-text `QV_TAG` format
+    Qv1,
+    /// The framed binary format, see [`super::format_v2`]
+    Qv2,
+    /// The framed binary format with every entry zstd-compressed
+    Qv2Zstd,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "qv1" => Ok(Format::Qv1),
+            "qv2" => Ok(Format::Qv2),
+            "qv2-zstd" => Ok(Format::Qv2Zstd),
+            other => Err(format!("Unknown format '{}', expected one of: qv1, qv2, qv2-zstd", other)),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ConvertReport {
+    /// Number of entries written and verified
+    pub entries: usize,
+}
+
+/// Checksums of the raw text of an archive, as its readers see it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Checksums {
+    /// Checksum of the bytes before the first entry
+    pub preamble: u64,
+    /// `(tag, checksum)` of every entry, from its `QV_TAG` line to the next
+    pub entries: Vec<(String, u64)>,
+}
+
+impl Checksums {
+    /// Checksums of every byte of `qv`.
+    pub fn of(qv: &Quiver) -> Result<Self, QuiverError> {
+        let entries = qv
+            .get_tags()
+            .into_iter()
+            .enumerate()
+            .map(|(index, tag)| Ok((tag, fnv1a64(&qv.entry_bytes(index)?))))
+            .collect::<Result<_, QuiverError>>()?;
+        Ok(Self {
+            preamble: fnv1a64(&qv.preamble_bytes()?),
+            entries,
+        })
+    }
+}
+
+/// 64-bit FNV-1a hash, used to compare entries before and after conversion.
+pub fn fnv1a64(data: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in data {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    hash
+}
+
+/// Copy `input` into `output`, an empty write handle, byte for byte: the
+/// header and every entry with all of its record lines.
+///
+/// Returns the checksums of what was copied, to be checked against the
+/// reopened output with [`verify`].
+pub fn convert(input: &Quiver, output: &mut Quiver) -> Result<Checksums, QuiverError> {
+    let preamble = fnv1a64(&output.copy_preamble(input)?);
+    let mut entries = Vec::with_capacity(input.size());
+    for (index, tag) in input.get_tags().into_iter().enumerate() {
+        let sum = fnv1a64(&output.copy_entry(input, index)?);
+        entries.push((tag, sum));
+    }
+    Ok(Checksums { preamble, entries })
+}
+
+/// Check that `written` reads back as exactly the bytes recorded by
+/// [`convert`]. Fails on anything the output format could not hold, e.g.
+/// `QV_HASH` lines or lines before the first entry in a v2 archive.
+pub fn verify(written: &Quiver, checksums: &Checksums) -> Result<ConvertReport, QuiverError> {
+    let found = Checksums::of(written)?;
+    if found.preamble != checksums.preamble {
+        return Err(QuiverError::InvalidOperation(
+            "Checksum mismatch for the header after conversion".to_string(),
+        ));
+    }
+    if let Some(idx) = (0..found.entries.len().min(checksums.entries.len()))
+        .find(|&idx| found.entries[idx] != checksums.entries[idx])
+    {
+        return Err(QuiverError::InvalidOperation(format!(
+            "Checksum mismatch for entry {} ({}) after conversion",
+            idx, found.entries[idx].0
+        )));
+    }
+    if found.entries.len() != checksums.entries.len() {
+        return Err(QuiverError::InvalidOperation(format!(
+            "Converted archive has {} entries, expected {}",
+            found.entries.len(),
+            checksums.entries.len()
+        )));
+    }
+    Ok(ConvertReport {
+        entries: found.entries.len(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::format_v2::V2File;
+    use super::super::storage::LocalFile;
+    use super::super::testdata::scratch_dir;
+    use super::*;
+    use std::path::Path;
+
+    fn write_source(path: &Path, hashed: bool) {
+        let mut qv = Quiver::new(path, "w").unwrap();
+        qv.set_write_hashes(hashed);
+        qv.set_header(&[("creator".to_string(), "test".to_string())]).unwrap();
+        qv.add_pdb(&["ATOM 1\n".to_string()], "a", Some("plddt=90.5")).unwrap();
+        qv.add_pdb(&["ATOM 2\n".to_string(), "END\n".to_string()], "b", None).unwrap();
+        qv.flush().unwrap();
+    }
+
+    fn convert_to(input: &Path, output: &Path, format: Format) -> Result<ConvertReport, QuiverError> {
+        let input = Quiver::new(input, "r")?;
+        let mut out = match format {
+            Format::Qv1 => Quiver::with_storage(Box::new(LocalFile::buffered(output)), "w")?,
+            Format::Qv2 => Quiver::with_storage(Box::new(V2File::new(output, false)?), "w")?,
+            Format::Qv2Zstd => Quiver::with_storage(Box::new(V2File::new(output, false)?.zstd()), "w")?,
+        };
+        let checksums = convert(&input, &mut out)?;
+        out.flush()?;
+        verify(&Quiver::new(output, "r")?, &checksums)
+    }
+
+    #[test]
+    fn every_format_round_trips() {
+        let dir = scratch_dir("convert-formats");
+        let source = dir.join("in.qv");
+        write_source(&source, false);
+        for (name, format) in [("out.qv", Format::Qv1), ("out.qv2", Format::Qv2), ("z.qv2", Format::Qv2Zstd)] {
+            let report = convert_to(&source, &dir.join(name), format).unwrap();
+            assert_eq!(report.entries, 2);
+            let back = Quiver::new(dir.join(name), "r").unwrap();
+            assert_eq!(back.get_header(), vec![("creator".to_string(), "test".to_string())]);
+            assert_eq!(back.get_pdblines("b").unwrap(), vec!["ATOM 2", "END"]);
+        }
+    }
+
+    #[test]
+    fn qv1_copies_every_byte() {
+        let dir = scratch_dir("convert-bytes");
+        let source = dir.join("in.qv");
+        write_source(&source, true);
+        convert_to(&source, &dir.join("out.qv"), Format::Qv1).unwrap();
+        assert_eq!(std::fs::read(&source).unwrap(), std::fs::read(dir.join("out.qv")).unwrap());
+    }
+
+    #[test]
+    fn lossy_conversion_fails_verification() {
+        let dir = scratch_dir("convert-lossy");
+        let source = dir.join("in.qv");
+        write_source(&source, true);
+        let err = convert_to(&source, &dir.join("out.qv2"), Format::Qv2).unwrap_err();
+        assert!(format!("{:?}", err).contains("Checksum mismatch for entry 0 (a)"));
+    }
+
+    #[test]
+    fn format_names() {
+        assert_eq!("qv2-zstd".parse::<Format>(), Ok(Format::Qv2Zstd));
+        assert!("qv3".parse::<Format>().is_err());
+    }
+}
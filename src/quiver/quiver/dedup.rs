@@ -0,0 +1,155 @@
+//! Dropping entries whose tag repeats an earlier or later one.
+//!
+//! Appending the output of several runs to one archive easily leaves two
+//! entries under the same tag, and readers then see only one of them
+//! depending on how they look it up. [`dedup`] rewrites the archive with one
+//! entry per tag, keeping the first or the last one in file order, and
+//! reports the entries it dropped.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use super::{EntryEdit, Quiver, QuiverError};
+
+/// Which of the entries sharing a tag [`dedup`] keeps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Keep {
+    /// The earliest in the file
+    #[default]
+    First,
+    /// The latest in the file, i.e. the most recently appended
+    Last,
+}
+
+impl FromStr for Keep {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "first" => Ok(Keep::First),
+            "last" => Ok(Keep::Last),
+            other => Err(format!(
+                "Unknown keep policy '{}', expected first or last",
+                other
+            )),
+        }
+    }
+}
+
+/// An entry dropped by [`dedup`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dropped {
+    pub tag: String,
+    /// Position of the entry in the archive before deduplication
+    pub position: usize,
+}
+
+/// Rewrite `input` with one entry per tag, to `output` if given and in
+/// place (atomically) otherwise. Returns the entries dropped, in file order.
+///
+/// `output` must not exist yet; it is a copy of `input` before the rewrite,
+/// so an archive with no repeated tags is copied as is.
+pub fn dedup<P: AsRef<Path>, Q: AsRef<Path>>(
+    input: P,
+    output: Option<Q>,
+    keep: Keep,
+) -> Result<Vec<Dropped>, QuiverError> {
+    let input = input.as_ref();
+    let mut qv = match output {
+        Some(output) => {
+            let output = output.as_ref();
+            if output.exists() {
+                return Err(QuiverError::InvalidOperation(format!(
+                    "'{}' already exists",
+                    output.display()
+                )));
+            }
+            fs::copy(input, output)?;
+            Quiver::new(output, "a")?
+        }
+        None => Quiver::new(input, "a")?,
+    };
+
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for tag in &qv.tags {
+        *counts.entry(tag.as_str()).or_default() += 1;
+    }
+    // Occurrence of each tag that is kept, counting from 0
+    let mut seen: HashMap<&str, usize> = HashMap::new();
+    let mut dropped = Vec::new();
+    for (position, tag) in qv.tags.iter().enumerate() {
+        let occurrence = seen.entry(tag.as_str()).or_default();
+        let kept = match keep {
+            Keep::First => 0,
+            Keep::Last => counts[tag.as_str()] - 1,
+        };
+        if *occurrence != kept {
+            dropped.push(Dropped {
+                tag: tag.clone(),
+                position,
+            });
+        }
+        *occurrence += 1;
+    }
+    if dropped.is_empty() {
+        return Ok(dropped);
+    }
+
+    let mut drop = dropped.iter().map(|d| d.position).peekable();
+    let mut position = 0;
+    qv.rewrite(|_| {
+        let edit = if drop.next_if_eq(&position).is_some() {
+            EntryEdit::Drop
+        } else {
+            EntryEdit::Keep
+        };
+        position += 1;
+        edit
+    })?;
+    Ok(dropped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::testdata::scratch_dir;
+    use super::*;
+
+    // Written by hand, as add_pdb refuses a repeated tag
+    fn write_archive(path: &Path, entries: &[(&str, &str)]) {
+        let text: String = entries.iter().map(|(tag, line)| format!("QV_TAG {}\n{}\n", tag, line)).collect();
+        fs::write(path, text).unwrap();
+    }
+
+    const ENTRIES: [(&str, &str); 4] = [("a", "ATOM 1"), ("b", "ATOM 2"), ("a", "ATOM 3"), ("a", "ATOM 4")];
+
+    #[test]
+    fn keep_first_or_last() {
+        let dir = scratch_dir("dedup-keep");
+        let input = dir.join("in.qv");
+        write_archive(&input, &ENTRIES);
+        let dropped = dedup(&input, Some(dir.join("first.qv")), Keep::First).unwrap();
+        assert_eq!(dropped.iter().map(|d| d.position).collect::<Vec<_>>(), vec![2, 3]);
+        let first = Quiver::new(dir.join("first.qv"), "r").unwrap();
+        assert_eq!(first.get_tags(), vec!["a", "b"]);
+        assert_eq!(first.get_pdblines("a").unwrap(), vec!["ATOM 1"]);
+
+        let dropped = dedup(&input, None::<&Path>, Keep::Last).unwrap();
+        assert_eq!(dropped, vec![Dropped { tag: "a".to_string(), position: 0 }, Dropped { tag: "a".to_string(), position: 2 }]);
+        let last = Quiver::new(&input, "r").unwrap();
+        assert_eq!(last.get_tags(), vec!["b", "a"]);
+        assert_eq!(last.get_pdblines("a").unwrap(), vec!["ATOM 4"]);
+    }
+
+    #[test]
+    fn unique_archives_are_copied_as_is() {
+        let dir = scratch_dir("dedup-unique");
+        let input = dir.join("in.qv");
+        write_archive(&input, &ENTRIES[..2]);
+        assert!(dedup(&input, Some(dir.join("out.qv")), Keep::First).unwrap().is_empty());
+        assert_eq!(fs::read(&input).unwrap(), fs::read(dir.join("out.qv")).unwrap());
+        assert!(dedup(&input, Some(dir.join("out.qv")), Keep::First).is_err());
+        assert!("middle".parse::<Keep>().is_err());
+    }
+}
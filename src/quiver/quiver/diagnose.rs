@@ -0,0 +1,328 @@
+//! Line-by-line diagnostics of a whole archive.
+//!
+//! Readers tolerate a lot: a repeated tag hides one of its entries, a score
+//! line under the wrong entry is silently attributed to it, and junk in a
+//! payload is passed on to whatever parses the structure. [`validate_archive`]
+//! reports all of these with the line they are on, so an archive can be
+//! fixed before a long production run trips over it.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use std::str::FromStr;
+
+use super::validate::{sniff_structure, PDB_RECORDS};
+use super::{open_storage, PayloadFormat, QuiverError};
+
+/// What is wrong with a line or entry, see [`Issue`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum IssueKind {
+    /// A `QV_TAG` line repeating the tag of an earlier entry
+    DuplicateTag,
+    /// An entry without any ATOM or HETATM record (or `_atom_site` items)
+    EmptyStructure,
+    /// A `QV_SCORE` line naming another tag than the entry it is in
+    ScoreTagMismatch,
+    /// A `QV_SCORE` line without fields, or with a field that is not
+    /// `key=value`
+    MalformedScore,
+    /// A line that is neither a Quiver record nor a PDB record
+    Junk,
+}
+
+impl IssueKind {
+    pub const ALL: [IssueKind; 5] = [
+        IssueKind::DuplicateTag,
+        IssueKind::EmptyStructure,
+        IssueKind::ScoreTagMismatch,
+        IssueKind::MalformedScore,
+        IssueKind::Junk,
+    ];
+
+    /// Machine-readable name of the kind.
+    pub fn name(self) -> &'static str {
+        match self {
+            IssueKind::DuplicateTag => "duplicate-tag",
+            IssueKind::EmptyStructure => "empty-structure",
+            IssueKind::ScoreTagMismatch => "score-tag-mismatch",
+            IssueKind::MalformedScore => "malformed-score",
+            IssueKind::Junk => "junk",
+        }
+    }
+}
+
+impl FromStr for IssueKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        IssueKind::ALL
+            .into_iter()
+            .find(|kind| kind.name() == s)
+            .ok_or_else(|| {
+                let names: Vec<&str> = IssueKind::ALL.iter().map(|kind| kind.name()).collect();
+                format!(
+                    "Unknown issue kind '{}', expected one of {}",
+                    s,
+                    names.join(", ")
+                )
+            })
+    }
+}
+
+impl fmt::Display for IssueKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// One problem found by [`validate_archive`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Issue {
+    pub kind: IssueKind,
+    /// Line number, counting from 1; for an empty structure, its `QV_TAG`
+    /// line
+    pub line: usize,
+    /// The entry the line is in, `None` before the first one
+    pub tag: Option<String>,
+    pub message: String,
+}
+
+/// Output of [`validate_archive`].
+#[derive(Debug, Clone, Default)]
+pub struct ValidationReport {
+    /// Entries in the archive
+    pub entries: usize,
+    /// Problems in file order
+    pub issues: Vec<Issue>,
+}
+
+impl ValidationReport {
+    pub fn is_valid(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    /// How many issues there are of each kind, by kind.
+    pub fn counts(&self) -> Vec<(IssueKind, usize)> {
+        let mut counts: HashMap<IssueKind, usize> = HashMap::new();
+        for issue in &self.issues {
+            *counts.entry(issue.kind).or_default() += 1;
+        }
+        let mut counts: Vec<_> = counts.into_iter().collect();
+        counts.sort();
+        counts
+    }
+}
+
+/// The entry being read.
+struct Current {
+    tag: Option<String>,
+    line: usize,
+    format: PayloadFormat,
+    payload: Vec<String>,
+}
+
+/// Read the archive at `path` once and report every duplicate tag, empty
+/// structure, misplaced or malformed score line and junk line in it. Unlike
+/// opening it, this never fails on what it reports.
+pub fn validate_archive<P: AsRef<Path>>(path: P) -> Result<ValidationReport, QuiverError> {
+    let storage = open_storage(path.as_ref(), "r")?;
+    let mut reader = BufReader::new(storage.reader()?);
+    let mut report = ValidationReport::default();
+    // Line of the first entry of each tag
+    let mut first_seen: HashMap<String, usize> = HashMap::new();
+    let mut current: Option<Current> = None;
+    let mut buf = Vec::new();
+    let mut line = 0;
+    loop {
+        buf.clear();
+        if reader.read_until(b'\n', &mut buf)? == 0 {
+            break;
+        }
+        line += 1;
+        let text = String::from_utf8_lossy(&buf);
+        let text = text.trim_end_matches(['\n', '\r']);
+        let tag = current.as_ref().and_then(|c| c.tag.clone());
+        let mut issue = |kind, message: String| {
+            report.issues.push(Issue {
+                kind,
+                line,
+                tag: tag.clone(),
+                message,
+            })
+        };
+
+        if text.starts_with("QV_TAG") {
+            if let Some(entry) = current.take() {
+                check_structure(entry, &mut report);
+            }
+            let new_tag = text.split_whitespace().nth(1).map(str::to_string);
+            match &new_tag {
+                Some(new_tag) => {
+                    report.entries += 1;
+                    if let Some(first) = first_seen.get(new_tag) {
+                        report.issues.push(Issue {
+                            kind: IssueKind::DuplicateTag,
+                            line,
+                            tag: Some(new_tag.clone()),
+                            message: format!(
+                                "tag {} is already used by the entry at line {}",
+                                new_tag, first
+                            ),
+                        });
+                    } else {
+                        first_seen.insert(new_tag.clone(), line);
+                    }
+                }
+                None => report.issues.push(Issue {
+                    kind: IssueKind::Junk,
+                    line,
+                    tag: None,
+                    message: "QV_TAG line without a tag".to_string(),
+                }),
+            }
+            current = Some(Current {
+                tag: new_tag,
+                line,
+                format: PayloadFormat::from_tag_line(text),
+                payload: Vec::new(),
+            });
+        } else if text.starts_with("QV_SCORE") {
+            let parts: Vec<&str> = text.split_whitespace().collect();
+            match (parts.get(1), tag.as_deref()) {
+                (None, _) => issue(
+                    IssueKind::MalformedScore,
+                    "QV_SCORE line without a tag".to_string(),
+                ),
+                (Some(&scored), Some(tag)) if scored == tag => {}
+                (Some(&scored), Some(tag)) => issue(
+                    IssueKind::ScoreTagMismatch,
+                    format!("QV_SCORE line for {} inside the entry of {}", scored, tag),
+                ),
+                (Some(&scored), None) => issue(
+                    IssueKind::ScoreTagMismatch,
+                    format!("QV_SCORE line for {} before the first entry", scored),
+                ),
+            }
+            match parts.get(2) {
+                None if parts.len() > 1 => issue(
+                    IssueKind::MalformedScore,
+                    "QV_SCORE line without fields".to_string(),
+                ),
+                Some(fields) => {
+                    if let Some(field) = fields
+                        .split('|')
+                        .find(|kv| !matches!(kv.split_once('='), Some((k, _)) if !k.is_empty()))
+                    {
+                        issue(
+                            IssueKind::MalformedScore,
+                            format!("score field '{}' is not key=value", field),
+                        );
+                    }
+                }
+                None => {}
+            }
+        } else if text.starts_with("QV_") {
+            // Header, metadata and checksum lines
+        } else if let Some(entry) = current.as_mut() {
+            if entry.format == PayloadFormat::Pdb {
+                let record = text.get(..6).unwrap_or(text).trim_end();
+                if !record.is_empty() && !PDB_RECORDS.contains(&record) {
+                    let shown: String = text.chars().take(60).collect();
+                    issue(IssueKind::Junk, format!("not a PDB record: {}", shown));
+                }
+            }
+            entry.payload.push(text.to_string());
+        } else if !text.trim().is_empty() {
+            let shown: String = text.chars().take(60).collect();
+            issue(
+                IssueKind::Junk,
+                format!("line before the first entry: {}", shown),
+            );
+        }
+    }
+    if let Some(entry) = current.take() {
+        check_structure(entry, &mut report);
+    }
+    // Empty structures are found when their entry ends
+    report.issues.sort_by_key(|issue| issue.line);
+    Ok(report)
+}
+
+/// Report `entry` if its payload holds no structure.
+fn check_structure(entry: Current, report: &mut ValidationReport) {
+    if let Err(message) = sniff_structure(entry.format, &entry.payload) {
+        report.issues.push(Issue {
+            kind: IssueKind::EmptyStructure,
+            line: entry.line,
+            tag: entry.tag,
+            message,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::testdata::scratch_dir;
+    use super::*;
+    use std::fs;
+
+    const ATOM: &str = "ATOM      1  CA  ALA A   1       0.000   0.000   0.000  1.00  0.00           C";
+
+    #[test]
+    fn every_issue_with_its_line() {
+        let path = scratch_dir("diagnose-issues").join("bad.qv");
+        let text = [
+            "stray line",
+            "QV_TAG a",
+            "QV_SCORE a plddt=90",
+            ATOM,
+            "QV_TAG b",
+            "QV_SCORE c x=1",
+            "QV_SCORE b plddt",
+            "hello world",
+            "QV_TAG a",
+            ATOM,
+        ]
+        .join("\n");
+        fs::write(&path, text + "\n").unwrap();
+        let report = validate_archive(&path).unwrap();
+        assert_eq!(report.entries, 3);
+        let found: Vec<(usize, IssueKind, Option<&str>)> = report
+            .issues
+            .iter()
+            .map(|issue| (issue.line, issue.kind, issue.tag.as_deref()))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (1, IssueKind::Junk, None),
+                (5, IssueKind::EmptyStructure, Some("b")),
+                (6, IssueKind::ScoreTagMismatch, Some("b")),
+                (7, IssueKind::MalformedScore, Some("b")),
+                (8, IssueKind::Junk, Some("b")),
+                (9, IssueKind::DuplicateTag, Some("a")),
+            ]
+        );
+        assert!(report.issues[5].message.contains("line 2"));
+        assert_eq!(report.counts()[0], (IssueKind::DuplicateTag, 1));
+        assert_eq!(report.counts()[4], (IssueKind::Junk, 2));
+    }
+
+    #[test]
+    fn clean_archive_is_valid() {
+        let path = scratch_dir("diagnose-clean").join("ok.qv");
+        fs::write(&path, format!("QV_TAG a\nQV_SCORE a plddt=90|rmsd=1.2\n{}\nEND\n", ATOM)).unwrap();
+        let report = validate_archive(&path).unwrap();
+        assert!(report.is_valid());
+        assert_eq!(report.entries, 1);
+    }
+
+    #[test]
+    fn kind_names() {
+        for kind in IssueKind::ALL {
+            assert_eq!(kind.name().parse::<IssueKind>(), Ok(kind));
+        }
+        assert!("typo".parse::<IssueKind>().is_err());
+    }
+}
@@ -0,0 +1,633 @@
+//! A small expression language over score fields.
+//!
+//! Grammar, lowest precedence first:
+//!
+//! ```text
+//! expr  := and ( ("or" | "||") and )*
+//! and   := not ( ("and" | "&&") not )*
+//! not   := ("not" | "!") not | cmp
+//! cmp   := sum ( ("<" | "<=" | ">" | ">=" | "==" | "!=") sum )?
+//! sum   := term ( ("+" | "-") term )*
+//! term  := unary ( ("*" | "/") unary )*
+//! unary := "-" unary | atom
+//! atom  := NUMBER | FIELD | "true" | "false" | "has" "(" FIELD ")" | "(" expr ")"
+//! ```
+//!
+//! A field missing from an entry's scores is "missing": arithmetic on it stays
+//! missing and every comparison involving it is false. Use `has(field)` to
+//! test for presence explicitly.
+//!
+//! Fields are typed (see [`super::scores`]): numbers take part in arithmetic
+//! and comparisons, `true`/`false` values are conditions, and string values
+//! count as missing.
+//!
+//! Tools that take a score field (rank keys such as qvtop's, the buckets of
+//! `qvsplit --by-score`, `qvgroupstats`) take a [`ScoreKey`] instead, so the
+//! same grammar selects, ranks, buckets and summarises entries.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+use super::scores::ScoreValue;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Num(f64),
+    Ident(String),
+    Op(&'static str),
+    LParen,
+    RParen,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Eq,
+    Ne,
+    And,
+    Or,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Num(f64),
+    Bool(bool),
+    Field(String),
+    Has(String),
+    Neg(Box<Node>),
+    Not(Box<Node>),
+    Bin(BinOp, Box<Node>, Box<Node>),
+}
+
+/// Result of evaluating an expression.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Value {
+    Num(f64),
+    Bool(bool),
+    Missing,
+}
+
+/// A parsed score expression, e.g. `plddt > 85 and (has(rmsd) and rmsd < 2)`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expr {
+    root: Node,
+    source: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExprError(pub String);
+
+impl fmt::Display for ExprError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for ExprError {}
+
+impl FromStr for Expr {
+    type Err = ExprError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Expr::parse(s)
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+impl Expr {
+    pub fn parse(source: &str) -> Result<Self, ExprError> {
+        let tokens = tokenize(source)?;
+        let mut parser = Parser { tokens, pos: 0 };
+        let root = parser.or()?;
+        if let Some(tok) = parser.peek() {
+            return Err(ExprError(format!("Unexpected token {:?}", tok)));
+        }
+        Ok(Self {
+            root,
+            source: source.to_string(),
+        })
+    }
+
+    /// Evaluate against a set of typed score fields.
+    pub fn eval(&self, fields: &HashMap<String, ScoreValue>) -> Result<Value, ExprError> {
+        eval(&self.root, fields)
+    }
+
+    /// Evaluate as a filter: `true` only if the expression is a true boolean.
+    ///
+    /// Missing values never match; a numeric result is an error.
+    pub fn matches(&self, fields: &HashMap<String, ScoreValue>) -> Result<bool, ExprError> {
+        match self.eval(fields)? {
+            Value::Bool(b) => Ok(b),
+            Value::Missing => Ok(false),
+            Value::Num(_) => Err(ExprError(format!(
+                "Expression '{}' is numeric, expected a condition",
+                self.source
+            ))),
+        }
+    }
+
+    /// Evaluate as a number: `None` if the result is missing, `NaN` or a
+    /// condition.
+    pub fn number(&self, fields: &HashMap<String, ScoreValue>) -> Result<Option<f64>, ExprError> {
+        Ok(match self.eval(fields)? {
+            Value::Num(n) if !n.is_nan() => Some(n),
+            _ => None,
+        })
+    }
+
+    /// Names of all fields referenced by the expression.
+    pub fn fields(&self) -> Vec<String> {
+        let mut out = Vec::new();
+        collect_fields(&self.root, &mut out);
+        out
+    }
+}
+
+/// A score field, or an expression over the fields such as `ddg - ref_ddg`.
+///
+/// A key naming a field of the entry is that field, even if it would also
+/// parse as an expression (`pae-mean`); otherwise it is evaluated. Text that
+/// is neither a field nor a valid expression is missing from every entry.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScoreKey {
+    name: String,
+    expr: Option<Expr>,
+}
+
+impl ScoreKey {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            expr: Expr::parse(name).ok(),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The typed value of the key; `None` if missing, `NaN`, or an expression
+    /// that does not evaluate to a number.
+    pub fn value(&self, fields: &HashMap<String, ScoreValue>) -> Option<ScoreValue> {
+        if let Some(value) = fields.get(&self.name) {
+            return Some(value.clone()).filter(|v| !v.as_f64().is_some_and(f64::is_nan));
+        }
+        let expr = self.expr.as_ref()?;
+        expr.number(fields).ok().flatten().map(ScoreValue::Float)
+    }
+
+    /// The value as a number; `None` also for booleans and strings.
+    pub fn number(&self, fields: &HashMap<String, ScoreValue>) -> Option<f64> {
+        self.value(fields).and_then(|v| v.as_f64())
+    }
+}
+
+impl FromStr for ScoreKey {
+    type Err = ExprError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.trim().is_empty() {
+            return Err(ExprError("The score field is empty".to_string()));
+        }
+        Ok(Self::new(s))
+    }
+}
+
+impl fmt::Display for ScoreKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.name)
+    }
+}
+
+fn collect_fields(node: &Node, out: &mut Vec<String>) {
+    match node {
+        Node::Field(name) | Node::Has(name) => {
+            if !out.contains(name) {
+                out.push(name.clone());
+            }
+        }
+        Node::Neg(inner) | Node::Not(inner) => collect_fields(inner, out),
+        Node::Bin(_, a, b) => {
+            collect_fields(a, out);
+            collect_fields(b, out);
+        }
+        Node::Num(_) | Node::Bool(_) => {}
+    }
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>, ExprError> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+        if c.is_ascii_digit() || (c == '.' && chars.get(i + 1).is_some_and(|d| d.is_ascii_digit())) {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                i += 1;
+            }
+            // Exponent, e.g. 1e-3
+            if i < chars.len() && (chars[i] == 'e' || chars[i] == 'E') {
+                let mut j = i + 1;
+                if j < chars.len() && (chars[j] == '+' || chars[j] == '-') {
+                    j += 1;
+                }
+                if j < chars.len() && chars[j].is_ascii_digit() {
+                    i = j;
+                    while i < chars.len() && chars[i].is_ascii_digit() {
+                        i += 1;
+                    }
+                }
+            }
+            let text: String = chars[start..i].iter().collect();
+            let num = text
+                .parse::<f64>()
+                .map_err(|_| ExprError(format!("Invalid number '{}'", text)))?;
+            tokens.push(Token::Num(num));
+            continue;
+        }
+        if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            tokens.push(Token::Ident(chars[start..i].iter().collect()));
+            continue;
+        }
+        let two: String = chars[i..(i + 2).min(chars.len())].iter().collect();
+        let op = match two.as_str() {
+            "<=" => Some("<="),
+            ">=" => Some(">="),
+            "==" => Some("=="),
+            "!=" => Some("!="),
+            "&&" => Some("and"),
+            "||" => Some("or"),
+            _ => None,
+        };
+        if let Some(op) = op {
+            tokens.push(Token::Op(op));
+            i += 2;
+            continue;
+        }
+        let tok = match c {
+            '(' => Token::LParen,
+            ')' => Token::RParen,
+            '<' => Token::Op("<"),
+            '>' => Token::Op(">"),
+            '+' => Token::Op("+"),
+            '-' => Token::Op("-"),
+            '*' => Token::Op("*"),
+            '/' => Token::Op("/"),
+            '!' => Token::Op("not"),
+            '=' => Token::Op("=="),
+            _ => return Err(ExprError(format!("Unexpected character '{}'", c))),
+        };
+        tokens.push(tok);
+        i += 1;
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let tok = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        tok
+    }
+
+    /// Consume an operator or keyword if it is next.
+    fn eat(&mut self, op: &str) -> bool {
+        let hit = match self.peek() {
+            Some(Token::Op(o)) => *o == op,
+            Some(Token::Ident(word)) => word == op && matches!(op, "and" | "or" | "not"),
+            _ => false,
+        };
+        if hit {
+            self.pos += 1;
+        }
+        hit
+    }
+
+    fn or(&mut self) -> Result<Node, ExprError> {
+        let mut node = self.and()?;
+        while self.eat("or") {
+            node = Node::Bin(BinOp::Or, Box::new(node), Box::new(self.and()?));
+        }
+        Ok(node)
+    }
+
+    fn and(&mut self) -> Result<Node, ExprError> {
+        let mut node = self.not()?;
+        while self.eat("and") {
+            node = Node::Bin(BinOp::And, Box::new(node), Box::new(self.not()?));
+        }
+        Ok(node)
+    }
+
+    fn not(&mut self) -> Result<Node, ExprError> {
+        if self.eat("not") {
+            return Ok(Node::Not(Box::new(self.not()?)));
+        }
+        self.cmp()
+    }
+
+    fn cmp(&mut self) -> Result<Node, ExprError> {
+        let left = self.sum()?;
+        let ops = [
+            ("<=", BinOp::Le),
+            (">=", BinOp::Ge),
+            ("<", BinOp::Lt),
+            (">", BinOp::Gt),
+            ("==", BinOp::Eq),
+            ("!=", BinOp::Ne),
+        ];
+        for (text, op) in ops {
+            if self.eat(text) {
+                return Ok(Node::Bin(op, Box::new(left), Box::new(self.sum()?)));
+            }
+        }
+        Ok(left)
+    }
+
+    fn sum(&mut self) -> Result<Node, ExprError> {
+        let mut node = self.term()?;
+        loop {
+            let op = if self.eat("+") {
+                BinOp::Add
+            } else if self.eat("-") {
+                BinOp::Sub
+            } else {
+                return Ok(node);
+            };
+            node = Node::Bin(op, Box::new(node), Box::new(self.term()?));
+        }
+    }
+
+    fn term(&mut self) -> Result<Node, ExprError> {
+        let mut node = self.unary()?;
+        loop {
+            let op = if self.eat("*") {
+                BinOp::Mul
+            } else if self.eat("/") {
+                BinOp::Div
+            } else {
+                return Ok(node);
+            };
+            node = Node::Bin(op, Box::new(node), Box::new(self.unary()?));
+        }
+    }
+
+    fn unary(&mut self) -> Result<Node, ExprError> {
+        if self.eat("-") {
+            return Ok(Node::Neg(Box::new(self.unary()?)));
+        }
+        self.atom()
+    }
+
+    fn atom(&mut self) -> Result<Node, ExprError> {
+        match self.next() {
+            Some(Token::Num(n)) => Ok(Node::Num(n)),
+            Some(Token::LParen) => {
+                let node = self.or()?;
+                match self.next() {
+                    Some(Token::RParen) => Ok(node),
+                    _ => Err(ExprError("Expected ')'".to_string())),
+                }
+            }
+            Some(Token::Ident(word)) => match word.as_str() {
+                "true" => Ok(Node::Bool(true)),
+                "false" => Ok(Node::Bool(false)),
+                "has" => {
+                    let field = match (self.next(), self.next(), self.next()) {
+                        (Some(Token::LParen), Some(Token::Ident(field)), Some(Token::RParen)) => {
+                            field
+                        }
+                        _ => return Err(ExprError("Expected has(field)".to_string())),
+                    };
+                    Ok(Node::Has(field))
+                }
+                "and" | "or" | "not" => Err(ExprError(format!("Unexpected keyword '{}'", word))),
+                _ => Ok(Node::Field(word)),
+            },
+            Some(tok) => Err(ExprError(format!("Unexpected token {:?}", tok))),
+            None => Err(ExprError("Unexpected end of expression".to_string())),
+        }
+    }
+}
+
+fn eval(node: &Node, fields: &HashMap<String, ScoreValue>) -> Result<Value, ExprError> {
+    Ok(match node {
+        Node::Num(n) => Value::Num(*n),
+        Node::Bool(b) => Value::Bool(*b),
+        Node::Field(name) => match fields.get(name) {
+            Some(ScoreValue::Bool(b)) => Value::Bool(*b),
+            Some(value) => value.as_f64().map_or(Value::Missing, Value::Num),
+            None => Value::Missing,
+        },
+        Node::Has(name) => Value::Bool(fields.contains_key(name)),
+        Node::Neg(inner) => match eval(inner, fields)? {
+            Value::Num(n) => Value::Num(-n),
+            Value::Missing => Value::Missing,
+            Value::Bool(_) => return Err(ExprError("Cannot negate a condition".to_string())),
+        },
+        Node::Not(inner) => match eval(inner, fields)? {
+            Value::Bool(b) => Value::Bool(!b),
+            Value::Missing => Value::Bool(true),
+            Value::Num(_) => return Err(ExprError("'not' expects a condition".to_string())),
+        },
+        Node::Bin(op @ (BinOp::And | BinOp::Or), a, b) => {
+            let a = truthy(eval(a, fields)?)?;
+            if (*op == BinOp::And && !a) || (*op == BinOp::Or && a) {
+                return Ok(Value::Bool(a));
+            }
+            Value::Bool(truthy(eval(b, fields)?)?)
+        }
+        Node::Bin(op, a, b) => {
+            let (a, b) = match (eval(a, fields)?, eval(b, fields)?) {
+                (Value::Num(a), Value::Num(b)) => (a, b),
+                (Value::Bool(_), _) | (_, Value::Bool(_)) => {
+                    return Err(ExprError("Cannot compare or add conditions".to_string()))
+                }
+                _ => {
+                    return Ok(match op {
+                        BinOp::Add | BinOp::Sub | BinOp::Mul | BinOp::Div => Value::Missing,
+                        _ => Value::Bool(false),
+                    })
+                }
+            };
+            match op {
+                BinOp::Add => Value::Num(a + b),
+                BinOp::Sub => Value::Num(a - b),
+                BinOp::Mul => Value::Num(a * b),
+                BinOp::Div => Value::Num(a / b),
+                BinOp::Lt => Value::Bool(a < b),
+                BinOp::Le => Value::Bool(a <= b),
+                BinOp::Gt => Value::Bool(a > b),
+                BinOp::Ge => Value::Bool(a >= b),
+                BinOp::Eq => Value::Bool(a == b),
+                BinOp::Ne => Value::Bool(a != b),
+                BinOp::And | BinOp::Or => unreachable!(),
+            }
+        }
+    })
+}
+
+fn truthy(value: Value) -> Result<bool, ExprError> {
+    match value {
+        Value::Bool(b) => Ok(b),
+        Value::Missing => Ok(false),
+        Value::Num(_) => Err(ExprError("'and'/'or' expect conditions".to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scores(pairs: &[(&str, &str)]) -> HashMap<String, ScoreValue> {
+        pairs.iter().map(|(k, v)| (k.to_string(), ScoreValue::parse(v))).collect()
+    }
+
+    fn eval_str(source: &str, fields: &HashMap<String, ScoreValue>) -> Value {
+        Expr::parse(source).unwrap().eval(fields).unwrap()
+    }
+
+    #[test]
+    fn arithmetic_precedence() {
+        let none = HashMap::new();
+        assert_eq!(eval_str("1 + 2 * 3", &none), Value::Num(7.0));
+        assert_eq!(eval_str("(1 + 2) * 3", &none), Value::Num(9.0));
+        assert_eq!(eval_str("10 - 4 - 3", &none), Value::Num(3.0));
+        assert_eq!(eval_str("8 / 4 / 2", &none), Value::Num(1.0));
+        assert_eq!(eval_str("-2 * -3", &none), Value::Num(6.0));
+        assert_eq!(eval_str("1e-3 * 2E3", &none), Value::Num(2.0));
+        assert_eq!(eval_str(".5 + 1", &none), Value::Num(1.5));
+    }
+
+    #[test]
+    fn logical_precedence() {
+        let none = HashMap::new();
+        // and binds tighter than or, not tighter than and
+        assert_eq!(eval_str("true or false and false", &none), Value::Bool(true));
+        assert_eq!(eval_str("(true or false) and false", &none), Value::Bool(false));
+        assert_eq!(eval_str("not false and false", &none), Value::Bool(false));
+        assert_eq!(eval_str("!(false and false) && true || false", &none), Value::Bool(true));
+        // comparisons bind tighter than the logical operators
+        assert_eq!(eval_str("1 + 1 == 2 and 3 > 2 * 1", &none), Value::Bool(true));
+        assert_eq!(eval_str("1 = 1", &none), Value::Bool(true));
+    }
+
+    #[test]
+    fn fields_are_typed() {
+        let fields = scores(&[("plddt", "91.5"), ("n", "3"), ("converged", "true"), ("note", "ok")]);
+        let expr = Expr::parse("plddt > 85 and n >= 3 and converged").unwrap();
+        assert!(expr.matches(&fields).unwrap());
+        assert_eq!(expr.fields(), vec!["plddt", "n", "converged"]);
+        assert_eq!(eval_str("plddt - n", &fields), Value::Num(88.5));
+        // String values count as missing
+        assert_eq!(eval_str("note", &fields), Value::Missing);
+        assert_eq!(eval_str("has(note)", &fields), Value::Bool(true));
+    }
+
+    #[test]
+    fn missing_scores() {
+        let fields = scores(&[("plddt", "91.5")]);
+        assert_eq!(eval_str("rmsd", &fields), Value::Missing);
+        assert_eq!(eval_str("rmsd * 2 + 1", &fields), Value::Missing);
+        assert_eq!(eval_str("-rmsd", &fields), Value::Missing);
+        // Every comparison with a missing value is false, including !=
+        assert_eq!(eval_str("rmsd < 2", &fields), Value::Bool(false));
+        assert_eq!(eval_str("rmsd >= 2", &fields), Value::Bool(false));
+        assert_eq!(eval_str("rmsd != 2", &fields), Value::Bool(false));
+        assert_eq!(eval_str("not rmsd", &fields), Value::Bool(true));
+        assert_eq!(eval_str("has(rmsd)", &fields), Value::Bool(false));
+        let expr = Expr::parse("rmsd").unwrap();
+        assert!(!expr.matches(&fields).unwrap());
+        assert_eq!(expr.number(&fields).unwrap(), None);
+        // The right side is not evaluated once the left decides
+        assert!(Expr::parse("has(rmsd) and rmsd < 2 or plddt > 90").unwrap().matches(&fields).unwrap());
+        assert!(Expr::parse("false and 1").unwrap().matches(&fields).is_ok());
+    }
+
+    #[test]
+    fn parse_errors() {
+        for source in [
+            "",
+            "plddt >",
+            "(plddt > 1",
+            "plddt > 1)",
+            "plddt # 1",
+            "has plddt",
+            "has(1)",
+            "and plddt",
+            "1.2.3",
+            "plddt 1",
+        ] {
+            assert!(Expr::parse(source).is_err(), "{:?} should not parse", source);
+        }
+        assert_eq!(
+            "plddt $ 2".parse::<Expr>().unwrap_err(),
+            ExprError("Unexpected character '$'".to_string())
+        );
+    }
+
+    #[test]
+    fn evaluation_errors() {
+        let fields = scores(&[("plddt", "91.5"), ("converged", "true")]);
+        for source in ["plddt and true", "not plddt", "-converged", "converged + 1", "converged < 1"] {
+            let expr = Expr::parse(source).unwrap();
+            assert!(expr.eval(&fields).is_err(), "{:?} should not evaluate", source);
+        }
+        let numeric = Expr::parse("plddt * 2").unwrap();
+        assert!(numeric.matches(&fields).is_err());
+        assert_eq!(numeric.number(&fields).unwrap(), Some(183.0));
+        assert_eq!(Expr::parse("plddt > 1").unwrap().number(&fields).unwrap(), None);
+        assert_eq!(Expr::parse("0 / 0").unwrap().number(&fields).unwrap(), None);
+    }
+
+    #[test]
+    fn display_keeps_the_source() {
+        let expr: Expr = "plddt>85 and  has(rmsd)".parse().unwrap();
+        assert_eq!(expr.to_string(), "plddt>85 and  has(rmsd)");
+    }
+
+    #[test]
+    fn score_key_prefers_a_field_of_that_name() {
+        let fields = scores(&[("pae-mean", "4.5"), ("pae", "10"), ("mean", "2"), ("name", "x"), ("bad", "nan")]);
+        assert_eq!(ScoreKey::new("pae-mean").number(&fields), Some(4.5));
+        assert_eq!(ScoreKey::new("pae - mean").number(&fields), Some(8.0));
+        assert_eq!(ScoreKey::new("pae > 1").number(&fields), None);
+        assert_eq!(ScoreKey::new("name").value(&fields), Some(ScoreValue::Str("x".to_string())));
+        assert_eq!(ScoreKey::new("name").number(&fields), None);
+        assert_eq!(ScoreKey::new("bad").value(&fields), None);
+        assert_eq!(ScoreKey::new("pae.mean").value(&fields), None);
+        assert!(" ".parse::<ScoreKey>().is_err());
+    }
+}
@@ -0,0 +1,259 @@
+//! A minimal C ABI for reading archives from other languages.
+//!
+//! C++ MD tools, Julia and anything else with a C FFI can open an archive,
+//! list its tags and read entries through the same reader and `.idx` tag
+//! index as the command-line tools, without a Python environment. The
+//! declarations are in `quiver.h` next to the crate root; the functions are
+//! only built with the `ffi` feature.
+//!
+//! Functions that can fail return `NULL` and leave a message for
+//! [`quiver_last_error`] and the tools' exit status for
+//! [`quiver_last_error_code`]. Strings returned by the library are owned by
+//! the caller and freed with [`quiver_string_free`].
+
+use std::cell::RefCell;
+use std::ffi::{c_char, c_int, CStr, CString};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+
+use super::{Quiver, QuiverError};
+
+/// An archive opened read-only by [`quiver_open`].
+pub struct QuiverHandle {
+    qv: Quiver,
+}
+
+thread_local! {
+    /// The error of the last failed call on this thread
+    static LAST_ERROR: RefCell<Option<(CString, c_int)>> = const { RefCell::new(None) };
+}
+
+fn set_error(message: String, code: c_int) {
+    // A message with a NUL in it is cut there
+    let message = CString::new(message).unwrap_or_else(|e| {
+        let end = e.nul_position();
+        CString::new(&e.into_vec()[..end]).unwrap_or_default()
+    });
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some((message, code)));
+}
+
+/// Run `f`, turning an error or a panic into `NULL` and a last error instead
+/// of unwinding into the caller.
+fn guard<T>(f: impl FnOnce() -> Result<*mut T, QuiverError>) -> *mut T {
+    LAST_ERROR.with(|last| *last.borrow_mut() = None);
+    match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(value)) => value,
+        Ok(Err(e)) => {
+            set_error(format!("{:?}", e), e.exit_code());
+            ptr::null_mut()
+        }
+        Err(_) => {
+            set_error("internal error (panic) in the quiver library".to_string(), 1);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// `s` as a `&str`, refusing `NULL` and invalid UTF-8.
+///
+/// # Safety
+/// `s` must be `NULL` or a valid NUL-terminated string.
+unsafe fn borrow_str<'a>(s: *const c_char, what: &str) -> Result<&'a str, QuiverError> {
+    if s.is_null() {
+        return Err(QuiverError::InvalidOperation(format!("{} is NULL", what)));
+    }
+    // SAFETY: non-NULL and NUL-terminated per the caller's contract.
+    unsafe { CStr::from_ptr(s) }
+        .to_str()
+        .map_err(|_| QuiverError::InvalidOperation(format!("{} is not valid UTF-8", what)))
+}
+
+/// `text` as a string the caller frees with [`quiver_string_free`].
+fn owned_string(text: String) -> Result<*mut c_char, QuiverError> {
+    CString::new(text)
+        .map(CString::into_raw)
+        .map_err(|_| QuiverError::Malformed("entry contains a NUL byte".to_string()))
+}
+
+/// Open the archive at `path` (a local file or an http(s):// or s3:// URL)
+/// read-only, through its `.idx` tag index if that is up to date. Returns
+/// `NULL` on failure.
+///
+/// # Safety
+/// `path` must be `NULL` or a valid NUL-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn quiver_open(path: *const c_char) -> *mut QuiverHandle {
+    guard(|| {
+        // SAFETY: forwarded from the caller.
+        let path = unsafe { borrow_str(path, "path") }?;
+        let qv = if super::remote::is_remote_url(path) {
+            Quiver::new(path, "r")?
+        } else {
+            Quiver::open_indexed(path)?
+        };
+        Ok(Box::into_raw(Box::new(QuiverHandle { qv })))
+    })
+}
+
+/// Close an archive opened by [`quiver_open`]. `NULL` is ignored.
+///
+/// # Safety
+/// `handle` must be `NULL` or a handle from [`quiver_open`] not closed yet.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn quiver_close(handle: *mut QuiverHandle) {
+    if !handle.is_null() {
+        // SAFETY: allocated by quiver_open and not freed yet.
+        drop(unsafe { Box::from_raw(handle) });
+    }
+}
+
+/// The number of entries of the archive; 0 with a last error if `handle`
+/// is `NULL`.
+///
+/// # Safety
+/// `handle` must be `NULL` or a live handle from [`quiver_open`].
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn quiver_len(handle: *const QuiverHandle) -> usize {
+    LAST_ERROR.with(|last| *last.borrow_mut() = None);
+    // SAFETY: a live handle per the caller's contract.
+    match unsafe { handle.as_ref() } {
+        Some(handle) => handle.qv.size(),
+        None => {
+            let e = QuiverError::InvalidOperation("handle is NULL".to_string());
+            set_error(format!("{:?}", e), e.exit_code());
+            0
+        }
+    }
+}
+
+/// The tags of the archive in file order, one per line, each ending with a
+/// newline. Free the result with [`quiver_string_free`].
+///
+/// # Safety
+/// `handle` must be a live handle from [`quiver_open`].
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn quiver_list(handle: *const QuiverHandle) -> *mut c_char {
+    guard(|| {
+        // SAFETY: a live handle per the caller's contract.
+        let Some(handle) = (unsafe { handle.as_ref() }) else {
+            return Err(QuiverError::InvalidOperation("handle is NULL".to_string()));
+        };
+        let mut text = String::new();
+        for tag in &handle.qv.tags {
+            text.push_str(tag);
+            text.push('\n');
+        }
+        owned_string(text)
+    })
+}
+
+/// The structure stored under `tag`, as the text of the PDB or mmCIF file,
+/// or `NULL` if it is not in the archive (error code 3) or cannot be read.
+/// Free the result with [`quiver_string_free`].
+///
+/// # Safety
+/// `handle` must be a live handle from [`quiver_open`] and `tag` a valid
+/// NUL-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn quiver_get(handle: *const QuiverHandle, tag: *const c_char) -> *mut c_char {
+    guard(|| {
+        // SAFETY: a live handle per the caller's contract.
+        let Some(handle) = (unsafe { handle.as_ref() }) else {
+            return Err(QuiverError::InvalidOperation("handle is NULL".to_string()));
+        };
+        // SAFETY: forwarded from the caller.
+        let tag = unsafe { borrow_str(tag, "tag") }?;
+        let mut text = String::new();
+        for line in handle.qv.get_lines(tag)? {
+            text.push_str(&line);
+            if !line.ends_with('\n') {
+                text.push('\n');
+            }
+        }
+        owned_string(text)
+    })
+}
+
+/// Free a string returned by this library. `NULL` is ignored.
+///
+/// # Safety
+/// `s` must be `NULL` or a string returned by this library not freed yet.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn quiver_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        // SAFETY: allocated by CString::into_raw in this module.
+        drop(unsafe { CString::from_raw(s) });
+    }
+}
+
+/// The message of the last failed call on this thread, or `NULL` if it
+/// succeeded. Valid until the next call on the same thread; not to be
+/// freed.
+#[unsafe(no_mangle)]
+pub extern "C" fn quiver_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| last.borrow().as_ref().map_or(ptr::null(), |(message, _)| message.as_ptr()))
+}
+
+/// The exit status the command-line tools would give the last failed call
+/// on this thread (see [`QuiverError::exit_code`]), or 0 if it succeeded.
+#[unsafe(no_mangle)]
+pub extern "C" fn quiver_last_error_code() -> c_int {
+    LAST_ERROR.with(|last| last.borrow().as_ref().map_or(0, |(_, code)| *code))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::testdata::scratch_dir;
+    use super::*;
+
+    fn last_error() -> Option<String> {
+        let message = quiver_last_error();
+        // SAFETY: NULL or a string owned by LAST_ERROR on this thread.
+        (!message.is_null()).then(|| unsafe { CStr::from_ptr(message) }.to_string_lossy().into_owned())
+    }
+
+    /// Take a string returned by the library and free it.
+    fn take(s: *mut c_char) -> String {
+        assert!(!s.is_null(), "{:?}", last_error());
+        // SAFETY: a live string from this library.
+        let text = unsafe { CStr::from_ptr(s) }.to_string_lossy().into_owned();
+        unsafe { quiver_string_free(s) };
+        text
+    }
+
+    #[test]
+    fn read_through_the_c_abi() {
+        let dir = scratch_dir("ffi");
+        let path = dir.join("a.qv");
+        std::fs::write(&path, "QV_TAG a\nATOM 1\nQV_TAG b\nATOM 2\n").unwrap();
+        let path = CString::new(path.to_str().unwrap()).unwrap();
+        unsafe {
+            let handle = quiver_open(path.as_ptr());
+            assert!(!handle.is_null(), "{:?}", last_error());
+            assert_eq!(quiver_len(handle), 2);
+            assert_eq!(take(quiver_list(handle)), "a\nb\n");
+            assert_eq!(take(quiver_get(handle, c"b".as_ptr())), "ATOM 2\n");
+
+            assert!(quiver_get(handle, c"missing".as_ptr()).is_null());
+            assert_eq!(quiver_last_error_code(), 3);
+            // Every call resets the last error, quiver_len included
+            assert_eq!(quiver_len(handle), 2);
+            assert_eq!(quiver_last_error_code(), 0);
+            assert!(last_error().is_none());
+            quiver_close(handle);
+        }
+    }
+
+    #[test]
+    fn null_arguments_are_errors() {
+        unsafe {
+            assert!(quiver_open(ptr::null()).is_null());
+            assert!(last_error().unwrap().contains("path is NULL"));
+            assert_eq!(quiver_len(ptr::null()), 0);
+            assert_eq!(quiver_last_error_code(), 1);
+            assert!(quiver_list(ptr::null()).is_null());
+            quiver_close(ptr::null_mut());
+            quiver_string_free(ptr::null_mut());
+        }
+    }
+}
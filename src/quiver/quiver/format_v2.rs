@@ -0,0 +1,708 @@
+//! Binary Quiver v2 format.
+//!
+//! A v2 archive starts with an 8-byte preamble (`QVB`, the format version,
+//! four reserved zero bytes) followed by framed records:
+//!
+//! ```text
+//! kind: u8 | len: u32 LE | payload: len bytes | checksum: u64 LE (FNV-1a of payload)
+//! ```
+//!
+//! Header records (`kind = 1`) hold `QV_HEADER` fields as `key=value` lines.
+//! Entry records (`kind = 2`) hold a tag block, a score block, a metadata
+//! block and the structure payload. The tag block carries the entry's
+//! `QV_TAG` attributes after the tag, e.g. `design_1 FORMAT=cif`:
+//!
+//! ```text
+//! tag_len: u32 | tag | score_len: u32 (u32::MAX = no score) | score
+//! | meta_len: u32 | key=value|key=value | PDB lines, each ending in '\n'
+//! ```
+//!
+//! Compressed entry records (`kind = 3`) hold the same payload as an entry
+//! record as one zstd frame; [`V2File::zstd`] writes them.
+//!
+//! Frames can be skipped without parsing their payload and every payload is
+//! checksummed, so random access and integrity checks are cheap. [`V2File`]
+//! presents a v2 archive as legacy text, so every `Quiver` operation works on
+//! it unchanged.
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+
+use super::compress::Compression;
+use super::convert::fnv1a64;
+use super::storage::{write_atomically, PositionalReader, Staged, Storage, StoredLen};
+use super::{format_header_lines, format_tag_line, parse_header_line, Entry, MetaFields, PayloadFormat};
+
+pub const MAGIC: [u8; 3] = *b"QVB";
+pub const VERSION: u8 = 2;
+const PREAMBLE: [u8; 8] = [b'Q', b'V', b'B', VERSION, 0, 0, 0, 0];
+
+const KIND_HEADER: u8 = 1;
+const KIND_ENTRY: u8 = 2;
+const KIND_ENTRY_ZSTD: u8 = 3;
+const NO_SCORE: u32 = u32::MAX;
+
+/// One framed record of a v2 archive.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Record {
+    Header(MetaFields),
+    Entry(Entry),
+}
+
+impl Record {
+    /// The record in the legacy text format.
+    pub fn to_text(&self) -> String {
+        match self {
+            Record::Header(fields) => format_header_lines(fields),
+            Record::Entry(entry) => entry.to_text(),
+        }
+    }
+}
+
+/// Whether `head` starts like a v2 archive.
+pub fn is_v2(head: &[u8]) -> bool {
+    head.starts_with(&MAGIC)
+}
+
+/// Whether `path` is a v2 archive: from its magic bytes if it has content,
+/// otherwise from a `.qv2` extension.
+pub fn detect<P: AsRef<Path>>(path: P) -> io::Result<bool> {
+    let path = path.as_ref();
+    if path.exists() {
+        let mut head = Vec::with_capacity(MAGIC.len());
+        File::open(path)?
+            .take(MAGIC.len() as u64)
+            .read_to_end(&mut head)?;
+        if !head.is_empty() {
+            return Ok(is_v2(&head));
+        }
+    }
+    Ok(path.extension().is_some_and(|ext| ext == "qv2"))
+}
+
+fn put_block(out: &mut Vec<u8>, block: &[u8]) -> io::Result<()> {
+    let len = u32::try_from(block.len())
+        .ok()
+        .filter(|&len| len != NO_SCORE)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "record block too large"))?;
+    out.extend_from_slice(&len.to_le_bytes());
+    out.extend_from_slice(block);
+    Ok(())
+}
+
+/// Encode a record as one frame.
+pub fn encode_record(record: &Record) -> io::Result<Vec<u8>> {
+    let (kind, payload) = record_payload(record)?;
+    frame(kind, &payload)
+}
+
+/// Like [`encode_record`], with an entry's payload zstd-compressed.
+pub fn encode_compressed_record(record: &Record) -> io::Result<Vec<u8>> {
+    match record_payload(record)? {
+        (KIND_ENTRY, payload) => {
+            let mut compressed = Vec::new();
+            Compression::Zstd.encode(&mut &payload[..], &mut compressed)?;
+            frame(KIND_ENTRY_ZSTD, &compressed)
+        }
+        (kind, payload) => frame(kind, &payload),
+    }
+}
+
+fn record_payload(record: &Record) -> io::Result<(u8, Vec<u8>)> {
+    Ok(match record {
+        Record::Header(fields) => {
+            let body: String = fields.iter().map(|(k, v)| format!("{}={}\n", k, v)).collect();
+            (KIND_HEADER, body.into_bytes())
+        }
+        Record::Entry(entry) => {
+            let mut payload = Vec::new();
+            let tag_line = format_tag_line(&entry.tag, entry.format);
+            put_block(&mut payload, &tag_line.as_bytes()["QV_TAG ".len()..])?;
+            match &entry.score {
+                Some(score) => put_block(&mut payload, score.as_bytes())?,
+                None => payload.extend_from_slice(&NO_SCORE.to_le_bytes()),
+            }
+            let meta: Vec<String> = entry.meta.iter().map(|(k, v)| format!("{}={}", k, v)).collect();
+            put_block(&mut payload, meta.join("|").as_bytes())?;
+            for line in &entry.lines {
+                payload.extend_from_slice(line.as_bytes());
+                if !line.ends_with('\n') {
+                    payload.push(b'\n');
+                }
+            }
+            (KIND_ENTRY, payload)
+        }
+    })
+}
+
+fn frame(kind: u8, payload: &[u8]) -> io::Result<Vec<u8>> {
+    let len = u32::try_from(payload.len())
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "record too large"))?;
+    let mut frame = Vec::with_capacity(payload.len() + 13);
+    frame.push(kind);
+    frame.extend_from_slice(&len.to_le_bytes());
+    frame.extend_from_slice(payload);
+    frame.extend_from_slice(&fnv1a64(payload).to_le_bytes());
+    Ok(frame)
+}
+
+/// Reads framed records, checking every checksum.
+pub struct Reader<R: Read> {
+    inner: R,
+    /// Byte offset of the next frame
+    offset: u64,
+    records: usize,
+    last_tag: Option<String>,
+    source: Option<String>,
+}
+
+impl<R: Read> Reader<R> {
+    /// Start reading an archive from its first byte, checking the preamble.
+    pub fn new(mut inner: R, source: Option<String>) -> io::Result<Self> {
+        let mut preamble = [0u8; 8];
+        inner.read_exact(&mut preamble)?;
+        if !is_v2(&preamble) {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "not a Quiver v2 archive"));
+        }
+        if preamble[3] != VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unsupported Quiver binary format version {}", preamble[3]),
+            ));
+        }
+        Ok(Self::resume(inner, PREAMBLE.len() as u64, source))
+    }
+
+    /// Continue reading at a frame boundary, e.g. after seeking to an offset
+    /// from an index.
+    pub fn resume(inner: R, offset: u64, source: Option<String>) -> Self {
+        Self {
+            inner,
+            offset,
+            records: 0,
+            last_tag: None,
+            source,
+        }
+    }
+
+    /// Byte offset of the next frame.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    fn corrupt(&self, what: &str) -> io::Error {
+        let mut msg = format!("{} in record {} at byte {}", what, self.records, self.offset);
+        if let Some(tag) = &self.last_tag {
+            msg.push_str(&format!(" after tag {}", tag));
+        }
+        if let Some(source) = &self.source {
+            msg.push_str(&format!(" in {}", source));
+        }
+        io::Error::new(io::ErrorKind::InvalidData, msg)
+    }
+
+    fn read_exact_or_corrupt(&mut self, buf: &mut [u8]) -> io::Result<()> {
+        self.inner.read_exact(buf).map_err(|e| match e.kind() {
+            io::ErrorKind::UnexpectedEof => self.corrupt("truncated record"),
+            _ => e,
+        })
+    }
+
+    /// Read the next frame as `(kind, payload)` after checking its checksum.
+    pub fn read_frame(&mut self) -> io::Result<Option<(u8, Vec<u8>)>> {
+        let mut kind = [0u8; 1];
+        if self.inner.read(&mut kind)? == 0 {
+            return Ok(None);
+        }
+        let mut word = [0u8; 4];
+        self.read_exact_or_corrupt(&mut word)?;
+        let mut payload = vec![0u8; u32::from_le_bytes(word) as usize];
+        self.read_exact_or_corrupt(&mut payload)?;
+        let mut checksum = [0u8; 8];
+        self.read_exact_or_corrupt(&mut checksum)?;
+        if u64::from_le_bytes(checksum) != fnv1a64(&payload) {
+            return Err(self.corrupt("checksum mismatch"));
+        }
+        self.offset += 13 + payload.len() as u64;
+        self.records += 1;
+        Ok(Some((kind[0], payload)))
+    }
+
+    /// Read and decode the next record.
+    pub fn next_record(&mut self) -> io::Result<Option<Record>> {
+        let Some((kind, payload)) = self.read_frame()? else {
+            return Ok(None);
+        };
+        let record = match kind {
+            KIND_HEADER => Record::Header(
+                String::from_utf8_lossy(&payload)
+                    .lines()
+                    .filter_map(|line| line.split_once('='))
+                    .map(|(k, v)| (k.to_string(), v.to_string()))
+                    .collect(),
+            ),
+            KIND_ENTRY | KIND_ENTRY_ZSTD => {
+                let payload = if kind == KIND_ENTRY_ZSTD {
+                    let mut plain = Vec::new();
+                    Compression::Zstd
+                        .decoder(&payload[..])?
+                        .read_to_end(&mut plain)
+                        .map_err(|_| self.corrupt("malformed compressed entry"))?;
+                    plain
+                } else {
+                    payload
+                };
+                let entry = decode_entry(&payload).ok_or_else(|| self.corrupt("malformed entry"))?;
+                self.last_tag = Some(entry.tag.clone());
+                Record::Entry(entry)
+            }
+            other => return Err(self.corrupt(&format!("unknown record kind {}", other))),
+        };
+        Ok(Some(record))
+    }
+}
+
+fn take_block<'a>(payload: &mut &'a [u8]) -> Option<Option<&'a [u8]>> {
+    let (len, rest) = payload.split_first_chunk::<4>()?;
+    let len = u32::from_le_bytes(*len);
+    if len == NO_SCORE {
+        *payload = rest;
+        return Some(None);
+    }
+    let (block, rest) = rest.split_at_checked(len as usize)?;
+    *payload = rest;
+    Some(Some(block))
+}
+
+fn decode_entry(mut payload: &[u8]) -> Option<Entry> {
+    let tag_line = format!("QV_TAG {}", String::from_utf8_lossy(take_block(&mut payload)??));
+    let tag = tag_line.split_whitespace().nth(1).unwrap_or("").to_string();
+    let format = PayloadFormat::from_tag_line(&tag_line);
+    let score = take_block(&mut payload)?.map(|s| String::from_utf8_lossy(s).to_string());
+    let meta = String::from_utf8_lossy(take_block(&mut payload)??)
+        .split('|')
+        .filter_map(|kv| kv.split_once('='))
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+    let lines = String::from_utf8_lossy(payload)
+        .lines()
+        .map(|l| l.to_string())
+        .collect();
+    Some(Entry {
+        tag,
+        score,
+        meta,
+        lines,
+        format,
+    })
+}
+
+/// Splits legacy text into records, one line at a time.
+///
+/// Lines before the first `QV_TAG` that are not `QV_HEADER` lines are dropped.
+#[derive(Default)]
+pub struct TextParser {
+    header: MetaFields,
+    entry: Option<Entry>,
+    done: Vec<Record>,
+}
+
+impl TextParser {
+    /// Feed one line, with or without its trailing newline.
+    pub fn push(&mut self, line: &str) {
+        let line = line.trim_end_matches(['\n', '\r']);
+        if let Some((key, value)) = parse_header_line(line) {
+            self.flush_entry();
+            self.header.push((key, value));
+            return;
+        }
+        self.flush_header();
+        if line.starts_with("QV_TAG") {
+            self.flush_entry();
+            self.entry = Some(Entry {
+                tag: line.split_whitespace().nth(1).unwrap_or("").to_string(),
+                format: PayloadFormat::from_tag_line(line),
+                ..Entry::default()
+            });
+            return;
+        }
+        let Some(entry) = self.entry.as_mut() else {
+            return;
+        };
+        if line.starts_with("QV_HASH") {
+            return;
+        }
+        if line.starts_with("QV_SCORE") {
+            entry.score = line.split_whitespace().nth(2).map(|s| s.to_string());
+        } else if let Some((_, fields)) = super::parse_meta_line(line) {
+            entry.meta.extend(fields);
+        } else {
+            entry.lines.push(line.to_string());
+        }
+    }
+
+    fn flush_header(&mut self) {
+        if !self.header.is_empty() {
+            self.done.push(Record::Header(std::mem::take(&mut self.header)));
+        }
+    }
+
+    fn flush_entry(&mut self) {
+        if let Some(entry) = self.entry.take() {
+            self.done.push(Record::Entry(entry));
+        }
+    }
+
+    /// Take the records completed so far.
+    pub fn drain(&mut self) -> Vec<Record> {
+        std::mem::take(&mut self.done)
+    }
+
+    /// Flush the record in progress and return everything left.
+    pub fn finish(mut self) -> Vec<Record> {
+        self.flush_header();
+        self.flush_entry();
+        self.done
+    }
+}
+
+/// Parse a complete chunk of legacy text into records.
+pub fn text_to_records(text: &[u8]) -> Vec<Record> {
+    let mut parser = TextParser::default();
+    for line in String::from_utf8_lossy(text).lines() {
+        parser.push(line);
+    }
+    parser.finish()
+}
+
+/// Convert a legacy text archive into a v2 archive. Returns the number of
+/// entries written.
+pub fn from_text<R: BufRead, W: Write>(input: R, output: W) -> io::Result<usize> {
+    let mut output = BufWriter::new(output);
+    output.write_all(&PREAMBLE)?;
+    let mut parser = TextParser::default();
+    let mut entries = 0;
+    let mut write = |records: Vec<Record>, output: &mut BufWriter<W>| -> io::Result<()> {
+        for record in records {
+            if matches!(record, Record::Entry(_)) {
+                entries += 1;
+            }
+            output.write_all(&encode_record(&record)?)?;
+        }
+        Ok(())
+    };
+    for line in input.lines() {
+        parser.push(&line?);
+        write(parser.drain(), &mut output)?;
+    }
+    write(parser.finish(), &mut output)?;
+    output.flush()?;
+    Ok(entries)
+}
+
+/// Convert a v2 archive into the legacy text format. Returns the number of
+/// entries written.
+pub fn to_text<R: Read, W: Write>(input: R, output: W) -> io::Result<usize> {
+    let mut reader = Reader::new(BufReader::new(input), None)?;
+    let mut output = BufWriter::new(output);
+    let mut entries = 0;
+    while let Some(record) = reader.next_record()? {
+        if matches!(record, Record::Entry(_)) {
+            entries += 1;
+        }
+        output.write_all(record.to_text().as_bytes())?;
+    }
+    output.flush()?;
+    Ok(entries)
+}
+
+/// Presents a v2 archive as a text stream.
+struct TextStream<R: Read> {
+    reader: Reader<R>,
+    buf: Vec<u8>,
+    pos: usize,
+}
+
+impl<R: Read> Read for TextStream<R> {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        while self.pos == self.buf.len() {
+            match self.reader.next_record()? {
+                Some(record) => {
+                    self.buf = record.to_text().into_bytes();
+                    self.pos = 0;
+                }
+                None => return Ok(0),
+            }
+        }
+        let n = out.len().min(self.buf.len() - self.pos);
+        out[..n].copy_from_slice(&self.buf[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+/// Where each frame starts, in the text view and in the file.
+#[derive(Default)]
+struct FrameIndex {
+    /// `(text offset, file offset)` of every frame
+    starts: Vec<(u64, u64)>,
+    text_len: u64,
+    file_len: u64,
+}
+
+/// A v2 archive on the local filesystem, exposed to `Quiver` as legacy text.
+///
+/// Offsets and lengths refer to the text view. The first access builds a
+/// frame index, so later reads at an offset start at the right frame.
+pub struct V2File {
+    path: PathBuf,
+    pinned: Option<File>,
+    frames: Mutex<Option<FrameIndex>>,
+    /// Write entries as compressed records
+    zstd: bool,
+}
+
+impl V2File {
+    /// With `pin`, reads keep going through a handle opened now, like
+    /// [`super::storage::LocalFile::pinned`].
+    pub fn new<P: AsRef<Path>>(path: P, pin: bool) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let pinned = if pin && path.exists() {
+            Some(File::open(&path)?)
+        } else {
+            None
+        };
+        Ok(Self {
+            path,
+            pinned,
+            frames: Mutex::new(None),
+            zstd: false,
+        })
+    }
+
+    /// Append entries as zstd-compressed records; existing records are
+    /// read either way.
+    pub fn zstd(mut self) -> Self {
+        self.zstd = true;
+        self
+    }
+
+    fn raw_len(&self) -> io::Result<u64> {
+        match &self.pinned {
+            Some(file) => Ok(file.metadata()?.len()),
+            None if !self.path.exists() => Ok(0),
+            None => Ok(self.path.metadata()?.len()),
+        }
+    }
+
+    fn raw_reader_at(&self, offset: u64) -> io::Result<Box<dyn Read + '_>> {
+        match &self.pinned {
+            Some(file) => Ok(Box::new(PositionalReader { file, pos: offset })),
+            None => {
+                let mut file = File::open(&self.path)?;
+                file.seek(SeekFrom::Start(offset))?;
+                Ok(Box::new(file))
+            }
+        }
+    }
+
+    fn frames(&self) -> io::Result<MutexGuard<'_, Option<FrameIndex>>> {
+        let mut frames = self.frames.lock().unwrap_or_else(|e| e.into_inner());
+        if frames.is_none() {
+            let mut index = FrameIndex::default();
+            if self.raw_len()? > 0 {
+                let raw = BufReader::new(self.raw_reader_at(0)?);
+                let mut reader = Reader::new(raw, self.name())?;
+                loop {
+                    let start = reader.offset();
+                    let Some(record) = reader.next_record()? else {
+                        break;
+                    };
+                    index.starts.push((index.text_len, start));
+                    index.text_len += record.to_text().len() as u64;
+                }
+                index.file_len = reader.offset();
+            }
+            *frames = Some(index);
+        }
+        Ok(frames)
+    }
+}
+
+impl Storage for V2File {
+    fn exists(&self) -> bool {
+        self.pinned.is_some() || self.path.exists()
+    }
+
+    fn len(&self) -> io::Result<u64> {
+        Ok(self.frames()?.as_ref().map_or(0, |f| f.text_len))
+    }
+
+    fn reader(&self) -> io::Result<Box<dyn Read + '_>> {
+        self.reader_at(0)
+    }
+
+    fn reader_at(&self, offset: u64) -> io::Result<Box<dyn Read + '_>> {
+        let (text_start, file_start) = {
+            let frames = self.frames()?;
+            let starts = frames.as_ref().map_or(&[][..], |f| &f.starts[..]);
+            match starts.partition_point(|&(text, _)| text <= offset) {
+                0 => return Ok(Box::new(io::empty())),
+                i => starts[i - 1],
+            }
+        };
+        let raw = BufReader::new(self.raw_reader_at(file_start)?);
+        let mut stream = TextStream {
+            reader: Reader::resume(raw, file_start, self.name()),
+            buf: Vec::new(),
+            pos: 0,
+        };
+        io::copy(&mut (&mut stream).take(offset - text_start), &mut io::sink())?;
+        Ok(Box::new(stream))
+    }
+
+    fn append(&mut self, data: &[u8]) -> io::Result<()> {
+        let records = text_to_records(data);
+        let mut frames = self.frames()?;
+        let index = frames.get_or_insert_with(FrameIndex::default);
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        let mut writer = BufWriter::new(file);
+        if index.file_len == 0 {
+            writer.write_all(&PREAMBLE)?;
+            index.file_len = PREAMBLE.len() as u64;
+        }
+        for record in &records {
+            let frame = if self.zstd {
+                encode_compressed_record(record)?
+            } else {
+                encode_record(record)?
+            };
+            writer.write_all(&frame)?;
+            index.starts.push((index.text_len, index.file_len));
+            index.text_len += record.to_text().len() as u64;
+            index.file_len += frame.len() as u64;
+        }
+        writer.flush()
+    }
+
+    fn stage(&self) -> io::Result<Option<Staged>> {
+        Staged::new(&self.path).map(Some)
+    }
+
+    fn replace(&mut self, data: &mut dyn Read) -> io::Result<()> {
+        write_atomically(&self.path, |file| {
+            from_text(BufReader::new(data), file)?;
+            Ok(0)
+        })?;
+        *self.frames.lock().unwrap_or_else(|e| e.into_inner()) = None;
+        if self.pinned.is_some() {
+            self.pinned = Some(File::open(&self.path)?);
+        }
+        Ok(())
+    }
+
+    /// The frames whose text starts inside the range, headers and checksums
+    /// included.
+    fn stored_len(&self, offset: u64, len: u64) -> io::Result<StoredLen> {
+        let frames = self.frames()?;
+        let Some(index) = frames.as_ref() else {
+            return Ok(StoredLen { bytes: 0, exact: true });
+        };
+        let first = index.starts.partition_point(|&(text, _)| text < offset);
+        let last = index.starts.partition_point(|&(text, _)| text < offset + len);
+        let file_offset = |i: usize| index.starts.get(i).map_or(index.file_len, |&(_, file)| file);
+        Ok(StoredLen {
+            bytes: file_offset(last) - file_offset(first),
+            exact: true,
+        })
+    }
+
+    fn name(&self) -> Option<String> {
+        Some(self.path.display().to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn records() -> Vec<Record> {
+        vec![
+            Record::Header(vec![("creator".to_string(), "test".to_string())]),
+            Record::Entry(Entry {
+                tag: "a".to_string(),
+                score: Some("plddt=90.5|rmsd=1.2".to_string()),
+                meta: vec![("seq".to_string(), "AG".to_string())],
+                lines: vec!["ATOM 1".to_string(), "END".to_string()],
+                format: PayloadFormat::Pdb,
+            }),
+            Record::Entry(Entry {
+                tag: "b".to_string(),
+                lines: vec!["data_b".to_string()],
+                format: PayloadFormat::Cif,
+                ..Entry::default()
+            }),
+        ]
+    }
+
+    fn encode_all(encode: fn(&Record) -> io::Result<Vec<u8>>) -> Vec<u8> {
+        let mut data = PREAMBLE.to_vec();
+        for record in records() {
+            data.extend(encode(&record).unwrap());
+        }
+        data
+    }
+
+    #[test]
+    fn text_round_trip() {
+        let text: String = records().iter().map(Record::to_text).collect();
+        let mut binary = Vec::new();
+        assert_eq!(from_text(text.as_bytes(), &mut binary).unwrap(), 2);
+        assert!(is_v2(&binary));
+        let mut back = Vec::new();
+        assert_eq!(to_text(&binary[..], &mut back).unwrap(), 2);
+        assert_eq!(String::from_utf8(back).unwrap(), text);
+        assert_eq!(text_to_records(text.as_bytes()), records());
+    }
+
+    #[test]
+    fn compressed_entries_decode_the_same() {
+        let data = encode_all(encode_compressed_record);
+        assert_ne!(data, encode_all(encode_record));
+        let mut reader = Reader::new(&data[..], None).unwrap();
+        let mut decoded = Vec::new();
+        while let Some(record) = reader.next_record().unwrap() {
+            decoded.push(record);
+        }
+        assert_eq!(decoded, records());
+        assert_eq!(reader.offset(), data.len() as u64);
+    }
+
+    #[test]
+    fn corruption_is_reported_with_its_place() {
+        let data = encode_all(encode_record);
+        let mut flipped = data.clone();
+        let last = flipped.len() - 10;
+        flipped[last] ^= 0xff;
+        let mut reader = Reader::new(&flipped[..], Some("x.qv2".to_string())).unwrap();
+        let err = loop {
+            match reader.next_record() {
+                Ok(Some(_)) => continue,
+                Ok(None) => panic!("corruption not detected"),
+                Err(e) => break e.to_string(),
+            }
+        };
+        assert!(err.starts_with("checksum mismatch in record 2"), "{}", err);
+        assert!(err.ends_with("after tag a in x.qv2"), "{}", err);
+
+        let mut reader = Reader::new(&data[..data.len() - 1], None).unwrap();
+        let err = std::iter::from_fn(|| reader.next_record().transpose()).find_map(Result::err).unwrap();
+        assert!(err.to_string().starts_with("truncated record"));
+        assert!(Reader::new(&b"QV_TAG a\n"[..], None).is_err());
+    }
+}
@@ -0,0 +1,161 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+use super::convert::fnv1a64;
+use super::{Entry, Quiver, QuiverError};
+
+/// Where a structure with a given content hash was found.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HashLocation {
+    pub archive: String,
+    pub tag: String,
+}
+
+/// Content hashes of structure payloads across one or more archives.
+///
+/// Only the payload lines are hashed, so the same structure stored under
+/// different tags or with different scores still matches.
+#[derive(Debug, Default)]
+pub struct HashIndex {
+    entries: HashMap<(u64, usize), Vec<HashLocation>>,
+}
+
+/// Hash and byte length of an entry's payload.
+pub fn payload_hash(entry: &Entry) -> (u64, usize) {
+    let mut payload = String::new();
+    for line in &entry.lines {
+        payload.push_str(line.trim_end_matches('\n'));
+        payload.push('\n');
+    }
+    (fnv1a64(payload.as_bytes()), payload.len())
+}
+
+impl HashIndex {
+    /// Hash every entry of the given archives.
+    pub fn build<P: AsRef<Path>>(quiver_files: &[P]) -> Result<Self, QuiverError> {
+        let mut index = Self::default();
+        for path in quiver_files {
+            let archive = path.as_ref().to_string_lossy().to_string();
+            let qv = Quiver::new(path, "r")?;
+            qv.scan_entries(|entry| {
+                index.entries.entry(payload_hash(&entry)).or_default().push(HashLocation {
+                    archive: archive.clone(),
+                    tag: entry.tag,
+                });
+                Ok(())
+            })?;
+        }
+        Ok(index)
+    }
+
+    /// Read an index written by [`HashIndex::save`].
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, QuiverError> {
+        let mut index = Self::default();
+        let reader = BufReader::new(File::open(path)?);
+        for (lineno, line) in reader.lines().enumerate() {
+            let line = line?;
+            let parts: Vec<&str> = line.splitn(4, '\t').collect();
+            let parsed = match parts.as_slice() {
+                [hash, len, archive, tag] => u64::from_str_radix(hash, 16)
+                    .ok()
+                    .zip(len.parse::<usize>().ok())
+                    .map(|key| (key, archive, tag)),
+                _ => None,
+            };
+            let Some((key, archive, tag)) = parsed else {
+                return Err(QuiverError::InvalidOperation(format!(
+                    "Malformed hash index line {}: {}",
+                    lineno + 1,
+                    line
+                )));
+            };
+            index.entries.entry(key).or_default().push(HashLocation {
+                archive: archive.to_string(),
+                tag: tag.to_string(),
+            });
+        }
+        Ok(index)
+    }
+
+    /// Write the index as `hash<TAB>length<TAB>archive<TAB>tag` lines.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), QuiverError> {
+        let mut out = BufWriter::new(File::create(path)?);
+        let mut keys: Vec<_> = self.entries.keys().collect();
+        keys.sort();
+        for key in keys {
+            for loc in &self.entries[key] {
+                writeln!(out, "{:016x}\t{}\t{}\t{}", key.0, key.1, loc.archive, loc.tag)?;
+            }
+        }
+        out.flush()?;
+        Ok(())
+    }
+
+    /// Number of distinct payloads in the index.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Entries of `qv` whose payload is already in the index, with every
+    /// location it was seen at.
+    pub fn intersect(&self, qv: &Quiver) -> Result<Vec<(String, Vec<HashLocation>)>, QuiverError> {
+        let mut hits = Vec::new();
+        qv.scan_entries(|entry| {
+            if let Some(locations) = self.entries.get(&payload_hash(&entry)) {
+                hits.push((entry.tag, locations.clone()));
+            }
+            Ok(())
+        })?;
+        Ok(hits)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::testdata::scratch_dir;
+    use super::*;
+
+    fn write_archive(path: &Path, entries: &[(&str, &str, Option<&str>)]) {
+        let mut qv = Quiver::new(path, "w").unwrap();
+        for (tag, line, score) in entries {
+            qv.add_pdb(&[line.to_string()], tag, *score).unwrap();
+        }
+        qv.flush().unwrap();
+    }
+
+    #[test]
+    fn same_payload_matches_across_tags_and_scores() {
+        let dir = scratch_dir("hashindex-match");
+        let (a, b) = (dir.join("a.qv"), dir.join("b.qv"));
+        write_archive(&a, &[("x", "ATOM 1", Some("plddt=90")), ("y", "ATOM 2", None)]);
+        write_archive(&b, &[("z", "ATOM 1", None), ("w", "ATOM 3", None)]);
+        let index = HashIndex::build(&[&a]).unwrap();
+        assert_eq!(index.len(), 2);
+        let hits = index.intersect(&Quiver::new(&b, "r").unwrap()).unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].0, "z");
+        assert_eq!(hits[0].1, vec![HashLocation { archive: a.to_string_lossy().to_string(), tag: "x".to_string() }]);
+    }
+
+    #[test]
+    fn save_and_load() {
+        let dir = scratch_dir("hashindex-save");
+        let a = dir.join("a.qv");
+        write_archive(&a, &[("x", "ATOM 1", None), ("y", "ATOM 1", None)]);
+        let index = HashIndex::build(&[&a]).unwrap();
+        index.save(dir.join("index.tsv")).unwrap();
+        let loaded = HashIndex::load(dir.join("index.tsv")).unwrap();
+        assert_eq!(loaded.len(), 1);
+        assert_eq!(loaded.entries, index.entries);
+
+        std::fs::write(dir.join("bad.tsv"), "zz\t1\ta.qv\tx\n").unwrap();
+        let err = HashIndex::load(dir.join("bad.tsv")).unwrap_err();
+        assert!(format!("{:?}", err).contains("Malformed hash index line 1"));
+    }
+}
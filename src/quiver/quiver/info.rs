@@ -0,0 +1,204 @@
+use std::collections::{BTreeMap, HashSet};
+
+use super::transform::SplitMix64;
+use super::{parse_score_line, EntryStat, Quiver, QuiverError};
+
+/// Summary of one per-entry quantity.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SizeStats {
+    pub min: u64,
+    pub max: u64,
+    pub mean: f64,
+    pub median: f64,
+}
+
+impl SizeStats {
+    fn from_values(mut values: Vec<u64>) -> Self {
+        if values.is_empty() {
+            return Self::default();
+        }
+        values.sort_unstable();
+        let n = values.len();
+        let median = if n % 2 == 1 {
+            values[n / 2] as f64
+        } else {
+            (values[n / 2 - 1] + values[n / 2]) as f64 / 2.0
+        };
+        Self {
+            min: values[0],
+            max: values[n - 1],
+            mean: values.iter().sum::<u64>() as f64 / n as f64,
+            median,
+        }
+    }
+}
+
+/// Archive statistics, possibly estimated from a sample of entries.
+#[derive(Debug, Clone, Default)]
+pub struct ArchiveInfo {
+    /// Number of entries in the archive (always exact)
+    pub entries: usize,
+    /// Number of entries the statistics below were computed from
+    pub sampled: usize,
+    /// True when `sampled < entries`, i.e. the statistics are estimates
+    pub estimate: bool,
+    /// Entry size in bytes, including its `QV_*` lines
+    pub bytes: SizeStats,
+    /// Bytes the archive spends per entry, see [`Quiver::stat`]
+    pub stored: SizeStats,
+    /// False when stored sizes are estimates (gzip and zstd archives)
+    pub stored_exact: bool,
+    /// Total entry and stored bytes of the sampled entries
+    pub bytes_total: u64,
+    pub stored_total: u64,
+    /// Sampled entries with the lowest compression ratio, worst first
+    pub least_compressible: Vec<EntryStat>,
+    /// `ATOM`/`HETATM` records per entry
+    pub atoms: SizeStats,
+    /// Fraction of entries with a `QV_SCORE` line
+    pub score_coverage: f64,
+    /// Fraction of entries carrying each score field, by field name
+    pub field_coverage: BTreeMap<String, f64>,
+}
+
+/// Number of entries kept in [`ArchiveInfo::least_compressible`].
+pub const OUTLIERS: usize = 5;
+
+impl ArchiveInfo {
+    /// Uncompressed over stored bytes of the sampled entries.
+    pub fn ratio(&self) -> f64 {
+        if self.stored_total == 0 {
+            1.0
+        } else {
+            self.bytes_total as f64 / self.stored_total as f64
+        }
+    }
+}
+
+/// Pick `k` distinct indices below `n` (Floyd's algorithm), in ascending order.
+fn sample_indices(n: usize, k: usize, rng: &mut SplitMix64) -> Vec<usize> {
+    let mut chosen = HashSet::with_capacity(k);
+    for j in n - k..n {
+        let t = (rng.next_u64() % (j as u64 + 1)) as usize;
+        if !chosen.insert(t) {
+            chosen.insert(j);
+        }
+    }
+    let mut indices: Vec<usize> = chosen.into_iter().collect();
+    indices.sort_unstable();
+    indices
+}
+
+/// Compute archive statistics from at most `sample` randomly chosen entries.
+///
+/// Entries are read through the tag index, so only the sampled entries are
+/// parsed. With `sample >= entries` every entry is read and the result is
+/// exact. The same `seed` always picks the same entries.
+pub fn info(qv: &Quiver, sample: usize, seed: u64) -> Result<ArchiveInfo, QuiverError> {
+    let entries = qv.size();
+    let k = sample.min(entries);
+    let indices = if k == entries {
+        (0..entries).collect()
+    } else {
+        sample_indices(entries, k, &mut SplitMix64::new(seed))
+    };
+
+    let mut bytes = Vec::with_capacity(k);
+    let mut stats = Vec::with_capacity(k);
+    let mut atoms = Vec::with_capacity(k);
+    let mut scored = 0usize;
+    let mut field_counts: BTreeMap<String, usize> = BTreeMap::new();
+    for i in indices {
+        let raw = qv.entry_bytes(i)?;
+        bytes.push(raw.len() as u64);
+        stats.push(qv.stat_at(i)?);
+        let text = String::from_utf8_lossy(&raw);
+        let mut natoms = 0u64;
+        let mut fields: HashSet<String> = HashSet::new();
+        let mut has_score = false;
+        for line in text.lines() {
+            if line.starts_with("ATOM") || line.starts_with("HETATM") {
+                natoms += 1;
+            } else if let Some((_, score)) = parse_score_line(line) {
+                has_score = true;
+                fields.extend(score.into_iter().map(|(k, _)| k));
+            }
+        }
+        atoms.push(natoms);
+        if has_score {
+            scored += 1;
+        }
+        for field in fields {
+            *field_counts.entry(field).or_default() += 1;
+        }
+    }
+
+    let stored: Vec<u64> = stats.iter().map(|s| s.stored).collect();
+    let stored_exact = stats.iter().all(|s| s.exact);
+    let bytes_total = stats.iter().map(|s| s.bytes).sum();
+    let stored_total = stored.iter().sum();
+    stats.sort_by(|a, b| a.ratio().total_cmp(&b.ratio()));
+    stats.truncate(OUTLIERS);
+
+    let fraction = |count: usize| if k == 0 { 0.0 } else { count as f64 / k as f64 };
+    Ok(ArchiveInfo {
+        entries,
+        sampled: k,
+        estimate: k < entries,
+        bytes: SizeStats::from_values(bytes),
+        stored: SizeStats::from_values(stored),
+        stored_exact,
+        bytes_total,
+        stored_total,
+        least_compressible: stats,
+        atoms: SizeStats::from_values(atoms),
+        score_coverage: fraction(scored),
+        field_coverage: field_counts
+            .into_iter()
+            .map(|(field, count)| (field, fraction(count)))
+            .collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::testdata::{generate_test_archive, scratch_dir};
+    use super::*;
+
+    #[test]
+    fn sizes_summary() {
+        let stats = SizeStats::from_values(vec![4, 1, 3, 2]);
+        assert_eq!(stats, SizeStats { min: 1, max: 4, mean: 2.5, median: 2.5 });
+        assert_eq!(SizeStats::from_values(vec![5, 1, 3]).median, 3.0);
+        assert_eq!(SizeStats::from_values(Vec::new()), SizeStats::default());
+    }
+
+    #[test]
+    fn samples_are_distinct_and_seeded() {
+        let pick = |seed| sample_indices(100, 10, &mut SplitMix64::new(seed));
+        let indices = pick(7);
+        assert_eq!(indices.len(), 10);
+        assert!(indices.windows(2).all(|w| w[0] < w[1]));
+        assert!(indices.iter().all(|&i| i < 100));
+        assert_eq!(indices, pick(7));
+    }
+
+    #[test]
+    fn full_and_sampled_statistics() {
+        let path = scratch_dir("info-stats").join("a.qv");
+        generate_test_archive(&path, 20, 5, true, 1).unwrap();
+        let qv = Quiver::new(&path, "r").unwrap();
+        let exact = info(&qv, 100, 0).unwrap();
+        assert_eq!((exact.entries, exact.sampled, exact.estimate), (20, 20, false));
+        assert_eq!(exact.atoms.min, 5);
+        assert_eq!(exact.atoms.max, 5);
+        assert_eq!(exact.score_coverage, 1.0);
+        assert_eq!(exact.field_coverage.keys().collect::<Vec<_>>(), vec!["plddt", "score"]);
+        assert_eq!(exact.ratio(), 1.0);
+        assert_eq!(exact.least_compressible.len(), OUTLIERS);
+
+        let sampled = info(&qv, 4, 0).unwrap();
+        assert_eq!((sampled.entries, sampled.sampled, sampled.estimate), (20, 4, true));
+        assert_eq!(sampled.bytes.min, info(&qv, 4, 0).unwrap().bytes.min);
+    }
+}
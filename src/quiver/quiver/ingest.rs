@@ -0,0 +1,369 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Component, Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread;
+
+use super::compress::open_reader;
+use super::shard::wildcard_match;
+use super::validate::StructureContent;
+use super::warnings::{WarningCode, Warnings};
+use super::{Entry, PayloadFormat, Quiver, QuiverError};
+
+/// How an ingested file's tag is derived from its path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TagFrom {
+    /// `run1/model.pdb` → `model`
+    Stem,
+    /// `run1/model.pdb` → `run1_model`
+    RelativePath,
+}
+
+impl FromStr for TagFrom {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "stem" => Ok(TagFrom::Stem),
+            "relative-path" | "relative_path" => Ok(TagFrom::RelativePath),
+            other => Err(format!(
+                "Unknown tag source '{}', expected stem or relative-path",
+                other
+            )),
+        }
+    }
+}
+
+/// What to do when two inputs derive the same tag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OnDuplicate {
+    Error,
+    Skip,
+    /// Append `_2`, `_3`, ... until the tag is unique
+    Rename,
+}
+
+impl FromStr for OnDuplicate {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "error" => Ok(OnDuplicate::Error),
+            "skip" => Ok(OnDuplicate::Skip),
+            "rename" => Ok(OnDuplicate::Rename),
+            other => Err(format!(
+                "Unknown duplicate policy '{}', expected error, skip or rename",
+                other
+            )),
+        }
+    }
+}
+
+/// File name suffixes stripped when deriving tags, longest first.
+const STRUCTURE_SUFFIXES: [&str; 6] = [".pdb.gz", ".pdb.zst", ".pdb", ".cif.gz", ".cif.zst", ".cif"];
+
+fn strip_structure_suffix(name: &str) -> &str {
+    STRUCTURE_SUFFIXES
+        .iter()
+        .find_map(|suffix| name.strip_suffix(suffix))
+        .unwrap_or(name)
+}
+
+/// Open a structure file for streaming, decompressing gzip or zstd input
+/// on the fly.
+///
+/// Compression is detected from the magic bytes, not the file name.
+pub fn open_structure<P: AsRef<Path>>(path: P) -> io::Result<Box<dyn Read>> {
+    open_reader(path)
+}
+
+/// Derives unique tags for ingested files.
+#[derive(Debug, Clone)]
+pub struct TagPolicy {
+    pub tag_from: TagFrom,
+    pub prefix: String,
+    pub suffix: String,
+    pub on_duplicate: OnDuplicate,
+    seen: HashSet<String>,
+}
+
+impl TagPolicy {
+    pub fn new(tag_from: TagFrom, prefix: &str, suffix: &str, on_duplicate: OnDuplicate) -> Self {
+        Self {
+            tag_from,
+            prefix: prefix.to_string(),
+            suffix: suffix.to_string(),
+            on_duplicate,
+            seen: HashSet::new(),
+        }
+    }
+
+    /// The tag a path maps to, before duplicate handling.
+    ///
+    /// Whitespace is replaced with `_` since tags are whitespace-delimited.
+    pub fn derive(&self, path: &Path) -> String {
+        let base = match self.tag_from {
+            TagFrom::Stem => path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .map(strip_structure_suffix)
+                .unwrap_or("UNKNOWN")
+                .to_string(),
+            TagFrom::RelativePath => {
+                let cwd = std::env::current_dir().ok();
+                let rel = cwd
+                    .as_deref()
+                    .and_then(|cwd| path.strip_prefix(cwd).ok())
+                    .unwrap_or(path);
+                let parts: Vec<String> = rel
+                    .components()
+                    .filter_map(|c| match c {
+                        Component::Normal(part) => Some(part.to_string_lossy().to_string()),
+                        _ => None,
+                    })
+                    .collect();
+                strip_structure_suffix(&parts.join("_")).to_string()
+            }
+        };
+        format!("{}{}{}", self.prefix, base, self.suffix)
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join("_")
+    }
+
+    /// Derive and reserve the tag for `path`.
+    ///
+    /// Returns `Ok(None)` if the file should be skipped under
+    /// [`OnDuplicate::Skip`], and an error under [`OnDuplicate::Error`].
+    pub fn assign(&mut self, path: &Path) -> Result<Option<String>, String> {
+        let tag = self.derive(path);
+        if self.seen.insert(tag.clone()) {
+            return Ok(Some(tag));
+        }
+        match self.on_duplicate {
+            OnDuplicate::Error => Err(format!(
+                "Duplicate tag '{}' derived from {}",
+                tag,
+                path.display()
+            )),
+            OnDuplicate::Skip => Ok(None),
+            OnDuplicate::Rename => {
+                let mut n = 2;
+                loop {
+                    let candidate = format!("{}_{}", tag, n);
+                    if self.seen.insert(candidate.clone()) {
+                        return Ok(Some(candidate));
+                    }
+                    n += 1;
+                }
+            }
+        }
+    }
+}
+
+/// Every file under `dir` whose name matches `pattern` (`*` and `?`), in
+/// path order. Symbolic links to directories are not followed.
+pub fn find_structures(dir: &Path, pattern: &str) -> io::Result<Vec<PathBuf>> {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let mut found = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        for entry in fs::read_dir(&dir)? {
+            let entry = entry?;
+            let file_type = entry.file_type()?;
+            if file_type.is_dir() {
+                pending.push(entry.path());
+            } else if wildcard_match(&pattern, &entry.file_name().to_string_lossy().chars().collect::<Vec<_>>()) {
+                found.push(entry.path());
+            }
+        }
+    }
+    found.sort();
+    Ok(found)
+}
+
+/// Read one structure file into an entry tagged `tag`.
+fn read_entry(path: &Path, tag: &str) -> io::Result<Entry> {
+    let mut contents = String::new();
+    open_structure(path)?.read_to_string(&mut contents)?;
+    Ok(Entry {
+        tag: tag.to_string(),
+        lines: contents.lines().map(str::to_string).collect(),
+        format: PayloadFormat::from_path(path),
+        ..Entry::default()
+    })
+}
+
+/// Output of [`ingest_directory`].
+#[derive(Debug, Clone, Default)]
+pub struct IngestReport {
+    /// Entries written to the archive
+    pub written: usize,
+    /// Files skipped or renamed because their tag was taken
+    pub warnings: Warnings,
+}
+
+/// Write every file under `dir` matching `pattern` (see [`find_structures`])
+/// into a new archive at `output`, tagged by file stem.
+///
+/// `threads` workers read and decompress files while the archive is written
+/// in path order, and only a few files per thread are held at a time, so the
+/// output never has to fit in memory. Tags are assigned before reading, so
+/// they do not depend on thread timing.
+///
+/// Unless `allow_non_structure` is set, a file without coordinates (see
+/// [`StructureContent`]) fails the ingestion, so a pattern that also matches
+/// logs or notes cannot fill the archive with them.
+///
+/// With `reproducible`, entries are written in tag order instead of path
+/// order, so the archive is byte-identical for the same set of files
+/// wherever the tree is rooted (see [`super::reproducible`]).
+pub fn ingest_directory<P: AsRef<Path>, Q: AsRef<Path>>(
+    dir: P,
+    pattern: &str,
+    output: Q,
+    threads: usize,
+    on_duplicate: OnDuplicate,
+    allow_non_structure: bool,
+    reproducible: bool,
+) -> Result<IngestReport, QuiverError> {
+    let output = output.as_ref();
+    if output.exists() {
+        return Err(QuiverError::InvalidOperation(format!("'{}' already exists", output.display())));
+    }
+    let mut policy = TagPolicy::new(TagFrom::Stem, "", "", on_duplicate);
+    let mut jobs = Vec::new();
+    let mut warnings = Warnings::new();
+    for path in find_structures(dir.as_ref(), pattern)? {
+        match policy.assign(&path).map_err(QuiverError::InvalidOperation)? {
+            Some(tag) => {
+                let derived = policy.derive(&path);
+                if tag != derived {
+                    warnings.push(
+                        WarningCode::RenamedDuplicate,
+                        format!("Duplicate tag {} for {}, tagged {}", derived, path.display(), tag),
+                    );
+                }
+                jobs.push((path, tag));
+            }
+            None => warnings.push(
+                WarningCode::SkippedFile,
+                format!("Duplicate tag for {}, skipping", path.display()),
+            ),
+        }
+    }
+
+    if reproducible {
+        jobs.sort_by(|a, b| a.1.cmp(&b.1));
+    }
+
+    let mut qv = Quiver::new(output, "w")?;
+    if !allow_non_structure {
+        qv.add_validator(Arc::new(StructureContent));
+    }
+    let threads = threads.max(1);
+    let next = AtomicUsize::new(0);
+    let written = thread::scope(|s| -> Result<usize, QuiverError> {
+        let (tx, rx) = mpsc::sync_channel(threads * 2);
+        for _ in 0..threads {
+            let (tx, jobs, next) = (tx.clone(), &jobs, &next);
+            s.spawn(move || loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                let Some((path, tag)) = jobs.get(index) else {
+                    break;
+                };
+                if tx.send((index, read_entry(path, tag))).is_err() {
+                    break;
+                }
+            });
+        }
+        drop(tx);
+        // Entries read ahead of the next one to write, by position
+        let mut ready = BTreeMap::new();
+        let mut written = 0;
+        for (index, entry) in rx {
+            ready.insert(index, entry);
+            while let Some(entry) = ready.remove(&written) {
+                let path = &jobs[written].0;
+                let entry = entry.map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path.display(), e)))?;
+                qv.add_entry(&entry)?;
+                written += 1;
+            }
+        }
+        Ok(written)
+    })?;
+    qv.finish()?;
+    Ok(IngestReport { written, warnings })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::compress::Compression;
+    use super::super::testdata::scratch_dir;
+    use super::*;
+
+    const ATOM: &str = "ATOM      1  CA  ALA A   1       0.000   0.000   0.000  1.00  0.00           C\n";
+
+    #[test]
+    fn tags_from_paths() {
+        let path = Path::new("run1/model 1.pdb.gz");
+        let stem = TagPolicy::new(TagFrom::Stem, "x_", "", OnDuplicate::Error);
+        assert_eq!(stem.derive(path), "x_model_1");
+        let relative = TagPolicy::new(TagFrom::RelativePath, "", "_v2", OnDuplicate::Error);
+        assert_eq!(relative.derive(path), "run1_model_1_v2");
+        assert_eq!("relative_path".parse::<TagFrom>(), Ok(TagFrom::RelativePath));
+        assert!("name".parse::<TagFrom>().is_err());
+    }
+
+    #[test]
+    fn duplicate_policies() {
+        let paths = [Path::new("a/m.pdb"), Path::new("b/m.pdb"), Path::new("c/m.cif")];
+        let assign_all = |on_duplicate| {
+            let mut policy = TagPolicy::new(TagFrom::Stem, "", "", on_duplicate);
+            paths.iter().map(|p| policy.assign(p)).collect::<Vec<_>>()
+        };
+        let renamed: Vec<_> = assign_all(OnDuplicate::Rename).into_iter().map(|t| t.unwrap().unwrap()).collect();
+        assert_eq!(renamed, vec!["m", "m_2", "m_3"]);
+        let skipped = assign_all(OnDuplicate::Skip);
+        assert_eq!(skipped[1], Ok(None));
+        let failed = assign_all(OnDuplicate::Error);
+        assert!(failed[1].as_ref().unwrap_err().contains("Duplicate tag 'm'"));
+    }
+
+    #[test]
+    fn ingest_reads_compressed_files_in_path_order() {
+        let dir = scratch_dir("ingest-tree");
+        let tree = dir.join("tree");
+        fs::create_dir_all(tree.join("run1")).unwrap();
+        fs::create_dir_all(tree.join("run2")).unwrap();
+        fs::write(tree.join("run1/m.pdb"), ATOM).unwrap();
+        let mut packed = Vec::new();
+        Compression::Gzip.encode(&mut ATOM.as_bytes(), &mut packed).unwrap();
+        fs::write(tree.join("run2/m.pdb.gz"), packed).unwrap();
+        fs::write(tree.join("run2/notes.txt"), "not a structure\n").unwrap();
+        assert_eq!(find_structures(&tree, "*.pdb*").unwrap().len(), 2);
+
+        let output = dir.join("out.qv");
+        let report = ingest_directory(&tree, "*.pdb*", &output, 2, OnDuplicate::Rename, false, false).unwrap();
+        assert_eq!(report.written, 2);
+        assert_eq!(report.warnings.with_code(WarningCode::RenamedDuplicate).count(), 1);
+        let qv = Quiver::new(&output, "r").unwrap();
+        assert_eq!(qv.get_tags(), vec!["m", "m_2"]);
+        assert_eq!(qv.get_pdblines("m_2").unwrap(), vec![ATOM.trim_end()]);
+        assert!(ingest_directory(&tree, "*.pdb*", &output, 1, OnDuplicate::Rename, false, false).is_err());
+    }
+
+    #[test]
+    fn non_structures_fail_unless_allowed() {
+        let dir = scratch_dir("ingest-notes");
+        let tree = dir.join("tree");
+        fs::create_dir_all(&tree).unwrap();
+        fs::write(tree.join("a.txt"), "a log\n").unwrap();
+        assert!(ingest_directory(&tree, "*", dir.join("strict.qv"), 1, OnDuplicate::Error, false, false).is_err());
+        let report = ingest_directory(&tree, "*", dir.join("loose.qv"), 1, OnDuplicate::Error, true, false).unwrap();
+        assert_eq!(report.written, 1);
+    }
+}
@@ -0,0 +1,107 @@
+//! Per-entry checksums stored as `QV_HASH` lines.
+//!
+//! A writer with hashes turned on (see `Quiver::set_write_hashes`) adds
+//! `QV_HASH <tag> fnv1a64:<digest>` after an entry's score and metadata
+//! lines. The digest covers the payload lines only, each ended by a single
+//! `\n`, so it survives renaming, rescoring and relabelling but not a
+//! truncated or partially written structure. Readers skip the line; tools
+//! that rewrite payloads (`qvclean`, `qvjitter`, conversion) drop it.
+
+use super::convert::fnv1a64;
+use super::RESERVED_PREFIXES;
+
+/// Algorithm prefix of the digests written.
+pub const HASH_ALGORITHM: &str = "fnv1a64";
+
+/// Outcome of checking one entry, see `Quiver::verify`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Integrity {
+    /// The payload matches its `QV_HASH` line
+    Ok,
+    /// The entry has no `QV_HASH` line
+    Unhashed,
+    /// The payload does not match: truncated, corrupted or edited
+    Mismatch { stored: String, actual: String },
+    /// The payload matches any checksum but holds no structure (see
+    /// [`super::validate::sniff_structure`]), e.g. an ingested log file
+    NotStructure { reason: String },
+}
+
+/// Digest of payload lines, as stored on a `QV_HASH` line.
+pub fn payload_digest<S: AsRef<str>>(lines: &[S]) -> String {
+    let mut payload = String::new();
+    for line in lines {
+        payload.push_str(line.as_ref().trim_end_matches(['\n', '\r']));
+        payload.push('\n');
+    }
+    format!("{}:{:016x}", HASH_ALGORITHM, fnv1a64(payload.as_bytes()))
+}
+
+/// Digest of a payload held as raw text, equal to [`payload_digest`] of its
+/// lines. Text already ending every line with a bare `\n` is hashed as is.
+pub fn raw_payload_digest(payload: &[u8]) -> String {
+    if (payload.is_empty() || payload.ends_with(b"\n")) && !payload.contains(&b'\r') {
+        return format!("{}:{:016x}", HASH_ALGORITHM, fnv1a64(payload));
+    }
+    let mut normalized = Vec::with_capacity(payload.len() + 1);
+    for line in payload.strip_suffix(b"\n").unwrap_or(payload).split(|&b| b == b'\n') {
+        let end = line.iter().rposition(|&b| b != b'\r').map_or(0, |i| i + 1);
+        normalized.extend_from_slice(&line[..end]);
+        normalized.push(b'\n');
+    }
+    format!("{}:{:016x}", HASH_ALGORITHM, fnv1a64(&normalized))
+}
+
+/// Format a `QV_HASH <tag> <digest>` line (without trailing newline).
+pub fn format_hash_line(tag: &str, digest: &str) -> String {
+    format!("QV_HASH {} {}", tag, digest)
+}
+
+/// Check a raw entry block, starting with its `QV_TAG` line, against the
+/// `QV_HASH` line it carries.
+pub fn check_block(block: &str) -> Integrity {
+    let mut stored = None;
+    let mut payload = Vec::new();
+    for line in block.lines().skip(1) {
+        if line.starts_with("QV_HASH") {
+            stored = line.split_whitespace().nth(2).map(str::to_string);
+        } else if !RESERVED_PREFIXES.iter().any(|p| line.starts_with(p)) {
+            payload.push(line);
+        }
+    }
+    let Some(stored) = stored else {
+        return Integrity::Unhashed;
+    };
+    let actual = payload_digest(&payload);
+    if actual == stored {
+        Integrity::Ok
+    } else {
+        Integrity::Mismatch { stored, actual }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn digest_ignores_line_endings() {
+        let digest = payload_digest(&["ATOM 1", "END"]);
+        assert!(digest.starts_with("fnv1a64:"));
+        assert_eq!(digest.len(), "fnv1a64:".len() + 16);
+        assert_eq!(payload_digest(&["ATOM 1\r\n", "END\n"]), digest);
+        assert_eq!(raw_payload_digest(b"ATOM 1\nEND\n"), digest);
+        assert_eq!(raw_payload_digest(b"ATOM 1\r\nEND"), digest);
+        assert_ne!(payload_digest(&["ATOM 1"]), digest);
+    }
+
+    #[test]
+    fn blocks_are_checked_against_their_hash_line() {
+        let digest = payload_digest(&["ATOM 1", "END"]);
+        let block = format!("QV_TAG a\nQV_SCORE a x=1\n{}\nATOM 1\nEND\n", format_hash_line("a", &digest));
+        assert_eq!(check_block(&block), Integrity::Ok);
+        assert_eq!(check_block("QV_TAG a\nATOM 1\n"), Integrity::Unhashed);
+        let truncated = format!("QV_TAG a\n{}\nATOM 1\n", format_hash_line("a", &digest));
+        assert!(matches!(check_block(&truncated), Integrity::Mismatch { stored, .. } if stored == digest));
+    }
+}
@@ -0,0 +1,175 @@
+//! Where extracted entries are written.
+//!
+//! The flat layout writes `<dir>/<tag>.pdb`. The per-tag layout gives every
+//! entry its own folder, `<dir>/<tag>/model.pdb`, next to a `meta.json`
+//! holding its scores, labels, where it came from and a checksum of the
+//! model file, as several analysis pipelines expect one folder per design.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use super::integrity::payload_digest;
+use super::scores::{json_string, ScoreValue};
+use super::{PayloadFormat, Quiver, QuiverError};
+
+/// Name of the metadata file in a per-tag folder.
+pub const META_FILE: &str = "meta.json";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ExtractLayout {
+    /// `<dir>/<tag>.<ext>`
+    #[default]
+    Flat,
+    /// `<dir>/<tag>/model.<ext>` and `<dir>/<tag>/meta.json`
+    PerTag,
+}
+
+impl FromStr for ExtractLayout {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "flat" => Ok(ExtractLayout::Flat),
+            "per-tag" => Ok(ExtractLayout::PerTag),
+            other => Err(format!("Unknown layout '{}', expected one of: flat, per-tag", other)),
+        }
+    }
+}
+
+impl ExtractLayout {
+    /// The file the entry `tag` is written to under `dir`.
+    pub fn model_path(self, dir: &Path, tag: &str, format: PayloadFormat) -> PathBuf {
+        match self {
+            ExtractLayout::Flat => dir.join(format!("{}.{}", tag, format.extension())),
+            ExtractLayout::PerTag => dir.join(tag).join(format!("model.{}", format.extension())),
+        }
+    }
+}
+
+/// What `meta.json` records about one extracted entry.
+pub struct EntryMeta<'a> {
+    pub tag: &'a str,
+    /// The archive the entry was extracted from
+    pub source: &'a Path,
+    pub format: PayloadFormat,
+    pub scores: &'a [(String, ScoreValue)],
+    pub labels: &'a [String],
+}
+
+impl EntryMeta<'_> {
+    /// The `meta.json` document for a model file holding `lines`. The
+    /// checksum is the `QV_HASH` digest of those lines, so it can be checked
+    /// against the model file as written.
+    pub fn to_json<S: AsRef<str>>(&self, model_file: &Path, lines: &[S]) -> String {
+        let file_name = model_file.file_name().map(|n| n.to_string_lossy()).unwrap_or_default();
+        let source = self.source.canonicalize().unwrap_or_else(|_| self.source.to_path_buf());
+        let scores: Vec<String> = self
+            .scores
+            .iter()
+            .map(|(k, v)| format!("    {}: {}", json_string(k), v.to_json()))
+            .collect();
+        let labels: Vec<String> = self.labels.iter().map(|l| json_string(l)).collect();
+        let scores = if scores.is_empty() {
+            "{}".to_string()
+        } else {
+            format!("{{\n{}\n  }}", scores.join(",\n"))
+        };
+        format!(
+            "{{\n  \"tag\": {},\n  \"model\": {},\n  \"format\": {},\n  \"scores\": {},\n  \"labels\": [{}],\n  \
+             \"provenance\": {{\n    \"source\": {},\n    \"tool\": {}\n  }},\n  \"checksum\": {}\n}}\n",
+            json_string(self.tag),
+            json_string(&file_name),
+            json_string(self.format.name()),
+            scores,
+            labels.join(", "),
+            json_string(&source.to_string_lossy()),
+            json_string(concat!("quiver ", env!("CARGO_PKG_VERSION"))),
+            json_string(&payload_digest(lines)),
+        )
+    }
+}
+
+/// Write the `meta.json` beside `model_file`, into which `tag` of `qv` (read
+/// from `source`) was extracted as `lines`.
+pub fn write_meta<S: AsRef<str>>(
+    qv: &Quiver,
+    source: &Path,
+    tag: &str,
+    model_file: &Path,
+    lines: &[S],
+) -> Result<(), QuiverError> {
+    // An entry without a score line has no scores to record
+    let scores = match qv.get_scores(tag) {
+        Ok(scores) => scores,
+        Err(QuiverError::InvalidOperation(_)) => Vec::new(),
+        Err(e) => return Err(e),
+    };
+    let labels = qv.get_labels(tag)?;
+    let meta = EntryMeta {
+        tag,
+        source,
+        format: qv.get_format(tag)?,
+        scores: &scores,
+        labels: &labels,
+    };
+    fs::write(model_file.with_file_name(META_FILE), meta.to_json(model_file, lines))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::testdata::scratch_dir;
+    use super::*;
+
+    #[test]
+    fn model_paths() {
+        let dir = Path::new("out");
+        assert_eq!(ExtractLayout::Flat.model_path(dir, "a", PayloadFormat::Pdb), Path::new("out/a.pdb"));
+        assert_eq!(ExtractLayout::PerTag.model_path(dir, "a", PayloadFormat::Cif), Path::new("out/a/model.cif"));
+        assert_eq!("per-tag".parse::<ExtractLayout>(), Ok(ExtractLayout::PerTag));
+        assert!("nested".parse::<ExtractLayout>().is_err());
+    }
+
+    #[test]
+    fn meta_json_records_scores_and_checksum() {
+        let scores = vec![("plddt".to_string(), ScoreValue::parse("90.5")), ("note".to_string(), ScoreValue::parse("ok"))];
+        let labels = vec!["keep".to_string()];
+        let meta = EntryMeta {
+            tag: "a",
+            source: Path::new("/no/such/a.qv"),
+            format: PayloadFormat::Pdb,
+            scores: &scores,
+            labels: &labels,
+        };
+        let json = meta.to_json(Path::new("out/a/model.pdb"), &["ATOM 1"]);
+        assert!(json.contains("\"tag\": \"a\",\n  \"model\": \"model.pdb\",\n  \"format\": \"pdb\""));
+        assert!(json.contains("\"plddt\": 90.5,\n    \"note\": \"ok\""));
+        assert!(json.contains("\"labels\": [\"keep\"]"));
+        assert!(json.contains("\"source\": \"/no/such/a.qv\""));
+        assert!(json.contains(&format!("\"checksum\": \"{}\"", payload_digest(&["ATOM 1"]))));
+
+        let unscored = EntryMeta { scores: &[], labels: &[], ..meta };
+        assert!(unscored.to_json(Path::new("a.pdb"), &["ATOM 1"]).contains("\"scores\": {},\n  \"labels\": []"));
+    }
+
+    #[test]
+    fn meta_file_beside_the_model() {
+        let dir = scratch_dir("layout-meta");
+        let path = dir.join("a.qv");
+        let mut qv = Quiver::new(&path, "w").unwrap();
+        qv.add_pdb(&["ATOM 1".to_string()], "scored", Some("plddt=90")).unwrap();
+        qv.add_pdb(&["ATOM 2".to_string()], "plain", None).unwrap();
+        qv.flush().unwrap();
+        let qv = Quiver::new(&path, "r").unwrap();
+        for tag in ["scored", "plain"] {
+            let model = ExtractLayout::PerTag.model_path(&dir, tag, PayloadFormat::Pdb);
+            fs::create_dir_all(model.parent().unwrap()).unwrap();
+            write_meta(&qv, &path, tag, &model, &qv.get_pdblines(tag).unwrap()).unwrap();
+        }
+        let scored = fs::read_to_string(dir.join("scored").join(META_FILE)).unwrap();
+        assert!(scored.contains("\"plddt\": 90"));
+        let plain = fs::read_to_string(dir.join("plain").join(META_FILE)).unwrap();
+        assert!(plain.contains("\"scores\": {}"));
+    }
+}
@@ -0,0 +1,292 @@
+//! Reading archives written with old spellings of the record keywords.
+//!
+//! Some early writers spelled the records without the underscore (`QVTAG`,
+//! `QVSCORE`) or with a space in its place (`QV TAG`, `QV SCORE`). Current
+//! readers take such lines for payload, so these archives look empty. A
+//! [`LegacyFile`] rewrites the variants listed in a [`LegacyRecords`] table
+//! to the canonical keywords as the archive is read, and [`modernize`]
+//! writes a canonical copy once and for all.
+//!
+//! The documented variants are in [`LEGACY_RECORDS`]; others can be added
+//! to a table with [`LegacyRecords::add`].
+
+use std::borrow::Cow;
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use super::storage::{write_atomically, Storage};
+use super::{open_storage, QuiverError};
+
+/// The canonical record keywords a variant can stand for.
+pub const CANONICAL_RECORDS: [&str; 5] = ["QV_TAG", "QV_SCORE", "QV_META", "QV_HASH", "QV_HEADER"];
+
+/// Spellings of the record keywords found in archives from older writers,
+/// as `(variant, canonical)` pairs.
+pub const LEGACY_RECORDS: [(&str, &str); 10] = [
+    ("QVTAG", "QV_TAG"),
+    ("QV TAG", "QV_TAG"),
+    ("QVSCORE", "QV_SCORE"),
+    ("QV SCORE", "QV_SCORE"),
+    ("QVMETA", "QV_META"),
+    ("QV META", "QV_META"),
+    ("QVHASH", "QV_HASH"),
+    ("QV HASH", "QV_HASH"),
+    ("QVHEADER", "QV_HEADER"),
+    ("QV HEADER", "QV_HEADER"),
+];
+
+/// Which legacy spellings are rewritten, and to what.
+///
+/// A variant only matches as a whole keyword: followed by whitespace or the
+/// end of the line, at the start of a line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LegacyRecords {
+    variants: Vec<(String, String)>,
+}
+
+impl Default for LegacyRecords {
+    /// The documented variants, [`LEGACY_RECORDS`].
+    fn default() -> Self {
+        Self {
+            variants: LEGACY_RECORDS
+                .iter()
+                .map(|(variant, canonical)| (variant.to_string(), canonical.to_string()))
+                .collect(),
+        }
+    }
+}
+
+impl LegacyRecords {
+    /// A table without any variants.
+    pub fn none() -> Self {
+        Self { variants: Vec::new() }
+    }
+
+    /// Also rewrite `variant` to `canonical`, one of [`CANONICAL_RECORDS`].
+    pub fn add(&mut self, variant: &str, canonical: &str) -> Result<(), QuiverError> {
+        if !CANONICAL_RECORDS.contains(&canonical) {
+            return Err(QuiverError::InvalidOperation(format!(
+                "Unknown record '{}', expected one of {}",
+                canonical,
+                CANONICAL_RECORDS.join(", ")
+            )));
+        }
+        if variant.trim().is_empty() || variant != variant.trim() || CANONICAL_RECORDS.contains(&variant) {
+            return Err(QuiverError::InvalidOperation(format!(
+                "Invalid legacy spelling '{}': it must be non-empty, without surrounding whitespace, \
+                 and not a canonical record",
+                variant
+            )));
+        }
+        self.variants.retain(|(v, _)| v != variant);
+        self.variants.push((variant.to_string(), canonical.to_string()));
+        // Longer variants first, so a variant "QV" does not shadow "QV TAG"
+        self.variants.sort_by(|a, b| b.0.len().cmp(&a.0.len()).then_with(|| a.0.cmp(&b.0)));
+        Ok(())
+    }
+
+    pub fn variants(&self) -> &[(String, String)] {
+        &self.variants
+    }
+
+    /// `line` with a legacy keyword at its start replaced by the canonical
+    /// one; borrowed if there is none.
+    pub fn normalize<'a>(&self, line: &'a [u8]) -> Cow<'a, [u8]> {
+        for (variant, canonical) in &self.variants {
+            let Some(rest) = line.strip_prefix(variant.as_bytes()) else {
+                continue;
+            };
+            if rest.first().is_none_or(|b| b.is_ascii_whitespace()) {
+                let mut fixed = Vec::with_capacity(canonical.len() + rest.len());
+                fixed.extend_from_slice(canonical.as_bytes());
+                fixed.extend_from_slice(rest);
+                return Cow::Owned(fixed);
+            }
+        }
+        Cow::Borrowed(line)
+    }
+}
+
+/// Lines read from `inner` with their legacy keywords rewritten.
+struct Normalizing<'a, R> {
+    inner: BufReader<R>,
+    records: &'a LegacyRecords,
+    line: Vec<u8>,
+    pending: Vec<u8>,
+    at: usize,
+}
+
+impl<R: Read> Read for Normalizing<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.at == self.pending.len() {
+            self.line.clear();
+            if self.inner.read_until(b'\n', &mut self.line)? == 0 {
+                return Ok(0);
+            }
+            self.pending = self.records.normalize(&self.line).into_owned();
+            self.at = 0;
+        }
+        let n = buf.len().min(self.pending.len() - self.at);
+        buf[..n].copy_from_slice(&self.pending[self.at..self.at + n]);
+        self.at += n;
+        Ok(n)
+    }
+}
+
+/// A read-only view of an archive with its legacy record spellings
+/// rewritten, see [`super::Quiver::open_legacy`].
+///
+/// Offsets are those of the canonical text, so reading an entry reads the
+/// archive from the start; convert archives read often with [`modernize`].
+pub struct LegacyFile {
+    inner: Box<dyn Storage>,
+    records: LegacyRecords,
+    /// Length of the canonical text
+    len: u64,
+}
+
+impl LegacyFile {
+    pub fn new(inner: Box<dyn Storage>, records: LegacyRecords) -> io::Result<Self> {
+        let mut file = Self { inner, records, len: 0 };
+        let len = io::copy(&mut file.reader()?, &mut io::sink())?;
+        file.len = len;
+        Ok(file)
+    }
+}
+
+fn read_only() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Unsupported,
+        "archives read with legacy spellings are read-only; modernize them first",
+    )
+}
+
+impl Storage for LegacyFile {
+    fn exists(&self) -> bool {
+        self.inner.exists()
+    }
+
+    fn len(&self) -> io::Result<u64> {
+        Ok(self.len)
+    }
+
+    fn reader(&self) -> io::Result<Box<dyn Read + '_>> {
+        Ok(Box::new(Normalizing {
+            inner: BufReader::new(self.inner.reader()?),
+            records: &self.records,
+            line: Vec::new(),
+            pending: Vec::new(),
+            at: 0,
+        }))
+    }
+
+    fn append(&mut self, _data: &[u8]) -> io::Result<()> {
+        Err(read_only())
+    }
+
+    fn replace(&mut self, _data: &mut dyn Read) -> io::Result<()> {
+        Err(read_only())
+    }
+
+    fn name(&self) -> Option<String> {
+        self.inner.name()
+    }
+}
+
+/// Output of [`modernize`].
+#[derive(Debug, Clone, Default)]
+pub struct ModernizeReport {
+    /// Entries in the archive
+    pub entries: usize,
+    /// Lines whose record keyword was rewritten
+    pub rewritten: usize,
+}
+
+/// Copy `input` to a new archive at `output` with every legacy spelling in
+/// `records` replaced by its canonical keyword; all other bytes are copied
+/// as they are. `output` must not exist yet and is written atomically.
+pub fn modernize<P: AsRef<Path>, Q: AsRef<Path>>(
+    input: P,
+    output: Q,
+    records: &LegacyRecords,
+) -> Result<ModernizeReport, QuiverError> {
+    let output = output.as_ref();
+    if output.exists() {
+        return Err(QuiverError::InvalidOperation(format!(
+            "Output file {} already exists",
+            output.display()
+        )));
+    }
+    let storage = open_storage(input.as_ref(), "r")?;
+    let mut report = ModernizeReport::default();
+    let written = write_atomically(output, |file| {
+        let mut reader = BufReader::new(storage.reader()?);
+        let mut out = BufWriter::new(file);
+        let mut buf = Vec::new();
+        let mut written = 0u64;
+        loop {
+            buf.clear();
+            if reader.read_until(b'\n', &mut buf)? == 0 {
+                break;
+            }
+            let line = records.normalize(&buf);
+            report.rewritten += usize::from(matches!(line, Cow::Owned(_)));
+            report.entries += usize::from(line.starts_with(b"QV_TAG"));
+            out.write_all(&line)?;
+            written += line.len() as u64;
+        }
+        out.flush()?;
+        Ok(written)
+    });
+    written?;
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::testdata::scratch_dir;
+    use super::super::Quiver;
+    use super::*;
+    use std::fs;
+
+    const LEGACY: &str = "QVTAG a\nQV SCORE a x=1\nATOM 1\nQV TAG b\nATOM 2 QVTAG\nQVTAGS c\n";
+
+    #[test]
+    fn whole_keywords_are_rewritten() {
+        let records = LegacyRecords::default();
+        assert_eq!(&*records.normalize(b"QVTAG a\n"), b"QV_TAG a\n");
+        assert_eq!(&*records.normalize(b"QV SCORE"), b"QV_SCORE");
+        assert!(matches!(records.normalize(b"QVTAGS a\n"), Cow::Borrowed(_)));
+        assert!(matches!(records.normalize(b"ATOM QVTAG\n"), Cow::Borrowed(_)));
+        assert!(matches!(LegacyRecords::none().normalize(b"QVTAG a\n"), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn added_variants_are_checked() {
+        let mut records = LegacyRecords::none();
+        records.add("QV", "QV_TAG").unwrap();
+        records.add("QV-SCORE", "QV_SCORE").unwrap();
+        assert_eq!(records.variants()[0].0, "QV-SCORE");
+        assert_eq!(&*records.normalize(b"QV a\n"), b"QV_TAG a\n");
+        assert!(records.add("QV-TAG", "QV_TAGS").is_err());
+        assert!(records.add(" QV", "QV_TAG").is_err());
+        assert!(records.add("QV_SCORE", "QV_TAG").is_err());
+    }
+
+    #[test]
+    fn read_and_modernize() {
+        let dir = scratch_dir("legacy-read");
+        let old = dir.join("old.qv");
+        fs::write(&old, LEGACY).unwrap();
+        assert!(Quiver::new(&old, "r").unwrap().get_tags().is_empty());
+        let qv = Quiver::open_legacy(&old, &LegacyRecords::default()).unwrap();
+        assert_eq!(qv.get_tags(), vec!["a", "b"]);
+        assert_eq!(qv.get_pdblines("b").unwrap(), vec!["ATOM 2 QVTAG", "QVTAGS c"]);
+
+        let report = modernize(&old, dir.join("new.qv"), &LegacyRecords::default()).unwrap();
+        assert_eq!((report.entries, report.rewritten), (2, 3));
+        let new = fs::read_to_string(dir.join("new.qv")).unwrap();
+        assert_eq!(new, "QV_TAG a\nQV_SCORE a x=1\nATOM 1\nQV_TAG b\nATOM 2 QVTAG\nQVTAGS c\n");
+        assert!(modernize(&old, dir.join("new.qv"), &LegacyRecords::default()).is_err());
+    }
+}
@@ -0,0 +1,145 @@
+use super::{Quiver, QuiverError};
+
+/// `QV_META` field holding an entry's ligand inventory, e.g. `ATP:1,MG:2`.
+pub const LIGANDS_FIELD: &str = "ligands";
+
+/// Residue names of solvent, which is not counted as a ligand.
+const WATER: &[&str] = &["HOH", "WAT", "DOD", "H2O"];
+
+/// Count the ligands in HETATM records by residue name.
+///
+/// Each distinct residue (chain, residue number and insertion code) counts
+/// once. Waters are skipped. Codes are returned in the order they first
+/// appear.
+pub fn ligand_counts<S: AsRef<str>>(pdb_lines: &[S]) -> Vec<(String, usize)> {
+    let mut counts: Vec<(String, usize)> = Vec::new();
+    let mut last_residue: Option<(String, String, String)> = None;
+
+    for line in pdb_lines {
+        let line = line.as_ref();
+        if !line.starts_with("HETATM") || line.len() < 27 {
+            continue;
+        }
+        let resname = line.get(17..20).unwrap_or("").trim().to_string();
+        if resname.is_empty() || WATER.contains(&resname.as_str()) {
+            continue;
+        }
+        let residue = (
+            resname.clone(),
+            line.get(21..22).unwrap_or(" ").to_string(),
+            line.get(22..27).unwrap_or("").to_string(),
+        );
+        if last_residue.as_ref() == Some(&residue) {
+            continue;
+        }
+        last_residue = Some(residue);
+
+        match counts.iter_mut().find(|(code, _)| *code == resname) {
+            Some((_, n)) => *n += 1,
+            None => counts.push((resname, 1)),
+        }
+    }
+    counts
+}
+
+/// The `ligands=CODE:N,...` metadata field for a structure, or `None` if it
+/// has no ligands.
+pub fn ligand_field<S: AsRef<str>>(pdb_lines: &[S]) -> Option<(String, String)> {
+    let counts = ligand_counts(pdb_lines);
+    if counts.is_empty() {
+        return None;
+    }
+    let value: Vec<String> = counts.iter().map(|(code, n)| format!("{}:{}", code, n)).collect();
+    Some((LIGANDS_FIELD.to_string(), value.join(",")))
+}
+
+/// Parse a stored inventory back into `(code, count)` pairs.
+pub fn parse_ligands(value: &str) -> Vec<(String, usize)> {
+    value
+        .split(',')
+        .filter_map(|item| {
+            let (code, n) = item.split_once(':')?;
+            Some((code.to_string(), n.parse().ok()?))
+        })
+        .collect()
+}
+
+/// An entry containing a searched ligand.
+#[derive(Debug, Clone)]
+pub struct LigandHit {
+    pub tag: String,
+    pub code: String,
+    pub count: usize,
+}
+
+/// Find the entries whose stored inventory lists `code` (case-insensitive).
+///
+/// Only structures annotated with ligands (see `qvannotate --ligands`) are
+/// searched; the PDB payloads are never parsed.
+pub fn find_ligand(qv: &Quiver, code: &str) -> Result<Vec<LigandHit>, QuiverError> {
+    let mut hits = Vec::new();
+    for (tag, fields) in qv.get_meta_records()? {
+        for (_, value) in fields.iter().filter(|(k, _)| k == LIGANDS_FIELD) {
+            for (found, count) in parse_ligands(value) {
+                if found.eq_ignore_ascii_case(code) {
+                    hits.push(LigandHit {
+                        tag: tag.clone(),
+                        code: found,
+                        count,
+                    });
+                }
+            }
+        }
+    }
+    Ok(hits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::testdata::scratch_dir;
+    use super::super::Entry;
+    use super::*;
+
+    fn hetatm(serial: usize, resname: &str, chain: char, resseq: usize) -> String {
+        format!(
+            "HETATM{:>5}  C1  {} {}{:>4}       0.000   0.000   0.000  1.00  0.00           C",
+            serial, resname, chain, resseq
+        )
+    }
+
+    #[test]
+    fn residues_count_once_and_water_is_skipped() {
+        let lines = vec![
+            "ATOM      1  CA  ALA A   1       0.000   0.000   0.000  1.00  0.00           C".to_string(),
+            hetatm(2, "ATP", 'A', 101),
+            hetatm(3, "ATP", 'A', 101),
+            hetatm(4, " MG", 'A', 102),
+            hetatm(5, "HOH", 'A', 201),
+            hetatm(6, " MG", 'B', 102),
+        ];
+        assert_eq!(ligand_counts(&lines), vec![("ATP".to_string(), 1), ("MG".to_string(), 2)]);
+        assert_eq!(ligand_field(&lines), Some((LIGANDS_FIELD.to_string(), "ATP:1,MG:2".to_string())));
+        assert_eq!(ligand_field(&lines[..1]), None);
+        assert_eq!(parse_ligands("ATP:1,MG:2,bad"), ligand_counts(&lines));
+    }
+
+    #[test]
+    fn search_reads_only_the_inventory() {
+        let path = scratch_dir("ligand-search").join("a.qv");
+        let mut qv = Quiver::new(&path, "w").unwrap();
+        for (tag, ligands) in [("a", Some("ATP:1,MG:2")), ("b", Some("GTP:1")), ("c", None)] {
+            qv.add_entry(&Entry {
+                tag: tag.to_string(),
+                meta: ligands.map(|l| (LIGANDS_FIELD.to_string(), l.to_string())).into_iter().collect(),
+                lines: vec![hetatm(1, "ATP", 'A', 1)],
+                ..Entry::default()
+            })
+            .unwrap();
+        }
+        qv.flush().unwrap();
+        let hits = find_ligand(&Quiver::new(&path, "r").unwrap(), "mg").unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!((hits[0].tag.as_str(), hits[0].code.as_str(), hits[0].count), ("a", "MG", 2));
+        assert!(find_ligand(&Quiver::new(&path, "r").unwrap(), "HEM").unwrap().is_empty());
+    }
+}
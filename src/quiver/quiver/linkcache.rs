@@ -0,0 +1,198 @@
+//! Content-addressed cache for extracted structures.
+//!
+//! Extracting the same entries into several directories normally writes the
+//! same bytes again each time. With an [`ExtractCache`], every distinct file
+//! is written into the cache once, and each output is then a copy-on-write
+//! clone (reflink) or a hard link to the cached copy.
+
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use super::convert::fnv1a64;
+
+/// How outputs are created from the cached copy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkMode {
+    /// Copy-on-write clone; falls back to a plain copy where unsupported
+    Reflink,
+    /// Hard link; outputs share the cached inode, so editing one edits all
+    Hardlink,
+}
+
+impl FromStr for LinkMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "reflink" => Ok(LinkMode::Reflink),
+            "hardlink" => Ok(LinkMode::Hardlink),
+            other => Err(format!("Unknown link mode '{}', expected reflink or hardlink", other)),
+        }
+    }
+}
+
+/// How [`ExtractCache::place`] created an output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Placed {
+    Reflinked,
+    Hardlinked,
+    /// The filesystem supports neither; the bytes were copied
+    Copied,
+}
+
+pub struct ExtractCache {
+    dir: PathBuf,
+    mode: LinkMode,
+}
+
+/// Distinguishes temporary files written by concurrent threads.
+static TMP_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+impl ExtractCache {
+    pub fn new<P: AsRef<Path>>(dir: P, mode: LinkMode) -> io::Result<Self> {
+        fs::create_dir_all(dir.as_ref())?;
+        Ok(Self {
+            dir: dir.as_ref().to_path_buf(),
+            mode,
+        })
+    }
+
+    /// Cached path for `contents`, keyed by hash and length and keeping the
+    /// extension of `dest`.
+    fn cached_path(&self, contents: &[u8], dest: &Path) -> PathBuf {
+        let ext = dest.extension().map(|e| e.to_string_lossy().to_string()).unwrap_or_default();
+        self.dir.join(format!("{:016x}-{}.{}", fnv1a64(contents), contents.len(), ext))
+    }
+
+    /// Create `dest` holding `contents`, from the cached copy when possible.
+    ///
+    /// `dest` must not exist.
+    pub fn place(&self, contents: &[u8], dest: &Path) -> io::Result<Placed> {
+        let cached = self.cached_path(contents, dest);
+        if !cached.exists() {
+            let tmp = self.dir.join(format!(
+                ".{}.tmp{}-{}",
+                cached.file_name().unwrap_or_default().to_string_lossy(),
+                process::id(),
+                TMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+            ));
+            let written = File::create(&tmp)
+                .and_then(|mut file| file.write_all(contents))
+                .and_then(|()| fs::rename(&tmp, &cached));
+            if written.is_err() {
+                let _ = fs::remove_file(&tmp);
+            }
+            written?;
+        }
+        match self.mode {
+            LinkMode::Hardlink => match fs::hard_link(&cached, dest) {
+                Ok(()) => Ok(Placed::Hardlinked),
+                // Cache and destination on different filesystems
+                Err(_) if !dest.exists() => fs::copy(&cached, dest).map(|_| Placed::Copied),
+                Err(e) => Err(e),
+            },
+            LinkMode::Reflink => match reflink(&cached, dest) {
+                Ok(()) => Ok(Placed::Reflinked),
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => Err(e),
+                Err(_) => {
+                    let _ = fs::remove_file(dest);
+                    fs::copy(&cached, dest).map(|_| Placed::Copied)
+                }
+            },
+        }
+    }
+}
+
+/// Clone `src` to a new file `dst` sharing its data blocks.
+///
+/// Supported on Linux (XFS, Btrfs, ...) through the `FICLONE` ioctl and on
+/// macOS (APFS) through `clonefile`; elsewhere this returns `Unsupported`.
+#[cfg(target_os = "linux")]
+pub fn reflink(src: &Path, dst: &Path) -> io::Result<()> {
+    use std::os::unix::io::AsRawFd;
+
+    // _IOW(0x94, 9, int)
+    const FICLONE: std::os::raw::c_ulong = 0x4004_9409;
+    unsafe extern "C" {
+        fn ioctl(fd: std::os::raw::c_int, request: std::os::raw::c_ulong, ...) -> std::os::raw::c_int;
+    }
+
+    let source = File::open(src)?;
+    let target = File::options().write(true).create_new(true).open(dst)?;
+    // SAFETY: both descriptors stay open for the duration of the call, and
+    // FICLONE takes the source descriptor as its only argument.
+    let ret = unsafe { ioctl(target.as_raw_fd(), FICLONE, source.as_raw_fd()) };
+    if ret != 0 {
+        let err = io::Error::last_os_error();
+        drop(target);
+        let _ = fs::remove_file(dst);
+        return Err(err);
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "macos")]
+pub fn reflink(src: &Path, dst: &Path) -> io::Result<()> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    unsafe extern "C" {
+        fn clonefile(src: *const std::os::raw::c_char, dst: *const std::os::raw::c_char, flags: u32) -> std::os::raw::c_int;
+    }
+
+    let to_c = |p: &Path| CString::new(p.as_os_str().as_bytes()).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e));
+    let (src, dst) = (to_c(src)?, to_c(dst)?);
+    // SAFETY: both pointers are valid NUL-terminated strings for the call.
+    if unsafe { clonefile(src.as_ptr(), dst.as_ptr(), 0) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+pub fn reflink(_src: &Path, _dst: &Path) -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "reflinks are not supported on this platform"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::testdata::scratch_dir;
+    use super::*;
+
+    fn cached_files(dir: &Path) -> usize {
+        fs::read_dir(dir).unwrap().count()
+    }
+
+    #[test]
+    fn hard_links_share_one_cached_copy() {
+        let dir = scratch_dir("linkcache-hard");
+        let cache = ExtractCache::new(dir.join("cache"), LinkMode::Hardlink).unwrap();
+        fs::create_dir_all(dir.join("out")).unwrap();
+        for name in ["a.pdb", "b.pdb"] {
+            assert_eq!(cache.place(b"ATOM 1\n", &dir.join("out").join(name)).unwrap(), Placed::Hardlinked);
+        }
+        cache.place(b"ATOM 2\n", &dir.join("out").join("c.pdb")).unwrap();
+        assert_eq!(cached_files(&dir.join("cache")), 2);
+        assert_eq!(fs::read(dir.join("out").join("b.pdb")).unwrap(), b"ATOM 1\n");
+        assert!(cache.place(b"ATOM 1\n", &dir.join("out").join("a.pdb")).is_err());
+    }
+
+    #[test]
+    fn reflinks_fall_back_to_copies() {
+        let dir = scratch_dir("linkcache-reflink");
+        let cache = ExtractCache::new(dir.join("cache"), LinkMode::Reflink).unwrap();
+        let placed = cache.place(b"ATOM 1\n", &dir.join("a.pdb")).unwrap();
+        assert!(matches!(placed, Placed::Reflinked | Placed::Copied));
+        assert_eq!(fs::read(dir.join("a.pdb")).unwrap(), b"ATOM 1\n");
+        assert_eq!(
+            cache.place(b"ATOM 1\n", &dir.join("a.pdb")).unwrap_err().kind(),
+            io::ErrorKind::AlreadyExists
+        );
+        assert_eq!(cached_files(&dir.join("cache")), 1);
+        assert!("symlink".parse::<LinkMode>().is_err());
+    }
+}
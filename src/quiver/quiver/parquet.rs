@@ -0,0 +1,218 @@
+//! Parquet export of score tables.
+//!
+//! Each column is written with the Arrow type of its [`ScoreType`] (int64,
+//! float64, boolean or utf8), and a missing score is a null rather than a
+//! `NaN` or empty-string placeholder. Rows are written in row groups of
+//! [`BATCH_ROWS`], so memory does not grow with the table. Writing needs
+//! the `parquet` feature.
+
+use std::collections::HashMap;
+use std::io;
+use std::path::Path;
+#[cfg(feature = "parquet")]
+use std::{fs::File, sync::Arc};
+
+#[cfg(feature = "parquet")]
+use arrow::array::{ArrayRef, BooleanBuilder, Float64Builder, Int64Builder, StringBuilder};
+#[cfg(feature = "parquet")]
+use arrow::datatypes::{DataType, Field, Schema};
+#[cfg(feature = "parquet")]
+use arrow::record_batch::RecordBatch;
+#[cfg(feature = "parquet")]
+use parquet::arrow::ArrowWriter;
+#[cfg(feature = "parquet")]
+use parquet::basic::Compression;
+#[cfg(feature = "parquet")]
+use parquet::file::properties::WriterProperties;
+
+use super::scores::{ScoreType, ScoreValue};
+
+/// Rows buffered before they are written out as one row group.
+pub const BATCH_ROWS: usize = 65_536;
+
+#[cfg(feature = "parquet")]
+enum ColumnBuilder {
+    Int(Int64Builder),
+    Float(Float64Builder),
+    Bool(BooleanBuilder),
+    Str(StringBuilder),
+}
+
+#[cfg(feature = "parquet")]
+impl ColumnBuilder {
+    fn new(ty: ScoreType) -> Self {
+        match ty {
+            ScoreType::Int => ColumnBuilder::Int(Int64Builder::new()),
+            ScoreType::Float => ColumnBuilder::Float(Float64Builder::new()),
+            ScoreType::Bool => ColumnBuilder::Bool(BooleanBuilder::new()),
+            ScoreType::Str => ColumnBuilder::Str(StringBuilder::new()),
+        }
+    }
+
+    fn data_type(&self) -> DataType {
+        match self {
+            ColumnBuilder::Int(_) => DataType::Int64,
+            ColumnBuilder::Float(_) => DataType::Float64,
+            ColumnBuilder::Bool(_) => DataType::Boolean,
+            ColumnBuilder::Str(_) => DataType::Utf8,
+        }
+    }
+
+    fn append(&mut self, value: Option<ScoreValue>) {
+        match (self, value) {
+            (ColumnBuilder::Int(b), Some(ScoreValue::Int(i))) => b.append_value(i),
+            (ColumnBuilder::Int(b), _) => b.append_null(),
+            (ColumnBuilder::Float(b), Some(ScoreValue::Float(f))) => b.append_value(f),
+            (ColumnBuilder::Float(b), _) => b.append_null(),
+            (ColumnBuilder::Bool(b), Some(ScoreValue::Bool(v))) => b.append_value(v),
+            (ColumnBuilder::Bool(b), _) => b.append_null(),
+            (ColumnBuilder::Str(b), Some(value)) => b.append_value(value.to_string()),
+            (ColumnBuilder::Str(b), None) => b.append_null(),
+        }
+    }
+
+    /// The values appended so far, leaving the builder empty.
+    fn finish(&mut self) -> ArrayRef {
+        match self {
+            ColumnBuilder::Int(b) => Arc::new(b.finish()),
+            ColumnBuilder::Float(b) => Arc::new(b.finish()),
+            ColumnBuilder::Bool(b) => Arc::new(b.finish()),
+            ColumnBuilder::Str(b) => Arc::new(b.finish()),
+        }
+    }
+}
+
+/// Writes a Parquet file with one column per `(name, type)`, a row at a
+/// time; call [`ScoreTableWriter::finish`] to complete the file.
+#[cfg(feature = "parquet")]
+pub struct ScoreTableWriter {
+    columns: Vec<(String, ScoreType)>,
+    builders: Vec<ColumnBuilder>,
+    buffered: usize,
+    schema: Arc<Schema>,
+    writer: ArrowWriter<File>,
+}
+
+#[cfg(feature = "parquet")]
+impl ScoreTableWriter {
+    pub fn create(path: &Path, columns: &[(String, ScoreType)]) -> io::Result<Self> {
+        let builders: Vec<ColumnBuilder> = columns.iter().map(|(_, ty)| ColumnBuilder::new(*ty)).collect();
+        let fields: Vec<Field> = columns
+            .iter()
+            .zip(&builders)
+            .map(|((name, _), b)| Field::new(name, b.data_type(), true))
+            .collect();
+        let schema = Arc::new(Schema::new(fields));
+        let properties = WriterProperties::builder().set_compression(Compression::SNAPPY).build();
+        let writer = ArrowWriter::try_new(File::create(path)?, schema.clone(), Some(properties))
+            .map_err(io::Error::other)?;
+        Ok(Self {
+            columns: columns.to_vec(),
+            builders,
+            buffered: 0,
+            schema,
+            writer,
+        })
+    }
+
+    pub fn write_row(&mut self, row: &HashMap<String, ScoreValue>) -> io::Result<()> {
+        for ((name, ty), builder) in self.columns.iter().zip(&mut self.builders) {
+            builder.append(row.get(name).map(|v| v.widen(*ty)));
+        }
+        self.buffered += 1;
+        if self.buffered == BATCH_ROWS {
+            self.flush_batch()?;
+        }
+        Ok(())
+    }
+
+    fn flush_batch(&mut self) -> io::Result<()> {
+        let arrays: Vec<ArrayRef> = self.builders.iter_mut().map(ColumnBuilder::finish).collect();
+        let batch = RecordBatch::try_new(self.schema.clone(), arrays).map_err(io::Error::other)?;
+        self.writer.write(&batch).map_err(io::Error::other)?;
+        self.buffered = 0;
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> io::Result<()> {
+        if self.buffered > 0 {
+            self.flush_batch()?;
+        }
+        self.writer.close().map_err(io::Error::other)?;
+        Ok(())
+    }
+}
+
+/// Writes a Parquet file with one column per `(name, type)`, a row at a
+/// time; call [`ScoreTableWriter::finish`] to complete the file.
+#[cfg(not(feature = "parquet"))]
+pub struct ScoreTableWriter(());
+
+#[cfg(not(feature = "parquet"))]
+impl ScoreTableWriter {
+    pub fn create(path: &Path, _columns: &[(String, ScoreType)]) -> io::Result<Self> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("{}: Parquet output needs the parquet feature", path.display()),
+        ))
+    }
+
+    pub fn write_row(&mut self, _row: &HashMap<String, ScoreValue>) -> io::Result<()> {
+        Ok(())
+    }
+
+    pub fn finish(self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::testdata::scratch_dir;
+    use super::*;
+
+    fn columns() -> Vec<(String, ScoreType)> {
+        vec![("plddt".to_string(), ScoreType::Float), ("note".to_string(), ScoreType::Str)]
+    }
+
+    #[cfg(feature = "parquet")]
+    fn row(pairs: &[(&str, &str)]) -> HashMap<String, ScoreValue> {
+        pairs.iter().map(|(k, v)| (k.to_string(), ScoreValue::parse(v))).collect()
+    }
+
+    #[cfg(not(feature = "parquet"))]
+    #[test]
+    fn needs_the_parquet_feature() {
+        let path = scratch_dir("parquet-disabled").join("scores.parquet");
+        let err = ScoreTableWriter::create(&path, &columns()).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::Unsupported);
+        assert!(!path.exists());
+    }
+
+    #[cfg(feature = "parquet")]
+    #[test]
+    fn typed_columns_with_nulls() {
+        use arrow::array::{Array, Float64Array, StringArray};
+        use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+        let path = scratch_dir("parquet-columns").join("scores.parquet");
+        let mut writer = ScoreTableWriter::create(&path, &columns()).unwrap();
+        writer.write_row(&row(&[("plddt", "90"), ("note", "ok")])).unwrap();
+        writer.write_row(&row(&[("plddt", "71.5")])).unwrap();
+        writer.finish().unwrap();
+
+        let reader = ParquetRecordBatchReaderBuilder::try_new(File::open(&path).unwrap())
+            .unwrap()
+            .build()
+            .unwrap();
+        let batches: Vec<RecordBatch> = reader.collect::<Result<_, _>>().unwrap();
+        assert_eq!(batches.len(), 1);
+        let batch = &batches[0];
+        assert_eq!(batch.schema().field(0).data_type(), &DataType::Float64);
+        let plddt = batch.column(0).as_any().downcast_ref::<Float64Array>().unwrap();
+        assert_eq!((plddt.value(0), plddt.value(1)), (90.0, 71.5));
+        let note = batch.column(1).as_any().downcast_ref::<StringArray>().unwrap();
+        assert_eq!(note.value(0), "ok");
+        assert!(note.is_null(1));
+    }
+}
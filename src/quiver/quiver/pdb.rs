@@ -0,0 +1,341 @@
+//! Structured access to stored PDB lines.
+//!
+//! [`Structure::from_lines`] groups ATOM/HETATM records into chains and
+//! residues and keeps the header records (see [`HEADER_RECORDS`]); every
+//! other record is ignored. Fields are read from their fixed PDB columns,
+//! and [`Atom::to_line`] writes them back the same way, so an edited
+//! structure can be stored again.
+
+use super::sequence::three_to_one;
+
+/// Title-section and primary-structure records (`HEADER`, `COMPND`,
+/// `SEQRES`, ...), kept unchanged by every rewrite unless stripped.
+pub const HEADER_RECORDS: &[&str] = &[
+    "HEADER", "OBSLTE", "TITLE", "SPLIT", "CAVEAT", "COMPND", "SOURCE", "KEYWDS", "EXPDTA", "NUMMDL",
+    "MDLTYP", "AUTHOR", "REVDAT", "SPRSDE", "JRNL", "REMARK", "DBREF", "DBREF1", "DBREF2", "SEQADV",
+    "SEQRES", "MODRES",
+];
+
+/// Whether `line` is one of the [`HEADER_RECORDS`].
+pub fn is_header_record(line: &str) -> bool {
+    HEADER_RECORDS.contains(&line.get(..6).unwrap_or(line).trim())
+}
+
+/// The header records of `pdb_lines`, in file order.
+pub fn header_records<S: AsRef<str>>(pdb_lines: &[S]) -> Vec<String> {
+    pdb_lines
+        .iter()
+        .map(|line| line.as_ref())
+        .filter(|line| is_header_record(line))
+        .map(|line| line.trim_end_matches('\n').to_string())
+        .collect()
+}
+
+/// One ATOM or HETATM record.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Atom {
+    pub hetatm: bool,
+    pub serial: u32,
+    pub name: String,
+    /// Alternate location indicator, `' '` if none
+    pub alt_loc: char,
+    pub resname: String,
+    pub chain: String,
+    pub resseq: i32,
+    /// Insertion code, `' '` if none
+    pub icode: char,
+    pub coord: [f64; 3],
+    pub occupancy: f64,
+    pub b_factor: f64,
+    pub element: String,
+}
+
+fn column(line: &str, range: std::ops::Range<usize>) -> &str {
+    line.get(range).unwrap_or("").trim()
+}
+
+fn column_char(line: &str, index: usize) -> char {
+    line.get(index..index + 1).and_then(|s| s.chars().next()).unwrap_or(' ')
+}
+
+impl Atom {
+    /// Parse an ATOM/HETATM record, or `None` for any other line or a record
+    /// without valid coordinates. Missing occupancy and B-factor read as 1.0
+    /// and 0.0.
+    pub fn parse(line: &str) -> Option<Atom> {
+        let hetatm = line.starts_with("HETATM");
+        if !hetatm && !line.starts_with("ATOM") {
+            return None;
+        }
+        let mut coord = [0.0; 3];
+        for (value, start) in coord.iter_mut().zip([30, 38, 46]) {
+            *value = column(line, start..start + 8).parse().ok()?;
+        }
+        Some(Atom {
+            hetatm,
+            serial: column(line, 6..11).parse().unwrap_or(0),
+            name: column(line, 12..16).to_string(),
+            alt_loc: column_char(line, 16),
+            resname: column(line, 17..20).to_string(),
+            chain: column(line, 21..22).to_string(),
+            resseq: column(line, 22..26).parse().ok()?,
+            icode: column_char(line, 26),
+            coord,
+            occupancy: column(line, 54..60).parse().unwrap_or(1.0),
+            b_factor: column(line, 60..66).parse().unwrap_or(0.0),
+            element: column(line, 76..78).to_string(),
+        })
+    }
+
+    /// Format the atom as a fixed-column PDB record.
+    ///
+    /// Names shorter than four characters start in column 14, as is usual
+    /// for single-letter elements, unless they start with a digit (`1H`) or
+    /// a two-letter element (`MG`).
+    pub fn to_line(&self) -> String {
+        let left = self.name.starts_with(|c: char| c.is_ascii_digit())
+            || (self.element.len() == 2 && self.name.eq_ignore_ascii_case(&self.element));
+        let name = if self.name.len() < 4 && !left {
+            format!(" {:<3}", self.name)
+        } else {
+            format!("{:<4}", self.name)
+        };
+        format!(
+            "{:<6}{:>5} {}{}{:>3} {:1}{:>4}{}   {:8.3}{:8.3}{:8.3}{:6.2}{:6.2}          {:>2}",
+            if self.hetatm { "HETATM" } else { "ATOM" },
+            self.serial,
+            name,
+            self.alt_loc,
+            self.resname,
+            self.chain,
+            self.resseq,
+            self.icode,
+            self.coord[0],
+            self.coord[1],
+            self.coord[2],
+            self.occupancy,
+            self.b_factor,
+            self.element,
+        )
+    }
+}
+
+/// The atoms sharing a residue number and insertion code within a chain.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Residue {
+    pub name: String,
+    pub resseq: i32,
+    pub icode: char,
+    pub atoms: Vec<Atom>,
+}
+
+impl Residue {
+    /// One-letter code of the residue, `'X'` if it is not an amino acid.
+    pub fn one_letter(&self) -> char {
+        three_to_one(&self.name)
+    }
+
+    /// The first atom called `name` (e.g. `"CA"`).
+    pub fn atom(&self, name: &str) -> Option<&Atom> {
+        self.atoms.iter().find(|a| a.name == name)
+    }
+}
+
+/// A chain's residues in file order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Chain {
+    /// Chain identifier, empty if the records have none
+    pub id: String,
+    pub residues: Vec<Residue>,
+}
+
+impl Chain {
+    /// One-letter sequence of the chain's ATOM residues.
+    ///
+    /// Residues made only of HETATM records (ligands, waters) are skipped.
+    pub fn sequence(&self) -> String {
+        self.residues
+            .iter()
+            .filter(|r| r.atoms.iter().any(|a| !a.hetatm))
+            .map(Residue::one_letter)
+            .collect()
+    }
+}
+
+/// The chains of one stored structure, in the order they first appear.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Structure {
+    /// Header records, written back first by [`Structure::to_lines`]
+    pub header: Vec<String>,
+    pub chains: Vec<Chain>,
+}
+
+impl Structure {
+    /// Parse the ATOM/HETATM records of `pdb_lines`.
+    ///
+    /// A new residue starts whenever the residue number or insertion code
+    /// changes within a chain. A chain that reappears later (e.g. its
+    /// ligands after a TER) is appended to, not duplicated.
+    pub fn from_lines<S: AsRef<str>>(pdb_lines: &[S]) -> Structure {
+        let mut chains: Vec<Chain> = Vec::new();
+        for atom in pdb_lines.iter().filter_map(|line| Atom::parse(line.as_ref())) {
+            let index = match chains.iter().position(|c| c.id == atom.chain) {
+                Some(index) => index,
+                None => {
+                    chains.push(Chain {
+                        id: atom.chain.clone(),
+                        residues: Vec::new(),
+                    });
+                    chains.len() - 1
+                }
+            };
+            let residues = &mut chains[index].residues;
+            match residues.last_mut() {
+                Some(r) if r.resseq == atom.resseq && r.icode == atom.icode => r.atoms.push(atom),
+                _ => residues.push(Residue {
+                    name: atom.resname.clone(),
+                    resseq: atom.resseq,
+                    icode: atom.icode,
+                    atoms: vec![atom],
+                }),
+            }
+        }
+        Structure {
+            header: header_records(pdb_lines),
+            chains,
+        }
+    }
+
+    /// The chain with identifier `id`.
+    pub fn chain(&self, id: &str) -> Option<&Chain> {
+        self.chains.iter().find(|c| c.id == id)
+    }
+
+    /// Every residue, chain by chain.
+    pub fn residues(&self) -> impl Iterator<Item = &Residue> {
+        self.chains.iter().flat_map(|c| c.residues.iter())
+    }
+
+    /// Every atom, chain by chain.
+    pub fn atoms(&self) -> impl Iterator<Item = &Atom> {
+        self.residues().flat_map(|r| r.atoms.iter())
+    }
+
+    /// Write the structure back as PDB lines: its header records, then the
+    /// atoms with a TER after each chain, and a closing END.
+    pub fn to_lines(&self) -> Vec<String> {
+        let mut lines = self.header.clone();
+        for chain in &self.chains {
+            lines.extend(chain.residues.iter().flat_map(|r| r.atoms.iter()).map(Atom::to_line));
+            lines.push("TER".to_string());
+        }
+        lines.push("END".to_string());
+        lines
+    }
+}
+
+/// Size of one structure, or summed over several, see
+/// [`Quiver::stats`](super::Quiver::stats).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StructureStats {
+    /// Structures counted
+    pub structures: usize,
+    pub atoms: usize,
+    /// Residues as grouped by [`Structure::from_lines`]
+    pub residues: usize,
+    /// Chain identifiers in the order they first appear
+    pub chains: Vec<String>,
+    /// Size of the structure's lines, newlines included
+    pub bytes: u64,
+}
+
+impl StructureStats {
+    /// The stats of the structure in `pdb_lines`.
+    pub fn of_lines<S: AsRef<str>>(pdb_lines: &[S]) -> Self {
+        let structure = Structure::from_lines(pdb_lines);
+        StructureStats {
+            structures: 1,
+            atoms: structure.atoms().count(),
+            residues: structure.residues().count(),
+            chains: structure.chains.iter().map(|c| c.id.clone()).collect(),
+            bytes: pdb_lines
+                .iter()
+                .map(|line| {
+                    let line = line.as_ref();
+                    line.len() as u64 + u64::from(!line.ends_with('\n'))
+                })
+                .sum(),
+        }
+    }
+
+    /// Add the counts of `other`, and its chains not seen yet.
+    pub fn add(&mut self, other: &StructureStats) {
+        self.structures += other.structures;
+        self.atoms += other.atoms;
+        self.residues += other.residues;
+        self.bytes += other.bytes;
+        for chain in &other.chains {
+            if !self.chains.contains(chain) {
+                self.chains.push(chain.clone());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LINES: [&str; 8] = [
+        "HEADER    DE NOVO PROTEIN",
+        "ATOM      1  N   ALA A   1      11.104   6.134  -6.504  1.00  0.00           N",
+        "ATOM      2  CA  ALA A   1      11.639   6.071  -5.147  1.00 12.50           C",
+        "ATOM      3  CA  GLY A   2      12.000   7.000  -4.000  1.00  0.00           C",
+        "ATOM      4  CA  SER B   1       1.000   2.000   3.000  0.50  0.00           C",
+        "TER",
+        "HETATM    5 MG    MG A 101       0.000   0.000   0.000  1.00  0.00          MG",
+        "END",
+    ];
+
+    #[test]
+    fn atoms_round_trip_through_their_columns() {
+        for line in LINES.iter().filter(|l| l.starts_with("ATOM") || l.starts_with("HETATM")) {
+            assert_eq!(Atom::parse(line).unwrap().to_line(), *line);
+        }
+        let atom = Atom::parse(LINES[2]).unwrap();
+        assert_eq!((atom.serial, atom.name.as_str(), atom.resseq, atom.b_factor), (2, "CA", 1, 12.5));
+        assert_eq!(atom.coord, [11.639, 6.071, -5.147]);
+        assert!(Atom::parse("ATOM      1  N   ALA A   1").is_none());
+        assert!(Atom::parse(LINES[0]).is_none());
+    }
+
+    #[test]
+    fn chains_and_residues() {
+        let structure = Structure::from_lines(&LINES);
+        assert_eq!(structure.header, vec![LINES[0]]);
+        assert_eq!(structure.chains.iter().map(|c| c.id.as_str()).collect::<Vec<_>>(), vec!["A", "B"]);
+        let a = structure.chain("A").unwrap();
+        // The ligand after TER joins chain A but not its sequence
+        assert_eq!(a.residues.len(), 3);
+        assert_eq!(a.sequence(), "AG");
+        assert_eq!(a.residues[0].atom("CA").unwrap().serial, 2);
+        assert_eq!(structure.atoms().count(), 5);
+
+        let lines = structure.to_lines();
+        assert_eq!(lines.first().map(String::as_str), Some(LINES[0]));
+        assert_eq!(lines.iter().filter(|l| *l == "TER").count(), 2);
+        assert_eq!(Structure::from_lines(&lines), structure);
+    }
+
+    #[test]
+    fn stats_add_up() {
+        let mut stats = StructureStats::of_lines(&LINES);
+        assert_eq!((stats.atoms, stats.residues), (5, 4));
+        assert_eq!(stats.bytes, LINES.iter().map(|l| l.len() as u64 + 1).sum::<u64>());
+        stats.add(&StructureStats::of_lines(&LINES[4..5]));
+        assert_eq!((stats.structures, stats.atoms), (2, 6));
+        assert_eq!(stats.chains, vec!["A", "B"]);
+        assert!(is_header_record("REMARK 1"));
+        assert!(!is_header_record("ATOM      1"));
+    }
+}
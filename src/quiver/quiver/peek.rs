@@ -0,0 +1,186 @@
+//! The first or last entries of an archive, without reading the rest.
+//!
+//! Meant for spot-checking what a producer still appending to an archive
+//! has written so far. [`head`] stops reading after `n` entries. [`tail`]
+//! starts from the `<archive>.idx` tag index (see [`super::tagindex`]) when
+//! it describes an earlier, shorter state of the archive, scanning only what
+//! was appended since; without one it scans backwards from the end in
+//! growing chunks until it has seen `n` tag lines.
+//!
+//! The archive size is taken once, so a last entry the producer is still
+//! writing comes back as far as it was written at that moment.
+
+use std::collections::VecDeque;
+use std::io::{self, BufRead, BufReader, Cursor, Read};
+use std::path::Path;
+
+use super::compress::Compression;
+use super::format_v2;
+use super::storage::Storage;
+use super::tagindex::{index_path, TagIndex};
+use super::{open_storage, Entries, Entry, QuiverError};
+
+/// Bytes read from the end of an archive by the first step of a backward
+/// scan; every further step doubles it.
+pub const TAIL_CHUNK: u64 = 1 << 20;
+
+/// The first `n` entries of `archive`, in file order.
+pub fn head<P: AsRef<Path>>(archive: P, n: usize) -> Result<Vec<Entry>, QuiverError> {
+    let storage = open_storage(archive.as_ref(), "r")?;
+    if n == 0 || !storage.exists() {
+        return Ok(Vec::new());
+    }
+    let entries = Entries::over(storage.reader()?).take(n).collect();
+    entries
+}
+
+/// The last `n` entries of `archive`, in file order.
+///
+/// Compressed and v2 archives have no byte offsets to start from, so they
+/// are read through once, keeping the last `n` entries.
+pub fn tail<P: AsRef<Path>>(archive: P, n: usize) -> Result<Vec<Entry>, QuiverError> {
+    let archive = archive.as_ref();
+    let storage = open_storage(archive, "r")?;
+    if n == 0 || !storage.exists() {
+        return Ok(Vec::new());
+    }
+    if Compression::detect(archive)?.is_some() || format_v2::detect(archive)? {
+        let mut last = VecDeque::with_capacity(n);
+        for entry in Entries::over(storage.reader()?) {
+            if last.len() == n {
+                last.pop_front();
+            }
+            last.push_back(entry?);
+        }
+        return Ok(last.into());
+    }
+    let len = storage.len()?;
+    let start = match indexed_start(storage.as_ref(), archive, len, n)? {
+        Some(start) => start,
+        None => scan_back(storage.as_ref(), len, n)?,
+    };
+    let bytes = storage.read_range(start, len - start)?;
+    Entries::over(Box::new(Cursor::new(bytes))).collect()
+}
+
+/// Offsets (plus `base`) of the `QV_TAG` lines read from `reader`, which
+/// starts at the beginning of a line.
+fn tag_line_offsets<R: BufRead>(mut reader: R, base: u64) -> io::Result<Vec<u64>> {
+    let mut offsets = Vec::new();
+    let mut buf = Vec::new();
+    let mut pos = base;
+    loop {
+        buf.clear();
+        let n = reader.read_until(b'\n', &mut buf)?;
+        if n == 0 {
+            return Ok(offsets);
+        }
+        if buf.starts_with(b"QV_TAG") {
+            offsets.push(pos);
+        }
+        pos += n as u64;
+    }
+}
+
+/// Where the last `n` entries of the first `len` bytes start, from the tag
+/// index of `archive`, or `None` if it has none that still fits the archive.
+fn indexed_start(storage: &dyn Storage, archive: &Path, len: u64, n: usize) -> Result<Option<u64>, QuiverError> {
+    let Some(index) = TagIndex::load(index_path(archive))? else {
+        return Ok(None);
+    };
+    let Some(last) = index.entries().last() else {
+        return Ok(None);
+    };
+    if index.archive_size() > len || last.offset >= len {
+        return Ok(None);
+    }
+    // An archive rewritten since (not just appended to) no longer has the
+    // last indexed entry where the index says
+    let mut reader = BufReader::new(storage.reader_at(last.offset)?.take(len - last.offset));
+    let mut first = String::new();
+    reader.read_line(&mut first)?;
+    if !first.starts_with("QV_TAG") || first.split_whitespace().nth(1) != Some(last.tag.as_str()) {
+        return Ok(None);
+    }
+    let base = last.offset + first.len() as u64;
+    let appended = tag_line_offsets(reader, base)?;
+    let mut offsets: Vec<u64> = index.entries().iter().map(|e| e.offset).collect();
+    offsets.extend(appended);
+    Ok(Some(offsets[offsets.len().saturating_sub(n)]))
+}
+
+/// Where the last `n` entries of the first `len` bytes start, reading
+/// backwards from `len`. `len` if there are none.
+fn scan_back(storage: &dyn Storage, len: u64, n: usize) -> io::Result<u64> {
+    let mut chunk = TAIL_CHUNK;
+    loop {
+        let from = len.saturating_sub(chunk);
+        let bytes = storage.read_range(from, len - from)?;
+        // A chunk starting mid-line cannot tell whether that line is a tag
+        // line; the next, larger chunk will
+        let skip = if from == 0 {
+            0
+        } else {
+            bytes.iter().position(|&b| b == b'\n').map_or(bytes.len(), |i| i + 1)
+        };
+        let offsets = tag_line_offsets(&bytes[skip..], from + skip as u64)?;
+        if offsets.len() >= n || from == 0 {
+            return Ok(offsets.get(offsets.len().saturating_sub(n)).copied().unwrap_or(len));
+        }
+        chunk *= 2;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::testdata::scratch_dir;
+    use super::*;
+    use std::fs::{self, OpenOptions};
+    use std::io::Write;
+
+    fn entries(tags: &[&str]) -> String {
+        tags.iter().map(|tag| format!("QV_TAG {}\nATOM {}\n", tag, tag)).collect()
+    }
+
+    fn tags(entries: Vec<Entry>) -> Vec<String> {
+        entries.into_iter().map(|e| e.tag).collect()
+    }
+
+    fn append(path: &Path, text: &str) {
+        OpenOptions::new().append(true).open(path).unwrap().write_all(text.as_bytes()).unwrap();
+    }
+
+    #[test]
+    fn head_and_tail_of_a_plain_archive() {
+        let path = scratch_dir("peek-plain").join("a.qv");
+        fs::write(&path, entries(&["a", "b", "c", "d"])).unwrap();
+        assert_eq!(tags(head(&path, 2).unwrap()), vec!["a", "b"]);
+        assert_eq!(tags(tail(&path, 3).unwrap()), vec!["b", "c", "d"]);
+        assert_eq!(tags(tail(&path, 10).unwrap()).len(), 4);
+        assert!(tail(&path, 0).unwrap().is_empty());
+        assert_eq!(tail(&path, 1).unwrap()[0].lines, vec!["ATOM d"]);
+        assert!(head(path.with_file_name("missing.qv"), 1).unwrap().is_empty());
+    }
+
+    #[test]
+    fn tail_reads_past_an_older_index() {
+        let path = scratch_dir("peek-index").join("a.qv");
+        fs::write(&path, entries(&["a", "b", "c"])).unwrap();
+        TagIndex::build(&path, &mut |_, _| {}).unwrap().save(index_path(&path)).unwrap();
+        append(&path, &entries(&["d", "e"]));
+        assert_eq!(tags(tail(&path, 3).unwrap()), vec!["c", "d", "e"]);
+        // A rewritten archive no longer matches the index and is scanned
+        fs::write(&path, entries(&["x", "y", "z", "w", "v"])).unwrap();
+        assert_eq!(tags(tail(&path, 2).unwrap()), vec!["w", "v"]);
+    }
+
+    #[test]
+    fn compressed_archives_are_read_through() {
+        let path = scratch_dir("peek-gzip").join("a.qv.gz");
+        let mut packed = Vec::new();
+        Compression::Gzip.encode(&mut entries(&["a", "b", "c"]).as_bytes(), &mut packed).unwrap();
+        fs::write(&path, packed).unwrap();
+        assert_eq!(tags(tail(&path, 2).unwrap()), vec!["b", "c"]);
+        assert_eq!(tags(head(&path, 1).unwrap()), vec!["a"]);
+    }
+}
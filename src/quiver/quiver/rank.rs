@@ -0,0 +1,449 @@
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+use std::str::FromStr;
+
+use regex::Regex;
+
+use super::expr::ScoreKey;
+use super::scores::{score_map, ScoreValue};
+use super::MetaFields;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Order {
+    Asc,
+    Desc,
+}
+
+/// One sort key of a composite ranking, e.g. `plddt:desc` or
+/// `ddg - ref_ddg:asc`.
+#[derive(Debug, Clone)]
+pub struct RankKey {
+    /// The key as written
+    pub field: String,
+    pub order: Order,
+    key: ScoreKey,
+}
+
+impl RankKey {
+    pub fn new(field: &str, order: Order) -> Self {
+        Self {
+            field: field.to_string(),
+            order,
+            key: ScoreKey::new(field),
+        }
+    }
+
+    /// The value of this key in `fields`, see [`ScoreKey::value`].
+    pub fn value(&self, fields: &MetaFields) -> Option<ScoreValue> {
+        self.key.value(&score_map(fields))
+    }
+
+    /// Compare two values of this key; `Less` means `a` ranks first.
+    pub fn compare(&self, a: &ScoreValue, b: &ScoreValue) -> Ordering {
+        let ord = a.total_cmp(b);
+        match self.order {
+            Order::Asc => ord,
+            Order::Desc => ord.reverse(),
+        }
+    }
+}
+
+impl FromStr for RankKey {
+    type Err = String;
+
+    /// Parse `field[:asc|desc]`; the order defaults to ascending.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (field, order) = match s.rsplit_once(':') {
+            Some((field, "asc")) => (field, Order::Asc),
+            Some((field, "desc")) => (field, Order::Desc),
+            Some((_, other)) => {
+                return Err(format!("Unknown sort order '{}', expected asc or desc", other))
+            }
+            None => (s, Order::Asc),
+        };
+        if field.is_empty() {
+            return Err("Sort key needs a score field name".to_string());
+        }
+        Ok(Self::new(field, order))
+    }
+}
+
+/// Where entries without a sort value go.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Missing {
+    First,
+    #[default]
+    Last,
+    /// Leave them out
+    Drop,
+}
+
+impl FromStr for Missing {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "first" => Ok(Missing::First),
+            "last" => Ok(Missing::Last),
+            "drop" => Ok(Missing::Drop),
+            other => Err(format!(
+                "Unknown missing-value placement '{}', expected first, last or drop",
+                other
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RankedEntry {
+    /// 1-based rank
+    pub rank: usize,
+    pub tag: String,
+    /// Value of each rank key, `None` if missing or NaN
+    pub values: Vec<Option<ScoreValue>>,
+}
+
+/// Rank tags by several score fields, breaking ties with each following key.
+///
+/// Values are compared by type (see [`ScoreValue::total_cmp`]), so numbers
+/// sort numerically. Missing and NaN values always sort after present ones,
+/// and fully tied entries keep their file order.
+pub fn rank(tags: &[String], scores: &[(String, MetaFields)], keys: &[RankKey]) -> Vec<RankedEntry> {
+    let by_tag: HashMap<&str, &MetaFields> =
+        scores.iter().map(|(tag, fields)| (tag.as_str(), fields)).collect();
+
+    let mut entries: Vec<RankedEntry> = tags
+        .iter()
+        .map(|tag| {
+            let fields = by_tag.get(tag.as_str());
+            let values = keys
+                .iter()
+                .map(|key| fields.and_then(|f| key.value(f)))
+                .collect();
+            RankedEntry {
+                rank: 0,
+                tag: tag.clone(),
+                values,
+            }
+        })
+        .collect();
+
+    entries.sort_by(|a, b| {
+        for (i, key) in keys.iter().enumerate() {
+            let ord = match (&a.values[i], &b.values[i]) {
+                (Some(x), Some(y)) => key.compare(x, y),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => Ordering::Equal,
+            };
+            if ord != Ordering::Equal {
+                return ord;
+            }
+        }
+        Ordering::Equal
+    });
+
+    for (i, entry) in entries.iter_mut().enumerate() {
+        entry.rank = i + 1;
+    }
+    entries
+}
+
+/// An entry waiting in [`BestFirst`].
+struct Queued {
+    /// Value of the key, `None` if missing or NaN
+    value: Option<ScoreValue>,
+    index: usize,
+    order: Order,
+}
+
+impl Queued {
+    /// `Less` means `self` ranks first; ties keep file order.
+    fn rank_cmp(&self, other: &Self) -> Ordering {
+        let ord = match (&self.value, &other.value) {
+            (Some(a), Some(b)) => match self.order {
+                Order::Asc => a.total_cmp(b),
+                Order::Desc => a.total_cmp(b).reverse(),
+            },
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        };
+        ord.then(self.index.cmp(&other.index))
+    }
+}
+
+impl PartialEq for Queued {
+    fn eq(&self, other: &Self) -> bool {
+        self.rank_cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Queued {}
+
+impl PartialOrd for Queued {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Queued {
+    /// The heap pops its greatest element, so the best entry is greatest.
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.rank_cmp(other).reverse()
+    }
+}
+
+/// Tags in ranking order by one key, ranked as they are taken.
+///
+/// Building the queue is linear and each tag taken costs a logarithmic
+/// step, so a consumer that stops early (an extraction hitting a time limit
+/// or disk quota) never pays for a full sort. Entries without the key (or
+/// with NaN) come last; ties keep file order.
+pub struct BestFirst {
+    tags: Vec<String>,
+    heap: BinaryHeap<Queued>,
+}
+
+impl Iterator for BestFirst {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        self.heap.pop().map(|queued| std::mem::take(&mut self.tags[queued.index]))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.heap.len(), Some(self.heap.len()))
+    }
+}
+
+/// Queue `tags` for [`BestFirst`] by the `key` values in `scores`.
+pub fn best_first(tags: &[String], scores: &[(String, MetaFields)], key: &RankKey) -> BestFirst {
+    let by_tag: HashMap<&str, &MetaFields> =
+        scores.iter().map(|(tag, fields)| (tag.as_str(), fields)).collect();
+    let queued: Vec<Queued> = tags
+        .iter()
+        .enumerate()
+        .map(|(index, tag)| Queued {
+            value: by_tag.get(tag.as_str()).and_then(|f| key.value(f)),
+            index,
+            order: key.order,
+        })
+        .collect();
+    BestFirst {
+        tags: tags.to_vec(),
+        heap: BinaryHeap::from(queued),
+    }
+}
+
+/// The group of `tag`: the first capture group of `pattern` if it has one,
+/// otherwise the whole match. Tags the pattern does not match are a group
+/// of their own.
+pub fn group_of(pattern: &Regex, tag: &str) -> String {
+    match pattern.captures(tag) {
+        Some(caps) => caps.get(1).or_else(|| caps.get(0)).map_or("", |m| m.as_str()).to_string(),
+        None => tag.to_string(),
+    }
+}
+
+/// Keep the `k` best entries of each group of a ranking, e.g. the best seed
+/// of every design with a pattern like `^(.*)_seed\d+$`.
+///
+/// Returns `(group, entry)` pairs in ranking order, renumbered from 1. An
+/// entry missing the score still counts towards its group's `k`, but only
+/// after every scored entry of that group.
+pub fn best_per_group(ranked: Vec<RankedEntry>, pattern: &Regex, k: usize) -> Vec<(String, RankedEntry)> {
+    let mut kept: HashMap<String, usize> = HashMap::new();
+    let mut best = Vec::new();
+    for mut entry in ranked {
+        let group = group_of(pattern, &entry.tag);
+        let count = kept.entry(group.clone()).or_insert(0);
+        if *count < k {
+            *count += 1;
+            entry.rank = best.len() + 1;
+            best.push((group, entry));
+        }
+    }
+    best
+}
+
+/// Count, mean, minimum and maximum of one numeric score field.
+#[derive(Debug, Clone, Copy)]
+pub struct FieldStats {
+    /// Entries with a numeric, non-NaN value
+    pub count: usize,
+    pub sum: f64,
+    pub min: f64,
+    pub max: f64,
+}
+
+impl Default for FieldStats {
+    fn default() -> Self {
+        Self {
+            count: 0,
+            sum: 0.0,
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+        }
+    }
+}
+
+impl FieldStats {
+    pub fn add(&mut self, value: f64) {
+        self.count += 1;
+        self.sum += value;
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+    }
+
+    pub fn mean(&self) -> Option<f64> {
+        (self.count > 0).then(|| self.sum / self.count as f64)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct GroupStats {
+    pub group: String,
+    /// Tags in the group, with or without the fields
+    pub entries: usize,
+    /// Statistics of each requested field, in request order
+    pub fields: Vec<FieldStats>,
+}
+
+/// Per-group statistics of score `fields` (or expressions, see
+/// [`ScoreKey`]), with tags grouped as for [`best_per_group`] (see
+/// [`group_of`]), e.g. per design across seeds.
+///
+/// Groups are returned in order of their first tag. Missing, NaN and
+/// non-numeric values are left out of a field's statistics.
+pub fn group_stats(
+    tags: &[String],
+    scores: &[(String, MetaFields)],
+    pattern: &Regex,
+    fields: &[ScoreKey],
+) -> Vec<GroupStats> {
+    let by_tag: HashMap<&str, &MetaFields> =
+        scores.iter().map(|(tag, fields)| (tag.as_str(), fields)).collect();
+    let mut index: HashMap<String, usize> = HashMap::new();
+    let mut groups: Vec<GroupStats> = Vec::new();
+    for tag in tags {
+        let group = group_of(pattern, tag);
+        let i = *index.entry(group.clone()).or_insert_with(|| {
+            groups.push(GroupStats {
+                group,
+                entries: 0,
+                fields: vec![FieldStats::default(); fields.len()],
+            });
+            groups.len() - 1
+        });
+        let stats = &mut groups[i];
+        stats.entries += 1;
+        let Some(values) = by_tag.get(tag.as_str()) else {
+            continue;
+        };
+        let values = score_map(values);
+        for (field, field_stats) in fields.iter().zip(&mut stats.fields) {
+            if let Some(value) = field.number(&values) {
+                field_stats.add(value);
+            }
+        }
+    }
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tags(names: &[&str]) -> Vec<String> {
+        names.iter().map(|s| s.to_string()).collect()
+    }
+
+    fn scores(rows: &[(&str, &str)]) -> Vec<(String, MetaFields)> {
+        rows.iter()
+            .map(|(tag, fields)| {
+                let fields = fields
+                    .split('|')
+                    .filter_map(|kv| kv.split_once('='))
+                    .map(|(k, v)| (k.to_string(), v.to_string()))
+                    .collect();
+                (tag.to_string(), fields)
+            })
+            .collect()
+    }
+
+    fn ranked_tags(ranked: &[RankedEntry]) -> Vec<&str> {
+        ranked.iter().map(|e| e.tag.as_str()).collect()
+    }
+
+    #[test]
+    fn keys_parse_with_their_order() {
+        let key: RankKey = "plddt:desc".parse().unwrap();
+        assert_eq!((key.field.as_str(), key.order), ("plddt", Order::Desc));
+        let key: RankKey = "ddg - ref:asc".parse().unwrap();
+        assert_eq!(key.field, "ddg - ref");
+        assert_eq!("rmsd".parse::<RankKey>().unwrap().order, Order::Asc);
+        assert!("rmsd:up".parse::<RankKey>().is_err());
+        assert!(":desc".parse::<RankKey>().is_err());
+        assert_eq!("drop".parse::<Missing>(), Ok(Missing::Drop));
+    }
+
+    #[test]
+    fn ties_fall_through_to_the_next_key() {
+        let all = tags(&["a", "b", "c", "d", "e"]);
+        let table = scores(&[
+            ("a", "plddt=90|rmsd=2.0"),
+            ("b", "plddt=95|rmsd=1.0"),
+            ("c", "plddt=90|rmsd=1.5"),
+            ("d", "rmsd=0.5"),
+            ("e", "plddt=nan|rmsd=0.1"),
+        ]);
+        let keys = vec!["plddt:desc".parse().unwrap(), "rmsd".parse().unwrap()];
+        let ranked = rank(&all, &table, &keys);
+        assert_eq!(ranked_tags(&ranked), vec!["b", "c", "a", "e", "d"]);
+        assert_eq!(ranked[0].rank, 1);
+        assert_eq!(ranked[3].values[0], None);
+        // Expressions rank like fields
+        let margin = rank(&all, &table, &["plddt - rmsd * 10:desc".parse().unwrap()]);
+        assert_eq!(ranked_tags(&margin)[..3], ["b", "c", "a"]);
+    }
+
+    #[test]
+    fn best_first_pops_in_rank_order() {
+        let all = tags(&["a", "b", "c", "d", "unscored"]);
+        let table = scores(&[("a", "plddt=71"), ("b", "plddt=93.5"), ("c", "plddt=80"), ("d", "plddt=93.5")]);
+        let key = "plddt:desc".parse().unwrap();
+        let mut order = best_first(&all, &table, &key);
+        assert_eq!(order.size_hint(), (5, Some(5)));
+        assert_eq!(order.next().as_deref(), Some("b"));
+        assert_eq!(order.collect::<Vec<_>>(), vec!["d", "c", "a", "unscored"]);
+    }
+
+    #[test]
+    fn groups_by_capture() {
+        let pattern = Regex::new(r"^(.*)_seed\d+$").unwrap();
+        assert_eq!(group_of(&pattern, "design_1_seed3"), "design_1");
+        assert_eq!(group_of(&pattern, "other"), "other");
+        assert_eq!(group_of(&Regex::new(r"design_\d").unwrap(), "x_design_2_y"), "design_2");
+
+        let all = tags(&["d1_seed1", "d1_seed2", "d2_seed1", "d1_seed3", "d2_seed2"]);
+        let table = scores(&[
+            ("d1_seed1", "plddt=80"),
+            ("d1_seed2", "plddt=90"),
+            ("d2_seed1", "plddt=85"),
+            ("d1_seed3", "plddt=70"),
+        ]);
+        let ranked = rank(&all, &table, &["plddt:desc".parse().unwrap()]);
+        let best = best_per_group(ranked, &pattern, 1);
+        let kept: Vec<(&str, &str, usize)> = best.iter().map(|(g, e)| (g.as_str(), e.tag.as_str(), e.rank)).collect();
+        assert_eq!(kept, vec![("d1", "d1_seed2", 1), ("d2", "d2_seed1", 2)]);
+
+        let stats = group_stats(&all, &table, &pattern, &[ScoreKey::new("plddt")]);
+        assert_eq!(stats.len(), 2);
+        assert_eq!((stats[0].group.as_str(), stats[0].entries), ("d1", 3));
+        assert_eq!(stats[0].fields[0].mean(), Some(80.0));
+        assert_eq!((stats[0].fields[0].min, stats[0].fields[0].max), (70.0, 90.0));
+        assert_eq!((stats[1].entries, stats[1].fields[0].count), (2, 1));
+        assert_eq!(FieldStats::default().mean(), None);
+    }
+}
@@ -0,0 +1,344 @@
+//! Read-only archives in object storage, over HTTP(S) or S3.
+//!
+//! [`RemoteFile`] is a [`Storage`] whose reads are ranged GET requests, so
+//! an archive is never downloaded to disk. Opening one scans it once in
+//! [`STREAM_CHUNK`] ranges to find the tags, unless a `<url>.idx` tag index
+//! sits next to it (written by `qvindex`, or uploaded by
+//! [`super::s3::MultipartWriter`]); then only the index and the entries read
+//! are fetched.
+//!
+//! Requests go through [`RangeClient`]; `HttpClient` needs the `http`
+//! feature and the S3 client the `s3` feature.
+
+use std::io::{self, Read};
+use std::sync::Arc;
+
+use super::storage::Storage;
+use super::tagindex::IndexEntry;
+
+/// Bytes fetched per request while streaming through an archive.
+pub const STREAM_CHUNK: u64 = 8 << 20;
+
+/// The requests reading a remote object is made of.
+pub trait RangeClient: Send + Sync {
+    /// Size of the object at `url`, or `None` if there is none.
+    fn size(&self, url: &str) -> io::Result<Option<u64>>;
+
+    /// `len` bytes of the object at `url`, starting at `offset`; none,
+    /// without a request, if `len` is 0.
+    fn get_range(&self, url: &str, offset: u64, len: u64) -> io::Result<Vec<u8>>;
+}
+
+/// Whether `path` names a remote archive: an `http://`, `https://` or
+/// `s3://` URL.
+pub fn is_remote_url(path: &str) -> bool {
+    ["http://", "https://", "s3://"].iter().any(|scheme| path.starts_with(scheme))
+}
+
+/// A client for `url`, by its scheme.
+pub fn client_for(url: &str) -> io::Result<Arc<dyn RangeClient>> {
+    if let Some(rest) = url.strip_prefix("s3://") {
+        return s3_client(rest.split('/').next().unwrap_or_default(), url);
+    }
+    http_client(url)
+}
+
+#[cfg(feature = "s3")]
+fn s3_client(bucket: &str, _url: &str) -> io::Result<Arc<dyn RangeClient>> {
+    Ok(Arc::new(super::s3::S3Client::new(bucket)?))
+}
+
+#[cfg(not(feature = "s3"))]
+fn s3_client(_bucket: &str, url: &str) -> io::Result<Arc<dyn RangeClient>> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!("Cannot read '{}': built without the s3 feature", url),
+    ))
+}
+
+#[cfg(feature = "http")]
+fn http_client(_url: &str) -> io::Result<Arc<dyn RangeClient>> {
+    Ok(Arc::new(HttpClient::new()))
+}
+
+#[cfg(not(feature = "http"))]
+fn http_client(url: &str) -> io::Result<Arc<dyn RangeClient>> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!("Cannot read '{}': built without the http feature", url),
+    ))
+}
+
+/// Read-only storage over a remote object.
+pub struct RemoteFile {
+    client: Arc<dyn RangeClient>,
+    url: String,
+    len: u64,
+}
+
+impl RemoteFile {
+    /// The archive at `url`; fails if there is none.
+    pub fn open(client: Arc<dyn RangeClient>, url: &str) -> io::Result<Self> {
+        let len = client
+            .size(url)?
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("No such remote archive: {}", url)))?;
+        Ok(Self {
+            client,
+            url: url.to_string(),
+            len,
+        })
+    }
+}
+
+fn read_only() -> io::Error {
+    io::Error::new(io::ErrorKind::Unsupported, "remote archives are read-only")
+}
+
+impl Storage for RemoteFile {
+    fn exists(&self) -> bool {
+        true
+    }
+
+    fn len(&self) -> io::Result<u64> {
+        Ok(self.len)
+    }
+
+    fn reader(&self) -> io::Result<Box<dyn Read + '_>> {
+        self.reader_at(0)
+    }
+
+    fn reader_at(&self, offset: u64) -> io::Result<Box<dyn Read + '_>> {
+        Ok(Box::new(RangeReader {
+            file: self,
+            pos: offset.min(self.len),
+            buf: Vec::new(),
+            at: 0,
+        }))
+    }
+
+    /// One request for exactly the bytes asked for.
+    fn read_range(&self, offset: u64, len: u64) -> io::Result<Vec<u8>> {
+        let len = len.min(self.len.saturating_sub(offset));
+        if len == 0 {
+            return Ok(Vec::new());
+        }
+        self.client.get_range(&self.url, offset, len)
+    }
+
+    fn append(&mut self, _data: &[u8]) -> io::Result<()> {
+        Err(read_only())
+    }
+
+    fn replace(&mut self, _data: &mut dyn Read) -> io::Result<()> {
+        Err(read_only())
+    }
+
+    fn name(&self) -> Option<String> {
+        Some(self.url.clone())
+    }
+}
+
+/// Sequential reads of a [`RemoteFile`], [`STREAM_CHUNK`] bytes per request.
+struct RangeReader<'a> {
+    file: &'a RemoteFile,
+    /// Offset of the next chunk to fetch
+    pos: u64,
+    buf: Vec<u8>,
+    /// Bytes of `buf` already read
+    at: usize,
+}
+
+impl Read for RangeReader<'_> {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        if self.at == self.buf.len() {
+            self.buf = self.file.read_range(self.pos, STREAM_CHUNK)?;
+            self.at = 0;
+            self.pos += self.buf.len() as u64;
+        }
+        let n = out.len().min(self.buf.len() - self.at);
+        out[..n].copy_from_slice(&self.buf[self.at..self.at + n]);
+        self.at += n;
+        Ok(n)
+    }
+}
+
+/// The entries listed by the `<url>.idx` index of the archive at `url`, or
+/// `None` if there is none. An index stamped with an archive size (written
+/// by `qvindex`) is ignored once `archive_len` differs.
+pub fn load_index(client: &dyn RangeClient, url: &str, archive_len: u64) -> io::Result<Option<Vec<IndexEntry>>> {
+    let index_url = format!("{}.idx", url);
+    let Some(size) = client.size(&index_url)?.filter(|&size| size > 0) else {
+        return Ok(None);
+    };
+    let text = String::from_utf8_lossy(&client.get_range(&index_url, 0, size)?).into_owned();
+    let mut lines = text.lines().peekable();
+    if let Some(stamp) = lines.next_if(|line| line.starts_with("QV_INDEX")) {
+        if stamp.split('\t').nth(1).and_then(|s| s.parse().ok()) != Some(archive_len) {
+            return Ok(None);
+        }
+    }
+    let mut entries = Vec::new();
+    for line in lines {
+        entries.push(IndexEntry::parse(line).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Malformed line in {}: {}", index_url, line),
+            )
+        })?);
+    }
+    Ok(Some(entries))
+}
+
+#[cfg(feature = "http")]
+pub use http::HttpClient;
+
+#[cfg(feature = "http")]
+mod http {
+    use std::io::{self, Read};
+
+    use super::RangeClient;
+
+    /// [`RangeClient`] for `http://` and `https://` URLs. The server must
+    /// answer `Range` requests.
+    pub struct HttpClient {
+        agent: ureq::Agent,
+    }
+
+    impl HttpClient {
+        pub fn new() -> Self {
+            Self {
+                agent: ureq::AgentBuilder::new().build(),
+            }
+        }
+    }
+
+    impl Default for HttpClient {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    fn http_error(e: ureq::Error) -> io::Error {
+        io::Error::other(e.to_string())
+    }
+
+    impl RangeClient for HttpClient {
+        fn size(&self, url: &str) -> io::Result<Option<u64>> {
+            match self.agent.head(url).call() {
+                Ok(response) => response
+                    .header("Content-Length")
+                    .and_then(|len| len.parse().ok())
+                    .map(Some)
+                    .ok_or_else(|| io::Error::other(format!("{} sent no Content-Length", url))),
+                Err(ureq::Error::Status(404, _)) => Ok(None),
+                Err(e) => Err(http_error(e)),
+            }
+        }
+
+        fn get_range(&self, url: &str, offset: u64, len: u64) -> io::Result<Vec<u8>> {
+            // A byte range cannot be empty: `offset + len - 1` would underflow
+            if len == 0 {
+                return Ok(Vec::new());
+            }
+            let response = self
+                .agent
+                .get(url)
+                .set("Range", &format!("bytes={}-{}", offset, offset + len - 1))
+                .call()
+                .map_err(http_error)?;
+            if response.status() != 206 {
+                return Err(io::Error::other(format!("{} does not support ranged reads", url)));
+            }
+            let mut data = Vec::with_capacity(len as usize);
+            response.into_reader().take(len).read_to_end(&mut data)?;
+            Ok(data)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::Quiver;
+    use super::*;
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    /// Objects held in memory, with a log of the ranges requested.
+    #[derive(Default)]
+    struct MemoryClient {
+        objects: HashMap<String, Vec<u8>>,
+        requests: Mutex<Vec<(String, u64, u64)>>,
+    }
+
+    impl RangeClient for MemoryClient {
+        fn size(&self, url: &str) -> io::Result<Option<u64>> {
+            Ok(self.objects.get(url).map(|data| data.len() as u64))
+        }
+
+        fn get_range(&self, url: &str, offset: u64, len: u64) -> io::Result<Vec<u8>> {
+            self.requests.lock().unwrap().push((url.to_string(), offset, len));
+            let data = &self.objects[url];
+            let end = (offset + len).min(data.len() as u64);
+            Ok(data[offset as usize..end as usize].to_vec())
+        }
+    }
+
+    const ARCHIVE: &str = "QV_TAG a\nATOM 1\nQV_TAG b\nQV_SCORE b x=1\nATOM 2\n";
+    const URL: &str = "https://example.org/a.qv";
+
+    fn memory_client(index: Option<&str>) -> Arc<MemoryClient> {
+        let mut client = MemoryClient::default();
+        client.objects.insert(URL.to_string(), ARCHIVE.as_bytes().to_vec());
+        if let Some(index) = index {
+            client.objects.insert(format!("{}.idx", URL), index.as_bytes().to_vec());
+        }
+        Arc::new(client)
+    }
+
+    #[test]
+    fn schemes() {
+        assert!(is_remote_url("s3://bucket/a.qv"));
+        assert!(is_remote_url("http://host/a.qv"));
+        assert!(!is_remote_url("/data/a.qv"));
+        #[cfg(not(feature = "s3"))]
+        assert_eq!(client_for("s3://bucket/a.qv").err().unwrap().kind(), io::ErrorKind::Unsupported);
+        #[cfg(not(feature = "http"))]
+        assert_eq!(client_for(URL).err().unwrap().kind(), io::ErrorKind::Unsupported);
+    }
+
+    #[test]
+    fn ranged_reads_of_a_scanned_archive() {
+        let client = memory_client(None);
+        assert_eq!(RemoteFile::open(client.clone(), "https://example.org/none.qv").err().unwrap().kind(), io::ErrorKind::NotFound);
+        let file = RemoteFile::open(client.clone(), URL).unwrap();
+        assert_eq!(file.read_range(9, 6).unwrap(), b"ATOM 1");
+        assert!(file.read_range(100, 6).unwrap().is_empty());
+        let mut qv = Quiver::with_storage(Box::new(file), "r").unwrap();
+        assert_eq!(qv.get_tags(), vec!["a", "b"]);
+        assert_eq!(qv.get_pdblines("b").unwrap(), vec!["ATOM 2"]);
+        assert!(qv.add_pdb(&["ATOM 3".to_string()], "c", None).is_err());
+        assert!(client.requests.lock().unwrap().iter().all(|(url, _, _)| url == URL));
+    }
+
+    #[test]
+    fn index_next_to_the_archive() {
+        let index = "a\t0\t16\nb\t16\t31\n";
+        let client = memory_client(Some(index));
+        let entries = load_index(client.as_ref(), URL, ARCHIVE.len() as u64).unwrap().unwrap();
+        assert_eq!(entries[1], IndexEntry { tag: "b".to_string(), offset: 16, length: 31 });
+
+        let stamped = format!("QV_INDEX\t{}\t0\n{}", ARCHIVE.len() + 1, index);
+        let stale = memory_client(Some(&stamped));
+        assert!(load_index(stale.as_ref(), URL, ARCHIVE.len() as u64).unwrap().is_none());
+        let malformed = memory_client(Some("a\t0\n"));
+        assert!(load_index(malformed.as_ref(), URL, ARCHIVE.len() as u64).is_err());
+        assert!(load_index(memory_client(None).as_ref(), URL, 0).unwrap().is_none());
+
+        let file = RemoteFile::open(client.clone(), URL).unwrap();
+        let qv = Quiver::from_entries(Box::new(file), entries, ARCHIVE.len() as u64).unwrap();
+        client.requests.lock().unwrap().clear();
+        assert_eq!(qv.get_pdblines("b").unwrap(), vec!["ATOM 2"]);
+        // Only the entry itself is fetched
+        assert_eq!(*client.requests.lock().unwrap(), vec![(URL.to_string(), 16, 31)]);
+    }
+}
@@ -0,0 +1,163 @@
+//! Renaming some tags of an archive by an old → new mapping.
+//!
+//! Unlike the positional form of `qvrename`, which needs one new tag for
+//! every entry in file order, a mapping names only the entries to rename and
+//! leaves the rest alone. [`check_mapping`] refuses a mapping that names a
+//! tag not in the archive or would leave two entries with the same tag;
+//! swapping two tags is fine.
+
+use std::collections::{HashMap, HashSet};
+use std::io::{BufRead, Write};
+
+use super::QuiverError;
+
+/// Parse a mapping file: one `old new` pair per line, separated by
+/// whitespace. Blank lines and lines starting with `#` are skipped.
+pub fn parse_mapping(text: &str) -> Result<Vec<(String, String)>, QuiverError> {
+    let mut pairs = Vec::new();
+    for (lineno, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match line.split_whitespace().collect::<Vec<_>>().as_slice() {
+            [old, new] => pairs.push((old.to_string(), new.to_string())),
+            _ => {
+                return Err(QuiverError::Malformed(format!(
+                    "Rename mapping line {} is not 'OLD NEW': {}",
+                    lineno + 1,
+                    line
+                )))
+            }
+        }
+    }
+    Ok(pairs)
+}
+
+/// The mapping `pairs` as a lookup, after checking it against the `tags`
+/// of the archive: every old tag must be in it and named once, and no new
+/// tag may be given twice or be the tag of an entry that is not renamed.
+pub fn check_mapping(tags: &[String], pairs: &[(String, String)]) -> Result<HashMap<String, String>, QuiverError> {
+    let present: HashSet<&str> = tags.iter().map(|t| t.as_str()).collect();
+    let mut mapping = HashMap::new();
+    for (old, new) in pairs {
+        if !present.contains(old.as_str()) {
+            return Err(QuiverError::TagNotFound(old.clone()));
+        }
+        if new.is_empty() || new.chars().any(char::is_whitespace) {
+            return Err(QuiverError::InvalidOperation(format!(
+                "Invalid new tag '{}' for {}: tags must be non-empty and contain no whitespace",
+                new, old
+            )));
+        }
+        if mapping.insert(old.clone(), new.clone()).is_some() {
+            return Err(QuiverError::InvalidOperation(format!("Tag {} is renamed twice", old)));
+        }
+    }
+    let kept: HashSet<&str> = present.into_iter().filter(|t| !mapping.contains_key(*t)).collect();
+    let mut new_tags = HashSet::new();
+    for new in mapping.values() {
+        if kept.contains(new.as_str()) || !new_tags.insert(new.as_str()) {
+            return Err(QuiverError::InvalidOperation(format!(
+                "Renaming would leave two entries tagged {}",
+                new
+            )));
+        }
+    }
+    Ok(mapping)
+}
+
+/// Replace the tag field of a `QV_TAG`, `QV_SCORE`, `QV_META` or `QV_HASH`
+/// line, keeping the rest of the line untouched.
+pub fn retag_line(line: &str, new_tag: &str) -> String {
+    let mut parts: Vec<&str> = line.trim_end_matches('\n').splitn(3, ' ').collect();
+    if parts.len() > 1 {
+        parts[1] = new_tag;
+    }
+    format!("{}\n", parts.join(" "))
+}
+
+/// Copy the archive read from `reader` to `out`, renaming the entries in
+/// `mapping` on their tag line and the score, metadata and hash lines that
+/// follow it. Returns the number of entries renamed.
+pub fn write_renamed<R: BufRead, W: Write>(
+    mut reader: R,
+    out: &mut W,
+    mapping: &HashMap<String, String>,
+) -> Result<usize, QuiverError> {
+    let mut renamed = 0;
+    // New tag of the entry being copied, if it is renamed
+    let mut current: Option<&String> = None;
+    let mut buf = Vec::new();
+    loop {
+        buf.clear();
+        if reader.read_until(b'\n', &mut buf)? == 0 {
+            break;
+        }
+        if buf.starts_with(b"QV_TAG") {
+            let line = String::from_utf8_lossy(&buf);
+            current = line.split_whitespace().nth(1).and_then(|tag| mapping.get(tag));
+            if current.is_some() {
+                renamed += 1;
+            }
+        }
+        match current {
+            Some(new_tag)
+                if [&b"QV_TAG"[..], b"QV_SCORE", b"QV_META", b"QV_HASH"]
+                    .iter()
+                    .any(|prefix| buf.starts_with(prefix)) =>
+            {
+                out.write_all(retag_line(&String::from_utf8_lossy(&buf), new_tag).as_bytes())?;
+            }
+            _ => out.write_all(&buf)?,
+        }
+    }
+    Ok(renamed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tags(names: &[&str]) -> Vec<String> {
+        names.iter().map(|s| s.to_string()).collect()
+    }
+
+    fn pairs(text: &str) -> Vec<(String, String)> {
+        parse_mapping(text).unwrap()
+    }
+
+    #[test]
+    fn mapping_files() {
+        assert_eq!(pairs("# old new\na x\n\n  b\ty  \n"), vec![
+            ("a".to_string(), "x".to_string()),
+            ("b".to_string(), "y".to_string())
+        ]);
+        let err = parse_mapping("a x\nb\n").unwrap_err();
+        assert!(format!("{:?}", err).contains("line 2"));
+    }
+
+    #[test]
+    fn mappings_are_checked_against_the_archive() {
+        let archive = tags(&["a", "b", "c"]);
+        assert_eq!(check_mapping(&archive, &pairs("a b\nb a")).unwrap().len(), 2);
+        assert!(matches!(check_mapping(&archive, &pairs("d x")), Err(QuiverError::TagNotFound(t)) if t == "d"));
+        assert!(check_mapping(&archive, &pairs("a c")).is_err());
+        assert!(check_mapping(&archive, &pairs("a x\nb x")).is_err());
+        assert!(check_mapping(&archive, &pairs("a x\na y")).is_err());
+        assert!(check_mapping(&archive, &[("a".to_string(), "x y".to_string())]).is_err());
+    }
+
+    #[test]
+    fn renamed_entries_keep_everything_else() {
+        let archive = "QV_HEADER k=v\nQV_TAG a\nQV_SCORE a x=1\nQV_META a seq=AG\nQV_HASH a fnv1a64:00\nATOM a\nQV_TAG b\nQV_SCORE b x=2\nATOM b\n";
+        let mapping = check_mapping(&tags(&["a", "b"]), &pairs("a b\nb a")).unwrap();
+        let mut out = Vec::new();
+        assert_eq!(write_renamed(archive.as_bytes(), &mut out, &mapping).unwrap(), 2);
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "QV_HEADER k=v\nQV_TAG b\nQV_SCORE b x=1\nQV_META b seq=AG\nQV_HASH b fnv1a64:00\nATOM a\nQV_TAG a\nQV_SCORE a x=2\nATOM b\n"
+        );
+        assert_eq!(retag_line("QV_TAG a FORMAT=cif", "z"), "QV_TAG z FORMAT=cif\n");
+    }
+}
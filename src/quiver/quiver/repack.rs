@@ -0,0 +1,166 @@
+//! Reordering the entries of an archive to match how they will be read.
+//!
+//! Pipelines that go through an archive best-first, or in the order of a
+//! tag list, read it at random when the entries are in the order they were
+//! appended. [`repack`] rewrites the archive atomically with the entries in
+//! that order, so the same pipeline reads it front to back. Entries are
+//! copied byte for byte; only their order changes.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use super::rank::{rank, RankKey};
+use super::warnings::{WarningCode, Warnings};
+use super::{is_generation_line, Quiver, QuiverError};
+
+/// The order [`repack`] puts entries in.
+#[derive(Debug, Clone)]
+pub enum RepackOrder {
+    /// `score:<field>[:asc|desc]`: by one score, entries without it last
+    Score(RankKey),
+    /// `taglist:<path>`: the whitespace-separated tags of a file first, in
+    /// its order, then every other entry in file order
+    TagList(PathBuf),
+}
+
+impl FromStr for RepackOrder {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            Some(("score", key)) => Ok(RepackOrder::Score(key.parse()?)),
+            Some(("taglist", path)) if !path.is_empty() => Ok(RepackOrder::TagList(PathBuf::from(path))),
+            _ => Err(format!(
+                "Unknown order '{}', expected score:<field>[:asc|desc] or taglist:<path>",
+                s
+            )),
+        }
+    }
+}
+
+/// Output of [`repack`].
+#[derive(Debug, Clone, Default)]
+pub struct RepackReport {
+    /// Entries that changed position
+    pub moved: usize,
+    /// Listed tags that are not in the archive, or listed twice
+    pub warnings: Warnings,
+}
+
+/// Rewrite `quiver_file` atomically with its entries in `order`. Nothing is
+/// written if they already are.
+pub fn repack<P: AsRef<Path>>(
+    quiver_file: P,
+    order: &RepackOrder,
+) -> Result<RepackReport, QuiverError> {
+    let mut qv = Quiver::new(quiver_file, "rw")?;
+    let mut warnings = Warnings::new();
+    let positions = match order {
+        RepackOrder::Score(key) => {
+            let scores = qv.get_score_records()?;
+            let mut by_tag = positions_by_tag(&qv.tags);
+            // Entries sharing a tag keep their relative order
+            rank(&qv.tags, &scores, std::slice::from_ref(key))
+                .into_iter()
+                .filter_map(|entry| by_tag.get_mut(entry.tag.as_str()).and_then(VecDeque::pop_front))
+                .collect()
+        }
+        RepackOrder::TagList(path) => {
+            let text = fs::read_to_string(path)?;
+            let present: HashSet<&str> = qv.tags.iter().map(|t| t.as_str()).collect();
+            let mut listed = Vec::new();
+            let mut seen = HashSet::new();
+            for tag in text.split_whitespace() {
+                if !seen.insert(tag) {
+                    warnings.push(WarningCode::DuplicateRequest, format!("Tag {} is listed more than once", tag));
+                } else if !present.contains(tag) {
+                    warnings.push(WarningCode::MissingTag, format!("Tag not found in Quiver file: {}", tag));
+                } else {
+                    listed.push(tag.to_string());
+                }
+            }
+            // Every entry of a listed tag, then the rest in file order
+            let mut by_tag = positions_by_tag(&qv.tags);
+            let mut positions = Vec::with_capacity(qv.tags.len());
+            for tag in &listed {
+                positions.extend(by_tag.remove(tag.as_str()).unwrap_or_default());
+            }
+            positions.extend((0..qv.tags.len()).filter(|&i| !seen.contains(qv.tags[i].as_str())));
+            positions
+        }
+    };
+
+    let moved = positions.iter().enumerate().filter(|(i, p)| i != *p).count();
+    if moved > 0 {
+        qv.replace_contents(positions.len(), |qv, spool| {
+            // The header lines come before the first entry
+            for line in qv.preamble_bytes()?.split_inclusive(|&b| b == b'\n') {
+                if !is_generation_line(line) {
+                    spool.write_all(line)?;
+                }
+            }
+            for &index in &positions {
+                spool.write_all(&qv.entry_bytes(index)?)?;
+            }
+            Ok(())
+        })?;
+    }
+    Ok(RepackReport { moved, warnings })
+}
+
+/// The positions of every tag in `tags`, in file order.
+fn positions_by_tag(tags: &[String]) -> HashMap<&str, VecDeque<usize>> {
+    let mut by_tag: HashMap<&str, VecDeque<usize>> = HashMap::new();
+    for (i, tag) in tags.iter().enumerate() {
+        by_tag.entry(tag.as_str()).or_default().push_back(i);
+    }
+    by_tag
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::testdata::scratch_dir;
+    use super::*;
+
+    fn write_archive(path: &Path) {
+        let mut qv = Quiver::new(path, "w").unwrap();
+        qv.set_header(&[("creator".to_string(), "test".to_string())]).unwrap();
+        for (tag, plddt) in [("a", Some("plddt=70")), ("b", Some("plddt=90")), ("c", None), ("d", Some("plddt=80"))] {
+            qv.add_pdb(&[format!("ATOM {}", tag)], tag, plddt).unwrap();
+        }
+        qv.flush().unwrap();
+    }
+
+    #[test]
+    fn repack_by_score() {
+        let path = scratch_dir("repack-score").join("a.qv");
+        write_archive(&path);
+        let report = repack(&path, &"score:plddt:desc".parse().unwrap()).unwrap();
+        assert_eq!(report.moved, 4);
+        let qv = Quiver::new(&path, "r").unwrap();
+        assert_eq!(qv.get_tags(), vec!["b", "d", "a", "c"]);
+        assert_eq!(qv.get_header(), vec![("creator".to_string(), "test".to_string())]);
+        assert_eq!(qv.get_pdblines("d").unwrap(), vec!["ATOM d"]);
+        // Already in order: nothing is written
+        let modified = fs::metadata(&path).unwrap().modified().unwrap();
+        assert_eq!(repack(&path, &"score:plddt:desc".parse().unwrap()).unwrap().moved, 0);
+        assert_eq!(fs::metadata(&path).unwrap().modified().unwrap(), modified);
+    }
+
+    #[test]
+    fn repack_by_tag_list() {
+        let dir = scratch_dir("repack-taglist");
+        let path = dir.join("a.qv");
+        write_archive(&path);
+        fs::write(dir.join("order.txt"), "d\nx\nb d\n").unwrap();
+        let order = format!("taglist:{}", dir.join("order.txt").display()).parse().unwrap();
+        let report = repack(&path, &order).unwrap();
+        assert_eq!(Quiver::new(&path, "r").unwrap().get_tags(), vec!["d", "b", "a", "c"]);
+        let codes: Vec<WarningCode> = report.warnings.iter().map(|w| w.code).collect();
+        assert_eq!(codes, vec![WarningCode::MissingTag, WarningCode::DuplicateRequest]);
+        assert!("random".parse::<RepackOrder>().is_err());
+        assert!("taglist:".parse::<RepackOrder>().is_err());
+    }
+}
@@ -0,0 +1,145 @@
+//! Cleaning up an archive left broken by a killed job.
+//!
+//! A writer killed mid-entry leaves a half-written record at the end of the
+//! archive, and concatenating such a file with another can leave `QV_SCORE`
+//! lines under the wrong entry, or before the first one. [`repair`] copies
+//! the archive without either and reports what it left out; everything else
+//! is copied byte for byte.
+
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use super::storage::write_atomically;
+use super::{PartialEntry, Quiver, QuiverError};
+
+/// A `QV_SCORE` line dropped by [`repair`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OrphanScore {
+    /// The tag the line scores
+    pub tag: String,
+    /// Line number in the original archive, counting from 1
+    pub line: usize,
+}
+
+/// Output of [`repair`].
+#[derive(Debug, Clone, Default)]
+pub struct RepairReport {
+    /// Entries written to the clean archive
+    pub entries: usize,
+    /// The incomplete record dropped from the end, see
+    /// [`Quiver::partial_entry`]
+    pub partial: Option<PartialEntry>,
+    /// `QV_SCORE` lines not preceded by the `QV_TAG` line of their tag
+    pub orphan_scores: Vec<OrphanScore>,
+}
+
+impl RepairReport {
+    /// Whether anything was left out.
+    pub fn is_clean(&self) -> bool {
+        self.partial.is_none() && self.orphan_scores.is_empty()
+    }
+}
+
+/// Copy `input` to a new archive at `output` without its trailing
+/// incomplete record and without `QV_SCORE` lines that do not belong to the
+/// entry they are in. `output` must not exist yet and is written
+/// atomically.
+pub fn repair<P: AsRef<Path>, Q: AsRef<Path>>(
+    input: P,
+    output: Q,
+) -> Result<RepairReport, QuiverError> {
+    let output = output.as_ref();
+    if output.exists() {
+        return Err(QuiverError::InvalidOperation(format!(
+            "Output file {} already exists",
+            output.display()
+        )));
+    }
+    let qv = Quiver::new(input, "r")?;
+    let mut report = RepairReport {
+        partial: qv.partial_entry().cloned(),
+        ..RepairReport::default()
+    };
+    // Reads stop before the incomplete record
+    let end = qv.snapshot_len.map_or_else(|| qv.storage.len(), Ok)?;
+
+    let written = write_atomically(output, |file| {
+        let mut reader = BufReader::new(qv.storage.reader()?.take(end));
+        let mut out = BufWriter::new(file);
+        let mut buf = Vec::new();
+        let mut current: Option<String> = None;
+        let mut written = 0u64;
+        let mut line = 0;
+        loop {
+            buf.clear();
+            if reader.read_until(b'\n', &mut buf)? == 0 {
+                break;
+            }
+            line += 1;
+            if buf.starts_with(b"QV_TAG") {
+                current = String::from_utf8_lossy(&buf)
+                    .split_whitespace()
+                    .nth(1)
+                    .map(str::to_string);
+                report.entries += usize::from(current.is_some());
+            } else if buf.starts_with(b"QV_SCORE") {
+                let text = String::from_utf8_lossy(&buf);
+                let tag = text.split_whitespace().nth(1).unwrap_or_default();
+                if current.as_deref() != Some(tag) {
+                    report.orphan_scores.push(OrphanScore {
+                        tag: tag.to_string(),
+                        line,
+                    });
+                    continue;
+                }
+            }
+            out.write_all(&buf)?;
+            written += buf.len() as u64;
+        }
+        out.flush()?;
+        Ok(written)
+    });
+    written?;
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::testdata::scratch_dir;
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn drops_orphan_scores_and_the_cut_entry() {
+        let dir = scratch_dir("repair-broken");
+        let input = dir.join("broken.qv");
+        fs::write(
+            &input,
+            "QV_SCORE z x=0\nQV_TAG a\nQV_SCORE b x=1\nATOM a\nQV_TAG b\nQV_SCORE b x=2\nATOM b\nQV_TAG c\nATOM",
+        )
+        .unwrap();
+        let report = repair(&input, dir.join("fixed.qv")).unwrap();
+        assert!(!report.is_clean());
+        assert_eq!(report.entries, 2);
+        assert_eq!(report.partial.as_ref().and_then(|p| p.tag.as_deref()), Some("c"));
+        assert_eq!(
+            report.orphan_scores,
+            vec![OrphanScore { tag: "z".to_string(), line: 1 }, OrphanScore { tag: "b".to_string(), line: 3 }]
+        );
+        assert_eq!(
+            fs::read_to_string(dir.join("fixed.qv")).unwrap(),
+            "QV_TAG a\nATOM a\nQV_TAG b\nQV_SCORE b x=2\nATOM b\n"
+        );
+        assert!(repair(&input, dir.join("fixed.qv")).is_err());
+    }
+
+    #[test]
+    fn clean_archives_are_copied_as_is() {
+        let dir = scratch_dir("repair-clean");
+        let input = dir.join("ok.qv");
+        fs::write(&input, "QV_HEADER k=v\nQV_TAG a\nQV_SCORE a x=1\nATOM a\n").unwrap();
+        let report = repair(&input, dir.join("out.qv")).unwrap();
+        assert!(report.is_clean());
+        assert_eq!(fs::read(&input).unwrap(), fs::read(dir.join("out.qv")).unwrap());
+    }
+}
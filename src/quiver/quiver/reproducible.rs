@@ -0,0 +1,166 @@
+//! Byte-identical archives from identical inputs.
+//!
+//! Content-addressed stores and build caches key archives by their bytes, so
+//! rebuilding one from the same structures has to give the same file. In
+//! reproducible mode, ingestion and merging order entries by tag instead of
+//! by argument or directory order, end every line with a bare `\n`, and keep
+//! the build time out of the header: `created` is taken from
+//! `SOURCE_DATE_EPOCH` when it is set (the reproducible-builds.org
+//! convention) and left out otherwise.
+
+use std::env;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use super::storage::write_atomically;
+use super::{format_header_lines, MetaFields, Quiver, QuiverError};
+
+/// Header fields that differ between two builds of the same archive.
+pub const VOLATILE_HEADER_FIELDS: [&str; 1] = ["created"];
+
+/// Seconds since the epoch in `SOURCE_DATE_EPOCH`, if it is set and valid.
+pub fn source_date_epoch() -> Option<u64> {
+    env::var("SOURCE_DATE_EPOCH").ok()?.trim().parse().ok()
+}
+
+/// `header` with its volatile fields set from `SOURCE_DATE_EPOCH`, or
+/// dropped when it is not set.
+pub fn stable_header(header: &[(String, String)]) -> MetaFields {
+    let epoch = source_date_epoch();
+    header
+        .iter()
+        .filter_map(|(key, value)| {
+            if !VOLATILE_HEADER_FIELDS.contains(&key.as_str()) {
+                return Some((key.clone(), value.clone()));
+            }
+            epoch.map(|epoch| (key.clone(), epoch.to_string()))
+        })
+        .collect()
+}
+
+/// `text` with every `\r\n` turned into `\n` and a final `\n` added if
+/// missing.
+pub fn normalize_newlines(text: &[u8]) -> Vec<u8> {
+    let mut normalized = Vec::with_capacity(text.len() + 1);
+    for (i, &b) in text.iter().enumerate() {
+        if b == b'\r' && text.get(i + 1) == Some(&b'\n') {
+            continue;
+        }
+        normalized.push(b);
+    }
+    if !normalized.is_empty() && !normalized.ends_with(b"\n") {
+        normalized.push(b'\n');
+    }
+    normalized
+}
+
+/// Merge `inputs` into a new archive at `output` with its entries in tag
+/// order, whatever the order of `inputs`, and the first input's header made
+/// stable (see [`stable_header`]). Fails without writing `output` if a tag
+/// appears twice. Returns the number of entries written.
+pub fn merge(inputs: &[PathBuf], output: &Path) -> Result<usize, QuiverError> {
+    if output.exists() {
+        return Err(QuiverError::InvalidOperation(format!(
+            "Output file {} already exists",
+            output.display()
+        )));
+    }
+    let archives = inputs
+        .iter()
+        .map(|path| Quiver::new(path, "r"))
+        .collect::<Result<Vec<_>, _>>()?;
+    // (tag, archive, index in the archive), sorted by tag
+    let mut entries: Vec<(&str, usize, usize)> = archives
+        .iter()
+        .enumerate()
+        .flat_map(|(a, qv)| qv.tags.iter().enumerate().map(move |(i, tag)| (tag.as_str(), a, i)))
+        .collect();
+    entries.sort();
+    if let Some(pair) = entries.windows(2).find(|pair| pair[0].0 == pair[1].0) {
+        return Err(QuiverError::DuplicateTag(pair[0].0.to_string()));
+    }
+    let header = archives.first().map(|qv| stable_header(&qv.header)).unwrap_or_default();
+
+    let mut failure = None;
+    let written = write_atomically(output, |file| {
+        let mut out = BufWriter::new(file);
+        let head = format_header_lines(&header);
+        out.write_all(head.as_bytes())?;
+        let mut written = head.len() as u64;
+        for &(tag, a, i) in &entries {
+            let bytes = archives[a].entry_bytes(i).and_then(|bytes| {
+                archives[a].record_access("merge", tag)?;
+                Ok(bytes)
+            });
+            let bytes = match bytes {
+                Ok(bytes) => normalize_newlines(&bytes),
+                Err(e) => {
+                    let message = format!("{:?}", e);
+                    failure = Some(e);
+                    return Err(io::Error::other(message));
+                }
+            };
+            out.write_all(&bytes)?;
+            written += bytes.len() as u64;
+        }
+        out.flush()?;
+        Ok(written)
+    });
+    if let Some(e) = failure {
+        return Err(e);
+    }
+    written?;
+    Ok(entries.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::testdata::scratch_dir;
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn newlines_are_normalized() {
+        assert_eq!(normalize_newlines(b"a\r\nb\rc"), b"a\nb\rc\n");
+        assert_eq!(normalize_newlines(b"a\n"), b"a\n");
+        assert!(normalize_newlines(b"").is_empty());
+    }
+
+    #[test]
+    fn volatile_fields_follow_source_date_epoch() {
+        let header = vec![
+            ("tool".to_string(), "qv".to_string()),
+            ("created".to_string(), "2024-01-01".to_string()),
+        ];
+        let mut expected = vec![("tool".to_string(), "qv".to_string())];
+        if let Some(epoch) = source_date_epoch() {
+            expected.push(("created".to_string(), epoch.to_string()));
+        }
+        assert_eq!(stable_header(&header), expected);
+    }
+
+    #[test]
+    fn merge_orders_entries_by_tag() {
+        let dir = scratch_dir("reproducible-merge");
+        fs::write(dir.join("one.qv"), "QV_TAG c\nATOM c\r\nQV_TAG a\nATOM a\n").unwrap();
+        fs::write(dir.join("two.qv"), "QV_TAG b\nQV_SCORE b x=1\nATOM b\n").unwrap();
+        let forward = [dir.join("one.qv"), dir.join("two.qv")];
+        let backward = [dir.join("two.qv"), dir.join("one.qv")];
+        assert_eq!(merge(&forward, &dir.join("forward.qv")).unwrap(), 3);
+        assert_eq!(merge(&backward, &dir.join("backward.qv")).unwrap(), 3);
+        let merged = fs::read_to_string(dir.join("forward.qv")).unwrap();
+        assert_eq!(merged, "QV_TAG a\nATOM a\nQV_TAG b\nQV_SCORE b x=1\nATOM b\nQV_TAG c\nATOM c\n");
+        assert_eq!(merged, fs::read_to_string(dir.join("backward.qv")).unwrap());
+        assert!(merge(&forward, &dir.join("forward.qv")).is_err());
+    }
+
+    #[test]
+    fn repeated_tags_are_refused() {
+        let dir = scratch_dir("reproducible-duplicate");
+        fs::write(dir.join("one.qv"), "QV_TAG a\nATOM 1\n").unwrap();
+        fs::write(dir.join("two.qv"), "QV_TAG a\nATOM 2\n").unwrap();
+        let err = merge(&[dir.join("one.qv"), dir.join("two.qv")], &dir.join("out.qv")).unwrap_err();
+        assert!(format!("{:?}", err).contains("DuplicateTag"));
+        assert!(!dir.join("out.qv").exists());
+    }
+}
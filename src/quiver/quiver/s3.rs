@@ -0,0 +1,487 @@
+//! Streaming uploads of new archives to S3.
+//!
+//! [`MultipartWriter`] is a write-only [`Storage`] that sends the archive in
+//! parts while it is being written, so a multi-GB archive never has to be
+//! staged on local disk. The object only appears once
+//! [`super::Quiver::finish`] completes the upload; dropping the writer
+//! earlier aborts it. A tag index is uploaded next to the archive as
+//! `<key>.idx`, one `tag<TAB>offset<TAB>length` line per entry.
+//!
+//! The upload protocol sits behind [`MultipartClient`]; the S3
+//! implementation, `S3Client`, needs the `s3` feature. It also reads
+//! archives back, as the [`super::remote::RangeClient`] of `s3://` URLs.
+
+use std::io::{self, Read};
+use std::mem;
+
+use super::storage::Storage;
+
+/// Smallest part S3 accepts, except for the last one.
+pub const MIN_PART_SIZE: usize = 5 << 20;
+/// Most parts one upload can have.
+pub const MAX_PARTS: i32 = 10_000;
+const DEFAULT_PART_SIZE: usize = 16 << 20;
+
+/// The requests a multipart upload is made of.
+pub trait MultipartClient: Send + Sync {
+    /// Start an upload of `key`, returning its upload id.
+    fn create(&self, key: &str) -> io::Result<String>;
+
+    /// Upload part `number` (counting from 1), returning its ETag.
+    fn upload_part(&self, key: &str, upload_id: &str, number: i32, data: Vec<u8>) -> io::Result<String>;
+
+    /// Assemble the object from `(number, ETag)` parts, in order.
+    fn complete(&self, key: &str, upload_id: &str, parts: &[(i32, String)]) -> io::Result<()>;
+
+    /// Discard an upload and the parts sent so far.
+    fn abort(&self, key: &str, upload_id: &str) -> io::Result<()>;
+
+    /// Upload a whole object in one request.
+    fn put(&self, key: &str, data: Vec<u8>) -> io::Result<()>;
+
+    /// A human-readable location for error messages.
+    fn url(&self, key: &str) -> String {
+        key.to_string()
+    }
+}
+
+struct Upload {
+    id: String,
+    parts: Vec<(i32, String)>,
+}
+
+/// Write-only storage that uploads the archive to `key` in parts.
+pub struct MultipartWriter<C: MultipartClient> {
+    client: C,
+    key: String,
+    part_size: usize,
+    /// Bytes not yet sent, always shorter than `part_size` between appends
+    buffer: Vec<u8>,
+    /// Started with the first full part; archives smaller than one part are
+    /// sent with a single `put` instead
+    upload: Option<Upload>,
+    len: u64,
+    /// `(tag, offset)` of every entry, for the index
+    index: Vec<(String, u64)>,
+    finished: bool,
+}
+
+impl<C: MultipartClient> MultipartWriter<C> {
+    pub fn new(client: C, key: &str) -> Self {
+        Self {
+            client,
+            key: key.to_string(),
+            part_size: DEFAULT_PART_SIZE,
+            buffer: Vec::new(),
+            upload: None,
+            len: 0,
+            index: Vec::new(),
+            finished: false,
+        }
+    }
+
+    /// Send parts of `bytes` (at least [`MIN_PART_SIZE`]) instead of the
+    /// default 16 MiB. With [`MAX_PARTS`] parts per upload this bounds the
+    /// archive size, e.g. about 156 GiB at the default.
+    pub fn with_part_size(mut self, bytes: usize) -> Self {
+        self.part_size = bytes.max(MIN_PART_SIZE);
+        self
+    }
+
+    fn send_part(&mut self, data: Vec<u8>) -> io::Result<()> {
+        let upload = match self.upload.as_mut() {
+            Some(upload) => upload,
+            None => {
+                let id = self.client.create(&self.key)?;
+                self.upload.insert(Upload { id, parts: Vec::new() })
+            }
+        };
+        let number = upload.parts.len() as i32 + 1;
+        if number > MAX_PARTS {
+            return Err(io::Error::other(format!(
+                "{}: more than {} parts; use a larger part size",
+                self.client.url(&self.key),
+                MAX_PARTS
+            )));
+        }
+        let etag = self.client.upload_part(&self.key, &upload.id, number, data)?;
+        upload.parts.push((number, etag));
+        Ok(())
+    }
+
+    fn index_text(&self) -> String {
+        let mut text = String::new();
+        for (i, (tag, offset)) in self.index.iter().enumerate() {
+            let end = self.index.get(i + 1).map_or(self.len, |&(_, next)| next);
+            text.push_str(&format!("{}\t{}\t{}\n", tag, offset, end - offset));
+        }
+        text
+    }
+}
+
+fn write_only() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Unsupported,
+        "an S3 upload is write-only; open the archive again to read it",
+    )
+}
+
+impl<C: MultipartClient> Storage for MultipartWriter<C> {
+    fn exists(&self) -> bool {
+        self.len > 0
+    }
+
+    fn len(&self) -> io::Result<u64> {
+        Ok(self.len)
+    }
+
+    fn reader(&self) -> io::Result<Box<dyn Read + '_>> {
+        Err(write_only())
+    }
+
+    /// Expects whole lines, as `Quiver` always appends them.
+    fn append(&mut self, data: &[u8]) -> io::Result<()> {
+        if self.finished {
+            return Err(io::Error::other(format!("{} is already complete", self.client.url(&self.key))));
+        }
+        let mut offset = self.len;
+        for line in data.split_inclusive(|&b| b == b'\n') {
+            if let Some(rest) = line.strip_prefix(b"QV_TAG ") {
+                let rest = String::from_utf8_lossy(rest);
+                if let Some(tag) = rest.split_whitespace().next() {
+                    self.index.push((tag.to_string(), offset));
+                }
+            }
+            offset += line.len() as u64;
+        }
+        self.len = offset;
+
+        self.buffer.extend_from_slice(data);
+        while self.buffer.len() >= self.part_size {
+            let rest = self.buffer.split_off(self.part_size);
+            let part = mem::replace(&mut self.buffer, rest);
+            self.send_part(part)?;
+        }
+        Ok(())
+    }
+
+    /// Parts are sent as they fill up; the rest waits for
+    /// [`Storage::finish`], since S3 rejects small parts.
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        if self.finished {
+            return Ok(());
+        }
+        let rest = mem::take(&mut self.buffer);
+        if self.upload.is_none() {
+            self.client.put(&self.key, rest)?;
+        } else {
+            if !rest.is_empty() {
+                self.send_part(rest)?;
+            }
+            if let Some(upload) = &self.upload {
+                self.client.complete(&self.key, &upload.id, &upload.parts)?;
+            }
+            self.upload = None;
+        }
+        self.client.put(&format!("{}.idx", self.key), self.index_text().into_bytes())?;
+        self.finished = true;
+        Ok(())
+    }
+
+    fn replace(&mut self, _data: &mut dyn Read) -> io::Result<()> {
+        Err(write_only())
+    }
+
+    fn name(&self) -> Option<String> {
+        Some(self.client.url(&self.key))
+    }
+}
+
+impl<C: MultipartClient> Drop for MultipartWriter<C> {
+    /// An unfinished upload is aborted, so no partial archive appears.
+    fn drop(&mut self) {
+        if let Some(upload) = self.upload.take() {
+            let _ = self.client.abort(&self.key, &upload.id);
+        }
+    }
+}
+
+/// Split `s3://bucket/key` into bucket and key.
+pub fn parse_url(url: &str) -> Option<(&str, &str)> {
+    let (bucket, key) = url.strip_prefix("s3://")?.split_once('/')?;
+    (!bucket.is_empty() && !key.is_empty()).then_some((bucket, key))
+}
+
+/// A writer for a new archive at `s3://bucket/key`.
+#[cfg(feature = "s3")]
+pub fn open_writer(url: &str) -> io::Result<Box<dyn Storage>> {
+    let (bucket, key) = parse_url(url)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, format!("Invalid S3 URL '{}'", url)))?;
+    Ok(Box::new(MultipartWriter::new(S3Client::new(bucket)?, key)))
+}
+
+#[cfg(not(feature = "s3"))]
+pub fn open_writer(url: &str) -> io::Result<Box<dyn Storage>> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!("Cannot write '{}': built without the s3 feature", url),
+    ))
+}
+
+#[cfg(feature = "s3")]
+pub use aws::S3Client;
+
+#[cfg(feature = "s3")]
+mod aws {
+    use std::io;
+
+    use aws_sdk_s3::error::DisplayErrorContext;
+    use aws_sdk_s3::primitives::ByteStream;
+    use aws_sdk_s3::types::{CompletedMultipartUpload, CompletedPart};
+    use tokio::runtime::Runtime;
+
+    use super::super::remote::RangeClient;
+    use super::MultipartClient;
+
+    /// [`MultipartClient`] for one S3 bucket, configured like the AWS CLI
+    /// (environment, profile or instance role).
+    pub struct S3Client {
+        client: aws_sdk_s3::Client,
+        bucket: String,
+        runtime: Runtime,
+    }
+
+    impl S3Client {
+        pub fn new(bucket: &str) -> io::Result<Self> {
+            let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build()?;
+            let config = runtime.block_on(aws_config::load_defaults(aws_config::BehaviorVersion::latest()));
+            Ok(Self {
+                client: aws_sdk_s3::Client::new(&config),
+                bucket: bucket.to_string(),
+                runtime,
+            })
+        }
+    }
+
+    fn s3_error<E: std::error::Error>(e: E) -> io::Error {
+        io::Error::other(DisplayErrorContext(e).to_string())
+    }
+
+    impl MultipartClient for S3Client {
+        fn create(&self, key: &str) -> io::Result<String> {
+            let output = self
+                .runtime
+                .block_on(self.client.create_multipart_upload().bucket(&self.bucket).key(key).send())
+                .map_err(s3_error)?;
+            output
+                .upload_id()
+                .map(str::to_string)
+                .ok_or_else(|| io::Error::other("S3 returned no upload id"))
+        }
+
+        fn upload_part(&self, key: &str, upload_id: &str, number: i32, data: Vec<u8>) -> io::Result<String> {
+            let request = self
+                .client
+                .upload_part()
+                .bucket(&self.bucket)
+                .key(key)
+                .upload_id(upload_id)
+                .part_number(number)
+                .body(ByteStream::from(data));
+            let output = self.runtime.block_on(request.send()).map_err(s3_error)?;
+            output
+                .e_tag()
+                .map(str::to_string)
+                .ok_or_else(|| io::Error::other(format!("S3 returned no ETag for part {}", number)))
+        }
+
+        fn complete(&self, key: &str, upload_id: &str, parts: &[(i32, String)]) -> io::Result<()> {
+            let parts = parts
+                .iter()
+                .map(|(number, etag)| CompletedPart::builder().part_number(*number).e_tag(etag).build())
+                .collect();
+            let request = self
+                .client
+                .complete_multipart_upload()
+                .bucket(&self.bucket)
+                .key(key)
+                .upload_id(upload_id)
+                .multipart_upload(CompletedMultipartUpload::builder().set_parts(Some(parts)).build());
+            self.runtime.block_on(request.send()).map_err(s3_error)?;
+            Ok(())
+        }
+
+        fn abort(&self, key: &str, upload_id: &str) -> io::Result<()> {
+            let request = self
+                .client
+                .abort_multipart_upload()
+                .bucket(&self.bucket)
+                .key(key)
+                .upload_id(upload_id);
+            self.runtime.block_on(request.send()).map_err(s3_error)?;
+            Ok(())
+        }
+
+        fn put(&self, key: &str, data: Vec<u8>) -> io::Result<()> {
+            let request = self
+                .client
+                .put_object()
+                .bucket(&self.bucket)
+                .key(key)
+                .body(ByteStream::from(data));
+            self.runtime.block_on(request.send()).map_err(s3_error)?;
+            Ok(())
+        }
+
+        fn url(&self, key: &str) -> String {
+            format!("s3://{}/{}", self.bucket, key)
+        }
+    }
+
+    /// The key of an `s3://bucket/key` URL in this client's bucket.
+    fn key_of<'a>(url: &'a str, bucket: &str) -> io::Result<&'a str> {
+        super::parse_url(url)
+            .filter(|(b, _)| *b == bucket)
+            .map(|(_, key)| key)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, format!("'{}' is not in s3://{}", url, bucket)))
+    }
+
+    impl RangeClient for S3Client {
+        fn size(&self, url: &str) -> io::Result<Option<u64>> {
+            let request = self.client.head_object().bucket(&self.bucket).key(key_of(url, &self.bucket)?);
+            match self.runtime.block_on(request.send()) {
+                Ok(output) => Ok(Some(output.content_length().unwrap_or(0).max(0) as u64)),
+                Err(e) if e.as_service_error().is_some_and(|e| e.is_not_found()) => Ok(None),
+                Err(e) => Err(s3_error(e)),
+            }
+        }
+
+        fn get_range(&self, url: &str, offset: u64, len: u64) -> io::Result<Vec<u8>> {
+            // There is no empty `bytes=` range to ask for
+            if len == 0 {
+                return Ok(Vec::new());
+            }
+            let request = self
+                .client
+                .get_object()
+                .bucket(&self.bucket)
+                .key(key_of(url, &self.bucket)?)
+                .range(format!("bytes={}-{}", offset, offset + len - 1));
+            self.runtime.block_on(async {
+                let output = request.send().await.map_err(s3_error)?;
+                let body = output.body.collect().await.map_err(io::Error::other)?;
+                Ok(body.into_bytes().to_vec())
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+
+    /// Records every request and keeps the objects it was sent.
+    #[derive(Clone, Default)]
+    struct MemoryClient {
+        log: Arc<Mutex<Vec<String>>>,
+        objects: Arc<Mutex<HashMap<String, Vec<u8>>>>,
+    }
+
+    impl MemoryClient {
+        fn requests(&self) -> Vec<String> {
+            self.log.lock().unwrap().clone()
+        }
+
+        fn object(&self, key: &str) -> Option<Vec<u8>> {
+            self.objects.lock().unwrap().get(key).cloned()
+        }
+    }
+
+    impl MultipartClient for MemoryClient {
+        fn create(&self, key: &str) -> io::Result<String> {
+            self.log.lock().unwrap().push(format!("create {}", key));
+            Ok("upload".to_string())
+        }
+
+        fn upload_part(&self, _key: &str, _upload_id: &str, number: i32, data: Vec<u8>) -> io::Result<String> {
+            self.log.lock().unwrap().push(format!("part {} {}", number, data.len()));
+            Ok(format!("etag{}", number))
+        }
+
+        fn complete(&self, key: &str, _upload_id: &str, parts: &[(i32, String)]) -> io::Result<()> {
+            self.log.lock().unwrap().push(format!("complete {} {}", key, parts.len()));
+            Ok(())
+        }
+
+        fn abort(&self, key: &str, _upload_id: &str) -> io::Result<()> {
+            self.log.lock().unwrap().push(format!("abort {}", key));
+            Ok(())
+        }
+
+        fn put(&self, key: &str, data: Vec<u8>) -> io::Result<()> {
+            self.log.lock().unwrap().push(format!("put {}", key));
+            self.objects.lock().unwrap().insert(key.to_string(), data);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn urls() {
+        assert_eq!(parse_url("s3://bucket/dir/a.qv"), Some(("bucket", "dir/a.qv")));
+        assert_eq!(parse_url("s3://bucket/"), None);
+        assert_eq!(parse_url("s3:///a.qv"), None);
+        assert_eq!(parse_url("/tmp/a.qv"), None);
+    }
+
+    #[test]
+    fn small_archives_are_put_whole_with_their_index() {
+        let client = MemoryClient::default();
+        let mut writer = MultipartWriter::new(client.clone(), "a.qv");
+        writer.append(b"QV_HEADER k=v\nQV_TAG a\nATOM a\n").unwrap();
+        writer.append(b"QV_TAG b extra\nATOM b\n").unwrap();
+        assert!(writer.reader().is_err());
+        writer.finish().unwrap();
+        assert_eq!(client.requests(), ["put a.qv", "put a.qv.idx"]);
+        assert_eq!(client.object("a.qv").unwrap().len(), 52);
+        assert_eq!(client.object("a.qv.idx").unwrap(), b"a\t14\t16\nb\t30\t22\n");
+        assert!(writer.append(b"QV_TAG c\n").is_err());
+    }
+
+    #[test]
+    fn large_archives_are_sent_in_parts() {
+        let client = MemoryClient::default();
+        let mut writer = MultipartWriter::new(client.clone(), "big.qv").with_part_size(0);
+        let line = vec![b'x'; 1023].into_iter().chain([b'\n']).collect::<Vec<u8>>();
+        for _ in 0..(MIN_PART_SIZE / 1024 * 2 + 1) {
+            writer.append(&line).unwrap();
+        }
+        writer.finish().unwrap();
+        assert_eq!(
+            client.requests(),
+            [
+                "create big.qv".to_string(),
+                format!("part 1 {}", MIN_PART_SIZE),
+                format!("part 2 {}", MIN_PART_SIZE),
+                "part 3 1024".to_string(),
+                "complete big.qv 3".to_string(),
+                "put big.qv.idx".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn unfinished_uploads_are_aborted() {
+        let client = MemoryClient::default();
+        let mut writer = MultipartWriter::new(client.clone(), "big.qv").with_part_size(0);
+        writer.append(&vec![b'\n'; MIN_PART_SIZE]).unwrap();
+        drop(writer);
+        assert_eq!(client.requests().last().map(String::as_str), Some("abort big.qv"));
+        assert!(client.object("big.qv").is_none());
+    }
+}
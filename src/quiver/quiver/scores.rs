@@ -0,0 +1,344 @@
+//! Typed score values.
+//!
+//! `QV_SCORE` lines store every value as text. [`ScoreValue::parse`] gives
+//! each one a type, so exports keep numbers numeric and sorting and
+//! filtering compare numbers as numbers rather than as strings.
+
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
+
+/// One score value. Integers are tried first, then floats (including `NaN`
+/// and `inf`), then `true`/`false`; anything else is a string.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScoreValue {
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    Str(String),
+}
+
+/// The type of a score value or of a whole column of them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScoreType {
+    Int,
+    Float,
+    Bool,
+    Str,
+}
+
+impl ScoreValue {
+    pub fn parse(raw: &str) -> Self {
+        if let Ok(i) = raw.parse::<i64>() {
+            ScoreValue::Int(i)
+        } else if let Ok(f) = raw.parse::<f64>() {
+            ScoreValue::Float(f)
+        } else if raw.eq_ignore_ascii_case("true") {
+            ScoreValue::Bool(true)
+        } else if raw.eq_ignore_ascii_case("false") {
+            ScoreValue::Bool(false)
+        } else {
+            ScoreValue::Str(raw.to_string())
+        }
+    }
+
+    pub fn score_type(&self) -> ScoreType {
+        match self {
+            ScoreValue::Int(_) => ScoreType::Int,
+            ScoreValue::Float(_) => ScoreType::Float,
+            ScoreValue::Bool(_) => ScoreType::Bool,
+            ScoreValue::Str(_) => ScoreType::Str,
+        }
+    }
+
+    /// The value as stored in a column of type `ty`: integers in a float
+    /// column become floats, everything else is unchanged.
+    pub fn widen(&self, ty: ScoreType) -> ScoreValue {
+        match (self, ty) {
+            (ScoreValue::Int(i), ScoreType::Float) => ScoreValue::Float(*i as f64),
+            _ => self.clone(),
+        }
+    }
+
+    /// The value as a number; `None` for booleans and strings.
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            ScoreValue::Int(i) => Some(*i as f64),
+            ScoreValue::Float(f) => Some(*f),
+            ScoreValue::Bool(_) | ScoreValue::Str(_) => None,
+        }
+    }
+
+    /// The value as a JSON literal; non-finite floats become `null`.
+    pub fn to_json(&self) -> String {
+        match self {
+            ScoreValue::Float(x) if !x.is_finite() => "null".to_string(),
+            ScoreValue::Str(s) => json_string(s),
+            other => other.to_string(),
+        }
+    }
+
+    /// A total order: numbers (by value), then booleans, then strings.
+    pub fn total_cmp(&self, other: &Self) -> Ordering {
+        let class = |v: &Self| match v {
+            ScoreValue::Int(_) | ScoreValue::Float(_) => 0,
+            ScoreValue::Bool(_) => 1,
+            ScoreValue::Str(_) => 2,
+        };
+        match (self, other) {
+            (ScoreValue::Int(a), ScoreValue::Int(b)) => a.cmp(b),
+            (ScoreValue::Bool(a), ScoreValue::Bool(b)) => a.cmp(b),
+            (ScoreValue::Str(a), ScoreValue::Str(b)) => a.cmp(b),
+            (a, b) => match (a.as_f64(), b.as_f64()) {
+                (Some(x), Some(y)) => x.total_cmp(&y),
+                _ => class(a).cmp(&class(b)),
+            },
+        }
+    }
+}
+
+/// Floats always print with a decimal point or exponent, so a value written
+/// out parses back to the same type.
+impl fmt::Display for ScoreValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScoreValue::Int(i) => write!(f, "{}", i),
+            ScoreValue::Float(x) => write!(f, "{:?}", x),
+            ScoreValue::Bool(b) => write!(f, "{}", b),
+            ScoreValue::Str(s) => f.write_str(s),
+        }
+    }
+}
+
+impl From<i64> for ScoreValue {
+    fn from(i: i64) -> Self {
+        ScoreValue::Int(i)
+    }
+}
+
+impl From<f64> for ScoreValue {
+    fn from(x: f64) -> Self {
+        ScoreValue::Float(x)
+    }
+}
+
+impl From<bool> for ScoreValue {
+    fn from(b: bool) -> Self {
+        ScoreValue::Bool(b)
+    }
+}
+
+impl From<&str> for ScoreValue {
+    fn from(s: &str) -> Self {
+        ScoreValue::Str(s.to_string())
+    }
+}
+
+impl From<String> for ScoreValue {
+    fn from(s: String) -> Self {
+        ScoreValue::Str(s)
+    }
+}
+
+impl ScoreType {
+    /// The narrowest type holding values of both types: integers widen to
+    /// floats, and any other mix is a string column.
+    pub fn unify(self, other: Self) -> Self {
+        match (self, other) {
+            (a, b) if a == b => a,
+            (ScoreType::Int, ScoreType::Float) | (ScoreType::Float, ScoreType::Int) => ScoreType::Float,
+            _ => ScoreType::Str,
+        }
+    }
+
+    /// What exporters write for a missing value in a column of this type.
+    pub fn missing(self) -> &'static str {
+        match self {
+            ScoreType::Int | ScoreType::Float => "NaN",
+            ScoreType::Bool | ScoreType::Str => "",
+        }
+    }
+}
+
+/// `s` as a quoted JSON string.
+pub fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Which value wins when a score line repeats a key.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DuplicatePolicy {
+    First,
+    #[default]
+    Last,
+    /// Reject the line
+    Error,
+    /// Keep the last value and report the key
+    Warn,
+}
+
+impl FromStr for DuplicatePolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "first" => Ok(DuplicatePolicy::First),
+            "last" => Ok(DuplicatePolicy::Last),
+            "error" => Ok(DuplicatePolicy::Error),
+            "warn" => Ok(DuplicatePolicy::Warn),
+            other => Err(format!(
+                "Unknown duplicate policy '{}', expected first, last, error or warn",
+                other
+            )),
+        }
+    }
+}
+
+/// Collapse repeated keys of one score line under `policy`.
+///
+/// Each key keeps the position of its first occurrence. Returns the keys
+/// that were repeated, or an error naming the first one under
+/// [`DuplicatePolicy::Error`].
+pub fn resolve_duplicates(fields: &mut Vec<(String, String)>, policy: DuplicatePolicy) -> Result<Vec<String>, String> {
+    let mut seen = HashSet::new();
+    let mut repeated = Vec::new();
+    for (key, _) in fields.iter() {
+        if !seen.insert(key.as_str()) && !repeated.contains(key) {
+            repeated.push(key.clone());
+        }
+    }
+    if repeated.is_empty() {
+        return Ok(repeated);
+    }
+    if policy == DuplicatePolicy::Error {
+        return Err(format!("duplicate score field '{}'", repeated[0]));
+    }
+    let mut resolved: Vec<(String, String)> = Vec::with_capacity(fields.len());
+    for (key, value) in fields.drain(..) {
+        match resolved.iter_mut().find(|(k, _)| *k == key) {
+            Some(field) if policy != DuplicatePolicy::First => field.1 = value,
+            Some(_) => {}
+            None => resolved.push((key, value)),
+        }
+    }
+    *fields = resolved;
+    Ok(repeated)
+}
+
+/// Type every value of parsed `key=value` score fields.
+pub fn typed_fields(fields: &[(String, String)]) -> Vec<(String, ScoreValue)> {
+    fields
+        .iter()
+        .map(|(k, v)| (k.clone(), ScoreValue::parse(v)))
+        .collect()
+}
+
+/// [`typed_fields`] keyed by field name, for expression filters.
+pub fn score_map(fields: &[(String, String)]) -> HashMap<String, ScoreValue> {
+    typed_fields(fields).into_iter().collect()
+}
+
+/// Output of [`Quiver::get_scores_many`](super::Quiver::get_scores_many).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ScoreLookup {
+    /// The typed scores of every requested tag in the archive, in request
+    /// order; empty for an entry without a `QV_SCORE` line
+    pub scores: Vec<(String, Vec<(String, ScoreValue)>)>,
+    /// Requested tags that are not in the archive, in request order
+    pub missing: Vec<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fields(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn values_are_typed_in_order() {
+        assert_eq!(ScoreValue::parse("42"), ScoreValue::Int(42));
+        assert_eq!(ScoreValue::parse("-1.5e2"), ScoreValue::Float(-150.0));
+        assert!(matches!(ScoreValue::parse("NaN"), ScoreValue::Float(x) if x.is_nan()));
+        assert_eq!(ScoreValue::parse("True"), ScoreValue::Bool(true));
+        assert_eq!(ScoreValue::parse("A12"), ScoreValue::Str("A12".to_string()));
+        assert_eq!(ScoreValue::Float(2.0).to_string(), "2.0");
+        assert_eq!(ScoreValue::parse(&ScoreValue::Float(2.0).to_string()), ScoreValue::Float(2.0));
+    }
+
+    #[test]
+    fn json_literals() {
+        assert_eq!(ScoreValue::Float(f64::INFINITY).to_json(), "null");
+        assert_eq!(ScoreValue::Int(3).to_json(), "3");
+        assert_eq!(ScoreValue::from("a\"b\\\n\u{1}").to_json(), "\"a\\\"b\\\\\\n\\u0001\"");
+    }
+
+    #[test]
+    fn numbers_then_booleans_then_strings() {
+        let mut values = vec![
+            ScoreValue::from("b"),
+            ScoreValue::from(true),
+            ScoreValue::from(2.5),
+            ScoreValue::from(2),
+            ScoreValue::from("a"),
+            ScoreValue::from(false),
+            ScoreValue::from(3),
+        ];
+        values.sort_by(ScoreValue::total_cmp);
+        assert_eq!(
+            values,
+            [
+                ScoreValue::from(2),
+                ScoreValue::from(2.5),
+                ScoreValue::from(3),
+                ScoreValue::from(false),
+                ScoreValue::from(true),
+                ScoreValue::from("a"),
+                ScoreValue::from("b"),
+            ]
+        );
+    }
+
+    #[test]
+    fn column_types_unify() {
+        assert_eq!(ScoreType::Int.unify(ScoreType::Float), ScoreType::Float);
+        assert_eq!(ScoreType::Bool.unify(ScoreType::Bool), ScoreType::Bool);
+        assert_eq!(ScoreType::Int.unify(ScoreType::Bool), ScoreType::Str);
+        assert_eq!(ScoreValue::Int(1).widen(ScoreType::Float), ScoreValue::Float(1.0));
+        assert_eq!(ScoreValue::Int(1).widen(ScoreType::Str), ScoreValue::Int(1));
+        assert_eq!(ScoreType::Float.missing(), "NaN");
+    }
+
+    #[test]
+    fn duplicate_keys_follow_the_policy() {
+        let line = fields(&[("a", "1"), ("b", "2"), ("a", "3")]);
+        let resolve = |policy| {
+            let mut fields = line.clone();
+            resolve_duplicates(&mut fields, policy).map(|repeated| (fields, repeated))
+        };
+        assert_eq!(
+            resolve(DuplicatePolicy::First).unwrap(),
+            (fields(&[("a", "1"), ("b", "2")]), vec!["a".to_string()])
+        );
+        assert_eq!(resolve(DuplicatePolicy::Warn).unwrap().0, fields(&[("a", "3"), ("b", "2")]));
+        assert!(resolve(DuplicatePolicy::Error).unwrap_err().contains("'a'"));
+        assert_eq!("warn".parse(), Ok(DuplicatePolicy::Warn));
+        assert!("newest".parse::<DuplicatePolicy>().is_err());
+    }
+}
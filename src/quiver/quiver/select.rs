@@ -0,0 +1,65 @@
+//! Selecting tags by pattern instead of listing them one by one.
+//!
+//! A [`TagPattern`] is either a shell-style glob, where `*` matches any run
+//! of characters and `?` any one and the whole tag has to match, or a
+//! regular expression, which (as with `grep -E`) may match anywhere in the
+//! tag unless anchored with `^` and `$`.
+
+use regex::Regex;
+
+use super::shard::wildcard_match;
+
+#[derive(Debug, Clone)]
+pub enum TagPattern {
+    /// `design_17_*`
+    Glob(Vec<char>),
+    /// `^design_(17|18)_seed[0-4]$`
+    Regex(Regex),
+}
+
+impl TagPattern {
+    /// A glob, or with `regex` a regular expression. Fails on an invalid
+    /// regular expression.
+    pub fn new(pattern: &str, regex: bool) -> Result<Self, String> {
+        if !regex {
+            return Ok(TagPattern::Glob(pattern.chars().collect()));
+        }
+        Regex::new(pattern)
+            .map(TagPattern::Regex)
+            .map_err(|e| format!("Invalid tag regex '{}': {}", pattern, e))
+    }
+
+    pub fn matches(&self, tag: &str) -> bool {
+        match self {
+            TagPattern::Glob(pattern) => {
+                let tag: Vec<char> = tag.chars().collect();
+                wildcard_match(pattern, &tag)
+            }
+            TagPattern::Regex(regex) => regex.is_match(tag),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn globs_match_the_whole_tag() {
+        let pattern = TagPattern::new("design_1?_*", false).unwrap();
+        assert!(pattern.matches("design_17_seed3"));
+        assert!(pattern.matches("design_10_"));
+        assert!(!pattern.matches("design_7_seed3"));
+        assert!(!pattern.matches("old_design_17_seed3"));
+    }
+
+    #[test]
+    fn regexes_match_anywhere_unless_anchored() {
+        let pattern = TagPattern::new("_(17|18)_", true).unwrap();
+        assert!(pattern.matches("design_18_seed0"));
+        assert!(!pattern.matches("design_19_seed0"));
+        let anchored = TagPattern::new("^design_17$", true).unwrap();
+        assert!(!anchored.matches("design_17_seed0"));
+        assert!(TagPattern::new("design_(", true).unwrap_err().contains("design_("));
+    }
+}
@@ -0,0 +1,299 @@
+use std::io::Write;
+use std::str::FromStr;
+
+use regex::Regex;
+
+use super::{PayloadFormat, Quiver, QuiverError};
+
+/// Map a three-letter residue name to its one-letter code.
+///
+/// Unknown residues map to 'X'.
+pub fn three_to_one(resname: &str) -> char {
+    match resname {
+        "ALA" => 'A',
+        "ARG" => 'R',
+        "ASN" => 'N',
+        "ASP" => 'D',
+        "CYS" => 'C',
+        "GLN" => 'Q',
+        "GLU" => 'E',
+        "GLY" => 'G',
+        "HIS" => 'H',
+        "ILE" => 'I',
+        "LEU" => 'L',
+        "LYS" => 'K',
+        "MET" => 'M',
+        "PHE" => 'F',
+        "PRO" => 'P',
+        "SER" => 'S',
+        "THR" => 'T',
+        "TRP" => 'W',
+        "TYR" => 'Y',
+        "VAL" => 'V',
+        "MSE" => 'M',
+        "SEC" => 'U',
+        "PYL" => 'O',
+        _ => 'X',
+    }
+}
+
+/// Derive the one-letter sequence of every chain from ATOM records.
+///
+/// Chains are returned in the order they first appear. A new residue starts
+/// whenever the residue number or insertion code changes within a chain.
+pub fn chain_sequences<S: AsRef<str>>(pdb_lines: &[S]) -> Vec<(String, String)> {
+    let mut chains: Vec<(String, String)> = Vec::new();
+    let mut last_residue: Option<(String, String)> = None;
+
+    for line in pdb_lines {
+        let line = line.as_ref();
+        if !line.starts_with("ATOM") || line.len() < 27 {
+            continue;
+        }
+        let resname = line.get(17..20).unwrap_or("").trim();
+        let chain = line.get(21..22).unwrap_or(" ").trim().to_string();
+        let resid = line.get(22..27).unwrap_or("").to_string();
+
+        let residue = (chain.clone(), resid);
+        if last_residue.as_ref() == Some(&residue) {
+            continue;
+        }
+        last_residue = Some(residue);
+
+        match chains.iter_mut().find(|(id, _)| *id == chain) {
+            Some((_, seq)) => seq.push(three_to_one(resname)),
+            None => chains.push((chain, three_to_one(resname).to_string())),
+        }
+    }
+    chains
+}
+
+/// Read the one-letter sequence of every chain from SEQRES records.
+///
+/// Chains are returned in the order they first appear.
+pub fn seqres_sequences<S: AsRef<str>>(pdb_lines: &[S]) -> Vec<(String, String)> {
+    let mut chains: Vec<(String, String)> = Vec::new();
+    for line in pdb_lines {
+        let line = line.as_ref();
+        if !line.starts_with("SEQRES") {
+            continue;
+        }
+        let chain = line.get(11..12).unwrap_or(" ").trim().to_string();
+        let residues: String = line.get(19..).unwrap_or("").split_whitespace().map(three_to_one).collect();
+        match chains.iter_mut().find(|(id, _)| *id == chain) {
+            Some((_, seq)) => seq.push_str(&residues),
+            None => chains.push((chain, residues)),
+        }
+    }
+    chains
+}
+
+/// Which records sequences are read from.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SequenceSource {
+    /// The modelled residues (ATOM records)
+    #[default]
+    Atom,
+    /// The SEQRES records, or the ATOM records of a structure without any
+    Seqres,
+}
+
+impl FromStr for SequenceSource {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "atom" => Ok(SequenceSource::Atom),
+            "seqres" => Ok(SequenceSource::Seqres),
+            other => Err(format!("Unknown sequence source '{}', expected one of: atom, seqres", other)),
+        }
+    }
+}
+
+/// Write one FASTA record per chain of every PDB entry, in file order.
+///
+/// Headers are `>tag_chain` (`>tag` for a chain without an identifier) and
+/// each sequence is on one line. With `chains`, only those chains are
+/// written. mmCIF entries are skipped. Returns the number of records.
+pub fn write_fasta<W: Write>(
+    qv: &Quiver,
+    mut out: W,
+    chains: Option<&[String]>,
+    source: SequenceSource,
+) -> Result<usize, QuiverError> {
+    let mut written = 0;
+    for entry in qv.iter()? {
+        let entry = entry?;
+        if entry.format != PayloadFormat::Pdb {
+            continue;
+        }
+        let mut sequences = match source {
+            SequenceSource::Atom => Vec::new(),
+            SequenceSource::Seqres => seqres_sequences(&entry.lines),
+        };
+        if sequences.is_empty() {
+            sequences = chain_sequences(&entry.lines);
+        }
+        for (chain, seq) in sequences {
+            if chains.is_some_and(|wanted| !wanted.contains(&chain)) {
+                continue;
+            }
+            if chain.is_empty() {
+                writeln!(out, ">{}\n{}", entry.tag, seq)?;
+            } else {
+                writeln!(out, ">{}_{}\n{}", entry.tag, chain, seq)?;
+            }
+            written += 1;
+        }
+    }
+    out.flush()?;
+    Ok(written)
+}
+
+/// Build the metadata fields (`seq_<chain>=<sequence>`) for a structure.
+///
+/// Chains without an identifier are stored under `seq__`.
+pub fn sequence_fields<S: AsRef<str>>(pdb_lines: &[S]) -> Vec<(String, String)> {
+    chain_sequences(pdb_lines)
+        .into_iter()
+        .map(|(chain, seq)| {
+            let chain = if chain.is_empty() { "_".to_string() } else { chain };
+            (format!("seq_{}", chain), seq)
+        })
+        .collect()
+}
+
+/// A motif match within a stored chain sequence.
+#[derive(Debug, Clone)]
+pub struct MotifHit {
+    pub tag: String,
+    pub chain: String,
+    /// 1-based position of the first matched residue
+    pub start: usize,
+    /// 1-based position of the last matched residue
+    pub end: usize,
+    pub matched: String,
+}
+
+/// Search the stored `seq_<chain>` metadata for a regex motif.
+///
+/// Only structures annotated with sequences (see `qvannotate --sequences`)
+/// are searched; the PDB payloads are never parsed.
+pub fn find_motif(
+    qv: &Quiver,
+    pattern: &Regex,
+    chain: Option<&str>,
+) -> Result<Vec<MotifHit>, QuiverError> {
+    let mut hits = Vec::new();
+    for (tag, fields) in qv.get_meta_records()? {
+        for (key, seq) in &fields {
+            let Some(seq_chain) = key.strip_prefix("seq_") else {
+                continue;
+            };
+            if chain.is_some_and(|c| c != seq_chain) {
+                continue;
+            }
+            for m in pattern.find_iter(seq) {
+                hits.push(MotifHit {
+                    tag: tag.clone(),
+                    chain: seq_chain.to_string(),
+                    start: m.start() + 1,
+                    end: m.end(),
+                    matched: m.as_str().to_string(),
+                });
+            }
+        }
+    }
+    Ok(hits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::testdata::scratch_dir;
+    use super::*;
+    use std::fs;
+
+    fn atom(serial: usize, resname: &str, chain: char, resid: &str) -> String {
+        format!(
+            "ATOM  {:>5}  CA  {} {}{:<5}      0.000   0.000   0.000  1.00  0.00           C",
+            serial, resname, chain, resid
+        )
+    }
+
+    #[test]
+    fn sequences_from_atom_records() {
+        let lines = [
+            atom(1, "MET", 'A', "   1"),
+            atom(2, "MET", 'A', "   1"),
+            atom(3, "GLY", 'A', "   2"),
+            atom(4, "GLY", 'A', "   2A"),
+            atom(5, "HOH", 'B', "   1"),
+            atom(6, "TRP", 'A', "   3"),
+            atom(7, "SEC", ' ', "   1"),
+        ];
+        assert_eq!(
+            chain_sequences(&lines),
+            [
+                ("A".to_string(), "MGGW".to_string()),
+                ("B".to_string(), "X".to_string()),
+                (String::new(), "U".to_string()),
+            ]
+        );
+        assert_eq!(sequence_fields(&lines)[2], ("seq__".to_string(), "U".to_string()));
+    }
+
+    #[test]
+    fn sequences_from_seqres_records() {
+        let lines = [
+            "SEQRES   1 A    4  MET GLY",
+            "SEQRES   1 B    1  LYS",
+            "SEQRES   2 A    4  ALA PHE",
+        ];
+        assert_eq!(
+            seqres_sequences(&lines),
+            [("A".to_string(), "MGAF".to_string()), ("B".to_string(), "K".to_string())]
+        );
+        assert_eq!("seqres".parse(), Ok(SequenceSource::Seqres));
+        assert!("dna".parse::<SequenceSource>().is_err());
+    }
+
+    #[test]
+    fn fasta_records_per_chain() {
+        let dir = scratch_dir("sequence-fasta");
+        let path = dir.join("a.qv");
+        let entry = [
+            "SEQRES   1 A    3  MET GLY ALA".to_string(),
+            atom(1, "MET", 'A', "   1"),
+            atom(2, "LYS", 'B', "   1"),
+        ]
+        .join("\n");
+        fs::write(&path, format!("QV_TAG d1\n{}\n", entry)).unwrap();
+        let qv = Quiver::new(&path, "r").unwrap();
+
+        let mut out = Vec::new();
+        assert_eq!(write_fasta(&qv, &mut out, None, SequenceSource::Atom).unwrap(), 2);
+        assert_eq!(String::from_utf8(out).unwrap(), ">d1_A\nM\n>d1_B\nK\n");
+        let mut out = Vec::new();
+        let chains = ["A".to_string()];
+        write_fasta(&qv, &mut out, Some(&chains), SequenceSource::Seqres).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), ">d1_A\nMGA\n");
+    }
+
+    #[test]
+    fn motifs_are_found_in_stored_sequences() {
+        let dir = scratch_dir("sequence-motif");
+        let path = dir.join("a.qv");
+        fs::write(
+            &path,
+            "QV_TAG d1\nQV_META d1 seq_A=MKGKGA|seq_B=GKG\nATOM\nQV_TAG d2\nATOM\n",
+        )
+        .unwrap();
+        let qv = Quiver::new(&path, "r").unwrap();
+        let pattern = Regex::new("G.G").unwrap();
+        let hits = find_motif(&qv, &pattern, None).unwrap();
+        let found: Vec<_> = hits.iter().map(|h| (h.tag.as_str(), h.chain.as_str(), h.start, h.end)).collect();
+        assert_eq!(found, [("d1", "A", 3, 5), ("d1", "B", 1, 3)]);
+        assert_eq!(hits[0].matched, "GKG");
+        assert_eq!(find_motif(&qv, &pattern, Some("B")).unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,316 @@
+//! Merging `qvsplit` shards back into one archive.
+//!
+//! [`super::Quiver::split`] writes `<prefix>_manifest.tsv` next to the
+//! shards, one `shard<TAB>tag` line per entry in source order. [`unsplit`]
+//! concatenates shards in numeric order (`split_9.qv` before `split_10.qv`)
+//! and checks the merged tags against that manifest, so an incomplete or
+//! doubled set of shards is caught before anything is written.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+use super::compress::open_reader;
+use super::expr::ScoreKey;
+use super::storage::write_atomically;
+use super::QuiverError;
+
+/// Name of the manifest `split` writes for `prefix`.
+pub fn manifest_name(prefix: &str) -> String {
+    format!("{}_manifest.tsv", prefix)
+}
+
+/// Parse a shard size such as `4096`, `500K`, `200M` or `2G` (powers of
+/// 1024, an optional trailing `B` and any case) into bytes.
+pub fn parse_size(s: &str) -> Result<u64, String> {
+    let upper = s.trim().to_ascii_uppercase();
+    let digits = upper.strip_suffix('B').unwrap_or(&upper);
+    let (number, shift) = match digits.char_indices().last() {
+        Some((i, 'K')) => (&digits[..i], 10),
+        Some((i, 'M')) => (&digits[..i], 20),
+        Some((i, 'G')) => (&digits[..i], 30),
+        Some((i, 'T')) => (&digits[..i], 40),
+        _ => (digits, 0),
+    };
+    number
+        .trim()
+        .parse::<u64>()
+        .ok()
+        .and_then(|n| n.checked_mul(1 << shift))
+        .ok_or_else(|| format!("Invalid size '{}', expected bytes or a K, M, G or T suffix", s))
+}
+
+/// Value ranges of one score, for [`super::Quiver::split_by_score`].
+///
+/// Edges `70,90` give the buckets `lt70` (below 70), `70-90` (from 70 up to
+/// but not including 90) and `ge90`; entries without the score, or with a
+/// value that is not a number, go to `missing`. The score may also be an
+/// expression over the fields (see [`ScoreKey`]).
+#[derive(Debug, Clone)]
+pub struct ScoreBuckets {
+    pub key: ScoreKey,
+    edges: Vec<f64>,
+}
+
+impl ScoreBuckets {
+    /// Fails unless `edges` are finite and strictly increasing.
+    pub fn new(field: &str, edges: &[f64]) -> Result<Self, String> {
+        if field.is_empty() {
+            return Err("The score field to split by is empty".to_string());
+        }
+        if edges.is_empty() {
+            return Err("At least one bucket edge is needed".to_string());
+        }
+        if edges.iter().any(|e| !e.is_finite()) || edges.windows(2).any(|w| w[0] >= w[1]) {
+            return Err(format!("Bucket edges must be finite and increasing, not {:?}", edges));
+        }
+        Ok(ScoreBuckets {
+            key: ScoreKey::new(field),
+            edges: edges.to_vec(),
+        })
+    }
+
+    /// Name of the bucket `value` falls in.
+    pub fn bucket_of(&self, value: Option<f64>) -> String {
+        let value = match value {
+            Some(v) if !v.is_nan() => v,
+            _ => return "missing".to_string(),
+        };
+        let i = self.edges.partition_point(|&edge| edge <= value);
+        match i {
+            0 => format!("lt{}", self.edges[0]),
+            i if i == self.edges.len() => format!("ge{}", self.edges[i - 1]),
+            i => format!("{}-{}", self.edges[i - 1], self.edges[i]),
+        }
+    }
+}
+
+/// The shard number of `split_<n>.qv`, if the file stem ends in `_<n>`.
+pub fn shard_number(path: &Path) -> Option<u64> {
+    let stem = path.file_name()?.to_str()?.split('.').next()?;
+    stem.rsplit_once('_')?.1.parse().ok()
+}
+
+/// Expand `*` and `?` in the file name of `pattern` (not in its directory).
+/// A pattern without wildcards is returned as is.
+pub fn expand_pattern(pattern: &str) -> io::Result<Vec<PathBuf>> {
+    let path = Path::new(pattern);
+    let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    if !name.contains(['*', '?']) {
+        return Ok(vec![path.to_path_buf()]);
+    }
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    let pattern: Vec<char> = name.chars().collect();
+    let mut paths = Vec::new();
+    for entry in fs::read_dir(&dir)? {
+        let entry = entry?;
+        let candidate: Vec<char> = entry.file_name().to_string_lossy().chars().collect();
+        if wildcard_match(&pattern, &candidate) && entry.file_type()?.is_file() {
+            paths.push(dir.join(entry.file_name()));
+        }
+    }
+    Ok(paths)
+}
+
+/// Whether `name` matches `pattern`, where `*` matches any run of characters
+/// and `?` any one.
+pub(crate) fn wildcard_match(pattern: &[char], name: &[char]) -> bool {
+    match pattern.split_first() {
+        None => name.is_empty(),
+        Some(('*', rest)) => (0..=name.len()).any(|skip| wildcard_match(rest, &name[skip..])),
+        Some((&c, rest)) => name
+            .split_first()
+            .is_some_and(|(&n, name)| (c == '?' || c == n) && wildcard_match(rest, name)),
+    }
+}
+
+/// Sort shards by shard number; shards without one keep their order after
+/// the numbered ones.
+pub fn sort_shards(shards: &mut [PathBuf]) {
+    shards.sort_by_key(|path| shard_number(path).map_or((1, 0), |n| (0, n)));
+}
+
+/// The manifest for `shards`: `<prefix>_manifest.tsv` beside the first one.
+pub fn default_manifest(shards: &[PathBuf]) -> Option<PathBuf> {
+    let first = shards.first()?;
+    let name = first.file_name()?.to_str()?;
+    let (prefix, _) = name.split('.').next()?.rsplit_once('_')?;
+    Some(first.with_file_name(manifest_name(prefix)))
+}
+
+/// The tags listed in a manifest, in order.
+pub fn read_manifest(path: &Path) -> io::Result<Vec<String>> {
+    let mut tags = Vec::new();
+    for line in BufReader::new(fs::File::open(path)?).lines() {
+        let line = line?;
+        if let Some((_, tag)) = line.split_once('\t') {
+            tags.push(tag.to_string());
+        }
+    }
+    Ok(tags)
+}
+
+#[derive(Debug, Default)]
+pub struct UnsplitReport {
+    pub shards: usize,
+    pub entries: usize,
+    /// Whether the tags were checked against a manifest
+    pub verified: bool,
+}
+
+/// Concatenate `shards`, in the order given, into a new archive at `output`.
+///
+/// Only the first shard's `QV_HEADER` lines are kept, since `split` copies
+/// the source header into every shard. Fails without writing `output` if a
+/// tag appears twice or, given a `manifest`, if the merged tags differ from
+/// it.
+pub fn unsplit(shards: &[PathBuf], output: &Path, manifest: Option<&Path>) -> Result<UnsplitReport, QuiverError> {
+    if output.exists() {
+        return Err(QuiverError::InvalidOperation(format!(
+            "Output file {} already exists",
+            output.display()
+        )));
+    }
+    let expected = manifest.map(read_manifest).transpose()?;
+    let mut report = UnsplitReport {
+        shards: shards.len(),
+        verified: expected.is_some(),
+        ..Default::default()
+    };
+
+    write_atomically(output, |file| {
+        let mut out = io::BufWriter::new(file);
+        let mut seen: HashMap<String, PathBuf> = HashMap::new();
+        let mut written = 0u64;
+        for (i, shard) in shards.iter().enumerate() {
+            for line in BufReader::new(open_reader(shard)?).lines() {
+                let line = line?;
+                if line.starts_with("QV_HEADER") && i > 0 {
+                    continue;
+                }
+                if let Some(tag) = line.strip_prefix("QV_TAG ").and_then(|t| t.split_whitespace().next()) {
+                    if let Some(first) = seen.insert(tag.to_string(), shard.clone()) {
+                        return Err(invalid(format!(
+                            "tag {} is in both {} and {}",
+                            tag,
+                            first.display(),
+                            shard.display()
+                        )));
+                    }
+                    report.entries += 1;
+                }
+                writeln!(out, "{}", line)?;
+                written += line.len() as u64 + 1;
+            }
+        }
+        if let Some(expected) = &expected {
+            check_manifest(expected, &seen)?;
+        }
+        out.flush()?;
+        Ok(written)
+    })?;
+    Ok(report)
+}
+
+fn check_manifest(expected: &[String], seen: &HashMap<String, PathBuf>) -> io::Result<()> {
+    let listed: HashSet<&str> = expected.iter().map(String::as_str).collect();
+    if listed.len() != expected.len() {
+        return Err(invalid("the manifest lists a tag twice".to_string()));
+    }
+    let lost: Vec<&str> = expected
+        .iter()
+        .map(String::as_str)
+        .filter(|tag| !seen.contains_key(*tag))
+        .collect();
+    let mut extra: Vec<&str> = seen.keys().map(String::as_str).filter(|tag| !listed.contains(tag)).collect();
+    extra.sort_unstable();
+    if lost.is_empty() && extra.is_empty() {
+        return Ok(());
+    }
+    let sample = |tags: &[&str]| tags.iter().take(5).copied().collect::<Vec<_>>().join(", ");
+    let mut problems = Vec::new();
+    if !lost.is_empty() {
+        problems.push(format!("{} tag(s) missing ({})", lost.len(), sample(&lost)));
+    }
+    if !extra.is_empty() {
+        problems.push(format!("{} tag(s) not in the manifest ({})", extra.len(), sample(&extra)));
+    }
+    Err(invalid(format!("shards do not match the manifest: {}", problems.join("; "))))
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::testdata::scratch_dir;
+    use super::*;
+
+    #[test]
+    fn sizes() {
+        assert_eq!(parse_size("4096"), Ok(4096));
+        assert_eq!(parse_size("500k"), Ok(500 << 10));
+        assert_eq!(parse_size("200MB"), Ok(200 << 20));
+        assert_eq!(parse_size(" 2G "), Ok(2 << 30));
+        assert!(parse_size("2X").is_err());
+        assert!(parse_size("99999999999T").is_err());
+    }
+
+    #[test]
+    fn buckets() {
+        let buckets = ScoreBuckets::new("plddt", &[70.0, 90.0]).unwrap();
+        assert_eq!(buckets.bucket_of(Some(12.0)), "lt70");
+        assert_eq!(buckets.bucket_of(Some(70.0)), "70-90");
+        assert_eq!(buckets.bucket_of(Some(90.5)), "ge90");
+        assert_eq!(buckets.bucket_of(Some(f64::NAN)), "missing");
+        assert_eq!(buckets.bucket_of(None), "missing");
+        assert!(ScoreBuckets::new("plddt", &[90.0, 70.0]).is_err());
+        assert!(ScoreBuckets::new("plddt", &[]).is_err());
+        assert!(ScoreBuckets::new("", &[1.0]).is_err());
+    }
+
+    #[test]
+    fn shards_sort_numerically_and_expand() {
+        let dir = scratch_dir("shard-expand");
+        for name in ["split_10.qv", "split_9.qv", "split_x.qv", "other.txt"] {
+            fs::write(dir.join(name), "").unwrap();
+        }
+        let mut shards = expand_pattern(&dir.join("split_*.qv").to_string_lossy()).unwrap();
+        sort_shards(&mut shards);
+        let names: Vec<_> = shards.iter().map(|p| p.file_name().unwrap().to_str().unwrap()).collect();
+        assert_eq!(names, ["split_9.qv", "split_10.qv", "split_x.qv"]);
+        assert_eq!(default_manifest(&shards), Some(dir.join("split_manifest.tsv")));
+        assert_eq!(expand_pattern("plain.qv").unwrap(), [PathBuf::from("plain.qv")]);
+        assert!(wildcard_match(&['a', '?', '*'], &['a', 'b']));
+        assert!(!wildcard_match(&['a', '?'], &['a']));
+    }
+
+    #[test]
+    fn unsplit_checks_the_manifest() {
+        let dir = scratch_dir("shard-unsplit");
+        let shards = [dir.join("split_1.qv"), dir.join("split_2.qv")];
+        fs::write(&shards[0], "QV_HEADER k=v\nQV_TAG a\nATOM a\n").unwrap();
+        fs::write(&shards[1], "QV_HEADER k=v\nQV_TAG b\nATOM b\n").unwrap();
+        fs::write(dir.join("split_manifest.tsv"), "split_1.qv\ta\nsplit_2.qv\tb\n").unwrap();
+
+        let manifest = dir.join("split_manifest.tsv");
+        let report = unsplit(&shards, &dir.join("all.qv"), Some(&manifest)).unwrap();
+        assert_eq!((report.shards, report.entries, report.verified), (2, 2, true));
+        assert_eq!(
+            fs::read_to_string(dir.join("all.qv")).unwrap(),
+            "QV_HEADER k=v\nQV_TAG a\nATOM a\nQV_TAG b\nATOM b\n"
+        );
+
+        let err = unsplit(&shards[..1], &dir.join("part.qv"), Some(&manifest)).unwrap_err();
+        assert!(format!("{:?}", err).contains("1 tag(s) missing (b)"));
+        assert!(!dir.join("part.qv").exists());
+        let doubled = [shards[0].clone(), shards[0].clone()];
+        assert!(unsplit(&doubled, &dir.join("twice.qv"), None).is_err());
+        assert!(unsplit(&shards, &dir.join("all.qv"), None).is_err());
+    }
+}
@@ -0,0 +1,897 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, BufWriter, Cursor, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicU64, Ordering as AtomicOrdering};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use memmap2::Mmap;
+
+use super::compress::Compression;
+
+/// Byte-level backend for a Quiver archive.
+///
+/// All high-level operations (listing, reading, slicing, splitting) go
+/// through this trait, so a new backend only has to provide sequential reads
+/// from the start of the archive and appends at its end.
+///
+/// Backends must be `Send + Sync` so a `Quiver` handle can be shared between
+/// threads; the crate keeps no global state of its own.
+pub trait Storage: Send + Sync {
+    /// Whether the backing data exists yet.
+    fn exists(&self) -> bool;
+
+    /// Current size in bytes.
+    fn len(&self) -> io::Result<u64>;
+
+    /// Whether the archive holds no bytes.
+    fn is_empty(&self) -> io::Result<bool> {
+        Ok(self.len()? == 0)
+    }
+
+    /// A reader positioned at the first byte of the archive.
+    fn reader(&self) -> io::Result<Box<dyn Read + '_>>;
+
+    /// A reader positioned `offset` bytes into the archive.
+    ///
+    /// The default skips forward from the start; backends with random
+    /// access should override it.
+    fn reader_at(&self, offset: u64) -> io::Result<Box<dyn Read + '_>> {
+        let mut reader = self.reader()?;
+        io::copy(&mut reader.by_ref().take(offset), &mut io::sink())?;
+        Ok(reader)
+    }
+
+    /// The `len` bytes at `offset`, or fewer at the end of the archive.
+    ///
+    /// Backends where every read costs a request, such as
+    /// [`super::remote::RemoteFile`], fetch exactly this range.
+    fn read_range(&self, offset: u64, len: u64) -> io::Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+        self.reader_at(offset)?.take(len).read_to_end(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    /// Append raw bytes to the end of the archive.
+    fn append(&mut self, data: &[u8]) -> io::Result<()>;
+
+    /// Push any appends the backend has buffered to the backing data.
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Complete the archive once no more appends will follow.
+    ///
+    /// Backends that only publish the archive at the end, such as
+    /// [`super::s3::MultipartWriter`], do so here; the rest just flush.
+    fn finish(&mut self) -> io::Result<()> {
+        self.flush()
+    }
+
+    /// Atomically replace the whole archive with the bytes read from `data`.
+    ///
+    /// Readers either see the old archive or the new one, never a mix.
+    fn replace(&mut self, data: &mut dyn Read) -> io::Result<()>;
+
+    /// A [`Staged`] file to write the new contents of the archive into, so a
+    /// rewrite can read the old archive while writing the new one without
+    /// spooling it first. `None` (the default) for backends that take the new
+    /// contents through [`Storage::replace`] only.
+    fn stage(&self) -> io::Result<Option<Staged>> {
+        Ok(None)
+    }
+
+    /// Swap in the contents written to a [`Staged`] file from
+    /// [`Storage::stage`].
+    ///
+    /// The default feeds the staged text to [`Storage::replace`], for
+    /// backends that encode it.
+    fn commit(&mut self, mut staged: Staged) -> io::Result<()> {
+        let mut text = staged.reopen()?;
+        self.replace(&mut text)
+    }
+
+    /// Bytes the backing data spends on `len` bytes at `offset`.
+    ///
+    /// The default is for backends that store the text as is.
+    fn stored_len(&self, _offset: u64, len: u64) -> io::Result<StoredLen> {
+        Ok(StoredLen { bytes: len, exact: true })
+    }
+
+    /// A human-readable name for error messages, such as the file path.
+    fn name(&self) -> Option<String> {
+        None
+    }
+}
+
+/// Stored size of a byte range, see [`Storage::stored_len`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StoredLen {
+    pub bytes: u64,
+    /// False when the backend can only estimate the size
+    pub exact: bool,
+}
+
+/// An archive stored as a plain file on the local filesystem.
+pub struct LocalFile {
+    path: PathBuf,
+    pinned: Option<File>,
+    /// Handle kept open for appends, see [`LocalFile::appending`]
+    appender: Option<File>,
+    /// Buffer appends in `writer`, see [`LocalFile::buffered`]
+    buffered: bool,
+    writer: Option<BufWriter<File>>,
+}
+
+impl LocalFile {
+    /// Reads and appends reopen `path` every time.
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            pinned: None,
+            appender: None,
+            buffered: false,
+            writer: None,
+        }
+    }
+
+    /// Open `path` on the first append and buffer every later one until
+    /// [`Storage::flush`] or drop.
+    ///
+    /// Meant for write-only handles: reads through the path do not see
+    /// appends that are still buffered.
+    pub fn buffered<P: AsRef<Path>>(path: P) -> Self {
+        Self {
+            buffered: true,
+            ..Self::new(path)
+        }
+    }
+
+    /// Keep a handle on the file as it exists now.
+    ///
+    /// Reads keep going through this handle even if the path is later
+    /// replaced by an atomic rewrite.
+    pub fn pinned<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let pinned = if path.exists() {
+            Some(File::open(&path)?)
+        } else {
+            None
+        };
+        Ok(Self {
+            pinned,
+            ..Self::new(path)
+        })
+    }
+
+    /// Open an existing file and keep the handle for all later appends.
+    pub fn appending<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let file = OpenOptions::new().read(true).append(true).open(&path)?;
+        Ok(Self {
+            appender: Some(file),
+            ..Self::new(path)
+        })
+    }
+
+    /// Like [`LocalFile::appending`], but creates the file if it is missing.
+    pub fn read_write<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(&path)?;
+        Ok(Self {
+            appender: Some(file),
+            ..Self::new(path)
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Point the handles this backend keeps at the file now at `path`.
+    fn reopen(&mut self) -> io::Result<()> {
+        if self.pinned.is_some() {
+            self.pinned = Some(File::open(&self.path)?);
+        }
+        if self.appender.is_some() {
+            self.appender = Some(OpenOptions::new().read(true).append(true).open(&self.path)?);
+        }
+        Ok(())
+    }
+}
+
+impl Storage for LocalFile {
+    fn exists(&self) -> bool {
+        self.pinned.is_some() || self.appender.is_some() || self.writer.is_some() || self.path.exists()
+    }
+
+    /// Counts appends that are still buffered.
+    fn len(&self) -> io::Result<u64> {
+        if let Some(writer) = &self.writer {
+            return Ok(writer.get_ref().metadata()?.len() + writer.buffer().len() as u64);
+        }
+        match self.pinned.as_ref().or(self.appender.as_ref()) {
+            Some(file) => Ok(file.metadata()?.len()),
+            None if !self.path.exists() => Ok(0),
+            None => Ok(fs::metadata(&self.path)?.len()),
+        }
+    }
+
+    fn reader(&self) -> io::Result<Box<dyn Read + '_>> {
+        self.reader_at(0)
+    }
+
+    fn reader_at(&self, offset: u64) -> io::Result<Box<dyn Read + '_>> {
+        match self.pinned.as_ref().or(self.appender.as_ref()) {
+            Some(file) => Ok(Box::new(PositionalReader { file, pos: offset })),
+            None => {
+                let mut file = File::open(&self.path)?;
+                file.seek(SeekFrom::Start(offset))?;
+                Ok(Box::new(file))
+            }
+        }
+    }
+
+    fn append(&mut self, data: &[u8]) -> io::Result<()> {
+        if let Some(file) = self.appender.as_mut() {
+            return file.write_all(data);
+        }
+        if self.buffered {
+            let writer = match self.writer.as_mut() {
+                Some(writer) => writer,
+                None => {
+                    let file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+                    self.writer.insert(BufWriter::new(file))
+                }
+            };
+            return writer.write_all(data);
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        file.write_all(data)
+    }
+
+    /// Writes out appends buffered by a [`LocalFile::buffered`] handle.
+    fn flush(&mut self) -> io::Result<()> {
+        match self.writer.as_mut() {
+            Some(writer) => writer.flush(),
+            None => Ok(()),
+        }
+    }
+
+    /// Writes a sibling temporary file and renames it over the archive, then
+    /// reopens any handles this backend keeps.
+    fn replace(&mut self, data: &mut dyn Read) -> io::Result<()> {
+        // The buffered writer reopens the new file on its next append
+        if let Some(mut writer) = self.writer.take() {
+            writer.flush()?;
+        }
+        write_atomically(&self.path, |file| io::copy(data, file))?;
+        self.reopen()
+    }
+
+    fn stage(&self) -> io::Result<Option<Staged>> {
+        Staged::new(&self.path).map(Some)
+    }
+
+    /// Renames the staged file over the archive, then reopens any handles
+    /// this backend keeps.
+    fn commit(&mut self, staged: Staged) -> io::Result<()> {
+        if let Some(mut writer) = self.writer.take() {
+            writer.flush()?;
+        }
+        staged.persist()?;
+        self.reopen()
+    }
+
+    fn name(&self) -> Option<String> {
+        Some(self.path.display().to_string())
+    }
+}
+
+/// A read-only archive on the local filesystem, memory-mapped once when it
+/// is opened.
+///
+/// Reads copy straight out of the mapping, without a system call per read,
+/// which suits archives read at random many times. The mapping shows the
+/// file as it was when opened: rewrites by this crate rename a new file
+/// over the path and leave the mapping alone, but a file truncated in place
+/// by another program can make reads fault.
+pub struct MappedFile {
+    path: PathBuf,
+    /// `None` for an empty file, which cannot be mapped
+    map: Option<Mmap>,
+}
+
+impl MappedFile {
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let file = File::open(&path)?;
+        let map = if file.metadata()?.len() == 0 {
+            None
+        } else {
+            // SAFETY: the mapping is only read, and the archive tools never
+            // modify a file in place; see the type's docs
+            Some(unsafe { Mmap::map(&file)? })
+        };
+        Ok(Self { path, map })
+    }
+
+    fn bytes(&self) -> &[u8] {
+        self.map.as_deref().unwrap_or_default()
+    }
+}
+
+impl Storage for MappedFile {
+    fn exists(&self) -> bool {
+        true
+    }
+
+    fn len(&self) -> io::Result<u64> {
+        Ok(self.bytes().len() as u64)
+    }
+
+    fn reader(&self) -> io::Result<Box<dyn Read + '_>> {
+        Ok(Box::new(self.bytes()))
+    }
+
+    fn reader_at(&self, offset: u64) -> io::Result<Box<dyn Read + '_>> {
+        let bytes = self.bytes();
+        Ok(Box::new(&bytes[(offset.min(bytes.len() as u64)) as usize..]))
+    }
+
+    fn read_range(&self, offset: u64, len: u64) -> io::Result<Vec<u8>> {
+        let bytes = self.bytes();
+        let start = offset.min(bytes.len() as u64) as usize;
+        let end = offset.saturating_add(len).min(bytes.len() as u64) as usize;
+        Ok(bytes[start..end].to_vec())
+    }
+
+    fn append(&mut self, _data: &[u8]) -> io::Result<()> {
+        Err(mapped_read_only())
+    }
+
+    fn replace(&mut self, _data: &mut dyn Read) -> io::Result<()> {
+        Err(mapped_read_only())
+    }
+
+    fn name(&self) -> Option<String> {
+        Some(self.path.display().to_string())
+    }
+}
+
+fn mapped_read_only() -> io::Error {
+    io::Error::new(io::ErrorKind::Unsupported, "memory-mapped archives are read-only")
+}
+
+/// New contents of a file, written next to it and renamed over it by
+/// [`Staged::persist`]; see [`Storage::stage`]. Dropped before that, the
+/// staged file is removed.
+pub struct Staged {
+    path: PathBuf,
+    tmp: PathBuf,
+    file: BufWriter<File>,
+    persisted: bool,
+}
+
+impl Staged {
+    /// Stage a replacement for the file at `path`.
+    pub fn new(path: &Path) -> io::Result<Self> {
+        let (tmp, file) = sibling_temp(path, "new")?;
+        let file = BufWriter::new(file);
+        Ok(Self {
+            path: path.to_path_buf(),
+            tmp,
+            file,
+            persisted: false,
+        })
+    }
+
+    /// Sync the staged file to disk and rename it over the original.
+    pub fn persist(mut self) -> io::Result<()> {
+        self.file.flush()?;
+        self.file.get_ref().sync_all()?;
+        fs::rename(&self.tmp, &self.path)?;
+        self.persisted = true;
+        sync_parent(&self.path)
+    }
+
+    /// The staged bytes written so far, for backends that encode them.
+    pub fn reopen(&mut self) -> io::Result<File> {
+        self.file.flush()?;
+        File::open(&self.tmp)
+    }
+}
+
+impl Write for Staged {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.file.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+impl Drop for Staged {
+    fn drop(&mut self) {
+        if !self.persisted {
+            let _ = fs::remove_file(&self.tmp);
+        }
+    }
+}
+
+/// Sync the directory holding `path`, so a rename into it survives a crash.
+#[cfg(unix)]
+fn sync_parent(path: &Path) -> io::Result<()> {
+    match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => File::open(dir)?.sync_all(),
+        _ => File::open(".")?.sync_all(),
+    }
+}
+
+/// Directories cannot be opened as files here; the rename is as durable as
+/// the platform makes it.
+#[cfg(not(unix))]
+fn sync_parent(_path: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+/// Numbers the temporary files of one process, so threads writing next to
+/// the same archive never share one.
+static TEMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Create `.<name>.<suffix><pid>.<n>` next to `path`. The file must not
+/// exist yet: a leftover from an earlier process with the same pid is
+/// skipped, never truncated.
+fn sibling_temp(path: &Path, suffix: &str) -> io::Result<(PathBuf, File)> {
+    let file_name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    loop {
+        let n = TEMP_COUNTER.fetch_add(1, AtomicOrdering::Relaxed);
+        let tmp = path.with_file_name(format!(".{}.{}{}.{}", file_name, suffix, process::id(), n));
+        match OpenOptions::new().write(true).create_new(true).open(&tmp) {
+            Ok(file) => return Ok((tmp, file)),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Write `path` through a sibling temporary file that is renamed over it.
+pub(crate) fn write_atomically<F>(path: &Path, write: F) -> io::Result<u64>
+where
+    F: FnOnce(&mut File) -> io::Result<u64>,
+{
+    let (tmp, file) = sibling_temp(path, "tmp")?;
+    let written = (|| {
+        let mut file = file;
+        let n = write(&mut file)?;
+        file.sync_all()?;
+        fs::rename(&tmp, path)?;
+        sync_parent(path)?;
+        Ok(n)
+    })();
+    if written.is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+    written
+}
+
+/// A gzip- or zstd-compressed archive on the local filesystem.
+///
+/// Reads decompress on the fly and every append adds one compressed
+/// member (gzip) or frame (zstd), so the file stays readable by the
+/// standard tools. Offsets and lengths refer to the decompressed bytes.
+pub struct CompressedFile {
+    path: PathBuf,
+    codec: Compression,
+    pinned: Option<File>,
+    /// Decompressed length, or `UNKNOWN_LEN` until first needed
+    len: AtomicU64,
+}
+
+const UNKNOWN_LEN: u64 = u64::MAX;
+
+impl CompressedFile {
+    /// With `pin`, reads keep going through a handle opened now, like
+    /// [`LocalFile::pinned`].
+    pub fn new<P: AsRef<Path>>(path: P, codec: Compression, pin: bool) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let pinned = if pin && path.exists() {
+            Some(File::open(&path)?)
+        } else {
+            None
+        };
+        Ok(Self {
+            path,
+            codec,
+            pinned,
+            len: AtomicU64::new(UNKNOWN_LEN),
+        })
+    }
+
+    pub fn codec(&self) -> Compression {
+        self.codec
+    }
+}
+
+impl Storage for CompressedFile {
+    fn exists(&self) -> bool {
+        self.pinned.is_some() || self.path.exists()
+    }
+
+    /// Decompresses the archive once, then tracks appends.
+    fn len(&self) -> io::Result<u64> {
+        let len = self.len.load(AtomicOrdering::Acquire);
+        if len != UNKNOWN_LEN {
+            return Ok(len);
+        }
+        let len = if self.exists() {
+            io::copy(&mut self.reader()?, &mut io::sink())?
+        } else {
+            0
+        };
+        self.len.store(len, AtomicOrdering::Release);
+        Ok(len)
+    }
+
+    fn reader(&self) -> io::Result<Box<dyn Read + '_>> {
+        let raw: Box<dyn Read + '_> = match &self.pinned {
+            Some(file) => Box::new(PositionalReader { file, pos: 0 }),
+            None if !self.path.exists() => return Ok(Box::new(io::empty())),
+            None => Box::new(File::open(&self.path)?),
+        };
+        let mut raw = BufReader::new(raw);
+        // An empty file is an empty archive, not a truncated stream
+        if raw.fill_buf()?.is_empty() {
+            return Ok(Box::new(io::empty()));
+        }
+        self.codec.decoder(raw)
+    }
+
+    fn append(&mut self, mut data: &[u8]) -> io::Result<()> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        let mut writer = BufWriter::new(file);
+        let n = self.codec.encode(&mut data, &mut writer)?;
+        writer.flush()?;
+        let len = self.len.load(AtomicOrdering::Acquire);
+        if len != UNKNOWN_LEN {
+            self.len.store(len + n, AtomicOrdering::Release);
+        }
+        Ok(())
+    }
+
+    fn stage(&self) -> io::Result<Option<Staged>> {
+        Staged::new(&self.path).map(Some)
+    }
+
+    fn replace(&mut self, data: &mut dyn Read) -> io::Result<()> {
+        let codec = self.codec;
+        let n = write_atomically(&self.path, |file| codec.encode(data, file))?;
+        self.len.store(n, AtomicOrdering::Release);
+        if self.pinned.is_some() {
+            self.pinned = Some(File::open(&self.path)?);
+        }
+        Ok(())
+    }
+
+    /// Members do not line up with entries, so this is the size of the range
+    /// compressed on its own.
+    fn stored_len(&self, offset: u64, len: u64) -> io::Result<StoredLen> {
+        let mut counter = CountingWriter(0);
+        self.codec.encode(&mut self.reader_at(offset)?.take(len), &mut counter)?;
+        Ok(StoredLen { bytes: counter.0, exact: false })
+    }
+
+    fn name(&self) -> Option<String> {
+        Some(self.path.display().to_string())
+    }
+}
+
+/// Discards its input, counting the bytes.
+struct CountingWriter(u64);
+
+impl Write for CountingWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0 += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Reads a shared file handle with positioned reads (`pread`), so several
+/// readers can use the same handle at once without sharing a file offset.
+pub(crate) struct PositionalReader<'a> {
+    pub(crate) file: &'a File,
+    pub(crate) pos: u64,
+}
+
+impl Read for PositionalReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        #[cfg(unix)]
+        let n = std::os::unix::fs::FileExt::read_at(self.file, buf, self.pos)?;
+        #[cfg(windows)]
+        let n = std::os::windows::fs::FileExt::seek_read(self.file, buf, self.pos)?;
+        self.pos += n as u64;
+        Ok(n)
+    }
+}
+
+/// An archive held entirely in memory, e.g. for building small archives
+/// before sending them elsewhere.
+///
+/// Clones share the same buffer, so a caller can keep a clone to inspect the
+/// bytes written through a `Quiver` that owns the other one.
+#[derive(Clone, Default)]
+pub struct MemoryStorage {
+    data: Arc<Mutex<Vec<u8>>>,
+}
+
+impl MemoryStorage {
+    pub fn new(data: Vec<u8>) -> Self {
+        Self {
+            data: Arc::new(Mutex::new(data)),
+        }
+    }
+
+    /// A copy of the current archive bytes.
+    pub fn to_vec(&self) -> Vec<u8> {
+        self.buffer().clone()
+    }
+
+    fn buffer(&self) -> MutexGuard<'_, Vec<u8>> {
+        self.data.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Storage for MemoryStorage {
+    fn exists(&self) -> bool {
+        true
+    }
+
+    fn len(&self) -> io::Result<u64> {
+        Ok(self.buffer().len() as u64)
+    }
+
+    fn reader(&self) -> io::Result<Box<dyn Read + '_>> {
+        Ok(Box::new(Cursor::new(self.to_vec())))
+    }
+
+    fn reader_at(&self, offset: u64) -> io::Result<Box<dyn Read + '_>> {
+        let mut cursor = Cursor::new(self.to_vec());
+        cursor.set_position(offset);
+        Ok(Box::new(cursor))
+    }
+
+    fn append(&mut self, data: &[u8]) -> io::Result<()> {
+        self.buffer().extend_from_slice(data);
+        Ok(())
+    }
+
+    fn replace(&mut self, data: &mut dyn Read) -> io::Result<()> {
+        let mut bytes = Vec::new();
+        data.read_to_end(&mut bytes)?;
+        *self.buffer() = bytes;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::remote::{RangeClient, RemoteFile};
+    use super::super::testdata::scratch_dir;
+    use super::*;
+    use std::thread;
+
+    const FIRST: &[u8] = b"QV_TAG a\nATOM 1\n";
+    const SECOND: &[u8] = b"QV_TAG b\nATOM 2\n";
+
+    /// Append two chunks, then check every read path sees them in order.
+    fn round_trip(storage: &mut dyn Storage) {
+        storage.append(FIRST).unwrap();
+        storage.append(SECOND).unwrap();
+        storage.flush().unwrap();
+        check_contents(storage, &[FIRST, SECOND].concat());
+    }
+
+    fn check_contents(storage: &dyn Storage, expected: &[u8]) {
+        assert!(storage.exists());
+        assert_eq!(storage.len().unwrap(), expected.len() as u64);
+        let mut all = Vec::new();
+        storage.reader().unwrap().read_to_end(&mut all).unwrap();
+        assert_eq!(all, expected);
+        let mut tail = Vec::new();
+        storage.reader_at(FIRST.len() as u64).unwrap().read_to_end(&mut tail).unwrap();
+        assert_eq!(tail, &expected[FIRST.len()..]);
+        assert_eq!(storage.read_range(3, 5).unwrap(), &expected[3..8]);
+        assert_eq!(storage.read_range(expected.len() as u64 - 2, 10).unwrap(), &expected[expected.len() - 2..]);
+    }
+
+    fn check_replace(storage: &mut dyn Storage) {
+        storage.replace(&mut &SECOND[..]).unwrap();
+        let mut all = Vec::new();
+        storage.reader().unwrap().read_to_end(&mut all).unwrap();
+        assert_eq!(all, SECOND);
+        assert_eq!(storage.len().unwrap(), SECOND.len() as u64);
+    }
+
+    #[test]
+    fn local_file_round_trip() {
+        let dir = scratch_dir("storage-local");
+        for (name, mut storage) in [
+            ("plain.qv", LocalFile::new(dir.join("plain.qv"))),
+            ("buffered.qv", LocalFile::buffered(dir.join("buffered.qv"))),
+            ("rw.qv", LocalFile::read_write(dir.join("rw.qv")).unwrap()),
+        ] {
+            round_trip(&mut storage);
+            check_replace(&mut storage);
+            assert_eq!(fs::read(dir.join(name)).unwrap(), SECOND);
+        }
+    }
+
+    #[test]
+    fn pinned_file_survives_replace_by_path() {
+        let dir = scratch_dir("storage-pinned");
+        let path = dir.join("a.qv");
+        fs::write(&path, FIRST).unwrap();
+        let pinned = LocalFile::pinned(&path).unwrap();
+        LocalFile::new(&path).replace(&mut &SECOND[..]).unwrap();
+        let mut all = Vec::new();
+        pinned.reader().unwrap().read_to_end(&mut all).unwrap();
+        assert_eq!(all, FIRST);
+    }
+
+    #[test]
+    fn write_atomically_leaves_no_temporary_file() {
+        let dir = scratch_dir("storage-atomic");
+        let path = dir.join("a.qv");
+        assert_eq!(write_atomically(&path, |file| file.write_all(FIRST).map(|_| 5)).unwrap(), 5);
+        assert!(write_atomically(&path, |_| Err(io::Error::other("failed"))).is_err());
+        assert_eq!(fs::read(&path).unwrap(), FIRST);
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn concurrent_atomic_writes_use_their_own_temporary_files() {
+        let dir = scratch_dir("storage-concurrent");
+        let path = dir.join("a.qv");
+        thread::scope(|s| {
+            for data in [FIRST, SECOND] {
+                let path = &path;
+                s.spawn(move || {
+                    for _ in 0..20 {
+                        write_atomically(path, |file| file.write_all(data).map(|_| 0)).unwrap();
+                    }
+                });
+            }
+        });
+        let contents = fs::read(&path).unwrap();
+        assert!(contents == FIRST || contents == SECOND);
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+    }
+
+    /// Stage `FIRST` while still reading the old contents, then commit it.
+    fn check_staged(storage: &mut dyn Storage, dir: &Path) {
+        let mut staged = storage.stage().unwrap().expect("a local backend stages");
+        let mut old = Vec::new();
+        storage.reader().unwrap().read_to_end(&mut old).unwrap();
+        staged.write_all(&old[..FIRST.len()]).unwrap();
+        storage.commit(staged).unwrap();
+        let mut all = Vec::new();
+        storage.reader().unwrap().read_to_end(&mut all).unwrap();
+        assert_eq!(all, FIRST);
+        assert_eq!(fs::read_dir(dir).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn staged_replacement_is_renamed_over_the_archive() {
+        let dir = scratch_dir("storage-staged");
+        let path = dir.join("a.qv");
+        let mut storage = LocalFile::read_write(&path).unwrap();
+        round_trip(&mut storage);
+        check_staged(&mut storage, &dir);
+        // The handle kept by read_write appends to the new file
+        storage.append(SECOND).unwrap();
+        assert_eq!(fs::read(&path).unwrap(), [FIRST, SECOND].concat());
+
+        drop(storage.stage().unwrap());
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+        assert!(MemoryStorage::default().stage().unwrap().is_none());
+    }
+
+    #[test]
+    fn mapped_file_round_trip() {
+        let dir = scratch_dir("storage-mapped");
+        let path = dir.join("a.qv");
+        fs::write(&path, [FIRST, SECOND].concat()).unwrap();
+        let mut mapped = MappedFile::open(&path).unwrap();
+        check_contents(&mapped, &[FIRST, SECOND].concat());
+        assert_eq!(mapped.append(FIRST).unwrap_err().kind(), io::ErrorKind::Unsupported);
+        assert_eq!(mapped.replace(&mut &FIRST[..]).unwrap_err().kind(), io::ErrorKind::Unsupported);
+
+        fs::write(dir.join("empty.qv"), b"").unwrap();
+        let empty = MappedFile::open(dir.join("empty.qv")).unwrap();
+        assert_eq!(empty.len().unwrap(), 0);
+        assert!(empty.read_range(0, 10).unwrap().is_empty());
+    }
+
+    #[test]
+    fn memory_storage_round_trip() {
+        let mut storage = MemoryStorage::default();
+        let view = storage.clone();
+        round_trip(&mut storage);
+        assert_eq!(view.to_vec(), [FIRST, SECOND].concat());
+        check_replace(&mut storage);
+        assert_eq!(view.to_vec(), SECOND);
+    }
+
+    #[test]
+    fn compressed_file_round_trip() {
+        let dir = scratch_dir("storage-compressed");
+        for (name, codec) in [("a.qv.gz", Compression::Gzip), ("a.qv.zst", Compression::Zstd)] {
+            let path = dir.join(name);
+            let mut storage = CompressedFile::new(&path, codec, false).unwrap();
+            round_trip(&mut storage);
+            // One member or frame per append, readable from a fresh handle
+            assert_eq!(Compression::detect(&path).unwrap(), Some(codec));
+            check_contents(&CompressedFile::new(&path, codec, true).unwrap(), &[FIRST, SECOND].concat());
+            check_replace(&mut storage);
+        }
+        for (name, codec) in [("staged.qv.gz", Compression::Gzip), ("staged.qv.zst", Compression::Zstd)] {
+            let dir = scratch_dir(&format!("storage-compressed-{}", name));
+            let mut storage = CompressedFile::new(dir.join(name), codec, false).unwrap();
+            round_trip(&mut storage);
+            check_staged(&mut storage, &dir);
+            assert_eq!(Compression::detect(dir.join(name)).unwrap(), Some(codec));
+        }
+    }
+
+    /// Serves one in-memory object and counts the requests.
+    struct MemoryClient {
+        data: Vec<u8>,
+        requests: AtomicU64,
+    }
+
+    impl RangeClient for MemoryClient {
+        fn size(&self, url: &str) -> io::Result<Option<u64>> {
+            Ok((url == "http://host/a.qv").then_some(self.data.len() as u64))
+        }
+
+        fn get_range(&self, _url: &str, offset: u64, len: u64) -> io::Result<Vec<u8>> {
+            self.requests.fetch_add(1, AtomicOrdering::Relaxed);
+            let start = (offset as usize).min(self.data.len());
+            let end = (offset + len).min(self.data.len() as u64) as usize;
+            Ok(self.data[start..end].to_vec())
+        }
+    }
+
+    #[test]
+    fn remote_file_round_trip() {
+        let client = Arc::new(MemoryClient {
+            data: [FIRST, SECOND].concat(),
+            requests: AtomicU64::new(0),
+        });
+        assert!(RemoteFile::open(client.clone(), "http://host/missing.qv").is_err());
+        let mut remote = RemoteFile::open(client.clone(), "http://host/a.qv").unwrap();
+        check_contents(&remote, &[FIRST, SECOND].concat());
+        client.requests.store(0, AtomicOrdering::Relaxed);
+        remote.read_range(2, 4).unwrap();
+        assert_eq!(client.requests.load(AtomicOrdering::Relaxed), 1);
+        assert_eq!(remote.append(FIRST).unwrap_err().kind(), io::ErrorKind::Unsupported);
+    }
+}
@@ -0,0 +1,110 @@
+//! Optimal superposition of two sets of paired coordinates.
+//!
+//! [`kabsch_rmsd`] gives the RMSD after the rotation and translation that
+//! best fit one set onto the other (the Kabsch problem). The rotation is
+//! found through Horn's quaternion form of the same problem, which needs
+//! only the largest eigenvalue of a symmetric 4x4 matrix and never returns
+//! a reflection.
+
+fn centroid(coords: &[[f64; 3]]) -> [f64; 3] {
+    let mut sum = [0.0; 3];
+    for c in coords {
+        for k in 0..3 {
+            sum[k] += c[k];
+        }
+    }
+    sum.map(|s| s / coords.len() as f64)
+}
+
+/// Largest eigenvalue of a symmetric 4x4 matrix (cyclic Jacobi rotations).
+fn largest_eigenvalue(mut m: [[f64; 4]; 4]) -> f64 {
+    for _ in 0..64 {
+        let off: f64 = (0..4)
+            .flat_map(|p| (p + 1..4).map(move |q| (p, q)))
+            .map(|(p, q)| m[p][q] * m[p][q])
+            .sum();
+        if off < 1e-22 {
+            break;
+        }
+        for p in 0..4 {
+            for q in p + 1..4 {
+                if m[p][q].abs() < 1e-30 {
+                    continue;
+                }
+                let theta = (m[q][q] - m[p][p]) / (2.0 * m[p][q]);
+                let t = theta.signum() / (theta.abs() + (theta * theta + 1.0).sqrt());
+                let c = 1.0 / (t * t + 1.0).sqrt();
+                let s = t * c;
+                for row in m.iter_mut() {
+                    let (rp, rq) = (row[p], row[q]);
+                    row[p] = c * rp - s * rq;
+                    row[q] = s * rp + c * rq;
+                }
+                let (mp, mq) = (m[p], m[q]);
+                m[p] = std::array::from_fn(|k| c * mp[k] - s * mq[k]);
+                m[q] = std::array::from_fn(|k| s * mp[k] + c * mq[k]);
+            }
+        }
+    }
+    (0..4).map(|i| m[i][i]).fold(f64::NEG_INFINITY, f64::max)
+}
+
+/// RMSD between `a` and `b`, paired by index, after optimally superposing
+/// `b` onto `a`. `None` if the sets differ in length or are empty.
+pub fn kabsch_rmsd(a: &[[f64; 3]], b: &[[f64; 3]]) -> Option<f64> {
+    if a.len() != b.len() || a.is_empty() {
+        return None;
+    }
+    let (ca, cb) = (centroid(a), centroid(b));
+    let mut s = [[0.0; 3]; 3];
+    let mut inner = 0.0;
+    for (pa, pb) in a.iter().zip(b) {
+        let x: [f64; 3] = std::array::from_fn(|k| pa[k] - ca[k]);
+        let y: [f64; 3] = std::array::from_fn(|k| pb[k] - cb[k]);
+        for i in 0..3 {
+            inner += x[i] * x[i] + y[i] * y[i];
+            for j in 0..3 {
+                s[i][j] += y[i] * x[j];
+            }
+        }
+    }
+    let [[sxx, sxy, sxz], [syx, syy, syz], [szx, szy, szz]] = s;
+    let horn = [
+        [sxx + syy + szz, syz - szy, szx - sxz, sxy - syx],
+        [syz - szy, sxx - syy - szz, sxy + syx, szx + sxz],
+        [szx - sxz, sxy + syx, -sxx + syy - szz, syz + szy],
+        [sxy - syx, szx + sxz, syz + szy, -sxx - syy + szz],
+    ];
+    let lambda = largest_eigenvalue(horn);
+    Some(((inner - 2.0 * lambda) / a.len() as f64).max(0.0).sqrt())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const POINTS: [[f64; 3]; 4] = [[0.0, 0.0, 0.0], [1.5, 0.0, 0.0], [0.0, 2.0, 0.0], [0.0, 0.0, 3.0]];
+
+    #[test]
+    fn rigid_motions_do_not_count() {
+        // 90 degrees about z, then shifted
+        let moved: Vec<[f64; 3]> = POINTS.iter().map(|p| [-p[1] + 4.0, p[0] - 1.0, p[2] + 2.5]).collect();
+        assert!(kabsch_rmsd(&POINTS, &moved).unwrap() < 1e-9);
+    }
+
+    #[test]
+    fn reflections_are_not_allowed() {
+        let mirrored: Vec<[f64; 3]> = POINTS.iter().map(|p| [p[0], p[1], -p[2]]).collect();
+        assert!(kabsch_rmsd(&POINTS, &mirrored).unwrap() > 0.1);
+    }
+
+    #[test]
+    fn residual_after_fitting() {
+        // Stretching a pair apart by 2 leaves 1 at each end after centring
+        let a = [[0.0, 0.0, 0.0], [2.0, 0.0, 0.0]];
+        let b = [[0.0, 0.0, 0.0], [4.0, 0.0, 0.0]];
+        assert!((kabsch_rmsd(&a, &b).unwrap() - 1.0).abs() < 1e-9);
+        assert_eq!(kabsch_rmsd(&a, &b[..1]), None);
+        assert_eq!(kabsch_rmsd(&[], &[]), None);
+    }
+}
@@ -0,0 +1,370 @@
+//! Sidecar tag index, `<archive>.idx`, so a huge archive is scanned once
+//! rather than on every open.
+//!
+//! The first line, `QV_INDEX<TAB>size<TAB>mtime_ns`, records the archive the
+//! index was built from; every other line is `tag<TAB>offset<TAB>length`, as
+//! in the index [`super::s3`] uploads. Once the archive changes the index is
+//! stale and [`TagIndex::load_or_build`] rebuilds it.
+//!
+//! Builds are serialized by a `<archive>.idx.lock` file created exclusively,
+//! so a second process waits for the first and then reads its index. The
+//! builder touches the lock as it goes, and a lock untouched for
+//! [`STALE_LOCK`] (left by a killed build) is broken. The index is written
+//! through a temporary file renamed into place, so readers never see half
+//! of one.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use super::compress::Compression;
+use super::format_v2;
+use super::storage::write_atomically;
+use super::{Location, QuiverError};
+
+/// How long a build may leave its lock untouched before others break it.
+pub const STALE_LOCK: Duration = Duration::from_secs(60);
+/// Bytes scanned between progress reports (and lock touches).
+const PROGRESS_EVERY: u64 = 64 << 20;
+const POLL: Duration = Duration::from_millis(100);
+
+/// One indexed entry: its tag and the byte range of its block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexEntry {
+    pub tag: String,
+    pub offset: u64,
+    pub length: u64,
+}
+
+impl IndexEntry {
+    /// Parse a `tag\toffset\tlength` index line, or `None` if it is malformed.
+    pub fn parse(line: &str) -> Option<IndexEntry> {
+        let parts: Vec<&str> = line.rsplitn(3, '\t').collect();
+        match parts.as_slice() {
+            [length, offset, tag] => Some(IndexEntry {
+                tag: tag.to_string(),
+                offset: offset.parse().ok()?,
+                length: length.parse().ok()?,
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// The entries of an archive, as of the size and modification time recorded.
+#[derive(Debug, Default)]
+pub struct TagIndex {
+    size: u64,
+    mtime_ns: u128,
+    entries: Vec<IndexEntry>,
+}
+
+/// The index file of `archive`: `<archive>.idx`.
+pub fn index_path(archive: &Path) -> PathBuf {
+    let mut name = archive.as_os_str().to_owned();
+    name.push(".idx");
+    PathBuf::from(name)
+}
+
+fn lock_path(archive: &Path) -> PathBuf {
+    let mut name = index_path(archive).into_os_string();
+    name.push(".lock");
+    PathBuf::from(name)
+}
+
+/// Size and modification time of `archive`, as recorded in its index.
+fn stamp(archive: &Path) -> io::Result<(u64, u128)> {
+    let meta = fs::metadata(archive)?;
+    let mtime = meta.modified()?.duration_since(UNIX_EPOCH).unwrap_or_default();
+    Ok((meta.len(), mtime.as_nanos()))
+}
+
+/// An exclusively created lock file, removed on drop.
+struct BuildLock {
+    path: PathBuf,
+    file: File,
+}
+
+impl BuildLock {
+    /// Take the lock, or `None` while another build holds it. A stale lock
+    /// is broken first.
+    fn try_acquire(path: &Path) -> Result<Option<Self>, QuiverError> {
+        if path.exists() && !Self::held(path) {
+            eprintln!("⚠️  Breaking stale index lock {}", path.display());
+            match fs::remove_file(path) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e.into()),
+                _ => {}
+            }
+        }
+        match OpenOptions::new().write(true).create_new(true).open(path) {
+            Ok(mut file) => {
+                writeln!(file, "{}", process::id())?;
+                Ok(Some(Self {
+                    path: path.to_path_buf(),
+                    file,
+                }))
+            }
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Whether a build holding the lock at `path` was alive recently.
+    fn held(path: &Path) -> bool {
+        fs::metadata(path)
+            .and_then(|meta| meta.modified())
+            .is_ok_and(|modified| modified.elapsed().unwrap_or_default() <= STALE_LOCK)
+    }
+
+    /// Show waiting processes the build is still alive.
+    fn touch(&self) {
+        let _ = self.file.set_modified(SystemTime::now());
+    }
+}
+
+impl Drop for BuildLock {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+impl TagIndex {
+    /// The index of `archive`, read from its index file if that is up to
+    /// date, otherwise built and saved.
+    ///
+    /// `force_rebuild` ignores an existing index file, unless another
+    /// process rebuilt it while this one waited for the lock. `progress` is
+    /// called with the bytes scanned and the archive size while building.
+    pub fn load_or_build<P: AsRef<Path>>(
+        archive: P,
+        mut force_rebuild: bool,
+        progress: &mut dyn FnMut(u64, u64),
+    ) -> Result<Self, QuiverError> {
+        let archive = archive.as_ref();
+        let path = index_path(archive);
+        loop {
+            if !force_rebuild {
+                if let Some(index) = Self::load(&path)?.filter(|index| index.is_fresh(archive).unwrap_or(false)) {
+                    return Ok(index);
+                }
+            }
+            if let Some(lock) = BuildLock::try_acquire(&lock_path(archive))? {
+                let index = Self::build(archive, &mut |done, total| {
+                    lock.touch();
+                    progress(done, total);
+                })?;
+                index.save(&path)?;
+                return Ok(index);
+            }
+            // Another process is building it; wait, then use its index
+            while BuildLock::held(&lock_path(archive)) {
+                thread::sleep(POLL);
+            }
+            force_rebuild = false;
+        }
+    }
+
+    /// Scan `archive` for its entries. Compressed and v2 archives have no
+    /// byte offsets to index and are refused.
+    pub fn build<P: AsRef<Path>>(archive: P, progress: &mut dyn FnMut(u64, u64)) -> Result<Self, QuiverError> {
+        let archive = archive.as_ref();
+        if Compression::detect(archive)?.is_some() || format_v2::detect(archive)? {
+            return Err(QuiverError::InvalidOperation(format!(
+                "Cannot index '{}': only plain text archives can be indexed",
+                archive.display()
+            )));
+        }
+        let (size, mtime_ns) = stamp(archive)?;
+        let mut reader = BufReader::new(File::open(archive)?);
+        let mut location = Location::new(Some(archive.display().to_string()));
+        let mut index = Self {
+            size,
+            mtime_ns,
+            entries: Vec::new(),
+        };
+        let mut buf = Vec::new();
+        let mut pos = 0u64;
+        let mut reported = 0u64;
+        loop {
+            buf.clear();
+            let n = reader.read_until(b'\n', &mut buf)?;
+            if n == 0 {
+                break;
+            }
+            location.check(&buf)?;
+            if buf.starts_with(b"QV_TAG") {
+                if let Some(last) = index.entries.last_mut() {
+                    last.length = pos - last.offset;
+                }
+                if let Some(tag) = String::from_utf8_lossy(&buf).split_whitespace().nth(1) {
+                    index.entries.push(IndexEntry {
+                        tag: tag.to_string(),
+                        offset: pos,
+                        length: 0,
+                    });
+                }
+            }
+            pos += n as u64;
+            if pos - reported >= PROGRESS_EVERY {
+                reported = pos;
+                progress(pos, size);
+            }
+        }
+        if let Some(last) = index.entries.last_mut() {
+            last.length = pos - last.offset;
+        }
+        progress(pos, size);
+        Ok(index)
+    }
+
+    /// Read an index file, or `None` if there is none.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Option<Self>, QuiverError> {
+        let path = path.as_ref();
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        let malformed = |lineno: usize, line: &str| {
+            QuiverError::Malformed(format!(
+                "Malformed index line {} in {}: {}",
+                lineno + 1,
+                path.display(),
+                line
+            ))
+        };
+        let mut lines = BufReader::new(file).lines();
+        let first = lines.next().transpose()?.unwrap_or_default();
+        let parts: Vec<&str> = first.split('\t').collect();
+        let (size, mtime_ns) = match parts.as_slice() {
+            ["QV_INDEX", size, mtime] => size
+                .parse()
+                .ok()
+                .zip(mtime.parse().ok())
+                .ok_or_else(|| malformed(0, &first))?,
+            _ => return Err(malformed(0, &first)),
+        };
+        let mut index = Self {
+            size,
+            mtime_ns,
+            entries: Vec::new(),
+        };
+        for (lineno, line) in lines.enumerate() {
+            let line = line?;
+            let entry = IndexEntry::parse(&line).ok_or_else(|| malformed(lineno + 1, &line))?;
+            index.entries.push(entry);
+        }
+        Ok(Some(index))
+    }
+
+    /// Write the index file atomically.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), QuiverError> {
+        write_atomically(path.as_ref(), |file| {
+            let mut out = BufWriter::new(file);
+            writeln!(out, "QV_INDEX\t{}\t{}", self.size, self.mtime_ns)?;
+            for entry in &self.entries {
+                writeln!(out, "{}\t{}\t{}", entry.tag, entry.offset, entry.length)?;
+            }
+            out.flush()?;
+            Ok(self.entries.len() as u64)
+        })?;
+        Ok(())
+    }
+
+    /// Whether `archive` is still the file this index was built from.
+    pub fn is_fresh<P: AsRef<Path>>(&self, archive: P) -> io::Result<bool> {
+        Ok(stamp(archive.as_ref())? == (self.size, self.mtime_ns))
+    }
+
+    /// Size of the archive when the index was built.
+    pub fn archive_size(&self) -> u64 {
+        self.size
+    }
+
+    pub fn entries(&self) -> &[IndexEntry] {
+        &self.entries
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::testdata::scratch_dir;
+    use super::*;
+
+    const ARCHIVE: &str = "QV_TAG a\nATOM 1\nQV_TAG b\nQV_SCORE b x=1\nATOM 2\n";
+
+    #[test]
+    fn build_save_and_load() {
+        let archive = scratch_dir("tagindex-build").join("a.qv");
+        fs::write(&archive, ARCHIVE).unwrap();
+        let mut reports = Vec::new();
+        let index = TagIndex::build(&archive, &mut |done, total| reports.push((done, total))).unwrap();
+        let size = ARCHIVE.len() as u64;
+        assert_eq!(reports.last(), Some(&(size, size)));
+        assert_eq!(
+            index.entries(),
+            [
+                IndexEntry { tag: "a".to_string(), offset: 0, length: 16 },
+                IndexEntry { tag: "b".to_string(), offset: 16, length: size - 16 },
+            ]
+        );
+        index.save(index_path(&archive)).unwrap();
+        let loaded = TagIndex::load(index_path(&archive)).unwrap().unwrap();
+        assert_eq!(loaded.entries(), index.entries());
+        assert!(loaded.is_fresh(&archive).unwrap());
+        assert!(TagIndex::load(archive.with_extension("none")).unwrap().is_none());
+    }
+
+    #[test]
+    fn stale_indexes_are_rebuilt() {
+        let archive = scratch_dir("tagindex-stale").join("a.qv");
+        fs::write(&archive, ARCHIVE).unwrap();
+        assert_eq!(TagIndex::load_or_build(&archive, false, &mut |_, _| {}).unwrap().len(), 2);
+        fs::write(&archive, format!("{}QV_TAG c\nATOM 3\n", ARCHIVE)).unwrap();
+        assert!(!TagIndex::load(index_path(&archive)).unwrap().unwrap().is_fresh(&archive).unwrap());
+        assert_eq!(TagIndex::load_or_build(&archive, false, &mut |_, _| {}).unwrap().len(), 3);
+        assert!(!lock_path(&archive).exists());
+    }
+
+    #[test]
+    fn stale_locks_are_broken() {
+        let archive = scratch_dir("tagindex-lock").join("a.qv");
+        fs::write(&archive, ARCHIVE).unwrap();
+        let lock = File::create(lock_path(&archive)).unwrap();
+        lock.set_modified(SystemTime::now() - STALE_LOCK * 2).unwrap();
+        assert!(!BuildLock::held(&lock_path(&archive)));
+        assert_eq!(TagIndex::load_or_build(&archive, true, &mut |_, _| {}).unwrap().len(), 2);
+        assert!(!lock_path(&archive).exists());
+    }
+
+    #[test]
+    fn index_lines_parse() {
+        let entry = IndexEntry::parse("a\t16\t31").unwrap();
+        assert_eq!(entry, IndexEntry { tag: "a".to_string(), offset: 16, length: 31 });
+        assert!(IndexEntry::parse("a\tx\t1").is_none());
+        assert!(IndexEntry::parse("a\t16").is_none());
+    }
+
+    #[test]
+    fn malformed_and_compressed_archives() {
+        let dir = scratch_dir("tagindex-errors");
+        fs::write(dir.join("bad.idx"), "QV_INDEX\t10\t0\na\tx\t1\n").unwrap();
+        let err = TagIndex::load(dir.join("bad.idx")).unwrap_err();
+        assert!(format!("{:?}", err).contains("Malformed index line 2"));
+        let mut packed = Vec::new();
+        Compression::Gzip.encode(&mut ARCHIVE.as_bytes(), &mut packed).unwrap();
+        fs::write(dir.join("a.qv.gz"), packed).unwrap();
+        assert!(TagIndex::build(dir.join("a.qv.gz"), &mut |_, _| {}).is_err());
+    }
+}
@@ -0,0 +1,93 @@
+//! Synthetic archives for tests and benchmarks.
+//!
+//! [`generate_test_archive`] writes `n_entries` pseudo-structures: a single
+//! chain A of CA atoms on a random walk with 3.8 Å steps and random residue
+//! names, optionally with `score` and `plddt` values. The output depends
+//! only on the arguments, so fixtures of any size can be regenerated instead
+//! of shipped as data files. The Python `generate_test_archive` follows the
+//! same recipe.
+
+use std::path::Path;
+
+use super::transform::SplitMix64;
+use super::{Quiver, QuiverError};
+
+/// Residue names drawn for the synthetic chains.
+const RESIDUES: [&str; 20] = [
+    "ALA", "ARG", "ASN", "ASP", "CYS", "GLN", "GLU", "GLY", "HIS", "ILE", "LEU", "LYS", "MET", "PHE", "PRO",
+    "SER", "THR", "TRP", "TYR", "VAL",
+];
+/// CA-CA distance of the random walk, in Å.
+const STEP: f64 = 3.8;
+
+/// Tag of the `index`-th synthetic entry.
+pub fn synthetic_tag(index: usize) -> String {
+    format!("synthetic_{:06}", index)
+}
+
+/// PDB lines of one synthetic structure with `atoms` CA atoms, closed by
+/// TER and END.
+pub fn synthetic_structure(atoms: usize, rng: &mut SplitMix64) -> Vec<String> {
+    let mut pos = [0.0f64; 3];
+    let mut lines = Vec::with_capacity(atoms + 2);
+    for i in 0..atoms {
+        let resname = RESIDUES[(rng.next_u64() % RESIDUES.len() as u64) as usize];
+        if i > 0 {
+            let step = [rng.next_gaussian(), rng.next_gaussian(), rng.next_gaussian()];
+            let norm = step.iter().map(|c| c * c).sum::<f64>().sqrt();
+            if norm > 0.0 {
+                for (p, s) in pos.iter_mut().zip(step) {
+                    *p += STEP * s / norm;
+                }
+            }
+        }
+        lines.push(format!(
+            "ATOM  {:>5}  CA  {} A{:>4}    {:8.3}{:8.3}{:8.3}  1.00  0.00           C",
+            i % 99_999 + 1,
+            resname,
+            i % 9_999 + 1,
+            pos[0],
+            pos[1],
+            pos[2]
+        ));
+    }
+    lines.push("TER".to_string());
+    lines.push("END".to_string());
+    lines
+}
+
+/// Write a new archive at `path` of `n_entries` synthetic structures (see
+/// the module docs), each with `atoms_per_entry` atoms and, if
+/// `with_scores`, a `score=..|plddt=..` line. The same `seed` always gives
+/// the same file. An existing file is never appended to.
+pub fn generate_test_archive<P: AsRef<Path>>(
+    path: P,
+    n_entries: usize,
+    atoms_per_entry: usize,
+    with_scores: bool,
+    seed: u64,
+) -> Result<(), QuiverError> {
+    let path = path.as_ref();
+    if path.exists() {
+        return Err(QuiverError::InvalidOperation(format!("'{}' already exists", path.display())));
+    }
+    let mut qv = Quiver::new(path, "w")?;
+    let mut rng = SplitMix64::new(seed);
+    for index in 0..n_entries {
+        let lines = synthetic_structure(atoms_per_entry, &mut rng);
+        let score = with_scores
+            .then(|| format!("score={:.3}|plddt={:.3}", -100.0 * rng.next_f64(), 50.0 + 50.0 * rng.next_f64()));
+        qv.add_pdb(&lines, &synthetic_tag(index), score.as_deref())?;
+    }
+    qv.finish()
+}
+
+/// A fresh, empty directory for one test, removed and recreated on each
+/// call so reruns start clean.
+#[cfg(test)]
+pub(crate) fn scratch_dir(name: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("quiver-test-{}-{}", std::process::id(), name));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).expect("create scratch directory");
+    dir
+}
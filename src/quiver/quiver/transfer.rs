@@ -0,0 +1,300 @@
+//! Moving entries from one archive into another.
+//!
+//! [`move_entries`] works in two phases recorded in a journal beside the
+//! source, `<source>.move`. While `copying`, the entries are appended to the
+//! destination; once that is synced to disk the journal says `copied` and
+//! the entries are removed from the source by an atomic rewrite. A move cut
+//! short by a crash is settled by [`recover_move`] (which every move runs
+//! first): a `copying` journal is rolled back by cutting the destination
+//! back to its length before the move, a `copied` one is completed. Either
+//! way every entry ends up in exactly one of the two archives.
+//!
+//! The journal starts with `QV_MOVE<TAB>phase<TAB>length<TAB>destination`,
+//! where `length` is `new` if the move created the destination, followed by
+//! the moved tags, one per line.
+
+use std::collections::HashSet;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+use super::storage::write_atomically;
+use super::{Quiver, QuiverError};
+
+/// How far a move got.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MovePhase {
+    /// Entries are being appended to the destination
+    Copying,
+    /// Entries are all in the destination and are being removed from the
+    /// source
+    Copied,
+}
+
+impl MovePhase {
+    fn name(self) -> &'static str {
+        match self {
+            MovePhase::Copying => "copying",
+            MovePhase::Copied => "copied",
+        }
+    }
+}
+
+/// The journal of a move in progress.
+struct Journal {
+    phase: MovePhase,
+    destination: PathBuf,
+    /// Length of the destination before the move, `None` if it was created
+    destination_len: Option<u64>,
+    tags: Vec<String>,
+}
+
+impl Journal {
+    fn save(&self, path: &Path) -> io::Result<()> {
+        write_atomically(path, |file| {
+            let len = self.destination_len.map_or_else(|| "new".to_string(), |len| len.to_string());
+            writeln!(file, "QV_MOVE\t{}\t{}\t{}", self.phase.name(), len, self.destination.display())?;
+            for tag in &self.tags {
+                writeln!(file, "{}", tag)?;
+            }
+            Ok(self.tags.len() as u64)
+        })?;
+        Ok(())
+    }
+
+    fn load(path: &Path) -> Result<Option<Self>, QuiverError> {
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        let mut lines = BufReader::new(file).lines();
+        let first = lines.next().transpose()?.unwrap_or_default();
+        let parts: Vec<&str> = first.splitn(4, '\t').collect();
+        let journal = match parts.as_slice() {
+            ["QV_MOVE", phase, len, destination] => {
+                let phase = match *phase {
+                    "copying" => Some(MovePhase::Copying),
+                    "copied" => Some(MovePhase::Copied),
+                    _ => None,
+                };
+                let len = match *len {
+                    "new" => Some(None),
+                    len => len.parse().ok().map(Some),
+                };
+                phase.zip(len).map(|(phase, destination_len)| Journal {
+                    phase,
+                    destination: PathBuf::from(destination),
+                    destination_len,
+                    tags: Vec::new(),
+                })
+            }
+            _ => None,
+        };
+        let mut journal = journal.ok_or_else(|| {
+            QuiverError::Malformed(format!("Malformed move journal {}: {}", path.display(), first))
+        })?;
+        for line in lines {
+            journal.tags.push(line?);
+        }
+        Ok(Some(journal))
+    }
+}
+
+/// The journal of moves out of `source`: `<source>.move`.
+pub fn journal_path(source: &Path) -> PathBuf {
+    let mut name = source.as_os_str().to_owned();
+    name.push(".move");
+    PathBuf::from(name)
+}
+
+/// Settle a move out of `source` that was cut short, rolling it back if the
+/// entries were still being copied and completing it otherwise. Returns
+/// the phase the move had reached, or `None` if there was none.
+pub fn recover_move<P: AsRef<Path>>(source: P) -> Result<Option<MovePhase>, QuiverError> {
+    let source = source.as_ref();
+    let path = journal_path(source);
+    let Some(journal) = Journal::load(&path)? else {
+        return Ok(None);
+    };
+    match journal.phase {
+        MovePhase::Copying => roll_back(&journal)?,
+        MovePhase::Copied => {
+            Quiver::new(source, "a")?.remove_tags(&journal.tags)?;
+        }
+    }
+    fs::remove_file(&path)?;
+    Ok(Some(journal.phase))
+}
+
+/// Undo the appends of a move that had not finished copying.
+fn roll_back(journal: &Journal) -> io::Result<()> {
+    match journal.destination_len {
+        Some(len) => OpenOptions::new().write(true).open(&journal.destination)?.set_len(len),
+        None => match fs::remove_file(&journal.destination) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        },
+    }
+}
+
+/// Move the entries `tags` of `source` to the end of `destination`, which
+/// is created (with the source header) if it does not exist. Returns the
+/// number of entries moved.
+///
+/// Nothing is changed if a tag is missing from the source or already in the
+/// destination. An earlier move out of `source` that was cut short is
+/// settled first, see [`recover_move`].
+pub fn move_entries<P: AsRef<Path>, Q: AsRef<Path>>(
+    source: P,
+    destination: Q,
+    tags: &[String],
+) -> Result<usize, QuiverError> {
+    let (source, destination) = (source.as_ref(), destination.as_ref());
+    recover_move(source)?;
+    if destination.exists() && fs::canonicalize(source)? == fs::canonicalize(destination)? {
+        return Err(QuiverError::InvalidOperation(format!(
+            "Cannot move entries of {} into itself",
+            source.display()
+        )));
+    }
+    let from = Quiver::new(source, "r")?;
+    let missing: Vec<String> = from
+        .tags_exist(tags)
+        .into_iter()
+        .filter(|(_, exists)| !exists)
+        .map(|(tag, _)| tag)
+        .collect();
+    if !missing.is_empty() {
+        return Err(QuiverError::InvalidOperation(format!(
+            "Tags not in {}: {}",
+            source.display(),
+            missing.join(", ")
+        )));
+    }
+    let wanted: HashSet<&str> = tags.iter().map(|t| t.as_str()).collect();
+    let mut seen = HashSet::new();
+    let moving: Vec<String> = from
+        .get_tags()
+        .into_iter()
+        .filter(|t| wanted.contains(t.as_str()) && seen.insert(t.clone()))
+        .collect();
+    if moving.is_empty() {
+        return Ok(0);
+    }
+
+    let destination_len = match fs::metadata(destination) {
+        Ok(meta) => Some(meta.len()),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+        Err(e) => return Err(e.into()),
+    };
+    let mut to = Quiver::new(destination, if destination_len.is_some() { "a" } else { "w" })?;
+    if let Some((tag, _)) = to.tags_exist(&moving).into_iter().find(|(_, exists)| *exists) {
+        return Err(QuiverError::InvalidOperation(format!(
+            "Tag {} is already in {}",
+            tag,
+            destination.display()
+        )));
+    }
+
+    let path = journal_path(source);
+    let mut journal = Journal {
+        phase: MovePhase::Copying,
+        destination: destination.to_path_buf(),
+        destination_len,
+        tags: moving,
+    };
+    journal.save(&path)?;
+    let copied = (|| {
+        let header = from.get_header();
+        if destination_len.is_none() && !header.is_empty() {
+            to.set_header(&header)?;
+        }
+        for tag in &journal.tags {
+            to.append(from.get_entry_text(tag, true)?.as_bytes())?;
+        }
+        to.finish()?;
+        File::open(destination)?.sync_all()?;
+        Ok::<(), QuiverError>(())
+    })();
+    if let Err(e) = copied {
+        roll_back(&journal)?;
+        fs::remove_file(&path)?;
+        return Err(e);
+    }
+
+    journal.phase = MovePhase::Copied;
+    journal.save(&path)?;
+    drop(from);
+    Quiver::new(source, "a")?.remove_tags(&journal.tags)?;
+    fs::remove_file(&path)?;
+    Ok(journal.tags.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::testdata::scratch_dir;
+    use super::*;
+
+    const SOURCE: &str = "QV_HEADER k=v\nQV_TAG a\nATOM a\nQV_TAG b\nATOM b\nQV_TAG c\nATOM c\n";
+
+    fn tags(names: &[&str]) -> Vec<String> {
+        names.iter().map(|t| t.to_string()).collect()
+    }
+
+    #[test]
+    fn entries_move_to_a_new_archive() {
+        let dir = scratch_dir("transfer-new");
+        let (source, destination) = (dir.join("src.qv"), dir.join("dst.qv"));
+        fs::write(&source, SOURCE).unwrap();
+        assert_eq!(move_entries(&source, &destination, &tags(&["c", "a"])).unwrap(), 2);
+        assert_eq!(Quiver::new(&source, "r").unwrap().get_tags(), ["b"]);
+        let moved = Quiver::new(&destination, "r").unwrap();
+        assert_eq!(moved.get_tags(), ["a", "c"]);
+        assert_eq!(moved.get_header(), [("k".to_string(), "v".to_string())]);
+        assert!(!journal_path(&source).exists());
+    }
+
+    #[test]
+    fn nothing_moves_on_a_bad_tag() {
+        let dir = scratch_dir("transfer-refused");
+        let (source, destination) = (dir.join("src.qv"), dir.join("dst.qv"));
+        fs::write(&source, SOURCE).unwrap();
+        fs::write(&destination, "QV_TAG b\nATOM other b\n").unwrap();
+        assert!(move_entries(&source, &destination, &tags(&["a", "z"])).is_err());
+        assert!(move_entries(&source, &destination, &tags(&["b"])).is_err());
+        assert!(move_entries(&source, &source, &tags(&["a"])).is_err());
+        assert_eq!(fs::read_to_string(&source).unwrap(), SOURCE);
+        assert_eq!(fs::read_to_string(&destination).unwrap(), "QV_TAG b\nATOM other b\n");
+    }
+
+    #[test]
+    fn interrupted_copies_are_rolled_back() {
+        let dir = scratch_dir("transfer-copying");
+        let (source, destination) = (dir.join("src.qv"), dir.join("dst.qv"));
+        fs::write(&source, SOURCE).unwrap();
+        fs::write(&destination, "QV_TAG x\nATOM x\nQV_TAG a\nAT").unwrap();
+        let journal = format!("QV_MOVE\tcopying\t16\t{}\na\n", destination.display());
+        fs::write(journal_path(&source), journal).unwrap();
+        assert_eq!(recover_move(&source).unwrap(), Some(MovePhase::Copying));
+        assert_eq!(fs::read_to_string(&destination).unwrap(), "QV_TAG x\nATOM x\n");
+        assert_eq!(fs::read_to_string(&source).unwrap(), SOURCE);
+        assert_eq!(recover_move(&source).unwrap(), None);
+    }
+
+    #[test]
+    fn copied_moves_are_completed() {
+        let dir = scratch_dir("transfer-copied");
+        let (source, destination) = (dir.join("src.qv"), dir.join("dst.qv"));
+        fs::write(&source, SOURCE).unwrap();
+        fs::write(&destination, "QV_TAG b\nATOM b\n").unwrap();
+        fs::write(journal_path(&source), format!("QV_MOVE\tcopied\tnew\t{}\nb\n", destination.display())).unwrap();
+        // The next move settles the earlier one first
+        assert_eq!(move_entries(&source, &destination, &tags(&["a"])).unwrap(), 1);
+        assert_eq!(Quiver::new(&source, "r").unwrap().get_tags(), ["c"]);
+        assert_eq!(Quiver::new(&destination, "r").unwrap().get_tags(), ["b", "a"]);
+
+        fs::write(journal_path(&source), "QV_MOVE\tstuck\n").unwrap();
+        assert!(format!("{:?}", recover_move(&source).unwrap_err()).contains("Malformed"));
+    }
+}
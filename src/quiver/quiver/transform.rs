@@ -0,0 +1,264 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use super::pdb::is_header_record;
+
+/// Small deterministic pseudo-random generator (SplitMix64).
+///
+/// Good enough for augmentation noise and reproducible across platforms,
+/// without pulling in an external RNG crate.
+pub struct SplitMix64 {
+    state: u64,
+}
+
+impl SplitMix64 {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform sample in the open interval (0, 1).
+    pub fn next_f64(&mut self) -> f64 {
+        ((self.next_u64() >> 11) as f64 + 0.5) / (1u64 << 53) as f64
+    }
+
+    /// Standard normal sample (Box-Muller).
+    pub fn next_gaussian(&mut self) -> f64 {
+        let u1 = self.next_f64();
+        let u2 = self.next_f64();
+        (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos()
+    }
+}
+
+/// Add isotropic Gaussian noise with standard deviation `sigma` (Å) to the
+/// coordinates of every ATOM/HETATM record. Other lines are left untouched.
+pub fn jitter_coords(pdb_lines: &mut [String], sigma: f64, rng: &mut SplitMix64) {
+    for line in pdb_lines.iter_mut() {
+        jitter_line(line, sigma, rng);
+    }
+}
+
+/// Jitter the coordinates of a single ATOM/HETATM record in place.
+pub fn jitter_line(line: &mut String, sigma: f64, rng: &mut SplitMix64) {
+    if !(line.starts_with("ATOM") || line.starts_with("HETATM")) || line.len() < 54 {
+        return;
+    }
+    let coords: Option<Vec<f64>> = [30..38, 38..46, 46..54]
+        .into_iter()
+        .map(|r| line.get(r).and_then(|s| s.trim().parse().ok()))
+        .collect();
+    let Some(coords) = coords else {
+        return;
+    };
+    let moved: String = coords
+        .iter()
+        .map(|c| format!("{:8.3}", c + sigma * rng.next_gaussian()))
+        .collect();
+    line.replace_range(30..54, &moved);
+}
+
+/// Identity of an atom across its alternate locations: atom name, residue
+/// name, chain, residue number and insertion code.
+fn atom_key(line: &str) -> Option<(&str, &str)> {
+    Some((line.get(12..16)?, line.get(17..27)?))
+}
+
+fn has_records(line: &str) -> bool {
+    line.starts_with("ATOM") || line.starts_with("HETATM") || line.starts_with("ANISOU")
+}
+
+/// Keep a single alternate location per atom.
+///
+/// For each atom the `keep` location is used if present, otherwise the first
+/// one that appears. Kept records get a blank altLoc and, for ATOM/HETATM,
+/// an occupancy of 1.00. Records without an altLoc pass through unchanged.
+pub fn clean_altloc(pdb_lines: &[String], keep: char) -> Vec<String> {
+    let mut chosen: HashMap<(&str, &str), char> = HashMap::new();
+    for line in pdb_lines {
+        let altloc = line.chars().nth(16).unwrap_or(' ');
+        if !has_records(line) || altloc == ' ' {
+            continue;
+        }
+        if let Some(key) = atom_key(line) {
+            let slot = chosen.entry(key).or_insert(altloc);
+            if altloc == keep {
+                *slot = keep;
+            }
+        }
+    }
+
+    let mut cleaned = Vec::with_capacity(pdb_lines.len());
+    for line in pdb_lines {
+        let altloc = line.chars().nth(16).unwrap_or(' ');
+        let key = atom_key(line).filter(|_| has_records(line) && altloc != ' ');
+        let Some(key) = key else {
+            cleaned.push(line.clone());
+            continue;
+        };
+        if chosen.get(&key) != Some(&altloc) {
+            continue;
+        }
+        let mut line = line.clone();
+        line.replace_range(16..17, " ");
+        if !line.starts_with("ANISOU") && line.get(54..60).is_some() {
+            line.replace_range(54..60, "  1.00");
+        }
+        cleaned.push(line);
+    }
+    cleaned
+}
+
+/// What rewrites do with header records (`HEADER`, `COMPND`, `SEQRES`, ...,
+/// see [`super::pdb::HEADER_RECORDS`]).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum HeaderPolicy {
+    /// Carry them through unchanged
+    #[default]
+    Keep,
+    /// Drop them
+    Strip,
+}
+
+impl FromStr for HeaderPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "keep" => Ok(HeaderPolicy::Keep),
+            "strip" => Ok(HeaderPolicy::Strip),
+            other => Err(format!("Unknown header policy '{}', expected one of: keep, strip", other)),
+        }
+    }
+}
+
+impl HeaderPolicy {
+    /// Apply the policy to the lines of one PDB payload.
+    pub fn apply(self, pdb_lines: Vec<String>) -> Vec<String> {
+        match self {
+            HeaderPolicy::Keep => pdb_lines,
+            HeaderPolicy::Strip => pdb_lines.into_iter().filter(|line| !is_header_record(line)).collect(),
+        }
+    }
+}
+
+/// The PDB record types to keep on read, e.g. `ATOM,HETATM,TER,END` to drop
+/// ANISOU and REMARK records. A record type is the first six columns of a
+/// line, without trailing blanks.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordFilter {
+    records: Vec<String>,
+}
+
+impl FromStr for RecordFilter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let records: Vec<String> = s
+            .split(',')
+            .map(|r| r.trim().to_ascii_uppercase())
+            .filter(|r| !r.is_empty())
+            .collect();
+        if let Some(long) = records.iter().find(|r| r.len() > 6) {
+            return Err(format!("Record type '{}' is longer than six characters", long));
+        }
+        if records.is_empty() {
+            return Err("Expected a comma-separated list of record types, e.g. ATOM,HETATM,TER,END".to_string());
+        }
+        Ok(Self { records })
+    }
+}
+
+impl RecordFilter {
+    pub fn new<S: AsRef<str>>(records: &[S]) -> Self {
+        Self {
+            records: records.iter().map(|r| r.as_ref().trim().to_ascii_uppercase()).collect(),
+        }
+    }
+
+    /// Whether `line` is of one of the kept record types.
+    pub fn keeps(&self, line: &str) -> bool {
+        let line = line.trim_end_matches(['\n', '\r']);
+        let record = line.get(..6).unwrap_or(line).trim_end();
+        self.records.iter().any(|r| r == record)
+    }
+
+    /// The lines of one PDB payload of the kept record types.
+    pub fn apply(&self, pdb_lines: Vec<String>) -> Vec<String> {
+        pdb_lines.into_iter().filter(|line| self.keeps(line)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CA: &str = "ATOM      1  CA  ALA A   1      11.104   6.134  -6.504  1.00  0.00           C";
+
+    fn with_altloc(altloc: char, occupancy: &str, x: &str) -> String {
+        format!("ATOM      1  CA {}ALA A   1    {}   6.134  -6.504{}  0.00           C", altloc, x, occupancy)
+    }
+
+    #[test]
+    fn generator_is_seeded() {
+        let mut a = SplitMix64::new(7);
+        let mut b = SplitMix64::new(7);
+        let first: Vec<u64> = (0..3).map(|_| a.next_u64()).collect();
+        assert_eq!(first, (0..3).map(|_| b.next_u64()).collect::<Vec<_>>());
+        assert_ne!(first[0], SplitMix64::new(8).next_u64());
+        assert!((0..1000).map(|_| a.next_f64()).all(|u| u > 0.0 && u < 1.0));
+    }
+
+    #[test]
+    fn jitter_moves_only_coordinates() {
+        let mut lines = vec![CA.to_string(), "TER".to_string()];
+        jitter_coords(&mut lines, 0.5, &mut SplitMix64::new(1));
+        assert_eq!(lines[1], "TER");
+        assert_ne!(lines[0], CA);
+        assert_eq!(lines[0].len(), CA.len());
+        assert_eq!((&lines[0][..30], &lines[0][54..]), (&CA[..30], &CA[54..]));
+        let mut still = vec![CA.to_string()];
+        jitter_coords(&mut still, 0.0, &mut SplitMix64::new(1));
+        assert_eq!(still[0], CA);
+    }
+
+    #[test]
+    fn one_alternate_location_per_atom() {
+        let lines = vec![
+            with_altloc('A', "  0.60", "  11.104"),
+            with_altloc('B', "  0.40", "  12.000"),
+            CA.replace("  1      ", "  2      "),
+        ];
+        let kept = clean_altloc(&lines, 'B');
+        assert_eq!(kept, [with_altloc(' ', "  1.00", "  12.000"), lines[2].clone()]);
+        let first = clean_altloc(&lines, 'C');
+        assert_eq!(first[0], with_altloc(' ', "  1.00", "  11.104"));
+    }
+
+    #[test]
+    fn header_policy() {
+        let lines = vec!["HEADER    DE NOVO".to_string(), CA.to_string()];
+        assert_eq!(HeaderPolicy::Keep.apply(lines.clone()), lines);
+        assert_eq!(HeaderPolicy::Strip.apply(lines), [CA]);
+        assert!("drop".parse::<HeaderPolicy>().is_err());
+    }
+
+    #[test]
+    fn record_filter() {
+        let filter: RecordFilter = "atom, ter,END".parse().unwrap();
+        assert!(filter.keeps(CA));
+        assert!(filter.keeps("TER\n"));
+        assert!(filter.keeps("END"));
+        assert!(!filter.keeps("ENDMDL"));
+        assert!(!filter.keeps("ANISOU    1  CA"));
+        assert_eq!(filter, RecordFilter::new(&["ATOM", "TER", "END"]));
+        assert!("HETATOM".parse::<RecordFilter>().is_err());
+        assert!(" , ".parse::<RecordFilter>().is_err());
+    }
+}
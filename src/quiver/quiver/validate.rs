@@ -0,0 +1,245 @@
+//! Checks run on every entry before it is written.
+//!
+//! An [`EntryValidator`] added to a `Quiver` handle sees each entry passed
+//! to `add_pdb` or `add_entry`; an error rejects the entry and nothing is
+//! written. Closures are validators too, and [`MaxResidues`],
+//! [`RequiredChains`], [`RequiredScores`], [`PdbRecords`] and
+//! [`StructureContent`] cover the common rules.
+
+use super::pdb::{Atom, Structure};
+use super::{Entry, PayloadFormat};
+
+/// Accepts or rejects an entry, saying why on rejection.
+pub trait EntryValidator: Send + Sync {
+    fn validate(&self, entry: &Entry) -> Result<(), String>;
+}
+
+impl<F> EntryValidator for F
+where
+    F: Fn(&Entry) -> Result<(), String> + Send + Sync,
+{
+    fn validate(&self, entry: &Entry) -> Result<(), String> {
+        self(entry)
+    }
+}
+
+/// Rejects PDB entries with more than this many residues.
+pub struct MaxResidues(pub usize);
+
+impl EntryValidator for MaxResidues {
+    fn validate(&self, entry: &Entry) -> Result<(), String> {
+        if entry.format != PayloadFormat::Pdb {
+            return Ok(());
+        }
+        let residues = Structure::from_lines(&entry.lines).residues().count();
+        if residues > self.0 {
+            return Err(format!("{} residues, more than the maximum of {}", residues, self.0));
+        }
+        Ok(())
+    }
+}
+
+/// Rejects PDB entries missing any of these chains.
+pub struct RequiredChains(pub Vec<String>);
+
+impl EntryValidator for RequiredChains {
+    fn validate(&self, entry: &Entry) -> Result<(), String> {
+        if entry.format != PayloadFormat::Pdb {
+            return Ok(());
+        }
+        let structure = Structure::from_lines(&entry.lines);
+        let missing: Vec<&str> = self
+            .0
+            .iter()
+            .filter(|id| structure.chain(id).is_none())
+            .map(String::as_str)
+            .collect();
+        if !missing.is_empty() {
+            return Err(format!("missing chain(s) {}", missing.join(", ")));
+        }
+        Ok(())
+    }
+}
+
+/// Rejects entries whose score string lacks any of these fields.
+pub struct RequiredScores(pub Vec<String>);
+
+impl EntryValidator for RequiredScores {
+    fn validate(&self, entry: &Entry) -> Result<(), String> {
+        let score = entry.score.as_deref().unwrap_or("");
+        let names: Vec<&str> = score.split('|').filter_map(|kv| kv.split_once('=')).map(|(k, _)| k).collect();
+        let missing: Vec<&str> = self
+            .0
+            .iter()
+            .filter(|key| !names.contains(&key.as_str()))
+            .map(String::as_str)
+            .collect();
+        if !missing.is_empty() {
+            return Err(format!("missing score field(s) {}", missing.join(", ")));
+        }
+        Ok(())
+    }
+}
+
+/// Record names of the PDB format, version 3.3.
+pub const PDB_RECORDS: [&str; 51] = [
+    "HEADER", "OBSLTE", "TITLE", "SPLIT", "CAVEAT", "COMPND", "SOURCE", "KEYWDS", "EXPDTA", "NUMMDL",
+    "MDLTYP", "AUTHOR", "REVDAT", "SPRSDE", "JRNL", "REMARK", "DBREF", "DBREF1", "DBREF2", "SEQADV",
+    "SEQRES", "MODRES", "HET", "HETNAM", "HETSYN", "FORMUL", "HELIX", "SHEET", "SSBOND", "LINK",
+    "CISPEP", "SITE", "CRYST1", "ORIGX1", "ORIGX2", "ORIGX3", "SCALE1", "SCALE2", "SCALE3", "MTRIX1",
+    "MTRIX2", "MTRIX3", "MODEL", "ATOM", "ANISOU", "TER", "HETATM", "ENDMDL", "CONECT", "MASTER",
+    "END",
+];
+
+/// Rejects PDB entries with lines that are not PDB records, or ATOM/HETATM
+/// records without readable coordinates. Blank lines are allowed.
+///
+/// Rosetta appends its score table to the PDB files it writes, so this is
+/// not a check to use on such files.
+pub struct PdbRecords;
+
+impl EntryValidator for PdbRecords {
+    fn validate(&self, entry: &Entry) -> Result<(), String> {
+        if entry.format != PayloadFormat::Pdb {
+            return Ok(());
+        }
+        for (i, line) in entry.lines.iter().enumerate() {
+            let record = line.get(..6).unwrap_or(line).trim_end();
+            if record.is_empty() {
+                continue;
+            }
+            if !PDB_RECORDS.contains(&record) {
+                return Err(format!("line {} is not a PDB record: {}", i + 1, record));
+            }
+            if matches!(record, "ATOM" | "HETATM") && Atom::parse(line).is_none() {
+                return Err(format!("line {} is an unreadable {} record", i + 1, record));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Rejects entries that are clearly not structures, such as a log file
+/// picked up by a loose glob: a PDB payload without any ATOM or HETATM
+/// record, or an mmCIF payload without `_atom_site` items.
+///
+/// Only the absence of coordinates is checked, so any real structure file
+/// passes; see [`PdbRecords`] for a strict check of every line.
+pub struct StructureContent;
+
+impl EntryValidator for StructureContent {
+    fn validate(&self, entry: &Entry) -> Result<(), String> {
+        sniff_structure(entry.format, &entry.lines)
+    }
+}
+
+/// Whether `lines` look like a payload of `format`, see [`StructureContent`].
+/// The error names the first non-blank line, to help spot what the file is.
+pub fn sniff_structure<S: AsRef<str>>(format: PayloadFormat, lines: &[S]) -> Result<(), String> {
+    let found = match format {
+        PayloadFormat::Pdb => lines.iter().map(AsRef::as_ref).any(|line| {
+            let record = line.get(..6).unwrap_or(line).trim_end();
+            matches!(record, "ATOM" | "HETATM")
+        }),
+        PayloadFormat::Cif => lines.iter().any(|line| line.as_ref().trim_start().starts_with("_atom_site.")),
+    };
+    if found {
+        return Ok(());
+    }
+    let first = lines.iter().map(|line| line.as_ref().trim()).find(|line| !line.is_empty());
+    let records = match format {
+        PayloadFormat::Pdb => "no ATOM or HETATM records",
+        PayloadFormat::Cif => "no _atom_site items",
+    };
+    Err(match first {
+        Some(line) => {
+            let line: String = line.chars().take(60).collect();
+            format!("not a structure ({}), starts with: {}", records, line)
+        }
+        None => format!("not a structure ({}), the payload is empty", records),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::testdata::scratch_dir;
+    use super::super::Quiver;
+    use super::*;
+    use std::sync::Arc;
+
+    fn atom(serial: usize, chain: char, resid: usize) -> String {
+        format!(
+            "ATOM  {:>5}  CA  ALA {}{:>4}      11.104   6.134  -6.504  1.00  0.00           C",
+            serial, chain, resid
+        )
+    }
+
+    fn entry(lines: &[String], score: Option<&str>) -> Entry {
+        Entry {
+            tag: "d1".to_string(),
+            score: score.map(str::to_string),
+            meta: Vec::new(),
+            lines: lines.to_vec(),
+            format: PayloadFormat::Pdb,
+        }
+    }
+
+    #[test]
+    fn residues_and_chains() {
+        let e = entry(&[atom(1, 'A', 1), atom(2, 'A', 2), atom(3, 'B', 1)], None);
+        assert!(MaxResidues(3).validate(&e).is_ok());
+        assert_eq!(MaxResidues(2).validate(&e).unwrap_err(), "3 residues, more than the maximum of 2");
+        assert!(RequiredChains(vec!["A".to_string(), "B".to_string()]).validate(&e).is_ok());
+        let chains = RequiredChains(vec!["A".to_string(), "C".to_string(), "D".to_string()]);
+        assert_eq!(chains.validate(&e).unwrap_err(), "missing chain(s) C, D");
+    }
+
+    #[test]
+    fn score_fields() {
+        let required = RequiredScores(vec!["plddt".to_string(), "rmsd".to_string()]);
+        assert!(required.validate(&entry(&[], Some("plddt=90|rmsd=1.2"))).is_ok());
+        assert_eq!(required.validate(&entry(&[], Some("plddt=90"))).unwrap_err(), "missing score field(s) rmsd");
+        assert!(required.validate(&entry(&[], None)).is_err());
+    }
+
+    #[test]
+    fn pdb_records() {
+        assert!(PdbRecords.validate(&entry(&[atom(1, 'A', 1), String::new(), "END".to_string()], None)).is_ok());
+        let scored = entry(&[atom(1, 'A', 1), "pose 12.5".to_string()], None);
+        assert_eq!(PdbRecords.validate(&scored).unwrap_err(), "line 2 is not a PDB record: pose 1");
+        let broken = entry(&["ATOM      1  CA".to_string()], None);
+        assert!(PdbRecords.validate(&broken).unwrap_err().contains("unreadable ATOM"));
+    }
+
+    #[test]
+    fn structures_are_sniffed() {
+        assert!(StructureContent.validate(&entry(&[atom(1, 'A', 1)], None)).is_ok());
+        let log = entry(&["".to_string(), "  Step 1 done".to_string()], None);
+        assert_eq!(
+            StructureContent.validate(&log).unwrap_err(),
+            "not a structure (no ATOM or HETATM records), starts with: Step 1 done"
+        );
+        assert!(sniff_structure(PayloadFormat::Cif, &["_atom_site.id"]).is_ok());
+        assert!(sniff_structure::<&str>(PayloadFormat::Cif, &[]).unwrap_err().contains("empty"));
+    }
+
+    #[test]
+    fn rejected_entries_are_not_written() {
+        let dir = scratch_dir("validate-add");
+        let path = dir.join("a.qv");
+        let mut qv = Quiver::new(&path, "w").unwrap();
+        qv.add_validator(Arc::new(MaxResidues(1)));
+        qv.add_validator(Arc::new(|e: &Entry| {
+            if e.tag.starts_with("tmp") {
+                Err("temporary".to_string())
+            } else {
+                Ok(())
+            }
+        }));
+        qv.add_pdb(&[atom(1, 'A', 1)], "d1", None).unwrap();
+        assert!(qv.add_pdb(&[atom(1, 'A', 1), atom(2, 'A', 2)], "d2", None).is_err());
+        assert!(qv.add_pdb(&[atom(1, 'A', 1)], "tmp3", None).is_err());
+        qv.finish().unwrap();
+        assert_eq!(Quiver::new(&path, "r").unwrap().get_tags(), ["d1"]);
+    }
+}
@@ -0,0 +1,208 @@
+//! Warnings collected by an operation instead of printed as they happen.
+//!
+//! Library code never writes to stderr: anything worth telling the user
+//! that does not stop the operation is pushed onto a [`Warnings`] carried by
+//! its result (or, for reads through a [`super::Quiver`] handle, taken with
+//! [`super::Quiver::take_warnings`]). Each warning has a [`WarningCode`], so
+//! callers can act on a kind of warning without matching message text; the
+//! CLIs print them with [`Warnings::report`].
+
+use std::collections::HashSet;
+use std::fmt;
+use std::str::FromStr;
+
+use super::scores::json_string;
+
+/// What a [`Warning`] is about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WarningCode {
+    /// A requested tag is not in the archive
+    MissingTag,
+    /// The same tag was requested more than once
+    DuplicateRequest,
+    /// Two entries of the archive share a tag
+    DuplicateTag,
+    /// An input file was left out, e.g. because its tag was taken
+    SkippedFile,
+    /// An input file was given another tag because its own was taken
+    RenamedDuplicate,
+    /// A `QV_SCORE` line names a field twice
+    RepeatedScoreField,
+    /// The archive ends in an entry cut short by its writer
+    PartialEntry,
+    /// Extraction stopped before exceeding its output quota
+    OutputQuota,
+}
+
+impl WarningCode {
+    /// The machine-readable name of the code, e.g. `missing-tag`.
+    pub fn name(self) -> &'static str {
+        match self {
+            WarningCode::MissingTag => "missing-tag",
+            WarningCode::DuplicateRequest => "duplicate-request",
+            WarningCode::DuplicateTag => "duplicate-tag",
+            WarningCode::SkippedFile => "skipped-file",
+            WarningCode::RenamedDuplicate => "renamed-duplicate",
+            WarningCode::RepeatedScoreField => "repeated-score-field",
+            WarningCode::PartialEntry => "partial-entry",
+            WarningCode::OutputQuota => "output-quota",
+        }
+    }
+}
+
+impl fmt::Display for WarningCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for WarningCode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        [
+            WarningCode::MissingTag,
+            WarningCode::DuplicateRequest,
+            WarningCode::DuplicateTag,
+            WarningCode::SkippedFile,
+            WarningCode::RenamedDuplicate,
+            WarningCode::RepeatedScoreField,
+            WarningCode::PartialEntry,
+            WarningCode::OutputQuota,
+        ]
+        .into_iter()
+        .find(|code| code.name() == s)
+        .ok_or_else(|| format!("Unknown warning code '{}'", s))
+    }
+}
+
+/// One warning: its code and a message for people.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Warning {
+    pub code: WarningCode,
+    pub message: String,
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}] {}", self.code, self.message)
+    }
+}
+
+/// Warnings in the order they were raised. The same warning is kept once.
+#[derive(Debug, Clone, Default)]
+pub struct Warnings {
+    warnings: Vec<Warning>,
+    seen: HashSet<(WarningCode, String)>,
+}
+
+impl Warnings {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a warning, unless the same one was already added.
+    pub fn push(&mut self, code: WarningCode, message: impl Into<String>) {
+        let message = message.into();
+        if self.seen.insert((code, message.clone())) {
+            self.warnings.push(Warning { code, message });
+        }
+    }
+
+    /// Add every warning of `other`, see [`Warnings::push`].
+    pub fn extend(&mut self, other: Warnings) {
+        for warning in other.warnings {
+            self.push(warning.code, warning.message);
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Warning> {
+        self.warnings.iter()
+    }
+
+    /// The warnings with the given code.
+    pub fn with_code(&self, code: WarningCode) -> impl Iterator<Item = &Warning> {
+        self.warnings.iter().filter(move |w| w.code == code)
+    }
+
+    pub fn len(&self) -> usize {
+        self.warnings.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.warnings.is_empty()
+    }
+
+    /// Print every warning on stderr, one per line.
+    pub fn report(&self) {
+        for warning in &self.warnings {
+            eprintln!("⚠️  {}", warning);
+        }
+    }
+
+    /// The warnings as a JSON array of `{"code", "message"}` objects.
+    pub fn to_json(&self) -> String {
+        let items: Vec<String> = self
+            .warnings
+            .iter()
+            .map(|w| {
+                format!(
+                    "{{\"code\": {}, \"message\": {}}}",
+                    json_string(w.code.name()),
+                    json_string(&w.message)
+                )
+            })
+            .collect();
+        format!("[{}]", items.join(", "))
+    }
+}
+
+impl IntoIterator for Warnings {
+    type Item = Warning;
+    type IntoIter = std::vec::IntoIter<Warning>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.warnings.into_iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn codes_round_trip_through_their_names() {
+        for name in ["missing-tag", "duplicate-tag", "partial-entry", "output-quota"] {
+            assert_eq!(name.parse::<WarningCode>().unwrap().to_string(), name);
+        }
+        assert!("missing_tag".parse::<WarningCode>().is_err());
+    }
+
+    #[test]
+    fn repeats_are_kept_once_in_order() {
+        let mut warnings = Warnings::new();
+        warnings.push(WarningCode::MissingTag, "d2");
+        warnings.push(WarningCode::DuplicateTag, "d1");
+        warnings.push(WarningCode::MissingTag, "d2");
+        let mut more = Warnings::new();
+        more.push(WarningCode::DuplicateTag, "d1");
+        more.push(WarningCode::MissingTag, "d3");
+        warnings.extend(more);
+        assert_eq!(warnings.len(), 3);
+        let missing: Vec<&str> = warnings.with_code(WarningCode::MissingTag).map(|w| w.message.as_str()).collect();
+        assert_eq!(missing, ["d2", "d3"]);
+        assert_eq!(warnings.iter().next().unwrap().to_string(), "[missing-tag] d2");
+    }
+
+    #[test]
+    fn json_array() {
+        let mut warnings = Warnings::new();
+        assert_eq!(warnings.to_json(), "[]");
+        warnings.push(WarningCode::SkippedFile, "\"a b\".pdb");
+        warnings.push(WarningCode::OutputQuota, "stopped");
+        assert_eq!(
+            warnings.to_json(),
+            r#"[{"code": "skipped-file", "message": "\"a b\".pdb"}, {"code": "output-quota", "message": "stopped"}]"#
+        );
+    }
+}
@@ -0,0 +1,108 @@
+use clap::Parser;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::process;
+
+use quiver::compress::open_reader;
+use quiver::ligand::{ligand_field, LIGANDS_FIELD};
+use quiver::sequence::sequence_fields;
+use quiver::{format_meta_line, parse_meta_line, PayloadFormat};
+
+/// Annotate every structure in a Quiver file with derived metadata.
+///
+/// Usage:
+///     qvannotate my.qv --sequences > annotated.qv
+///     qvannotate my.qv --ligands > annotated.qv
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// Quiver file to annotate
+    quiver_file: String,
+
+    /// Store each chain's one-letter sequence as QV_META fields
+    #[arg(long)]
+    sequences: bool,
+
+    /// Store each structure's ligand codes and counts (from HETATM records, waters excluded)
+    #[arg(long)]
+    ligands: bool,
+}
+
+/// One structure block, split into its header lines and payload.
+struct Entry {
+    tag: String,
+    format: PayloadFormat,
+    header: Vec<String>,
+    meta: Vec<(String, String)>,
+    payload: Vec<String>,
+}
+
+fn main() {
+    let args = Args::parse();
+
+    if let Err(e) = annotate(&args) {
+        eprintln!("❌ Error: {}", e);
+        process::exit(1);
+    }
+}
+
+fn annotate(args: &Args) -> io::Result<()> {
+    let file = open_reader(&args.quiver_file)?;
+    let reader = BufReader::new(file);
+    let stdout = io::stdout();
+    let mut out = BufWriter::new(stdout.lock());
+
+    let mut current: Option<Entry> = None;
+    for line in reader.lines() {
+        let line = line?;
+        if let Some(tag) = line.strip_prefix("QV_TAG ") {
+            if let Some(entry) = current.take() {
+                write_entry(&mut out, entry, args)?;
+            }
+            current = Some(Entry {
+                tag: tag.split_whitespace().next().unwrap_or("").to_string(),
+                format: PayloadFormat::from_tag_line(&line),
+                header: vec![line],
+                meta: Vec::new(),
+                payload: Vec::new(),
+            });
+            continue;
+        }
+        match current.as_mut() {
+            Some(entry) => {
+                if let Some((_, fields)) = parse_meta_line(&line) {
+                    entry.meta.extend(fields);
+                } else if line.starts_with("QV_SCORE") || line.starts_with("QV_HASH") {
+                    entry.header.push(line);
+                } else {
+                    entry.payload.push(line);
+                }
+            }
+            None => writeln!(out, "{}", line)?,
+        }
+    }
+    if let Some(entry) = current.take() {
+        write_entry(&mut out, entry, args)?;
+    }
+    out.flush()
+}
+
+fn write_entry<W: Write>(out: &mut W, mut entry: Entry, args: &Args) -> io::Result<()> {
+    if args.sequences && entry.format == PayloadFormat::Pdb {
+        entry.meta.retain(|(k, _)| !k.starts_with("seq_"));
+        entry.meta.extend(sequence_fields(&entry.payload));
+    }
+    if args.ligands && entry.format == PayloadFormat::Pdb {
+        entry.meta.retain(|(k, _)| k != LIGANDS_FIELD);
+        entry.meta.extend(ligand_field(&entry.payload));
+    }
+    for line in &entry.header {
+        writeln!(out, "{}", line)?;
+    }
+    if !entry.meta.is_empty() {
+        writeln!(out, "{}", format_meta_line(&entry.tag, &entry.meta))?;
+    }
+    for line in &entry.payload {
+        writeln!(out, "{}", line)?;
+    }
+    Ok(())
+}
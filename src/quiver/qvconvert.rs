@@ -0,0 +1,94 @@
+use clap::Parser;
+use std::path::Path;
+use std::process;
+
+use quiver::convert::{convert, verify, Format};
+use quiver::format_v2::V2File;
+use quiver::storage::LocalFile;
+use quiver::Quiver;
+
+/// Convert a Quiver file to another format, verifying every entry.
+///
+/// The header and each entry are checksummed byte for byte before writing
+/// and compared after reading the output back, so a successful conversion
+/// is lossless. Archives with lines qv2 cannot hold (`QV_HASH` lines,
+/// several `QV_SCORE` lines in an entry) only convert to qv1.
+///
+/// Outputs on S3 (`s3://bucket/key`, qv1 only, needs the `s3` feature) are
+/// uploaded as they are written and not read back.
+///
+/// Usage:
+///     qvconvert old.qv new.qv2 --format qv2
+///     qvconvert old.qv small.qv2 --format qv2-zstd
+///     qvconvert new.qv2 plain.qv --format qv1
+///     qvconvert big.qv s3://bucket/big.qv
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// Quiver file to convert
+    input: String,
+
+    /// Output file (must not exist)
+    output: String,
+
+    /// Target format
+    #[arg(long, default_value = "qv1")]
+    format: Format,
+}
+
+fn main() {
+    let args = Args::parse();
+
+    if args.output.starts_with("s3://") {
+        upload(&args);
+        return;
+    }
+
+    if Path::new(&args.output).exists() {
+        eprintln!("❌ Output file {} already exists.", args.output);
+        process::exit(1);
+    }
+
+    let result = Quiver::new(&args.input, "r").and_then(|input| {
+        let mut output = match args.format {
+            Format::Qv1 => Quiver::with_storage(Box::new(LocalFile::buffered(&args.output)), "w")?,
+            Format::Qv2 => Quiver::with_storage(Box::new(V2File::new(&args.output, false)?), "w")?,
+            Format::Qv2Zstd => Quiver::with_storage(Box::new(V2File::new(&args.output, false)?.zstd()), "w")?,
+        };
+        let checksums = convert(&input, &mut output)?;
+        output.flush()?;
+        verify(&Quiver::new(&args.output, "r")?, &checksums)
+    });
+
+    match result {
+        Ok(report) => println!(
+            "✅ Converted and verified {} entries into {}",
+            report.entries, args.output
+        ),
+        Err(e) => {
+            eprintln!("❌ Error: {:?}", e);
+            process::exit(e.exit_code());
+        }
+    }
+}
+
+fn upload(args: &Args) {
+    if args.format != Format::Qv1 {
+        eprintln!("❌ Only qv1 archives can be written to S3.");
+        process::exit(1);
+    }
+    let result = Quiver::new(&args.input, "r").and_then(|input| {
+        let mut output = Quiver::new(&args.output, "w")?;
+        let checksums = convert(&input, &mut output)?;
+        output.finish()?;
+        Ok(checksums.entries.len())
+    });
+
+    match result {
+        Ok(entries) => println!("✅ Converted and uploaded {} entries to {}", entries, args.output),
+        Err(e) => {
+            eprintln!("❌ Error: {:?}", e);
+            process::exit(e.exit_code());
+        }
+    }
+}
@@ -0,0 +1,49 @@
+use clap::Parser;
+use std::process;
+
+use quiver::dedup::{dedup, Keep};
+use quiver::QuiverError;
+
+/// Keep one entry per tag in a Quiver file.
+///
+/// Appending the output of several runs to one archive can leave the same
+/// tag on more than one entry. The first (or, with `--keep last`, the last)
+/// entry of each tag is kept and the others are dropped and listed on
+/// stderr. Without `--output` the file is rewritten atomically in place.
+///
+/// Usage:
+///     qvdedup merged.qv
+///     qvdedup merged.qv --keep last --output clean.qv
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// Quiver file to deduplicate
+    quiver_file: String,
+
+    /// Which entry of a repeated tag to keep (first, last)
+    #[arg(long, default_value = "first")]
+    keep: Keep,
+
+    /// Write the result to this new file instead of rewriting QUIVER_FILE
+    #[arg(short, long)]
+    output: Option<String>,
+}
+
+fn main() {
+    let args = Args::parse();
+
+    if let Err(e) = run(&args) {
+        eprintln!("❌ Error: {:?}", e);
+        process::exit(e.exit_code());
+    }
+}
+
+fn run(args: &Args) -> Result<(), QuiverError> {
+    let dropped = dedup(&args.quiver_file, args.output.as_deref(), args.keep)?;
+    for entry in &dropped {
+        eprintln!("🗑️  Dropped entry {} tagged {}", entry.position + 1, entry.tag);
+    }
+    let target = args.output.as_deref().unwrap_or(&args.quiver_file);
+    println!("✅ Dropped {} duplicate entries, wrote {}", dropped.len(), target);
+    Ok(())
+}
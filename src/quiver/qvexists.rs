@@ -0,0 +1,69 @@
+use clap::Parser;
+use std::io::{self, Read};
+use std::process;
+
+use quiver::Quiver;
+
+/// Check whether tags exist in a Quiver file.
+///
+/// Prints `tag<TAB>true|false` for every requested tag, or only the missing
+/// tags with `--missing`, so workflow rules can decide what to regenerate.
+///
+/// Usage:
+///     qvexists my.qv tag1 tag2
+///     cat wanted.txt | qvexists my.qv - --missing > todo.txt
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// Quiver file to check
+    quiver_file: String,
+
+    /// Tags to check; `-` reads more, whitespace-separated, from stdin
+    tags: Vec<String>,
+
+    /// Only print the tags that are missing
+    #[arg(long)]
+    missing: bool,
+}
+
+fn main() {
+    let args = Args::parse();
+
+    // Collect tags from CLI, and from stdin only when asked to with "-", so
+    // an open but silent stdin (cron, notebooks) cannot block the tool
+    let read_stdin = args.tags.iter().any(|t| t == "-");
+    let mut tag_list: Vec<String> = args.tags.iter().filter(|t| *t != "-").cloned().collect();
+    if read_stdin {
+        let mut stdin_data = String::new();
+        if let Err(e) = io::stdin().read_to_string(&mut stdin_data) {
+            eprintln!("❌ Failed to read from stdin: {}", e);
+            process::exit(1);
+        }
+        tag_list.extend(stdin_data.split_whitespace().map(|s| s.to_string()));
+    }
+
+    // An empty list piped on stdin is a no-op, not an error
+    if tag_list.is_empty() && !read_stdin {
+        eprintln!("❌ No tags provided. Provide tags as arguments, or - to read them from stdin.");
+        process::exit(1);
+    }
+
+    // A missing file simply has no tags
+    let qv = match Quiver::new(&args.quiver_file, "r") {
+        Ok(q) => q,
+        Err(e) => {
+            eprintln!("❌ Failed to open Quiver file: {:?}", e);
+            process::exit(e.exit_code());
+        }
+    };
+
+    for (tag, exists) in qv.tags_exist(&tag_list) {
+        if args.missing {
+            if !exists {
+                println!("{}", tag);
+            }
+        } else {
+            println!("{}\t{}", tag, exists);
+        }
+    }
+}
@@ -4,38 +4,93 @@ This is a command-line tool to extract all PDB files from a Quiver file.
 
 Usage:
     qvextract.py <quiver_file>
+    qvextract.py <quiver_file> --threads 8
+    qvextract.py <quiver_file> -o designs/ --layout per-tag
+    qvextract.py <quiver_file> --records ATOM,HETATM,TER,END
+    qvextract.py <quiver_file> --best-first plddt
+    qvextract.py <quiver_file> --best-first plddt --max-output-bytes 50000000000
 """
 
-import os
+import sys
+
 import click
-from quiver import Quiver
+from quiver import EXIT_QUOTA_REACHED, Warnings, extract_pdbs as extract_entries
 
 
 @click.command()
 @click.argument("quiver_file", type=click.Path(exists=True, dir_okay=False))
-def extract_pdbs(quiver_file):
+@click.option(
+    "--threads",
+    default=1,
+    show_default=True,
+    type=click.IntRange(min=1),
+    help="Threads reading and writing entries (more than 1 builds the tag index)",
+)
+@click.option(
+    "--output-dir",
+    "-o",
+    default=".",
+    show_default=True,
+    type=click.Path(file_okay=False),
+    help="Directory to save extracted PDB files",
+)
+@click.option(
+    "--layout",
+    default="flat",
+    show_default=True,
+    type=click.Choice(["flat", "per-tag"]),
+    help="DIR/<tag>.pdb (flat) or DIR/<tag>/model.pdb with a meta.json (per-tag)",
+)
+@click.option(
+    "--records",
+    default=None,
+    help="Only keep these PDB record types, e.g. ATOM,HETATM,TER,END to drop ANISOU and REMARK",
+)
+@click.option(
+    "--best-first",
+    default=None,
+    metavar="FIELD",
+    help="Extract in descending order of this score, so a job cut short has the best designs",
+)
+@click.option(
+    "--ascending",
+    is_flag=True,
+    help="With --best-first, lowest scores first (for energies)",
+)
+@click.option(
+    "--max-output-bytes",
+    default=None,
+    type=click.IntRange(min=0),
+    help="Stop before the PDB file that would take the bytes written past this (exits with status 6)",
+)
+def extract_pdbs(quiver_file, threads, output_dir, layout, records, best_first, ascending, max_output_bytes):
     """
     Extract all PDB files from a Quiver file.
     """
-    qv = Quiver(quiver_file, "r")
-
-    for tag in qv.get_tags():
-        # Sanitize tag to prevent path traversal
-        safe_tag = os.path.basename(tag)
-        outfn = f"{safe_tag}.pdb"
-
-        if os.path.exists(outfn):
-            click.echo(f"⚠️  File {outfn} already exists, skipping")
-            continue
-
-        lines = qv.get_pdblines(tag)
-        with open(outfn, "w") as f:
-            f.writelines(lines)
-
+    if max_output_bytes is not None and threads > 1:
+        raise click.UsageError("--max-output-bytes needs --threads 1")
+    warnings = Warnings()
+    written = extract_entries(
+        quiver_file,
+        output_dir=output_dir,
+        threads=threads,
+        layout=layout,
+        record_filter=records.split(",") if records else None,
+        warnings=warnings,
+        best_first=best_first,
+        ascending=ascending,
+        max_output_bytes=max_output_bytes,
+    )
+    for outfn in written:
         click.echo(f"✅ Extracted {outfn}")
+    warnings.report()
+
+    if warnings.with_code("output-quota"):
+        click.echo(f"⛔ Stopped after {len(written)} PDB files from {quiver_file}", err=True)
+        sys.exit(EXIT_QUOTA_REACHED)
 
     click.secho(
-        f"\n🎉 Successfully extracted {qv.size()} PDB files from {quiver_file}",
+        f"\n🎉 Successfully extracted {len(written)} PDB files from {quiver_file}",
         fg="green",
     )
 
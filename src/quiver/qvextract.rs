@@ -1,56 +1,379 @@
 use clap::Parser;
+use std::collections::HashSet;
 use std::fs::{self, File};
 use std::io::Write;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::process;
+use std::thread;
 
-mod quiver;
-use quiver::{Quiver, QuiverError};
+use quiver::cif::expand_assembly;
+use quiver::layout::{write_meta, ExtractLayout};
+use quiver::linkcache::{ExtractCache, LinkMode};
+use quiver::rank::{Order, RankKey};
+use quiver::transform::{clean_altloc, HeaderPolicy, RecordFilter};
+use quiver::warnings::{WarningCode, Warnings};
+use quiver::{PayloadFormat, Quiver, QuiverError, EXIT_QUOTA_REACHED};
 
 /// Extract all PDB files from a Quiver file.
+///
+/// mmCIF entries are written as `<tag>.cif`; `--expand-assembly` writes a
+/// biological assembly instead of the asymmetric unit. `--layout per-tag`
+/// writes `<dir>/<tag>/model.pdb` with a `meta.json` of scores, labels,
+/// provenance and checksum beside it.
+///
+/// An entry cut short at the end of the file, by a writer that is still
+/// running or was killed, is skipped with a warning unless
+/// `--include-partial` is given.
+///
+/// `--best-first FIELD` extracts entries by descending FIELD score
+/// (ascending with `--ascending`), taking them from a priority queue built
+/// from the score lines and reading each through its offset, so a job cut
+/// short by `--max-output-bytes`, a disk quota or a time limit has the best
+/// designs. Entries without the score come last.
+///
+/// Stopping at the `--max-output-bytes` quota is reported on stderr and
+/// exits with status 6 instead of 0.
+///
+/// Usage:
+///     qvextract my.qv
+///     qvextract my.qv --clean-altloc A
+///     qvextract my.qv --headers strip
+///     qvextract my.qv --records ATOM,HETATM,TER,END
+///     qvextract my.qv --threads 8
+///     qvextract my.qv -o designs/ --layout per-tag
+///     qvextract deposited.qv --expand-assembly 1
+///     qvextract my.qv --label round3
+///     qvextract my.qv --cache /scratch/qvcache
+///     qvextract running.qv --include-partial
+///     qvextract my.qv --best-first plddt --max-output-bytes 50000000000
+///     qvextract my.qv --best-first ddg --ascending
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 struct Args {
     /// Path to the Quiver file
     quiver_file: String,
+
+    /// Directory to save extracted files
+    #[arg(short, long, default_value = ".", value_name = "DIR")]
+    output_dir: String,
+
+    /// Where entries are written: DIR/<tag>.pdb (flat) or DIR/<tag>/model.pdb and meta.json (per-tag)
+    #[arg(long, default_value = "flat")]
+    layout: ExtractLayout,
+
+    /// Stop before the extracted files would exceed this many bytes in total
+    #[arg(long, value_name = "BYTES")]
+    max_output_bytes: Option<u64>,
+
+    /// Keep one alternate location per atom (preferring KEEP) and set its occupancy to 1.00
+    #[arg(long, value_name = "KEEP")]
+    clean_altloc: Option<char>,
+
+    /// What to do with header records (HEADER, COMPND, SEQRES, ...) of PDB entries (keep, strip)
+    #[arg(long, default_value = "keep", value_name = "POLICY")]
+    headers: HeaderPolicy,
+
+    /// Only keep these PDB record types, e.g. ATOM,HETATM,TER,END to drop ANISOU and REMARK
+    #[arg(long, value_name = "RECORDS")]
+    records: Option<RecordFilter>,
+
+    /// For mmCIF entries, write biological assembly ID instead of the asymmetric unit
+    #[arg(long, value_name = "ID")]
+    expand_assembly: Option<String>,
+
+    /// Only extract entries carrying this label
+    #[arg(long)]
+    label: Option<String>,
+
+    /// Write each distinct file once into DIR and reflink (or hard link) outputs to it
+    #[arg(long, value_name = "DIR")]
+    cache: Option<String>,
+
+    /// How outputs are created from the cache (reflink, hardlink)
+    #[arg(long, default_value = "reflink", requires = "cache")]
+    link: LinkMode,
+
+    /// Number of threads reading from the shared file handle
+    #[arg(long, default_value_t = 1, conflicts_with_all = ["max_output_bytes", "best_first"])]
+    threads: usize,
+
+    /// Extract entries in descending order of this score, best first
+    #[arg(long, value_name = "FIELD")]
+    best_first: Option<String>,
+
+    /// With --best-first, lowest scores first (for energies)
+    #[arg(long, requires = "best_first")]
+    ascending: bool,
+
+    /// Also extract an entry cut short at the end of the file
+    #[arg(long)]
+    include_partial: bool,
 }
 
 fn main() {
     let args = Args::parse();
 
-    if let Err(e) = extract_pdbs(&args.quiver_file) {
-        eprintln!("❌ Error: {:?}", e);
+    let cache = match &args.cache {
+        Some(dir) => match ExtractCache::new(dir, args.link) {
+            Ok(cache) => Some(cache),
+            Err(e) => {
+                eprintln!("❌ Failed to create cache directory {}: {}", dir, e);
+                process::exit(1);
+            }
+        },
+        None => None,
+    };
+    if let Err(e) = fs::create_dir_all(&args.output_dir) {
+        eprintln!("❌ Failed to create output directory {}: {}", args.output_dir, e);
         process::exit(1);
     }
+    let transforms = Transforms {
+        source: PathBuf::from(&args.quiver_file),
+        output_dir: PathBuf::from(&args.output_dir),
+        layout: args.layout,
+        clean_altloc: args.clean_altloc,
+        headers: args.headers,
+        records: args.records.clone(),
+        expand_assembly: args.expand_assembly.clone(),
+        cache,
+    };
+    let result = if args.threads > 1 {
+        extract_pdbs_parallel(&args, &transforms)
+    } else {
+        extract_pdbs(&args, &transforms)
+    };
+    if let Err(e) = result {
+        eprintln!("❌ Error: {:?}", e);
+        process::exit(e.exit_code());
+    }
+}
+
+/// Per-entry rewrites applied on extraction, and how outputs are written.
+struct Transforms {
+    /// The archive extracted from, recorded in metadata files
+    source: PathBuf,
+    output_dir: PathBuf,
+    layout: ExtractLayout,
+    /// altLoc to keep, for PDB entries
+    clean_altloc: Option<char>,
+    /// Header records of PDB entries
+    headers: HeaderPolicy,
+    /// Record types kept, for PDB entries
+    records: Option<RecordFilter>,
+    /// Assembly to expand, for mmCIF entries
+    expand_assembly: Option<String>,
+    cache: Option<ExtractCache>,
+}
+
+/// Create `outfn` with `contents`, through the cache if there is one, and
+/// the metadata file of `tag` if the layout has one.
+fn write_output(
+    qv: &Quiver,
+    tag: &str,
+    outfn: &Path,
+    contents: &[u8],
+    transforms: &Transforms,
+) -> Result<(), QuiverError> {
+    if let Some(parent) = outfn.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    match &transforms.cache {
+        Some(cache) => {
+            cache.place(contents, outfn)?;
+        }
+        None => File::create(outfn)?.write_all(contents)?,
+    }
+    if transforms.layout == ExtractLayout::PerTag {
+        let text = String::from_utf8_lossy(contents);
+        let lines: Vec<&str> = text.lines().collect();
+        write_meta(qv, &transforms.source, tag, outfn, &lines)?;
+    }
+    Ok(())
+}
+
+/// Open `quiver_file`, warning about an entry cut short at its end unless
+/// `include_partial` asks for it to be extracted too.
+fn open_archive(quiver_file: &str, include_partial: bool) -> Result<Quiver, QuiverError> {
+    let mut qv = Quiver::new(quiver_file, "r")?;
+    if include_partial {
+        qv.set_include_partial(true);
+    } else {
+        qv.take_warnings().report();
+    }
+    Ok(qv)
 }
 
-fn extract_pdbs(quiver_file: &str) -> Result<(), QuiverError> {
-    let qv = Quiver::new(quiver_file, "r")?;
+/// The tags to extract: all of them, or those carrying `label`.
+fn selected_tags(qv: &Quiver, label: Option<&str>) -> Vec<String> {
+    match label {
+        Some(label) => qv.list_by_label(label),
+        None => qv.get_tags(),
+    }
+}
+
+/// The tags to extract in the order they are extracted: file order, or
+/// best first by the `--best-first` score, ranked as they are taken.
+fn extraction_order(qv: &Quiver, args: &Args) -> Result<Box<dyn Iterator<Item = String>>, QuiverError> {
+    let tags = selected_tags(qv, args.label.as_deref());
+    let Some(field) = &args.best_first else {
+        return Ok(Box::new(tags.into_iter()));
+    };
+    let key = RankKey::new(field, if args.ascending { Order::Asc } else { Order::Desc });
+    let ranked = qv.best_first(&key)?;
+    if args.label.is_none() {
+        return Ok(Box::new(ranked));
+    }
+    let selected: HashSet<String> = tags.into_iter().collect();
+    Ok(Box::new(ranked.filter(move |tag| selected.contains(tag))))
+}
+
+fn extract_pdbs(args: &Args, transforms: &Transforms) -> Result<(), QuiverError> {
+    let qv = open_archive(&args.quiver_file, args.include_partial)?;
+    let max_output_bytes = args.max_output_bytes;
+    let mut extracted_count = 0;
+    let mut bytes_written = 0u64;
+    let mut warnings = Warnings::new();
 
-    for tag in qv.get_tags() {
-        let outfn = format!("{}.pdb", tag);
+    for tag in extraction_order(&qv, args)? {
+        let outfn = output_path(&qv, &tag, transforms)?;
 
-        if Path::new(&outfn).exists() {
-            println!("⚠️  File {} already exists, skipping", outfn);
+        if outfn.exists() {
+            warnings.push(
+                WarningCode::SkippedFile,
+                format!("File {} already exists, skipping", outfn.display()),
+            );
             continue;
         }
 
-        let lines = qv.get_pdblines(&tag)?;
-        let mut file = File::create(&outfn)?;
-        for line in lines {
-            file.write_all(line.as_bytes())?;
-            if !line.ends_with('\n') {
-                file.write_all(b"\n")?;
+        let contents = pdb_contents(&qv, &tag, transforms)?;
+
+        if let Some(max) = max_output_bytes {
+            if bytes_written + contents.len() as u64 > max {
+                warnings.push(
+                    WarningCode::OutputQuota,
+                    format!("Output quota of {} bytes reached, stopping before {}", max, outfn.display()),
+                );
+                break;
+            }
+        }
+
+        write_output(&qv, &tag, &outfn, &contents, transforms)?;
+        bytes_written += contents.len() as u64;
+        extracted_count += 1;
+
+        println!("✅ Extracted {}", outfn.display());
+    }
+
+    warnings.report();
+    if warnings.with_code(WarningCode::OutputQuota).next().is_some() {
+        eprintln!(
+            "⛔ Stopped after {} PDB files ({} bytes) from {}",
+            extracted_count, bytes_written, args.quiver_file
+        );
+        process::exit(EXIT_QUOTA_REACHED);
+    }
+    println!(
+        "\n🎉 Successfully extracted {} PDB files ({} bytes) from {}",
+        extracted_count, bytes_written, args.quiver_file
+    );
+
+    Ok(())
+}
+
+/// Path `tag` is extracted to, with the extension of its payload format.
+fn output_path(qv: &Quiver, tag: &str, transforms: &Transforms) -> Result<PathBuf, QuiverError> {
+    Ok(transforms.layout.model_path(&transforms.output_dir, tag, qv.get_format(tag)?))
+}
+
+/// The file contents for `tag`, one newline-terminated line per record.
+///
+/// The record filter, altLoc cleanup and header policy only apply to PDB
+/// entries and assembly expansion only to mmCIF entries.
+fn pdb_contents(qv: &Quiver, tag: &str, transforms: &Transforms) -> Result<Vec<u8>, QuiverError> {
+    let format = qv.get_format(tag)?;
+    let mut lines = match (&transforms.records, format) {
+        (Some(filter), PayloadFormat::Pdb) => qv.get_lines_filtered(tag, filter)?,
+        _ => qv.get_lines(tag)?,
+    };
+    match format {
+        PayloadFormat::Pdb => {
+            if let Some(keep) = transforms.clean_altloc {
+                lines = clean_altloc(&lines, keep);
             }
+            lines = transforms.headers.apply(lines);
         }
+        PayloadFormat::Cif => {
+            if let Some(assembly) = &transforms.expand_assembly {
+                lines = expand_assembly(&lines, assembly)
+                    .map_err(|e| QuiverError::InvalidOperation(format!("{}: {}", tag, e)))?;
+            }
+        }
+    }
+    let mut contents = Vec::new();
+    for line in lines {
+        contents.extend_from_slice(line.as_bytes());
+        if !line.ends_with('\n') {
+            contents.push(b'\n');
+        }
+    }
+    Ok(contents)
+}
+
+/// Extract with several threads sharing one read handle.
+fn extract_pdbs_parallel(args: &Args, transforms: &Transforms) -> Result<(), QuiverError> {
+    let qv = open_archive(&args.quiver_file, args.include_partial)?;
+    let threads = args.threads;
+    let tags = selected_tags(&qv, args.label.as_deref());
+    let chunk = tags.len().div_ceil(threads).max(1);
+
+    let results: Vec<Result<(usize, u64, Warnings), QuiverError>> = thread::scope(|s| {
+        let workers: Vec<_> = tags
+            .chunks(chunk)
+            .map(|chunk| {
+                let qv = &qv;
+                s.spawn(move || {
+                    let mut count = 0;
+                    let mut bytes = 0u64;
+                    let mut warnings = Warnings::new();
+                    for tag in chunk {
+                        let outfn = output_path(qv, tag, transforms)?;
+                        if outfn.exists() {
+                            warnings.push(
+                                WarningCode::SkippedFile,
+                                format!("File {} already exists, skipping", outfn.display()),
+                            );
+                            continue;
+                        }
+                        let contents = pdb_contents(qv, tag, transforms)?;
+                        write_output(qv, tag, &outfn, &contents, transforms)?;
+                        bytes += contents.len() as u64;
+                        count += 1;
+                        println!("✅ Extracted {}", outfn.display());
+                    }
+                    Ok((count, bytes, warnings))
+                })
+            })
+            .collect();
+        workers
+            .into_iter()
+            .map(|w| w.join().expect("extraction thread panicked"))
+            .collect()
+    });
 
-        println!("✅ Extracted {}", outfn);
+    let mut extracted_count = 0;
+    let mut bytes_written = 0u64;
+    let mut warnings = Warnings::new();
+    for result in results {
+        let (count, bytes, chunk_warnings) = result?;
+        extracted_count += count;
+        bytes_written += bytes;
+        warnings.extend(chunk_warnings);
     }
+    warnings.report();
 
     println!(
-        "\n🎉 Successfully extracted {} PDB files from {}",
-        qv.size(),
-        quiver_file
+        "\n🎉 Successfully extracted {} PDB files ({} bytes) from {} using {} threads",
+        extracted_count, bytes_written, args.quiver_file, threads
     );
 
     Ok(())
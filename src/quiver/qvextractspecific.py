@@ -4,14 +4,13 @@ This is a command-line tool to extract specific PDB files from a Quiver file.
 
 Usage:
     qvextractspecific.py [OPTIONS] <quiver_file> [tag1 tag2 ...]
-    cat tags.txt | qvextractspecific.py [OPTIONS] <quiver_file>
+    cat tags.txt | qvextractspecific.py [OPTIONS] <quiver_file> -
 """
 
 import os
 import sys
-import stat
 import click
-from quiver import Quiver
+from quiver import Quiver, Warnings
 
 
 @click.command()
@@ -28,12 +27,13 @@ def extract_selected_pdbs(quiver_file, tags, output_dir):
     """
     Extract specific PDB files from a Quiver file.
 
-    Tags can be passed as command-line arguments or via stdin (piped).
+    Tags can be passed as command-line arguments, or via stdin with -.
     """
-    tag_buffers = list(tags)
+    tag_buffers = [tag for tag in tags if tag != "-"]
 
-    # Check if input is being piped via stdin
-    if not sys.stdin.isatty() and stat.S_ISFIFO(os.fstat(0).st_mode):
+    # Read stdin only when asked to, so a silent stdin (cron, notebooks)
+    # never blocks
+    if "-" in tags:
         stdin_tags = [line.strip() for line in sys.stdin.readlines()]
         for line in stdin_tags:
             tag_buffers.extend(line.split())
@@ -50,6 +50,7 @@ def extract_selected_pdbs(quiver_file, tags, output_dir):
 
     qv = Quiver(quiver_file, "r")
     extracted_count = 0
+    warnings = Warnings()
 
     for tag in unique_tags:
         # Sanitize tag to prevent path traversal
@@ -57,15 +58,13 @@ def extract_selected_pdbs(quiver_file, tags, output_dir):
         outfn = os.path.join(output_dir, f"{safe_tag}.pdb")
 
         if os.path.exists(outfn):
-            click.echo(f"⚠️  File {outfn} already exists, skipping")
+            warnings.push("skipped-file", f"File {outfn} already exists, skipping")
             continue
 
         try:
             lines = qv.get_pdblines(tag)
         except KeyError:
-            click.secho(
-                f"❌ Could not find tag {tag} in Quiver file, skipping", fg="yellow"
-            )
+            warnings.push("missing-tag", f"Tag not found in Quiver file: {tag}")
             continue
 
         with open(outfn, "w") as f:
@@ -74,6 +73,7 @@ def extract_selected_pdbs(quiver_file, tags, output_dir):
         click.echo(f"✅ Extracted {outfn}")
         extracted_count += 1
 
+    warnings.report()
     click.secho(
         f"\n🎉 Successfully extracted {extracted_count} PDB file(s) from {quiver_file} to {output_dir}",
         fg="green",
@@ -2,32 +2,69 @@ use clap::{Parser};
 use std::collections::HashSet;
 use std::fs::{self, File};
 use std::io::{self, BufRead, Write};
-use std::path::{Path, PathBuf};
+use std::path::Path;
 use std::process;
 
-mod quiver;
-use quiver::{Quiver, QuiverError};
+use quiver::cif::expand_assembly;
+use quiver::linkcache::{ExtractCache, LinkMode};
+use quiver::transform::{clean_altloc, HeaderPolicy, RecordFilter};
+use quiver::warnings::{WarningCode, Warnings};
+use quiver::{PayloadFormat, Quiver, QuiverError, EXIT_QUOTA_REACHED};
 
 // This is a command-line tool to extract specific PDB files from a Quiver file.
 
 // Usage:
 //     qvextractspecific.py [OPTIONS] <quiver_file> [tag1 tag2 ...]
-//     cat tags.txt | qvextractspecific.py [OPTIONS] <quiver_file>
+//     cat tags.txt | qvextractspecific.py [OPTIONS] <quiver_file> -
 /// Extract specific PDB files from a Quiver file.
 ///
-/// Tags can be passed as command-line arguments or via stdin (piped).
+/// Tags can be passed as command-line arguments, or via stdin with `-`.
+/// Stopping at the `--max-output-bytes` quota is reported on stderr and
+/// exits with status 6 instead of 0.
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 struct Args {
     /// Path to the Quiver file
     quiver_file: String,
 
-    /// Tags to extract (can be empty if piped via stdin)
+    /// Tags to extract; `-` reads more, whitespace-separated, from stdin
     tags: Vec<String>,
 
     /// Directory to save extracted PDB files
     #[arg(short, long, default_value = ".", value_name = "DIR")]
     output_dir: String,
+
+    /// Stop before the extracted files would exceed this many bytes in total
+    #[arg(long, value_name = "BYTES")]
+    max_output_bytes: Option<u64>,
+
+    /// Keep one alternate location per atom (preferring KEEP) and set its occupancy to 1.00
+    #[arg(long, value_name = "KEEP")]
+    clean_altloc: Option<char>,
+
+    /// What to do with header records (HEADER, COMPND, SEQRES, ...) of PDB entries (keep, strip)
+    #[arg(long, default_value = "keep", value_name = "POLICY")]
+    headers: HeaderPolicy,
+
+    /// Only keep these PDB record types, e.g. ATOM,HETATM,TER,END to drop ANISOU and REMARK
+    #[arg(long, value_name = "RECORDS")]
+    records: Option<RecordFilter>,
+
+    /// Also extract every entry carrying this label
+    #[arg(long)]
+    label: Option<String>,
+
+    /// For mmCIF entries, write biological assembly ID instead of the asymmetric unit
+    #[arg(long, value_name = "ID")]
+    expand_assembly: Option<String>,
+
+    /// Write each distinct file once into DIR and reflink (or hard link) outputs to it
+    #[arg(long, value_name = "DIR")]
+    cache: Option<String>,
+
+    /// How outputs are created from the cache (reflink, hardlink)
+    #[arg(long, default_value = "reflink", requires = "cache")]
+    link: LinkMode,
 }
 
 fn main() {
@@ -35,16 +72,16 @@ fn main() {
 
     if let Err(e) = extract_selected_pdbs(&args) {
         eprintln!("❌ Error: {:?}", e);
-        process::exit(1);
+        process::exit(e.exit_code());
     }
 }
 
 fn extract_selected_pdbs(args: &Args) -> Result<(), QuiverError> {
-    // Collect tags from CLI and possibly from stdin (piped)
-    let mut tag_buffers: Vec<String> = args.tags.clone();
-
-    // Check if stdin is piped (not a tty)
-    if !atty::is(atty::Stream::Stdin) {
+    // Collect tags from CLI, and from stdin only when asked to with "-", so
+    // an open but silent stdin (cron, notebooks) cannot block the tool
+    let read_stdin = args.tags.iter().any(|t| t == "-");
+    let mut tag_buffers: Vec<String> = args.tags.iter().filter(|t| *t != "-").cloned().collect();
+    if read_stdin {
         let stdin = io::stdin();
         for line in stdin.lock().lines() {
             let line = line?;
@@ -52,6 +89,11 @@ fn extract_selected_pdbs(args: &Args) -> Result<(), QuiverError> {
         }
     }
 
+    let qv = Quiver::new(&args.quiver_file, "r")?;
+    if let Some(label) = &args.label {
+        tag_buffers.extend(qv.list_by_label(label));
+    }
+
     // Clean and deduplicate tags
     let mut unique_tags: Vec<String> = tag_buffers
         .into_iter()
@@ -61,7 +103,8 @@ fn extract_selected_pdbs(args: &Args) -> Result<(), QuiverError> {
         .collect();
     unique_tags.sort();
 
-    if unique_tags.is_empty() {
+    // An empty list piped on stdin is a no-op, not an error
+    if unique_tags.is_empty() && args.label.is_none() && !read_stdin {
         eprintln!("❗ No tags provided.");
         process::exit(1);
     }
@@ -69,38 +112,94 @@ fn extract_selected_pdbs(args: &Args) -> Result<(), QuiverError> {
     // Ensure output directory exists
     fs::create_dir_all(&args.output_dir)?;
 
-    let qv = Quiver::new(&args.quiver_file, "r")?;
+    let cache = match &args.cache {
+        Some(dir) => Some(ExtractCache::new(dir, args.link)?),
+        None => None,
+    };
     let mut extracted_count = 0;
+    let mut bytes_written = 0u64;
+    let mut warnings = Warnings::new();
 
     for tag in &unique_tags {
-        let outfn = Path::new(&args.output_dir).join(format!("{}.pdb", tag));
+        let format = match qv.get_format(tag) {
+            Ok(format) => format,
+            Err(QuiverError::TagNotFound(_)) => {
+                warnings.push(WarningCode::MissingTag, format!("Tag not found in Quiver file: {}", tag));
+                continue;
+            }
+            Err(e) => return Err(e),
+        };
+        let outfn = Path::new(&args.output_dir).join(format!("{}.{}", tag, format.extension()));
         if outfn.exists() {
-            println!("⚠️  File {} already exists, skipping", outfn.display());
+            warnings.push(
+                WarningCode::SkippedFile,
+                format!("File {} already exists, skipping", outfn.display()),
+            );
             continue;
         }
 
-        match qv.get_pdblines(tag) {
-            Ok(lines) => {
-                let mut file = File::create(&outfn)?;
+        let lines = match (&args.records, format) {
+            (Some(filter), PayloadFormat::Pdb) => qv.get_lines_filtered(tag, filter),
+            _ => qv.get_lines(tag),
+        };
+        match lines {
+            Ok(mut lines) => {
+                match (format, args.clean_altloc, &args.expand_assembly) {
+                    (PayloadFormat::Pdb, Some(keep), _) => lines = clean_altloc(&lines, keep),
+                    (PayloadFormat::Cif, _, Some(assembly)) => {
+                        lines = expand_assembly(&lines, assembly)
+                            .map_err(|e| QuiverError::InvalidOperation(format!("{}: {}", tag, e)))?;
+                    }
+                    _ => {}
+                }
+                if format == PayloadFormat::Pdb {
+                    lines = args.headers.apply(lines);
+                }
+                let mut contents = Vec::new();
                 for line in lines {
-                    file.write_all(line.as_bytes())?;
+                    contents.extend_from_slice(line.as_bytes());
                     if !line.ends_with('\n') {
-                        file.write_all(b"\n")?;
+                        contents.push(b'\n');
+                    }
+                }
+                if let Some(max) = args.max_output_bytes {
+                    if bytes_written + contents.len() as u64 > max {
+                        warnings.push(
+                            WarningCode::OutputQuota,
+                            format!("Output quota of {} bytes reached, stopping before {}", max, outfn.display()),
+                        );
+                        break;
                     }
                 }
+                match &cache {
+                    Some(cache) => {
+                        cache.place(&contents, &outfn)?;
+                    }
+                    None => File::create(&outfn)?.write_all(&contents)?,
+                }
+                bytes_written += contents.len() as u64;
                 println!("✅ Extracted {}", outfn.display());
                 extracted_count += 1;
             }
             Err(QuiverError::TagNotFound(_)) => {
-                println!("❌ Could not find tag {} in Quiver file, skipping", tag);
+                warnings.push(WarningCode::MissingTag, format!("Tag not found in Quiver file: {}", tag));
             }
             Err(e) => return Err(e),
         }
     }
 
+    warnings.report();
+    if warnings.with_code(WarningCode::OutputQuota).next().is_some() {
+        eprintln!(
+            "⛔ Stopped after {} PDB file(s) ({} bytes) from {} to {}",
+            extracted_count, bytes_written, args.quiver_file, args.output_dir
+        );
+        process::exit(EXIT_QUOTA_REACHED);
+    }
     println!(
-        "\n🎉 Successfully extracted {} PDB file(s) from {} to {}",
+        "\n🎉 Successfully extracted {} PDB file(s) ({} bytes) from {} to {}",
         extracted_count,
+        bytes_written,
         args.quiver_file,
         args.output_dir
     );
@@ -0,0 +1,70 @@
+use clap::Parser;
+use std::fs::{self, File};
+use std::io::{self, BufWriter};
+use std::process;
+
+use quiver::sequence::{write_fasta, SequenceSource};
+use quiver::Quiver;
+
+/// Write the one-letter sequence of every chain of every structure as FASTA.
+///
+/// Records are headed `>tag_chain` and read from the ATOM records, or with
+/// `--source seqres` from the SEQRES records where a structure has them.
+/// mmCIF entries are skipped.
+///
+/// Usage:
+///     qvfasta my.qv > my.fasta
+///     qvfasta my.qv --chains A --output binders.fasta
+///     qvfasta my.qv --source seqres > constructs.fasta
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// Quiver file to read
+    quiver_file: String,
+
+    /// Write to this file instead of stdout
+    #[arg(short, long, value_name = "FASTA")]
+    output: Option<String>,
+
+    /// Only write these chains (comma-separated)
+    #[arg(long, value_name = "CHAINS", value_delimiter = ',')]
+    chains: Vec<String>,
+
+    /// Records to read sequences from (atom, seqres)
+    #[arg(long, default_value = "atom")]
+    source: SequenceSource,
+}
+
+fn main() {
+    let args = Args::parse();
+
+    let qv = match Quiver::new(&args.quiver_file, "r") {
+        Ok(q) => q,
+        Err(e) => {
+            eprintln!("❌ Failed to open Quiver file: {:?}", e);
+            process::exit(e.exit_code());
+        }
+    };
+    let chains = (!args.chains.is_empty()).then_some(args.chains.as_slice());
+
+    let result = match &args.output {
+        Some(path) => File::create(path)
+            .map_err(Into::into)
+            .and_then(|file| write_fasta(&qv, BufWriter::new(file), chains, args.source)),
+        None => write_fasta(&qv, io::stdout().lock(), chains, args.source),
+    };
+    match result {
+        Ok(written) => {
+            if let Some(path) = &args.output {
+                println!("✅ Wrote {} sequences to {}", written, path);
+            }
+        }
+        Err(e) => {
+            if let Some(path) = &args.output {
+                let _ = fs::remove_file(path);
+            }
+            eprintln!("❌ Failed to write sequences: {:?}", e);
+            process::exit(e.exit_code());
+        }
+    }
+}
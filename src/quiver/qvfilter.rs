@@ -0,0 +1,54 @@
+use clap::Parser;
+use std::process;
+
+use quiver::expr::Expr;
+use quiver::scores::DuplicatePolicy;
+use quiver::Quiver;
+
+/// Print the tags whose scores satisfy an expression.
+///
+/// Expressions support comparisons, arithmetic, `and`/`or`/`not` and
+/// `has(field)`. Fields missing from an entry make comparisons false;
+/// `true`/`false` fields can be used as conditions directly.
+///
+/// Usage:
+///     qvfilter my.qv 'plddt > 85 and rmsd < 1.5' | qvslice my.qv - > good.qv
+///     qvfilter my.qv 'not has(pae)' | wc -l
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// Quiver file to filter
+    quiver_file: String,
+
+    /// Score expression
+    expression: Expr,
+
+    /// Which value to use when a score line repeats a field (first, last, error, warn)
+    #[arg(long, default_value = "last", value_name = "POLICY")]
+    duplicate_fields: DuplicatePolicy,
+}
+
+fn main() {
+    let args = Args::parse();
+
+    let mut qv = match Quiver::new(&args.quiver_file, "r") {
+        Ok(q) => q,
+        Err(e) => {
+            eprintln!("❌ Failed to open Quiver file: {:?}", e);
+            process::exit(e.exit_code());
+        }
+    };
+
+    qv.set_duplicate_policy(args.duplicate_fields);
+    let tags = match qv.filter_by_expr(&args.expression) {
+        Ok(tags) => tags,
+        Err(e) => {
+            eprintln!("❌ Failed to evaluate expression: {:?}", e);
+            process::exit(e.exit_code());
+        }
+    };
+    qv.take_warnings().report();
+    for tag in tags {
+        println!("{}", tag);
+    }
+}
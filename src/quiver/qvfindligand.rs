@@ -0,0 +1,47 @@
+use clap::Parser;
+use std::process;
+
+use quiver::ligand::find_ligand;
+use quiver::Quiver;
+
+/// Find structures containing a ligand, e.g. ATP or a cofactor.
+///
+/// Ligands must first be stored with `qvannotate --ligands`. Prints one
+/// `tag code count` line per structure containing the ligand.
+///
+/// Usage:
+///     qvfindligand annotated.qv ATP
+///     qvfindligand annotated.qv HEM | cut -f1 | qvslice annotated.qv - > heme.qv
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// Quiver file to search
+    quiver_file: String,
+
+    /// Three-letter ligand code (case-insensitive)
+    code: String,
+}
+
+fn main() {
+    let args = Args::parse();
+
+    let qv = match Quiver::new(&args.quiver_file, "r") {
+        Ok(q) => q,
+        Err(e) => {
+            eprintln!("❌ Failed to open Quiver file: {:?}", e);
+            process::exit(e.exit_code());
+        }
+    };
+
+    match find_ligand(&qv, &args.code) {
+        Ok(hits) => {
+            for hit in hits {
+                println!("{}\t{}\t{}", hit.tag, hit.code, hit.count);
+            }
+        }
+        Err(e) => {
+            eprintln!("❌ Failed to search ligands: {:?}", e);
+            process::exit(e.exit_code());
+        }
+    }
+}
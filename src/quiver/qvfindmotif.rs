@@ -0,0 +1,63 @@
+use clap::Parser;
+use regex::Regex;
+use std::process;
+
+use quiver::sequence::find_motif;
+use quiver::Quiver;
+
+/// Find structures whose stored sequence matches a regex motif.
+///
+/// Sequences must first be stored with `qvfrompdbs --sequences` or
+/// `qvannotate --sequences`. Prints one `tag chain start end match` line per hit.
+///
+/// Usage:
+///     qvfindmotif my.qv 'C..C'
+///     qvfindmotif my.qv 'N[^P][ST]' --chain A | cut -f1 | sort -u
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// Quiver file to search
+    quiver_file: String,
+
+    /// Regular expression matched against one-letter sequences
+    motif: String,
+
+    /// Only search this chain
+    #[arg(long)]
+    chain: Option<String>,
+}
+
+fn main() {
+    let args = Args::parse();
+
+    let pattern = match Regex::new(&args.motif) {
+        Ok(p) => p,
+        Err(e) => {
+            eprintln!("❌ Invalid motif regex: {}", e);
+            process::exit(1);
+        }
+    };
+
+    let qv = match Quiver::new(&args.quiver_file, "r") {
+        Ok(q) => q,
+        Err(e) => {
+            eprintln!("❌ Failed to open Quiver file: {:?}", e);
+            process::exit(e.exit_code());
+        }
+    };
+
+    match find_motif(&qv, &pattern, args.chain.as_deref()) {
+        Ok(hits) => {
+            for hit in hits {
+                println!(
+                    "{}\t{}\t{}\t{}\t{}",
+                    hit.tag, hit.chain, hit.start, hit.end, hit.matched
+                );
+            }
+        }
+        Err(e) => {
+            eprintln!("❌ Failed to search motifs: {:?}", e);
+            process::exit(e.exit_code());
+        }
+    }
+}
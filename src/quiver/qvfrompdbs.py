@@ -4,28 +4,46 @@ This tool combines multiple PDB files into a Quiver-compatible stream.
 
 Usage:
     qvfrompdbs.py <pdb1> <pdb2> ... <pdbN> > output.qv
+    qvfrompdbs.py --allow-non-structure <file1> ... <fileN> > output.qv
 """
 
 import os
+import sys
 
 import click
 
+from validate import sniff_structure
+
 
 @click.command()
 @click.argument(
     "pdb_files", type=click.Path(exists=True, dir_okay=False), nargs=-1, required=True
 )
-def qv_from_pdbs(pdb_files):
+@click.option(
+    "--allow-non-structure",
+    is_flag=True,
+    help="Write files even if they hold no ATOM/HETATM (or _atom_site) records.",
+)
+def qv_from_pdbs(pdb_files, allow_non_structure):
     """
     Converts one or more PDB files into a Quiver-formatted stream.
 
-    Output is printed to stdout.
+    Output is printed to stdout. A file without a single coordinate record
+    (a log or a script caught by a loose glob) stops the run before it is
+    written, unless --allow-non-structure is given.
     """
     for pdbfn in pdb_files:
         pdbtag = os.path.basename(pdbfn).removesuffix(".pdb")
-        click.echo(f"QV_TAG {pdbtag}")
         with open(pdbfn, "r") as f:
-            click.echo(f.read(), nl=False)
+            contents = f.read()
+        if not allow_non_structure:
+            try:
+                sniff_structure(contents.splitlines())
+            except ValueError as e:
+                click.echo(f"❌ Error: {pdbfn}: rejected: {e}", err=True)
+                sys.exit(1)
+        click.echo(f"QV_TAG {pdbtag}")
+        click.echo(contents, nl=False)
 
 
 if __name__ == "__main__":
@@ -1,39 +1,200 @@
 use clap::Parser;
-use std::fs::File;
 use std::io::{self, Read, Write};
 use std::path::Path;
+use std::process;
+
+use quiver::integrity::{format_hash_line, payload_digest};
+use quiver::{format_meta_line, format_tag_line, Entry, PayloadFormat};
+use quiver::ingest::{open_structure, OnDuplicate, TagFrom, TagPolicy};
+use quiver::sequence::sequence_fields;
+use quiver::validate::{EntryValidator, MaxResidues, PdbRecords, RequiredChains, StructureContent};
 
 /// Combines multiple PDB files into a Quiver-compatible stream.
 ///
+/// Gzipped inputs (`.pdb.gz`) are detected and decompressed transparently.
+/// mmCIF inputs (`.cif`, `.cif.gz`) are stored with `FORMAT=cif` on their
+/// `QV_TAG` line. With `--max-residues`, `--require-chains` or
+/// `--check-records`, a structure breaking the rule stops the run before it
+/// is written. So does a tag with whitespace or a line starting with a
+/// `QV_` record, which would corrupt the archive, unless `--no-strict` is
+/// given. A file without a single coordinate record (a log or a script
+/// caught by a loose glob) stops the run as well, unless
+/// `--allow-non-structure` is given. With `--hash`, every entry gets a
+/// `QV_HASH` checksum for `qvverify`. With `--reproducible`, entries are
+/// written in tag order with `\n` line endings, so the same files give a
+/// byte-identical stream whatever order they are listed in.
+///
 /// Usage:
 ///     qvfrompdbs <pdb1> <pdb2> ... <pdbN> > output.qv
+///     qvfrompdbs --sequences *.pdb > output.qv
+///     qvfrompdbs *.pdb.gz > output.qv
+///     qvfrompdbs *.cif > output.qv
+///     qvfrompdbs --tag-from relative-path run*/model.pdb > output.qv
+///     qvfrompdbs --max-residues 500 --require-chains A,B *.pdb > output.qv
+///     qvfrompdbs --check-records *.pdb > output.qv
+///     qvfrompdbs --hash *.pdb > output.qv
+///     qvfrompdbs --reproducible --hash *.pdb > output.qv
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 struct Args {
     /// PDB files to combine
     #[arg(required = true)]
     pdb_files: Vec<String>,
+
+    /// Store each chain's one-letter sequence as QV_META fields
+    #[arg(long)]
+    sequences: bool,
+
+    /// Derive tags from the file stem or the relative path (stem, relative-path)
+    #[arg(long, default_value = "stem")]
+    tag_from: TagFrom,
+
+    /// Text prepended to every tag
+    #[arg(long, default_value = "")]
+    prefix: String,
+
+    /// Text appended to every tag
+    #[arg(long, default_value = "")]
+    suffix: String,
+
+    /// What to do when two files map to the same tag (error, skip, rename)
+    #[arg(long, default_value = "error")]
+    on_duplicate: OnDuplicate,
+
+    /// Reject PDB files with more residues than this
+    #[arg(long, value_name = "N")]
+    max_residues: Option<usize>,
+
+    /// Reject PDB files missing any of these chains (comma-separated)
+    #[arg(long, value_name = "CHAINS", value_delimiter = ',')]
+    require_chains: Vec<String>,
+
+    /// Reject PDB files with lines that are not PDB records
+    #[arg(long)]
+    check_records: bool,
+
+    /// Write files even if their tag or lines would corrupt the archive
+    #[arg(long)]
+    no_strict: bool,
+
+    /// Write files even if they hold no ATOM/HETATM (or _atom_site) records
+    #[arg(long)]
+    allow_non_structure: bool,
+
+    /// Store a checksum of every structure (QV_HASH lines, see qvverify)
+    #[arg(long)]
+    hash: bool,
+
+    /// Write entries in tag order with \n line endings for byte-identical rebuilds
+    #[arg(long)]
+    reproducible: bool,
 }
 
-fn main() -> io::Result<()> {
+fn main() {
     let args = Args::parse();
 
+    if let Err(e) = qv_from_pdbs(&args) {
+        eprintln!("❌ Error: {}", e);
+        process::exit(1);
+    }
+}
+
+fn qv_from_pdbs(args: &Args) -> Result<(), String> {
     let stdout = io::stdout();
     let mut handle = stdout.lock();
+    let mut policy = TagPolicy::new(args.tag_from, &args.prefix, &args.suffix, args.on_duplicate);
+    let mut validators: Vec<Box<dyn EntryValidator>> = Vec::new();
+    if !args.allow_non_structure {
+        validators.push(Box::new(StructureContent));
+    }
+    if let Some(max) = args.max_residues {
+        validators.push(Box::new(MaxResidues(max)));
+    }
+    if !args.require_chains.is_empty() {
+        validators.push(Box::new(RequiredChains(args.require_chains.clone())));
+    }
+    if args.check_records {
+        validators.push(Box::new(PdbRecords));
+    }
 
-    for pdbfn in &args.pdb_files {
+    let mut pdb_files: Vec<&String> = args.pdb_files.iter().collect();
+    if args.reproducible {
+        // Renamed duplicates must not depend on the argument order either
+        pdb_files.sort();
+    }
+    let mut jobs = Vec::with_capacity(pdb_files.len());
+    for pdbfn in pdb_files {
         let path = Path::new(pdbfn);
-        let pdbtag = path
-            .file_name()
-            .and_then(|name| name.to_str())
-            .map(|name| name.strip_suffix(".pdb").unwrap_or(name))
-            .unwrap_or("UNKNOWN");
+        let Some(pdbtag) = policy.assign(path)? else {
+            eprintln!("⚠️  Duplicate tag for {}, skipping", pdbfn);
+            continue;
+        };
+        jobs.push((pdbfn, pdbtag));
+    }
+    if args.reproducible {
+        jobs.sort_by(|a, b| a.1.cmp(&b.1));
+    }
+
+    for (pdbfn, pdbtag) in &jobs {
+        write_entry(&mut handle, Path::new(pdbfn), pdbtag, args, &validators)
+            .map_err(|e| format!("{}: {}", pdbfn, e))?;
+    }
 
-        writeln!(handle, "QV_TAG {}", pdbtag)?;
+    Ok(())
+}
 
-        let mut file = File::open(path)?;
-        io::copy(&mut file, &mut handle)?;
+fn write_entry<W: Write>(
+    handle: &mut W,
+    path: &Path,
+    pdbtag: &str,
+    args: &Args,
+    validators: &[Box<dyn EntryValidator>],
+) -> io::Result<()> {
+    let format = PayloadFormat::from_path(path);
+    let mut file = open_structure(path)?;
+    let sequences = args.sequences && format == PayloadFormat::Pdb;
+    let strict = !args.no_strict;
+    if !sequences && !strict && !args.hash && !args.reproducible && validators.is_empty() {
+        writeln!(handle, "{}", format_tag_line(pdbtag, format))?;
+        io::copy(&mut file, handle)?;
+        return Ok(());
     }
 
+    let mut contents = String::new();
+    file.read_to_string(&mut contents)?;
+    let entry = Entry {
+        tag: pdbtag.to_string(),
+        score: None,
+        meta: Vec::new(),
+        lines: contents.lines().map(str::to_string).collect(),
+        format,
+    };
+    if strict {
+        entry
+            .check_layout()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("rejected: {}", e)))?;
+    }
+    for validator in validators {
+        validator
+            .validate(&entry)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("rejected: {}", e)))?;
+    }
+    writeln!(handle, "{}", format_tag_line(pdbtag, format))?;
+    if sequences {
+        let fields = sequence_fields(&entry.lines);
+        if !fields.is_empty() {
+            writeln!(handle, "{}", format_meta_line(pdbtag, &fields))?;
+        }
+    }
+    if args.hash {
+        writeln!(handle, "{}", format_hash_line(pdbtag, &payload_digest(&entry.lines)))?;
+    }
+    if args.reproducible {
+        for line in &entry.lines {
+            writeln!(handle, "{}", line)?;
+        }
+    } else {
+        handle.write_all(contents.as_bytes())?;
+    }
     Ok(())
 }
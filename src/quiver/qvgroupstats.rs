@@ -0,0 +1,95 @@
+use clap::Parser;
+use regex::Regex;
+use std::fs::File;
+use std::io::{self, Write};
+use std::process;
+
+use quiver::expr::ScoreKey;
+use quiver::rank::{group_stats, GroupStats};
+use quiver::scores::DuplicatePolicy;
+use quiver::Quiver;
+
+/// Summarise score fields per group of tags, e.g. per design across seeds.
+///
+/// Tags are grouped by the pattern's first capture group (or whole match);
+/// tags it does not match are a group of their own. The table is written as
+/// CSV with `group,entries` and the mean, min and max of every field
+/// (`<field>_mean,<field>_min,<field>_max`). Missing and non-numeric values
+/// are left out; a group without any value of a field gets `NaN`. A field
+/// may also be an expression over the score fields, as for qvfilter.
+///
+/// Usage:
+///     qvgroupstats my.qv --group '^(.*)_seed\d+$' --field ddg --field plddt
+///     qvgroupstats my.qv --group '^(.*)_seed\d+$' --field 'ddg - ref_ddg'
+///     qvgroupstats my.qv --group '^(.*)_seed\d+$' --field ddg -o designs.csv
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// Quiver file to summarise
+    quiver_file: String,
+
+    /// Group tags by this regex (first capture group, or the whole match)
+    #[arg(long, value_name = "REGEX")]
+    group: Regex,
+
+    /// Score field or expression to summarise; repeat for more
+    #[arg(short, long = "field", required = true, value_name = "FIELD")]
+    fields: Vec<ScoreKey>,
+
+    /// Write the CSV here instead of stdout
+    #[arg(short, long, value_name = "CSV")]
+    output: Option<String>,
+
+    /// Which value to use when a score line repeats a field (first, last, error, warn)
+    #[arg(long, default_value = "last", value_name = "POLICY")]
+    duplicate_fields: DuplicatePolicy,
+}
+
+fn main() {
+    let args = Args::parse();
+
+    if let Err(e) = run(&args) {
+        eprintln!("❌ Error: {}", e);
+        process::exit(1);
+    }
+}
+
+fn run(args: &Args) -> Result<(), String> {
+    let mut qv = Quiver::new(&args.quiver_file, "r")
+        .map_err(|e| format!("Failed to open Quiver file: {:?}", e))?;
+    qv.set_duplicate_policy(args.duplicate_fields);
+    let scores = qv
+        .get_score_records()
+        .map_err(|e| format!("Failed to read scores: {:?}", e))?;
+    qv.take_warnings().report();
+    let groups = group_stats(&qv.get_tags(), &scores, &args.group, &args.fields);
+
+    let out: Box<dyn Write> = match &args.output {
+        Some(path) => Box::new(
+            File::create(path).map_err(|e| format!("Failed to create output file: {}", e))?,
+        ),
+        None => Box::new(io::stdout()),
+    };
+    write_csv(out, &groups, &args.fields).map_err(|e| format!("Failed to write CSV: {}", e))
+}
+
+fn write_csv<W: Write>(out: W, groups: &[GroupStats], fields: &[ScoreKey]) -> csv::Result<()> {
+    let mut wtr = csv::Writer::from_writer(out);
+    let mut header = vec!["group".to_string(), "entries".to_string()];
+    for field in fields {
+        header.extend(["mean", "min", "max"].map(|stat| format!("{}_{}", field, stat)));
+    }
+    wtr.write_record(&header)?;
+    for group in groups {
+        let mut row = vec![group.group.clone(), group.entries.to_string()];
+        for stats in &group.fields {
+            match stats.mean() {
+                Some(mean) => row.extend([mean, stats.min, stats.max].map(|v| v.to_string())),
+                None => row.extend(["NaN"; 3].map(String::from)),
+            }
+        }
+        wtr.write_record(&row)?;
+    }
+    wtr.flush()?;
+    Ok(())
+}
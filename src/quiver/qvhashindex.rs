@@ -0,0 +1,70 @@
+use clap::{Parser, Subcommand};
+use std::process;
+
+use quiver::hashindex::HashIndex;
+use quiver::{Quiver, QuiverError};
+
+/// Find structures that are physically present in several Quiver files.
+///
+/// Payloads are compared by content hash, so duplicates are found even when
+/// tags or scores differ.
+///
+/// Usage:
+///     qvhashindex build train.idx train_a.qv train_b.qv
+///     qvhashindex intersect candidates.qv train.idx | cut -f1 | sort -u
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Hash every structure of the given Quiver files into an index file
+    Build {
+        /// Index file to write
+        index: String,
+
+        /// Quiver files to index
+        #[arg(required = true)]
+        quiver_files: Vec<String>,
+    },
+    /// Print `tag archive other_tag` for structures already in an index
+    Intersect {
+        /// Quiver file to check
+        quiver_file: String,
+
+        /// Index file written by `build`
+        index: String,
+    },
+}
+
+fn main() {
+    let args = Args::parse();
+
+    if let Err(e) = run(args.command) {
+        eprintln!("❌ Error: {:?}", e);
+        process::exit(e.exit_code());
+    }
+}
+
+fn run(command: Command) -> Result<(), QuiverError> {
+    match command {
+        Command::Build { index, quiver_files } => {
+            let built = HashIndex::build(&quiver_files)?;
+            built.save(&index)?;
+            eprintln!("✅ Indexed {} distinct structures into {}", built.len(), index);
+        }
+        Command::Intersect { quiver_file, index } => {
+            let loaded = HashIndex::load(&index)?;
+            let qv = Quiver::new(&quiver_file, "r")?;
+            for (tag, locations) in loaded.intersect(&qv)? {
+                for loc in locations {
+                    println!("{}\t{}\t{}", tag, loc.archive, loc.tag);
+                }
+            }
+        }
+    }
+    Ok(())
+}
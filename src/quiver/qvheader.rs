@@ -0,0 +1,94 @@
+use clap::Parser;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::process;
+
+use quiver::compress::open_reader;
+use quiver::{default_header, format_header_lines, MetaFields, Quiver};
+
+/// Show or rewrite the archive-wide QV_HEADER fields of a Quiver file.
+///
+/// Without options the header is printed as `key=value` lines. With `--set`
+/// or `--stamp` the whole file is written to stdout with the updated header.
+///
+/// Usage:
+///     qvheader my.qv
+///     qvheader my.qv --stamp --set description="round 3 designs" > stamped.qv
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// Quiver file to read
+    quiver_file: String,
+
+    /// Set a header field as KEY=VALUE (repeatable)
+    #[arg(long = "set", value_name = "KEY=VALUE", value_parser = parse_field)]
+    fields: Vec<(String, String)>,
+
+    /// Add creator, creation time and format fields
+    #[arg(long)]
+    stamp: bool,
+}
+
+fn parse_field(s: &str) -> Result<(String, String), String> {
+    match s.split_once('=') {
+        Some((k, v)) if !k.is_empty() && !k.contains(char::is_whitespace) => {
+            Ok((k.to_string(), v.to_string()))
+        }
+        _ => Err(format!("Expected KEY=VALUE, got '{}'", s)),
+    }
+}
+
+fn main() {
+    let args = Args::parse();
+
+    let qv = match Quiver::new(&args.quiver_file, "r") {
+        Ok(q) => q,
+        Err(e) => {
+            eprintln!("❌ Failed to open Quiver file: {:?}", e);
+            process::exit(e.exit_code());
+        }
+    };
+    let mut header = qv.get_header();
+
+    if args.fields.is_empty() && !args.stamp {
+        for (k, v) in header {
+            println!("{}={}", k, v);
+        }
+        return;
+    }
+
+    if args.stamp {
+        for field in default_header() {
+            set_field(&mut header, field);
+        }
+    }
+    for field in args.fields.iter().cloned() {
+        set_field(&mut header, field);
+    }
+
+    if let Err(e) = rewrite(&args.quiver_file, &header) {
+        eprintln!("❌ Error: {}", e);
+        process::exit(1);
+    }
+}
+
+fn set_field(header: &mut MetaFields, (key, value): (String, String)) {
+    match header.iter_mut().find(|(k, _)| *k == key) {
+        Some(field) => field.1 = value,
+        None => header.push((key, value)),
+    }
+}
+
+/// Copy the file to stdout with the new header in front and old headers dropped.
+fn rewrite(quiver_file: &str, header: &[(String, String)]) -> io::Result<()> {
+    let reader = BufReader::new(open_reader(quiver_file)?);
+    let stdout = io::stdout();
+    let mut out = BufWriter::new(stdout.lock());
+    out.write_all(format_header_lines(header).as_bytes())?;
+    for line in reader.lines() {
+        let line = line?;
+        if !line.starts_with("QV_HEADER") {
+            writeln!(out, "{}", line)?;
+        }
+    }
+    out.flush()
+}
@@ -0,0 +1,57 @@
+use clap::Parser;
+use std::io::{self, IsTerminal, Write};
+use std::path::Path;
+use std::process;
+
+use quiver::tagindex::{index_path, TagIndex};
+use quiver::QuiverError;
+
+/// Build the sidecar tag index (`<file>.idx`) of a Quiver file.
+///
+/// An up-to-date index is left alone unless `--force` is given. Concurrent
+/// builds of the same index are serialized by a lock file: a second
+/// `qvindex` waits for the first and then uses its index. Progress is shown
+/// on stderr when it is a terminal.
+///
+/// Usage:
+///     qvindex big.qv
+///     qvindex big.qv --force
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// Quiver file to index
+    quiver_file: String,
+
+    /// Rebuild the index even if it is up to date
+    #[arg(long)]
+    force: bool,
+}
+
+fn main() {
+    let args = Args::parse();
+
+    if let Err(e) = run(&args) {
+        eprintln!("❌ Error: {:?}", e);
+        process::exit(e.exit_code());
+    }
+}
+
+fn run(args: &Args) -> Result<(), QuiverError> {
+    let show = io::stderr().is_terminal();
+    let index = TagIndex::load_or_build(&args.quiver_file, args.force, &mut |done, total| {
+        if show {
+            let percent = if total == 0 { 100.0 } else { done as f64 * 100.0 / total as f64 };
+            eprint!("\r⏳ Indexed {} of {} bytes ({:.1}%)", done, total, percent);
+            let _ = io::stderr().flush();
+        }
+    })?;
+    if show {
+        eprintln!();
+    }
+    eprintln!(
+        "✅ {} entries indexed in {}",
+        index.len(),
+        index_path(Path::new(&args.quiver_file)).display()
+    );
+    Ok(())
+}
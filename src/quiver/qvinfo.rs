@@ -0,0 +1,108 @@
+use clap::Parser;
+use std::process;
+
+use quiver::info::{info, SizeStats};
+use quiver::Quiver;
+
+/// Summarize a Quiver file: entry sizes, storage and score coverage.
+///
+/// Statistics come from a random sample of entries, so huge archives answer
+/// quickly; results are marked as estimates unless every entry was read.
+/// For compressed and binary archives the stored size of each entry is
+/// reported next to its uncompressed size, with the least compressible
+/// entries listed.
+///
+/// Usage:
+///     qvinfo my.qv
+///     qvinfo my.qv --sample 0        # read every entry
+///     qvinfo my.qv --tag design_7    # atoms, residues and chains of one structure
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// Quiver file to summarize
+    quiver_file: String,
+
+    /// Number of entries to sample (0 reads every entry)
+    #[arg(long, default_value_t = 1000)]
+    sample: usize,
+
+    /// Seed for choosing the sample
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+
+    /// Describe this structure instead: its atoms, residues, chains and size
+    #[arg(long)]
+    tag: Option<String>,
+}
+
+fn print_stats(name: &str, stats: &SizeStats) {
+    println!(
+        "{:<10} min {}  median {:.1}  mean {:.1}  max {}",
+        name, stats.min, stats.median, stats.mean, stats.max
+    );
+}
+
+fn main() {
+    let args = Args::parse();
+
+    let qv = match Quiver::new(&args.quiver_file, "r") {
+        Ok(q) => q,
+        Err(e) => {
+            eprintln!("❌ Failed to open Quiver file: {:?}", e);
+            process::exit(e.exit_code());
+        }
+    };
+
+    if let Some(tag) = &args.tag {
+        match qv.stats(Some(tag)) {
+            Ok(stats) => {
+                println!("atoms      {}", stats.atoms);
+                println!("residues   {}", stats.residues);
+                println!("chains     {}", stats.chains.join(","));
+                println!("bytes      {}", stats.bytes);
+            }
+            Err(e) => {
+                eprintln!("❌ Failed to read {}: {:?}", tag, e);
+                process::exit(e.exit_code());
+            }
+        }
+        return;
+    }
+
+    let sample = if args.sample == 0 { usize::MAX } else { args.sample };
+    let summary = match info(&qv, sample, args.seed) {
+        Ok(s) => s,
+        Err(e) => {
+            eprintln!("❌ Failed to read Quiver file: {:?}", e);
+            process::exit(e.exit_code());
+        }
+    };
+
+    println!("entries    {}", summary.entries);
+    if summary.estimate {
+        println!(
+            "⚠️  Estimated from a random sample of {} of {} entries",
+            summary.sampled, summary.entries
+        );
+    }
+    print_stats("bytes", &summary.bytes);
+    print_stats("stored", &summary.stored);
+    if summary.stored_total != summary.bytes_total {
+        println!(
+            "ratio      {:.2}x ({} -> {} bytes{})",
+            summary.ratio(),
+            summary.bytes_total,
+            summary.stored_total,
+            if summary.stored_exact { "" } else { ", estimated per entry" }
+        );
+        println!("least compressible");
+        for stat in &summary.least_compressible {
+            println!("  {:.2}x {:>10} -> {:<10} {}", stat.ratio(), stat.bytes, stat.stored, stat.tag);
+        }
+    }
+    print_stats("atoms", &summary.atoms);
+    println!("scored     {:.1}%", summary.score_coverage * 100.0);
+    for (field, coverage) in &summary.field_coverage {
+        println!("  {:<8} {:.1}%", field, coverage * 100.0);
+    }
+}
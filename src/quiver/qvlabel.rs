@@ -0,0 +1,87 @@
+use clap::Parser;
+use std::io::{self, Read};
+use std::process;
+
+use quiver::Quiver;
+
+/// Add or remove a label on structures in a Quiver file.
+///
+/// Labels are stored in each entry's QV_META line and can be used to select
+/// entries with `--label` in qvls, qvslice, qvsplit and qvextract. The file
+/// is rewritten atomically.
+///
+/// Usage:
+///     qvlabel my.qv round3 tag1 tag2
+///     cat picked.txt | qvlabel my.qv round3 -
+///     qvlabel my.qv round3 --remove tag1
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// Quiver file to label structures in
+    quiver_file: String,
+
+    /// Label to add or remove
+    label: String,
+
+    /// Tags to label; `-` reads more, whitespace-separated, from stdin
+    tags: Vec<String>,
+
+    /// Remove the label instead of adding it
+    #[arg(long)]
+    remove: bool,
+}
+
+fn main() {
+    let args = Args::parse();
+
+    // Collect tags from CLI, and from stdin only when asked to with "-", so
+    // an open but silent stdin (cron, notebooks) cannot block the tool
+    let read_stdin = args.tags.iter().any(|t| t == "-");
+    let mut tag_list: Vec<String> = args.tags.iter().filter(|t| *t != "-").cloned().collect();
+    if read_stdin {
+        let mut stdin_data = String::new();
+        if let Err(e) = io::stdin().read_to_string(&mut stdin_data) {
+            eprintln!("❌ Failed to read from stdin: {}", e);
+            process::exit(1);
+        }
+        tag_list.extend(stdin_data.split_whitespace().map(|s| s.to_string()));
+    }
+
+    // An empty list piped on stdin is a no-op, not an error
+    if tag_list.is_empty() && !read_stdin {
+        eprintln!("❌ No tags provided. Provide tags as arguments, or - to read them from stdin.");
+        process::exit(1);
+    }
+
+    let mut qv = match Quiver::new(&args.quiver_file, "a") {
+        Ok(q) => q,
+        Err(e) => {
+            eprintln!("❌ Failed to open Quiver file: {:?}", e);
+            process::exit(e.exit_code());
+        }
+    };
+
+    let missing = qv.tags_exist(&tag_list).into_iter().filter(|(_, e)| !e).count();
+    if missing > 0 {
+        eprintln!("⚠️  {} requested tag(s) not found in {}", missing, args.quiver_file);
+    }
+
+    let result = if args.remove {
+        qv.remove_labels(&tag_list, &args.label)
+    } else {
+        qv.add_labels(&tag_list, &args.label)
+    };
+    match result {
+        Ok(changed) => eprintln!(
+            "✅ {} label '{}' on {} structure(s); {} now carry it",
+            if args.remove { "Removed" } else { "Added" },
+            args.label,
+            changed,
+            qv.list_by_label(&args.label).len()
+        ),
+        Err(e) => {
+            eprintln!("❌ Failed to update labels: {:?}", e);
+            process::exit(e.exit_code());
+        }
+    }
+}
@@ -1,32 +1,69 @@
 use clap::Parser;
+use std::fs::{self, File};
+use std::io::BufWriter;
 use std::process;
 
-mod quiver;
-use quiver::Quiver;
+use quiver::{dump_tags, Quiver};
 
 /// List all tags in the given Quiver file.
 ///
+/// With `--output`, tags are streamed to a file in chunks instead, without
+/// loading the whole tag list; use it for archives with very many entries.
+///
 /// Usage:
 ///     qvls <quiver_file>
+///     qvls <quiver_file> --label round3
+///     qvls <quiver_file> --output tags.txt --chunk-size 100000
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 struct Args {
     /// Quiver file to list tags from
     quiver_file: String,
+
+    /// Only list entries carrying this label
+    #[arg(long, conflicts_with = "output")]
+    label: Option<String>,
+
+    /// Stream the tags to this file
+    #[arg(short, long, value_name = "FILE")]
+    output: Option<String>,
+
+    /// Tags held in memory between writes to the output file
+    #[arg(long, default_value_t = 65536, value_name = "N", requires = "output")]
+    chunk_size: usize,
 }
 
 fn main() {
     let args = Args::parse();
 
+    if let Some(path) = &args.output {
+        let result = File::create(path)
+            .map_err(Into::into)
+            .and_then(|file| dump_tags(&args.quiver_file, BufWriter::new(file), args.chunk_size));
+        match result {
+            Ok(count) => eprintln!("✅ Wrote {} tags to {}", count, path),
+            Err(e) => {
+                let _ = fs::remove_file(path);
+                eprintln!("❌ Failed to list tags: {:?}", e);
+                process::exit(e.exit_code());
+            }
+        }
+        return;
+    }
+
     let qv = match Quiver::new(&args.quiver_file, "r") {
         Ok(q) => q,
         Err(e) => {
             eprintln!("❌ Failed to open Quiver file: {:?}", e);
-            process::exit(1);
+            process::exit(e.exit_code());
         }
     };
 
-    for tag in qv.get_tags() {
+    let tags = match &args.label {
+        Some(label) => qv.list_by_label(label),
+        None => qv.get_tags(),
+    };
+    for tag in tags {
         println!("{}", tag);
     }
 }
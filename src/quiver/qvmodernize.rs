@@ -0,0 +1,74 @@
+use clap::Parser;
+use std::process;
+
+use quiver::legacy::{modernize, LegacyRecords};
+use quiver::QuiverError;
+
+/// Rewrite a Quiver file from an older writer into the canonical format.
+///
+/// Record keywords spelled without the underscore or with a space in its
+/// place (`QVTAG`, `QV TAG`, `QVSCORE`, `QV SCORE`, and likewise for
+/// `QV_META`, `QV_HASH` and `QV_HEADER`) are replaced by the canonical ones;
+/// every other byte is copied as is. Other spellings can be added with
+/// `--variant`. The original file is left alone.
+///
+/// Usage:
+///     qvmodernize old.qv new.qv
+///     qvmodernize old.qv new.qv --variant 'QV-TAG=QV_TAG' --variant 'QV-SCORE=QV_SCORE'
+///     qvmodernize old.qv new.qv --only-variants --variant 'QV TAG=QV_TAG'
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// Quiver file to modernize
+    quiver_file: String,
+
+    /// New Quiver file to write the canonical archive to
+    output: String,
+
+    /// Also rewrite this spelling (repeatable)
+    #[arg(long, value_name = "VARIANT=CANONICAL")]
+    variant: Vec<String>,
+
+    /// Rewrite only the spellings given with --variant, not the documented ones
+    #[arg(long)]
+    only_variants: bool,
+}
+
+fn main() {
+    let args = Args::parse();
+
+    if let Err(e) = run(&args) {
+        eprintln!("❌ Error: {:?}", e);
+        process::exit(e.exit_code());
+    }
+}
+
+fn run(args: &Args) -> Result<(), QuiverError> {
+    let mut records = if args.only_variants {
+        LegacyRecords::none()
+    } else {
+        LegacyRecords::default()
+    };
+    for pair in &args.variant {
+        let Some((variant, canonical)) = pair.split_once('=') else {
+            return Err(QuiverError::InvalidOperation(format!(
+                "Invalid --variant '{}', expected VARIANT=CANONICAL",
+                pair
+            )));
+        };
+        records.add(variant, canonical)?;
+    }
+    let report = modernize(&args.quiver_file, &args.output, &records)?;
+    if report.rewritten == 0 {
+        let known: Vec<&str> = records.variants().iter().map(|(variant, _)| variant.as_str()).collect();
+        eprintln!(
+            "⚠️  No legacy spellings found (looked for: {}); copied as is",
+            known.join(", ")
+        );
+    }
+    println!(
+        "✅ Rewrote {} records, wrote {} entries to {}",
+        report.rewritten, report.entries, args.output
+    );
+    Ok(())
+}
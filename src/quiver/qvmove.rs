@@ -0,0 +1,61 @@
+use clap::Parser;
+use std::io::{self, Read};
+use std::process;
+
+use quiver::transfer::{move_entries, recover_move, MovePhase};
+use quiver::QuiverError;
+
+/// Move structures from one Quiver file to the end of another.
+///
+/// The structures are appended to DEST (created with the source header if
+/// needed) and then removed from SOURCE. Progress is journaled in
+/// `SOURCE.move`, so a move cut short by a crash is rolled back or completed
+/// by the next `qvmove` out of the same source; run it without tags to only
+/// do that. Nothing is moved if a tag is missing from SOURCE or already in
+/// DEST.
+///
+/// Usage:
+///     qvmove designs.qv accepted.qv design_0001 design_0002
+///     qvfilter designs.qv 'plddt > 90' | qvmove designs.qv accepted.qv -
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// Quiver file to move structures out of
+    source: String,
+
+    /// Quiver file to append them to
+    dest: String,
+
+    /// Tags to move; `-` reads more, whitespace-separated, from stdin
+    tags: Vec<String>,
+}
+
+fn main() {
+    let args = Args::parse();
+
+    if let Err(e) = run(&args) {
+        eprintln!("❌ Error: {:?}", e);
+        process::exit(e.exit_code());
+    }
+}
+
+fn run(args: &Args) -> Result<(), QuiverError> {
+    let mut tags: Vec<String> = args.tags.iter().filter(|t| *t != "-").cloned().collect();
+    if args.tags.iter().any(|t| t == "-") {
+        let mut stdin_data = String::new();
+        io::stdin().read_to_string(&mut stdin_data)?;
+        tags.extend(stdin_data.split_whitespace().map(|s| s.to_string()));
+    }
+
+    match recover_move(&args.source)? {
+        Some(MovePhase::Copying) => eprintln!("⚠️  Rolled back an interrupted move out of {}", args.source),
+        Some(MovePhase::Copied) => eprintln!("⚠️  Completed an interrupted move out of {}", args.source),
+        None => {}
+    }
+    if tags.is_empty() {
+        return Ok(());
+    }
+    let moved = move_entries(&args.source, &args.dest, &tags)?;
+    println!("✅ Moved {} entries from {} to {}", moved, args.source, args.dest);
+    Ok(())
+}
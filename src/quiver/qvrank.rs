@@ -0,0 +1,171 @@
+use clap::Parser;
+use regex::Regex;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::process;
+
+use quiver::compress::open_reader;
+use quiver::rank::{best_per_group, rank, RankKey, RankedEntry};
+use quiver::scores::{resolve_duplicates, DuplicatePolicy, ScoreType};
+use quiver::{format_score_line, parse_score_line, Quiver};
+
+/// Rank the structures of a Quiver file by one or more score fields.
+///
+/// Later keys break ties in earlier ones. Entries missing a field sort last.
+/// The ranked table is written as CSV (`rank,tag,<fields>`).
+///
+/// With `--group`, tags are grouped by the pattern's first capture group (or
+/// whole match) and only the `--top` best of each group are kept; the table
+/// gains a `group` column after `tag`.
+///
+/// Usage:
+///     qvrank my.qv --key plddt:desc --key rmsd:asc > ranked.csv
+///     qvrank my.qv --key plddt:desc --output ranked.csv --annotate ranked.qv
+///     qvrank my.qv --key ddg:asc --group '^(.*)_seed\d+$' --top 2 > best.csv
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// Quiver file to rank
+    quiver_file: String,
+
+    /// Sort key as FIELD[:asc|desc], FIELD also an expression; repeat for tie-breakers
+    #[arg(short, long = "key", required = true, value_name = "FIELD[:ORDER]")]
+    keys: Vec<RankKey>,
+
+    /// Write the ranked CSV here instead of stdout
+    #[arg(short, long, value_name = "CSV")]
+    output: Option<String>,
+
+    /// Also write a copy of the Quiver file with a `rank` score added
+    #[arg(long, value_name = "QV")]
+    annotate: Option<String>,
+
+    /// Which value to use when a score line repeats a field (first, last, error, warn)
+    #[arg(long, default_value = "last", value_name = "POLICY")]
+    duplicate_fields: DuplicatePolicy,
+
+    /// Group tags by this regex (first capture group, or the whole match)
+    #[arg(long, value_name = "REGEX")]
+    group: Option<Regex>,
+
+    /// Entries to keep per group
+    #[arg(long, default_value_t = 1, value_name = "K", requires = "group")]
+    top: usize,
+}
+
+fn main() {
+    let args = Args::parse();
+
+    if let Err(e) = run(&args) {
+        eprintln!("❌ Error: {}", e);
+        process::exit(1);
+    }
+}
+
+fn run(args: &Args) -> Result<(), String> {
+    let mut qv = Quiver::new(&args.quiver_file, "r")
+        .map_err(|e| format!("Failed to open Quiver file: {:?}", e))?;
+    qv.set_duplicate_policy(args.duplicate_fields);
+    let scores = qv
+        .get_score_records()
+        .map_err(|e| format!("Failed to read scores: {:?}", e))?;
+    qv.take_warnings().report();
+    let ranked = rank(&qv.get_tags(), &scores, &args.keys);
+    let (ranked, groups) = match &args.group {
+        Some(pattern) => {
+            let (groups, ranked): (Vec<String>, Vec<RankedEntry>) = best_per_group(ranked, pattern, args.top).into_iter().unzip();
+            (ranked, Some(groups))
+        }
+        None => (ranked, None),
+    };
+
+    let out: Box<dyn Write> = match &args.output {
+        Some(path) => Box::new(
+            File::create(path).map_err(|e| format!("Failed to create output file: {}", e))?,
+        ),
+        None => Box::new(io::stdout()),
+    };
+    write_csv(out, &ranked, groups.as_deref(), &args.keys).map_err(|e| format!("Failed to write CSV: {}", e))?;
+
+    if let Some(path) = &args.annotate {
+        write_annotated(&args.quiver_file, path, &ranked, args.duplicate_fields)
+            .map_err(|e| format!("Failed to write annotated Quiver file: {}", e))?;
+        eprintln!("✅ Ranked Quiver file written to: {}", path);
+    }
+    Ok(())
+}
+
+fn write_csv<W: Write>(
+    out: W,
+    ranked: &[RankedEntry],
+    groups: Option<&[String]>,
+    keys: &[RankKey],
+) -> csv::Result<()> {
+    let mut wtr = csv::Writer::from_writer(out);
+    let mut header = vec!["rank".to_string(), "tag".to_string()];
+    if groups.is_some() {
+        header.push("group".to_string());
+    }
+    header.extend(keys.iter().map(|k| k.field.clone()));
+    wtr.write_record(&header)?;
+    // Every column is written in its common type; missing values get the
+    // type's placeholder
+    let types: Vec<Option<ScoreType>> = (0..keys.len())
+        .map(|i| {
+            ranked
+                .iter()
+                .filter_map(|e| e.values[i].as_ref().map(|v| v.score_type()))
+                .reduce(ScoreType::unify)
+        })
+        .collect();
+    for (i, entry) in ranked.iter().enumerate() {
+        let mut row = vec![entry.rank.to_string(), entry.tag.clone()];
+        if let Some(groups) = groups {
+            row.push(groups[i].clone());
+        }
+        row.extend(entry.values.iter().zip(&types).map(|(v, ty)| match (v, ty) {
+            (Some(v), Some(ty)) => v.widen(*ty).to_string(),
+            _ => ty.map_or("NaN", ScoreType::missing).to_string(),
+        }));
+        wtr.write_record(&row)?;
+    }
+    wtr.flush()?;
+    Ok(())
+}
+
+/// Copy the archive, adding (or replacing) a `rank` field on every score line.
+///
+/// Repeated fields were already reported while ranking, so here they are
+/// only collapsed.
+fn write_annotated(input: &str, output: &str, ranked: &[RankedEntry], policy: DuplicatePolicy) -> io::Result<()> {
+    let ranks: HashMap<&str, usize> = ranked.iter().map(|e| (e.tag.as_str(), e.rank)).collect();
+    let reader = BufReader::new(open_reader(input)?);
+    let mut out = BufWriter::new(File::create(output)?);
+
+    let mut lines = reader.lines().peekable();
+    while let Some(line) = lines.next() {
+        let line = line?;
+        writeln!(out, "{}", line)?;
+        let Some(tag) = line.strip_prefix("QV_TAG ").and_then(|t| t.split_whitespace().next())
+        else {
+            continue;
+        };
+        let Some(&rank) = ranks.get(tag) else {
+            continue;
+        };
+        let mut fields = match lines.peek() {
+            Some(Ok(next)) if next.starts_with("QV_SCORE") => {
+                let next = lines.next().unwrap()?;
+                parse_score_line(&next).map(|(_, f)| f).unwrap_or_default()
+            }
+            _ => Vec::new(),
+        };
+        resolve_duplicates(&mut fields, policy)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", tag, e)))?;
+        fields.retain(|(k, _)| k != "rank");
+        fields.push(("rank".to_string(), rank.to_string()));
+        writeln!(out, "{}", format_score_line(tag, &fields))?;
+    }
+    out.flush()
+}
@@ -0,0 +1,72 @@
+use clap::Parser;
+use std::io::{self, Read};
+use std::process;
+
+use quiver::Quiver;
+
+/// Remove structures from a Quiver file in place.
+///
+/// The file is rewritten atomically, so readers never see a half-written
+/// archive. Score and metadata lines of removed structures are dropped too.
+///
+/// Usage:
+///     qvremove my.qv tag1 tag2
+///     cat bad_tags.txt | qvremove my.qv -
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// Quiver file to remove structures from
+    quiver_file: String,
+
+    /// Tags to remove; `-` reads more, whitespace-separated, from stdin
+    tags: Vec<String>,
+}
+
+fn main() {
+    let args = Args::parse();
+
+    // Collect tags from CLI, and from stdin only when asked to with "-", so
+    // an open but silent stdin (cron, notebooks) cannot block the tool
+    let read_stdin = args.tags.iter().any(|t| t == "-");
+    let mut tag_list: Vec<String> = args.tags.iter().filter(|t| *t != "-").cloned().collect();
+    if read_stdin {
+        let mut stdin_data = String::new();
+        if let Err(e) = io::stdin().read_to_string(&mut stdin_data) {
+            eprintln!("❌ Failed to read from stdin: {}", e);
+            process::exit(1);
+        }
+        tag_list.extend(stdin_data.split_whitespace().map(|s| s.to_string()));
+    }
+
+    // An empty list piped on stdin is a no-op, not an error
+    if tag_list.is_empty() && !read_stdin {
+        eprintln!("❌ No tags provided. Provide tags as arguments, or - to read them from stdin.");
+        process::exit(1);
+    }
+
+    let mut qv = match Quiver::new(&args.quiver_file, "a") {
+        Ok(q) => q,
+        Err(e) => {
+            eprintln!("❌ Failed to open Quiver file: {:?}", e);
+            process::exit(e.exit_code());
+        }
+    };
+
+    let missing = qv.tags_exist(&tag_list).into_iter().filter(|(_, e)| !e).count();
+    if missing > 0 {
+        eprintln!("⚠️  {} requested tag(s) not found in {}", missing, args.quiver_file);
+    }
+
+    match qv.remove_tags(&tag_list) {
+        Ok(removed) => eprintln!(
+            "✅ Removed {} structure(s); {} left in {}",
+            removed,
+            qv.size(),
+            args.quiver_file
+        ),
+        Err(e) => {
+            eprintln!("❌ Failed to remove structures: {:?}", e);
+            process::exit(e.exit_code());
+        }
+    }
+}
@@ -3,28 +3,70 @@
 Rename the tags in a Quiver file using new tags from stdin or command-line arguments.
 
 Usage examples:
-    qvls.py my.qv | sed 's/$/_new/' | qvrename.py my.qv > renamed.qv
+    qvls.py my.qv | sed 's/$/_new/' | qvrename.py my.qv - > renamed.qv
     qvrename.py my.qv tag1_new tag2_new ... > renamed.qv
+    qvrename.py my.qv --map renames.txt > renamed.qv
+    qvrename.py my.qv --map renames.txt --in-place
 """
 
 import sys
-import os
-import stat
 import click
-from quiver import Quiver
+from quiver import Quiver, _check_rename_map, _write_renamed
 
 
 @click.command()
 @click.argument("quiver_file", type=click.Path(exists=True, dir_okay=False))
 @click.argument("new_tags", nargs=-1)
-def rename_tags(quiver_file, new_tags):
+@click.option(
+    "--map",
+    "map_file",
+    type=click.File("r"),
+    default=None,
+    help="Rename only the tags in this file of OLD NEW lines (- for stdin)",
+)
+@click.option(
+    "--in-place",
+    "-i",
+    is_flag=True,
+    help="Rename in QUIVER_FILE itself, atomically, instead of writing to stdout",
+)
+def rename_tags(quiver_file, new_tags, map_file, in_place):
     """
-    Rename tags in a Quiver file. New tags are read from arguments or stdin.
+    Rename tags in a Quiver file. New tags are read from arguments, and
+    from stdin where one of them is -.
     """
-    tag_buffers = list(new_tags)
-
-    # Read from stdin if piped
-    if not sys.stdin.isatty() and stat.S_ISFIFO(os.fstat(0).st_mode):
+    if map_file is not None:
+        if new_tags:
+            raise click.UsageError("New tags cannot be given together with --map")
+        mapping = {}
+        for line in map_file:
+            if not line.strip() or line.lstrip().startswith("#"):
+                continue
+            parts = line.split()
+            if len(parts) != 2:
+                raise click.UsageError(f"Rename mapping line is not 'OLD NEW': {line.strip()}")
+            old, new = parts
+            if old in mapping:
+                raise click.UsageError(f"Tag {old} is renamed twice")
+            mapping[old] = new
+        if in_place:
+            rename_in_place(quiver_file, mapping)
+            return
+        try:
+            _check_rename_map(Quiver(quiver_file, "r").get_tags(), mapping)
+        except (KeyError, ValueError) as e:
+            click.secho(f"❌ {e}", fg="red", err=True)
+            sys.exit(1)
+        with open(quiver_file, "rb") as src:
+            renamed = _write_renamed(src, sys.stdout.buffer, mapping)
+        click.secho(f"✅ Renamed {renamed} entries", err=True)
+        return
+
+    tag_buffers = [tag for tag in new_tags if tag != "-"]
+
+    # Read stdin only when asked to, so a silent stdin (cron, notebooks)
+    # cannot block the tool
+    if "-" in new_tags:
         stdin_lines = sys.stdin.read().splitlines()
         for line in stdin_lines:
             tag_buffers.extend(line.strip().split())
@@ -42,7 +84,14 @@ def rename_tags(quiver_file, new_tags):
         )
         sys.exit(1)
 
+    if in_place:
+        rename_in_place(
+            quiver_file, {old: new for old, new in zip(present_tags, tags) if old != new}
+        )
+        return
+
     tag_idx = 0
+    new_tag = None
     with open(quiver_file, "r") as f:
         while True:
             line = f.readline()
@@ -50,7 +99,8 @@ def rename_tags(quiver_file, new_tags):
                 break
 
             if line.startswith("QV_TAG"):
-                line = f"QV_TAG {tags[tag_idx]}\n"
+                new_tag = tags[tag_idx]
+                line = f"QV_TAG {new_tag}\n"
 
                 # Read next line (could be QV_SCORE or structure)
                 next_line = f.readline()
@@ -61,16 +111,31 @@ def rename_tags(quiver_file, new_tags):
                     )
                     sys.exit(1)
 
-                if next_line.startswith("QV_SCORE"):
+                if next_line.startswith(("QV_SCORE", "QV_META", "QV_HASH")):
                     parts = next_line.split(" ")
-                    parts[1] = tags[tag_idx]
+                    parts[1] = new_tag
                     next_line = " ".join(parts)
 
                 line += next_line
                 tag_idx += 1
+            elif new_tag is not None and line.startswith(("QV_META", "QV_HASH")):
+                # Metadata and checksum lines follow their entry's new tag
+                parts = line.split(" ")
+                parts[1] = new_tag
+                line = " ".join(parts)
 
             sys.stdout.write(line)
 
 
+def rename_in_place(quiver_file, mapping):
+    try:
+        with Quiver(quiver_file, "w") as qv:
+            renamed = qv.rename_tags(mapping)
+    except (KeyError, ValueError) as e:
+        click.secho(f"❌ {e}", fg="red", err=True)
+        sys.exit(1)
+    click.secho(f"✅ Renamed {renamed} entries in {quiver_file}", err=True)
+
+
 if __name__ == "__main__":
     rename_tags()
@@ -1,34 +1,57 @@
 use clap::Parser;
-use std::fs::File;
-use std::io::{self, BufRead, BufReader, Write};
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read, Write};
 use std::process;
 
-mod quiver;
-use quiver::Quiver;
+use quiver::compress::open_reader;
+use quiver::rename::{check_mapping, parse_mapping, retag_line, write_renamed};
+use quiver::{Quiver, QuiverError};
 
 /// Rename the tags in a Quiver file using new tags from stdin or command-line arguments.
 ///
+/// With `--map`, only the tags listed in a file of `OLD NEW` lines are
+/// renamed and every other entry is left as it is. With `--in-place` the
+/// file itself is rewritten atomically instead of printing a renamed copy.
+///
 /// Usage examples:
-///     qvls my.qv | sed 's/$/_new/' | qvrename my.qv > renamed.qv
+///     qvls my.qv | sed 's/$/_new/' | qvrename my.qv - > renamed.qv
 ///     qvrename my.qv tag1_new tag2_new ... > renamed.qv
+///     qvrename my.qv --map renames.txt > renamed.qv
+///     qvrename my.qv --map renames.txt --in-place
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 struct Args {
     /// Quiver file to rename tags in
     quiver_file: String,
 
-    /// New tags (can be empty if piped via stdin)
+    /// New tags, in file order; `-` reads more, whitespace-separated, from stdin
+    #[arg(conflicts_with = "map")]
     new_tags: Vec<String>,
+
+    /// Rename only the tags in FILE, one `OLD NEW` pair per line (`-` for stdin)
+    #[arg(long, value_name = "FILE")]
+    map: Option<String>,
+
+    /// Rename in QUIVER_FILE itself, atomically, instead of writing to stdout
+    #[arg(short, long)]
+    in_place: bool,
 }
 
 fn main() {
     let args = Args::parse();
 
-    // Gather new tags from CLI and possibly from stdin (piped)
-    let mut tag_buffers: Vec<String> = args.new_tags.clone();
+    if let Some(map) = &args.map {
+        if let Err(e) = rename_by_map(&args.quiver_file, map, args.in_place) {
+            eprintln!("❌ Error: {:?}", e);
+            process::exit(e.exit_code());
+        }
+        return;
+    }
 
-    // If stdin is piped, read tags from stdin
-    if !atty::is(atty::Stream::Stdin) {
+    // Gather new tags from CLI, and from stdin only when asked to with "-",
+    // so an open but silent stdin (cron, notebooks) cannot block the tool
+    let mut tag_buffers: Vec<String> = args.new_tags.iter().filter(|t| *t != "-").cloned().collect();
+    if args.new_tags.iter().any(|t| t == "-") {
         let mut stdin_data = String::new();
         if let Err(e) = io::stdin().read_to_string(&mut stdin_data) {
             eprintln!("❌ Failed to read from stdin: {}", e);
@@ -47,7 +70,7 @@ fn main() {
         Ok(q) => q,
         Err(e) => {
             eprintln!("❌ Failed to open Quiver file: {:?}", e);
-            process::exit(1);
+            process::exit(e.exit_code());
         }
     };
     let present_tags = qv.get_tags();
@@ -61,8 +84,18 @@ fn main() {
         process::exit(1);
     }
 
+    if args.in_place {
+        drop(qv);
+        let pairs: Vec<(String, String)> = present_tags.into_iter().zip(tags).filter(|(old, new)| old != new).collect();
+        if let Err(e) = rename_in_place(&args.quiver_file, &pairs) {
+            eprintln!("❌ Error: {:?}", e);
+            process::exit(e.exit_code());
+        }
+        return;
+    }
+
     let mut tag_idx = 0;
-    let file = match File::open(&args.quiver_file) {
+    let file = match open_reader(&args.quiver_file) {
         Ok(f) => f,
         Err(e) => {
             eprintln!("❌ Failed to open file: {}", e);
@@ -79,8 +112,13 @@ fn main() {
         buffer.clear();
 
         if line.starts_with("QV_TAG") {
-            // Replace tag
-            line = format!("QV_TAG {}\n", tags[tag_idx]);
+            // Replace tag, keeping attributes such as FORMAT=cif
+            let attrs: Vec<&str> = line.split_whitespace().skip(2).collect();
+            line = if attrs.is_empty() {
+                format!("QV_TAG {}\n", tags[tag_idx])
+            } else {
+                format!("QV_TAG {} {}\n", tags[tag_idx], attrs.join(" "))
+            };
 
             // Read next line (could be QV_SCORE or structure)
             let mut next_line = String::new();
@@ -102,10 +140,44 @@ fn main() {
                     parts[1] = &tags[tag_idx];
                 }
                 next_line = format!("{}\n", parts.join(" "));
+            } else if next_line.starts_with("QV_META") || next_line.starts_with("QV_HASH") {
+                next_line = retag_line(&next_line, &tags[tag_idx]);
             }
             line.push_str(&next_line);
             tag_idx += 1;
+        } else if (line.starts_with("QV_META") || line.starts_with("QV_HASH")) && tag_idx > 0 {
+            line = retag_line(&line, &tags[tag_idx - 1]);
         }
         handle.write_all(line.as_bytes()).unwrap();
     }
 }
+
+/// Write `quiver_file` to stdout with the tags listed in `map` renamed, or
+/// rename them in the file itself with `in_place`.
+fn rename_by_map(quiver_file: &str, map: &str, in_place: bool) -> Result<(), QuiverError> {
+    let text = if map == "-" {
+        let mut text = String::new();
+        io::stdin().read_to_string(&mut text)?;
+        text
+    } else {
+        fs::read_to_string(map)?
+    };
+    if in_place {
+        return rename_in_place(quiver_file, &parse_mapping(&text)?);
+    }
+    let qv = Quiver::new(quiver_file, "r")?;
+    let mapping = check_mapping(&qv.get_tags(), &parse_mapping(&text)?)?;
+    let stdout = io::stdout();
+    let mut out = io::BufWriter::new(stdout.lock());
+    let renamed = write_renamed(BufReader::new(open_reader(quiver_file)?), &mut out, &mapping)?;
+    out.flush()?;
+    eprintln!("✅ Renamed {} entries", renamed);
+    Ok(())
+}
+
+/// Rename the `pairs` (old tag, new tag) in `quiver_file` itself.
+fn rename_in_place(quiver_file: &str, pairs: &[(String, String)]) -> Result<(), QuiverError> {
+    let renamed = Quiver::new(quiver_file, "a")?.rename_tags(pairs)?;
+    eprintln!("✅ Renamed {} entries in {}", renamed, quiver_file);
+    Ok(())
+}
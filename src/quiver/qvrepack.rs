@@ -0,0 +1,49 @@
+use clap::Parser;
+use std::process;
+
+use quiver::repack::{repack, RepackOrder};
+use quiver::QuiverError;
+
+/// Reorder the entries of a Quiver file to match how they will be read.
+///
+/// A pipeline that consumes designs best-first, or in the order of a tag
+/// list, then reads the archive front to back instead of jumping around in
+/// it. `score:FIELD[:asc|desc]` sorts by one score (entries without it go
+/// last); `taglist:PATH` puts the tags listed in PATH first, in that order,
+/// and keeps every other entry after them in file order. The file is
+/// rewritten atomically and entries are copied byte for byte.
+///
+/// Usage:
+///     qvrepack designs.qv --order-by score:plddt:desc
+///     qvrank designs.qv --key ddg:asc | cut -d, -f2 | tail -n +2 > order.txt
+///     qvrepack designs.qv --order-by taglist:order.txt
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// Quiver file to reorder in place
+    quiver_file: String,
+
+    /// New order of the entries: score:FIELD[:asc|desc] or taglist:PATH
+    #[arg(long)]
+    order_by: RepackOrder,
+}
+
+fn main() {
+    let args = Args::parse();
+
+    if let Err(e) = run(&args) {
+        eprintln!("❌ Error: {:?}", e);
+        process::exit(e.exit_code());
+    }
+}
+
+fn run(args: &Args) -> Result<(), QuiverError> {
+    let report = repack(&args.quiver_file, &args.order_by)?;
+    report.warnings.report();
+    if report.moved == 0 {
+        println!("✅ {} is already in that order", args.quiver_file);
+    } else {
+        println!("✅ Moved {} entries of {}", report.moved, args.quiver_file);
+    }
+    Ok(())
+}
@@ -0,0 +1,84 @@
+use clap::Parser;
+use std::fs;
+use std::process;
+
+use quiver::repair::{repair, RepairReport};
+use quiver::QuiverError;
+
+/// Write a clean copy of a Quiver file left broken by a killed job.
+///
+/// The half-written record a killed writer leaves at the end of the file is
+/// dropped, as are `QV_SCORE` lines that are not preceded by the `QV_TAG`
+/// line of their tag. What was removed is listed on stderr and, with
+/// `--report`, written to a tab-separated file (kind, tag, byte offset or
+/// line number, bytes). The original file is left alone.
+///
+/// Usage:
+///     qvrepair killed.qv fixed.qv
+///     qvrepair killed.qv fixed.qv --report removed.tsv
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// Quiver file to repair
+    quiver_file: String,
+
+    /// New Quiver file to write the clean archive to
+    output: String,
+
+    /// Also write what was removed to this file
+    #[arg(long)]
+    report: Option<String>,
+}
+
+fn main() {
+    let args = Args::parse();
+
+    if let Err(e) = run(&args) {
+        eprintln!("❌ Error: {:?}", e);
+        process::exit(e.exit_code());
+    }
+}
+
+fn run(args: &Args) -> Result<(), QuiverError> {
+    let report = repair(&args.quiver_file, &args.output)?;
+    let rows = report_rows(&report);
+    for row in &rows {
+        eprintln!("🗑️  Removed {}", row.replace('\t', " "));
+    }
+    if let Some(path) = &args.report {
+        let mut text = String::from("kind\ttag\tat\tbytes\n");
+        for row in &rows {
+            text.push_str(row);
+            text.push('\n');
+        }
+        fs::write(path, text)?;
+    }
+    if report.is_clean() {
+        println!("✅ Nothing to repair, copied {} entries to {}", report.entries, args.output);
+    } else {
+        println!(
+            "✅ Removed {} damaged records, wrote {} entries to {}",
+            rows.len(),
+            report.entries,
+            args.output
+        );
+    }
+    Ok(())
+}
+
+/// One tab-separated row per record left out by `repair`.
+fn report_rows(report: &RepairReport) -> Vec<String> {
+    let mut rows = Vec::new();
+    if let Some(partial) = &report.partial {
+        rows.push(format!(
+            "partial-entry\t{}\tbyte {}\t{}",
+            partial.tag.as_deref().unwrap_or("-"),
+            partial.offset,
+            partial.bytes
+        ));
+    }
+    for orphan in &report.orphan_scores {
+        rows.push(format!("orphan-score\t{}\tline {}\t-", orphan.tag, orphan.line));
+    }
+    rows
+}
@@ -4,40 +4,82 @@ This script extracts the scorefile from a Quiver (.qv) file and writes it as a .
 
 Usage:
     qvscorefile.py mydesigns.qv
+    qvscorefile.py mydesigns.qv --duplicate-fields error
+    qvscorefile.py mydesigns.qv --format parquet
+    qvscorefile.py mydesigns.qv --format jsonl
+    qvscorefile.py legacy.qv --field-charset fold
 """
 
 import os
 import sys
 import click
 import pandas as pd
+from quiver import legacy_error_handler, normalize_field, parse_score_value, resolve_duplicates
 
 
 @click.command()
 @click.argument("qvfile", type=click.Path(exists=True, dir_okay=False))
-def extract_scorefile(qvfile):
+@click.option(
+    "--duplicate-fields",
+    type=click.Choice(["first", "last", "error", "warn"]),
+    default="last",
+    show_default=True,
+    help="Which value to use when a score line repeats a field",
+)
+@click.option(
+    "--format",
+    "out_format",
+    type=click.Choice(["tsv", "parquet", "json", "jsonl"]),
+    default="tsv",
+    show_default=True,
+    help="Write a tab-separated .sc file, a typed .parquet file (needs pyarrow), "
+    "a JSON array or one JSON object per line",
+)
+@click.option(
+    "--legacy-bytes",
+    default="cp1252",
+    show_default=True,
+    help="How to read bytes that are not UTF-8 (cp1252, replace, replace=TEXT, error)",
+)
+@click.option(
+    "--field-charset",
+    type=click.Choice(["utf8", "ascii", "fold"]),
+    default="utf8",
+    show_default=True,
+    help="Characters allowed in column names; fold transliterates to ASCII",
+)
+def extract_scorefile(qvfile, duplicate_fields, out_format, legacy_bytes, field_charset):
     """
     Extracts the scorefile from the provided Quiver file and saves it as a .sc file.
     """
     records = []
+    try:
+        errors = legacy_error_handler(legacy_bytes)
+    except ValueError as e:
+        raise click.BadParameter(str(e), param_hint="--legacy-bytes")
 
-    with open(qvfile, "r") as qv:
+    with open(qvfile, "r", encoding="utf-8", errors=errors) as qv:
         for line in qv:
             if line.startswith("QV_SCORE"):
                 splits = line.split()
                 tag = splits[1]
 
-                try:
-                    scores = {
-                        entry[0]: float(entry[1])
-                        for entry in (s.split("=") for s in splits[2].split("|"))
-                    }
-                except ValueError as e:
+                fields = [s.split("=", 1) for s in splits[2].split("|")]
+                if any(len(kv) != 2 or not kv[0] for kv in fields):
                     click.secho(
-                        f"❌ Failed parsing scores for tag {tag}: {e}",
+                        f"❌ Failed parsing scores for tag {tag}: malformed QV_SCORE line",
                         fg="red",
                         err=True,
                     )
                     continue
+                try:
+                    fields = [(normalize_field(k, field_charset), v) for k, v in fields]
+                    fields = resolve_duplicates(fields, duplicate_fields, tag)
+                except ValueError as e:
+                    click.secho(f"❌ {e}", fg="red", err=True)
+                    sys.exit(1)
+                # Typed values, so pandas keeps numeric columns numeric
+                scores = {name: parse_score_value(value) for name, value in fields.items()}
 
                 scores["tag"] = tag
                 records.append(scores)
@@ -48,8 +90,19 @@ def extract_scorefile(qvfile):
 
     df = pd.DataFrame.from_records(records)
 
-    outfn = os.path.splitext(qvfile)[0] + ".sc"
-    df.to_csv(outfn, sep="\t", na_rep="NaN", index=False)
+    if out_format == "parquet":
+        outfn = os.path.splitext(qvfile)[0] + ".parquet"
+        # Nullable dtypes, so missing scores are nulls and int and bool
+        # columns with gaps keep their type
+        df.convert_dtypes().to_parquet(outfn, index=False)
+    elif out_format in ("json", "jsonl"):
+        outfn = os.path.splitext(qvfile)[0] + "." + out_format
+        # tag first; missing scores become null
+        df = df[["tag"] + [c for c in df.columns if c != "tag"]]
+        df.convert_dtypes().to_json(outfn, orient="records", lines=out_format == "jsonl")
+    else:
+        outfn = os.path.splitext(qvfile)[0] + ".sc"
+        df.to_csv(outfn, sep="\t", na_rep="NaN", index=False, encoding="utf-8")
 
     click.secho(f"✅ Scorefile written to: {outfn}", fg="green")
 
@@ -2,72 +2,230 @@ use clap::Parser;
 use std::collections::BTreeSet;
 use std::collections::HashMap;
 use std::fs::File;
-use std::io::{self, BufRead, BufReader, Write};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
 use std::path::Path;
+use std::str::FromStr;
+
+use quiver::charset::{decode_line, normalize_field, FieldCharset, LegacyBytes};
+use quiver::parquet::ScoreTableWriter;
+use quiver::scores::{json_string, resolve_duplicates, DuplicatePolicy, ScoreType, ScoreValue};
 
 /// Extracts the scorefile from a Quiver (.qv) file and writes it as a .sc file.
+///
+/// Values are typed: integer, float and boolean columns keep their type, and
+/// missing values are written as `NaN` in numeric columns and left empty in
+/// the others. A field repeated on one score line is resolved by
+/// `--duplicate-fields`.
+///
+/// Score lines that are not valid UTF-8 (e.g. field names written as
+/// Windows-1252 by older tools) are decoded by `--legacy-bytes`, so the
+/// output is always UTF-8. `--field-charset ascii` refuses non-ASCII column
+/// names and `--field-charset fold` transliterates them (`ΔG` -> `DeltaG`).
+///
+/// `--format parquet` writes `<name>.parquet` instead, with typed columns and
+/// nulls for missing values (needs the `parquet` feature). `--format jsonl`
+/// writes `<name>.jsonl` with one object per tag, and `--format json` a
+/// single array in `<name>.json`; every object has every column, with
+/// `null` for missing values.
+///
+/// Usage:
+///     qvscorefile my.qv
+///     qvscorefile my.qv --format parquet
+///     qvscorefile my.qv --format jsonl && jq 'select(.ddg < -40) | .tag' my.jsonl
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 struct Args {
     /// Quiver file to extract scores from
     qvfile: String,
+
+    /// Which value to use when a score line repeats a field (first, last, error, warn)
+    #[arg(long, default_value = "last", value_name = "POLICY")]
+    duplicate_fields: DuplicatePolicy,
+
+    /// Output format (tsv, parquet, json, jsonl)
+    #[arg(long, default_value = "tsv")]
+    format: ScorefileFormat,
+
+    /// How to read bytes that are not UTF-8 (cp1252, replace, replace=TEXT, error)
+    #[arg(long, default_value = "cp1252", value_name = "POLICY")]
+    legacy_bytes: LegacyBytes,
+
+    /// Characters allowed in column names (utf8, ascii, fold)
+    #[arg(long, default_value = "utf8", value_name = "CHARSET")]
+    field_charset: FieldCharset,
+}
+
+/// How score lines are read and their fields named.
+struct ReadOptions {
+    duplicates: DuplicatePolicy,
+    legacy_bytes: LegacyBytes,
+    field_charset: FieldCharset,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ScorefileFormat {
+    Tsv,
+    Parquet,
+    Json,
+    Jsonl,
+}
+
+impl FromStr for ScorefileFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "tsv" => Ok(ScorefileFormat::Tsv),
+            "parquet" => Ok(ScorefileFormat::Parquet),
+            "json" => Ok(ScorefileFormat::Json),
+            "jsonl" => Ok(ScorefileFormat::Jsonl),
+            other => Err(format!(
+                "Unknown format '{}', expected one of: tsv, parquet, json, jsonl",
+                other
+            )),
+        }
+    }
 }
 
 fn main() {
     let args = Args::parse();
 
-    if let Err(e) = extract_scorefile(&args.qvfile) {
+    let options = ReadOptions {
+        duplicates: args.duplicate_fields,
+        legacy_bytes: args.legacy_bytes,
+        field_charset: args.field_charset,
+    };
+    if let Err(e) = extract_scorefile(&args.qvfile, &options, args.format) {
         eprintln!("❌ Error: {}", e);
         std::process::exit(1);
     }
 }
 
-fn extract_scorefile(qvfile: &str) -> Result<(), String> {
+/// Call `f` with the tag and typed fields of every well-formed score line,
+/// in file order. With `loud`, malformed lines and repeated fields are
+/// reported on stderr; the second pass over the file stays quiet.
+fn for_each_record<F>(qvfile: &str, options: &ReadOptions, loud: bool, mut f: F) -> Result<(), String>
+where
+    F: FnMut(&str, Vec<(String, ScoreValue)>) -> Result<(), String>,
+{
     let file = File::open(qvfile).map_err(|e| format!("Failed to open file: {}", e))?;
-    let reader = BufReader::new(file);
+    let mut reader = BufReader::new(file);
+    let policy = options.duplicates;
 
-    let mut records: Vec<HashMap<String, String>> = Vec::new();
-    let mut all_keys: BTreeSet<String> = BTreeSet::new();
+    let mut buf = Vec::new();
+    for lineno in 0.. {
+        buf.clear();
+        let n = reader
+            .read_until(b'\n', &mut buf)
+            .map_err(|e| format!("Failed to read line {}: {}", lineno + 1, e))?;
+        if n == 0 {
+            break;
+        }
+        if !buf.starts_with(b"QV_SCORE") {
+            continue;
+        }
+        let line = decode_line(&buf, &options.legacy_bytes)
+            .map_err(|e| format!("Line {} in {}: {}", lineno + 1, qvfile, e))?;
+        let splits: Vec<&str> = line.split_whitespace().collect();
+        if splits.len() < 3 {
+            continue;
+        }
+        let tag = splits[1];
+        let mut fields = Vec::new();
+        let mut parse_error = false;
 
-    for line in reader.lines() {
-        let line = line.map_err(|e| format!("Failed to read line: {}", e))?;
-        if line.starts_with("QV_SCORE") {
-            let splits: Vec<&str> = line.split_whitespace().collect();
-            if splits.len() < 3 {
-                continue;
+        for s in splits[2].split('|') {
+            let mut kv = s.splitn(2, '=');
+            let key = normalize_field(kv.next().unwrap_or(""), options.field_charset)
+                .map_err(|e| format!("Tag {} at line {} in {}: {}", tag, lineno + 1, qvfile, e))?
+                .into_owned();
+            let val = kv.next().unwrap_or("").to_string();
+            if key.is_empty() || val.is_empty() {
+                parse_error = true;
+                break;
             }
-            let tag = splits[1];
-            let mut scores = HashMap::new();
-            let mut parse_error = false;
-
-            for s in splits[2].split('|') {
-                let mut kv = s.splitn(2, '=');
-                let key = kv.next().unwrap_or("").to_string();
-                let val = kv.next().unwrap_or("");
-                if key.is_empty() || val.is_empty() {
-                    parse_error = true;
-                    break;
-                }
-                // Try to parse as float, but store as string for now
-                if val.parse::<f64>().is_err() {
-                    parse_error = true;
-                    break;
-                }
-                scores.insert(key.clone(), val.to_string());
-                all_keys.insert(key);
+            fields.push((key, val));
+        }
+        if parse_error {
+            if loud {
+                eprintln!(
+                    "❌ Failed parsing scores for tag {}: malformed QV_SCORE at line {} in {}",
+                    tag,
+                    lineno + 1,
+                    qvfile
+                );
             }
-            if parse_error {
-                eprintln!("❌ Failed parsing scores for tag {}: Malformed score string", tag);
-                continue;
+            continue;
+        }
+        let repeated = resolve_duplicates(&mut fields, policy)
+            .map_err(|e| format!("Tag {} at line {} in {}: {}", tag, lineno + 1, qvfile, e))?;
+        if loud && policy == DuplicatePolicy::Warn {
+            for key in repeated {
+                eprintln!("⚠️  Tag {} repeats score field '{}', keeping the last value", tag, key);
             }
-            scores.insert("tag".to_string(), tag.to_string());
-            all_keys.insert("tag".to_string());
-            records.push(scores);
         }
+        f(tag, fields.into_iter().map(|(k, v)| (k, ScoreValue::parse(&v))).collect())?;
     }
+    Ok(())
+}
+
+/// One output row: the typed fields plus the `tag` column.
+fn record(tag: &str, fields: Vec<(String, ScoreValue)>) -> HashMap<String, ScoreValue> {
+    let mut scores: HashMap<String, ScoreValue> = fields.into_iter().collect();
+    scores.insert("tag".to_string(), ScoreValue::Str(tag.to_string()));
+    scores
+}
+
+/// Read the archive twice: once to find the columns and their types, then
+/// again to write the rows one at a time, so memory stays flat however many
+/// entries there are.
+fn extract_scorefile(qvfile: &str, options: &ReadOptions, format: ScorefileFormat) -> Result<(), String> {
+    let mut column_types: HashMap<String, ScoreType> = HashMap::new();
+    // `tag` is always a column, so an archive without scores still gets a
+    // well-formed (header-only) scorefile
+    let mut all_keys: BTreeSet<String> = BTreeSet::from(["tag".to_string()]);
+    let mut rows = 0usize;
+    for_each_record(qvfile, options, true, |_, fields| {
+        for (key, value) in fields {
+            column_types
+                .entry(key.clone())
+                .and_modify(|t| *t = t.unify(value.score_type()))
+                .or_insert(value.score_type());
+            all_keys.insert(key);
+        }
+        rows += 1;
+        Ok(())
+    })?;
 
-    if records.is_empty() {
-        return Err("No score lines found in Quiver file.".to_string());
+    let columns: Vec<(String, ScoreType)> = all_keys
+        .iter()
+        .map(|k| (k.clone(), column_types.get(k).copied().unwrap_or(ScoreType::Str)))
+        .collect();
+    match format {
+        ScorefileFormat::Tsv => {}
+        ScorefileFormat::Parquet => {
+            let outfn = Path::new(qvfile).with_extension("parquet");
+            let failed = |e: io::Error| format!("Failed to write Parquet file: {}", e);
+            let mut writer = ScoreTableWriter::create(&outfn, &columns).map_err(failed)?;
+            for_each_record(qvfile, options, false, |tag, fields| {
+                writer.write_row(&record(tag, fields)).map_err(failed)
+            })?;
+            writer.finish().map_err(failed)?;
+            report(rows == 0, &outfn.to_string_lossy());
+            return Ok(());
+        }
+        ScorefileFormat::Json | ScorefileFormat::Jsonl => {
+            let lines = format == ScorefileFormat::Jsonl;
+            let outfn = Path::new(qvfile).with_extension(if lines { "jsonl" } else { "json" });
+            let failed = |e: io::Error| format!("Failed to write {}: {}", outfn.display(), e);
+            let mut writer = JsonWriter::create(&outfn, &columns, lines).map_err(failed)?;
+            for_each_record(qvfile, options, false, |tag, fields| {
+                writer.write(&record(tag, fields)).map_err(failed)
+            })?;
+            writer.finish().map_err(failed)?;
+            report(rows == 0, &outfn.to_string_lossy());
+            return Ok(());
+        }
     }
 
     // Output file name
@@ -89,18 +247,86 @@ fn extract_scorefile(qvfile: &str) -> Result<(), String> {
         .map_err(|e| format!("Failed to write header: {}", e))?;
 
     // Write records
-    for rec in &records {
+    for_each_record(qvfile, options, false, |tag, fields| {
+        let rec = record(tag, fields);
         let row: Vec<String> = all_keys
             .iter()
-            .map(|k| rec.get(k).cloned().unwrap_or_else(|| "NaN".to_string()))
+            .map(|k| match (rec.get(k), column_types.get(k)) {
+                (Some(value), Some(&ty)) => value.widen(ty).to_string(),
+                (Some(value), None) => value.to_string(),
+                (None, ty) => ty.map_or("NaN", |t| t.missing()).to_string(),
+            })
             .collect();
         wtr.write_record(&row)
-            .map_err(|e| format!("Failed to write row: {}", e))?;
-    }
+            .map_err(|e| format!("Failed to write row: {}", e))
+    })?;
     wtr.flush()
         .map_err(|e| format!("Failed to flush output: {}", e))?;
 
-    println!("✅ Scorefile written to: {}", outfn);
-
+    report(rows == 0, &outfn);
     Ok(())
 }
+
+/// Writes one JSON object per record, `tag` first and the other columns in
+/// header order, as JSON lines or as one array.
+struct JsonWriter {
+    out: BufWriter<File>,
+    order: Vec<(String, ScoreType)>,
+    lines: bool,
+    written: usize,
+}
+
+impl JsonWriter {
+    fn create(path: &Path, columns: &[(String, ScoreType)], lines: bool) -> io::Result<Self> {
+        let mut out = BufWriter::new(File::create(path)?);
+        let order = columns
+            .iter()
+            .filter(|(k, _)| k == "tag")
+            .chain(columns.iter().filter(|(k, _)| k != "tag"))
+            .cloned()
+            .collect();
+        if !lines {
+            write!(out, "[")?;
+        }
+        Ok(Self {
+            out,
+            order,
+            lines,
+            written: 0,
+        })
+    }
+
+    fn write(&mut self, rec: &HashMap<String, ScoreValue>) -> io::Result<()> {
+        let fields: Vec<String> = self
+            .order
+            .iter()
+            .map(|(k, ty)| {
+                let value = rec.get(k).map_or("null".to_string(), |v| v.widen(*ty).to_json());
+                format!("{}: {}", json_string(k), value)
+            })
+            .collect();
+        let object = format!("{{{}}}", fields.join(", "));
+        if self.lines {
+            writeln!(self.out, "{}", object)?;
+        } else {
+            write!(self.out, "{}\n  {}", if self.written == 0 { "" } else { "," }, object)?;
+        }
+        self.written += 1;
+        Ok(())
+    }
+
+    fn finish(mut self) -> io::Result<()> {
+        if !self.lines {
+            writeln!(self.out, "{}]", if self.written == 0 { "" } else { "\n" })?;
+        }
+        self.out.flush()
+    }
+}
+
+fn report(empty: bool, outfn: &str) {
+    if empty {
+        println!("⚠️  No score lines found, wrote an empty scorefile to: {}", outfn);
+    } else {
+        println!("✅ Scorefile written to: {}", outfn);
+    }
+}
@@ -4,10 +4,15 @@ Slice a specific set of tags from a Quiver file into another Quiver file.
 
 Usage:
     qvslice.py big.qv tag1 tag2 ... > sliced.qv
-    echo "tag1 tag2" | qvslice.py big.qv > sliced.qv
+    echo "tag1 tag2" | qvslice.py big.qv - > sliced.qv
+    qvslice.py big.qv --pattern 'design_17_*' > design_17.qv
+    qvslice.py big.qv --pattern '^design_(17|18)_seed[0-4]$' --regex > seeds.qv
+    qvslice.py big.qv tag1 tag2 --strict > sliced.qv
 """
 
+import re
 import sys
+
 import click
 from quiver import Quiver
 
@@ -15,38 +20,61 @@ from quiver import Quiver
 @click.command()
 @click.argument("quiver_file", type=click.Path(exists=True, dir_okay=False))
 @click.argument("tags", nargs=-1)
-def qvslice(quiver_file, tags):
+@click.option(
+    "--pattern",
+    default=None,
+    help="Also slice every tag matching this glob (or regex, with --regex).",
+)
+@click.option(
+    "--regex", is_flag=True, help="Read --pattern as a regular expression instead of a glob."
+)
+@click.option(
+    "--strict", is_flag=True, help="Fail without writing anything if any requested tag is missing."
+)
+def qvslice(quiver_file, tags, pattern, regex, strict):
     """
     Extract selected TAGS from QUIVER_FILE and output to stdout.
-    If no TAGS are provided as arguments, they are read from stdin.
+    A TAG of - reads more tags, whitespace-separated, from stdin.
     """
-    tag_list = list(tags)
+    if regex and pattern is None:
+        raise click.UsageError("--regex needs --pattern")
+    tag_list = [tag for tag in tags if tag != "-"]
 
-    # ✅ Read tags from stdin if no arguments are provided
-    if not tag_list and not sys.stdin.isatty():
+    # ✅ Read tags from stdin only when asked to, so a silent stdin (cron,
+    # notebooks) never blocks
+    if "-" in tags:
         stdin_data = sys.stdin.read()
         tag_list.extend(stdin_data.strip().split())
 
     # ✅ Clean and validate tag list
     tag_list = [tag.strip() for tag in tag_list if tag.strip()]
-    if not tag_list:
+    if not tag_list and pattern is None:
         click.secho(
-            "❌ No tags provided. Provide tags as arguments or via stdin.",
+            "❌ No tags provided. Provide tags as arguments, or - to read them from stdin.",
             fg="red",
             err=True,
         )
         sys.exit(1)
 
     qv = Quiver(quiver_file, "r")
-    qv_lines, found_tags = qv.get_struct_list(tag_list)
+    if pattern is not None:
+        try:
+            tag_list.extend(qv.tags_matching(pattern, regex))
+        except re.error as e:
+            click.secho(f"❌ Invalid tag regex '{pattern}': {e}", fg="red", err=True)
+            sys.exit(2)
+    try:
+        result = qv.slice(tag_list, strict=strict)
+    except KeyError as e:
+        click.secho(f"❌ {e.args[0]}", fg="red", err=True)
+        sys.exit(1)
 
-    # Warn about missing tags
-    missing_tags = [tag for tag in tag_list if tag not in found_tags]
-    for tag in missing_tags:
-        click.secho(f"⚠️  Tag not found in Quiver file: {tag}", fg="yellow", err=True)
+    # Report problems on stderr only, so stdout stays a valid Quiver stream
+    for warning in result.warnings:
+        click.secho(f"⚠️  [{warning.code}] {warning.message}", fg="yellow", err=True)
 
     # Output sliced content
-    click.echo(qv_lines, nl=False)
+    click.echo(result.data, nl=False)
 
 
 if __name__ == "__main__":
@@ -1,34 +1,56 @@
 use clap::Parser;
-use std::collections::HashSet;
 use std::io::{self, Read, Write};
 use std::process;
 
-mod quiver;
+use quiver::select::TagPattern;
 use quiver::{Quiver, QuiverError};
 
 /// Slice a specific set of tags from a Quiver file into another Quiver file.
 ///
+/// `--pattern` adds every tag matching a glob (`*` and `?`, matching the
+/// whole tag), or with `--regex` a regular expression (matching anywhere in
+/// the tag unless anchored), so a family of designs need not be listed.
+///
 /// Usage:
 ///     qvslice big.qv tag1 tag2 ... > sliced.qv
-///     echo "tag1 tag2" | qvslice big.qv > sliced.qv
+///     echo "tag1 tag2" | qvslice big.qv - > sliced.qv
+///     qvslice big.qv --label round3 > round3.qv
+///     qvslice big.qv --pattern 'design_17_*' > design_17.qv
+///     qvslice big.qv --pattern '^design_(17|18)_seed[0-4]$' --regex > seeds.qv
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 struct Args {
     /// Quiver file to slice from
     quiver_file: String,
 
-    /// Tags to extract (can be empty if piped via stdin)
+    /// Tags to extract; `-` reads more, whitespace-separated, from stdin
     tags: Vec<String>,
+
+    /// Also slice every entry carrying this label
+    #[arg(long)]
+    label: Option<String>,
+
+    /// Also slice every tag matching this glob (or regex, with --regex)
+    #[arg(long)]
+    pattern: Option<String>,
+
+    /// Read --pattern as a regular expression instead of a glob
+    #[arg(long, requires = "pattern")]
+    regex: bool,
+
+    /// Fail without writing anything if any requested tag is missing
+    #[arg(long)]
+    strict: bool,
 }
 
 fn main() {
     let args = Args::parse();
 
-    // Collect tags from CLI and possibly from stdin (piped)
-    let mut tag_list: Vec<String> = args.tags.clone();
-
-    // If no tags provided as arguments and stdin is piped, read from stdin
-    if tag_list.is_empty() && !atty::is(atty::Stream::Stdin) {
+    // Collect tags from CLI, and from stdin only when asked to with "-", so
+    // an open but silent stdin (cron, notebooks) cannot block the tool
+    let read_stdin = args.tags.iter().any(|t| t == "-");
+    let mut tag_list: Vec<String> = args.tags.iter().filter(|t| *t != "-").cloned().collect();
+    if read_stdin {
         let mut stdin_data = String::new();
         if let Err(e) = io::stdin().read_to_string(&mut stdin_data) {
             eprintln!("❌ Failed to read from stdin: {}", e);
@@ -44,8 +66,9 @@ fn main() {
         .filter(|s| !s.is_empty())
         .collect();
 
-    if tag_list.is_empty() {
-        eprintln!("❌ No tags provided. Provide tags as arguments or via stdin.");
+    // An empty list piped on stdin is a no-op, not an error
+    if tag_list.is_empty() && args.label.is_none() && args.pattern.is_none() && !read_stdin {
+        eprintln!("❌ No tags provided. Provide tags as arguments, or - to read them from stdin.");
         process::exit(1);
     }
 
@@ -53,30 +76,42 @@ fn main() {
         Ok(q) => q,
         Err(e) => {
             eprintln!("❌ Failed to open Quiver file: {:?}", e);
-            process::exit(1);
+            process::exit(e.exit_code());
         }
     };
 
-    let (qv_lines, found_tags) = match qv.get_struct_list(&tag_list) {
+    if let Some(label) = &args.label {
+        tag_list.extend(qv.list_by_label(label));
+    }
+    if let Some(pattern) = &args.pattern {
+        match TagPattern::new(pattern, args.regex) {
+            Ok(pattern) => tag_list.extend(qv.tags_matching(&pattern)),
+            Err(e) => {
+                eprintln!("❌ {}", e);
+                process::exit(2);
+            }
+        }
+    }
+
+    let result = match qv.slice(&tag_list, args.strict) {
         Ok(res) => res,
+        Err(QuiverError::TagNotFound(missing)) => {
+            eprintln!("❌ Tags not found in Quiver file: {}", missing);
+            process::exit(1);
+        }
         Err(e) => {
             eprintln!("❌ Failed to extract tags: {:?}", e);
-            process::exit(1);
+            process::exit(e.exit_code());
         }
     };
 
-    // Warn about missing tags
-    let found_set: HashSet<_> = found_tags.iter().collect();
-    for tag in &tag_list {
-        if !found_set.contains(tag) {
-            eprintln!("⚠️  Tag not found in Quiver file: {}", tag);
-        }
-    }
+    // Report problems on stderr only, so stdout stays a valid Quiver stream
+    result.warnings.report();
 
     // Output sliced content to stdout
     let stdout = io::stdout();
     let mut handle = stdout.lock();
-    if let Err(e) = handle.write_all(qv_lines.as_bytes()) {
+    if let Err(e) = handle.write_all(result.data.as_bytes()) {
         eprintln!("❌ Failed to write output: {}", e);
         process::exit(1);
     }
@@ -36,9 +36,12 @@ def qvsplit(file, ntags, prefix, output_dir):
     click.secho(f"🔪 Splitting into chunks of {ntags} tags...", fg="green")
 
     q = Quiver(file, "r")
-    q.split(ntags, output_dir, prefix)
+    written = q.split(ntags, output_dir, prefix)
 
-    click.secho(f"✅ Files written to {output_dir} with prefix '{prefix}'", fg="green")
+    if written == 0:
+        click.secho(f"✅ {file} has no entries, no files written", fg="green")
+    else:
+        click.secho(f"✅ {written} file(s) written to {output_dir} with prefix '{prefix}'", fg="green")
 
 
 if __name__ == "__main__":
@@ -1,15 +1,32 @@
 use clap::Parser;
 use std::process;
 
-mod quiver;
-use quiver::{Quiver, QuiverError};
+use quiver::shard::{parse_size, ScoreBuckets};
+use quiver::Quiver;
 
 /// Split a Quiver (.qv) file into multiple smaller Quiver files,
 /// each containing a specified number of tags.
 ///
+/// With `--max-bytes` the shards are cut by size instead: a new one is
+/// started before an entry that would take the current one past the
+/// budget, and no structure is ever split across files.
+///
+/// With `--round-robin N` the entries are dealt out to N files in turn
+/// instead of in contiguous chunks, so every file gets a mix of early and
+/// late designs, which balances array jobs working on the files.
+///
+/// With `--by-score FIELD --edges 70,90` the entries are sorted into one
+/// file per range of that score instead (`split_lt70.qv`, `split_70-90.qv`,
+/// `split_ge90.qv`, and `split_missing.qv` for entries without it), e.g. to
+/// triage a large design set by quality.
+///
 /// Usage:
 ///     qvsplit mydesigns.qv 100
 ///     → produces: split_000.qv, split_001.qv, ...
+///     qvsplit mydesigns.qv 100 --label round3
+///     qvsplit mydesigns.qv --max-bytes 2G
+///     qvsplit mydesigns.qv --round-robin 8
+///     qvsplit mydesigns.qv --by-score plddt --edges 70,90
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 struct Args {
@@ -17,7 +34,27 @@ struct Args {
     file: String,
 
     /// Number of tags per split file
-    ntags: usize,
+    #[arg(
+        required_unless_present_any = ["max_bytes", "round_robin", "by_score"],
+        conflicts_with_all = ["max_bytes", "round_robin", "by_score"]
+    )]
+    ntags: Option<usize>,
+
+    /// Largest size of a split file, e.g. 500M or 2G, instead of NTAGS
+    #[arg(long, value_parser = parse_size, conflicts_with_all = ["round_robin", "by_score"])]
+    max_bytes: Option<u64>,
+
+    /// Deal the entries out to this many files in turn, instead of NTAGS
+    #[arg(long, value_name = "NFILES", conflicts_with = "by_score")]
+    round_robin: Option<usize>,
+
+    /// Split by ranges of this score field (or expression, e.g. 'ddg - ref_ddg') instead of NTAGS
+    #[arg(long, requires = "edges")]
+    by_score: Option<String>,
+
+    /// Increasing bucket edges for --by-score, e.g. 70,90
+    #[arg(long, value_delimiter = ',', requires = "by_score")]
+    edges: Vec<f64>,
 
     /// Prefix for the output files (default: "split")
     #[arg(long, default_value = "split")]
@@ -26,34 +63,90 @@ struct Args {
     /// Directory to save the split files (default: current directory)
     #[arg(long, default_value = ".")]
     output_dir: String,
+
+    /// Only split entries carrying this label
+    #[arg(long)]
+    label: Option<String>,
 }
 
 fn main() {
     let args = Args::parse();
 
-    if args.ntags == 0 {
-        eprintln!("❌ NTAGS must be a positive integer.");
+    if args.ntags == Some(0) || args.max_bytes == Some(0) || args.round_robin == Some(0) {
+        eprintln!("❌ NTAGS, --max-bytes and --round-robin must be positive integers.");
         process::exit(1);
     }
 
+    if let Some(field) = &args.by_score {
+        let buckets = match ScoreBuckets::new(field, &args.edges) {
+            Ok(buckets) => buckets,
+            Err(e) => {
+                eprintln!("❌ {}", e);
+                process::exit(2);
+            }
+        };
+        split_by_score(&args, &buckets);
+        return;
+    }
+
     println!("📂 Reading: {}", args.file);
-    println!("🔪 Splitting into chunks of {} tags...", args.ntags);
+    let split = |q: Quiver| match (args.ntags, args.max_bytes, args.round_robin) {
+        (_, Some(max_bytes), _) => {
+            println!("🔪 Splitting into files of at most {} bytes...", max_bytes);
+            q.split_by_size(max_bytes, &args.output_dir, &args.prefix, args.label.as_deref())
+        }
+        (_, None, Some(nfiles)) => {
+            println!("🔪 Dealing entries out to {} files...", nfiles);
+            q.split_round_robin(nfiles, &args.output_dir, &args.prefix, args.label.as_deref())
+        }
+        (ntags, None, None) => {
+            let ntags = ntags.unwrap_or(1);
+            println!("🔪 Splitting into chunks of {} tags...", ntags);
+            q.split(ntags, &args.output_dir, &args.prefix, args.label.as_deref())
+        }
+    };
 
-    match Quiver::new(&args.file, "r") {
-        Ok(q) => {
-            if let Err(e) = q.split(args.ntags, &args.output_dir, &args.prefix) {
+    let written = match Quiver::new(&args.file, "r") {
+        Ok(q) => match split(q) {
+            Ok(written) => written,
+            Err(e) => {
                 eprintln!("❌ Error during split: {:?}", e);
-                process::exit(1);
+                process::exit(e.exit_code());
             }
-        }
+        },
         Err(e) => {
             eprintln!("❌ Failed to open Quiver file: {:?}", e);
-            process::exit(1);
+            process::exit(e.exit_code());
         }
+    };
+
+    if written == 0 {
+        println!("✅ {} has no entries, no files written", args.file);
+    } else {
+        println!(
+            "✅ {} file(s) written to {} with prefix '{}'",
+            written, args.output_dir, args.prefix
+        );
     }
+}
 
-    println!(
-        "✅ Files written to {} with prefix '{}'",
-        args.output_dir, args.prefix
-    );
+fn split_by_score(args: &Args, buckets: &ScoreBuckets) {
+    println!("📂 Reading: {}", args.file);
+    println!("🔪 Splitting by {} ranges...", buckets.key);
+    let written = Quiver::new(&args.file, "r")
+        .and_then(|q| q.split_by_score(buckets, &args.output_dir, &args.prefix, args.label.as_deref()));
+    let written = match written {
+        Ok(written) => written,
+        Err(e) => {
+            eprintln!("❌ Error during split: {:?}", e);
+            process::exit(e.exit_code());
+        }
+    };
+
+    if written.is_empty() {
+        println!("✅ {} has no entries, no files written", args.file);
+    }
+    for (bucket, count) in &written {
+        println!("✅ {} entries written to {}_{}.qv", count, args.prefix, bucket);
+    }
 }
@@ -0,0 +1,51 @@
+use clap::Parser;
+use std::process;
+
+use quiver::audit::audit_tags;
+use quiver::Quiver;
+
+/// Find tags that differ only by case, surrounding whitespace or unicode
+/// confusables, and suggest renames that make them distinct.
+///
+/// Suggested renames are printed as `old<TAB>new` lines; a summary of each
+/// group of look-alike tags goes to stderr.
+///
+/// Usage:
+///     qvtagaudit my.qv > renames.tsv
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// Quiver file to audit
+    quiver_file: String,
+}
+
+fn main() {
+    let args = Args::parse();
+
+    let qv = match Quiver::new(&args.quiver_file, "r") {
+        Ok(q) => q,
+        Err(e) => {
+            eprintln!("❌ Failed to open Quiver file: {:?}", e);
+            process::exit(e.exit_code());
+        }
+    };
+
+    let groups = audit_tags(&qv.get_tags());
+    if groups.is_empty() {
+        eprintln!("✅ No look-alike tags in {}", args.quiver_file);
+        return;
+    }
+
+    for group in &groups {
+        eprintln!(
+            "⚠️  {} tags look like '{}': {}",
+            group.tags.len(),
+            group.canonical,
+            group.tags.join(", ")
+        );
+        for (old, new) in &group.renames {
+            println!("{}\t{}", old, new);
+        }
+    }
+    eprintln!("⚠️  Found {} group(s) of look-alike tags", groups.len());
+}
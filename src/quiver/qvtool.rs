@@ -0,0 +1,517 @@
+use clap::{Parser, Subcommand};
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+
+use quiver::layout::{write_meta, ExtractLayout};
+use quiver::peek::{head, tail};
+use quiver::reproducible;
+use quiver::scores::{json_string, ScoreValue};
+use quiver::shard::unsplit;
+use quiver::warnings::{WarningCode, Warnings};
+use quiver::{format_header_lines, Entry, Quiver, QuiverError};
+
+/// Everyday Quiver file operations in one self-contained binary.
+///
+/// Covers what the Python scripts do (listing, slicing, splitting,
+/// extracting, score tables, merging, renaming and per-entry metadata), plus
+/// a look at the first or last entries of an archive still being written,
+/// for machines without a Python environment, such as HPC compute nodes. Tags for `slice`,
+/// `extract` and `rename` are read from stdin when one of them is `-`.
+///
+/// With `--json`, every subcommand prints one JSON object on stdout instead
+/// of its usual output: `"ok": true`, its results and its `"warnings"`, or
+/// `"ok": false` and the `"error"` (category, exit status and message).
+///
+/// Usage:
+///     qvtool list my.qv
+///     qvtool tail running.qv -n 5 --tags
+///     qvtool slice my.qv tag1 tag2 > sliced.qv
+///     qvtool split my.qv 100 --output-dir shards/
+///     qvtool extract my.qv -o pdbs/ --layout per-tag
+///     qvtool scores my.qv > scores.tsv
+///     qvtool scores my.qv design_7 design_12
+///     qvtool merge a.qv b.qv --output merged.qv
+///     qvtool merge b.qv a.qv --output merged.qv --reproducible
+///     qvls my.qv | sed 's/$/_v2/' | qvtool rename my.qv - > renamed.qv
+///     qvtool meta my.qv design_7 --set model=af2 --set seed=7
+///     qvtool --json scores my.qv design_7
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    #[command(subcommand)]
+    command: Command,
+
+    /// Print one JSON object with the result, or the error, on stdout
+    #[arg(long, global = true)]
+    json: bool,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Print the tags of a Quiver file
+    List {
+        quiver_file: String,
+
+        /// Only list entries carrying this label
+        #[arg(long)]
+        label: Option<String>,
+    },
+    /// Write the first N entries to stdout as a Quiver stream
+    Head {
+        quiver_file: String,
+
+        /// Number of entries
+        #[arg(short, default_value_t = 10, value_name = "N")]
+        n: usize,
+
+        /// Print only the tags
+        #[arg(long)]
+        tags: bool,
+    },
+    /// Write the last N entries to stdout, reading only the end of the file
+    Tail {
+        quiver_file: String,
+
+        /// Number of entries
+        #[arg(short, default_value_t = 10, value_name = "N")]
+        n: usize,
+
+        /// Print only the tags
+        #[arg(long)]
+        tags: bool,
+    },
+    /// Write the entries with the given tags to stdout as a Quiver file
+    Slice {
+        quiver_file: String,
+
+        /// Tags to slice (`-` reads them from stdin)
+        tags: Vec<String>,
+
+        /// Fail without writing anything if any requested tag is missing
+        #[arg(long)]
+        strict: bool,
+    },
+    /// Split a Quiver file into files of NTAGS entries each
+    Split {
+        quiver_file: String,
+
+        /// Number of tags per split file
+        ntags: usize,
+
+        /// Prefix for the output files
+        #[arg(long, default_value = "split")]
+        prefix: String,
+
+        /// Directory to save the split files
+        #[arg(long, default_value = ".")]
+        output_dir: String,
+    },
+    /// Extract entries (all, or the given tags) as PDB or mmCIF files
+    Extract {
+        quiver_file: String,
+
+        /// Tags to extract (`-` reads them from stdin; all entries if none)
+        tags: Vec<String>,
+
+        /// Directory to save extracted files
+        #[arg(short, long, default_value = ".", value_name = "DIR")]
+        output_dir: String,
+
+        /// Where entries are written: DIR/<tag>.pdb (flat) or DIR/<tag>/model.pdb and meta.json (per-tag)
+        #[arg(long, default_value = "flat")]
+        layout: ExtractLayout,
+    },
+    /// Print the scores of every entry, or of the given tags, as a tab-separated table
+    Scores {
+        quiver_file: String,
+
+        /// Only look up these tags (`-` reads them from stdin), reading just their entries
+        tags: Vec<String>,
+    },
+    /// Concatenate Quiver files into a new one, refusing duplicate tags
+    Merge {
+        #[arg(required = true)]
+        quiver_files: Vec<String>,
+
+        /// Merged Quiver file to write (must not exist)
+        #[arg(short, long)]
+        output: String,
+
+        /// Order entries by tag, normalize line endings and drop the build
+        /// time from the header, for byte-identical rebuilds
+        #[arg(long)]
+        reproducible: bool,
+    },
+    /// Write a copy with every tag replaced, in order, to stdout
+    Rename {
+        quiver_file: String,
+
+        /// New tags, one per entry (`-` reads them from stdin)
+        new_tags: Vec<String>,
+    },
+    /// Print the metadata fields of an entry, or set them in place
+    Meta {
+        quiver_file: String,
+
+        tag: String,
+
+        /// Field to set, rewriting the file atomically (repeatable)
+        #[arg(long, value_name = "KEY=VALUE")]
+        set: Vec<String>,
+    },
+}
+
+fn main() {
+    let args = Args::parse();
+
+    match run(args.command, args.json) {
+        Ok(report) if args.json => println!("{}", report.to_json()),
+        Ok(report) => report.warnings.report(),
+        Err(e) if args.json => {
+            println!("{{\"ok\": false, \"error\": {}}}", e.to_json());
+            process::exit(e.exit_code());
+        }
+        Err(e) => {
+            eprintln!("❌ Error: {:?}", e);
+            process::exit(e.exit_code());
+        }
+    }
+}
+
+/// What a subcommand did, for `--json`: its results as JSON values in
+/// order, and the warnings it raised (printed on stderr without `--json`).
+#[derive(Default)]
+struct Report {
+    fields: Vec<(&'static str, String)>,
+    warnings: Warnings,
+}
+
+impl Report {
+    fn field(&mut self, name: &'static str, value: String) {
+        self.fields.push((name, value));
+    }
+
+    fn to_json(&self) -> String {
+        let mut out = String::from("{\"ok\": true");
+        for (name, value) in &self.fields {
+            out.push_str(&format!(", {}: {}", json_string(name), value));
+        }
+        out.push_str(&format!(", \"warnings\": {}}}", self.warnings.to_json()));
+        out
+    }
+}
+
+fn json_array<I: IntoIterator<Item = String>>(items: I) -> String {
+    format!("[{}]", items.into_iter().collect::<Vec<_>>().join(", "))
+}
+
+fn json_strings<S: AsRef<str>>(items: &[S]) -> String {
+    json_array(items.iter().map(|s| json_string(s.as_ref())))
+}
+
+/// `fields` as a JSON object, with values parsed like score values when
+/// `typed`.
+fn json_object(fields: &[(String, String)], typed: bool) -> String {
+    let members: Vec<String> = fields
+        .iter()
+        .map(|(k, v)| {
+            let value = if typed { ScoreValue::parse(v).to_json() } else { json_string(v) };
+            format!("{}: {}", json_string(k), value)
+        })
+        .collect();
+    format!("{{{}}}", members.join(", "))
+}
+
+fn entry_json(entry: &Entry) -> String {
+    format!(
+        "{{\"tag\": {}, \"format\": {}, \"score\": {}, \"meta\": {}, \"lines\": {}}}",
+        json_string(&entry.tag),
+        json_string(entry.format.name()),
+        entry.score.as_deref().map_or("null".to_string(), json_string),
+        json_object(&entry.meta, false),
+        json_strings(&entry.lines)
+    )
+}
+
+/// `tags`, with a `-` among them replaced by the whitespace-separated tags
+/// read from stdin. Stdin is never read otherwise, so an open but silent
+/// stdin (cron, notebooks) cannot block the tool.
+fn with_piped_tags(tags: Vec<String>) -> io::Result<Vec<String>> {
+    let read_stdin = tags.iter().any(|t| t == "-");
+    let mut tags: Vec<String> = tags.into_iter().filter(|t| t != "-").collect();
+    if read_stdin {
+        let mut stdin_data = String::new();
+        io::stdin().read_to_string(&mut stdin_data)?;
+        tags.extend(stdin_data.split_whitespace().map(|s| s.to_string()));
+    }
+    Ok(tags)
+}
+
+fn run(command: Command, json: bool) -> Result<Report, QuiverError> {
+    let mut report = Report::default();
+    match command {
+        Command::List { quiver_file, label } => {
+            let qv = Quiver::new(&quiver_file, "r")?;
+            let tags = match &label {
+                Some(label) => qv.list_by_label(label),
+                None => qv.get_tags(),
+            };
+            if json {
+                report.field("tags", json_strings(&tags));
+            } else {
+                let mut out = BufWriter::new(io::stdout().lock());
+                for tag in tags {
+                    writeln!(out, "{}", tag)?;
+                }
+                out.flush()?;
+            }
+        }
+        Command::Head { quiver_file, n, tags } => print_entries(&head(&quiver_file, n)?, tags, json, &mut report)?,
+        Command::Tail { quiver_file, n, tags } => print_entries(&tail(&quiver_file, n)?, tags, json, &mut report)?,
+        Command::Slice {
+            quiver_file,
+            tags,
+            strict,
+        } => {
+            let tags = with_piped_tags(tags)?;
+            let result = Quiver::new(&quiver_file, "r")?.slice(&tags, strict)?;
+            if json {
+                report.field("found_tags", json_strings(&result.found_tags));
+                report.field("missing_tags", json_strings(&result.missing_tags));
+                report.field("data", json_string(&result.data));
+                report.warnings = result.warnings;
+            } else {
+                // Report problems on stderr only, so stdout stays a valid Quiver stream
+                result.warnings.report();
+                io::stdout().lock().write_all(result.data.as_bytes())?;
+            }
+        }
+        Command::Split {
+            quiver_file,
+            ntags,
+            prefix,
+            output_dir,
+        } => {
+            if ntags == 0 {
+                return Err(QuiverError::InvalidOperation("NTAGS must be a positive integer".to_string()));
+            }
+            let written = Quiver::new(&quiver_file, "r")?.split(ntags, &output_dir, &prefix, None)?;
+            if !json {
+                eprintln!("✅ {} file(s) written to {} with prefix '{}'", written, output_dir, prefix);
+            }
+            report.field("files", written.to_string());
+            report.field("output_dir", json_string(&output_dir));
+            report.field("prefix", json_string(&prefix));
+        }
+        Command::Extract {
+            quiver_file,
+            tags,
+            output_dir,
+            layout,
+        } => {
+            let files = extract(
+                &quiver_file,
+                with_piped_tags(tags)?,
+                Path::new(&output_dir),
+                layout,
+                &mut report.warnings,
+            )?;
+            if !json {
+                eprintln!(
+                    "✅ Extracted {} file(s) from {} to {}",
+                    files.len(),
+                    quiver_file,
+                    output_dir
+                );
+            }
+            let files: Vec<String> = files.iter().map(|f| f.display().to_string()).collect();
+            report.field("files", json_strings(&files));
+        }
+        Command::Scores { quiver_file, tags } => {
+            let records: Vec<(String, Vec<(String, String)>)> = if tags.is_empty() {
+                Quiver::new(&quiver_file, "r")?.get_score_records()?
+            } else {
+                let lookup = Quiver::open_indexed(&quiver_file)?.get_scores_many(&with_piped_tags(tags)?)?;
+                for tag in &lookup.missing {
+                    report
+                        .warnings
+                        .push(WarningCode::MissingTag, format!("Tag not found in Quiver file: {}", tag));
+                }
+                lookup
+                    .scores
+                    .into_iter()
+                    .map(|(tag, fields)| (tag, fields.into_iter().map(|(k, v)| (k, v.to_string())).collect()))
+                    .collect()
+            };
+            if json {
+                let rows = records.iter().map(|(tag, fields)| {
+                    format!("{{\"tag\": {}, \"scores\": {}}}", json_string(tag), json_object(fields, true))
+                });
+                report.field("scores", json_array(rows));
+                return Ok(report);
+            }
+            let mut columns: Vec<&str> = Vec::new();
+            for (_, fields) in &records {
+                for (key, _) in fields {
+                    if !columns.contains(&key.as_str()) {
+                        columns.push(key);
+                    }
+                }
+            }
+            let mut out = BufWriter::new(io::stdout().lock());
+            writeln!(out, "tag\t{}", columns.join("\t"))?;
+            for (tag, fields) in &records {
+                let row: Vec<&str> = columns
+                    .iter()
+                    .map(|c| fields.iter().find(|(k, _)| k == c).map_or("", |(_, v)| v.as_str()))
+                    .collect();
+                writeln!(out, "{}\t{}", tag, row.join("\t"))?;
+            }
+            out.flush()?;
+        }
+        Command::Merge { quiver_files, output, reproducible } => {
+            let inputs: Vec<PathBuf> = quiver_files.iter().map(PathBuf::from).collect();
+            let entries = if reproducible {
+                reproducible::merge(&inputs, Path::new(&output))?
+            } else {
+                unsplit(&inputs, Path::new(&output), None)?.entries
+            };
+            if !json {
+                eprintln!(
+                    "✅ Merged {} entries from {} file(s) into {}",
+                    entries,
+                    inputs.len(),
+                    output
+                );
+            }
+            report.field("entries", entries.to_string());
+            report.field("output", json_string(&output));
+        }
+        Command::Rename { quiver_file, new_tags } => {
+            let new_tags = with_piped_tags(new_tags)?;
+            let qv = Quiver::new(&quiver_file, "r")?;
+            if new_tags.len() != qv.size() {
+                return Err(QuiverError::InvalidOperation(format!(
+                    "Number of tags in file ({}) does not match number of tags provided ({})",
+                    qv.size(),
+                    new_tags.len()
+                )));
+            }
+            let mut data = Vec::new();
+            let mut out: Box<dyn Write> = if json {
+                Box::new(&mut data)
+            } else {
+                Box::new(BufWriter::new(io::stdout().lock()))
+            };
+            out.write_all(format_header_lines(&qv.get_header()).as_bytes())?;
+            for (entry, new_tag) in qv.iter()?.zip(new_tags) {
+                let mut entry = entry?;
+                entry.tag = new_tag;
+                out.write_all(entry.to_text().as_bytes())?;
+            }
+            out.flush()?;
+            drop(out);
+            report.field("entries", qv.size().to_string());
+            if json {
+                report.field("data", json_string(&String::from_utf8_lossy(&data)));
+            }
+        }
+        Command::Meta { quiver_file, tag, set } => {
+            report.field("tag", json_string(&tag));
+            if set.is_empty() {
+                let meta = Quiver::new(&quiver_file, "r")?.get_meta(&tag)?;
+                if json {
+                    report.field("meta", json_object(&meta, false));
+                } else {
+                    let mut out = BufWriter::new(io::stdout().lock());
+                    for (key, value) in meta {
+                        writeln!(out, "{}\t{}", key, value)?;
+                    }
+                    out.flush()?;
+                }
+            } else {
+                let mut qv = Quiver::new(&quiver_file, "w")?;
+                for field in &set {
+                    let Some((key, value)) = field.split_once('=') else {
+                        return Err(QuiverError::InvalidOperation(format!(
+                            "Invalid --set '{}', expected KEY=VALUE",
+                            field
+                        )));
+                    };
+                    qv.add_meta(&tag, key, value)?;
+                }
+                if !json {
+                    eprintln!("✅ Set {} metadata field(s) of {}", set.len(), tag);
+                }
+                report.field("set", set.len().to_string());
+            }
+        }
+    }
+    Ok(report)
+}
+
+/// Write `entries` to stdout, or only their tags with `tags_only`; with
+/// `json`, add them to `report` instead.
+fn print_entries(entries: &[Entry], tags_only: bool, json: bool, report: &mut Report) -> io::Result<()> {
+    if json {
+        if tags_only {
+            let tags: Vec<&str> = entries.iter().map(|e| e.tag.as_str()).collect();
+            report.field("tags", json_strings(&tags));
+        } else {
+            report.field("entries", json_array(entries.iter().map(entry_json)));
+        }
+        return Ok(());
+    }
+    let mut out = BufWriter::new(io::stdout().lock());
+    for entry in entries {
+        if tags_only {
+            writeln!(out, "{}", entry.tag)?;
+        } else {
+            out.write_all(entry.to_text().as_bytes())?;
+        }
+    }
+    out.flush()
+}
+
+/// Write `tags` (every entry if empty) of `quiver_file` under `output_dir`.
+/// Existing files are kept, with a warning. Returns the files written.
+fn extract(
+    quiver_file: &str,
+    tags: Vec<String>,
+    output_dir: &Path,
+    layout: ExtractLayout,
+    warnings: &mut Warnings,
+) -> Result<Vec<PathBuf>, QuiverError> {
+    let qv = Quiver::new(quiver_file, "r")?;
+    let tags = if tags.is_empty() { qv.get_tags() } else { tags };
+    let mut seen = HashSet::new();
+    let mut written = Vec::new();
+    for tag in tags.iter().filter(|tag| seen.insert(tag.as_str())) {
+        let outfn = layout.model_path(output_dir, tag, qv.get_format(tag)?);
+        if outfn.exists() {
+            warnings.push(
+                WarningCode::SkippedFile,
+                format!("File {} already exists, skipping", outfn.display()),
+            );
+            continue;
+        }
+        let lines = qv.get_lines(tag)?;
+        if let Some(parent) = outfn.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut out = BufWriter::new(File::create(&outfn)?);
+        for line in &lines {
+            out.write_all(line.trim_end_matches('\n').as_bytes())?;
+            out.write_all(b"\n")?;
+        }
+        out.flush()?;
+        if layout == ExtractLayout::PerTag {
+            write_meta(&qv, Path::new(quiver_file), tag, &outfn, &lines)?;
+        }
+        written.push(outfn);
+    }
+    Ok(written)
+}
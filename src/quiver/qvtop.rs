@@ -0,0 +1,111 @@
+use clap::Parser;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::Path;
+use std::process;
+
+use quiver::rank::RankKey;
+use quiver::warnings::{WarningCode, Warnings};
+use quiver::{Quiver, QuiverError};
+
+/// Extract the N best structures of a Quiver file by one score field.
+///
+/// The archive is read once. Entries are written as `<tag>.pdb` (or
+/// `<tag>.cif`) files, or with `--qv` as a new Quiver file in ranking order.
+/// Entries missing the field are never selected; ties keep file order. The
+/// key may also be an expression over the score fields, as for qvfilter.
+///
+/// Usage:
+///     qvtop my.qv --key plddt:desc -n 10 --output-dir best/
+///     qvtop my.qv --key ddg:asc -n 100 --qv top100.qv
+///     qvtop my.qv --key 'ddg - ref_ddg:asc' -n 10 --output-dir best/
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// Quiver file to select from
+    quiver_file: String,
+
+    /// Score field or expression to rank by, as FIELD[:asc|desc]
+    #[arg(short, long, value_name = "FIELD[:ORDER]")]
+    key: RankKey,
+
+    /// Number of structures to keep
+    #[arg(short, value_name = "N")]
+    n: usize,
+
+    /// Directory to write the structure files to
+    #[arg(short, long, default_value = ".", value_name = "DIR", conflicts_with = "qv")]
+    output_dir: String,
+
+    /// Write a Quiver file (must not exist) instead of structure files
+    #[arg(long, value_name = "QV")]
+    qv: Option<String>,
+}
+
+fn main() {
+    let args = Args::parse();
+
+    if let Err(e) = run(&args) {
+        eprintln!("❌ Error: {:?}", e);
+        process::exit(e.exit_code());
+    }
+}
+
+fn run(args: &Args) -> Result<(), QuiverError> {
+    let qv = Quiver::new(&args.quiver_file, "r")?;
+    let best = qv.top_n(&args.key, args.n)?;
+    if best.len() < args.n {
+        eprintln!(
+            "⚠️  Only {} of {} entries have a '{}' score",
+            best.len(),
+            args.n,
+            args.key.field
+        );
+    }
+
+    if let Some(path) = &args.qv {
+        if Path::new(path).exists() {
+            return Err(QuiverError::InvalidOperation(format!("Output file {} already exists", path)));
+        }
+        let mut out = Quiver::new(path, "w")?;
+        let header = qv.get_header();
+        if !header.is_empty() {
+            out.set_header(&header)?;
+        }
+        for entry in &best {
+            out.add_entry(entry)?;
+        }
+        out.flush()?;
+        println!("✅ Wrote the top {} entries by {} to {}", best.len(), args.key.field, path);
+        return Ok(());
+    }
+
+    fs::create_dir_all(&args.output_dir)?;
+    let mut extracted = 0;
+    let mut warnings = Warnings::new();
+    for entry in &best {
+        let outfn = Path::new(&args.output_dir).join(format!("{}.{}", entry.tag, entry.format.extension()));
+        if outfn.exists() {
+            warnings.push(
+                WarningCode::SkippedFile,
+                format!("File {} already exists, skipping", outfn.display()),
+            );
+            continue;
+        }
+        let mut file = File::create(&outfn)?;
+        for line in &entry.lines {
+            file.write_all(line.as_bytes())?;
+            if !line.ends_with('\n') {
+                file.write_all(b"\n")?;
+            }
+        }
+        println!("✅ Extracted {}", outfn.display());
+        extracted += 1;
+    }
+    warnings.report();
+    println!(
+        "\n🎉 Successfully extracted the top {} structures by {} from {} to {}",
+        extracted, args.key.field, args.quiver_file, args.output_dir
+    );
+    Ok(())
+}
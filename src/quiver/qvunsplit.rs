@@ -0,0 +1,92 @@
+use clap::Parser;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+
+use quiver::shard::{default_manifest, expand_pattern, sort_shards, unsplit};
+
+/// Merge shards written by qvsplit back into one Quiver file.
+///
+/// Shards are merged in numeric order (split_9.qv before split_10.qv) and
+/// the merged tags are checked against the manifest qvsplit wrote beside
+/// them, so lost or duplicated entries fail the merge before the output is
+/// written. Quote a pattern to have it expanded here rather than by the
+/// shell.
+///
+/// Usage:
+///     qvunsplit 'split_*.qv' --output merged.qv
+///     qvunsplit parts/split_*.qv --output merged.qv --delete
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// Shard files or patterns (`*` and `?` in the file name)
+    #[arg(required = true)]
+    shards: Vec<String>,
+
+    /// Merged Quiver file to write (must not exist)
+    #[arg(short, long)]
+    output: String,
+
+    /// Manifest to check against (default: <prefix>_manifest.tsv beside the shards)
+    #[arg(long, value_name = "TSV")]
+    manifest: Option<String>,
+
+    /// Delete the shards and the manifest after a successful merge
+    #[arg(long)]
+    delete: bool,
+}
+
+fn main() {
+    let args = Args::parse();
+
+    let mut shards: Vec<PathBuf> = Vec::new();
+    for pattern in &args.shards {
+        match expand_pattern(pattern) {
+            Ok(paths) => shards.extend(paths),
+            Err(e) => {
+                eprintln!("❌ Failed to expand {}: {}", pattern, e);
+                process::exit(1);
+            }
+        }
+    }
+    shards.sort();
+    shards.dedup();
+    sort_shards(&mut shards);
+    if shards.is_empty() {
+        eprintln!("❌ No shards match {}", args.shards.join(" "));
+        process::exit(1);
+    }
+
+    let manifest = match &args.manifest {
+        Some(path) => Some(PathBuf::from(path)),
+        None => default_manifest(&shards).filter(|path| path.exists()),
+    };
+    if manifest.is_none() {
+        eprintln!("⚠️  No manifest found, only checking for duplicate tags");
+    }
+
+    let report = match unsplit(&shards, Path::new(&args.output), manifest.as_deref()) {
+        Ok(report) => report,
+        Err(e) => {
+            eprintln!("❌ Error: {:?}", e);
+            process::exit(e.exit_code());
+        }
+    };
+    println!(
+        "✅ Merged {} entries from {} shard(s) into {}{}",
+        report.entries,
+        report.shards,
+        args.output,
+        if report.verified { " (matches the manifest)" } else { "" }
+    );
+
+    if args.delete {
+        for path in shards.iter().chain(manifest.iter()) {
+            if let Err(e) = fs::remove_file(path) {
+                eprintln!("❌ Failed to delete {}: {}", path.display(), e);
+                process::exit(1);
+            }
+        }
+        println!("🗑️  Deleted {} shard(s)", shards.len());
+    }
+}
@@ -0,0 +1,78 @@
+use clap::Parser;
+use std::process;
+
+use quiver::diagnose::{validate_archive, IssueKind};
+use quiver::QuiverError;
+
+/// Check a Quiver file line by line before a long production run uses it.
+///
+/// Prints one `line<TAB>kind<TAB>tag<TAB>message` row per problem, in file
+/// order, and exits with status 4 if there are any. The kinds are
+/// `duplicate-tag` (a tag already used by an earlier entry),
+/// `empty-structure` (an entry without ATOM or HETATM records),
+/// `score-tag-mismatch` (a `QV_SCORE` line naming another tag than its
+/// entry, or before the first one), `malformed-score` (a score line without
+/// fields, or a field that is not `key=value`) and `junk` (a payload line
+/// that is not a PDB record). The tag is `-` before the first entry.
+///
+/// `--allow` lists kinds that are still printed but do not fail the check,
+/// e.g. `junk` for Rosetta outputs, which append their score table to the
+/// PDB file.
+///
+/// Usage:
+///     qvvalidate my.qv
+///     qvvalidate rosetta.qv --allow junk
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// Quiver file to check
+    quiver_file: String,
+
+    /// Kinds of problems that do not fail the check
+    #[arg(long, value_delimiter = ',', value_name = "KINDS")]
+    allow: Vec<IssueKind>,
+}
+
+fn main() {
+    let args = Args::parse();
+
+    match run(&args) {
+        Ok(entries) => eprintln!("✅ {} entries checked", entries),
+        Err(e) => {
+            eprintln!("❌ Error: {:?}", e);
+            process::exit(e.exit_code());
+        }
+    }
+}
+
+fn run(args: &Args) -> Result<usize, QuiverError> {
+    let report = validate_archive(&args.quiver_file)?;
+    for issue in &report.issues {
+        println!(
+            "{}\t{}\t{}\t{}",
+            issue.line,
+            issue.kind,
+            issue.tag.as_deref().unwrap_or("-"),
+            issue.message
+        );
+    }
+    let counts = report.counts();
+    let failing: usize = counts
+        .iter()
+        .filter(|(kind, _)| !args.allow.contains(kind))
+        .map(|(_, n)| n)
+        .sum();
+    let summary: Vec<String> = counts.iter().map(|(kind, n)| format!("{} {}", n, kind)).collect();
+    if failing > 0 {
+        return Err(QuiverError::Malformed(format!(
+            "{} problems in {} entries ({})",
+            failing,
+            report.entries,
+            summary.join(", ")
+        )));
+    }
+    if !report.is_valid() {
+        eprintln!("⚠️  Allowed problems: {}", summary.join(", "));
+    }
+    Ok(report.entries)
+}
@@ -0,0 +1,160 @@
+#!/usr/bin/env python3
+"""
+Read-only access to Quiver files in object storage, over HTTP(S) or S3.
+
+A remote archive is read through ranged GET requests, so nothing is
+downloaded to disk. Without a tag index the tag list is found by streaming
+the archive once, in large ranges; with a "<url>.idx" index next to it (as
+written by qvindex, or uploaded by the Rust tools' S3 writer) only the
+index and the entries asked for are fetched.
+
+S3 URLs (s3://bucket/key) need boto3, configured like the AWS CLI.
+
+Usage:
+    qv = Quiver("https://example.org/designs.qv", "r")
+    lines = qv.get_pdblines("design_0001")
+"""
+
+import io
+import urllib.error
+import urllib.request
+
+REMOTE_SCHEMES = ("http://", "https://", "s3://")
+
+# Bytes fetched per request while streaming through an archive
+STREAM_CHUNK = 8 << 20
+
+
+def is_remote(path):
+    """Whether `path` is an http(s):// or s3:// URL."""
+    return isinstance(path, str) and path.startswith(REMOTE_SCHEMES)
+
+
+class _HttpSource:
+    def __init__(self, url):
+        self.url = url
+
+    def size(self):
+        # Size of the object, or None if it does not exist
+        request = urllib.request.Request(self.url, method="HEAD")
+        try:
+            with urllib.request.urlopen(request) as response:
+                return int(response.headers["Content-Length"])
+        except urllib.error.HTTPError as e:
+            if e.code == 404:
+                return None
+            raise
+
+    def get_range(self, offset, length):
+        request = urllib.request.Request(
+            self.url, headers={"Range": f"bytes={offset}-{offset + length - 1}"}
+        )
+        with urllib.request.urlopen(request) as response:
+            if response.status != 206:
+                raise OSError(f"{self.url} does not support ranged reads")
+            return response.read()
+
+
+class _S3Source:
+    def __init__(self, url):
+        try:
+            import boto3
+        except ImportError:
+            raise ImportError(f"Reading {url} needs boto3: pip install boto3") from None
+        bucket, _, key = url[len("s3://"):].partition("/")
+        if not bucket or not key:
+            raise ValueError(f"Invalid S3 URL '{url}'")
+        self.url = url
+        self.bucket, self.key = bucket, key
+        self.client = boto3.client("s3")
+
+    def size(self):
+        try:
+            return self.client.head_object(Bucket=self.bucket, Key=self.key)["ContentLength"]
+        except self.client.exceptions.ClientError as e:
+            if e.response["Error"]["Code"] in ("404", "NoSuchKey"):
+                return None
+            raise
+
+    def get_range(self, offset, length):
+        response = self.client.get_object(
+            Bucket=self.bucket, Key=self.key, Range=f"bytes={offset}-{offset + length - 1}"
+        )
+        return response["Body"].read()
+
+
+def _source(url):
+    return _S3Source(url) if url.startswith("s3://") else _HttpSource(url)
+
+
+class RangeReader(io.RawIOBase):
+    """A seekable, read-only binary file over a remote object, each read
+    one ranged request."""
+
+    def __init__(self, url):
+        self.url = url
+        self._source = _source(url)
+        self._size = self._source.size()
+        if self._size is None:
+            raise FileNotFoundError(f"No such remote archive: {url}")
+        self._pos = 0
+
+    def readable(self):
+        return True
+
+    def seekable(self):
+        return True
+
+    def tell(self):
+        return self._pos
+
+    def seek(self, offset, whence=io.SEEK_SET):
+        if whence == io.SEEK_CUR:
+            offset += self._pos
+        elif whence == io.SEEK_END:
+            offset += self._size
+        self._pos = max(offset, 0)
+        return self._pos
+
+    def readinto(self, buffer):
+        length = min(len(buffer), self._size - self._pos)
+        if length <= 0:
+            return 0
+        data = self._source.get_range(self._pos, length)
+        buffer[: len(data)] = data
+        self._pos += len(data)
+        return len(data)
+
+
+def open_binary(url):
+    """The archive at `url` as an unbuffered binary file: every read is one
+    request for exactly the bytes asked for, as when reading an entry
+    located through the index."""
+    return RangeReader(url)
+
+
+def open_text(url, errors="strict"):
+    """The archive at `url` as a text file for reading it through, fetched
+    STREAM_CHUNK bytes at a time."""
+    buffered = io.BufferedReader(RangeReader(url), buffer_size=STREAM_CHUNK)
+    return io.TextIOWrapper(buffered, encoding="utf-8", errors=errors)
+
+
+def load_index(url):
+    """[(tag, offset, length)] from the "<url>.idx" index, or None if there
+    is none. An index stamped with an archive size (written by qvindex) is
+    ignored once the archive's size differs."""
+    source = _source(f"{url}.idx")
+    size = source.size()
+    if not size:
+        return None
+    lines = source.get_range(0, size).decode("utf-8").splitlines()
+    if lines and lines[0].startswith("QV_INDEX"):
+        stamp = lines.pop(0).split("\t")
+        if len(stamp) < 2 or int(stamp[1]) != _source(url).size():
+            return None
+    entries = []
+    for line in lines:
+        tag, offset, length = line.rsplit("\t", 2)
+        entries.append((tag, int(offset), int(length)))
+    return entries
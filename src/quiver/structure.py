@@ -0,0 +1,220 @@
+#!/usr/bin/env python3
+"""
+Structured access to stored PDB lines.
+
+Structure.from_lines groups ATOM/HETATM records into chains and residues and
+keeps the header records (see HEADER_RECORDS); every other record is ignored. Fields are read from their fixed PDB columns,
+and Atom.to_line writes them back the same way.
+
+Usage:
+    structure = Quiver("designs.qv", "r").get_structure("design_0001")
+    for chain in structure.chains:
+        print(chain.id, chain.sequence())
+"""
+
+THREE_TO_ONE = {
+    "ALA": "A", "ARG": "R", "ASN": "N", "ASP": "D", "CYS": "C",
+    "GLN": "Q", "GLU": "E", "GLY": "G", "HIS": "H", "ILE": "I",
+    "LEU": "L", "LYS": "K", "MET": "M", "PHE": "F", "PRO": "P",
+    "SER": "S", "THR": "T", "TRP": "W", "TYR": "Y", "VAL": "V",
+    "MSE": "M", "SEC": "U", "PYL": "O",
+}
+
+# Title-section and primary-structure records (HEADER, COMPND, SEQRES, ...),
+# kept unchanged by every rewrite unless stripped
+HEADER_RECORDS = (
+    "HEADER", "OBSLTE", "TITLE", "SPLIT", "CAVEAT", "COMPND", "SOURCE", "KEYWDS",
+    "EXPDTA", "NUMMDL", "MDLTYP", "AUTHOR", "REVDAT", "SPRSDE", "JRNL", "REMARK",
+    "DBREF", "DBREF1", "DBREF2", "SEQADV", "SEQRES", "MODRES",
+)
+
+
+def is_header_record(line):
+    """Whether `line` is one of the HEADER_RECORDS."""
+    return line[:6].strip() in HEADER_RECORDS
+
+
+def header_records(pdb_lines):
+    """The header records of `pdb_lines` (with newlines), in file order."""
+    return [line if line.endswith("\n") else line + "\n" for line in pdb_lines if is_header_record(line)]
+
+
+def _float(text, default):
+    text = text.strip()
+    return float(text) if text else default
+
+
+class Atom:
+    """One ATOM or HETATM record."""
+
+    __slots__ = (
+        "hetatm", "serial", "name", "alt_loc", "resname", "chain",
+        "resseq", "icode", "coord", "occupancy", "b_factor", "element",
+    )
+
+    def __init__(self, hetatm, serial, name, alt_loc, resname, chain, resseq, icode,
+                 coord, occupancy=1.0, b_factor=0.0, element=""):
+        self.hetatm = hetatm
+        self.serial = serial
+        self.name = name
+        self.alt_loc = alt_loc
+        self.resname = resname
+        self.chain = chain
+        self.resseq = resseq
+        self.icode = icode
+        self.coord = coord
+        self.occupancy = occupancy
+        self.b_factor = b_factor
+        self.element = element
+
+    @classmethod
+    def parse(cls, line):
+        """Parse an ATOM/HETATM record, or return None for any other line or
+        a record without valid coordinates."""
+        hetatm = line.startswith("HETATM")
+        if not hetatm and not line.startswith("ATOM"):
+            return None
+        line = line.rstrip("\n").ljust(80)
+        try:
+            coord = (float(line[30:38]), float(line[38:46]), float(line[46:54]))
+            resseq = int(line[22:26])
+        except ValueError:
+            return None
+        serial = line[6:11].strip()
+        return cls(
+            hetatm=hetatm,
+            serial=int(serial) if serial.isdigit() else 0,
+            name=line[12:16].strip(),
+            alt_loc=line[16],
+            resname=line[17:20].strip(),
+            chain=line[21].strip(),
+            resseq=resseq,
+            icode=line[26],
+            coord=coord,
+            occupancy=_float(line[54:60], 1.0),
+            b_factor=_float(line[60:66], 0.0),
+            element=line[76:78].strip(),
+        )
+
+    def to_line(self):
+        """The atom as a fixed-column PDB record (without a newline)."""
+        # Column 14 as usual for one-letter elements; 1H and MG start in 13
+        left = self.name[:1].isdigit() or (
+            len(self.element) == 2 and self.name.upper() == self.element.upper()
+        )
+        if len(self.name) < 4 and not left:
+            name = f" {self.name:<3}"
+        else:
+            name = f"{self.name:<4}"
+        x, y, z = self.coord
+        return (
+            f"{'HETATM' if self.hetatm else 'ATOM':<6}{self.serial:>5} {name}{self.alt_loc}"
+            f"{self.resname:>3} {self.chain:1}{self.resseq:>4}{self.icode}   "
+            f"{x:8.3f}{y:8.3f}{z:8.3f}{self.occupancy:6.2f}{self.b_factor:6.2f}"
+            f"          {self.element:>2}"
+        )
+
+    def __repr__(self):
+        return f"Atom({self.name!r}, {self.resname!r}, {self.chain!r}, {self.resseq})"
+
+
+class Residue:
+    """The atoms sharing a residue number and insertion code within a chain."""
+
+    __slots__ = ("name", "resseq", "icode", "atoms")
+
+    def __init__(self, name, resseq, icode=" ", atoms=None):
+        self.name = name
+        self.resseq = resseq
+        self.icode = icode
+        self.atoms = atoms if atoms is not None else []
+
+    def one_letter(self):
+        """One-letter code of the residue, "X" if it is not an amino acid."""
+        return THREE_TO_ONE.get(self.name, "X")
+
+    def atom(self, name):
+        """The first atom called `name` (e.g. "CA"), or None."""
+        return next((a for a in self.atoms if a.name == name), None)
+
+    def __repr__(self):
+        return f"Residue({self.name!r}, {self.resseq}{self.icode.strip()})"
+
+
+class Chain:
+    """A chain's residues in file order. `id` is "" if the records have none."""
+
+    __slots__ = ("id", "residues")
+
+    def __init__(self, id, residues=None):
+        self.id = id
+        self.residues = residues if residues is not None else []
+
+    def sequence(self):
+        """One-letter sequence of the ATOM residues; residues made only of
+        HETATM records (ligands, waters) are skipped."""
+        return "".join(
+            r.one_letter() for r in self.residues if any(not a.hetatm for a in r.atoms)
+        )
+
+    def __repr__(self):
+        return f"Chain({self.id!r}, {len(self.residues)} residues)"
+
+
+class Structure:
+    """The chains of one stored structure, in the order they first appear,
+    and its header records (`header`, lines with newlines)."""
+
+    __slots__ = ("header", "chains")
+
+    def __init__(self, chains=None, header=None):
+        self.chains = chains if chains is not None else []
+        self.header = header if header is not None else []
+
+    @classmethod
+    def from_lines(cls, pdb_lines):
+        """Parse the ATOM/HETATM records of `pdb_lines`.
+
+        A new residue starts whenever the residue number or insertion code
+        changes within a chain. A chain that reappears later (e.g. its
+        ligands after a TER) is appended to, not duplicated.
+        """
+        chains = {}
+        for line in pdb_lines:
+            atom = Atom.parse(line)
+            if atom is None:
+                continue
+            chain = chains.get(atom.chain)
+            if chain is None:
+                chain = chains[atom.chain] = Chain(atom.chain)
+            last = chain.residues[-1] if chain.residues else None
+            if last is not None and last.resseq == atom.resseq and last.icode == atom.icode:
+                last.atoms.append(atom)
+            else:
+                chain.residues.append(Residue(atom.resname, atom.resseq, atom.icode, [atom]))
+        return cls(list(chains.values()), header_records(pdb_lines))
+
+    def chain(self, id):
+        """The chain with identifier `id`, or None."""
+        return next((c for c in self.chains if c.id == id), None)
+
+    def residues(self):
+        """Every residue, chain by chain."""
+        return [r for c in self.chains for r in c.residues]
+
+    def atoms(self):
+        """Every atom, chain by chain."""
+        return [a for r in self.residues() for a in r.atoms]
+
+    def to_lines(self):
+        """The structure as PDB lines (with newlines): its header records,
+        then the atoms with a TER after each chain, and a closing END."""
+        lines = list(self.header)
+        for chain in self.chains:
+            lines.extend(a.to_line() + "\n" for r in chain.residues for a in r.atoms)
+            lines.append("TER\n")
+        lines.append("END\n")
+        return lines
+
+    def __repr__(self):
+        return f"Structure({len(self.chains)} chains)"
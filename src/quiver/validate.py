@@ -0,0 +1,111 @@
+#!/usr/bin/env python3
+"""
+Checks run on every entry before it is written.
+
+A validator is any callable `validator(tag, pdb_lines, scores)`, where
+`scores` is a dict of the raw score strings. It raises ValueError to reject
+the entry, and Quiver.add_pdb then writes nothing. The factories here cover
+the common rules.
+
+Usage:
+    qv = Quiver("designs.qv", "w", validators=[max_residues(500), required_chains("A")])
+    qv.add_validator(required_scores("ddg", "plddt"))
+"""
+
+try:
+    from .structure import Atom, Structure
+except ImportError:  # run as a script from this directory
+    from structure import Atom, Structure
+
+# Record names of the PDB format, version 3.3
+PDB_RECORDS = frozenset(
+    "HEADER OBSLTE TITLE SPLIT CAVEAT COMPND SOURCE KEYWDS EXPDTA NUMMDL MDLTYP "
+    "AUTHOR REVDAT SPRSDE JRNL REMARK DBREF DBREF1 DBREF2 SEQADV SEQRES MODRES "
+    "HET HETNAM HETSYN FORMUL HELIX SHEET SSBOND LINK CISPEP SITE CRYST1 ORIGX1 "
+    "ORIGX2 ORIGX3 SCALE1 SCALE2 SCALE3 MTRIX1 MTRIX2 MTRIX3 MODEL ATOM ANISOU "
+    "TER HETATM ENDMDL CONECT MASTER END".split()
+)
+
+
+def max_residues(limit):
+    """Reject structures with more than `limit` residues."""
+
+    def validator(tag, pdb_lines, scores):
+        count = len(Structure.from_lines(pdb_lines).residues())
+        if count > limit:
+            raise ValueError(f"{count} residues, more than the maximum of {limit}")
+
+    return validator
+
+
+def required_chains(*chain_ids):
+    """Reject structures missing any of `chain_ids`."""
+
+    def validator(tag, pdb_lines, scores):
+        structure = Structure.from_lines(pdb_lines)
+        missing = [c for c in chain_ids if structure.chain(c) is None]
+        if missing:
+            raise ValueError(f"missing chain(s) {', '.join(missing)}")
+
+    return validator
+
+
+def required_scores(*names):
+    """Reject entries whose score string lacks any of `names`."""
+
+    def validator(tag, pdb_lines, scores):
+        missing = [n for n in names if n not in scores]
+        if missing:
+            raise ValueError(f"missing score field(s) {', '.join(missing)}")
+
+    return validator
+
+
+def pdb_records():
+    """Reject structures with lines that are not PDB records, or ATOM/HETATM
+    records without readable coordinates. Blank lines are allowed.
+
+    Rosetta appends its score table to the PDB files it writes, so this is
+    not a check to use on such files.
+    """
+
+    def validator(tag, pdb_lines, scores):
+        for i, line in enumerate(pdb_lines):
+            record = line[:6].rstrip()
+            if not record:
+                continue
+            if record not in PDB_RECORDS:
+                raise ValueError(f"line {i + 1} is not a PDB record: {record}")
+            if record in ("ATOM", "HETATM") and Atom.parse(line) is None:
+                raise ValueError(f"line {i + 1} is an unreadable {record} record")
+
+    return validator
+
+
+def sniff_structure(pdb_lines):
+    """Raise ValueError if `pdb_lines` are clearly not a structure: no ATOM
+    or HETATM record and no mmCIF _atom_site item, as for a log file picked
+    up by a loose glob. The message names the first non-blank line.
+    """
+    for line in pdb_lines:
+        if line[:6].rstrip() in ("ATOM", "HETATM") or line.lstrip().startswith("_atom_site."):
+            return
+    first = next((line.strip() for line in pdb_lines if line.strip()), None)
+    if first is None:
+        raise ValueError("not a structure (no ATOM or HETATM records), the payload is empty")
+    raise ValueError(
+        f"not a structure (no ATOM or HETATM records), starts with: {first[:60]}"
+    )
+
+
+def structure_content():
+    """Reject entries that are clearly not structures, see sniff_structure().
+
+    Only the absence of coordinates is checked, so any real structure file
+    passes; see pdb_records() for a strict check of every line.
+    """
+
+    def validator(tag, pdb_lines, scores):
+        sniff_structure(pdb_lines)
+
+    return validator
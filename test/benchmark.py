@@ -91,7 +91,7 @@ def slice_quiver_file(input_qv_file, tags_to_slice, output_qv_file):
         raise FileNotFoundError(f"Script not found: {slice_script}")
 
     tags_str = "\n".join(tags_to_slice)
-    cmd = [sys.executable, slice_script, str(input_qv_file)]
+    cmd = [sys.executable, slice_script, str(input_qv_file), "-"]
     process = subprocess.Popen(
         cmd,
         stdin=subprocess.PIPE,
@@ -124,6 +124,31 @@ def extract_from_quiver(qv_file, output_dir):
         )
 
 
+# Path of the Rust qvextract binary; defaults to the cargo release build
+RUST_QVEXTRACT_ENV = "QV_RUST_QVEXTRACT"
+
+
+def rust_qvextract_bin():
+    """Path of the Rust qvextract to benchmark, or None if it is not built.
+
+    Taken from $QV_RUST_QVEXTRACT, else target/release/qvextract in the
+    repository. PATH is not searched: the Python package installs a console
+    script of the same name."""
+    path = os.environ.get(RUST_QVEXTRACT_ENV) or os.path.join(
+        parent_dir, "target", "release", "qvextract"
+    )
+    if os.path.isfile(path) and os.access(path, os.X_OK):
+        return path
+    return None
+
+
+def extract_with_rust_threads(qvextract_bin, qv_file, output_dir, threads):
+    """Extracts all files with the Rust qvextract, sharing one read handle
+    across `threads` threads."""
+    cmd = [qvextract_bin, os.path.abspath(qv_file), "--threads", str(threads)]
+    subprocess.run(cmd, check=True, cwd=output_dir, capture_output=True, text=True)
+
+
 def main():
     """Main function to run the benchmarks."""
     basedir = parent_dir  # Project root directory
@@ -187,6 +212,30 @@ def main():
                 f" ({len(input_pdb_files)} files): {extract_full_time:.4f} seconds"
             )
 
+            # Benchmark concurrent reads on one handle (Rust binary, if built)
+            qvextract_bin = rust_qvextract_bin()
+            if qvextract_bin is None:
+                print(
+                    "SKIPPED: qvextract --threads benchmark; no Rust qvextract found. "
+                    f"Build it with 'cargo build --release' or set {RUST_QVEXTRACT_ENV}.",
+                    file=sys.stderr,
+                )
+            else:
+                for threads in (1, 4):
+                    threads_dir = benchmark_dir / f"extracted_threads_{threads}"
+                    threads_dir.mkdir(exist_ok=True)
+                    threads_time = measure_time(
+                        extract_with_rust_threads,
+                        qvextract_bin,
+                        test_qv_file,
+                        threads_dir,
+                        threads,
+                    )
+                    print(
+                        f"Time to extract all files with qvextract"
+                        f" --threads {threads}: {threads_time:.4f} seconds"
+                    )
+
     except Exception as e:
         print(f"An error occurred during benchmarking: {e}")
     finally:
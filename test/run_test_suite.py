@@ -137,7 +137,7 @@ def test_qvextractspecific(basedir):
 
     # Extraction 명령어 수정 (--output-dir 추가)
     os.system(
-        f"cat tags.txt | {basedir}/src/quiver/qvextractspecific.py test.qv --output-dir {test_dir}"
+        f"cat tags.txt | {basedir}/src/quiver/qvextractspecific.py test.qv - --output-dir {test_dir}"
     )
 
     with open("tags.txt", "r") as f:
@@ -210,7 +210,7 @@ def test_qvslice(basedir):
     os.system(f"{basedir}/src/quiver/qvls.py test.qv | shuf | head -n 5 > tags.txt")
 
     # Run qvslice
-    os.system(f"cat tags.txt | {basedir}/src/quiver/qvslice.py test.qv > sliced.qv")
+    os.system(f"cat tags.txt | {basedir}/src/quiver/qvslice.py test.qv - > sliced.qv")
 
     # Run qvextract
     os.system(f"{basedir}/src/quiver/qvextract.py sliced.qv")
@@ -420,7 +420,7 @@ def test_qvrename(basedir):
 
     # Run qvrename
     os.system(
-        f"cat newtags.txt | {basedir}/src/quiver/qvrename.py {qvpath} > renamed.qv"
+        f"cat newtags.txt | {basedir}/src/quiver/qvrename.py {qvpath} - > renamed.qv"
     )
 
     # Run qvextract
@@ -0,0 +1,63 @@
+import asyncio
+import pytest
+import os
+import sys
+
+# Ensure src is in sys.path
+sys.path.insert(0, os.path.abspath(os.path.join(os.path.dirname(__file__), "..", "src")))
+
+from quiver.async_quiver import AsyncQuiver
+
+@pytest.fixture
+def temp_qv_file(tmp_path):
+    qv_path = tmp_path / "test.qv"
+    with open(qv_path, "w") as f:
+        for i in range(1, 4):
+            f.write(f"QV_TAG tag{i}\n")
+            f.write(f"QV_SCORE tag{i} ddg=-{i}.5\n")
+            f.write(f"ATOM      {i}  N   ALA A   1      11.104  13.203  10.000  1.00 20.00           N\n")
+    return qv_path
+
+def test_async_reads(temp_qv_file):
+    """Verify reads are awaitable and match the synchronous API."""
+    async def main():
+        async with AsyncQuiver(str(temp_qv_file)) as qv:
+            assert qv.get_tags() == ["tag1", "tag2", "tag3"]
+            assert "tag2" in qv
+            lines = await qv.get_pdblines("tag2")
+            scores = await qv.get_scores("tag2")
+            ranked = await qv.sort_tags("ddg", descending=False)
+            return lines, scores, ranked
+
+    lines, scores, ranked = asyncio.run(main())
+    assert lines[0].startswith("ATOM      2")
+    assert scores == {"ddg": -2.5}
+    assert ranked == ["tag3", "tag2", "tag1"]
+
+def test_async_concurrent_reads(temp_qv_file):
+    """Verify several reads can be awaited together."""
+    async def main():
+        async with AsyncQuiver(str(temp_qv_file), max_workers=3) as qv:
+            return await asyncio.gather(*(qv.get_pdblines(t) for t in qv.get_tags()))
+
+    results = asyncio.run(main())
+    assert [lines[0][:11] for lines in results] == ["ATOM      1", "ATOM      2", "ATOM      3"]
+
+def test_async_missing_tag(temp_qv_file):
+    """Verify errors from the worker thread reach the awaiting coroutine."""
+    async def main():
+        async with AsyncQuiver(str(temp_qv_file)) as qv:
+            await qv.get_pdblines("tag9")
+
+    with pytest.raises(KeyError, match="Requested tag: tag9 does not exist"):
+        asyncio.run(main())
+
+def test_async_closed(temp_qv_file):
+    """Verify reads after close() are rejected."""
+    async def main():
+        qv = AsyncQuiver(str(temp_qv_file))
+        qv.close()
+        await qv.get_scores("tag1")
+
+    with pytest.raises(RuntimeError, match="closed"):
+        asyncio.run(main())
@@ -0,0 +1,55 @@
+import pytest
+import os
+import sys
+
+# Ensure src is in sys.path
+sys.path.insert(0, os.path.abspath(os.path.join(os.path.dirname(__file__), "..", "src")))
+
+from quiver.quiver import Quiver, ACCESS_LOG_ENV
+
+@pytest.fixture
+def temp_qv_file(tmp_path):
+    qv_path = tmp_path / "test.qv"
+    with open(qv_path, "w") as f:
+        f.write("QV_TAG tag1\n")
+        f.write("ATOM      1  N   ALA A   1      11.104  13.203  10.000  1.00 20.00           N\n")
+        f.write("QV_TAG tag2\n")
+        f.write("ATOM      1  N   ALA A   1      12.104  14.203  11.000  1.00 21.00           N\n")
+    return qv_path
+
+def test_hook_sees_reads(temp_qv_file, tmp_path):
+    """Verify every read operation reports the tags it returned."""
+    seen = []
+    q = Quiver(str(temp_qv_file), "r", access_hook=lambda archive, op, tag: seen.append((op, tag)))
+    q.get_pdblines("tag2")
+    list(q)
+    q.get_struct_list(["tag1"])
+    q.split(1, str(tmp_path / "split"), "part")
+    assert seen == [
+        ("get_pdblines", "tag2"),
+        ("iter", "tag1"),
+        ("iter", "tag2"),
+        ("get_struct_list", "tag1"),
+        ("split", "tag1"),
+        ("split", "tag2"),
+    ]
+
+def test_missing_tag_is_not_recorded(temp_qv_file):
+    """Verify a failed lookup is not reported as an access."""
+    seen = []
+    q = Quiver(str(temp_qv_file), "r", access_hook=lambda archive, op, tag: seen.append(tag))
+    with pytest.raises(KeyError):
+        q.get_pdblines("tag3")
+    assert seen == []
+
+def test_log_file_from_env(temp_qv_file, tmp_path):
+    """Verify QUIVER_ACCESS_LOG appends one tab-separated line per access."""
+    log = tmp_path / "access.log"
+    os.environ[ACCESS_LOG_ENV] = str(log)
+    try:
+        Quiver(str(temp_qv_file), "r").get_pdblines("tag1")
+    finally:
+        del os.environ[ACCESS_LOG_ENV]
+    fields = log.read_text().rstrip("\n").split("\t")
+    assert len(fields) == 7
+    assert fields[4:] == ["get_pdblines", str(temp_qv_file), "tag1"]
@@ -0,0 +1,47 @@
+import pytest
+import os
+import sys
+
+# Ensure src is in sys.path
+sys.path.insert(0, os.path.abspath(os.path.join(os.path.dirname(__file__), "..", "src")))
+
+from quiver.quiver import Quiver
+
+PDB_LINE = "ATOM      1  CA  ALA A   1      11.104   6.134  -6.504  1.00  0.00           C\n"
+HEADER = "QV_HEADER creator=test\nQV_HEADER description=round 3\n"
+
+@pytest.fixture
+def temp_qv_file(tmp_path):
+    # As left by a Rust rewrite: the generation lines describe this archive only
+    qv_path = tmp_path / "test.qv"
+    qv_path.write_text(
+        HEADER
+        + "QV_HEADER generation=9\nQV_HEADER generation_entries=3\n"
+        + "".join(f"QV_TAG design_{i}\n{PDB_LINE}" for i in range(3))
+    )
+    return str(qv_path)
+
+def test_split_copies_header(temp_qv_file, tmp_path):
+    """Verify every split file starts with the archive header, without the generation."""
+    qv = Quiver(temp_qv_file, "r")
+    entry_bytes = len(f"QV_TAG design_0\n{PDB_LINE}")
+    splits = {
+        "split": qv.split(2, str(tmp_path / "split"), "s"),
+        "size": qv.split_by_size(len(HEADER) + 2 * entry_bytes, str(tmp_path / "size"), "s"),
+        "stripe": qv.split_round_robin(2, str(tmp_path / "stripe"), "s"),
+    }
+    for name, count in splits.items():
+        assert count == 2
+        for i in range(count):
+            text = (tmp_path / name / f"s_{i}.qv").read_text()
+            assert text.startswith(HEADER + "QV_TAG ")
+            assert "generation" not in text
+
+def test_slice_copies_header(temp_qv_file):
+    """Verify get_struct_list and slice put the archive header before the entries."""
+    qv = Quiver(temp_qv_file, "r")
+    data, found = qv.get_struct_list(["design_2", "design_0"])
+    assert found == ["design_0", "design_2"]
+    assert data == HEADER + f"QV_TAG design_0\n{PDB_LINE}QV_TAG design_2\n{PDB_LINE}"
+    assert qv.slice(["design_1"]).data == HEADER + f"QV_TAG design_1\n{PDB_LINE}"
+    assert qv.get_struct_list([]) == (HEADER, [])
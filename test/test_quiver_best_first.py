@@ -0,0 +1,57 @@
+import pytest
+import os
+import sys
+
+# Ensure src is in sys.path
+sys.path.insert(0, os.path.abspath(os.path.join(os.path.dirname(__file__), "..", "src")))
+
+from quiver.quiver import Quiver, Warnings, _best_first, extract_pdbs
+
+PDB_LINES = ["ATOM      1  CA  ALA A   1      11.104   6.134  -6.504  1.00  0.00           C\n"]
+
+PLDDT = {"d0": 71.0, "d1": 93.5, "d2": 80.0, "d3": 93.5, "d4": 55.0}
+
+@pytest.fixture
+def temp_qv_file(tmp_path):
+    qv_path = tmp_path / "test.qv"
+    with Quiver(str(qv_path), "w") as qv:
+        for tag, plddt in PLDDT.items():
+            qv.add_pdb(PDB_LINES, tag, f"plddt={plddt}")
+        qv.add_pdb(PDB_LINES, "unscored")
+    return str(qv_path)
+
+@pytest.mark.parametrize("threads", [1, 2])
+def test_best_first_order(temp_qv_file, tmp_path, threads):
+    """Verify entries are extracted best score first, ties in file order, unscored last."""
+    out = tmp_path / "out"
+    written = extract_pdbs(temp_qv_file, str(out), threads=threads, best_first="plddt")
+    names = [os.path.basename(path) for path in written]
+    assert names == ["d1.pdb", "d3.pdb", "d2.pdb", "d0.pdb", "d4.pdb", "unscored.pdb"]
+
+def test_best_first_ascending(temp_qv_file, tmp_path):
+    """Verify ascending extracts the lowest scores first and honours the tag list."""
+    written = extract_pdbs(
+        temp_qv_file, str(tmp_path / "out"), tags=["d1", "d4", "d0"], best_first="plddt", ascending=True
+    )
+    assert [os.path.basename(path) for path in written] == ["d4.pdb", "d0.pdb", "d1.pdb"]
+
+def test_best_first_stops_early(temp_qv_file):
+    """Verify the priority queue can be left after the first entries."""
+    order = _best_first(Quiver(temp_qv_file, "r")._score_column("plddt"), list(PLDDT))
+    assert next(order) == "d1"
+    assert next(order) == "d3"
+
+@pytest.mark.parametrize("best_first", [None, "plddt"])
+def test_max_output_bytes(temp_qv_file, tmp_path, best_first):
+    """Verify extraction stops before the file that would exceed the quota and warns."""
+    warnings = Warnings()
+    size = len(PDB_LINES[0].encode("utf-8"))
+    written = extract_pdbs(
+        temp_qv_file, str(tmp_path / "out"), best_first=best_first, max_output_bytes=2 * size + 1, warnings=warnings
+    )
+    names = [os.path.basename(path) for path in written]
+    assert names == (["d0.pdb", "d1.pdb"] if best_first is None else ["d1.pdb", "d3.pdb"])
+    assert sorted(os.listdir(tmp_path / "out")) == sorted(names)
+    assert [w.code for w in warnings] == ["output-quota"]
+    with pytest.raises(ValueError):
+        extract_pdbs(temp_qv_file, str(tmp_path / "other"), threads=2, max_output_bytes=size)
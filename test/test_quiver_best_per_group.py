@@ -0,0 +1,47 @@
+import pytest
+import os
+import sys
+
+# Ensure src is in sys.path
+sys.path.insert(0, os.path.abspath(os.path.join(os.path.dirname(__file__), "..", "src")))
+
+from quiver.quiver import best_per_group
+
+@pytest.fixture
+def temp_qv_file(tmp_path):
+    qv_path = tmp_path / "test.qv"
+    entries = [
+        ("d1_seed1", "ddg=-3"),
+        ("d1_seed2", "ddg=-5"),
+        ("d1_seed3", "ddg=-1"),
+        ("d2_seed1", "ddg=-2"),
+        ("d2_seed2", None),
+        ("other", "ddg=-9"),
+    ]
+    with open(qv_path, "w") as f:
+        for tag, scores in entries:
+            f.write(f"QV_TAG {tag}\n")
+            if scores:
+                f.write(f"QV_SCORE {tag} {scores}\n")
+            f.write("ATOM      1  N   ALA A   1      11.104  13.203  10.000  1.00 20.00           N\n")
+    return qv_path
+
+def test_best_per_group(temp_qv_file):
+    """Verify the best seed of every design is kept, in ranking order."""
+    best = best_per_group(str(temp_qv_file), r"^(.*)_seed\d+$", "ddg")
+    assert best == ["other", "d1_seed2", "d2_seed1"]
+
+def test_best_per_group_top_k(temp_qv_file):
+    """Verify unscored entries only fill a group after its scored ones."""
+    best = best_per_group(str(temp_qv_file), r"^(.*)_seed\d+$", "ddg", k=2)
+    assert best == ["other", "d1_seed2", "d1_seed1", "d2_seed1", "d2_seed2"]
+
+def test_best_per_group_descending(temp_qv_file):
+    """Verify ':desc' keeps the highest values."""
+    best = best_per_group(str(temp_qv_file), r"^(.*)_seed\d+$", "ddg:desc")
+    assert best == ["d1_seed3", "d2_seed1", "other"]
+
+def test_best_per_group_bad_order(temp_qv_file):
+    """Verify an unknown sort order is rejected."""
+    with pytest.raises(ValueError, match="Unknown sort order 'up'"):
+        best_per_group(str(temp_qv_file), r"^(.*)_seed\d+$", "ddg:up")
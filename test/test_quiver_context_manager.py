@@ -0,0 +1,57 @@
+import pytest
+import os
+import sys
+
+# Ensure src is in sys.path
+sys.path.insert(0, os.path.abspath(os.path.join(os.path.dirname(__file__), "..", "src")))
+
+from quiver.quiver import Quiver
+
+PDB_LINES = ["ATOM      1  N   ALA A   1      11.104  13.203  10.000  1.00 20.00           N\n"]
+
+def test_with_block_flushes_on_exit(tmp_path):
+    """Verify entries written inside a with block are readable afterwards."""
+    qv_path = str(tmp_path / "test.qv")
+    with Quiver(qv_path, "w") as q:
+        q.add_pdb(PDB_LINES, "tag1", "ddg=-1.0")
+        q.add_pdb(PDB_LINES, "tag2")
+    assert q.closed
+    assert Quiver(qv_path, "r").get_tags() == ["tag1", "tag2"]
+
+def test_exception_leaves_complete_entries(tmp_path):
+    """Verify an exception inside the block still closes the file cleanly."""
+    qv_path = str(tmp_path / "test.qv")
+    with pytest.raises(ValueError, match="Tag tag1 already exists"):
+        with Quiver(qv_path, "w") as q:
+            q.add_pdb(PDB_LINES, "tag1")
+            q.add_pdb(PDB_LINES, "tag1")
+    r = Quiver(qv_path, "r")
+    assert r.get_tags() == ["tag1"]
+    assert r.get_pdblines("tag1") == PDB_LINES
+
+def test_add_after_close_raises(tmp_path):
+    """Verify writing through a closed handle is rejected."""
+    q = Quiver(str(tmp_path / "test.qv"), "w")
+    q.close()
+    q.close()
+    with pytest.raises(RuntimeError, match="Quiver file is closed."):
+        q.add_pdb(PDB_LINES, "tag1")
+
+def test_flush_makes_entries_visible(tmp_path):
+    """Verify flush() exposes entries to other readers before close."""
+    qv_path = str(tmp_path / "test.qv")
+    q = Quiver(qv_path, "w")
+    q.add_pdb(PDB_LINES, "tag1")
+    q.flush()
+    assert Quiver(qv_path, "r").get_tags() == ["tag1"]
+    q.close()
+
+def test_entries_visible_without_flush(tmp_path):
+    """Verify each added entry is readable elsewhere before flush or close."""
+    qv_path = str(tmp_path / "test.qv")
+    q = Quiver(qv_path, "w")
+    q.add_pdb(PDB_LINES, "tag1")
+    assert Quiver(qv_path, "r").get_tags() == ["tag1"]
+    q.add_pdb(PDB_LINES, "tag2", "ddg=-1.0")
+    assert Quiver(qv_path, "r").get_scores("tag2") == {"ddg": -1.0}
+    q.close()
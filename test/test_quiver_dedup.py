@@ -0,0 +1,66 @@
+import pytest
+import os
+import sys
+
+# Ensure src is in sys.path
+sys.path.insert(0, os.path.abspath(os.path.join(os.path.dirname(__file__), "..", "src")))
+
+from quiver.quiver import Quiver, dedup_tags
+
+def pdb_line(x):
+    return f"ATOM      1  CA  ALA A   1      {x:6.3f}   6.134  -6.504  1.00  0.00           C\n"
+
+@pytest.fixture
+def merged_qv_file(tmp_path):
+    # Written by hand, since Quiver refuses to add a tag twice
+    qv_path = tmp_path / "merged.qv"
+    entries = [("a", 1.0), ("b", 2.0), ("a", 3.0), ("c", 4.0), ("a", 5.0)]
+    qv_path.write_text(
+        "".join(f"QV_TAG {tag}\nQV_SCORE {tag} x={x}\n{pdb_line(x)}" for tag, x in entries)
+    )
+    return str(qv_path)
+
+@pytest.mark.parametrize("keep, expected_x", [("first", 1.0), ("last", 5.0)])
+def test_dedup_in_place(merged_qv_file, keep, expected_x):
+    """Verify one entry per tag is kept, with its score lines, in place."""
+    dropped = dedup_tags(merged_qv_file, keep=keep)
+    assert [tag for tag, _ in dropped] == ["a", "a"]
+    assert [pos for _, pos in dropped] == ([2, 4] if keep == "first" else [0, 2])
+    qv = Quiver(merged_qv_file, "r")
+    assert sorted(qv.get_tags()) == ["a", "b", "c"]
+    assert qv.get_pdblines("a") == [pdb_line(expected_x)]
+    assert f"QV_SCORE a x={expected_x}" in open(merged_qv_file).read()
+
+def test_dedup_to_output(merged_qv_file, tmp_path):
+    """Verify writing to an output leaves the input alone and refuses to overwrite."""
+    before = open(merged_qv_file).read()
+    out = tmp_path / "clean.qv"
+    dedup_tags(merged_qv_file, output=str(out))
+    assert open(merged_qv_file).read() == before
+    assert Quiver(str(out), "r").get_tags() == ["a", "b", "c"]
+    with pytest.raises(FileExistsError):
+        dedup_tags(merged_qv_file, output=str(out))
+
+def test_dedup_without_duplicates(tmp_path):
+    """Verify an archive with unique tags is left as it is."""
+    qv_path = tmp_path / "unique.qv"
+    with Quiver(str(qv_path), "w") as qv:
+        qv.add_pdb([pdb_line(1.0)], "a")
+        qv.add_pdb([pdb_line(2.0)], "b")
+    before = qv_path.read_text()
+    assert dedup_tags(str(qv_path), keep="last") == []
+    assert qv_path.read_text() == before
+    with pytest.raises(ValueError):
+        dedup_tags(str(qv_path), keep="middle")
+
+TORN_TAIL = "QV_TAG y\nATOM      1  CA  ALA A   1      11.1"
+
+@pytest.mark.parametrize("keep", ["first", "last"])
+def test_dedup_keeps_partial_tail(tmp_path, keep):
+    """Verify an entry cut short at the end is copied through, not dropped or miscounted."""
+    qv_path = tmp_path / "torn.qv"
+    qv_path.write_text(f"QV_TAG x\n{pdb_line(1.0)}QV_TAG x\n{pdb_line(2.0)}" + TORN_TAIL)
+    dropped = dedup_tags(str(qv_path), keep=keep)
+    assert dropped == [("x", 1 if keep == "first" else 0)]
+    kept_x = 1.0 if keep == "first" else 2.0
+    assert qv_path.read_text() == f"QV_TAG x\n{pdb_line(kept_x)}" + TORN_TAIL
@@ -0,0 +1,43 @@
+import pytest
+import os
+import sys
+
+# Ensure src is in sys.path
+sys.path.insert(0, os.path.abspath(os.path.join(os.path.dirname(__file__), "..", "src")))
+
+from quiver.quiver import Quiver
+
+@pytest.fixture
+def temp_qv_file(tmp_path):
+    qv_path = tmp_path / "test.qv"
+    with open(qv_path, "w") as f:
+        f.write("QV_TAG tag1\n")
+        f.write("ATOM      1  N   ALA A   1      11.104  13.203  10.000  1.00 20.00           N\n")
+        f.write("QV_TAG tag2\n")
+        f.write("ATOM      1  N   ALA A   1      12.104  14.203  11.000  1.00 21.00           N\n")
+    return qv_path
+
+def test_len_and_contains(temp_qv_file):
+    """Verify len() and `in` reflect the tags in the file."""
+    q = Quiver(str(temp_qv_file), "r")
+    assert len(q) == 2
+    assert "tag1" in q
+    assert "tag3" not in q
+
+def test_getitem_returns_pdblines(temp_qv_file):
+    """Verify qv[tag] returns the same lines as get_pdblines."""
+    q = Quiver(str(temp_qv_file), "r")
+    assert q["tag2"] == q.get_pdblines("tag2")
+
+def test_getitem_missing_tag(temp_qv_file):
+    """Verify KeyError is raised for a tag that is not in the file."""
+    q = Quiver(str(temp_qv_file), "r")
+    with pytest.raises(KeyError, match="Requested tag: tag3 does not exist"):
+        q["tag3"]
+
+def test_contains_sees_added_tags(tmp_path):
+    """Verify tags added through a write handle are found with `in`."""
+    with Quiver(str(tmp_path / "test.qv"), "w") as q:
+        q.add_pdb(["ATOM\n"], "new")
+        assert "new" in q
+        assert len(q) == 1
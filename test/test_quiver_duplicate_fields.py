@@ -0,0 +1,53 @@
+import pytest
+import os
+import sys
+import warnings
+
+# Ensure src is in sys.path
+sys.path.insert(0, os.path.abspath(os.path.join(os.path.dirname(__file__), "..", "src")))
+
+from quiver.quiver import Quiver, resolve_duplicates
+
+@pytest.fixture
+def temp_qv_file(tmp_path):
+    qv_path = tmp_path / "test.qv"
+    with open(qv_path, "w") as f:
+        f.write("QV_TAG tag1\n")
+        f.write("QV_SCORE tag1 ddg=-12.5|rmsd=0.8|ddg=-3.0\n")
+        f.write("ATOM      1  N   ALA A   1      11.104  13.203  10.000  1.00 20.00           N\n")
+    return qv_path
+
+def test_duplicate_default_keeps_last(temp_qv_file):
+    """Verify the last value of a repeated field wins by default."""
+    q = Quiver(str(temp_qv_file), "r")
+    assert q.get_scores("tag1") == {"ddg": -3.0, "rmsd": 0.8}
+
+def test_duplicate_first(temp_qv_file):
+    """Verify the "first" policy keeps the first value."""
+    q = Quiver(str(temp_qv_file), "r", duplicate_policy="first")
+    assert q.get_scores("tag1") == {"ddg": -12.5, "rmsd": 0.8}
+
+def test_duplicate_error(temp_qv_file):
+    """Verify the "error" policy rejects the line and names the field."""
+    q = Quiver(str(temp_qv_file), "r", duplicate_policy="error")
+    with pytest.raises(ValueError, match="Tag tag1: duplicate score field 'ddg'"):
+        q.get_scores("tag1")
+
+def test_duplicate_warn(temp_qv_file):
+    """Verify the "warn" policy keeps the last value and warns once per repeat."""
+    q = Quiver(str(temp_qv_file), "r", duplicate_policy="warn")
+    with warnings.catch_warnings(record=True) as caught:
+        warnings.simplefilter("always")
+        assert q.get_scores("tag1") == {"ddg": -3.0, "rmsd": 0.8}
+    assert len(caught) == 1
+    assert "repeats score field 'ddg'" in str(caught[0].message)
+
+def test_duplicate_unknown_policy(temp_qv_file):
+    """Verify an unknown policy is rejected when opening the file."""
+    with pytest.raises(ValueError, match="Unknown duplicate policy 'newest'"):
+        Quiver(str(temp_qv_file), "r", duplicate_policy="newest")
+
+def test_resolve_duplicates_order():
+    """Verify each field keeps the position of its first occurrence."""
+    fields = [("b", "1"), ("a", "2"), ("b", "3")]
+    assert list(resolve_duplicates(fields).items()) == [("b", "3"), ("a", "2")]
@@ -0,0 +1,46 @@
+import pytest
+import os
+import sys
+
+# Ensure src is in sys.path
+sys.path.insert(0, os.path.abspath(os.path.join(os.path.dirname(__file__), "..", "src")))
+
+from quiver.quiver import Quiver
+
+PDB_LINE = "ATOM      1  CA  ALA A   1      11.104   6.134  -6.504  1.00  0.00           C\n"
+
+EMPTY_ARCHIVES = ["", "QV_HEADER creator=test\n"]
+
+def write_empty(tmp_path, text):
+    qv_path = tmp_path / "empty.qv"
+    qv_path.write_text(text)
+    return str(qv_path)
+
+@pytest.mark.parametrize("text", EMPTY_ARCHIVES)
+def test_empty_archive_split(tmp_path, text):
+    """Verify splitting an archive without entries writes no files."""
+    outdir = tmp_path / "out"
+    qv = Quiver(write_empty(tmp_path, text), "r")
+    assert qv.get_tags() == []
+    assert qv.split(3, str(outdir), "split") == 0
+    assert qv.split_round_robin(3, str(outdir), "stripe") == 0
+    assert os.listdir(outdir) == []
+
+@pytest.mark.parametrize("text", EMPTY_ARCHIVES)
+def test_empty_tag_list(tmp_path, text):
+    """Verify an empty tag list gives empty results rather than errors."""
+    qv = Quiver(write_empty(tmp_path, text), "r")
+    assert qv.get_struct_list([]) == (text, [])
+    result = qv.slice([])
+    assert result.found_tags == [] and result.missing_tags == []
+    assert list(result.warnings) == []
+
+def test_split_needs_a_positive_size(tmp_path):
+    """Verify split refuses chunks of 0 tags instead of dividing by zero."""
+    qv_path = tmp_path / "one.qv"
+    with Quiver(str(qv_path), "w") as qv:
+        qv.add_pdb([PDB_LINE], "design_0")
+    for ntags in (0, -1):
+        with pytest.raises(ValueError):
+            Quiver(str(qv_path), "r").split(ntags, str(tmp_path / "out"), "split")
+    assert Quiver(str(qv_path), "r").split(2, str(tmp_path / "out"), "split") == 1
@@ -0,0 +1,56 @@
+import pytest
+import json
+import os
+import sys
+
+# Ensure src is in sys.path
+sys.path.insert(0, os.path.abspath(os.path.join(os.path.dirname(__file__), "..", "src")))
+
+from quiver.quiver import Quiver, extract_pdbs, payload_digest
+
+def pdb_lines(i):
+    return [
+        f"ATOM      1  CA  ALA A   1    {i:8.3f}   0.000   0.000  1.00  0.00           C\n",
+        "END\n",
+    ]
+
+@pytest.fixture
+def temp_qv_file(tmp_path):
+    qv_path = tmp_path / "designs.qv"
+    with Quiver(str(qv_path), "w") as qv:
+        qv.add_pdb(pdb_lines(0), "design_0", "score=-1.5|rank=2|ok=true")
+        qv.add_pdb(pdb_lines(1), "design_1")
+    return str(qv_path)
+
+@pytest.mark.parametrize("threads", [1, 3])
+def test_extract_per_tag_layout(temp_qv_file, tmp_path, threads):
+    """Verify each entry gets a folder with its model and meta.json."""
+    out = tmp_path / "out"
+    written = extract_pdbs(temp_qv_file, str(out), threads=threads, layout="per-tag")
+    assert written == [str(out / "design_0" / "model.pdb"), str(out / "design_1" / "model.pdb")]
+    assert open(written[0]).readlines() == pdb_lines(0)
+
+    meta = json.load(open(out / "design_0" / "meta.json"))
+    assert meta["tag"] == "design_0"
+    assert meta["model"] == "model.pdb"
+    assert meta["scores"] == {"score": -1.5, "rank": 2, "ok": True}
+    assert meta["provenance"]["source"] == os.path.abspath(temp_qv_file)
+    assert meta["checksum"] == payload_digest(open(written[0]).readlines())
+
+    # An entry without scores still gets its metadata
+    assert json.load(open(out / "design_1" / "meta.json"))["scores"] == {}
+
+def test_extract_per_tag_skips_existing(temp_qv_file, tmp_path):
+    """Verify an existing model file is kept and its folder not rewritten."""
+    folder = tmp_path / "out" / "design_0"
+    folder.mkdir(parents=True)
+    (folder / "model.pdb").write_text("keep me\n")
+    written = extract_pdbs(temp_qv_file, str(tmp_path / "out"), layout="per-tag")
+    assert written == [str(tmp_path / "out" / "design_1" / "model.pdb")]
+    assert (folder / "model.pdb").read_text() == "keep me\n"
+    assert not (folder / "meta.json").exists()
+
+def test_extract_unknown_layout(temp_qv_file, tmp_path):
+    """Verify an unknown layout is rejected."""
+    with pytest.raises(ValueError):
+        extract_pdbs(temp_qv_file, str(tmp_path), layout="nested")
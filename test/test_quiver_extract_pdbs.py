@@ -0,0 +1,48 @@
+import pytest
+import os
+import sys
+
+# Ensure src is in sys.path
+sys.path.insert(0, os.path.abspath(os.path.join(os.path.dirname(__file__), "..", "src")))
+
+from quiver.quiver import Quiver, extract_pdbs, index_path
+
+def pdb_lines(i):
+    return [
+        f"ATOM      1  CA  ALA A   1    {i:8.3f}   0.000   0.000  1.00  0.00           C\n",
+        "END\n",
+    ]
+
+@pytest.fixture
+def temp_qv_file(tmp_path):
+    qv_path = tmp_path / "many.qv"
+    with Quiver(str(qv_path), "w") as qv:
+        for i in range(50):
+            qv.add_pdb(pdb_lines(i), f"design_{i:03d}", f"score={i}")
+    return str(qv_path)
+
+def test_extract_threads_match_serial(temp_qv_file, tmp_path):
+    """Verify a threaded extraction writes the same files as a serial one."""
+    serial = extract_pdbs(temp_qv_file, str(tmp_path / "serial"))
+    threaded = extract_pdbs(temp_qv_file, str(tmp_path / "threaded"), threads=4)
+    assert len(serial) == len(threaded) == 50
+    assert [os.path.basename(p) for p in serial] == [os.path.basename(p) for p in threaded]
+    for a, b in zip(serial, threaded):
+        assert open(a).read() == open(b).read()
+    assert open(threaded[7]).readlines() == pdb_lines(7)
+    assert os.path.exists(index_path(temp_qv_file))
+
+def test_extract_selected_tags_skips_existing(temp_qv_file, tmp_path):
+    """Verify only the requested tags are written and existing files kept."""
+    out = tmp_path / "out"
+    out.mkdir()
+    (out / "design_001.pdb").write_text("keep me\n")
+    written = extract_pdbs(temp_qv_file, str(out), tags=["design_002", "design_001"], threads=2)
+    assert written == [str(out / "design_002.pdb")]
+    assert (out / "design_001.pdb").read_text() == "keep me\n"
+
+def test_extract_missing_tag(temp_qv_file, tmp_path):
+    """Verify an unknown tag fails before anything is written."""
+    with pytest.raises(KeyError):
+        extract_pdbs(temp_qv_file, str(tmp_path / "out"), tags=["design_000", "nope"])
+    assert not (tmp_path / "out").exists()
@@ -0,0 +1,45 @@
+import pytest
+import os
+import sys
+
+# Ensure src is in sys.path
+sys.path.insert(0, os.path.abspath(os.path.join(os.path.dirname(__file__), "..", "src")))
+
+from quiver.quiver import extract_sequences
+
+@pytest.fixture
+def temp_qv_file(tmp_path):
+    qv_path = tmp_path / "test.qv"
+    with open(qv_path, "w") as f:
+        f.write("QV_TAG tag1\n")
+        f.write("SEQRES   1 A    3  MET ALA GLY\n")
+        f.write("ATOM      1  CA  ALA A   2       0.000   0.000   0.000  1.00  0.00           C\n")
+        f.write("ATOM      2  CA  GLY A   3       3.800   0.000   0.000  1.00  0.00           C\n")
+        f.write("ATOM      3  CA  TRP B   1       0.000   3.800   0.000  1.00  0.00           C\n")
+        f.write("HETATM    4 MG    MG C 101       0.000   0.000   0.000  1.00  0.00          MG\n")
+        f.write("QV_TAG tag2\n")
+        f.write("ATOM      1  CA  CYS A   1       0.000   0.000   0.000  1.00  0.00           C\n")
+    return qv_path
+
+def test_extract_sequences_atom(temp_qv_file, tmp_path):
+    """Verify one record per chain with ATOM residues, headed by tag and chain."""
+    out = tmp_path / "out.fasta"
+    assert extract_sequences(str(temp_qv_file), str(out)) == 3
+    assert out.read_text() == ">tag1_A\nAG\n>tag1_B\nW\n>tag2_A\nC\n"
+
+def test_extract_sequences_chains(temp_qv_file, tmp_path):
+    """Verify `chains` limits the output to the named chains."""
+    out = tmp_path / "out.fasta"
+    assert extract_sequences(str(temp_qv_file), str(out), chains=["B"]) == 1
+    assert out.read_text() == ">tag1_B\nW\n"
+
+def test_extract_sequences_seqres(temp_qv_file, tmp_path):
+    """Verify SEQRES records are used where present, ATOM records elsewhere."""
+    out = tmp_path / "out.fasta"
+    extract_sequences(str(temp_qv_file), str(out), source="seqres")
+    assert out.read_text() == ">tag1_A\nMAG\n>tag2_A\nC\n"
+
+def test_extract_sequences_bad_source(temp_qv_file, tmp_path):
+    """Verify an unknown source is rejected."""
+    with pytest.raises(ValueError, match="Unknown sequence source"):
+        extract_sequences(str(temp_qv_file), str(tmp_path / "out.fasta"), source="mmcif")
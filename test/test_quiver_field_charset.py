@@ -0,0 +1,54 @@
+import pytest
+import os
+import sys
+
+# Ensure src is in sys.path
+sys.path.insert(0, os.path.abspath(os.path.join(os.path.dirname(__file__), "..", "src")))
+
+from quiver.quiver import Quiver, normalize_field
+
+@pytest.fixture
+def legacy_qv_file(tmp_path):
+    """A file whose score names were written as Windows-1252."""
+    qv_path = tmp_path / "legacy.qv"
+    qv_path.write_bytes(
+        b"QV_TAG tag1\n"
+        b"QV_SCORE tag1 \xe9nergie=1.5|\xce\x94G=-2.0\n"
+        b"ATOM      1  CA  ALA A   1       0.000   0.000   0.000  1.00  0.00           C\n"
+    )
+    return qv_path
+
+def test_legacy_bytes_cp1252(legacy_qv_file):
+    """Verify invalid UTF-8 is read as Windows-1252 by default, keeping valid UTF-8."""
+    qv = Quiver(str(legacy_qv_file), "r")
+    assert qv.get_scores("tag1") == {"énergie": 1.5, "ΔG": -2.0}
+
+def test_legacy_bytes_replace(legacy_qv_file):
+    """Verify the replace policies substitute the invalid bytes."""
+    assert "�nergie" in Quiver(str(legacy_qv_file), "r", legacy_bytes="replace").get_scores("tag1")
+    assert "?nergie" in Quiver(str(legacy_qv_file), "r", legacy_bytes="replace=?").get_scores("tag1")
+
+def test_legacy_bytes_error(legacy_qv_file):
+    """Verify the error policy refuses files that are not UTF-8."""
+    with pytest.raises(UnicodeDecodeError):
+        Quiver(str(legacy_qv_file), "r", legacy_bytes="error").get_scores("tag1")
+
+def test_normalize_field():
+    """Verify the field charsets keep, refuse or fold non-ASCII names."""
+    assert normalize_field("ΔG") == "ΔG"
+    assert normalize_field("ΔG_kcal", "fold") == "DeltaG_kcal"
+    assert normalize_field("énergie→µ", "fold") == "energie_u"
+    with pytest.raises(ValueError, match="not ASCII"):
+        normalize_field("énergie", "ascii")
+    with pytest.raises(ValueError, match="Unknown field charset"):
+        normalize_field("ddg", "latin1")
+
+def test_write_folds_field_names(tmp_path):
+    """Verify add_pdb and update_scores apply the field charset."""
+    qv_path = tmp_path / "out.qv"
+    with Quiver(str(qv_path), "w", field_charset="fold") as qv:
+        qv.add_pdb(["ATOM\n"], "tag1", score_str="ΔG=-1.0|énergie=2")
+        qv.update_scores("tag1", {"°C": 25})
+    text = qv_path.read_text(encoding="utf-8")
+    assert "QV_SCORE tag1 DeltaG=-1.0|energie=2|degC=25\n" in text
+    assert text.isascii()
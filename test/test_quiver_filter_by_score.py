@@ -0,0 +1,50 @@
+import pytest
+import os
+import sys
+
+# Ensure src is in sys.path
+sys.path.insert(0, os.path.abspath(os.path.join(os.path.dirname(__file__), "..", "src")))
+
+from quiver.quiver import Quiver
+
+@pytest.fixture
+def temp_qv_file(tmp_path):
+    qv_path = tmp_path / "test.qv"
+    with open(qv_path, "w") as f:
+        f.write("QV_TAG tag1\n")
+        f.write("QV_SCORE tag1 ddg=-12.5|rmsd=0.8\n")
+        f.write("ATOM      1  N   ALA A   1      11.104  13.203  10.000  1.00 20.00           N\n")
+        f.write("QV_TAG tag2\n")
+        f.write("ATOM      1  N   ALA A   1      12.104  14.203  11.000  1.00 21.00           N\n")
+        f.write("QV_TAG tag3\n")
+        f.write("QV_SCORE tag3 ddg=-3|rmsd=nan\n")
+        f.write("ATOM      1  N   ALA A   1      13.104  15.203  12.000  1.00 22.00           N\n")
+        f.write("QV_TAG tag4\n")
+        f.write("QV_SCORE tag4 ddg=high|rmsd=true\n")
+        f.write("ATOM      1  N   ALA A   1      14.104  16.203  13.000  1.00 23.00           N\n")
+    return qv_path
+
+def test_filter_by_score_range(temp_qv_file):
+    """Verify both bounds are inclusive."""
+    q = Quiver(str(temp_qv_file), "r")
+    assert q.filter_by_score("ddg", min=-12.5, max=-3) == ["tag1", "tag3"]
+    assert q.filter_by_score("ddg", min=-10, max=0) == ["tag3"]
+
+def test_filter_by_score_open_bounds(temp_qv_file):
+    """Verify a missing bound leaves that side open."""
+    q = Quiver(str(temp_qv_file), "r")
+    assert q.filter_by_score("ddg", max=-5) == ["tag1"]
+    assert q.filter_by_score("ddg", min=-5) == ["tag3"]
+    assert q.filter_by_score("ddg") == ["tag1", "tag3"]
+
+def test_filter_by_score_skips_non_numeric(temp_qv_file):
+    """Verify nan, booleans and missing columns never match."""
+    q = Quiver(str(temp_qv_file), "r")
+    assert q.filter_by_score("rmsd") == ["tag1"]
+    assert q.filter_by_score("plddt") == []
+
+def test_filter_by_score_write_mode(tmp_path):
+    """Verify filtering requires read mode."""
+    q = Quiver(str(tmp_path / "new.qv"), "w")
+    with pytest.raises(RuntimeError, match="read mode"):
+        q.filter_by_score("ddg", min=0)
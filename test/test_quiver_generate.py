@@ -0,0 +1,39 @@
+import pytest
+import math
+import os
+import sys
+
+# Ensure src is in sys.path
+sys.path.insert(0, os.path.abspath(os.path.join(os.path.dirname(__file__), "..", "src")))
+
+from quiver.quiver import Quiver, generate_test_archive
+
+def test_generated_archive_is_valid(tmp_path):
+    """Verify the archive has the requested entries, atoms and scores."""
+    path = str(tmp_path / "fixture.qv")
+    generate_test_archive(path, 5, atoms_per_entry=20, seed=7)
+    qv = Quiver(path, "r")
+    assert qv.get_tags() == [f"synthetic_{i:06d}" for i in range(5)]
+    structure = qv.get_structure("synthetic_000003")
+    atoms = structure.atoms()
+    assert len(atoms) == 20
+    assert [c.id for c in structure.chains] == ["A"]
+    assert math.dist(atoms[0].coord, atoms[1].coord) == pytest.approx(3.8, rel=1e-3)
+    assert set(qv.get_scores("synthetic_000000")) == {"score", "plddt"}
+
+def test_generated_archive_is_deterministic(tmp_path):
+    """Verify a seed reproduces the same bytes and another seed does not."""
+    paths = [str(tmp_path / f"{name}.qv") for name in ("a", "b", "c")]
+    generate_test_archive(paths[0], 3, atoms_per_entry=10, seed=1)
+    generate_test_archive(paths[1], 3, atoms_per_entry=10, seed=1)
+    generate_test_archive(paths[2], 3, atoms_per_entry=10, seed=2)
+    data = [open(p).read() for p in paths]
+    assert data[0] == data[1] != data[2]
+
+def test_generated_archive_without_scores(tmp_path):
+    """Verify unscored archives have no score lines and existing files are kept."""
+    path = tmp_path / "plain.qv"
+    generate_test_archive(str(path), 2, atoms_per_entry=3, with_scores=False)
+    assert "QV_SCORE" not in path.read_text()
+    with pytest.raises(FileExistsError):
+        generate_test_archive(str(path), 1)
@@ -0,0 +1,47 @@
+import pytest
+import os
+import sys
+
+# Ensure src is in sys.path
+sys.path.insert(0, os.path.abspath(os.path.join(os.path.dirname(__file__), "..", "src")))
+
+from quiver.quiver import Quiver
+
+@pytest.fixture
+def temp_qv_file(tmp_path):
+    qv_path = tmp_path / "test.qv"
+    with open(qv_path, "w") as f:
+        f.write("QV_TAG tag1\n")
+        f.write("QV_SCORE tag1 ddg=-12.5\n")
+        f.write("ATOM      1  N   ALA A   1      11.104  13.203  10.000  1.00 20.00           N\n")
+        f.write("ATOM      2  CA  ALA A   1      12.560  13.203  10.000  1.00 20.00           C\n")
+        f.write("ATOM      3  CA  GLY B  12      -1.500   0.250   3.125  1.00 20.00           C\n")
+        f.write("HETATM    4 MG    MG B 101       0.000   0.000   0.000  1.00 20.00          MG\n")
+    return qv_path
+
+def test_get_coords_ca(temp_qv_file):
+    """Verify CA atoms are selected with their chain and residue metadata."""
+    np = pytest.importorskip("numpy")
+    result = Quiver(str(temp_qv_file), "r").get_coords("tag1")
+    assert result.coords.shape == (2, 3)
+    assert np.allclose(result.coords[1], [-1.5, 0.25, 3.125])
+    assert list(result.chain) == ["A", "B"]
+    assert list(result.resseq) == [1, 12]
+    assert list(result.resname) == ["ALA", "GLY"]
+
+def test_get_coords_all_atoms(temp_qv_file):
+    """Verify "all" selects every ATOM record but no HETATM records."""
+    pytest.importorskip("numpy")
+    result = Quiver(str(temp_qv_file), "r").get_coords("tag1", atom_selection="all")
+    assert list(result.name) == ["N", "CA", "CA"]
+
+def test_get_coords_no_match(temp_qv_file):
+    """Verify a selection without matches gives an empty (0, 3) array."""
+    pytest.importorskip("numpy")
+    result = Quiver(str(temp_qv_file), "r").get_coords("tag1", atom_selection=["CB"])
+    assert result.coords.shape == (0, 3)
+
+def test_get_coords_empty_selection(temp_qv_file):
+    """Verify an empty list of atom names is rejected."""
+    with pytest.raises(ValueError, match="at least one atom"):
+        Quiver(str(temp_qv_file), "r").get_coords("tag1", atom_selection=[])
@@ -0,0 +1,55 @@
+import pytest
+import os
+import sys
+
+# Ensure src is in sys.path
+sys.path.insert(0, os.path.abspath(os.path.join(os.path.dirname(__file__), "..", "src")))
+
+from quiver.quiver import Quiver
+
+@pytest.fixture
+def temp_qv_file(tmp_path):
+    qv_path = tmp_path / "test.qv"
+    with open(qv_path, "w") as f:
+        f.write("QV_TAG tag1\n")
+        f.write("QV_SCORE tag1 ddg=-12.5|rmsd=0.8\n")
+        f.write("ATOM      1  N   ALA A   1      11.104  13.203  10.000  1.00 20.00           N\n")
+        f.write("QV_TAG tag2\n")
+        f.write("ATOM      1  N   ALA A   1      12.104  14.203  11.000  1.00 21.00           N\n")
+        f.write("QV_TAG tag3\n")
+        f.write("QV_SCORE tag3 ddg\n")
+        f.write("ATOM      1  N   ALA A   1      13.104  15.203  12.000  1.00 22.00           N\n")
+    return qv_path
+
+def test_get_scores(temp_qv_file):
+    """Verify the score line is parsed into floats."""
+    q = Quiver(str(temp_qv_file), "r")
+    assert q.get_scores("tag1") == {"ddg": -12.5, "rmsd": 0.8}
+
+def test_get_scores_missing_tag(temp_qv_file):
+    """Verify KeyError is raised for a tag that is not in the file."""
+    q = Quiver(str(temp_qv_file), "r")
+    with pytest.raises(KeyError, match="Requested tag: tag4 does not exist"):
+        q.get_scores("tag4")
+
+def test_get_scores_missing_score_line(temp_qv_file):
+    """Verify ValueError is raised for an entry without a QV_SCORE line."""
+    q = Quiver(str(temp_qv_file), "r")
+    with pytest.raises(ValueError, match="Tag tag2 has no QV_SCORE line"):
+        q.get_scores("tag2")
+
+def test_get_scores_typed(tmp_path):
+    """Verify values keep their int, float, bool or string type."""
+    qv_path = tmp_path / "typed.qv"
+    qv_path.write_text("QV_TAG t\nQV_SCORE t n=12|x=1.5|e=1e-3|ok=True|ss=helix|bad=nan\nATOM\n")
+    scores = Quiver(str(qv_path), "r").get_scores("t")
+    bad = scores.pop("bad")
+    assert scores == {"n": 12, "x": 1.5, "e": 0.001, "ok": True, "ss": "helix"}
+    assert type(scores["n"]) is int and type(scores["ok"]) is bool
+    assert bad != bad
+
+def test_get_scores_malformed(temp_qv_file):
+    """Verify ValueError is raised for a field without a value."""
+    q = Quiver(str(temp_qv_file), "r")
+    with pytest.raises(ValueError, match="malformed QV_SCORE line"):
+        q.get_scores("tag3")
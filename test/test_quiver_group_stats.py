@@ -0,0 +1,48 @@
+import pytest
+import os
+import sys
+
+# Ensure src is in sys.path
+sys.path.insert(0, os.path.abspath(os.path.join(os.path.dirname(__file__), "..", "src")))
+
+from quiver.quiver import group_stats
+
+@pytest.fixture
+def temp_qv_file(tmp_path):
+    qv_path = tmp_path / "test.qv"
+    entries = [
+        ("d1_seed1", "ddg=-10|plddt=80"),
+        ("d1_seed2", "ddg=-14|plddt=nan"),
+        ("d2_seed1", "ddg=-3|plddt=high"),
+        ("d2_seed2", None),
+        ("other", "ddg=-9"),
+    ]
+    with open(qv_path, "w") as f:
+        for tag, scores in entries:
+            f.write(f"QV_TAG {tag}\n")
+            if scores:
+                f.write(f"QV_SCORE {tag} {scores}\n")
+            f.write("ATOM      1  N   ALA A   1      11.104  13.203  10.000  1.00 20.00           N\n")
+    return qv_path
+
+def test_group_stats(temp_qv_file):
+    """Verify mean, min and max are computed per group, in first-tag order."""
+    table = group_stats(str(temp_qv_file), r"^(.*)_seed\d+$", ["ddg"])
+    assert table == [
+        {"group": "d1", "entries": 2, "ddg_mean": -12.0, "ddg_min": -14, "ddg_max": -10},
+        {"group": "d2", "entries": 2, "ddg_mean": -3.0, "ddg_min": -3, "ddg_max": -3},
+        {"group": "other", "entries": 1, "ddg_mean": -9.0, "ddg_min": -9, "ddg_max": -9},
+    ]
+
+def test_group_stats_skips_missing_values(temp_qv_file):
+    """Verify nan and non-numeric values are left out, and empty groups get None."""
+    table = group_stats(str(temp_qv_file), r"^(.*)_seed\d+$", "plddt")
+    by_group = {row["group"]: row for row in table}
+    assert by_group["d1"]["plddt_mean"] == 80
+    assert by_group["d2"]["plddt_mean"] is None
+    assert by_group["other"]["plddt_max"] is None
+
+def test_group_stats_whole_match(temp_qv_file):
+    """Verify a pattern without a capture group groups by the whole match."""
+    table = group_stats(str(temp_qv_file), r"^d\d", ["ddg"])
+    assert [(row["group"], row["entries"]) for row in table] == [("d1", 2), ("d2", 2), ("other", 1)]
@@ -0,0 +1,68 @@
+import pytest
+import os
+import sys
+
+# Ensure src is in sys.path
+sys.path.insert(0, os.path.abspath(os.path.join(os.path.dirname(__file__), "..", "src")))
+
+import quiver.quiver as quiver_module
+from quiver.quiver import Quiver, build_index, head_entries, tail_entries
+
+PDB_LINES = ["ATOM      1  CA  ALA A   1      11.104   6.134  -6.504  1.00  0.00           C\n"]
+
+def write_archive(path, tags):
+    with Quiver(str(path), "w") as qv:
+        for i, tag in enumerate(tags):
+            qv.add_pdb(PDB_LINES, tag, f"score={i}")
+
+@pytest.fixture
+def temp_qv_file(tmp_path):
+    qv_path = tmp_path / "test.qv"
+    write_archive(qv_path, [f"design_{i}" for i in range(20)])
+    return str(qv_path)
+
+def test_head_entries(temp_qv_file):
+    """Verify the first entries come back in file order."""
+    entries = head_entries(temp_qv_file, 3)
+    assert [tag for tag, _, _ in entries] == ["design_0", "design_1", "design_2"]
+    assert entries[1] == ("design_1", "score=1", PDB_LINES)
+    assert len(head_entries(temp_qv_file, 100)) == 20
+    assert head_entries(temp_qv_file, 0) == []
+
+def test_tail_entries_without_index(temp_qv_file):
+    """Verify the backward scan finds the last entries across chunk edges."""
+    chunk = quiver_module.TAIL_CHUNK
+    quiver_module.TAIL_CHUNK = 64
+    try:
+        entries = tail_entries(temp_qv_file, 3)
+        all_entries = tail_entries(temp_qv_file, 100)
+    finally:
+        quiver_module.TAIL_CHUNK = chunk
+    assert [tag for tag, _, _ in entries] == ["design_17", "design_18", "design_19"]
+    assert entries[-1] == ("design_19", "score=19", PDB_LINES)
+    assert [tag for tag, _, _ in all_entries] == [f"design_{i}" for i in range(20)]
+
+def test_tail_entries_uses_stale_index(temp_qv_file):
+    """Verify an index of an earlier state is extended by the appended entries."""
+    build_index(temp_qv_file)
+    with open(temp_qv_file, "a") as f:
+        f.write("QV_TAG late_0\n" + PDB_LINES[0] + "QV_TAG late_1\n" + PDB_LINES[0])
+    entries = tail_entries(temp_qv_file, 3)
+    assert [tag for tag, _, _ in entries] == ["design_19", "late_0", "late_1"]
+
+def test_tail_entries_ignores_rewritten_archive(tmp_path):
+    """Verify an index whose entries moved is not trusted."""
+    qv_path = tmp_path / "test.qv"
+    write_archive(qv_path, [f"design_{i}" for i in range(5)])
+    build_index(str(qv_path))
+    os.remove(qv_path)
+    write_archive(qv_path, [f"other_{i}" for i in range(8)])
+    assert [tag for tag, _, _ in tail_entries(str(qv_path), 2)] == ["other_6", "other_7"]
+
+def test_tail_entries_partial_last_entry(temp_qv_file):
+    """Verify an entry still being written comes back as far as it goes."""
+    with open(temp_qv_file, "a") as f:
+        f.write("QV_TAG running\nATOM      1  CA")
+    entries = tail_entries(temp_qv_file, 2)
+    assert [tag for tag, _, _ in entries] == ["design_19", "running"]
+    assert entries[-1][2] == ["ATOM      1  CA"]
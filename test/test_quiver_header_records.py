@@ -0,0 +1,49 @@
+import pytest
+import os
+import sys
+
+# Ensure src is in sys.path
+sys.path.insert(0, os.path.abspath(os.path.join(os.path.dirname(__file__), "..", "src")))
+
+from quiver.quiver import Quiver
+from quiver.structure import Structure
+
+HEADER = [
+    "HEADER    DE NOVO PROTEIN                         01-JAN-24   XXXX              \n",
+    "COMPND    MOL_ID: 1;                                                            \n",
+    "SEQRES   1 A    2  ALA GLY                                                      \n",
+]
+ATOMS = [
+    "ATOM      1  CA  ALA A   1       0.000   0.000   0.000  1.00  0.00           C\n",
+    "ATOM      2  CA  GLY A   2       3.800   0.000   0.000  1.00  0.00           C\n",
+]
+
+@pytest.fixture
+def temp_qv_file(tmp_path):
+    qv_path = tmp_path / "test.qv"
+    with Quiver(str(qv_path), "w") as qv:
+        qv.add_pdb(HEADER + ATOMS, "tag1", "ddg=-1.0")
+        qv.add_pdb(ATOMS, "tag2")
+    return qv_path
+
+def test_get_header_records(temp_qv_file):
+    """Verify the header records of an entry are returned in file order."""
+    qv = Quiver(str(temp_qv_file), "r")
+    assert qv.get_header_records("tag1") == HEADER
+    assert qv.get_header_records("tag2") == []
+    with pytest.raises(KeyError):
+        qv.get_header_records("nope")
+
+def test_structure_round_trip_keeps_header(temp_qv_file):
+    """Verify rewriting a structure carries its header records through."""
+    structure = Quiver(str(temp_qv_file), "r").get_structure("tag1")
+    assert structure.header == HEADER
+    lines = structure.to_lines()
+    assert lines[:3] == HEADER
+    assert Structure.from_lines(lines).header == HEADER
+
+def test_header_can_be_stripped(temp_qv_file):
+    """Verify clearing the header writes only the atoms."""
+    structure = Quiver(str(temp_qv_file), "r").get_structure("tag1")
+    structure.header = []
+    assert structure.to_lines()[0].startswith("ATOM")
@@ -0,0 +1,84 @@
+import pytest
+import os
+import sys
+import threading
+import time
+
+# Ensure src is in sys.path
+sys.path.insert(0, os.path.abspath(os.path.join(os.path.dirname(__file__), "..", "src")))
+
+from quiver.quiver import Quiver, build_index, index_path
+
+PDB_LINES = [
+    "ATOM      1  CA  ALA A   1       0.000   0.000   0.000  1.00  0.00           C\n",
+    "ATOM      2  CA  GLY A   2       3.800   0.000   0.000  1.00  0.00           C\n",
+]
+
+@pytest.fixture
+def qv_file(tmp_path):
+    qv_path = tmp_path / "big.qv"
+    with Quiver(str(qv_path), "w", write_hashes=True) as qv:
+        qv.add_pdb(PDB_LINES, "tag1", "ddg=-1.0")
+        qv.add_pdb(PDB_LINES, "tag2")
+    return str(qv_path)
+
+def test_index_written_and_used(qv_file):
+    """Verify the sidecar is written and reads through it match a scan."""
+    calls = []
+    qv = Quiver(qv_file, "r", index=True, progress=lambda done, total: calls.append((done, total)))
+    size = os.path.getsize(qv_file)
+    assert calls[-1] == (size, size)
+    assert qv.get_tags() == ["tag1", "tag2"]
+    assert qv.get_pdblines("tag2") == PDB_LINES
+    assert qv.get_pdblines("tag1") == Quiver(qv_file, "r").get_pdblines("tag1")
+    with pytest.raises(KeyError):
+        qv.get_pdblines("nope")
+
+    text = open(index_path(qv_file)).read()
+    assert text.startswith(f"QV_INDEX\t{size}\t")
+    assert not any(name.startswith(".") for name in os.listdir(os.path.dirname(qv_file)))
+
+def test_fresh_index_reused(qv_file):
+    """Verify an up-to-date index is read without rebuilding, unless forced."""
+    build_index(qv_file)
+    calls = []
+    build_index(qv_file, progress=lambda *a: calls.append(a))
+    assert calls == []
+    build_index(qv_file, force_rebuild=True, progress=lambda *a: calls.append(a))
+    assert calls
+
+def test_stale_index_rebuilt(qv_file):
+    """Verify appending to the archive makes the index rebuild."""
+    build_index(qv_file)
+    with Quiver(qv_file, "w") as qv:
+        qv.add_pdb(PDB_LINES, "tag3")
+    assert [tag for tag, _, _ in build_index(qv_file)] == ["tag1", "tag2", "tag3"]
+
+def test_waits_for_concurrent_build(qv_file):
+    """Verify a forced build waits for one holding the lock, then uses the
+    index that build wrote instead of building again."""
+    build_index(qv_file)
+    lock = index_path(qv_file) + ".lock"
+    open(lock, "w").close()
+    release = threading.Timer(0.3, os.remove, [lock])
+    release.start()
+    calls = []
+    entries = build_index(qv_file, force_rebuild=True, progress=lambda *a: calls.append(a))
+    release.join()
+    assert calls == []
+    assert [tag for tag, _, _ in entries] == ["tag1", "tag2"]
+
+def test_stale_lock_broken(qv_file):
+    """Verify a lock left by a killed build does not block forever."""
+    lock = index_path(qv_file) + ".lock"
+    open(lock, "w").close()
+    old = time.time() - 3600
+    os.utime(lock, (old, old))
+    with pytest.warns(UserWarning):
+        assert len(build_index(qv_file)) == 2
+    assert not os.path.exists(lock)
+
+def test_index_requires_read_mode(tmp_path):
+    """Verify the index is refused in write mode."""
+    with pytest.raises(ValueError):
+        Quiver(str(tmp_path / "new.qv"), "w", index=True)
@@ -0,0 +1,64 @@
+import pytest
+import gzip
+import os
+import sys
+
+# Ensure src is in sys.path
+sys.path.insert(0, os.path.abspath(os.path.join(os.path.dirname(__file__), "..", "src")))
+
+from quiver.quiver import Quiver, ingest_directory
+
+def pdb_text(i):
+    return f"ATOM      1  CA  ALA A   1    {i:8.3f}   0.000   0.000  1.00  0.00           C\nEND\n"
+
+@pytest.fixture
+def input_dir(tmp_path):
+    root = tmp_path / "designs"
+    for i in range(12):
+        sub = root / f"run{i % 3}"
+        sub.mkdir(parents=True, exist_ok=True)
+        (sub / f"design_{i:02d}.pdb").write_text(pdb_text(i))
+    (root / "run0" / "notes.txt").write_text("not a structure\n")
+    return root
+
+def test_ingest_directory(input_dir, tmp_path):
+    """Verify matching files are written in path order, tagged by stem."""
+    output = str(tmp_path / "out.qv")
+    assert ingest_directory(str(input_dir), output, threads=4) == 12
+    qv = Quiver(output, "r")
+    expected = sorted(
+        (f"run{i % 3}", f"design_{i:02d}") for i in range(12)
+    )
+    assert qv.get_tags() == [tag for _, tag in expected]
+    assert qv.get_pdblines("design_05") == pdb_text(5).splitlines(keepends=True)
+
+def test_ingest_directory_threads_agree(input_dir, tmp_path):
+    """Verify the archive does not depend on the number of threads."""
+    one, many = str(tmp_path / "one.qv"), str(tmp_path / "many.qv")
+    ingest_directory(str(input_dir), one)
+    ingest_directory(str(input_dir), many, threads=8)
+    assert open(one).read() == open(many).read()
+
+def test_ingest_directory_pattern_and_gzip(tmp_path):
+    """Verify the pattern selects files and gzip input is decompressed."""
+    root = tmp_path / "in"
+    root.mkdir()
+    (root / "a.pdb.gz").write_bytes(gzip.compress(pdb_text(1).encode()))
+    (root / "b.pdb").write_text(pdb_text(2))
+    output = str(tmp_path / "gz.qv")
+    assert ingest_directory(str(root), output, pattern="*.pdb.gz") == 1
+    assert Quiver(output, "r").get_pdblines("a") == pdb_text(1).splitlines(keepends=True)
+
+def test_ingest_directory_duplicates(tmp_path):
+    """Verify duplicate stems fail by default and can be renamed."""
+    root = tmp_path / "in"
+    for sub in ("x", "y"):
+        (root / sub).mkdir(parents=True)
+        (root / sub / "model.pdb").write_text(pdb_text(0))
+    with pytest.raises(ValueError):
+        ingest_directory(str(root), str(tmp_path / "a.qv"))
+    output = str(tmp_path / "b.qv")
+    ingest_directory(str(root), output, on_duplicate="rename")
+    assert Quiver(output, "r").get_tags() == ["model", "model_2"]
+    with pytest.raises(FileExistsError):
+        ingest_directory(str(root), output)
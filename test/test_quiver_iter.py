@@ -0,0 +1,46 @@
+import pytest
+import os
+import sys
+
+# Ensure src is in sys.path
+sys.path.insert(0, os.path.abspath(os.path.join(os.path.dirname(__file__), "..", "src")))
+
+from quiver.quiver import Quiver
+
+@pytest.fixture
+def temp_qv_file(tmp_path):
+    qv_path = tmp_path / "test.qv"
+    with open(qv_path, "w") as f:
+        f.write("QV_TAG tag1\n")
+        f.write("QV_SCORE tag1 ddg=-1.5|rms=0.3\n")
+        f.write("ATOM      1  N   ALA A   1      11.104  13.203  10.000  1.00 20.00           N\n")
+        f.write("QV_TAG tag2\n")
+        f.write("ATOM      1  N   ALA A   1      12.104  14.203  11.000  1.00 21.00           N\n")
+    return qv_path
+
+def test_iter_yields_entries_in_file_order(temp_qv_file):
+    """Verify iteration yields (tag, score_str, pdb_lines) for every entry."""
+    q = Quiver(str(temp_qv_file), "r")
+    entries = list(q)
+    assert [tag for tag, _, _ in entries] == ["tag1", "tag2"]
+    assert entries[0][1] == "ddg=-1.5|rms=0.3"
+    assert entries[1][1] is None
+
+def test_iter_matches_get_pdblines(temp_qv_file):
+    """Verify the yielded lines are the same as get_pdblines returns."""
+    q = Quiver(str(temp_qv_file), "r")
+    for tag, _, lines in q:
+        assert lines == q.get_pdblines(tag)
+
+def test_iter_empty_file(tmp_path):
+    """Verify an empty or missing file yields nothing."""
+    empty = tmp_path / "empty.qv"
+    empty.write_text("")
+    assert list(Quiver(str(empty), "r")) == []
+    assert list(Quiver(str(tmp_path / "missing.qv"), "r")) == []
+
+def test_iter_invalid_mode(temp_qv_file):
+    """Verify RuntimeError is raised when iterating in write mode."""
+    q = Quiver(str(temp_qv_file), "w")
+    with pytest.raises(RuntimeError, match="Quiver file must be opened in read mode to allow for reading."):
+        list(q)
@@ -0,0 +1,68 @@
+import pytest
+import os
+import sys
+
+# Ensure src is in sys.path
+sys.path.insert(0, os.path.abspath(os.path.join(os.path.dirname(__file__), "..", "src")))
+
+from quiver.quiver import Quiver, modernize_archive
+
+PDB_LINES = [
+    "ATOM      1  CA  ALA A   1       0.000   0.000   0.000  1.00  0.00           C\n",
+    "ATOM      2  CA  GLY A   2       3.800   0.000   0.000  1.00  0.00           C\n",
+]
+
+@pytest.fixture
+def legacy_file(tmp_path):
+    qv_path = tmp_path / "old.qv"
+    qv_path.write_text(
+        "QVTAG design_1\n"
+        "QVSCORE design_1 ddg=-1.5\n"
+        + "".join(PDB_LINES)
+        + "QV TAG design_2\n"
+        "QV SCORE design_2 ddg=-2.5\n"
+        + "".join(PDB_LINES)
+    )
+    return qv_path
+
+def test_legacy_records_read(legacy_file):
+    """Verify legacy spellings are read as the canonical records."""
+    assert Quiver(str(legacy_file), "r").get_tags() == []
+    qv = Quiver(str(legacy_file), "r", legacy_records=True)
+    assert qv.get_tags() == ["design_1", "design_2"]
+    assert qv.get_pdblines("design_2") == PDB_LINES
+    assert qv.get_scores("design_1") == {"ddg": -1.5}
+    assert [tag for tag, _, _ in qv] == ["design_1", "design_2"]
+
+def test_legacy_records_configurable(tmp_path):
+    """Verify a custom table replaces the documented variants."""
+    qv_path = tmp_path / "custom.qv"
+    qv_path.write_text("QV-TAG a\n" + "".join(PDB_LINES) + "QVTAG b\n" + "".join(PDB_LINES))
+    qv = Quiver(str(qv_path), "r", legacy_records={"QV-TAG": "QV_TAG"})
+    assert qv.get_tags() == ["a"]
+    with pytest.raises(ValueError):
+        Quiver(str(qv_path), "r", legacy_records={"QV-TAG": "QV_TAGS"})
+    with pytest.raises(ValueError):
+        Quiver(str(qv_path), "w", legacy_records=True)
+
+def test_keyword_must_be_whole(tmp_path):
+    """Verify a variant does not match a longer word."""
+    qv_path = tmp_path / "words.qv"
+    qv_path.write_text("QVTAG a\n" + "".join(PDB_LINES) + "QVTAGS b\n")
+    qv = Quiver(str(qv_path), "r", legacy_records=True)
+    assert qv.get_tags() == ["a"]
+    assert qv.get_pdblines("a")[-1] == "QVTAGS b\n"
+
+def test_modernize_archive(legacy_file, tmp_path):
+    """Verify modernize_archive writes the canonical format and reports it."""
+    out = tmp_path / "new.qv"
+    report = modernize_archive(str(legacy_file), str(out))
+    assert report == (2, 4)
+    assert out.read_text() == legacy_file.read_text().replace("QV ", "QV_").replace("QVTAG", "QV_TAG").replace(
+        "QVSCORE", "QV_SCORE"
+    )
+    qv = Quiver(str(out), "r")
+    assert qv.get_tags() == ["design_1", "design_2"]
+    assert qv.get_scores("design_2") == {"ddg": -2.5}
+    with pytest.raises(FileExistsError):
+        modernize_archive(str(legacy_file), str(out))
@@ -0,0 +1,78 @@
+import pytest
+import os
+import sys
+
+# Ensure src is in sys.path
+sys.path.insert(0, os.path.abspath(os.path.join(os.path.dirname(__file__), "..", "src")))
+
+from quiver.quiver import Quiver, rename_tags_map
+
+PDB_LINES = [
+    "ATOM      1  CA  ALA A   1       0.000   0.000   0.000  1.00  0.00           C\n",
+    "ATOM      2  CA  GLY A   2       3.800   0.000   0.000  1.00  0.00           C\n",
+]
+
+@pytest.fixture
+def qv_file(tmp_path):
+    qv_path = tmp_path / "meta.qv"
+    with Quiver(str(qv_path), "w", write_hashes=True) as qv:
+        qv.add_pdb(PDB_LINES, "tag1", "ddg=-1.0", meta={"model": "af2", "seed": 7})
+        qv.add_pdb(PDB_LINES, "tag2")
+    return qv_path
+
+def test_meta_written_and_read(qv_file):
+    """Verify metadata given to add_pdb is read back and kept out of the structure."""
+    assert "QV_META tag1 model=af2|seed=7\n" in qv_file.read_text()
+    qv = Quiver(str(qv_file), "r")
+    assert qv.get_meta("tag1") == {"model": "af2", "seed": "7"}
+    assert qv.get_meta("tag2") == {}
+    assert qv.get_pdblines("tag1") == PDB_LINES
+    assert [lines for _, _, lines in qv] == [PDB_LINES, PDB_LINES]
+    assert qv.verify() == {"tag1": "ok", "tag2": "ok"}
+    with pytest.raises(KeyError):
+        qv.get_meta("nope")
+
+def test_add_meta(qv_file):
+    """Verify add_meta overwrites a field, adds one and gives unscored entries a line."""
+    with Quiver(str(qv_file), "w") as qv:
+        qv.add_meta("tag1", "seed", "8")
+        qv.add_meta("tag1", "run", "r1")
+        qv.add_meta("tag2", "model", "esm")
+    qv = Quiver(str(qv_file), "r")
+    assert qv.get_meta("tag1") == {"model": "af2", "seed": "8", "run": "r1"}
+    assert qv.get_meta("tag2") == {"model": "esm"}
+    assert qv.get_scores("tag1") == {"ddg": -1.0}
+    assert qv.get_pdblines("tag2") == PDB_LINES
+    assert qv_file.read_text().count("QV_META") == 2
+
+def test_add_meta_refused(qv_file):
+    """Verify invalid fields, labels and unknown tags are refused."""
+    with Quiver(str(qv_file), "w") as qv:
+        with pytest.raises(ValueError):
+            qv.add_meta("tag1", "bad key", "x")
+        with pytest.raises(ValueError):
+            qv.add_meta("tag1", "k", "a|b")
+        with pytest.raises(ValueError):
+            qv.add_meta("tag1", "labels", "best")
+        with pytest.raises(KeyError):
+            qv.add_meta("nope", "k", "v")
+
+def test_meta_follows_rename(qv_file, tmp_path):
+    """Verify metadata is carried over to the new tag by a rename."""
+    out = tmp_path / "renamed.qv"
+    rename_tags_map(str(qv_file), {"tag1": "design_1"}, output=str(out))
+    qv = Quiver(str(out), "r")
+    assert qv.get_meta("design_1") == {"model": "af2", "seed": "7"}
+    assert qv.verify("design_1") == {"design_1": "ok"}
+
+def test_meta_follows_slice_and_split(qv_file, tmp_path):
+    """Verify sliced and split entries keep their metadata."""
+    qv = Quiver(str(qv_file), "r")
+    sliced = tmp_path / "sliced.qv"
+    sliced.write_text(qv.slice(["tag1"]).data)
+    assert Quiver(str(sliced), "r").get_meta("tag1") == {"model": "af2", "seed": "7"}
+
+    qv.split(1, str(tmp_path / "shards"), "part")
+    shard = Quiver(str(tmp_path / "shards" / "part_0.qv"), "r")
+    assert shard.get_meta("tag1") == {"model": "af2", "seed": "7"}
+    assert shard.get_pdblines("tag1") == PDB_LINES
@@ -0,0 +1,72 @@
+import pytest
+import os
+import sys
+
+# Ensure src is in sys.path
+sys.path.insert(0, os.path.abspath(os.path.join(os.path.dirname(__file__), "..", "src")))
+
+from quiver.quiver import Quiver, move_entries, move_journal_path, recover_move
+
+PDB_LINES = ["ATOM      1  CA  ALA A   1      11.104   6.134  -6.504  1.00  0.00           C\n"]
+
+def write_archive(path, tags):
+    with Quiver(str(path), "w") as qv:
+        for i, tag in enumerate(tags):
+            qv.add_pdb(PDB_LINES, tag, f"score={i}")
+    return str(path)
+
+@pytest.fixture
+def source(tmp_path):
+    return write_archive(tmp_path / "designs.qv", [f"design_{i}" for i in range(5)])
+
+def test_move_into_new_archive(source, tmp_path):
+    """Verify moved entries leave the source and keep their scores."""
+    dest = str(tmp_path / "accepted.qv")
+    assert move_entries(source, dest, ["design_3", "design_1"]) == 2
+    assert Quiver(source, "r").get_tags() == ["design_0", "design_2", "design_4"]
+    moved = Quiver(dest, "r")
+    assert moved.get_tags() == ["design_1", "design_3"]
+    assert moved.get_scores("design_3") == {"score": 3.0}
+    assert moved.get_pdblines("design_1") == PDB_LINES
+    assert not os.path.exists(move_journal_path(source))
+
+def test_move_appends_to_existing_archive(source, tmp_path):
+    """Verify entries are appended after those already in the destination."""
+    dest = write_archive(tmp_path / "accepted.qv", ["old_0"])
+    move_entries(source, dest, ["design_0"])
+    assert Quiver(dest, "r").get_tags() == ["old_0", "design_0"]
+
+def test_move_refuses_missing_or_clashing_tags(source, tmp_path):
+    """Verify nothing changes when a tag is missing or already moved."""
+    dest = write_archive(tmp_path / "accepted.qv", ["design_2"])
+    with open(source) as f:
+        before = f.read()
+    with pytest.raises(KeyError):
+        move_entries(source, dest, ["design_0", "nope"])
+    with pytest.raises(ValueError):
+        move_entries(source, dest, ["design_2"])
+    with open(source) as f:
+        assert f.read() == before
+    assert Quiver(dest, "r").get_tags() == ["design_2"]
+
+def test_recover_rolls_back_interrupted_copy(source, tmp_path):
+    """Verify a journal still copying cuts the destination back."""
+    dest = write_archive(tmp_path / "accepted.qv", ["old_0"])
+    size = os.path.getsize(dest)
+    with open(dest, "a") as f:
+        f.write("QV_TAG design_0\nATOM")
+    with open(move_journal_path(source), "w") as f:
+        f.write(f"QV_MOVE\tcopying\t{size}\t{dest}\ndesign_0\n")
+    assert recover_move(source) == "copying"
+    assert Quiver(dest, "r").get_tags() == ["old_0"]
+    assert len(Quiver(source, "r").get_tags()) == 5
+    assert not os.path.exists(move_journal_path(source))
+
+def test_recover_completes_copied_move(source, tmp_path):
+    """Verify a journal past copying removes the entries from the source."""
+    dest = str(tmp_path / "accepted.qv")
+    with open(move_journal_path(source), "w") as f:
+        f.write(f"QV_MOVE\tcopied\tnew\t{dest}\ndesign_0\n")
+    assert recover_move(source) == "copied"
+    assert "design_0" not in Quiver(source, "r").get_tags()
+    assert recover_move(source) is None
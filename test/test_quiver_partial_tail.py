@@ -0,0 +1,68 @@
+import pytest
+import os
+import sys
+
+# Ensure src is in sys.path
+sys.path.insert(0, os.path.abspath(os.path.join(os.path.dirname(__file__), "..", "src")))
+
+from quiver.quiver import Quiver
+
+PDB_LINES = [
+    "ATOM      1  CA  ALA A   1       0.000   0.000   0.000  1.00  0.00           C\n",
+    "ATOM      2  CA  GLY A   2       3.800   0.000   0.000  1.00  0.00           C\n",
+]
+
+@pytest.fixture
+def qv_file(tmp_path):
+    qv_path = tmp_path / "running.qv"
+    with Quiver(str(qv_path), "w", write_hashes=True) as qv:
+        qv.add_pdb(PDB_LINES, "tag1", "ddg=-1.0")
+        qv.add_pdb(PDB_LINES, "tag2", "ddg=-2.0")
+    return qv_path
+
+def test_complete_archive(qv_file):
+    """Verify an archive whose entries are all complete hides nothing."""
+    qv = Quiver(str(qv_file), "r")
+    assert qv.partial_entry is None
+    assert qv.get_tags() == ["tag1", "tag2"]
+
+def test_torn_last_line_hidden(qv_file):
+    """Verify an entry whose last line is cut short is hidden with a warning."""
+    text = qv_file.read_text()
+    qv_file.write_text(text[:-10])
+    offset = text.index("QV_TAG tag2")
+    with pytest.warns(UserWarning, match="incomplete last entry tag2"):
+        qv = Quiver(str(qv_file), "r")
+    assert qv.partial_entry == ("tag2", offset, len(text) - 10 - offset)
+    assert qv.get_tags() == ["tag1"]
+    assert "tag2" not in qv
+    assert qv.get_pdblines("tag1") == PDB_LINES
+    with pytest.raises(KeyError):
+        qv.get_pdblines("tag2")
+
+def test_include_partial(qv_file):
+    """Verify include_partial reads what was written of the cut entry."""
+    text = qv_file.read_text()
+    qv_file.write_text(text[:-10])
+    qv = Quiver(str(qv_file), "r", include_partial=True)
+    assert qv.partial_entry.tag == "tag2"
+    assert qv.get_tags() == ["tag1", "tag2"]
+    assert qv.get_pdblines("tag2") == [PDB_LINES[0], PDB_LINES[1][:-10]]
+
+def test_hash_mismatch_hidden(qv_file):
+    """Verify a last entry that lost whole lines is caught by its checksum."""
+    text = qv_file.read_text()
+    qv_file.write_text(text[: -len(PDB_LINES[1])])
+    with pytest.warns(UserWarning):
+        qv = Quiver(str(qv_file), "r")
+    assert qv.partial_entry.tag == "tag2"
+    assert qv.get_tags() == ["tag1"]
+
+def test_torn_tag_line(qv_file):
+    """Verify a tag line cut before its tag does not break reading."""
+    with open(qv_file, "a") as f:
+        f.write("QV_TAG")
+    with pytest.warns(UserWarning):
+        qv = Quiver(str(qv_file), "r")
+    assert qv.get_tags() == ["tag1", "tag2"]
+    assert qv.get_pdblines("tag2") == PDB_LINES
@@ -305,7 +305,7 @@ def test_qvslice(basedir, tmp_path, input_pdb_files, input_qv_file):
 
     # Run qvslice using the tag file as input
     slice_cmd = (
-        f"cat {tags_to_slice_path} | {sys.executable} {slice_script} {input_qv_file}"
+        f"cat {tags_to_slice_path} | {sys.executable} {slice_script} {input_qv_file} -"
     )
     try:
         with open(sliced_qv_path, "w") as f_out:
@@ -528,7 +528,7 @@ def test_qvrename(basedir):
 
     # Run qvrename
     os.system(
-        f"cat newtags.txt | {basedir}/src/quiver/qvrename.py {qvpath} > renamed.qv"
+        f"cat newtags.txt | {basedir}/src/quiver/qvrename.py {qvpath} - > renamed.qv"
     )
 
     # Run qvextract
@@ -609,7 +609,7 @@ def test_qvextractspecific(basedir):
 
     # Extraction 명령어 수정 (--output-dir 추가)
     os.system(
-        f"cat tags.txt | {basedir}/src/quiver/qvextractspecific.py test.qv --output-dir {test_dir}"
+        f"cat tags.txt | {basedir}/src/quiver/qvextractspecific.py test.qv - --output-dir {test_dir}"
     )
 
     with open("tags.txt", "r") as f:
@@ -0,0 +1,51 @@
+import pytest
+import os
+import sys
+
+# Ensure src is in sys.path
+sys.path.insert(0, os.path.abspath(os.path.join(os.path.dirname(__file__), "..", "src")))
+
+from quiver.quiver import Quiver
+
+PDB_LINES = [
+    "ATOM      1  CA  ALA A   1       0.000   0.000   0.000  1.00  0.00           C\n",
+    "ATOM      2  CA  GLY A   2       3.800   0.000   0.000  1.00  0.00           C\n",
+]
+RAW = "".join(PDB_LINES).encode("utf-8")
+
+def test_raw_entry_reads_back(tmp_path):
+    """Verify a raw entry is written byte for byte and mixes with add_pdb."""
+    qv_path = str(tmp_path / "raw.qv")
+    with Quiver(qv_path, "w") as qv:
+        qv.add_pdb(PDB_LINES, "lines", "ddg=-1.0")
+        qv.add_raw_entry("raw", RAW, "ddg=-2.0")
+        qv.add_raw_entry("unterminated", RAW.rstrip(b"\n"))
+        qv.add_pdb(PDB_LINES, "after")
+    qv = Quiver(qv_path, "r")
+    assert qv.get_tags() == ["lines", "raw", "unterminated", "after"]
+    for tag in qv.get_tags():
+        assert qv.get_pdblines(tag) == PDB_LINES
+    assert "QV_SCORE raw ddg=-2.0\n" in open(qv_path).read()
+
+def test_raw_entry_hash_matches_lines(tmp_path):
+    """Verify the checksum of a raw payload equals that of the same lines."""
+    qv_path = str(tmp_path / "hashed.qv")
+    with Quiver(qv_path, "w", write_hashes=True) as qv:
+        qv.add_raw_entry("raw", RAW)
+        qv.add_raw_entry("crlf", RAW.replace(b"\n", b"\r\n").rstrip(b"\n"))
+        qv.add_pdb(PDB_LINES, "lines")
+    assert Quiver(qv_path, "r").verify() == {"raw": "ok", "crlf": "ok", "lines": "ok"}
+
+def test_raw_entry_checks(tmp_path):
+    """Verify tags are always checked and payloads only with validate."""
+    qv_path = str(tmp_path / "checked.qv")
+    bad = RAW + b"QV_TAG smuggled\n"
+    with Quiver(qv_path, "w") as qv:
+        qv.add_raw_entry("raw", RAW)
+        with pytest.raises(ValueError):
+            qv.add_raw_entry("raw", RAW)
+        with pytest.raises(ValueError):
+            qv.add_raw_entry("two words", RAW)
+        with pytest.raises(ValueError):
+            qv.add_raw_entry("bad", bad, validate=True)
+    assert Quiver(qv_path, "r").get_tags() == ["raw"]
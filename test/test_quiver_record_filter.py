@@ -0,0 +1,50 @@
+import pytest
+import os
+import sys
+
+# Ensure src is in sys.path
+sys.path.insert(0, os.path.abspath(os.path.join(os.path.dirname(__file__), "..", "src")))
+
+from quiver.quiver import Quiver, extract_pdbs
+
+PDB_LINES = [
+    "REMARK   1 GENERATED BY A DESIGN RUN\n",
+    "ATOM      1  CA  ALA A   1      11.104   6.134  -6.504  1.00  0.00           C\n",
+    "ANISOU    1  CA  ALA A   1     2406   1892   1614    198    519   -328       C\n",
+    "HETATM    2 ZN    ZN A 101      12.000   7.000  -5.000  1.00  0.00          ZN\n",
+    "TER       3      ALA A   1\n",
+    "END\n",
+]
+KEPT = [PDB_LINES[1], PDB_LINES[3], PDB_LINES[4], PDB_LINES[5]]
+
+@pytest.fixture
+def temp_qv_file(tmp_path):
+    qv_path = tmp_path / "test.qv"
+    with Quiver(str(qv_path), "w") as qv:
+        qv.add_pdb(PDB_LINES, "design_0", "score=1.0")
+        qv.add_pdb(PDB_LINES, "design_1", "score=2.0")
+    return str(qv_path)
+
+@pytest.mark.parametrize("index", [False, True])
+def test_get_pdblines_record_filter(temp_qv_file, index):
+    """Verify only the requested record types are returned."""
+    qv = Quiver(temp_qv_file, "r", index=index)
+    assert qv.get_pdblines("design_1", record_filter=["ATOM", "HETATM", "TER", "END"]) == KEPT
+    assert qv.get_pdblines("design_1", record_filter=["atom"]) == [PDB_LINES[1]]
+    assert qv.get_pdblines("design_1") == PDB_LINES
+
+def test_record_filter_rejects_string(temp_qv_file):
+    """Verify a bare string is not taken for a list of record types."""
+    with pytest.raises(TypeError):
+        Quiver(temp_qv_file, "r").get_pdblines("design_0", record_filter="ATOM")
+
+@pytest.mark.parametrize("threads", [1, 2])
+def test_extract_record_filter(temp_qv_file, tmp_path, threads):
+    """Verify extraction writes only the requested record types."""
+    written = extract_pdbs(
+        temp_qv_file, str(tmp_path / "out"), threads=threads,
+        record_filter=["ATOM", "HETATM", "TER", "END"],
+    )
+    assert len(written) == 2
+    for outfn in written:
+        assert open(outfn).readlines() == KEPT
@@ -0,0 +1,92 @@
+import pytest
+import os
+import sys
+import threading
+from functools import partial
+from http.server import HTTPServer, SimpleHTTPRequestHandler
+
+# Ensure src is in sys.path
+sys.path.insert(0, os.path.abspath(os.path.join(os.path.dirname(__file__), "..", "src")))
+
+from quiver.quiver import Quiver, build_index
+
+def pdb_lines(i):
+    return [
+        f"ATOM      1  CA  ALA A   1    {i:8.3f}   0.000   0.000  1.00  0.00           C\n",
+        "END\n",
+    ]
+
+class RangeHandler(SimpleHTTPRequestHandler):
+    """Serves files with HEAD and single-range GET, counting bytes sent."""
+
+    def do_GET(self):
+        path = self.translate_path(self.path)
+        if not os.path.isfile(path):
+            self.send_error(404)
+            return
+        data = open(path, "rb").read()
+        start, end = self.headers["Range"].removeprefix("bytes=").split("-")
+        body = data[int(start):int(end) + 1]
+        self.server.bytes_sent += len(body)
+        self.send_response(206)
+        self.send_header("Content-Length", str(len(body)))
+        self.end_headers()
+        self.wfile.write(body)
+
+    def log_message(self, *args):
+        pass
+
+@pytest.fixture
+def server(tmp_path):
+    httpd = HTTPServer(("127.0.0.1", 0), partial(RangeHandler, directory=str(tmp_path)))
+    httpd.bytes_sent = 0
+    thread = threading.Thread(target=httpd.serve_forever, daemon=True)
+    thread.start()
+    yield httpd, f"http://127.0.0.1:{httpd.server_port}"
+    httpd.shutdown()
+    httpd.server_close()
+
+@pytest.fixture
+def archive(tmp_path):
+    qv_path = tmp_path / "designs.qv"
+    with Quiver(str(qv_path), "w") as qv:
+        for i in range(200):
+            qv.add_pdb(pdb_lines(i), f"design_{i:03d}", f"score={i}")
+    return qv_path
+
+def test_remote_read_without_index(server, archive):
+    """Verify a remote archive reads like the local file when it has no index."""
+    httpd, base = server
+    qv = Quiver(f"{base}/designs.qv", "r")
+    assert qv.get_tags() == Quiver(str(archive), "r").get_tags()
+    assert qv.get_pdblines("design_042") == pdb_lines(42)
+    assert qv.get_scores("design_042") == {"score": 42}
+    assert [tag for tag, _, _ in qv][:2] == ["design_000", "design_001"]
+
+def test_remote_read_with_index(server, archive):
+    """Verify only the index and the entry read are fetched when an index exists."""
+    httpd, base = server
+    build_index(str(archive))
+    qv = Quiver(f"{base}/designs.qv", "r")
+    assert len(qv) == 200
+    httpd.bytes_sent = 0
+    assert qv.get_pdblines("design_150") == pdb_lines(150)
+    assert httpd.bytes_sent < 200
+    assert qv.try_get("design_007").pdb_lines == pdb_lines(7)
+
+def test_remote_stale_index_ignored(server, archive):
+    """Verify an index stamped with another archive size is not used."""
+    httpd, base = server
+    build_index(str(archive))
+    with open(archive, "a") as f:
+        f.write("QV_TAG extra\n" + "".join(pdb_lines(0)))
+    qv = Quiver(f"{base}/designs.qv", "r")
+    assert qv.get_tags()[-1] == "extra"
+
+def test_remote_errors(server):
+    """Verify a missing remote archive and write mode are refused."""
+    _, base = server
+    with pytest.raises(FileNotFoundError):
+        Quiver(f"{base}/missing.qv", "r")
+    with pytest.raises(ValueError):
+        Quiver(f"{base}/new.qv", "w")
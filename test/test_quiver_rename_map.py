@@ -0,0 +1,86 @@
+import pytest
+import os
+import subprocess
+import sys
+
+# Ensure src is in sys.path
+sys.path.insert(0, os.path.abspath(os.path.join(os.path.dirname(__file__), "..", "src")))
+
+from quiver.quiver import Quiver, rename_tags_map
+
+PDB_LINES = ["ATOM      1  CA  ALA A   1      11.104   6.134  -6.504  1.00  0.00           C\n"]
+
+@pytest.fixture
+def temp_qv_file(tmp_path):
+    qv_path = tmp_path / "test.qv"
+    with Quiver(str(qv_path), "w", write_hashes=True) as qv:
+        for i in range(4):
+            qv.add_pdb(PDB_LINES, f"design_{i}", f"score={i}")
+    return str(qv_path)
+
+def test_rename_only_mapped_tags(temp_qv_file, tmp_path):
+    """Verify mapped tags are renamed with their score and hash lines."""
+    output = str(tmp_path / "renamed.qv")
+    assert rename_tags_map(temp_qv_file, {"design_1": "keep_1", "design_3": "keep_3"}, output) == 2
+    qv = Quiver(output, "r")
+    assert qv.get_tags() == ["design_0", "keep_1", "design_2", "keep_3"]
+    assert qv.get_scores("keep_3") == {"score": 3.0}
+    assert dict(qv.verify()) == {tag: "ok" for tag in qv.get_tags()}
+
+def test_rename_swap(temp_qv_file, tmp_path):
+    """Verify two tags can trade names."""
+    output = str(tmp_path / "renamed.qv")
+    rename_tags_map(temp_qv_file, {"design_0": "design_1", "design_1": "design_0"}, output)
+    assert Quiver(output, "r").get_tags() == ["design_1", "design_0", "design_2", "design_3"]
+
+@pytest.mark.parametrize(
+    "mapping, error",
+    [
+        ({"nope": "x"}, KeyError),
+        ({"design_0": "design_2"}, ValueError),
+        ({"design_0": "x", "design_1": "x"}, ValueError),
+        ({"design_0": "has space"}, ValueError),
+    ],
+)
+def test_rename_rejects_bad_mapping(temp_qv_file, tmp_path, mapping, error):
+    """Verify unknown tags and collisions are refused before writing."""
+    output = str(tmp_path / "renamed.qv")
+    with pytest.raises(error):
+        rename_tags_map(temp_qv_file, mapping, output)
+    assert not os.path.exists(output)
+
+def test_qvrename_map_option(temp_qv_file, tmp_path):
+    """Verify qvrename.py --map writes the renamed archive to stdout."""
+    map_file = tmp_path / "renames.txt"
+    map_file.write_text("# old new\ndesign_2 final_2\n")
+    script = os.path.join(os.path.dirname(__file__), "..", "src", "quiver", "qvrename.py")
+    result = subprocess.run(
+        [sys.executable, script, temp_qv_file, "--map", str(map_file)],
+        capture_output=True,
+        text=True,
+        stdin=subprocess.DEVNULL,
+        timeout=30,
+    )
+    assert result.returncode == 0
+    assert "QV_TAG final_2\n" in result.stdout
+    assert "QV_SCORE final_2 score=2\n" in result.stdout
+    assert "QV_TAG design_0\n" in result.stdout
+
+def test_rename_in_place(temp_qv_file):
+    """Verify an in-place rename updates the file and the open handle."""
+    with Quiver(temp_qv_file, "w") as qv:
+        assert qv.rename_tags({"design_0": "first"}) == 1
+        assert qv.get_tags() == ["first", "design_1", "design_2", "design_3"]
+        assert "first" in qv and "design_0" not in qv
+    assert Quiver(temp_qv_file, "r").get_scores("first") == {"score": 0.0}
+    leftovers = [n for n in os.listdir(os.path.dirname(temp_qv_file)) if ".tmp" in n]
+    assert leftovers == []
+
+def test_rename_in_place_refused_mapping_changes_nothing(temp_qv_file):
+    """Verify a refused in-place rename leaves the file as it was."""
+    with open(temp_qv_file) as f:
+        before = f.read()
+    with pytest.raises(ValueError):
+        rename_tags_map(temp_qv_file, {"design_0": "design_1"}, in_place=True)
+    with open(temp_qv_file) as f:
+        assert f.read() == before
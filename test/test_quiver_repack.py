@@ -0,0 +1,65 @@
+import pytest
+import os
+import sys
+
+# Ensure src is in sys.path
+sys.path.insert(0, os.path.abspath(os.path.join(os.path.dirname(__file__), "..", "src")))
+
+from quiver.quiver import Quiver, Warnings, repack
+
+PDB_LINE = "ATOM      1  CA  ALA A   1      11.104   6.134  -6.504  1.00  0.00           C\n"
+
+@pytest.fixture
+def temp_qv_file(tmp_path):
+    qv_path = tmp_path / "test.qv"
+    with Quiver(str(qv_path), "w") as qv:
+        qv.add_pdb([PDB_LINE], "a", "plddt=70")
+        qv.add_pdb([PDB_LINE], "b", "plddt=95")
+        qv.add_pdb([PDB_LINE], "c")
+        qv.add_pdb([PDB_LINE], "d", "plddt=80")
+    return str(qv_path)
+
+def entry_texts(path):
+    qv = Quiver(path, "r")
+    return {tag: qv.get_pdblines(tag) for tag in qv.get_tags()}
+
+def test_repack_by_score(temp_qv_file):
+    """Verify entries are reordered best-first with unscored ones last."""
+    before = entry_texts(temp_qv_file)
+    assert repack(temp_qv_file, "score:plddt:desc") == 4
+    assert Quiver(temp_qv_file, "r").get_tags() == ["b", "d", "a", "c"]
+    assert entry_texts(temp_qv_file) == before
+    assert Quiver(temp_qv_file, "r").get_scores("b") == {"plddt": 95}
+    # Already in that order: nothing moves
+    assert repack(temp_qv_file, "score:plddt:desc") == 0
+
+def test_repack_by_taglist(temp_qv_file, tmp_path):
+    """Verify listed tags come first and problems in the list are reported."""
+    order = tmp_path / "order.txt"
+    order.write_text("d\nc\nnope\nd\n")
+    warnings = Warnings()
+    repack(temp_qv_file, f"taglist:{order}", warnings=warnings)
+    assert Quiver(temp_qv_file, "r").get_tags() == ["d", "c", "a", "b"]
+    assert [w.code for w in warnings] == ["missing-tag", "duplicate-request"]
+
+def test_repack_rejects_unknown_order(temp_qv_file):
+    """Verify an order that is neither score: nor taglist: is refused."""
+    with pytest.raises(ValueError):
+        repack(temp_qv_file, "random")
+    with pytest.raises(ValueError):
+        repack(temp_qv_file, "score:plddt:sideways")
+
+def test_repack_keeps_partial_tail(tmp_path):
+    """Verify an entry cut short at the end stays last instead of being deleted."""
+    qv_path = tmp_path / "torn.qv"
+    torn = "QV_TAG y\nATOM      1  CA  ALA A   1      11.1"
+    qv_path.write_text(
+        f"QV_TAG x\nQV_SCORE x plddt=70\n{PDB_LINE}QV_TAG w\nQV_SCORE w plddt=90\n{PDB_LINE}" + torn
+    )
+    order = tmp_path / "order.txt"
+    order.write_text("y\n")
+    assert repack(str(qv_path), f"taglist:{order}") == 0
+    assert repack(str(qv_path), "score:plddt:desc") == 2
+    assert qv_path.read_text() == (
+        f"QV_TAG w\nQV_SCORE w plddt=90\n{PDB_LINE}QV_TAG x\nQV_SCORE x plddt=70\n{PDB_LINE}" + torn
+    )
@@ -0,0 +1,53 @@
+import pytest
+import os
+import sys
+
+# Ensure src is in sys.path
+sys.path.insert(0, os.path.abspath(os.path.join(os.path.dirname(__file__), "..", "src")))
+
+from quiver.quiver import Quiver, repair_archive
+
+PDB_LINES = [
+    "ATOM      1  CA  ALA A   1       0.000   0.000   0.000  1.00  0.00           C\n",
+    "ATOM      2  CA  GLY A   2       3.800   0.000   0.000  1.00  0.00           C\n",
+]
+
+@pytest.fixture
+def qv_file(tmp_path):
+    qv_path = tmp_path / "killed.qv"
+    with Quiver(str(qv_path), "w") as qv:
+        qv.add_pdb(PDB_LINES, "tag1", "ddg=-1.0")
+        qv.add_pdb(PDB_LINES, "tag2", "ddg=-2.0")
+    return qv_path
+
+def test_repair_clean_archive(qv_file, tmp_path):
+    """Verify an undamaged archive is copied byte for byte."""
+    out = tmp_path / "fixed.qv"
+    report = repair_archive(str(qv_file), str(out))
+    assert report == (2, None, [])
+    assert out.read_bytes() == qv_file.read_bytes()
+
+def test_repair_drops_damage(qv_file, tmp_path):
+    """Verify the trailing incomplete record and orphan score lines are removed."""
+    clean = qv_file.read_text()
+    text = "QV_SCORE ghost ddg=0.0\n" + clean.replace(
+        "QV_TAG tag2\n", "QV_SCORE lost ddg=5.0\nQV_TAG tag2\n"
+    ) + "QV_TAG tag3\nQV_SCORE tag3 ddg=-3.0\nATOM      1  CA"
+    qv_file.write_text(text)
+    out = tmp_path / "fixed.qv"
+    report = repair_archive(str(qv_file), str(out))
+    assert report.entries == 2
+    assert report.partial_entry.tag == "tag3"
+    assert report.orphan_scores == [("ghost", 1), ("lost", 6)]
+    assert out.read_text() == clean
+    qv = Quiver(str(out), "r")
+    assert qv.partial_entry is None
+    assert qv.get_tags() == ["tag1", "tag2"]
+
+def test_repair_refuses_existing_output(qv_file, tmp_path):
+    """Verify an existing output file is not overwritten."""
+    out = tmp_path / "fixed.qv"
+    out.write_text("keep me\n")
+    with pytest.raises(FileExistsError):
+        repair_archive(str(qv_file), str(out))
+    assert out.read_text() == "keep me\n"
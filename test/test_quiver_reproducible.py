@@ -0,0 +1,64 @@
+import pytest
+import os
+import sys
+
+# Ensure src is in sys.path
+sys.path.insert(0, os.path.abspath(os.path.join(os.path.dirname(__file__), "..", "src")))
+
+from quiver.quiver import Quiver, ingest_directory, merge_archives, stable_header
+
+def pdb_line(x):
+    return f"ATOM      1  CA  ALA A   1      {x:6.3f}   6.134  -6.504  1.00  0.00           C\n"
+
+def write_archive(path, tags, header="QV_HEADER creator=quiver\nQV_HEADER created=1700000000\n"):
+    path.write_text(header + "".join(f"QV_TAG {tag}\n{pdb_line(i)}" for i, tag in enumerate(tags)))
+    return str(path)
+
+def test_merge_reproducible_ignores_input_order(tmp_path):
+    """Verify the merged bytes do not depend on the order of the inputs."""
+    a = write_archive(tmp_path / "a.qv", ["d_3", "d_1"])
+    b = write_archive(tmp_path / "b.qv", ["d_2"], header="QV_HEADER created=1800000000\n")
+    assert merge_archives([a, b], str(tmp_path / "ab.qv"), reproducible=True) == 3
+    assert merge_archives([b, a], str(tmp_path / "ba.qv"), reproducible=True) == 3
+    ab = (tmp_path / "ab.qv").read_bytes()
+    assert Quiver(str(tmp_path / "ab.qv"), "r").get_tags() == ["d_1", "d_2", "d_3"]
+    assert b"created" not in ab
+    # The header comes from the first input, so only its volatile fields may differ
+    assert (tmp_path / "ba.qv").read_bytes() == ab.replace(b"QV_HEADER creator=quiver\n", b"")
+
+def test_merge_keeps_order_and_refuses_duplicates(tmp_path):
+    """Verify a plain merge concatenates and duplicate tags write nothing."""
+    a = write_archive(tmp_path / "a.qv", ["d_3", "d_1"])
+    b = write_archive(tmp_path / "b.qv", ["d_1"])
+    c = write_archive(tmp_path / "c.qv", ["d_0"], header="")
+    assert merge_archives([a, c], str(tmp_path / "ac.qv")) == 3
+    assert Quiver(str(tmp_path / "ac.qv"), "r").get_tags() == ["d_3", "d_1", "d_0"]
+    assert (tmp_path / "ac.qv").read_text().startswith("QV_HEADER creator=quiver\nQV_HEADER created=1700000000\n")
+    with pytest.raises(ValueError, match="d_1"):
+        merge_archives([a, b], str(tmp_path / "ab.qv"))
+    assert not (tmp_path / "ab.qv").exists()
+    with pytest.raises(FileExistsError):
+        merge_archives([a], str(tmp_path / "ac.qv"))
+
+def test_stable_header_uses_source_date_epoch():
+    """Verify the build time comes from SOURCE_DATE_EPOCH when it is set."""
+    header = [("creator", "quiver"), ("created", "1700000000")]
+    old = os.environ.pop("SOURCE_DATE_EPOCH", None)
+    try:
+        assert stable_header(header) == [("creator", "quiver")]
+        os.environ["SOURCE_DATE_EPOCH"] = "315532800"
+        assert stable_header(header) == [("creator", "quiver"), ("created", "315532800")]
+    finally:
+        os.environ.pop("SOURCE_DATE_EPOCH", None)
+        if old is not None:
+            os.environ["SOURCE_DATE_EPOCH"] = old
+
+def test_ingest_directory_reproducible(tmp_path):
+    """Verify ingestion orders by tag and normalizes line endings."""
+    src = tmp_path / "runs"
+    (src / "z").mkdir(parents=True)
+    (src / "z" / "alpha.pdb").write_bytes(pdb_line(1.0).replace("\n", "\r\n").encode())
+    (src / "beta.pdb").write_text(pdb_line(2.0).rstrip("\n"))
+    out = tmp_path / "out.qv"
+    ingest_directory(str(src), str(out), reproducible=True)
+    assert out.read_text() == f"QV_TAG alpha\n{pdb_line(1.0)}QV_TAG beta\n{pdb_line(2.0)}"
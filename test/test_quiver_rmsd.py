@@ -0,0 +1,79 @@
+import math
+import os
+import sys
+
+import pytest
+
+# Ensure src is in sys.path
+sys.path.insert(0, os.path.abspath(os.path.join(os.path.dirname(__file__), "..", "src")))
+
+from quiver.quiver import Quiver
+
+CA_COORDS = [(0.0, 0.0, 0.0), (3.8, 0.0, 0.0), (5.0, 3.6, 0.0), (4.1, 5.2, 3.3)]
+
+
+def atom_line(serial, name, coord, resseq):
+    x, y, z = coord
+    return (
+        f"ATOM  {serial:5d}  {name:<3} ALA A{resseq:4d}    "
+        f"{x:8.3f}{y:8.3f}{z:8.3f}  1.00 20.00           C\n"
+    )
+
+
+def rotate(coord, angle=1.1):
+    """Rotate about z and translate, which superposition should undo."""
+    x, y, z = coord
+    c, s = math.cos(angle), math.sin(angle)
+    return (c * x - s * y + 7.0, s * x + c * y - 2.0, z + 4.5)
+
+
+@pytest.fixture
+def temp_qv_file(tmp_path):
+    qv_path = tmp_path / "test.qv"
+    structures = {
+        "orig": CA_COORDS,
+        "moved": [rotate(c) for c in CA_COORDS],
+        "mirror": [(-x, y, z) for x, y, z in CA_COORDS],
+        "short": CA_COORDS[:3],
+    }
+    with open(qv_path, "w") as f:
+        for tag, coords in structures.items():
+            f.write(f"QV_TAG {tag}\n")
+            for i, coord in enumerate(coords):
+                f.write(atom_line(i + 1, "CA", coord, i + 1))
+    return qv_path
+
+
+def test_rmsd_rigid_motion(temp_qv_file):
+    """Verify a rotated and translated copy superposes with zero RMSD."""
+    pytest.importorskip("numpy")
+    qv = Quiver(str(temp_qv_file), "r")
+    assert qv.rmsd("orig", "moved") == pytest.approx(0.0, abs=1e-3)
+    assert qv.rmsd("moved", "orig") == pytest.approx(0.0, abs=1e-3)
+
+
+def test_rmsd_mirror_is_not_superposed(temp_qv_file):
+    """Verify a mirror image is not fitted by a reflection."""
+    pytest.importorskip("numpy")
+    assert Quiver(str(temp_qv_file), "r").rmsd("orig", "mirror") > 0.1
+
+
+def test_rmsd_size_mismatch(temp_qv_file):
+    """Verify selections of different sizes are rejected."""
+    pytest.importorskip("numpy")
+    with pytest.raises(ValueError, match="4 CA atoms but short has 3"):
+        Quiver(str(temp_qv_file), "r").rmsd("orig", "short")
+
+
+def test_rmsd_empty_selection(temp_qv_file):
+    """Verify a selection without matching atoms is rejected."""
+    pytest.importorskip("numpy")
+    with pytest.raises(ValueError, match="No CB atoms"):
+        Quiver(str(temp_qv_file), "r").rmsd("orig", "moved", atom_selection="CB")
+
+
+def test_rmsd_missing_tag(temp_qv_file):
+    """Verify an unknown tag raises KeyError."""
+    pytest.importorskip("numpy")
+    with pytest.raises(KeyError):
+        Quiver(str(temp_qv_file), "r").rmsd("orig", "nope")
@@ -0,0 +1,53 @@
+import pytest
+import os
+import sys
+
+# Ensure src is in sys.path
+sys.path.insert(0, os.path.abspath(os.path.join(os.path.dirname(__file__), "..", "src")))
+
+from quiver.quiver import Quiver
+
+@pytest.fixture
+def temp_qv_file(tmp_path):
+    qv_path = tmp_path / "test.qv"
+    with open(qv_path, "w") as f:
+        f.write("QV_TAG tag1\n")
+        f.write("QV_SCORE tag1 ddg=-12.5|n=3|ok=true\n")
+        f.write("ATOM      1  N   ALA A   1      11.104  13.203  10.000  1.00 20.00           N\n")
+        f.write("QV_TAG tag2\n")
+        f.write("ATOM      1  N   ALA A   1      12.104  14.203  11.000  1.00 21.00           N\n")
+        f.write("QV_TAG tag3\n")
+        f.write("QV_SCORE tag3 ddg=-3|note=fixed\n")
+        f.write("ATOM      1  N   ALA A   1      13.104  15.203  12.000  1.00 22.00           N\n")
+    return qv_path
+
+def test_get_score_records(temp_qv_file):
+    """Verify score lines are read in file order with typed values."""
+    q = Quiver(str(temp_qv_file), "r")
+    assert q.get_score_records() == [
+        ("tag1", {"ddg": -12.5, "n": 3, "ok": True}),
+        ("tag3", {"ddg": -3, "note": "fixed"}),
+    ]
+
+def test_scores_dataframe_pandas(temp_qv_file):
+    """Verify column order, dtypes and nulls of the pandas frame."""
+    pd = pytest.importorskip("pandas")
+    df = Quiver(str(temp_qv_file), "r").scores_dataframe()
+    assert list(df.columns) == ["tag", "ddg", "n", "ok", "note"]
+    assert list(df["tag"]) == ["tag1", "tag3"]
+    assert str(df["n"].dtype) == "Int64"
+    assert str(df["ok"].dtype) == "boolean"
+    assert df["ddg"].tolist() == [-12.5, -3.0]
+    assert pd.isna(df["n"][1]) and pd.isna(df["note"][0])
+
+def test_scores_dataframe_polars(temp_qv_file):
+    """Verify the polars frame has the same columns and nulls."""
+    pytest.importorskip("polars")
+    df = Quiver(str(temp_qv_file), "r").scores_dataframe(backend="polars")
+    assert df.columns == ["tag", "ddg", "n", "ok", "note"]
+    assert df["n"].to_list() == [3, None]
+
+def test_scores_dataframe_unknown_backend(temp_qv_file):
+    """Verify an unknown backend is rejected."""
+    with pytest.raises(ValueError, match="Unknown backend 'arrow'"):
+        Quiver(str(temp_qv_file), "r").scores_dataframe(backend="arrow")
@@ -0,0 +1,41 @@
+import pytest
+import os
+import sys
+
+# Ensure src is in sys.path
+sys.path.insert(0, os.path.abspath(os.path.join(os.path.dirname(__file__), "..", "src")))
+
+from quiver.quiver import Quiver
+
+PDB_LINES = ["ATOM      1  CA  ALA A   1      11.104   6.134  -6.504  1.00  0.00           C\n"]
+
+@pytest.fixture
+def temp_qv_file(tmp_path):
+    qv_path = tmp_path / "test.qv"
+    with Quiver(str(qv_path), "w") as qv:
+        for i in range(20):
+            qv.add_pdb(PDB_LINES, f"design_{i}", f"ddg={-i}.5|pass={'true' if i % 2 else 'false'}")
+        qv.add_pdb(PDB_LINES, "unscored")
+    return str(qv_path)
+
+@pytest.mark.parametrize("index", [False, True])
+def test_get_scores_many(temp_qv_file, index):
+    """Verify scores come back in request order, with missing tags listed apart."""
+    qv = Quiver(temp_qv_file, "r", index=index)
+    lookup = qv.get_scores_many(["design_7", "nope", "design_2", "unscored", "design_7"])
+    assert list(lookup.scores) == ["design_7", "design_2", "unscored"]
+    assert lookup.scores["design_7"] == {"ddg": -7.5, "pass": True}
+    assert lookup.scores["design_2"] == qv.get_scores("design_2")
+    assert lookup.scores["unscored"] == {}
+    assert lookup.missing_tags == ["nope"]
+
+def test_get_scores_many_empty(temp_qv_file):
+    """Verify an empty request reads nothing and finds nothing."""
+    assert Quiver(temp_qv_file, "r").get_scores_many([]) == ({}, [])
+
+def test_get_scores_many_malformed(tmp_path):
+    """Verify a malformed score line of a requested tag raises."""
+    qv_path = tmp_path / "bad.qv"
+    qv_path.write_text("QV_TAG a\nQV_SCORE a ddg\n" + PDB_LINES[0])
+    with pytest.raises(ValueError):
+        Quiver(str(qv_path), "r").get_scores_many(["a"])
@@ -0,0 +1,44 @@
+import pytest
+import os
+import sys
+
+# Ensure src is in sys.path
+sys.path.insert(0, os.path.abspath(os.path.join(os.path.dirname(__file__), "..", "src")))
+
+from quiver.quiver import Quiver, ingest_directory, verify_archive
+from quiver.validate import structure_content
+
+PDB_LINE = "ATOM      1  CA  ALA A   1      11.104   6.134  -6.504  1.00  0.00           C\n"
+LOG_LINES = ["2024-05-01 12:00:01 starting run\n", "2024-05-01 12:03:17 done\n"]
+
+def test_structure_content_rejects_logs(tmp_path):
+    """Verify payloads without coordinates are refused, naming their first line."""
+    with Quiver(str(tmp_path / "out.qv"), "w", validators=[structure_content()]) as qv:
+        qv.add_pdb([PDB_LINE], "design_0")
+        with pytest.raises(ValueError, match="not a structure .*starts with: 2024-05-01 12:00:01"):
+            qv.add_pdb(LOG_LINES, "run_log")
+        with pytest.raises(ValueError, match="payload is empty"):
+            qv.add_pdb(["\n"], "blank")
+        assert qv.get_tags() == ["design_0"]
+
+def test_verify_flags_non_structures(tmp_path):
+    """Verify verification reports ingested junk as not-structure."""
+    qv_path = str(tmp_path / "mixed.qv")
+    with Quiver(qv_path, "w", write_hashes=True) as qv:
+        qv.add_pdb([PDB_LINE], "design_0")
+        qv.add_pdb(LOG_LINES, "run_log")
+    expected = {"design_0": "ok", "run_log": "not-structure"}
+    assert Quiver(qv_path, "r").verify() == expected
+    assert verify_archive(qv_path) == expected
+
+def test_ingest_directory_refuses_junk(tmp_path):
+    """Verify a loose pattern picking up a log fails unless allowed."""
+    src = tmp_path / "runs"
+    src.mkdir()
+    (src / "a.pdb").write_text(PDB_LINE)
+    (src / "notes.pdb").write_text("".join(LOG_LINES))
+    with pytest.raises(ValueError, match="notes rejected: not a structure"):
+        ingest_directory(str(src), str(tmp_path / "strict.qv"), pattern="*")
+    assert ingest_directory(
+        str(src), str(tmp_path / "loose.qv"), pattern="*", allow_non_structure=True
+    ) == 2
@@ -0,0 +1,43 @@
+import pytest
+import os
+import sys
+
+# Ensure src is in sys.path
+sys.path.insert(0, os.path.abspath(os.path.join(os.path.dirname(__file__), "..", "src")))
+
+from quiver.quiver import Quiver
+
+@pytest.fixture
+def temp_qv_file(tmp_path):
+    qv_path = tmp_path / "test.qv"
+    with open(qv_path, "w") as f:
+        f.write("QV_TAG tag1\n")
+        f.write("QV_SCORE tag1 plddt=81.5\n")
+        f.write("ATOM      1  N   ALA A   1      11.104  13.203  10.000  1.00 20.00           N\n")
+        f.write("QV_TAG tag2\n")
+        f.write("ATOM      1  N   ALA A   1      12.104  14.203  11.000  1.00 21.00           N\n")
+        f.write("QV_TAG tag3\n")
+        f.write("QV_SCORE tag3 plddt=92\n")
+        f.write("ATOM      1  N   ALA A   1      13.104  15.203  12.000  1.00 22.00           N\n")
+        f.write("QV_TAG tag4\n")
+        f.write("QV_SCORE tag4 plddt=81.5\n")
+        f.write("ATOM      1  N   ALA A   1      14.104  16.203  13.000  1.00 23.00           N\n")
+    return qv_path
+
+def test_sort_tags_descending(temp_qv_file):
+    """Verify the default order is descending with ties in file order."""
+    q = Quiver(str(temp_qv_file), "r")
+    assert q.sort_tags("plddt") == ["tag3", "tag1", "tag4", "tag2"]
+
+def test_sort_tags_ascending(temp_qv_file):
+    """Verify ascending order keeps ties in file order too."""
+    q = Quiver(str(temp_qv_file), "r")
+    assert q.sort_tags("plddt", descending=False) == ["tag1", "tag4", "tag3", "tag2"]
+
+def test_sort_tags_missing(temp_qv_file):
+    """Verify tags without the score can go first, last or be dropped."""
+    q = Quiver(str(temp_qv_file), "r")
+    assert q.sort_tags("plddt", missing="first") == ["tag2", "tag3", "tag1", "tag4"]
+    assert q.sort_tags("plddt", missing="drop") == ["tag3", "tag1", "tag4"]
+    with pytest.raises(ValueError, match="Unknown missing-value placement 'middle'"):
+        q.sort_tags("plddt", missing="middle")
@@ -0,0 +1,40 @@
+import pytest
+import os
+import sys
+
+# Ensure src is in sys.path
+sys.path.insert(0, os.path.abspath(os.path.join(os.path.dirname(__file__), "..", "src")))
+
+from quiver.quiver import Quiver
+
+PDB_LINE = "ATOM      1  CA  ALA A   1      11.104   6.134  -6.504  1.00  0.00           C\n"
+
+@pytest.fixture
+def temp_qv_file(tmp_path):
+    qv_path = tmp_path / "test.qv"
+    with Quiver(str(qv_path), "w") as qv:
+        for i in range(7):
+            qv.add_pdb([PDB_LINE], f"design_{i}", score_str=f"rank={i}")
+    return str(qv_path)
+
+def shard_tags(outdir, prefix, count):
+    return [Quiver(os.path.join(outdir, f"{prefix}_{i}.qv"), "r").get_tags() for i in range(count)]
+
+def test_split_round_robin_deals_entries(temp_qv_file, tmp_path):
+    """Verify entries are dealt out to the shards in turn, with their scores."""
+    outdir = str(tmp_path / "out")
+    assert Quiver(temp_qv_file, "r").split_round_robin(3, outdir, "stripe") == 3
+    assert shard_tags(outdir, "stripe", 3) == [
+        ["design_0", "design_3", "design_6"],
+        ["design_1", "design_4"],
+        ["design_2", "design_5"],
+    ]
+    assert Quiver(os.path.join(outdir, "stripe_1.qv"), "r").get_scores("design_4") == {"rank": 4}
+
+def test_split_round_robin_more_shards_than_entries(temp_qv_file, tmp_path):
+    """Verify only as many files as entries are written, and 0 shards is refused."""
+    outdir = str(tmp_path / "out")
+    assert Quiver(temp_qv_file, "r").split_round_robin(10, outdir, "one") == 7
+    assert sorted(os.listdir(outdir)) == [f"one_{i}.qv" for i in range(7)]
+    with pytest.raises(ValueError):
+        Quiver(temp_qv_file, "r").split_round_robin(0, outdir, "none")
@@ -0,0 +1,43 @@
+import pytest
+import os
+import sys
+
+# Ensure src is in sys.path
+sys.path.insert(0, os.path.abspath(os.path.join(os.path.dirname(__file__), "..", "src")))
+
+from quiver.quiver import Quiver
+
+PDB_LINE = "ATOM      1  CA  ALA A   1      11.104   6.134  -6.504  1.00  0.00           C\n"
+
+@pytest.fixture
+def scored_qv_file(tmp_path):
+    qv_path = tmp_path / "designs.qv"
+    plddts = [55.0, 70.0, 89.9, 90.0, None, 95.5, "bad"]
+    with Quiver(str(qv_path), "w") as qv:
+        for i, plddt in enumerate(plddts):
+            scores = {"rmsd": 1.0} if plddt is None else {"plddt": plddt}
+            qv.add_pdb([PDB_LINE], f"design_{i}", score_str="|".join(f"{k}={v}" for k, v in scores.items()))
+    return str(qv_path)
+
+def test_split_by_score_buckets(scored_qv_file, tmp_path):
+    """Verify entries land in the bucket of their score, edges going up."""
+    outdir = str(tmp_path / "out")
+    counts = Quiver(scored_qv_file, "r").split_by_score("plddt", [70, 90], outdir, "triage")
+    assert counts == {"lt70": 1, "70-90": 2, "ge90": 2, "missing": 2}
+    def tags(bucket):
+        return Quiver(os.path.join(outdir, f"triage_{bucket}.qv"), "r").get_tags()
+    assert tags("lt70") == ["design_0"]
+    assert tags("70-90") == ["design_1", "design_2"]
+    assert tags("ge90") == ["design_3", "design_5"]
+    assert tags("missing") == ["design_4", "design_6"]
+    assert Quiver(os.path.join(outdir, "triage_ge90.qv"), "r").get_scores("design_5") == {"plddt": 95.5}
+
+def test_split_by_score_only_used_buckets(scored_qv_file, tmp_path):
+    """Verify empty buckets get no file and bad edges are refused."""
+    outdir = str(tmp_path / "out")
+    counts = Quiver(scored_qv_file, "r").split_by_score("plddt", [0.5], outdir, "split")
+    assert counts == {"ge0.5": 5, "missing": 2}
+    assert sorted(os.listdir(outdir)) == ["split_ge0.5.qv", "split_missing.qv"]
+    for edges in ([], [90, 70], [70, 70], [float("nan")]):
+        with pytest.raises(ValueError):
+            Quiver(scored_qv_file, "r").split_by_score("plddt", edges, outdir, "split")
@@ -0,0 +1,46 @@
+import pytest
+import os
+import sys
+
+# Ensure src is in sys.path
+sys.path.insert(0, os.path.abspath(os.path.join(os.path.dirname(__file__), "..", "src")))
+
+from quiver.quiver import Quiver
+
+PDB_LINE = "ATOM      1  CA  ALA A   1      11.104   6.134  -6.504  1.00  0.00           C\n"
+
+@pytest.fixture
+def temp_qv_file(tmp_path):
+    qv_path = tmp_path / "test.qv"
+    with Quiver(str(qv_path), "w") as qv:
+        for i, atoms in enumerate([1, 1, 1, 5, 1]):
+            qv.add_pdb([PDB_LINE] * atoms, f"design_{i}")
+    return str(qv_path)
+
+def shard_tags(outdir, prefix, count):
+    return [Quiver(os.path.join(outdir, f"{prefix}_{i}.qv"), "r").get_tags() for i in range(count)]
+
+def test_split_by_size_respects_budget(temp_qv_file, tmp_path):
+    """Verify shards stay within the budget and keep every entry whole."""
+    outdir = str(tmp_path / "out")
+    entry = len(f"QV_TAG design_0\n{PDB_LINE}")
+    written = Quiver(temp_qv_file, "r").split_by_size(2 * entry, outdir, "part")
+    assert written == 4
+    assert shard_tags(outdir, "part", written) == [
+        ["design_0", "design_1"],
+        ["design_2"],
+        ["design_3"],
+        ["design_4"],
+    ]
+    for i in (0, 1, 3):
+        assert os.path.getsize(os.path.join(outdir, f"part_{i}.qv")) <= 2 * entry
+    # The oversized entry gets a shard of its own, intact
+    assert Quiver(os.path.join(outdir, "part_2.qv"), "r").get_pdblines("design_3") == [PDB_LINE] * 5
+
+def test_split_by_size_large_budget(temp_qv_file, tmp_path):
+    """Verify a budget larger than the archive writes a single shard."""
+    outdir = str(tmp_path / "out")
+    assert Quiver(temp_qv_file, "r").split_by_size(1 << 30, outdir, "all") == 1
+    assert open(os.path.join(outdir, "all_0.qv")).read() == open(temp_qv_file).read()
+    with pytest.raises(ValueError):
+        Quiver(temp_qv_file, "r").split_by_size(0, outdir, "none")
@@ -0,0 +1,48 @@
+import pytest
+import os
+import sys
+
+# Ensure src is in sys.path
+sys.path.insert(0, os.path.abspath(os.path.join(os.path.dirname(__file__), "..", "src")))
+
+from quiver.quiver import Quiver
+
+TWO_CHAINS = [
+    "ATOM      1  N   ALA A   1      11.104   6.134  -6.504  1.00  0.00           N\n",
+    "ATOM      2  CA  ALA A   1      11.639   6.071  -5.147  1.00  0.00           C\n",
+    "ATOM      3  CA  GLY A   2      12.104   7.134  -4.504  1.00  0.00           C\n",
+    "TER\n",
+    "ATOM      4  CA  SER B   1      14.104   8.134  -3.504  1.00  0.00           C\n",
+    "END\n",
+]
+ONE_CHAIN = [
+    "ATOM      1  CA  ALA C   5      11.104   6.134  -6.504  1.00  0.00           C\n",
+    "HETATM    2  O   HOH C 101       1.000   1.000   1.000  1.00  0.00           O\n",
+]
+
+@pytest.fixture
+def temp_qv_file(tmp_path):
+    qv_path = tmp_path / "test.qv"
+    with Quiver(str(qv_path), "w") as qv:
+        qv.add_pdb(TWO_CHAINS, "a", "ddg=-1.0")
+        qv.add_pdb(ONE_CHAIN, "b")
+    return str(qv_path)
+
+def test_stats_one_structure(temp_qv_file):
+    """Verify atoms, residues, chains and bytes of one structure."""
+    stats = Quiver(temp_qv_file, "r").stats("a")
+    assert stats == (1, 4, 3, ("A", "B"), sum(len(line) for line in TWO_CHAINS))
+
+def test_stats_whole_file(temp_qv_file):
+    """Verify stats over the whole file are summed, with chains in first-seen order."""
+    stats = Quiver(temp_qv_file, "r").stats()
+    assert stats.structures == 2
+    assert stats.atoms == 6
+    assert stats.residues == 5
+    assert stats.chains == ("A", "B", "C")
+    assert stats.bytes == sum(len(line) for line in TWO_CHAINS + ONE_CHAIN)
+
+def test_stats_missing_tag(temp_qv_file):
+    """Verify an unknown tag raises KeyError."""
+    with pytest.raises(KeyError):
+        Quiver(temp_qv_file, "r").stats("nope")
@@ -0,0 +1,65 @@
+import pytest
+import os
+import subprocess
+import sys
+
+# Ensure src is in sys.path
+sys.path.insert(0, os.path.abspath(os.path.join(os.path.dirname(__file__), "..", "src")))
+
+from quiver.quiver import Quiver
+
+SCRIPT_DIR = os.path.abspath(os.path.join(os.path.dirname(__file__), "..", "src", "quiver"))
+PDB_LINES = ["ATOM      1  CA  ALA A   1      11.104   6.134  -6.504  1.00  0.00           C\n"]
+
+@pytest.fixture
+def temp_qv_file(tmp_path):
+    qv_path = tmp_path / "test.qv"
+    with Quiver(str(qv_path), "w") as qv:
+        for i in range(3):
+            qv.add_pdb(PDB_LINES, f"design_{i}")
+    return str(qv_path)
+
+def run_script(name, args, **kwargs):
+    return subprocess.run(
+        [sys.executable, os.path.join(SCRIPT_DIR, name)] + args,
+        capture_output=True,
+        text=True,
+        timeout=30,
+        **kwargs,
+    )
+
+def test_qvslice_reads_stdin_for_dash(temp_qv_file):
+    """Verify a - tag reads the remaining tags from stdin."""
+    result = run_script("qvslice.py", [temp_qv_file, "design_0", "-"], input="design_2\n")
+    assert result.returncode == 0
+    assert "QV_TAG design_0" in result.stdout
+    assert "QV_TAG design_2" in result.stdout
+    assert "design_1" not in result.stdout
+
+def test_qvslice_ignores_open_stdin_without_dash(temp_qv_file):
+    """Verify an open stdin that never closes is not read without -."""
+    with subprocess.Popen(["sleep", "60"], stdout=subprocess.PIPE) as silent:
+        try:
+            result = run_script("qvslice.py", [temp_qv_file], stdin=silent.stdout)
+        finally:
+            silent.kill()
+    assert result.returncode == 1
+    assert "No tags provided" in result.stderr
+
+def test_qvslice_strict_fails_on_missing_tag(temp_qv_file):
+    """Verify --strict exits non-zero with nothing on stdout when a tag is missing."""
+    result = run_script("qvslice.py", [temp_qv_file, "design_0", "nope", "--strict"])
+    assert result.returncode == 1
+    assert result.stdout == ""
+    assert "nope" in result.stderr
+
+def test_qvextractspecific_reads_stdin_for_dash(temp_qv_file, tmp_path):
+    """Verify qvextractspecific takes its tags from stdin with -."""
+    out_dir = tmp_path / "out"
+    result = run_script(
+        "qvextractspecific.py",
+        [temp_qv_file, "-", "--output-dir", str(out_dir)],
+        input="design_1\n",
+    )
+    assert result.returncode == 0
+    assert sorted(os.listdir(out_dir)) == ["design_1.pdb"]
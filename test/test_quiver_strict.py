@@ -0,0 +1,52 @@
+import pytest
+import os
+import sys
+
+# Ensure src is in sys.path
+sys.path.insert(0, os.path.abspath(os.path.join(os.path.dirname(__file__), "..", "src")))
+
+from quiver.quiver import Quiver
+
+PDB_LINES = [
+    "ATOM      1  CA  ALA A   1       0.000   0.000   0.000  1.00  0.00           C\n",
+    "ATOM      2  CA  GLY A   2       3.800   0.000   0.000  1.00  0.00           C\n",
+]
+
+def assert_rejected(qv_path, message, pdb_lines=PDB_LINES, tag="tag1", score_str=None):
+    with Quiver(str(qv_path), "w") as qv:
+        with pytest.raises(ValueError, match="rejected: " + message):
+            qv.add_pdb(pdb_lines, tag, score_str)
+        assert qv.get_tags() == []
+    assert not qv_path.exists()
+
+def test_reserved_prefix_rejected(tmp_path):
+    """Verify a PDB line that would start a new entry is not written."""
+    assert_rejected(
+        tmp_path / "bad.qv",
+        "payload line 3 starts with reserved record QV_TAG",
+        pdb_lines=PDB_LINES + ["QV_TAG smuggled\n"],
+    )
+
+def test_embedded_reserved_prefix_rejected(tmp_path):
+    """Verify a reserved record hidden after a newline inside a line is caught."""
+    assert_rejected(
+        tmp_path / "bad.qv",
+        "payload line 1 starts with reserved record QV_SCORE",
+        pdb_lines=[PDB_LINES[0] + "QV_SCORE tag1 ddg=0\n"],
+    )
+
+def test_tag_with_whitespace_rejected(tmp_path):
+    """Verify tags that would not read back as one word are not written."""
+    assert_rejected(tmp_path / "bad.qv", "tag 'my design' is empty or contains whitespace", tag="my design")
+    assert_rejected(tmp_path / "bad.qv", "tag '' is empty", tag="")
+
+def test_score_with_whitespace_rejected(tmp_path):
+    """Verify a score string with spaces is not written."""
+    assert_rejected(tmp_path / "bad.qv", "score string contains whitespace", score_str="ddg=-1 0")
+
+def test_non_strict_writes_anyway(tmp_path):
+    """Verify strict=False restores the old unchecked behaviour."""
+    qv_path = tmp_path / "loose.qv"
+    with Quiver(str(qv_path), "w", strict=False) as qv:
+        qv.add_pdb(PDB_LINES + ["QV_TAG smuggled\n"], "tag1")
+    assert Quiver(str(qv_path), "r").get_tags() == ["tag1", "smuggled"]
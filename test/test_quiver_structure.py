@@ -0,0 +1,68 @@
+import pytest
+import os
+import sys
+
+# Ensure src is in sys.path
+sys.path.insert(0, os.path.abspath(os.path.join(os.path.dirname(__file__), "..", "src")))
+
+from quiver.quiver import Quiver
+from quiver.structure import Atom, Structure
+
+LINES = [
+    "ATOM      1  N   ALA A   1      11.104  13.203  10.000  1.00 20.00           N\n",
+    "ATOM      2  CA  ALA A   1      12.560  13.203  10.000  1.00 20.00           C\n",
+    "ATOM      3  CA  GLY A   2      13.000  14.000  10.500  1.00 20.00           C\n",
+    "ATOM      4  CA  GLY A   2A     14.000  15.000  11.000  1.00 20.00           C\n",
+    "TER\n",
+    "ATOM      5  CA  TRP B  12      -1.500   0.250   3.125  1.00 30.00           C\n",
+    "HETATM    6 MG    MG B 101       0.000   0.000   0.000  1.00 20.00          MG\n",
+    "ATOM      7 1H   TRP B  13      -1.000   0.000   3.000  1.00  0.00           H\n",
+]
+
+@pytest.fixture
+def temp_qv_file(tmp_path):
+    qv_path = tmp_path / "test.qv"
+    with open(qv_path, "w") as f:
+        f.write("QV_TAG tag1\n")
+        f.write("QV_SCORE tag1 ddg=-12.5\n")
+        f.writelines(LINES)
+    return qv_path
+
+def test_structure_hierarchy(temp_qv_file):
+    """Verify atoms are grouped into chains and residues, split on insertion codes."""
+    structure = Quiver(str(temp_qv_file), "r").get_structure("tag1")
+    assert [c.id for c in structure.chains] == ["A", "B"]
+    chain_a = structure.chain("A")
+    assert [(r.name, r.resseq, r.icode) for r in chain_a.residues] == [
+        ("ALA", 1, " "), ("GLY", 2, " "), ("GLY", 2, "A"),
+    ]
+    assert [a.name for a in chain_a.residues[0].atoms] == ["N", "CA"]
+    assert chain_a.residues[0].atom("CA").coord == (12.56, 13.203, 10.0)
+    assert len(structure.atoms()) == 7
+
+def test_structure_sequence_skips_hetatm(temp_qv_file):
+    """Verify chain sequences use one-letter codes and skip ligand residues."""
+    structure = Quiver(str(temp_qv_file), "r").get_structure("tag1")
+    assert structure.chain("A").sequence() == "AGG"
+    assert structure.chain("B").sequence() == "WW"
+
+def test_atom_round_trip():
+    """Verify Atom.to_line reproduces the stored fixed-column records."""
+    for line in LINES:
+        atom = Atom.parse(line)
+        if atom is None:
+            assert line == "TER\n"
+            continue
+        assert atom.to_line() == line.rstrip("\n")
+
+def test_structure_to_lines():
+    """Verify to_lines writes each chain followed by TER and a closing END."""
+    lines = Structure.from_lines(LINES).to_lines()
+    assert lines[-1] == "END\n"
+    assert lines.count("TER\n") == 2
+    assert Structure.from_lines(lines).atoms()[5].hetatm
+
+def test_get_structure_missing_tag(temp_qv_file):
+    """Verify a missing tag raises KeyError."""
+    with pytest.raises(KeyError):
+        Quiver(str(temp_qv_file), "r").get_structure("missing")
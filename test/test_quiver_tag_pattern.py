@@ -0,0 +1,53 @@
+import pytest
+import os
+import re
+import subprocess
+import sys
+
+# Ensure src is in sys.path
+sys.path.insert(0, os.path.abspath(os.path.join(os.path.dirname(__file__), "..", "src")))
+
+from quiver.quiver import Quiver
+
+SCRIPT_DIR = os.path.abspath(os.path.join(os.path.dirname(__file__), "..", "src", "quiver"))
+PDB_LINES = ["ATOM      1  CA  ALA A   1      11.104   6.134  -6.504  1.00  0.00           C\n"]
+TAGS = ["design_17_seed0", "design_17_seed1", "design_170_seed0", "design_18_seed3", "design_[1]"]
+
+@pytest.fixture
+def temp_qv_file(tmp_path):
+    qv_path = tmp_path / "test.qv"
+    with Quiver(str(qv_path), "w") as qv:
+        for tag in TAGS:
+            qv.add_pdb(PDB_LINES, tag)
+    return str(qv_path)
+
+@pytest.mark.parametrize("pattern, regex, expected", [
+    ("design_17_*", False, ["design_17_seed0", "design_17_seed1"]),
+    ("design_1?_seed?", False, ["design_17_seed0", "design_17_seed1", "design_18_seed3"]),
+    ("design_[1]", False, ["design_[1]"]),
+    ("design_17", False, []),
+    ("^design_(17|18)_seed[0-2]$", True, ["design_17_seed0", "design_17_seed1"]),
+    ("seed0", True, ["design_17_seed0", "design_170_seed0"]),
+])
+def test_tags_matching(temp_qv_file, pattern, regex, expected):
+    """Verify globs match whole tags and regexes match anywhere, in file order."""
+    assert Quiver(temp_qv_file, "r").tags_matching(pattern, regex) == expected
+
+def test_get_struct_list_matching(temp_qv_file):
+    """Verify the entries of the matching tags are returned, and bad regexes raise."""
+    qv = Quiver(temp_qv_file, "r")
+    data, found = qv.get_struct_list_matching("*_seed3")
+    assert found == ["design_18_seed3"]
+    assert data == "QV_TAG design_18_seed3\n" + PDB_LINES[0]
+    with pytest.raises(re.error):
+        qv.tags_matching("design_(", regex=True)
+
+def test_qvslice_pattern(temp_qv_file):
+    """Verify qvslice adds the tags matching --pattern to those listed."""
+    result = subprocess.run(
+        [sys.executable, os.path.join(SCRIPT_DIR, "qvslice.py"), temp_qv_file,
+         "design_[1]", "--pattern", "_18_", "--regex"],
+        capture_output=True, text=True, timeout=30,
+    )
+    assert result.returncode == 0
+    assert re.findall(r"QV_TAG (\S+)", result.stdout) == ["design_18_seed3", "design_[1]"]
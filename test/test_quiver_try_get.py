@@ -0,0 +1,51 @@
+import pytest
+import os
+import sys
+
+# Ensure src is in sys.path
+sys.path.insert(0, os.path.abspath(os.path.join(os.path.dirname(__file__), "..", "src")))
+
+from quiver.quiver import EntryStatus, Quiver
+
+PDB_LINES = [
+    "ATOM      1  CA  ALA A   1       0.000   0.000   0.000  1.00  0.00           C\n",
+    "ATOM      2  CA  GLY A   2       3.800   0.000   0.000  1.00  0.00           C\n",
+]
+
+@pytest.fixture
+def hashed_qv_file(tmp_path):
+    qv_path = tmp_path / "hashed.qv"
+    with Quiver(str(qv_path), "w", write_hashes=True) as qv:
+        qv.add_pdb(PDB_LINES, "tag1", "ddg=-1.0")
+        qv.add_pdb(PDB_LINES, "tag2")
+    return qv_path
+
+def test_try_get_found(hashed_qv_file):
+    """Verify an intact entry is returned with its payload."""
+    result = Quiver(str(hashed_qv_file), "r").try_get("tag1")
+    assert result.status == EntryStatus.FOUND == "found"
+    assert result.pdb_lines == PDB_LINES
+    assert result.detail is None
+
+def test_try_get_missing(hashed_qv_file):
+    """Verify a missing tag is reported, not raised."""
+    result = Quiver(str(hashed_qv_file), "r").try_get("nope")
+    assert result.status == EntryStatus.MISSING
+    assert result.pdb_lines is None
+
+def test_try_get_corrupt(hashed_qv_file):
+    """Verify a payload failing its checksum is reported with the payload."""
+    text = hashed_qv_file.read_text().replace("3.800", "3.900", 1)
+    hashed_qv_file.write_text(text)
+    result = Quiver(str(hashed_qv_file), "r").try_get("tag1")
+    assert result.status == EntryStatus.CORRUPT
+    assert "3.900" in result.pdb_lines[1]
+    assert "does not match" in result.detail
+
+@pytest.mark.parametrize("index", [False, True])
+def test_try_get_stale(hashed_qv_file, index):
+    """Verify an entry removed after the file was opened is reported stale."""
+    qv = Quiver(str(hashed_qv_file), "r", index=index)
+    text = hashed_qv_file.read_text()
+    hashed_qv_file.write_text(text[text.index("QV_TAG tag2"):])
+    assert qv.try_get("tag1").status == EntryStatus.STALE
@@ -0,0 +1,71 @@
+import pytest
+import os
+import sys
+
+# Ensure src is in sys.path
+sys.path.insert(0, os.path.abspath(os.path.join(os.path.dirname(__file__), "..", "src")))
+
+from quiver.quiver import Quiver
+
+@pytest.fixture
+def temp_qv_file(tmp_path):
+    qv_path = tmp_path / "test.qv"
+    with open(qv_path, "w") as f:
+        f.write("QV_TAG tag1\n")
+        f.write("QV_SCORE tag1 ddg=-12.5|rmsd=0.8\n")
+        f.write("ATOM      1  N   ALA A   1      11.104  13.203  10.000  1.00 20.00           N\n")
+        f.write("QV_TAG tag2\n")
+        f.write("ATOM      1  N   ALA A   1      12.104  14.203  11.000  1.00 21.00           N\n")
+    return qv_path
+
+def test_update_scores_overwrites_and_adds(temp_qv_file):
+    """Verify existing fields are overwritten and new ones appended."""
+    with Quiver(str(temp_qv_file), "w") as q:
+        q.update_scores("tag1", {"ddg": -14.0, "pae": 3.5})
+    q = Quiver(str(temp_qv_file), "r")
+    assert q.get_scores("tag1") == {"ddg": -14.0, "rmsd": 0.8, "pae": 3.5}
+    assert q.get_pdblines("tag1")[0].startswith("ATOM")
+
+def test_set_score_on_unscored_entry(temp_qv_file):
+    """Verify an entry without a QV_SCORE line gets one."""
+    with Quiver(str(temp_qv_file), "w") as q:
+        q.set_score("tag2", "pae", 7.25)
+    q = Quiver(str(temp_qv_file), "r")
+    assert q.get_scores("tag2") == {"pae": 7.25}
+    assert len(q.get_pdblines("tag2")) == 1
+
+def test_update_scores_keeps_value_types(temp_qv_file):
+    """Verify ints, bools and strings read back with their type, leaving no temp file."""
+    with Quiver(str(temp_qv_file), "w") as q:
+        q.update_scores("tag2", {"rank": 3, "converged": True, "method": "relax"})
+    assert Quiver(str(temp_qv_file), "r").get_scores("tag2") == {"rank": 3, "converged": True, "method": "relax"}
+    assert os.listdir(temp_qv_file.parent) == [temp_qv_file.name]
+
+def test_update_scores_after_add_pdb(tmp_path):
+    """Verify entries added on the same handle are rewritten too."""
+    fn = str(tmp_path / "test.qv")
+    with Quiver(fn, "w") as q:
+        q.add_pdb(["ATOM\n"], "new", "ddg=1.0")
+        q.set_score("new", "pae", 2.0)
+        q.add_pdb(["ATOM\n"], "later")
+    q = Quiver(fn, "r")
+    assert q.get_scores("new") == {"ddg": 1.0, "pae": 2.0}
+    assert q.get_tags() == ["new", "later"]
+
+def test_update_scores_missing_tag(temp_qv_file):
+    """Verify KeyError is raised for a tag that is not in the file."""
+    with Quiver(str(temp_qv_file), "w") as q:
+        with pytest.raises(KeyError, match="Requested tag: tag3 does not exist"):
+            q.set_score("tag3", "pae", 1.0)
+
+def test_update_scores_invalid_field(temp_qv_file):
+    """Verify names that would corrupt the score line are rejected."""
+    with Quiver(str(temp_qv_file), "w") as q:
+        with pytest.raises(ValueError, match="Invalid score field"):
+            q.set_score("tag1", "p|ae", 1.0)
+
+def test_update_scores_read_mode(temp_qv_file):
+    """Verify a read-only handle cannot rewrite the file."""
+    q = Quiver(str(temp_qv_file), "r")
+    with pytest.raises(RuntimeError, match="write mode"):
+        q.set_score("tag1", "pae", 1.0)
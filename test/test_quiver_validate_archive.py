@@ -0,0 +1,47 @@
+import pytest
+import os
+import sys
+
+# Ensure src is in sys.path
+sys.path.insert(0, os.path.abspath(os.path.join(os.path.dirname(__file__), "..", "src")))
+
+from quiver.quiver import Quiver, validate_archive
+
+ATOM = "ATOM      1  CA  ALA A   1      11.104   6.134  -6.504  1.00  0.00           C\n"
+
+def test_validate_clean_archive(tmp_path):
+    """Verify an archive written by Quiver has no issues."""
+    qv_path = tmp_path / "clean.qv"
+    with Quiver(str(qv_path), "w") as qv:
+        qv.add_pdb([ATOM, "TER\n", "END\n"], "a", "ddg=-1.0")
+        qv.add_pdb([ATOM], "b")
+    assert validate_archive(str(qv_path)) == (2, [])
+
+def test_validate_reports_problems(tmp_path):
+    """Verify every kind of problem is reported at its line, in file order."""
+    qv_path = tmp_path / "broken.qv"
+    qv_path.write_text(
+        "QV_SCORE ghost ddg=0\n"       # 1
+        "QV_TAG a\n"                   # 2
+        "QV_SCORE b ddg=-1.0\n"        # 3
+        + ATOM +                       # 4
+        "Rosetta says hi\n"            # 5
+        "QV_TAG empty\n"               # 6
+        "QV_SCORE empty ddg|=2\n"      # 7
+        "QV_TAG a\n"                   # 8
+        "QV_SCORE a\n"                 # 9
+        + ATOM                         # 10
+    )
+    report = validate_archive(str(qv_path))
+    assert report.entries == 3
+    assert [(i.line, i.kind, i.tag) for i in report.issues] == [
+        (1, "score-tag-mismatch", None),
+        (3, "score-tag-mismatch", "a"),
+        (5, "junk", "a"),
+        (6, "empty-structure", "empty"),
+        (7, "malformed-score", "empty"),
+        (8, "duplicate-tag", "a"),
+        (9, "malformed-score", "a"),
+    ]
+    assert "line 2" in report.issues[5].message
+    assert "ddg" in report.issues[4].message
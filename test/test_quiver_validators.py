@@ -0,0 +1,74 @@
+import pytest
+import os
+import sys
+
+# Ensure src is in sys.path
+sys.path.insert(0, os.path.abspath(os.path.join(os.path.dirname(__file__), "..", "src")))
+
+from quiver.quiver import Quiver
+from quiver.validate import max_residues, pdb_records, required_chains, required_scores
+
+PDB_LINES = [
+    "ATOM      1  CA  ALA A   1       0.000   0.000   0.000  1.00  0.00           C\n",
+    "ATOM      2  CA  GLY A   2       3.800   0.000   0.000  1.00  0.00           C\n",
+    "ATOM      3  CA  SER B   1       0.000   3.800   0.000  1.00  0.00           C\n",
+]
+
+def test_validators_accept(tmp_path):
+    """Verify conforming entries are written."""
+    qv_path = tmp_path / "ok.qv"
+    validators = [max_residues(3), required_chains("A", "B"), required_scores("ddg")]
+    with Quiver(str(qv_path), "w", validators=validators) as qv:
+        qv.add_pdb(PDB_LINES, "tag1", score_str="ddg=-1.0")
+    assert Quiver(str(qv_path), "r").get_tags() == ["tag1"]
+
+def assert_rejected(qv_path, validator, message):
+    with Quiver(str(qv_path), "w") as qv:
+        qv.add_validator(validator)
+        with pytest.raises(ValueError, match="Entry tag1 rejected: " + message):
+            qv.add_pdb(PDB_LINES, "tag1", score_str="ddg=-1.0")
+        assert qv.get_tags() == []
+    assert not qv_path.exists() or qv_path.read_text() == ""
+
+def test_max_residues_rejects(tmp_path):
+    """Verify a structure with too many residues is not written."""
+    assert_rejected(tmp_path / "bad.qv", max_residues(2), "3 residues, more than the maximum of 2")
+
+def test_required_chains_rejects(tmp_path):
+    """Verify a structure missing a required chain is not written."""
+    assert_rejected(tmp_path / "bad.qv", required_chains("A", "C"), "missing chain\\(s\\) C")
+
+def test_required_scores_rejects(tmp_path):
+    """Verify an entry missing a required score field is not written."""
+    assert_rejected(
+        tmp_path / "bad.qv", required_scores("ddg", "plddt"), "missing score field\\(s\\) plddt"
+    )
+
+def test_pdb_records_accepts(tmp_path):
+    """Verify well-formed PDB records pass the record check."""
+    with Quiver(str(tmp_path / "ok.qv"), "w", validators=[pdb_records()]) as qv:
+        qv.add_pdb(PDB_LINES + ["TER\n", "\n", "END\n"], "tag1")
+        assert qv.get_tags() == ["tag1"]
+
+def test_pdb_records_rejects(tmp_path):
+    """Verify lines that are not PDB records are not written."""
+    with Quiver(str(tmp_path / "bad.qv"), "w", validators=[pdb_records()]) as qv:
+        with pytest.raises(ValueError, match="line 4 is not a PDB record: score"):
+            qv.add_pdb(PDB_LINES + ["score -12.5\n"], "tag1")
+        with pytest.raises(ValueError, match="line 1 is an unreadable ATOM record"):
+            qv.add_pdb(["ATOM      1  CA  ALA A   1\n"], "tag2")
+
+def test_custom_validator(tmp_path):
+    """Verify any callable can be registered and sees the tag, lines and scores."""
+    seen = []
+
+    def no_hetatm(tag, pdb_lines, scores):
+        seen.append((tag, len(pdb_lines), scores))
+        if any(line.startswith("HETATM") for line in pdb_lines):
+            raise ValueError("HETATM records are not allowed")
+
+    with Quiver(str(tmp_path / "custom.qv"), "w", validators=[no_hetatm]) as qv:
+        qv.add_pdb(PDB_LINES, "tag1", score_str="ddg=-1.0|rmsd=0.5")
+        with pytest.raises(ValueError, match="not allowed"):
+            qv.add_pdb(PDB_LINES + ["HETATM    4 MG    MG B 101       0.000   0.000   0.000  1.00  0.00          MG\n"], "tag2")
+    assert seen == [("tag1", 3, {"ddg": "-1.0", "rmsd": "0.5"}), ("tag2", 4, {})]
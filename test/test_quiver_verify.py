@@ -0,0 +1,82 @@
+import pytest
+import os
+import sys
+
+# Ensure src is in sys.path
+sys.path.insert(0, os.path.abspath(os.path.join(os.path.dirname(__file__), "..", "src")))
+
+from quiver.quiver import Quiver, payload_digest, verify_archive
+
+PDB_LINES = [
+    "ATOM      1  CA  ALA A   1       0.000   0.000   0.000  1.00  0.00           C\n",
+    "ATOM      2  CA  GLY A   2       3.800   0.000   0.000  1.00  0.00           C\n",
+]
+
+@pytest.fixture
+def hashed_qv_file(tmp_path):
+    qv_path = tmp_path / "hashed.qv"
+    with Quiver(str(qv_path), "w", write_hashes=True) as qv:
+        qv.add_pdb(PDB_LINES, "tag1", "ddg=-1.0")
+        qv.add_pdb(PDB_LINES, "tag2")
+    return qv_path
+
+def test_hash_line_written(hashed_qv_file):
+    """Verify each entry gets a QV_HASH line that readers do not return."""
+    text = hashed_qv_file.read_text()
+    assert f"QV_HASH tag1 {payload_digest(PDB_LINES)}\n" in text
+    qv = Quiver(str(hashed_qv_file), "r")
+    assert qv.get_pdblines("tag1") == PDB_LINES
+    assert [lines for _, _, lines in qv] == [PDB_LINES, PDB_LINES]
+
+def test_verify_ok(hashed_qv_file):
+    """Verify intact entries pass."""
+    assert Quiver(str(hashed_qv_file), "r").verify() == {"tag1": "ok", "tag2": "ok"}
+
+def test_verify_truncated(hashed_qv_file):
+    """Verify an entry cut short by an interrupted write is reported."""
+    text = hashed_qv_file.read_text()
+    hashed_qv_file.write_text(text[:-40])
+    qv = Quiver(str(hashed_qv_file), "r", include_partial=True)
+    assert qv.verify() == {"tag1": "ok", "tag2": "mismatch"}
+
+def test_verify_corrupted_single_tag(hashed_qv_file):
+    """Verify an edited coordinate is caught when checking one tag."""
+    text = hashed_qv_file.read_text().replace("3.800", "3.900", 1)
+    hashed_qv_file.write_text(text)
+    qv = Quiver(str(hashed_qv_file), "r")
+    assert qv.verify("tag1") == {"tag1": "mismatch"}
+    assert qv.verify("tag2") == {"tag2": "ok"}
+    with pytest.raises(KeyError):
+        qv.verify("nope")
+
+def test_verify_unhashed(tmp_path):
+    """Verify entries written without hashes are reported as unhashed."""
+    qv_path = tmp_path / "plain.qv"
+    with Quiver(str(qv_path), "w") as qv:
+        qv.add_pdb(PDB_LINES, "tag1")
+    assert "QV_HASH" not in qv_path.read_text()
+    assert Quiver(str(qv_path), "r").verify() == {"tag1": "unhashed"}
+
+@pytest.mark.parametrize("threads", [1, 4])
+def test_verify_archive_threads(tmp_path, threads):
+    """Verify the threaded check agrees with Quiver.verify, in file order."""
+    qv_path = tmp_path / "many.qv"
+    with Quiver(str(qv_path), "w", write_hashes=True) as qv:
+        for i in range(20):
+            qv.add_pdb(PDB_LINES, f"tag{i}")
+    text = qv_path.read_text().replace("3.800", "3.900", 1)
+    qv_path.write_text(text)
+    results = verify_archive(str(qv_path), threads=threads)
+    assert results == Quiver(str(qv_path), "r").verify()
+    assert list(results) == [f"tag{i}" for i in range(20)]
+    assert results["tag0"] == "mismatch"
+
+def test_verify_archive_fail_fast(tmp_path):
+    """Verify fail_fast stops reading entries after the first mismatch."""
+    qv_path = tmp_path / "many.qv"
+    with Quiver(str(qv_path), "w", write_hashes=True) as qv:
+        for i in range(50):
+            qv.add_pdb(PDB_LINES, f"tag{i}")
+    qv_path.write_text(qv_path.read_text().replace("3.800", "3.900", 1))
+    results = verify_archive(str(qv_path), fail_fast=True)
+    assert results == {"tag0": "mismatch"}
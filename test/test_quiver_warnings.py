@@ -0,0 +1,75 @@
+import pytest
+import os
+import sys
+
+# Ensure src is in sys.path
+sys.path.insert(0, os.path.abspath(os.path.join(os.path.dirname(__file__), "..", "src")))
+
+from quiver.quiver import Quiver, Warnings, extract_pdbs, ingest_directory
+
+PDB_LINES = ["ATOM      1  CA  ALA A   1      11.104   6.134  -6.504  1.00  0.00           C\n"]
+
+@pytest.fixture
+def temp_qv_file(tmp_path):
+    # Written by hand, since Quiver refuses to add a tag twice
+    qv_path = tmp_path / "test.qv"
+    qv_path.write_text(
+        "".join(f"QV_TAG {tag}\n" + "".join(PDB_LINES) for tag in ("design_0", "design_1", "design_0"))
+    )
+    return str(qv_path)
+
+def test_slice_warnings_have_codes(temp_qv_file):
+    """Verify slice reports missing, repeated and duplicated tags by code."""
+    result = Quiver(temp_qv_file, "r").slice(["design_1", "design_0", "design_1", "nope"])
+    assert result.missing_tags == ["nope"]
+    assert [w.code for w in result.warnings] == [
+        "duplicate-request",
+        "missing-tag",
+        "duplicate-tag",
+    ]
+    assert [w.message for w in result.warnings.with_code("missing-tag")] == [
+        "Tag not found in Quiver file: nope"
+    ]
+
+def test_slice_strict(temp_qv_file):
+    """Verify strict slicing fails on a missing tag and passes when all exist."""
+    qv = Quiver(temp_qv_file, "r")
+    with pytest.raises(KeyError, match="nope, gone"):
+        qv.slice(["design_1", "nope", "gone"], strict=True)
+    result = qv.slice(["design_1"], strict=True)
+    assert result.found_tags == ["design_1"] and result.missing_tags == []
+
+def test_warnings_keep_each_warning_once():
+    """Verify pushing the same warning twice keeps one, and unknown codes fail."""
+    warnings = Warnings()
+    warnings.push("missing-tag", "Tag not found in Quiver file: a")
+    warnings.push("missing-tag", "Tag not found in Quiver file: a")
+    assert len(warnings) == 1
+    with pytest.raises(ValueError):
+        warnings.push("nope", "message")
+
+def test_extract_reports_skipped_files(temp_qv_file, tmp_path):
+    """Verify files left alone because they exist are reported as skipped."""
+    out = tmp_path / "out"
+    out.mkdir()
+    (out / "design_1.pdb").write_text("keep\n")
+    warnings = Warnings()
+    extract_pdbs(temp_qv_file, str(out), tags=["design_1"], warnings=warnings)
+    assert [w.code for w in warnings] == ["skipped-file"]
+    assert (out / "design_1.pdb").read_text() == "keep\n"
+
+def test_ingest_reports_renamed_and_skipped(tmp_path):
+    """Verify ingest_directory reports renamed and skipped duplicate files."""
+    root = tmp_path / "in"
+    (root / "a").mkdir(parents=True)
+    (root / "b").mkdir()
+    for sub in ("a", "b"):
+        (root / sub / "model.pdb").write_text("".join(PDB_LINES))
+
+    renamed = Warnings()
+    ingest_directory(str(root), str(tmp_path / "r.qv"), on_duplicate="rename", warnings=renamed)
+    assert [w.code for w in renamed] == ["renamed-duplicate"]
+
+    skipped = Warnings()
+    assert ingest_directory(str(root), str(tmp_path / "s.qv"), on_duplicate="skip", warnings=skipped) == 1
+    assert [w.code for w in skipped] == ["skipped-file"]